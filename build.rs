@@ -0,0 +1,14 @@
+// build.rs
+//
+// Only does work for the optional gRPC transport: compiles proto/ with a
+// vendored protoc so no system protobuf install is required.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/unifiedlab.proto");
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::compile_protos("proto/unifiedlab.proto").expect("compile protos");
+    }
+}
@@ -0,0 +1,9 @@
+// Generated logs with seeded damage: intact records must all come back.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use unifiedlab::eventlog::testing;
+
+fuzz_target!(|seed: u64| {
+    testing::run_scenario(seed).unwrap();
+});
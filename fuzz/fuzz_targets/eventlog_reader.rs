@@ -0,0 +1,9 @@
+// Arbitrary bytes as a log file: the reader must neither panic nor stall.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use unifiedlab::eventlog::testing;
+
+fuzz_target!(|data: &[u8]| {
+    testing::fuzz_reader(data).unwrap();
+});
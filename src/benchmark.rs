@@ -0,0 +1,227 @@
+// src/benchmark.rs
+//
+// =============================================================================
+// UNIFIEDLAB: BENCHMARK SUITE (v 0.1 )
+// =============================================================================
+//
+// The Stopwatch.
+//
+// `unifiedlab benchmark` deploys the synthetic scenarios of the importer
+// (mixed chaos, fan-out, starvation) against a live cluster and times them:
+// 1. Scheduling latency: from a job becoming ready (submitted and all parents
+//    finished) to its dispatch (finish time minus the measured run time).
+// 2. Dispatch throughput: finished jobs per second of wall time.
+// 3. Overhead: growth of the event log and checkpoint DB per job.
+//
+// This module only does the arithmetic; the submit/poll loop lives in main.rs.
+// The JSON form is stable so reports can be diffed across releases.
+
+use crate::core::JobSummary;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Scenario signatures run when none are given (see `DrawIoLoader`).
+pub const DEFAULT_SUITE: &[&str] = &["mixed_40", "fanout_20_janus", "starve"];
+
+/// Bump when a field changes meaning, so old reports are not compared blindly.
+pub const REPORT_VERSION: u32 = 1;
+
+// ============================================================================
+// 1. REPORT TYPES
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioReport {
+    pub scenario: String,
+    pub jobs: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    /// Still pending/blocked when the scenario stalled or timed out.
+    pub unfinished: usize,
+    pub wall_s: f64,
+    /// None when no job reported a run time (e.g. everything failed early).
+    pub latency: Option<LatencyStats>,
+    pub throughput_jobs_per_s: f64,
+    pub eventlog_bytes_per_job: f64,
+    pub db_bytes_per_job: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub report_version: u32,
+    pub unifiedlab_version: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub workers: usize,
+    pub scenarios: Vec<ScenarioReport>,
+}
+
+impl BenchmarkReport {
+    pub fn new(workers: usize) -> Self {
+        Self {
+            report_version: REPORT_VERSION,
+            unifiedlab_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: chrono::Utc::now(),
+            workers,
+            scenarios: Vec::new(),
+        }
+    }
+}
+
+// ============================================================================
+// 2. MEASUREMENT
+// ============================================================================
+
+/// Terminal as far as the benchmark is concerned.
+pub fn is_finished(status: &str) -> bool {
+    matches!(status, "Completed" | "Failed" | "Cancelled")
+}
+
+/// One scenario from the final DB rows of its jobs.
+/// `parents` maps a job id to the ids it waits on; `submitted_ms` is when the
+/// submission was sent.
+pub fn summarize(
+    scenario: &str,
+    submitted_ms: i64,
+    wall_s: f64,
+    parents: &HashMap<String, Vec<String>>,
+    rows: &[JobSummary],
+    eventlog_bytes: u64,
+    db_bytes: u64,
+) -> ScenarioReport {
+    let by_id: HashMap<&str, &JobSummary> = rows.iter().map(|r| (r.id.as_str(), r)).collect();
+    let count = |s: &str| rows.iter().filter(|r| r.status == s).count();
+    let jobs = parents.len().max(rows.len());
+    let finished: Vec<&JobSummary> = rows.iter().filter(|r| is_finished(&r.status)).collect();
+
+    let mut latencies: Vec<f64> = finished
+        .iter()
+        .filter(|r| r.t_total > 0.0 && r.status != "Cancelled")
+        .map(|r| {
+            let ready = parents
+                .get(&r.id)
+                .into_iter()
+                .flatten()
+                .filter_map(|p| by_id.get(p.as_str()).map(|p| p.updated_at))
+                .fold(submitted_ms, i64::max);
+            let dispatched = r.updated_at as f64 - r.t_total;
+            (dispatched - ready as f64).max(0.0)
+        })
+        .collect();
+    latencies.sort_by(f64::total_cmp);
+
+    let span_s = finished
+        .iter()
+        .map(|r| r.updated_at)
+        .max()
+        .map(|last| (last - submitted_ms).max(1) as f64 / 1000.0);
+    let per_job = |bytes: u64| {
+        if jobs > 0 {
+            bytes as f64 / jobs as f64
+        } else {
+            0.0
+        }
+    };
+
+    ScenarioReport {
+        scenario: scenario.to_string(),
+        jobs,
+        completed: count("Completed"),
+        failed: count("Failed"),
+        cancelled: count("Cancelled"),
+        unfinished: jobs - finished.len(),
+        wall_s,
+        latency: latency_stats(&latencies),
+        throughput_jobs_per_s: span_s.map_or(0.0, |s| finished.len() as f64 / s),
+        eventlog_bytes_per_job: per_job(eventlog_bytes),
+        db_bytes_per_job: per_job(db_bytes),
+    }
+}
+
+fn latency_stats(sorted: &[f64]) -> Option<LatencyStats> {
+    let last = *sorted.last()?;
+    let pct = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+    Some(LatencyStats {
+        samples: sorted.len(),
+        mean_ms: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        p50_ms: pct(0.5),
+        p95_ms: pct(0.95),
+        max_ms: last,
+    })
+}
+
+/// Bytes of the coordinator's event log (all segments) under `root`.
+pub fn eventlog_bytes(root: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.starts_with("events") && name.ends_with(".log")
+        })
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Size of a file, 0 if it does not exist.
+pub fn file_bytes(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+// ============================================================================
+// 3. OUTPUT
+// ============================================================================
+
+pub fn print_report(report: &BenchmarkReport) {
+    println!(
+        "UnifiedLAB {} benchmark, {} worker(s)",
+        report.unifiedlab_version, report.workers
+    );
+    println!(
+        "{:<18} {:>5} {:>5} {:>5} {:>6} {:>8} {:>9} {:>9} {:>8} {:>9} {:>9}",
+        "SCENARIO",
+        "JOBS",
+        "DONE",
+        "FAIL",
+        "STUCK",
+        "WALL(s)",
+        "P50(ms)",
+        "P95(ms)",
+        "JOBS/s",
+        "LOG B/job",
+        "DB B/job"
+    );
+    for s in &report.scenarios {
+        let (p50, p95) = match &s.latency {
+            Some(l) => (format!("{:.0}", l.p50_ms), format!("{:.0}", l.p95_ms)),
+            None => ("-".into(), "-".into()),
+        };
+        println!(
+            "{:<18} {:>5} {:>5} {:>5} {:>6} {:>8.1} {:>9} {:>9} {:>8.2} {:>9.0} {:>9.0}",
+            s.scenario,
+            s.jobs,
+            s.completed,
+            s.failed + s.cancelled,
+            s.unfinished,
+            s.wall_s,
+            p50,
+            p95,
+            s.throughput_jobs_per_s,
+            s.eventlog_bytes_per_job,
+            s.db_bytes_per_job
+        );
+    }
+}
@@ -0,0 +1,398 @@
+// src/chaos.rs
+//
+// =============================================================================
+// UNIFIEDLAB: FAULT INJECTION (v 0.1 )
+// =============================================================================
+//
+// The Gremlin.
+//
+// Behind the hidden `--chaos` flag of `start` and `run`, for hardening the
+// recovery paths. Each fault has its own probability:
+// 1. Delayed writes: a transport write waits up to `delay_ms` first.
+// 2. Dropped grants: a WorkGrant counts as sent but never arrives, so the
+//    grant-ack timeout has to hand its jobs out again.
+// 3. Killed drivers: a running engine is killed within `kill_ms` of starting.
+// 4. Crashed ticks: after a tick the Coordinator loses everything it has not
+//    checkpointed and restarts from the store (`crash_restart`).
+// 5. Invariants: the Coordinator's broadcasts are watched, and afterwards no
+//    submitted job may be missing from the checkpoint, and no job may have
+//    been completed by two different executions.
+//
+// Randomness comes from uuid v4, like everywhere else (no rand dependency).
+
+use crate::core::{Job, JobStatus};
+use crate::eventlog::EventEnvelope;
+use crate::marketplace::{JobCompleteReport, EV_JOB_COMPLETE, EV_JOB_SUBMIT, EV_WORK_GRANT};
+use crate::transport::{Transport, TransportStats};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// What a bare `--chaos` turns on.
+pub const DEFAULT_CHAOS: &str = "delay=0.05,drop=0.05,kill=0.02,crash=0.01";
+
+// ============================================================================
+// 1. CONFIGURATION
+// ============================================================================
+
+/// Fault probabilities (0..=1) and timings, parsed from
+/// `delay=0.1,drop=0.05,kill=0.02,crash=0.01,delay_ms=250,kill_ms=2000`.
+/// Keys left out stay off (probabilities) or at their default (timings).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosConfig {
+    pub delay_write: f64,
+    pub delay_ms: u64,
+    pub drop_grant: f64,
+    pub kill_driver: f64,
+    pub kill_ms: u64,
+    pub crash_tick: f64,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            delay_write: 0.0,
+            delay_ms: 250,
+            drop_grant: 0.0,
+            kill_driver: 0.0,
+            kill_ms: 2000,
+            crash_tick: 0.0,
+        }
+    }
+}
+
+impl FromStr for ChaosConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut cfg = Self::default();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", part))?;
+            let bad = |e: &dyn std::fmt::Display| format!("{}: {}", key, e);
+            let prob = || -> std::result::Result<f64, String> {
+                let p: f64 = value.parse().map_err(|e| bad(&e))?;
+                if !(0.0..=1.0).contains(&p) {
+                    return Err(bad(&"probability must be between 0 and 1"));
+                }
+                Ok(p)
+            };
+            match key {
+                "delay" => cfg.delay_write = prob()?,
+                "drop" => cfg.drop_grant = prob()?,
+                "kill" => cfg.kill_driver = prob()?,
+                "crash" => cfg.crash_tick = prob()?,
+                "delay_ms" => cfg.delay_ms = value.parse().map_err(|e| bad(&e))?,
+                "kill_ms" => cfg.kill_ms = value.parse().map_err(|e| bad(&e))?,
+                other => {
+                    return Err(format!(
+                        "unknown chaos key '{}' (delay, drop, kill, crash, delay_ms, kill_ms)",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(cfg)
+    }
+}
+
+// ============================================================================
+// 2. THE INJECTOR
+// ============================================================================
+
+/// Faults injected so far.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FaultCounts {
+    pub delayed_writes: u64,
+    pub dropped_grants: u64,
+    pub killed_drivers: u64,
+    pub crashed_ticks: u64,
+}
+
+/// What the Coordinator broadcast: job ids submitted, and for each job the
+/// executions (host + start time) that reported it Completed.
+#[derive(Default)]
+struct Witness {
+    submitted: BTreeSet<Uuid>,
+    completions: BTreeMap<Uuid, HashSet<String>>,
+}
+
+/// Shared by the transports, the Guardian and the Coordinator loop of one
+/// process; it outlives Coordinator restarts.
+pub struct Chaos {
+    cfg: ChaosConfig,
+    faults: Mutex<FaultCounts>,
+    witness: Mutex<Witness>,
+}
+
+impl Chaos {
+    pub fn new(cfg: ChaosConfig) -> Self {
+        log::warn!("🐒 Chaos mode: {:?}", cfg);
+        Self {
+            cfg,
+            faults: Mutex::new(FaultCounts::default()),
+            witness: Mutex::new(Witness::default()),
+        }
+    }
+
+    pub fn faults(&self) -> FaultCounts {
+        self.faults.lock().map(|f| f.clone()).unwrap_or_default()
+    }
+
+    fn count(&self, f: impl FnOnce(&mut FaultCounts)) {
+        if let Ok(mut faults) = self.faults.lock() {
+            f(&mut faults);
+        }
+    }
+
+    /// Waits a random time up to `delay_ms`, with probability `delay`.
+    pub async fn maybe_delay_write(&self) {
+        if roll(self.cfg.delay_write) {
+            self.count(|f| f.delayed_writes += 1);
+            let ms = (unit() * self.cfg.delay_ms as f64) as u64;
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+        }
+    }
+
+    /// Whether to lose the grant about to be sent.
+    pub fn drop_grant(&self) -> bool {
+        let drop = roll(self.cfg.drop_grant);
+        if drop {
+            self.count(|f| f.dropped_grants += 1);
+        }
+        drop
+    }
+
+    /// When to kill the driver about to start, if it is to be killed.
+    /// Counted as it is decided; a driver that finishes first escapes.
+    pub fn driver_kill_after(&self) -> Option<Duration> {
+        roll(self.cfg.kill_driver).then(|| {
+            self.count(|f| f.killed_drivers += 1);
+            Duration::from_millis((unit() * self.cfg.kill_ms as f64) as u64)
+        })
+    }
+
+    /// Whether the Coordinator should crash after this tick.
+    pub fn crash_tick(&self) -> bool {
+        let crash = roll(self.cfg.crash_tick);
+        if crash {
+            self.count(|f| f.crashed_ticks += 1);
+        }
+        crash
+    }
+
+    /// Notes the submitted jobs and completions in a Coordinator broadcast.
+    fn witness(&self, kind: &str, payload: &Value) {
+        let Ok(mut w) = self.witness.lock() else {
+            return;
+        };
+        match kind {
+            EV_JOB_SUBMIT => {
+                let ids = payload["jobs"].as_array().into_iter().flatten();
+                w.submitted.extend(
+                    ids.filter_map(|j| j["id"].as_str())
+                        .filter_map(|id| id.parse::<Uuid>().ok()),
+                );
+            }
+            EV_JOB_COMPLETE => {
+                let Ok(rep) = serde_json::from_value::<JobCompleteReport>(payload.clone()) else {
+                    return;
+                };
+                if rep.status != JobStatus::Completed {
+                    return;
+                }
+                // A restarted Coordinator echoes replayed reports again; the
+                // execution, not the echo, is what counts
+                let execution = match &rep.result {
+                    Some(r) => format!(
+                        "{}@{}",
+                        r.provenance.execution_host,
+                        r.provenance.start_time.timestamp_micros()
+                    ),
+                    None => "-".into(),
+                };
+                w.completions
+                    .entry(rep.job_id)
+                    .or_default()
+                    .insert(execution);
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks the witnessed broadcasts against the jobs in the checkpoint.
+    pub fn check<'a>(&self, checkpoint: impl IntoIterator<Item = &'a Job>) -> InvariantReport {
+        let stored: BTreeMap<Uuid, &Job> = checkpoint.into_iter().map(|j| (j.id, j)).collect();
+        let Ok(w) = self.witness.lock() else {
+            return InvariantReport::default();
+        };
+        InvariantReport {
+            submitted: w.submitted.len(),
+            lost: w
+                .submitted
+                .iter()
+                .filter(|id| !stored.contains_key(id))
+                .copied()
+                .collect(),
+            double_completed: w
+                .completions
+                .iter()
+                .filter(|(_, runs)| runs.len() > 1)
+                .map(|(id, _)| *id)
+                .collect(),
+            unfinished: stored
+                .values()
+                .filter(|j| {
+                    !matches!(
+                        j.status,
+                        JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+                    )
+                })
+                .map(|j| j.id)
+                .collect(),
+            faults: self.faults(),
+        }
+    }
+}
+
+/// A probability draw.
+fn roll(p: f64) -> bool {
+    p > 0.0 && unit() < p
+}
+
+/// Uniform in [0, 1) from the low 53 bits of a v4 uuid (all random).
+fn unit() -> f64 {
+    (Uuid::new_v4().as_u128() as u64 & ((1 << 53) - 1)) as f64 / (1u64 << 53) as f64
+}
+
+// ============================================================================
+// 3. INVARIANTS
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InvariantReport {
+    /// Distinct jobs the Coordinator broadcast as submitted.
+    pub submitted: usize,
+    /// Submitted, but not in the checkpoint.
+    pub lost: Vec<Uuid>,
+    /// Reported Completed by more than one execution.
+    pub double_completed: Vec<Uuid>,
+    /// Not finished when checked (only a violation if the run was over).
+    pub unfinished: Vec<Uuid>,
+    pub faults: FaultCounts,
+}
+
+impl InvariantReport {
+    /// No job lost or completed twice.
+    pub fn holds(&self) -> bool {
+        self.lost.is_empty() && self.double_completed.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        let f = &self.faults;
+        format!(
+            "Chaos: {} delayed write(s), {} dropped grant(s), {} killed driver(s), \
+             {} coordinator crash(es). Invariants over {} job(s): {} lost, {} completed twice, {} unfinished",
+            f.delayed_writes,
+            f.dropped_grants,
+            f.killed_drivers,
+            f.crashed_ticks,
+            self.submitted,
+            self.lost.len(),
+            self.double_completed.len(),
+            self.unfinished.len()
+        )
+    }
+}
+
+// ============================================================================
+// 4. THE FAULTY TRANSPORT
+// ============================================================================
+
+/// Wraps a transport with delayed writes and dropped grants, and watches
+/// the Coordinator's broadcasts for the invariant check.
+pub struct ChaosTransport {
+    inner: Box<dyn Transport>,
+    chaos: Arc<Chaos>,
+}
+
+impl ChaosTransport {
+    pub fn wrap(inner: Box<dyn Transport>, chaos: Arc<Chaos>) -> Box<dyn Transport> {
+        Box::new(Self { inner, chaos })
+    }
+}
+
+#[async_trait]
+impl Transport for ChaosTransport {
+    async fn send_to_coordinator(&mut self, kind: &str, payload: Value) -> Result<()> {
+        self.chaos.maybe_delay_write().await;
+        self.inner.send_to_coordinator(kind, payload).await
+    }
+
+    async fn send_to_coordinator_with_id(
+        &mut self,
+        kind: &str,
+        payload: Value,
+        event_id: Uuid,
+    ) -> Result<()> {
+        self.chaos.maybe_delay_write().await;
+        self.inner
+            .send_to_coordinator_with_id(kind, payload, event_id)
+            .await
+    }
+
+    async fn broadcast(&mut self, kind: &str, payload: Value) -> Result<u64> {
+        self.chaos.maybe_delay_write().await;
+        self.chaos.witness(kind, &payload);
+        self.inner.broadcast(kind, payload).await
+    }
+
+    async fn broadcast_batch(&mut self, records: Vec<(&str, Value)>) -> Result<Vec<u64>> {
+        self.chaos.maybe_delay_write().await;
+        for (kind, payload) in &records {
+            self.chaos.witness(kind, payload);
+        }
+        self.inner.broadcast_batch(records).await
+    }
+
+    async fn send_to_worker(&mut self, worker_id: &str, kind: &str, payload: Value) -> Result<u64> {
+        if kind == EV_WORK_GRANT && self.chaos.drop_grant() {
+            log::warn!("🐒 Chaos: dropped a grant to {}", worker_id);
+            return Ok(0);
+        }
+        self.chaos.maybe_delay_write().await;
+        self.inner.send_to_worker(worker_id, kind, payload).await
+    }
+
+    async fn recv_broadcasts(&mut self) -> Result<Vec<EventEnvelope>> {
+        self.inner.recv_broadcasts().await
+    }
+
+    async fn recv_worker_messages(&mut self) -> Result<Vec<EventEnvelope>> {
+        self.inner.recv_worker_messages().await
+    }
+
+    async fn seek(&mut self, offset: u64) -> Result<()> {
+        self.inner.seek(offset).await
+    }
+
+    fn shares_filesystem(&self) -> bool {
+        self.inner.shares_filesystem()
+    }
+
+    async fn wait_for_traffic(&mut self, max: Duration) {
+        self.inner.wait_for_traffic(max).await
+    }
+
+    fn stats(&self) -> TransportStats {
+        self.inner.stats()
+    }
+}
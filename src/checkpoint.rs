@@ -11,14 +11,39 @@
 // - High-traffic fields (status, timestamp) are columns.
 // - Complex data (Structure, JobConfig, Provenance) is JSON text.
 // - TUI-optimized queries using partial JSON deserialization.
-// - HPC-safe journaling (DELETE mode).
+// - Retention: finished rows can be pruned (optionally archived as JSONL).
+// - Snapshots: the whole DB as diffable JSONL, for moving or rebuilding it.
+// - Full-text search (FTS5) over engine code, params and error logs.
+// - Artifact references: which stored files (by hash) belong to which job.
+// - DAG edges as rows, so the graph can be rebuilt without parsing jobs.
+// - Memoization results by fingerprint, kept when their jobs are pruned.
+// - Progress snapshots of running jobs (`job_progress`), oldest first.
+//   The same table in a standalone file (`MemoCache`) is shared by campaigns.
+// - Versioned schema: ordered migrations run on open, so a campaign DB
+//   survives an upgraded binary.
+// - One held connection per store, with cached prepared statements.
+// - HPC-safe journaling (DELETE mode). On one machine (`StoreConfig::local`)
+//   WAL instead, so TUI reads do not wait on the Coordinator's writes.
 
-use crate::core::{Engine, Job, JobConfig, JobSummary};
-use anyhow::{Context, Result};
-use rusqlite::{params, Connection, OptionalExtension};
+use crate::core::{
+    CalculationResult, ElectronVolts, Engine, FileRole, Force, Job, JobConfig, JobStatus,
+    JobSummary, ResourceReq, ResourceUsage, WorkFile,
+};
+use crate::marketplace::{job_fingerprint, FLOW_WORKFLOW};
+use crate::provenance::{ArtifactStore, ContentType, StoredObject};
+use crate::resources::ClusterType;
+use crate::schema::{self, DroppedRecord, RestoreReport, Restored};
+use crate::wire::STRUCTURE_BLOB_EXT;
+use crate::workflow::NodeType;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
 use uuid::Uuid;
 
 // -----------------------------------------------------------------------------
@@ -31,72 +56,500 @@ pub struct WorkerInfo {
     pub cores: usize,
     pub tasks: usize,
     pub last_seen_ms: i64,
+    /// Unread broadcast log behind the worker's cursor, as it last reported.
+    #[serde(default)]
+    pub read_lag: Option<u64>,
+    /// Free GPUs, as it last reported.
+    #[serde(default)]
+    pub gpus: usize,
+    /// What jobs it can take ("brain", "muscle", "gpu"...), sorted.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// Gets no new work; see `WorkerDrain`.
+    #[serde(default)]
+    pub draining: bool,
+    /// Cores and GPUs of the whole machine, if the worker reports them.
+    #[serde(default)]
+    pub total_cores: Option<usize>,
+    #[serde(default)]
+    pub total_gpus: Option<usize>,
+}
+
+impl WorkerInfo {
+    /// True if the worker carries every one of `required` (a job's
+    /// `required_tags`).
+    pub fn has_tags(&self, required: &[String]) -> bool {
+        required.iter().all(|t| self.tags.contains(t))
+    }
+
+    /// True if the worker's tags let it run a job asking for `req`.
+    pub fn accepts(&self, req: &ResourceReq) -> bool {
+        self.has_tags(&req.required_tags) && !req.avoid_tags.iter().any(|t| self.tags.contains(t))
+    }
+}
+
+/// One completed job, flattened for `unifiedlab export`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultRecord {
+    pub id: String,
+    pub engine: String,
+    /// The blueprint node it came from (`structure.source`).
+    pub node: String,
+    pub workflow: Option<String>,
+    pub params: serde_json::Map<String, serde_json::Value>,
+    pub energy_ev: Option<f64>,
+    /// Largest force on one atom, in eV/Å.
+    pub max_force: Option<f64>,
+    pub t_total_ms: f64,
+    pub created_at: DateTime<Utc>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub host: String,
+    pub exit_code: i32,
+    pub binary_hash: Option<String>,
+    pub memoized_from: Option<String>,
+}
+
+/// Requested vs measured resources of one completed job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub code: String,
+    pub requested: ResourceReq,
+    pub usage: ResourceUsage,
+}
+
+/// One completed or failed job, as the throughput stats need it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinishedRecord {
+    pub id: String,
+    pub code: String,
+    pub failed: bool,
+    /// When the DB saw it finish (its last update).
+    pub finished_ms: i64,
+    /// Creation to driver start. None without a result, or for a memoized
+    /// copy, which never waited for a worker.
+    pub queue_ms: Option<i64>,
+    /// Driver start to end, from the provenance (None as above).
+    pub runtime_ms: Option<i64>,
+}
+
+// -----------------------------------------------------------------------------
+// StoreConfig
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalMode {
+    /// Rollback journal, deleted after each commit. No side files that
+    /// Lustre/GPFS locking could trip over.
+    Delete,
+    /// Write-ahead log: readers never block the writer. Needs shared memory
+    /// between processes, so one machine only.
+    Wal,
+}
+
+impl JournalMode {
+    fn pragma(self) -> &'static str {
+        match self {
+            Self::Delete => "DELETE",
+            Self::Wal => "WAL",
+        }
+    }
+}
+
+/// How the checkpoint DB is opened.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreConfig {
+    /// None: keep the DB's mode (DELETE for a new DB). Readers such as the
+    /// TUI use this, so they never switch a running Coordinator's DB.
+    pub journal: Option<JournalMode>,
+    /// SQLite page cache per connection, in KiB. None: SQLite's default.
+    pub cache_kib: Option<u64>,
+}
+
+impl StoreConfig {
+    /// Conservative settings for shared filesystems.
+    pub fn shared() -> Self {
+        Self {
+            journal: Some(JournalMode::Delete),
+            cache_kib: None,
+        }
+    }
+
+    /// One machine: WAL and a 64 MiB cache.
+    pub fn local() -> Self {
+        Self {
+            journal: Some(JournalMode::Wal),
+            cache_kib: Some(64 * 1024),
+        }
+    }
+
+    /// `local` on a workstation, `shared` under a batch system.
+    pub fn for_cluster(cluster: ClusterType) -> Self {
+        match cluster {
+            ClusterType::Local => Self::local(),
+            ClusterType::Slurm | ClusterType::Pbs => Self::shared(),
+        }
+    }
+}
+
+/// A job found by `search_jobs`, with the matching text marked `[like this]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub job: JobSummary,
+    pub snippet: String,
+}
+
+/// One row of `artifacts`: a file of a job's work dir, kept in the
+/// `ArtifactStore` under its hash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactRef {
+    pub job_id: String,
+    /// sha256 of the content.
+    pub hash: String,
+    /// `ContentType` guessed from the file name ("trajectory", "log"...).
+    pub kind: String,
+    /// Relative to the work dir, e.g. `OUTCAR` or `pseudo/POTCAR`.
+    pub path: String,
+    pub size: u64,
+    pub role: FileRole,
+}
+
+impl ArtifactRef {
+    /// Where the content sits in `store`.
+    pub fn store_path(&self, store: &ArtifactStore) -> PathBuf {
+        store.workfile_path(&self.path, &self.hash)
+    }
+}
+
+/// How a dependency came about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// Part of a submission (`deploy`, `submit`, an architect).
+    Submitted,
+    /// Created by a generator's expansion.
+    Expansion,
+    /// Added at runtime from the console (`edge add`, `node add`).
+    Manual,
+    /// Taken from the `parent_ids` of a job stored before edges were.
+    Unknown,
+}
+
+impl EdgeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Submitted => "submitted",
+            Self::Expansion => "expansion",
+            Self::Manual => "manual",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    fn from_db(s: &str) -> Self {
+        match s {
+            "submitted" => Self::Submitted,
+            "expansion" => Self::Expansion,
+            "manual" => Self::Manual,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// One row of `edges`: `child` waits on `parent`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DagEdge {
+    pub parent: Uuid,
+    pub child: Uuid,
+    pub kind: EdgeKind,
+}
+
+/// Edges added and removed since the last write, stored together with
+/// the jobs they belong to (`apply_batch_with_edges`).
+#[derive(Debug, Clone, Default)]
+pub struct EdgeDelta {
+    added: Vec<DagEdge>,
+    removed: Vec<(Uuid, Uuid)>,
+}
+
+impl EdgeDelta {
+    pub fn add(&mut self, parent: Uuid, child: Uuid, kind: EdgeKind) {
+        self.removed.retain(|e| *e != (parent, child));
+        self.added.push(DagEdge {
+            parent,
+            child,
+            kind,
+        });
+    }
+
+    pub fn remove(&mut self, parent: Uuid, child: Uuid) {
+        self.added
+            .retain(|e| (e.parent, e.child) != (parent, child));
+        self.removed.push((parent, child));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.added.clear();
+        self.removed.clear();
+    }
+}
+
+/// One row of `job_events`: a job entering `to_status` at `ts_ms`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobTransition {
+    /// None for the job's first row.
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub ts_ms: i64,
+    /// The job's `node_id` at the time, if any.
+    pub worker_id: Option<String>,
+}
+
+/// One row of `job_progress`: an intermediate result a running job sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressRecord {
+    /// When the Coordinator received it.
+    pub ts_ms: i64,
+    /// The worker running the attempt that sent it.
+    pub worker_id: String,
+    pub snapshot: CalculationResult,
+}
+
+/// Milliseconds spent in each status, in order of first appearance.
+/// The last status counts up to `until_ms`: now for a live job, its last
+/// `ts_ms` for a finished one.
+pub fn time_in_status(history: &[JobTransition], until_ms: i64) -> Vec<(String, i64)> {
+    let mut out: Vec<(String, i64)> = Vec::new();
+    for (i, t) in history.iter().enumerate() {
+        let end = history.get(i + 1).map_or(until_ms, |next| next.ts_ms);
+        let spent = (end - t.ts_ms).max(0);
+        match out.iter_mut().find(|(s, _)| *s == t.to_status) {
+            Some((_, ms)) => *ms += spent,
+            None => out.push((t.to_status.clone(), spent)),
+        }
+    }
+    out
+}
+
+/// Which finished jobs the Coordinator prunes from the DB, and where to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    /// Rows not updated for this long are pruned.
+    pub older_than: Duration,
+    pub statuses: Vec<JobStatus>,
+    /// Some: append pruned jobs to this JSONL file first.
+    pub archive: Option<PathBuf>,
+}
+
+impl RetentionPolicy {
+    /// Completed and Failed jobs older than `older_than`, deleted outright.
+    pub fn new(older_than: Duration) -> Self {
+        Self {
+            older_than,
+            statuses: vec![JobStatus::Completed, JobStatus::Failed],
+            archive: None,
+        }
+    }
+
+    pub fn with_archive(mut self, path: impl Into<PathBuf>) -> Self {
+        self.archive = Some(path.into());
+        self
+    }
+
+    pub fn with_statuses(mut self, statuses: Vec<JobStatus>) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    /// Runs one retention pass; returns the number of jobs pruned.
+    pub fn apply(&self, store: &CheckpointStore) -> Result<usize> {
+        store.prune_into(self.older_than, &self.statuses, self.archive.as_deref())
+    }
+
+    /// What `purge` would delete now, deleting nothing. Objects of
+    /// `artifacts` are listed only if given.
+    pub fn plan(
+        &self,
+        store: &CheckpointStore,
+        artifacts: Option<&ArtifactStore>,
+    ) -> Result<PurgePlan> {
+        let jobs = store.get_prunable(self.older_than, &self.statuses)?;
+        let blobs = match artifacts {
+            Some(a) => {
+                let going: HashSet<String> = jobs.iter().map(|j| j.id.clone()).collect();
+                self.unreferenced(store, a, &going)?
+            }
+            None => Vec::new(),
+        };
+        Ok(PurgePlan { jobs, blobs })
+    }
+
+    /// `apply`, then removes the objects of `artifacts` (if given) that no
+    /// job left in the DB refers to. Returns the jobs pruned and the
+    /// objects removed.
+    pub fn purge(
+        &self,
+        store: &CheckpointStore,
+        artifacts: Option<&ArtifactStore>,
+    ) -> Result<(usize, Vec<StoredObject>)> {
+        let jobs = self.apply(store)?;
+        let Some(artifacts) = artifacts else {
+            return Ok((jobs, Vec::new()));
+        };
+        let blobs = self.unreferenced(store, artifacts, &HashSet::new())?;
+        for b in &blobs {
+            artifacts.remove(&b.hash, &b.extension)?;
+        }
+        Ok((jobs, blobs))
+    }
+
+    /// Objects nothing refers to once the jobs in `going` are gone: no
+    /// job's files, no memo result. Structure blobs belong to the wire
+    /// codec and are kept, as is anything stored within `older_than`, so a
+    /// job finishing right now does not lose its files.
+    fn unreferenced(
+        &self,
+        store: &CheckpointStore,
+        artifacts: &ArtifactStore,
+        going: &HashSet<String>,
+    ) -> Result<Vec<StoredObject>> {
+        let referenced = store.get_referenced_hashes(going)?;
+        let cutoff = std::time::SystemTime::now() - self.older_than;
+        let mut out: Vec<StoredObject> = artifacts
+            .objects()?
+            .into_iter()
+            .filter(|o| {
+                o.extension != STRUCTURE_BLOB_EXT
+                    && o.modified < cutoff
+                    && !referenced.contains(&o.hash)
+            })
+            .collect();
+        out.sort_by_key(|o| o.modified);
+        Ok(out)
+    }
+}
+
+/// What `unifiedlab purge` deletes, worked out before anything is.
+#[derive(Debug, Clone, Default)]
+pub struct PurgePlan {
+    /// Jobs `prune` would delete, oldest first.
+    pub jobs: Vec<JobSummary>,
+    /// Artifact store objects left with no reference, oldest first.
+    pub blobs: Vec<StoredObject>,
 }
 
 // -----------------------------------------------------------------------------
 // CheckpointStore
 // -----------------------------------------------------------------------------
 
+/// Prepared statements kept per connection (the hot queries all fit).
+const STATEMENT_CACHE: usize = 32;
+
+/// One SQLite connection, opened with the store and held until it drops.
+/// Every call locks it for the duration of one statement or transaction;
+/// the TUI refresh and per-job Guardian writes no longer pay for opening
+/// the file and setting pragmas each time.
 pub struct CheckpointStore {
     path: PathBuf,
+    cfg: StoreConfig,
+    conn: Mutex<Connection>,
 }
 
 impl CheckpointStore {
+    /// Opens without changing the journal mode (see `StoreConfig::journal`).
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with(path, StoreConfig::default())
+    }
+
+    pub fn open_with(path: impl AsRef<Path>, cfg: StoreConfig) -> Result<Self> {
+        let conn = Connection::open(path.as_ref()).context("Failed to open Checkpoint DB")?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE);
+        if let Some(kib) = cfg.cache_kib {
+            // Negative: a size in KiB rather than in pages
+            conn.execute_batch(&format!("PRAGMA cache_size=-{};", kib))?;
+        }
         let store = Self {
             path: path.as_ref().to_path_buf(),
+            cfg,
+            conn: Mutex::new(conn),
         };
         store.init()?;
         Ok(store)
     }
 
-    /// Initialize the schema if it doesn't exist.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn config(&self) -> &StoreConfig {
+        &self.cfg
+    }
+
+    /// The schema version recorded in the DB (`DB_SCHEMA_VERSION` once open).
+    pub fn schema_version(&self) -> Result<u32> {
+        let conn = self.conn()?;
+        stored_version(&conn)
+    }
+
+    /// The journal mode the DB file is in.
+    pub fn journal_mode(&self) -> Result<String> {
+        let conn = self.conn()?;
+        Ok(conn.query_row("PRAGMA journal_mode", [], |r| r.get(0))?)
+    }
+
+    /// Brings the schema up to `DB_SCHEMA_VERSION` (see `migrate`).
     /// Sets strict timeout/journaling pragmas for HPC shared filesystems.
     fn init(&self) -> Result<()> {
-        let conn = self.conn()?;
+        let mut conn = self.conn()?;
 
         // HPC Optimization:
         // - DELETE journal mode avoids WAL files (locking issues on Lustre/GPFS).
+        //   WAL only when asked for (one machine). The mode is kept in the file.
         // - synchronous=NORMAL is safe enough given we have an Event Log for recovery.
         // - Busy timeout handles contention from TUI readers.
+        if let Some(mode) = self.cfg.journal {
+            // Leaving WAL needs the DB to itself; another open connection
+            // (e.g. a TUI) keeps the old mode until the next open
+            let set = conn
+                .query_row(&format!("PRAGMA journal_mode={}", mode.pragma()), [], |r| {
+                    r.get::<_, String>(0)
+                })
+                .map_err(anyhow::Error::from);
+            match set {
+                Ok(set) if set.eq_ignore_ascii_case(mode.pragma()) => {}
+                Ok(set) => log::warn!(
+                    "Checkpoint DB {:?} stays in {} mode (asked for {})",
+                    self.path,
+                    set,
+                    mode.pragma()
+                ),
+                Err(e) => log::warn!(
+                    "Checkpoint DB {:?}: cannot switch to {} mode yet: {}",
+                    self.path,
+                    mode.pragma(),
+                    e
+                ),
+            }
+        }
         conn.execute_batch(
-            "PRAGMA journal_mode=DELETE;
-             PRAGMA synchronous=NORMAL;
+            "PRAGMA synchronous=NORMAL;
              PRAGMA busy_timeout=10000;",
         )?;
 
-        conn.execute_batch(
-            "BEGIN;
-            CREATE TABLE IF NOT EXISTS meta (
-                key TEXT PRIMARY KEY,
-                value TEXT
-            );
-            
-            CREATE TABLE IF NOT EXISTS workers (
-                id TEXT PRIMARY KEY,
-                last_seen_ms INTEGER,
-                state_json TEXT
-            );
-
-            CREATE TABLE IF NOT EXISTS jobs (
-                id TEXT PRIMARY KEY,
-                status TEXT,
-                updated_at_ms INTEGER,
-                node_id TEXT,
-                full_json TEXT
-            );
-            
-            -- Indices for TUI filtering / sorting
-            CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status);
-            CREATE INDEX IF NOT EXISTS idx_jobs_updated ON jobs(updated_at_ms);
-            COMMIT;",
-        )?;
-
+        migrate(&mut conn, &self.path)?;
         Ok(())
     }
 
-    fn conn(&self) -> Result<Connection> {
-        Connection::open(&self.path).context("Failed to open Checkpoint DB")
+    /// A panic mid-transaction leaves nothing half-applied (the
+    /// transaction rolls back on drop), so a poisoned lock is still usable.
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>> {
+        Ok(self.conn.lock().unwrap_or_else(|e| e.into_inner()))
     }
 
     // -------------------------------------------------------------------------
@@ -105,21 +558,57 @@ impl CheckpointStore {
 
     pub fn save_cursor(&self, offset: u64) -> Result<()> {
         let conn = self.conn()?;
-        conn.execute(
+        conn.prepare_cached(
             "INSERT INTO meta (key, value) VALUES ('cursor', ?1)
              ON CONFLICT(key) DO UPDATE SET value=excluded.value",
-            params![offset.to_string()],
-        )?;
+        )?
+        .execute(params![offset.to_string()])?;
+        Ok(())
+    }
+
+    /// Generic key/value state (e.g. Coordinator control flags).
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        let conn = self.conn()?;
+        conn.prepare_cached(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value=excluded.value",
+        )?
+        .execute(params![key, value])?;
         Ok(())
     }
 
+    pub fn get_meta(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.conn()?;
+        let value = conn
+            .prepare_cached("SELECT value FROM meta WHERE key = ?1")?
+            .query_row(params![key], |r| r.get(0))
+            .optional()?;
+        Ok(value)
+    }
+
     /// Batch Upsert.
     /// Updates job states and worker heartbeats in a single transaction.
+    /// A job whose status differs from its stored row also gets a
+    /// `job_events` row (see `get_job_history`).
     pub fn apply_batch(
         &self,
         cursor: u64,
         updated_jobs: &[&Job],
         workers: &[WorkerInfo],
+    ) -> Result<()> {
+        self.apply_batch_with_edges(cursor, updated_jobs, workers, &EdgeDelta::default(), &[])
+    }
+
+    /// `apply_batch`, plus the edge changes behind the jobs' `parent_ids`
+    /// and meta keys describing them, in the same transaction so they never
+    /// disagree.
+    pub fn apply_batch_with_edges(
+        &self,
+        cursor: u64,
+        updated_jobs: &[&Job],
+        workers: &[WorkerInfo],
+        edges: &EdgeDelta,
+        meta: &[(&str, String)],
     ) -> Result<()> {
         let mut conn = self.conn()?;
         let tx = conn.transaction()?;
@@ -132,10 +621,17 @@ impl CheckpointStore {
                 params![cursor.to_string()],
             )?;
         }
+        for (key, value) in meta {
+            tx.execute(
+                "INSERT INTO meta (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value=excluded.value",
+                params![key, value],
+            )?;
+        }
 
         // 2. Upsert Workers
         {
-            let mut stmt = tx.prepare(
+            let mut stmt = tx.prepare_cached(
                 "INSERT INTO workers (id, last_seen_ms, state_json) 
                  VALUES (?1, ?2, ?3)
                  ON CONFLICT(id) DO UPDATE SET 
@@ -148,9 +644,14 @@ impl CheckpointStore {
             }
         }
 
-        // 3. Upsert Jobs
+        // 3. Upsert Jobs (recording status transitions first)
         {
-            let mut stmt = tx.prepare(
+            let mut prev = tx.prepare_cached("SELECT status FROM jobs WHERE id = ?1")?;
+            let mut event = tx.prepare_cached(
+                "INSERT INTO job_events (job_id, from_status, to_status, ts, worker_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            let mut stmt = tx.prepare_cached(
                 "INSERT INTO jobs (id, status, updated_at_ms, node_id, full_json)
                  VALUES (?1, ?2, ?3, ?4, ?5)
                  ON CONFLICT(id) DO UPDATE SET
@@ -165,6 +666,26 @@ impl CheckpointStore {
                 let status_str = format!("{:?}", job.status);
                 let updated_ms = job.updated_at.timestamp_millis();
 
+                let id = job.id.to_string();
+                let from: Option<String> = prev.query_row(params![id], |r| r.get(0)).optional()?;
+                if from.as_deref() != Some(status_str.as_str()) {
+                    event.execute(params![id, from, status_str, updated_ms, job.node_id])?;
+                    // Params are fixed at creation; the error log comes with a status change
+                    index_job(
+                        &tx,
+                        &id,
+                        &job.config.engine.code(),
+                        &job.config.params.to_string(),
+                        job.error_log.as_deref().unwrap_or_default(),
+                    )?;
+                    // So does the result; a retried job drops its old files
+                    let files = job.result.as_ref().map_or(&[][..], |r| &r.provenance.files);
+                    index_artifacts(&tx, &id, files)?;
+                    if job.status == JobStatus::Completed {
+                        record_memo(&tx, job)?;
+                    }
+                }
+
                 stmt.execute(params![
                     job.id.to_string(),
                     status_str,
@@ -175,10 +696,146 @@ impl CheckpointStore {
             }
         }
 
+        // 4. Edges (a re-sent submission adds nothing)
+        if !edges.is_empty() {
+            let mut add = tx.prepare_cached(
+                "INSERT INTO edges (parent, child, kind) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(parent, child) DO NOTHING",
+            )?;
+            for e in &edges.added {
+                add.execute(params![
+                    e.parent.to_string(),
+                    e.child.to_string(),
+                    e.kind.as_str()
+                ])?;
+            }
+            let mut remove =
+                tx.prepare_cached("DELETE FROM edges WHERE parent = ?1 AND child = ?2")?;
+            for (parent, child) in &edges.removed {
+                remove.execute(params![parent.to_string(), child.to_string()])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Appends progress snapshots, by job, in one transaction.
+    pub fn record_progress(&self, rows: &[(Uuid, ProgressRecord)]) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO job_progress (job_id, ts, worker_id, snapshot_json)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (job_id, rec) in rows {
+                insert.execute(params![
+                    job_id.to_string(),
+                    rec.ts_ms,
+                    rec.worker_id,
+                    serde_json::to_string(&rec.snapshot)?
+                ])?;
+            }
+        }
         tx.commit()?;
         Ok(())
     }
 
+    /// Deletes jobs in one of `statuses` not updated for `older_than`, with
+    /// their history, progress, artifact references and edges. See `prune_into`.
+    pub fn prune(&self, older_than: Duration, statuses: &[JobStatus]) -> Result<usize> {
+        self.prune_into(older_than, statuses, None)
+    }
+
+    /// Like `prune`, but first appends each pruned job to `archive` as one
+    /// JSON line. A job that is still a parent of an unfinished job is
+    /// kept, since a restored Coordinator would never release the child.
+    pub fn prune_into(
+        &self,
+        older_than: Duration,
+        statuses: &[JobStatus],
+        archive: Option<&Path>,
+    ) -> Result<usize> {
+        let (statuses, cutoff) = prune_params(older_than, statuses)?;
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute_batch("CREATE TEMP TABLE IF NOT EXISTS prune_ids (id TEXT PRIMARY KEY); DELETE FROM prune_ids;")?;
+        tx.execute(
+            &format!("INSERT INTO prune_ids {}", PRUNABLE_IDS),
+            params![statuses, cutoff],
+        )?;
+
+        if let Some(path) = archive {
+            let mut stmt =
+                tx.prepare("SELECT full_json FROM jobs WHERE id IN (SELECT id FROM prune_ids)")?;
+            let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+            let mut out = std::io::BufWriter::new(
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open archive {:?}", path))?,
+            );
+            for json in rows {
+                writeln!(out, "{}", json?)?;
+            }
+            // On disk before the rows go: a crash here only archives them twice
+            out.into_inner()
+                .map_err(|e| anyhow!("Failed to write archive {:?}: {}", path, e))?
+                .sync_all()?;
+        }
+
+        tx.execute(
+            "DELETE FROM job_events WHERE job_id IN (SELECT id FROM prune_ids)",
+            [],
+        )?;
+        tx.execute(
+            "DELETE FROM job_progress WHERE job_id IN (SELECT id FROM prune_ids)",
+            [],
+        )?;
+        tx.execute(
+            "DELETE FROM jobs_fts WHERE job_id IN (SELECT id FROM prune_ids)",
+            [],
+        )?;
+        tx.execute(
+            "DELETE FROM artifacts WHERE job_id IN (SELECT id FROM prune_ids)",
+            [],
+        )?;
+        tx.execute(
+            "DELETE FROM edges
+             WHERE child IN (SELECT id FROM prune_ids) OR parent IN (SELECT id FROM prune_ids)",
+            [],
+        )?;
+        let pruned = tx.execute(
+            "DELETE FROM jobs WHERE id IN (SELECT id FROM prune_ids)",
+            [],
+        )?;
+        tx.execute("DELETE FROM prune_ids", [])?;
+        tx.commit()?;
+        Ok(pruned)
+    }
+
+    /// The jobs `prune` would delete now, oldest first.
+    pub fn get_prunable(
+        &self,
+        older_than: Duration,
+        statuses: &[JobStatus],
+    ) -> Result<Vec<JobSummary>> {
+        let (statuses, cutoff) = prune_params(older_than, statuses)?;
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, status, node_id, updated_at_ms, full_json FROM jobs
+             WHERE id IN ({}) ORDER BY updated_at_ms",
+            PRUNABLE_IDS
+        ))?;
+        let rows = stmt
+            .query_map(params![statuses, cutoff], summary_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
     // -------------------------------------------------------------------------
     // READ API (Restoration)
     // -------------------------------------------------------------------------
@@ -186,9 +843,8 @@ impl CheckpointStore {
     pub fn get_cursor(&self) -> Result<u64> {
         let conn = self.conn()?;
         let val: Option<String> = conn
-            .query_row("SELECT value FROM meta WHERE key = 'cursor'", [], |r| {
-                r.get(0)
-            })
+            .prepare_cached("SELECT value FROM meta WHERE key = 'cursor'")?
+            .query_row([], |r| r.get(0))
             .optional()?;
 
         match val {
@@ -200,69 +856,116 @@ impl CheckpointStore {
     /// Full restoration of all jobs.
     /// Used on Coordinator startup to rebuild the in-memory graph.
     pub fn restore_jobs(&self) -> Result<HashMap<Uuid, Job>> {
-        let conn = self.conn()?;
-        let mut stmt = conn.prepare("SELECT full_json FROM jobs")?;
+        let (map, report) = self.restore_jobs_with_report()?;
+        report.log();
+        Ok(map)
+    }
 
-        let rows = stmt.query_map([], |row| {
-            let json: String = row.get(0)?;
-            Ok(json)
-        })?;
+    /// Like `restore_jobs`, but also says which records were upgraded or dropped.
+    /// Upgraded records are written back so the upgrade only happens once;
+    /// dropped rows are left as they are for a newer build (or a human) to read.
+    pub fn restore_jobs_with_report(&self) -> Result<(HashMap<Uuid, Job>, RestoreReport)> {
+        let mut conn = self.conn()?;
+        let rows: Vec<(String, String)> = {
+            let mut stmt = conn.prepare("SELECT id, full_json FROM jobs")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
 
         let mut map = HashMap::new();
-        for r in rows {
-            let json = r?;
-            // Defensive deserialization: If schema evolved, skip bad records
-            if let Ok(job) = serde_json::from_str::<Job>(&json) {
-                map.insert(job.id, job);
-            } else {
-                log::warn!("Failed to deserialize a job record during restore.");
+        let mut report = RestoreReport::default();
+        let mut upgraded = Vec::new();
+        for (id, json) in rows {
+            match schema::restore_job(&json) {
+                Ok(Restored::Current(job)) => {
+                    map.insert(job.id, job);
+                }
+                Ok(Restored::Upgraded(job)) => {
+                    upgraded.push(job.id);
+                    map.insert(job.id, job);
+                }
+                Err(e) => report.dropped.push(DroppedRecord {
+                    id,
+                    reason: format!("{:#}", e),
+                }),
             }
         }
-        Ok(map)
+
+        if !upgraded.is_empty() {
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare("UPDATE jobs SET full_json = ?2 WHERE id = ?1")?;
+                for id in &upgraded {
+                    let json = serde_json::to_string(&map[id])?;
+                    stmt.execute(params![id.to_string(), json])?;
+                }
+            }
+            tx.commit()?;
+        }
+
+        report.loaded = map.len();
+        report.upgraded = upgraded.len();
+        Ok((map, report))
     }
 
     // -------------------------------------------------------------------------
     // READ API (TUI Optimized)
     // -------------------------------------------------------------------------
 
-    pub fn get_active_workers(&self) -> Result<Vec<WorkerInfo>> {
+    /// Completed jobs that carry measured usage (for `report efficiency`).
+    /// Skips the structure, like `get_jobs_summary`.
+    pub fn get_usage_records(&self) -> Result<Vec<UsageRecord>> {
         let conn = self.conn()?;
-        // Fetch workers seen in last 5 minutes (approx) to filter ghosts?
-        // For now, fetch all, TUI can sort.
-        let mut stmt = conn.prepare("SELECT state_json FROM workers ORDER BY last_seen_ms DESC")?;
+        let mut stmt = conn.prepare("SELECT full_json FROM jobs WHERE status = 'Completed'")?;
 
-        let rows = stmt.query_map([], |row| {
-            let json: String = row.get(0)?;
-            Ok(json)
-        })?;
+        #[derive(Deserialize)]
+        struct PartialJob {
+            config: PartialConfig,
+            resources: ResourceReq,
+            result: Option<PartialResult>,
+        }
+        #[derive(Deserialize)]
+        struct PartialConfig {
+            engine: Engine,
+        }
+        #[derive(Deserialize)]
+        struct PartialResult {
+            usage: Option<ResourceUsage>,
+        }
 
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
         let mut out = Vec::new();
         for r in rows {
-            if let Ok(w) = serde_json::from_str::<WorkerInfo>(&r?) {
-                out.push(w);
+            let Ok(p) = serde_json::from_str::<PartialJob>(&r?) else {
+                continue;
+            };
+            if let Some(usage) = p.result.and_then(|r| r.usage) {
+                out.push(UsageRecord {
+                    code: p.config.engine.code(),
+                    requested: p.resources,
+                    usage,
+                });
             }
         }
         Ok(out)
     }
 
-    /// Fast summary fetch for TUI.
-    /// Manually extracts Engine type string from the JSON blob.
-    /// CRITICAL: Does NOT deserialize the 'structure' field (heavy atoms).
-    pub fn get_jobs_summary(&self) -> Result<Vec<JobSummary>> {
+    /// Jobs that completed or failed at or after `since_ms` (by their last
+    /// update). Called with a rising watermark, it only reads the new rows.
+    pub fn get_finished_since(&self, since_ms: i64) -> Result<Vec<FinishedRecord>> {
         let conn = self.conn()?;
-
-        let mut stmt = conn.prepare(
-            "SELECT id, status, node_id, updated_at_ms, full_json 
-             FROM jobs 
-             ORDER BY updated_at_ms DESC 
-             LIMIT 1000",
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, status, updated_at_ms, full_json FROM jobs
+             WHERE status IN ('Completed', 'Failed') AND updated_at_ms >= ?1",
         )?;
 
-        // Lightweight struct to peek inside the full JSON without full deserialization
         #[derive(Deserialize)]
         struct PartialJob {
+            created_at: DateTime<Utc>,
             config: PartialConfig,
             result: Option<PartialResult>,
+            #[serde(default)]
+            flow_context: HashMap<String, serde_json::Value>,
         }
         #[derive(Deserialize)]
         struct PartialConfig {
@@ -270,45 +973,198 @@ impl CheckpointStore {
         }
         #[derive(Deserialize)]
         struct PartialResult {
-            t_total_ms: f64,
+            provenance: PartialProvenance,
+        }
+        #[derive(Deserialize)]
+        struct PartialProvenance {
+            start_time: DateTime<Utc>,
+            end_time: DateTime<Utc>,
         }
 
-        let iter = stmt.query_map([], |row| {
-            let id: String = row.get(0)?;
-            let status: String = row.get(1)?;
-            let node_id: Option<String> = row.get(2)?;
-            let updated_at: i64 = row.get(3)?;
-            let json: String = row.get(4)?;
-
-            // Extract display code (e.g., "janus:mace_mp" or "vasp")
-            // Default to "?" if parsing fails
-            let (code, t_total) = match serde_json::from_str::<PartialJob>(&json) {
-                Ok(p) => {
-                    let code_str = match p.config.engine {
-                        Engine::Janus { arch, .. } => format!("janus:{}", arch),
-                        Engine::Gulp { .. } => "gulp".to_string(),
-                        Engine::Vasp { mpi_ranks, .. } => format!("vasp:{}p", mpi_ranks),
-                        Engine::Cp2k { mpi_ranks, .. } => format!("cp2k:{}p", mpi_ranks),
-                        Engine::Agent { strategy, .. } => format!("agent:{}", strategy),
-                    };
-                    let time = p.result.map(|r| r.t_total_ms).unwrap_or(0.0);
-                    (code_str, time)
-                }
-                Err(_) => ("?".to_string(), 0.0),
-            };
-
-            Ok(JobSummary {
-                id,
-                status,
-                code,
-                node_id: node_id.unwrap_or_default(),
-                updated_at,
-                t_total,
-            })
+        let rows = stmt.query_map(params![since_ms], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+            ))
         })?;
-
         let mut out = Vec::new();
-        for i in iter {
+        for r in rows {
+            let (id, status, updated_ms, json) = r?;
+            let Ok(p) = serde_json::from_str::<PartialJob>(&json) else {
+                continue;
+            };
+            let span = p
+                .result
+                .filter(|_| !p.flow_context.contains_key("memoized_from"))
+                .map(|r| r.provenance);
+            out.push(FinishedRecord {
+                id,
+                code: p.config.engine.code(),
+                failed: status == "Failed",
+                finished_ms: updated_ms,
+                queue_ms: span
+                    .as_ref()
+                    .map(|s| (s.start_time - p.created_at).num_milliseconds().max(0)),
+                runtime_ms: span.map(|s| (s.end_time - s.start_time).num_milliseconds()),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Every completed job with its result, oldest first. Like
+    /// `get_jobs_summary`, it never parses structures or trajectories.
+    pub fn get_completed_results(&self) -> Result<Vec<ResultRecord>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, full_json FROM jobs
+             WHERE status = 'Completed' ORDER BY updated_at_ms, id",
+        )?;
+
+        #[derive(Deserialize)]
+        struct PartialJob {
+            created_at: DateTime<Utc>,
+            structure: PartialStructure,
+            config: PartialConfig,
+            result: Option<PartialResult>,
+            #[serde(default)]
+            flow_context: HashMap<String, serde_json::Value>,
+        }
+        #[derive(Deserialize)]
+        struct PartialStructure {
+            #[serde(default)]
+            source: String,
+        }
+        #[derive(Deserialize)]
+        struct PartialConfig {
+            engine: Engine,
+            #[serde(default)]
+            params: serde_json::Value,
+        }
+        #[derive(Deserialize)]
+        struct PartialResult {
+            energy: Option<ElectronVolts>,
+            forces: Option<Vec<[Force; 3]>>,
+            t_total_ms: f64,
+            provenance: PartialProvenance,
+        }
+        #[derive(Deserialize)]
+        struct PartialProvenance {
+            execution_host: String,
+            start_time: DateTime<Utc>,
+            end_time: DateTime<Utc>,
+            binary_hash: Option<String>,
+            exit_code: i32,
+        }
+
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            let (id, json) = r?;
+            let Ok(p) = serde_json::from_str::<PartialJob>(&json) else {
+                continue;
+            };
+            let Some(result) = p.result else {
+                continue;
+            };
+            let text = |key: &str| {
+                p.flow_context
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .map(String::from)
+            };
+            out.push(ResultRecord {
+                id,
+                engine: p.config.engine.code(),
+                node: p.structure.source,
+                workflow: text(FLOW_WORKFLOW),
+                params: match p.config.params {
+                    serde_json::Value::Object(map) => map,
+                    _ => Default::default(),
+                },
+                energy_ev: result.energy.map(|e| e.0),
+                max_force: result.forces.map(|f| {
+                    f.iter()
+                        .map(|[x, y, z]| (x.0 * x.0 + y.0 * y.0 + z.0 * z.0).sqrt())
+                        .fold(0.0, f64::max)
+                }),
+                t_total_ms: result.t_total_ms,
+                created_at: p.created_at,
+                started_at: result.provenance.start_time,
+                finished_at: result.provenance.end_time,
+                host: result.provenance.execution_host,
+                exit_code: result.provenance.exit_code,
+                binary_hash: result.provenance.binary_hash,
+                memoized_from: text("memoized_from"),
+            });
+        }
+        Ok(out)
+    }
+
+    pub fn get_active_workers(&self) -> Result<Vec<WorkerInfo>> {
+        let conn = self.conn()?;
+        // Fetch workers seen in last 5 minutes (approx) to filter ghosts?
+        // For now, fetch all, TUI can sort.
+        let mut stmt =
+            conn.prepare_cached("SELECT state_json FROM workers ORDER BY last_seen_ms DESC")?;
+
+        let rows = stmt.query_map([], |row| {
+            let json: String = row.get(0)?;
+            Ok(json)
+        })?;
+
+        let mut out = Vec::new();
+        for r in rows {
+            if let Ok(w) = serde_json::from_str::<WorkerInfo>(&r?) {
+                out.push(w);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Number of jobs in each status (by name, e.g. "Running").
+    pub fn count_by_status(&self) -> Result<HashMap<String, u64>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached("SELECT status, COUNT(*) FROM jobs GROUP BY status")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The `Pending` job with the oldest last update, i.e. the one that has
+    /// waited longest for a worker.
+    pub fn get_oldest_pending(&self) -> Result<Option<JobSummary>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, status, node_id, updated_at_ms, full_json
+             FROM jobs
+             WHERE status = 'Pending'
+             ORDER BY updated_at_ms ASC
+             LIMIT 1",
+        )?;
+        Ok(stmt.query_row([], summary_from_row).optional()?)
+    }
+
+    /// Fast summary fetch for TUI.
+    /// Manually extracts Engine type string from the JSON blob.
+    /// CRITICAL: Does NOT deserialize the 'structure' field (heavy atoms).
+    pub fn get_jobs_summary(&self) -> Result<Vec<JobSummary>> {
+        let conn = self.conn()?;
+
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, status, node_id, updated_at_ms, full_json 
+             FROM jobs 
+             ORDER BY updated_at_ms DESC 
+             LIMIT 1000",
+        )?;
+
+        let iter = stmt.query_map([], summary_from_row)?;
+        let mut out = Vec::new();
+        for i in iter {
             if let Ok(s) = i {
                 out.push(s);
             }
@@ -316,15 +1172,1186 @@ impl CheckpointStore {
         Ok(out)
     }
 
+    /// Like `get_jobs_summary`, but only jobs updated at or after `ts_ms`.
+    /// Called with a rising watermark, it only parses the rows that changed.
+    pub fn get_jobs_summary_since(&self, ts_ms: i64) -> Result<Vec<JobSummary>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, status, node_id, updated_at_ms, full_json
+             FROM jobs
+             WHERE updated_at_ms >= ?1
+             ORDER BY updated_at_ms DESC
+             LIMIT 1000",
+        )?;
+        let rows = stmt
+            .query_map(params![ts_ms], summary_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
+    /// Summaries of exactly these jobs (missing ids are skipped), for
+    /// callers tracking one submission in a DB of any size.
+    pub fn get_jobs_summary_for(&self, ids: &[String]) -> Result<Vec<JobSummary>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, status, node_id, updated_at_ms, full_json 
+             FROM jobs 
+             WHERE id IN (SELECT value FROM json_each(?1))",
+        )?;
+        let rows = stmt
+            .query_map(params![serde_json::to_string(ids)?], summary_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
+    /// Expands a short ID (e.g. the 8 chars shown in logs) to the full job ID.
+    pub fn resolve_job_id(&self, prefix: &str) -> Result<String> {
+        let conn = self.conn()?;
+        let mut stmt =
+            conn.prepare_cached("SELECT id FROM jobs WHERE id LIKE ?1 || '%' LIMIT 2")?;
+        let ids: Vec<String> = stmt
+            .query_map(params![prefix], |r| r.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        match ids.as_slice() {
+            [id] => Ok(id.clone()),
+            [] => Err(anyhow!("No job matches '{}'", prefix)),
+            _ => Err(anyhow!("'{}' is ambiguous, type more characters", prefix)),
+        }
+    }
+
+    /// Status transitions of one job, oldest first. Only what `apply_batch`
+    /// saw: a job that moved twice between checkpoints shows one step.
+    pub fn get_job_history(&self, id: &str) -> Result<Vec<JobTransition>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT from_status, to_status, ts, worker_id FROM job_events
+             WHERE job_id = ?1 ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![id], |r| {
+                Ok(JobTransition {
+                    from_status: r.get(0)?,
+                    to_status: r.get(1)?,
+                    ts_ms: r.get(2)?,
+                    worker_id: r.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
+    /// Progress snapshots of one job, oldest first, upgraded to the current
+    /// schema. Earlier attempts' snapshots are kept too (see `worker_id`).
+    pub fn get_job_progress(&self, id: &str) -> Result<Vec<ProgressRecord>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT ts, worker_id, snapshot_json FROM job_progress
+             WHERE job_id = ?1 ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![id], |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (ts_ms, worker_id, json) = row?;
+            let mut raw: serde_json::Value = serde_json::from_str(&json)?;
+            schema::upgrade_result(&mut raw)?;
+            out.push(ProgressRecord {
+                ts_ms,
+                worker_id,
+                snapshot: serde_json::from_value(raw)?,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Jobs whose engine code, params or error log contain every word of
+    /// `query`, best matches first (at most 1000). Words are matched whole;
+    /// end one with `*` to match a prefix, e.g. `cuda out of mem*`.
+    pub fn search_jobs(&self, query: &str) -> Result<Vec<SearchHit>> {
+        let fts = fts_query(query)?;
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT j.id, j.status, j.node_id, j.updated_at_ms, j.full_json,
+                    snippet(jobs_fts, -1, '[', ']', '…', 12)
+             FROM jobs_fts f JOIN jobs j ON j.id = f.job_id
+             WHERE jobs_fts MATCH ?1
+             ORDER BY f.rank
+             LIMIT 1000",
+        )?;
+        let rows = stmt
+            .query_map(params![fts], |row| {
+                Ok(SearchHit {
+                    job: summary_from_row(row)?,
+                    snippet: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
+    /// Every dependency in the DB, by child. Cheap next to `restore_jobs`:
+    /// no job JSON is read.
+    pub fn get_edges(&self) -> Result<Vec<DagEdge>> {
+        let conn = self.conn()?;
+        let mut stmt =
+            conn.prepare_cached("SELECT parent, child, kind FROM edges ORDER BY child, parent")?;
+        let rows = stmt.query_map([], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (parent, child, kind) = row?;
+            // Ids are written from Uuids; anything else was not
+            if let (Ok(parent), Ok(child)) = (Uuid::parse_str(&parent), Uuid::parse_str(&child)) {
+                out.push(DagEdge {
+                    parent,
+                    child,
+                    kind: EdgeKind::from_db(&kind),
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    /// Every memoized fingerprint with the job that answers for it, pruned
+    /// jobs included.
+    pub fn get_memo_index(&self) -> Result<HashMap<String, Uuid>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached("SELECT fingerprint, job_id FROM memo")?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+        let mut out = HashMap::new();
+        for row in rows {
+            let (fingerprint, job_id) = row?;
+            if let Ok(id) = Uuid::parse_str(&job_id) {
+                out.insert(fingerprint, id);
+            }
+        }
+        Ok(out)
+    }
+
+    /// The result stored for `fingerprint`, upgraded to the current schema.
+    pub fn get_memo_result(&self, fingerprint: &str) -> Result<Option<CalculationResult>> {
+        let conn = self.conn()?;
+        Ok(memo_lookup(&conn, fingerprint)?.map(|(_, result)| result))
+    }
+
+    /// Files a job left in the artifact store, inputs first, by path.
+    pub fn get_job_artifacts(&self, job_id: &str) -> Result<Vec<ArtifactRef>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT job_id, hash, kind, path, size, role FROM artifacts
+             WHERE job_id = ?1 ORDER BY role, path",
+        )?;
+        let rows = stmt
+            .query_map(params![job_id], artifact_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
+    /// Every job referencing the object `hash`. Empty means nothing in
+    /// this DB still needs it.
+    pub fn get_artifact_refs(&self, hash: &str) -> Result<Vec<ArtifactRef>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT job_id, hash, kind, path, size, role FROM artifacts
+             WHERE hash = ?1 ORDER BY job_id, path",
+        )?;
+        let rows = stmt
+            .query_map(params![hash], artifact_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
+    /// Every object hash a job's files (other than those of the jobs in
+    /// `except`) or a memo result refers to.
+    pub fn get_referenced_hashes(&self, except: &HashSet<String>) -> Result<HashSet<String>> {
+        let conn = self.conn()?;
+        let mut out = HashSet::new();
+        let mut stmt = conn.prepare("SELECT job_id, hash FROM artifacts")?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (job_id, hash) = row?;
+            if !except.contains(&job_id) {
+                out.insert(hash);
+            }
+        }
+        let mut stmt = conn.prepare(
+            "SELECT json_extract(f.value, '$.sha256')
+             FROM memo, json_each(memo.result_json, '$.provenance.files') f",
+        )?;
+        for hash in stmt.query_map([], |r| r.get::<_, Option<String>>(0))? {
+            out.extend(hash?);
+        }
+        Ok(out)
+    }
+
     /// Fetch full details for the Inspector panel.
     pub fn get_job_details(&self, id: &str) -> Result<Job> {
         let conn = self.conn()?;
-        let json: String = conn.query_row(
-            "SELECT full_json FROM jobs WHERE id = ?1",
-            params![id],
-            |r| r.get(0),
-        )?;
+        let json: String = conn
+            .prepare_cached("SELECT full_json FROM jobs WHERE id = ?1")?
+            .query_row(params![id], |r| r.get(0))?;
         let job: Job = serde_json::from_str(&json)?;
         Ok(job)
     }
 }
+
+// -----------------------------------------------------------------------------
+// MemoCache
+// -----------------------------------------------------------------------------
+
+/// Completed results shared by campaigns: a standalone SQLite file holding
+/// only a `memo` table, so a blueprint deployed into a fresh root can reuse
+/// what earlier campaigns computed. Several Coordinators may use one file.
+pub struct MemoCache {
+    path: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl MemoCache {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(&path)
+            .with_context(|| format!("Failed to open memo cache {:?}", path))?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE);
+        conn.execute_batch(
+            "PRAGMA synchronous=NORMAL;
+             PRAGMA busy_timeout=10000;",
+        )?;
+        create_memo_table(&conn)?;
+        Ok(Self {
+            path,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>> {
+        Ok(self.conn.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// The job that answers for `fingerprint` and its result.
+    pub fn get(&self, fingerprint: &str) -> Result<Option<(Uuid, CalculationResult)>> {
+        let conn = self.conn()?;
+        Ok(memo_lookup(&conn, fingerprint)?
+            .and_then(|(id, result)| Some((Uuid::parse_str(&id).ok()?, result))))
+    }
+
+    /// Records the completed jobs among `jobs` in one transaction; how many.
+    pub fn record(&self, jobs: &[&Job]) -> Result<usize> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let mut n = 0;
+        for job in jobs.iter().filter(|j| j.status == JobStatus::Completed) {
+            n += record_memo(&tx, job)? as usize;
+        }
+        tx.commit()?;
+        Ok(n)
+    }
+
+    /// Distinct fingerprints held.
+    pub fn entries(&self) -> Result<usize> {
+        let conn = self.conn()?;
+        let n: i64 = conn.query_row("SELECT count(*) FROM memo", [], |r| r.get(0))?;
+        Ok(n as usize)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Migrations
+// -----------------------------------------------------------------------------
+
+/// Schema version of checkpoint.db this build writes.
+pub const DB_SCHEMA_VERSION: u32 = 7;
+/// Meta key holding the DB's schema version. DBs without one are v0.
+pub const META_SCHEMA_VERSION: &str = "schema_version";
+
+/// One migration: moves the DB from version N to N + 1.
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Indexed by the version being migrated *from*. Append only: a step that
+/// shipped must never change. Steps use IF NOT EXISTS, since DBs from
+/// before versioning may already hold some of the later tables.
+const MIGRATIONS: &[Migration] = &[
+    db_v0_to_v1,
+    db_v1_to_v2,
+    db_v2_to_v3,
+    db_v3_to_v4,
+    db_v4_to_v5,
+    db_v5_to_v6,
+    db_v6_to_v7,
+];
+
+fn stored_version(conn: &Connection) -> Result<u32> {
+    let has_meta: bool = conn.query_row(
+        "SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'meta'",
+        [],
+        |r| r.get(0),
+    )?;
+    if !has_meta {
+        return Ok(0);
+    }
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM meta WHERE key = ?1",
+            params![META_SCHEMA_VERSION],
+            |r| r.get(0),
+        )
+        .optional()?;
+    match value {
+        None => Ok(0),
+        Some(v) => v
+            .parse()
+            .map_err(|_| anyhow!("checkpoint.db has a non-numeric schema_version '{}'", v)),
+    }
+}
+
+/// Runs the missing migrations in order, each in its own write
+/// transaction together with its version bump. A crash between two steps
+/// resumes at the next one; two processes opening the same old DB take
+/// turns, and the second finds nothing left to do.
+fn migrate(conn: &mut Connection, path: &Path) -> Result<()> {
+    let start = stored_version(conn)?;
+    if start > DB_SCHEMA_VERSION {
+        return Err(anyhow!(
+            "{:?} has schema v{}, written by a newer UnifiedLAB (this build reads up to v{})",
+            path,
+            start,
+            DB_SCHEMA_VERSION
+        ));
+    }
+
+    let mut version = start;
+    while version < DB_SCHEMA_VERSION {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        // Another process may have migrated while we waited for the lock
+        version = stored_version(&tx)?;
+        if version >= DB_SCHEMA_VERSION {
+            break;
+        }
+        MIGRATIONS[version as usize](&tx)
+            .with_context(|| format!("Migrating {:?} from schema v{}", path, version))?;
+        version += 1;
+        tx.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value=excluded.value",
+            params![META_SCHEMA_VERSION, version.to_string()],
+        )?;
+        tx.commit()?;
+    }
+    // v0 is also a brand-new file; only a real upgrade is worth a line
+    if start > 0 && version > start {
+        log::info!(
+            "🗄️ Migrated checkpoint DB {:?} from schema v{} to v{}",
+            path,
+            start,
+            version
+        );
+    }
+    Ok(())
+}
+
+/// The original layout: key/value meta, worker heartbeats, jobs.
+fn db_v0_to_v1(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS workers (
+            id TEXT PRIMARY KEY,
+            last_seen_ms INTEGER,
+            state_json TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS jobs (
+            id TEXT PRIMARY KEY,
+            status TEXT,
+            updated_at_ms INTEGER,
+            node_id TEXT,
+            full_json TEXT
+        );
+
+        -- Indices for TUI filtering / sorting
+        CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status);
+        CREATE INDEX IF NOT EXISTS idx_jobs_updated ON jobs(updated_at_ms);",
+    )?;
+    Ok(())
+}
+
+/// Status transitions, appended by apply_batch.
+fn db_v1_to_v2(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS job_events (
+            id INTEGER PRIMARY KEY,
+            job_id TEXT NOT NULL,
+            from_status TEXT,
+            to_status TEXT NOT NULL,
+            ts INTEGER,
+            worker_id TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_job_events_job ON job_events(job_id);",
+    )?;
+    Ok(())
+}
+
+/// Full-text search; rowid is derived from the job id (`search_rowid`).
+/// Jobs already stored are indexed here.
+fn db_v2_to_v3(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS jobs_fts USING fts5(
+            job_id UNINDEXED,
+            code,
+            params,
+            error_log
+        );",
+    )?;
+    let n = index_unindexed(conn)?;
+    if n > 0 {
+        log::info!("🔎 Indexed {} stored job(s) for search", n);
+    }
+    Ok(())
+}
+
+/// Artifact references, filled from each job's `provenance.files`.
+/// Jobs already stored are filled in here.
+fn db_v3_to_v4(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS artifacts (
+            job_id TEXT NOT NULL,
+            hash TEXT NOT NULL,
+            kind TEXT,
+            path TEXT,
+            size INTEGER,
+            role TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_artifacts_job ON artifacts(job_id);
+        CREATE INDEX IF NOT EXISTS idx_artifacts_hash ON artifacts(hash);",
+    )?;
+    let n = index_unlisted_artifacts(conn)?;
+    if n > 0 {
+        log::info!("📦 Listed {} stored artifact reference(s)", n);
+    }
+    Ok(())
+}
+
+/// DAG edges, filled from the `parent_ids` of the jobs already stored.
+fn db_v4_to_v5(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS edges (
+            parent TEXT NOT NULL,
+            child TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            PRIMARY KEY (parent, child)
+        );
+        CREATE INDEX IF NOT EXISTS idx_edges_child ON edges(child);",
+    )?;
+    let n = edges_from_parent_ids(conn)?;
+    if n > 0 {
+        log::info!("🔗 Recorded {} stored dependency edge(s)", n);
+    }
+    Ok(())
+}
+
+/// Memoization results by fingerprint, filled from the completed jobs
+/// already stored.
+fn db_v5_to_v6(conn: &Connection) -> Result<()> {
+    create_memo_table(conn)?;
+    let n = memo_from_jobs(conn)?;
+    if n > 0 {
+        log::info!("♻️ Recorded {} stored memoization result(s)", n);
+    }
+    Ok(())
+}
+
+/// Progress snapshots of running jobs (`record_progress`).
+fn db_v6_to_v7(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS job_progress (
+            id INTEGER PRIMARY KEY,
+            job_id TEXT NOT NULL,
+            ts INTEGER,
+            worker_id TEXT,
+            snapshot_json TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_job_progress_job ON job_progress(job_id);",
+    )?;
+    Ok(())
+}
+
+fn create_memo_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS memo (
+            fingerprint TEXT PRIMARY KEY,
+            job_id TEXT NOT NULL,
+            updated_at_ms INTEGER,
+            result_json TEXT NOT NULL
+        );",
+    )?;
+    Ok(())
+}
+
+/// The job id and result stored for `fingerprint`, the result upgraded to
+/// the current schema.
+fn memo_lookup(
+    conn: &Connection,
+    fingerprint: &str,
+) -> Result<Option<(String, CalculationResult)>> {
+    let row: Option<(String, String)> = conn
+        .prepare_cached("SELECT job_id, result_json FROM memo WHERE fingerprint = ?1")?
+        .query_row(params![fingerprint], |r| Ok((r.get(0)?, r.get(1)?)))
+        .optional()?;
+    let Some((job_id, json)) = row else {
+        return Ok(None);
+    };
+    let mut raw: serde_json::Value = serde_json::from_str(&json)?;
+    schema::upgrade_result(&mut raw)?;
+    Ok(Some((job_id, serde_json::from_value(raw)?)))
+}
+
+/// Makes `job` the answer for its fingerprint; false if it cannot be one.
+/// Only Compute jobs are looked up. Copies answered from the cache are
+/// skipped, so a row always names the job that actually ran.
+fn record_memo(conn: &Connection, job: &Job) -> Result<bool> {
+    let Some(result) = &job.result else {
+        return Ok(false);
+    };
+    let compute = job.flow_context.get("node_type").map_or(true, |t| {
+        serde_json::from_value::<NodeType>(t.clone()).is_ok_and(|t| t == NodeType::Compute)
+    });
+    if !compute || job.flow_context.contains_key("memoized_from") {
+        return Ok(false);
+    }
+    conn.prepare_cached(
+        "INSERT INTO memo (fingerprint, job_id, updated_at_ms, result_json)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(fingerprint) DO UPDATE SET
+            job_id=excluded.job_id,
+            updated_at_ms=excluded.updated_at_ms,
+            result_json=excluded.result_json",
+    )?
+    .execute(params![
+        job_fingerprint(&job.config),
+        job.id.to_string(),
+        job.updated_at.timestamp_millis(),
+        serde_json::to_string(result)?
+    ])?;
+    Ok(true)
+}
+
+/// Records every stored completed job whose fingerprint has no row yet
+/// (older DBs, imports of older snapshots). Rows that do not parse are skipped.
+fn memo_from_jobs(conn: &Connection) -> Result<usize> {
+    let rows: Vec<String> = conn
+        .prepare("SELECT full_json FROM jobs WHERE status = 'Completed' ORDER BY updated_at_ms")?
+        .query_map([], |r| r.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    let mut n = 0;
+    for json in rows {
+        let job = match schema::restore_job(&json) {
+            Ok(Restored::Current(job) | Restored::Upgraded(job)) => job,
+            Err(_) => continue,
+        };
+        let known: bool = conn
+            .prepare_cached("SELECT count(*) > 0 FROM memo WHERE fingerprint = ?1")?
+            .query_row(params![job_fingerprint(&job.config)], |r| r.get(0))?;
+        if !known && record_memo(conn, &job)? {
+            n += 1;
+        }
+    }
+    Ok(n)
+}
+
+/// Adds an `unknown` edge for every `parent_ids` entry not yet in `edges`
+/// (older DBs, imports of older snapshots).
+fn edges_from_parent_ids(conn: &Connection) -> Result<usize> {
+    let n = conn.execute(
+        "INSERT OR IGNORE INTO edges (parent, child, kind)
+         SELECT p.value, j.id, 'unknown'
+         FROM jobs j, json_each(j.full_json, '$.parent_ids') p",
+        [],
+    )?;
+    Ok(n)
+}
+
+/// FTS rowid of a job: stable across VACUUM (unlike `jobs.rowid`), so an
+/// entry can be replaced without scanning the index.
+fn search_rowid(id: &str) -> Option<i64> {
+    Uuid::parse_str(id).ok().map(|u| u.as_u64_pair().0 as i64)
+}
+
+fn index_job(conn: &Connection, id: &str, code: &str, params: &str, error_log: &str) -> Result<()> {
+    let Some(rowid) = search_rowid(id) else {
+        return Ok(());
+    };
+    conn.prepare_cached("DELETE FROM jobs_fts WHERE rowid = ?1")?
+        .execute(params![rowid])?;
+    conn.prepare_cached(
+        "INSERT INTO jobs_fts (rowid, job_id, code, params, error_log)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![rowid, id, code, params, error_log])?;
+    Ok(())
+}
+
+/// Adds every job missing from `jobs_fts` (older DBs, imports).
+fn index_unindexed(conn: &Connection) -> Result<usize> {
+    #[derive(Deserialize)]
+    struct PartialJob {
+        config: PartialConfig,
+        error_log: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct PartialConfig {
+        engine: serde_json::Value,
+        params: serde_json::Value,
+    }
+
+    let rows: Vec<(String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, full_json FROM jobs WHERE id NOT IN (SELECT job_id FROM jobs_fts)",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    let mut n = 0;
+    for (id, json) in rows {
+        let Ok(p) = serde_json::from_str::<PartialJob>(&json) else {
+            continue;
+        };
+        let code = serde_json::from_value::<Engine>(p.config.engine)
+            .map(|e| e.code())
+            .unwrap_or_else(|_| "?".into());
+        index_job(
+            conn,
+            &id,
+            &code,
+            &p.config.params.to_string(),
+            p.error_log.as_deref().unwrap_or_default(),
+        )?;
+        n += 1;
+    }
+    Ok(n)
+}
+
+/// Ids of the jobs `prune` deletes, given the statuses as a JSON array
+/// (?1) and the cutoff in ms (?2). A finished job that is still a parent
+/// of an unfinished one stays.
+const PRUNABLE_IDS: &str = "SELECT id FROM jobs
+     WHERE status IN (SELECT value FROM json_each(?1))
+       AND updated_at_ms < ?2
+       AND id NOT IN (
+           SELECT p.value FROM jobs j, json_each(j.full_json, '$.parent_ids') p
+           WHERE j.status NOT IN ('Completed', 'Failed', 'Cancelled'))";
+
+/// `PRUNABLE_IDS` parameters for `statuses` not updated for `older_than`.
+fn prune_params(older_than: Duration, statuses: &[JobStatus]) -> Result<(String, i64)> {
+    let cutoff = Utc::now().timestamp_millis() - older_than.as_millis() as i64;
+    let statuses: Vec<String> = statuses.iter().map(|s| format!("{:?}", s)).collect();
+    Ok((serde_json::to_string(&statuses)?, cutoff))
+}
+
+/// Replaces the `artifacts` rows of one job with `files`.
+fn index_artifacts(conn: &Connection, id: &str, files: &[WorkFile]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM artifacts WHERE job_id = ?1")?
+        .execute(params![id])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO artifacts (job_id, hash, kind, path, size, role)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for f in files {
+        let kind = ContentType::guess(Path::new(&f.path)).as_str();
+        let role = match f.role {
+            FileRole::Input => "input",
+            FileRole::Output => "output",
+        };
+        stmt.execute(params![id, f.sha256, kind, f.path, f.size as i64, role])?;
+    }
+    Ok(())
+}
+
+/// Lists the files of every job with none in `artifacts` (older DBs,
+/// imports). Reads the manifests straight from the JSON, so a job that
+/// no longer deserializes still gets its rows.
+fn index_unlisted_artifacts(conn: &Connection) -> Result<usize> {
+    let rows: Vec<(String, Vec<WorkFile>)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, json_extract(full_json, '$.result.provenance.files') FROM jobs
+             WHERE id NOT IN (SELECT job_id FROM artifacts)
+               AND json_array_length(full_json, '$.result.provenance.files') > 0",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (id, files) = row?;
+            if let Ok(files) = serde_json::from_str(&files) {
+                out.push((id, files));
+            }
+        }
+        out
+    };
+    let mut n = 0;
+    for (id, files) in &rows {
+        index_artifacts(conn, id, files)?;
+        n += files.len();
+    }
+    Ok(n)
+}
+
+fn artifact_from_row(row: &rusqlite::Row) -> rusqlite::Result<ArtifactRef> {
+    let role: String = row.get(5)?;
+    Ok(ArtifactRef {
+        job_id: row.get(0)?,
+        hash: row.get(1)?,
+        kind: row.get(2)?,
+        path: row.get(3)?,
+        size: row.get::<_, i64>(4)? as u64,
+        role: match role.as_str() {
+            "input" => FileRole::Input,
+            _ => FileRole::Output,
+        },
+    })
+}
+
+/// Free text as an FTS5 query: each word quoted (so `-`, `:` or `.` in it
+/// are not operators), all of them required. A trailing `*` is kept.
+fn fts_query(text: &str) -> Result<String> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|w| {
+            let (word, prefix) = match w.strip_suffix('*') {
+                Some(stem) if !stem.is_empty() => (stem, "*"),
+                _ => (w, ""),
+            };
+            format!("\"{}\"{}", word.replace('"', "\"\""), prefix)
+        })
+        .collect();
+    if words.is_empty() {
+        return Err(anyhow!("Nothing to search for"));
+    }
+    Ok(words.join(" "))
+}
+
+/// One `jobs` row as a `JobSummary`, peeking into the JSON without the structure.
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<JobSummary> {
+    // Lightweight struct to peek inside the full JSON without full deserialization
+    #[derive(Deserialize)]
+    struct PartialJob {
+        config: PartialConfig,
+        result: Option<PartialResult>,
+    }
+    #[derive(Deserialize)]
+    struct PartialConfig {
+        engine: Engine,
+    }
+    #[derive(Deserialize)]
+    struct PartialResult {
+        t_total_ms: f64,
+    }
+
+    let id: String = row.get(0)?;
+    let status: String = row.get(1)?;
+    let node_id: Option<String> = row.get(2)?;
+    let updated_at: i64 = row.get(3)?;
+    let json: String = row.get(4)?;
+
+    // Extract display code (e.g., "janus:mace_mp" or "vasp")
+    // Default to "?" if parsing fails
+    let (code, t_total) = match serde_json::from_str::<PartialJob>(&json) {
+        Ok(p) => {
+            let time = p.result.map(|r| r.t_total_ms).unwrap_or(0.0);
+            (p.config.engine.code(), time)
+        }
+        Err(_) => ("?".to_string(), 0.0),
+    };
+
+    Ok(JobSummary {
+        id,
+        status,
+        code,
+        node_id: node_id.unwrap_or_default(),
+        updated_at,
+        t_total,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Snapshots (JSONL)
+// -----------------------------------------------------------------------------
+
+/// `format` of a snapshot's header line.
+pub const SNAPSHOT_FORMAT: &str = "unifiedlab-checkpoint";
+/// Bumped when the line shapes change; `import_jsonl` refuses newer files.
+/// v2 added `edge` lines, v3 `memo` lines, v4 `progress` lines.
+pub const SNAPSHOT_VERSION: u32 = 4;
+
+/// Rows written by `export_jsonl` or read by `import_jsonl`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotCounts {
+    pub meta: usize,
+    pub workers: usize,
+    pub jobs: usize,
+    pub job_events: usize,
+    #[serde(default)]
+    pub edges: usize,
+    #[serde(default)]
+    pub memo: usize,
+    #[serde(default)]
+    pub progress: usize,
+}
+
+/// One line of a snapshot. Columns are kept as they are in the DB, so a
+/// job written by an older build is upgraded when the Coordinator restores
+/// it, not on import.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SnapshotLine {
+    Header {
+        format: String,
+        version: u32,
+    },
+    Meta {
+        key: String,
+        value: Option<String>,
+    },
+    Worker {
+        id: String,
+        last_seen_ms: Option<i64>,
+        state: serde_json::Value,
+    },
+    Job {
+        id: String,
+        status: Option<String>,
+        updated_at_ms: Option<i64>,
+        node_id: Option<String>,
+        job: serde_json::Value,
+    },
+    JobEvent {
+        job_id: String,
+        from_status: Option<String>,
+        to_status: String,
+        ts: Option<i64>,
+        worker_id: Option<String>,
+    },
+    Edge {
+        parent: String,
+        child: String,
+        kind: String,
+    },
+    Memo {
+        fingerprint: String,
+        job_id: String,
+        updated_at_ms: Option<i64>,
+        result: serde_json::Value,
+    },
+    Progress {
+        job_id: String,
+        ts: Option<i64>,
+        worker_id: Option<String>,
+        snapshot: serde_json::Value,
+    },
+}
+
+/// A JSON column as a value, so the snapshot stays readable. Text that
+/// does not parse is kept as a string and written back unchanged.
+fn json_column(text: String) -> serde_json::Value {
+    serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text))
+}
+
+fn column_text(value: serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s,
+        v => v.to_string(),
+    }
+}
+
+impl CheckpointStore {
+    /// Writes every row as one JSON line, sorted by key, so two snapshots
+    /// of the same run diff cleanly. Reads a single consistent view.
+    pub fn export_jsonl(&self, path: impl AsRef<Path>) -> Result<SnapshotCounts> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create snapshot {:?}", path))?;
+        let mut out = std::io::BufWriter::new(file);
+        let counts = self.export_to(&mut out)?;
+        out.into_inner()
+            .map_err(|e| anyhow!("Failed to write snapshot {:?}: {}", path, e))?
+            .sync_all()?;
+        Ok(counts)
+    }
+
+    fn export_to(&self, out: &mut impl Write) -> Result<SnapshotCounts> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let mut counts = SnapshotCounts::default();
+        let mut emit = |line: SnapshotLine| -> Result<()> {
+            serde_json::to_writer(&mut *out, &line)?;
+            out.write_all(b"\n")?;
+            Ok(())
+        };
+
+        emit(SnapshotLine::Header {
+            format: SNAPSHOT_FORMAT.into(),
+            version: SNAPSHOT_VERSION,
+        })?;
+
+        let mut stmt = tx.prepare("SELECT key, value FROM meta ORDER BY key")?;
+        let mut rows = stmt.query([])?;
+        while let Some(r) = rows.next()? {
+            emit(SnapshotLine::Meta {
+                key: r.get(0)?,
+                value: r.get(1)?,
+            })?;
+            counts.meta += 1;
+        }
+
+        let mut stmt =
+            tx.prepare("SELECT id, last_seen_ms, state_json FROM workers ORDER BY id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(r) = rows.next()? {
+            emit(SnapshotLine::Worker {
+                id: r.get(0)?,
+                last_seen_ms: r.get(1)?,
+                state: json_column(r.get::<_, Option<String>>(2)?.unwrap_or_default()),
+            })?;
+            counts.workers += 1;
+        }
+
+        let mut stmt = tx.prepare(
+            "SELECT id, status, updated_at_ms, node_id, full_json FROM jobs ORDER BY id",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(r) = rows.next()? {
+            emit(SnapshotLine::Job {
+                id: r.get(0)?,
+                status: r.get(1)?,
+                updated_at_ms: r.get(2)?,
+                node_id: r.get(3)?,
+                job: json_column(r.get::<_, Option<String>>(4)?.unwrap_or_default()),
+            })?;
+            counts.jobs += 1;
+        }
+
+        let mut stmt = tx.prepare(
+            "SELECT job_id, from_status, to_status, ts, worker_id FROM job_events
+             ORDER BY job_id, id",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(r) = rows.next()? {
+            emit(SnapshotLine::JobEvent {
+                job_id: r.get(0)?,
+                from_status: r.get(1)?,
+                to_status: r.get(2)?,
+                ts: r.get(3)?,
+                worker_id: r.get(4)?,
+            })?;
+            counts.job_events += 1;
+        }
+
+        let mut stmt =
+            tx.prepare("SELECT parent, child, kind FROM edges ORDER BY child, parent")?;
+        let mut rows = stmt.query([])?;
+        while let Some(r) = rows.next()? {
+            emit(SnapshotLine::Edge {
+                parent: r.get(0)?,
+                child: r.get(1)?,
+                kind: r.get(2)?,
+            })?;
+            counts.edges += 1;
+        }
+
+        let mut stmt = tx.prepare(
+            "SELECT fingerprint, job_id, updated_at_ms, result_json FROM memo
+             ORDER BY fingerprint",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(r) = rows.next()? {
+            emit(SnapshotLine::Memo {
+                fingerprint: r.get(0)?,
+                job_id: r.get(1)?,
+                updated_at_ms: r.get(2)?,
+                result: json_column(r.get(3)?),
+            })?;
+            counts.memo += 1;
+        }
+
+        let mut stmt = tx.prepare(
+            "SELECT job_id, ts, worker_id, snapshot_json FROM job_progress
+             ORDER BY job_id, id",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(r) = rows.next()? {
+            emit(SnapshotLine::Progress {
+                job_id: r.get(0)?,
+                ts: r.get(1)?,
+                worker_id: r.get(2)?,
+                snapshot: json_column(r.get(3)?),
+            })?;
+            counts.progress += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Loads a snapshot written by `export_jsonl` in one transaction.
+    /// Refuses a DB that already holds jobs, so two runs are never mixed.
+    pub fn import_jsonl(&self, path: impl AsRef<Path>) -> Result<SnapshotCounts> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open snapshot {:?}", path))?;
+        let reader = std::io::BufReader::new(file);
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let existing: i64 = tx.query_row("SELECT count(*) FROM jobs", [], |r| r.get(0))?;
+        if existing > 0 {
+            return Err(anyhow!(
+                "{:?} already holds {} job(s); import into an empty DB",
+                self.path,
+                existing
+            ));
+        }
+
+        let mut counts = SnapshotCounts::default();
+        let mut header = false;
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parsed: SnapshotLine = serde_json::from_str(&line)
+                .with_context(|| format!("{:?} line {}", path, n + 1))?;
+            match parsed {
+                SnapshotLine::Header { format, version } => {
+                    if format != SNAPSHOT_FORMAT {
+                        return Err(anyhow!("{:?} is not a checkpoint snapshot", path));
+                    }
+                    if version > SNAPSHOT_VERSION {
+                        return Err(anyhow!(
+                            "Snapshot v{} was written by a newer UnifiedLAB (this build reads up to v{})",
+                            version,
+                            SNAPSHOT_VERSION
+                        ));
+                    }
+                    header = true;
+                }
+                _ if !header => {
+                    return Err(anyhow!("{:?} does not start with a snapshot header", path));
+                }
+                // The target DB's own layout decides its schema version
+                SnapshotLine::Meta { key, .. } if key == META_SCHEMA_VERSION => counts.meta += 1,
+                SnapshotLine::Meta { key, value } => {
+                    tx.execute(
+                        "INSERT INTO meta (key, value) VALUES (?1, ?2)
+                         ON CONFLICT(key) DO UPDATE SET value=excluded.value",
+                        params![key, value],
+                    )?;
+                    counts.meta += 1;
+                }
+                SnapshotLine::Worker {
+                    id,
+                    last_seen_ms,
+                    state,
+                } => {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO workers (id, last_seen_ms, state_json)
+                         VALUES (?1, ?2, ?3)",
+                        params![id, last_seen_ms, column_text(state)],
+                    )?;
+                    counts.workers += 1;
+                }
+                SnapshotLine::Job {
+                    id,
+                    status,
+                    updated_at_ms,
+                    node_id,
+                    job,
+                } => {
+                    tx.execute(
+                        "INSERT INTO jobs (id, status, updated_at_ms, node_id, full_json)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![id, status, updated_at_ms, node_id, column_text(job)],
+                    )?;
+                    counts.jobs += 1;
+                }
+                SnapshotLine::JobEvent {
+                    job_id,
+                    from_status,
+                    to_status,
+                    ts,
+                    worker_id,
+                } => {
+                    tx.execute(
+                        "INSERT INTO job_events (job_id, from_status, to_status, ts, worker_id)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![job_id, from_status, to_status, ts, worker_id],
+                    )?;
+                    counts.job_events += 1;
+                }
+                SnapshotLine::Edge {
+                    parent,
+                    child,
+                    kind,
+                } => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO edges (parent, child, kind) VALUES (?1, ?2, ?3)",
+                        params![parent, child, kind],
+                    )?;
+                    counts.edges += 1;
+                }
+                SnapshotLine::Memo {
+                    fingerprint,
+                    job_id,
+                    updated_at_ms,
+                    result,
+                } => {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO memo (fingerprint, job_id, updated_at_ms, result_json)
+                         VALUES (?1, ?2, ?3, ?4)",
+                        params![fingerprint, job_id, updated_at_ms, column_text(result)],
+                    )?;
+                    counts.memo += 1;
+                }
+                SnapshotLine::Progress {
+                    job_id,
+                    ts,
+                    worker_id,
+                    snapshot,
+                } => {
+                    tx.execute(
+                        "INSERT INTO job_progress (job_id, ts, worker_id, snapshot_json)
+                         VALUES (?1, ?2, ?3, ?4)",
+                        params![job_id, ts, worker_id, column_text(snapshot)],
+                    )?;
+                    counts.progress += 1;
+                }
+            }
+        }
+        if !header {
+            return Err(anyhow!("{:?} is empty", path));
+        }
+        index_unindexed(&tx)?;
+        index_unlisted_artifacts(&tx)?;
+        // A v1 snapshot has no edge lines, a v2 one no memo lines
+        edges_from_parent_ids(&tx)?;
+        memo_from_jobs(&tx)?;
+        tx.commit()?;
+        Ok(counts)
+    }
+}
@@ -0,0 +1,698 @@
+// src/console.rs
+//
+// =============================================================================
+// UNIFIEDLAB: OPERATOR CONSOLE (v 0.1 )
+// =============================================================================
+//
+// The Control Room.
+//
+// A REPL attached to a running Coordinator:
+// 1. Queries (jobs, workers, memo, why, history) read the checkpoint DB (lags up to ~5s).
+// 2. Commands (cancel, pause, resume, expand-limit) travel as `control.command`
+//    messages over the normal transport; the Coordinator answers with a
+//    `control.ack` broadcast, which we wait for. drain/undrain send a
+//    `worker.drain` message and are acknowledged the same way.
+//    `pause <workflow>` holds back only the jobs of that workflow.
+// 3. Graph edits (add-edge, remove-edge, add-node) are commands too; they are
+//    also what `unifiedlab graph ...` runs, without the prompt.
+// 4. Bulk cancel (`CancelJobs`, what `unifiedlab cancel` runs) selects jobs by
+//    id, workflow, project, engine or status; a dry run lists them instead.
+//    Bulk retry (`RetryJobs`, what `unifiedlab retry` runs) selects the same
+//    way among failed and cancelled jobs and queues them again.
+
+use crate::checkpoint::{self, CheckpointStore};
+use crate::core::{Job, JobStatus};
+use crate::marketplace::{
+    job_workflow, ControlAck, ControlCommand, ControlRequest, JobFilter, WorkerDrain,
+    DEFAULT_WORKER_TIMEOUT, EV_CONTROL_ACK, FLOW_WORKFLOW, META_PAUSED, META_PAUSED_WORKFLOWS,
+    META_QUOTAS, MSG_CONTROL, MSG_WORKER_DRAIN,
+};
+use crate::quota::QuotaStatus;
+use crate::report;
+use crate::transport::Transport;
+
+use anyhow::{anyhow, Result};
+use chrono::{TimeZone, Utc};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use uuid::Uuid;
+
+/// How long to wait for the Coordinator to acknowledge a command.
+/// Inbox discovery alone can take ~2s on FileTransport.
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Rows printed by `jobs` before truncating.
+const MAX_ROWS: usize = 50;
+
+pub const COMMANDS: &[&str] = &[
+    "jobs",
+    "workers",
+    "memo",
+    "why",
+    "history",
+    "cancel",
+    "retry",
+    "pause",
+    "resume",
+    "drain",
+    "undrain",
+    "expand-limit",
+    "add-edge",
+    "remove-edge",
+    "add-node",
+    "help",
+    "quit",
+];
+
+const STATUSES: &[&str] = &[
+    "pending",
+    "blocked",
+    "queued",
+    "running",
+    "completed",
+    "failed",
+    "cancelled",
+];
+
+const HELP: &str = "\
+  jobs [status]        list jobs (optionally filtered by status)
+  workers              list workers from the last checkpoint, stale ones flagged
+  memo [n]             memoization hit rates and the n most duplicated jobs
+  why <id>             explain why a job is (not) running
+  history <id>         status changes of a job and time spent in each
+  cancel <id> [id...]  cancel pending/blocked/running jobs
+  retry <id> [id...]   queue failed/cancelled jobs again
+  pause | resume [wf]  stop/restart handing out work (of one workflow)
+  drain <worker>       no new work for a worker; its running jobs finish
+  undrain <worker>     let a drained worker take work again
+  expand-limit <n>     max children accepted per generator expansion
+  add-edge <p> <c>     make job <c> wait on job <p> (c must not have started)
+  remove-edge <p> <c>  drop that dependency again
+  add-node <file> [p]  add the job in a JSON file, waiting on jobs [p...]
+  quit                 leave the console";
+
+// ============================================================================
+// 1. COMMAND PARSING
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    Jobs(Option<String>),
+    Workers,
+    Memo(usize),
+    Why(String),
+    History(String),
+    Cancel(String),
+    /// Jobs (by id or prefix) narrowed by a filter; `true` only lists them.
+    CancelJobs(Vec<String>, JobFilter, bool),
+    /// Failed or cancelled jobs (by id or prefix) narrowed by a filter.
+    RetryJobs(Vec<String>, JobFilter),
+    /// All work, or that of one workflow.
+    Pause(Option<String>),
+    Resume(Option<String>),
+    Drain(String),
+    Undrain(String),
+    ExpandLimit(usize),
+    AddEdge(String, String),
+    RemoveEdge(String, String),
+    AddNode(String, Vec<String>),
+    Help,
+    Quit,
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let cmd = words.next().unwrap_or_default();
+        let rest: Vec<String> = words.map(String::from).collect();
+        let arg = rest.first().map(String::as_str);
+
+        let need = |what: &str| {
+            arg.map(String::from)
+                .ok_or_else(|| anyhow!("usage: {} {}", cmd, what))
+        };
+        let pair = || match rest.as_slice() {
+            [p, c] => Ok((p.clone(), c.clone())),
+            _ => Err(anyhow!("usage: {} <parent> <child>", cmd)),
+        };
+
+        Ok(match cmd {
+            "jobs" => Self::Jobs(arg.map(|s| s.to_lowercase())),
+            "workers" => Self::Workers,
+            "memo" => Self::Memo(match arg {
+                Some(n) => n.parse().map_err(|_| anyhow!("memo expects a number"))?,
+                None => report::DEFAULT_MEMO_TOP,
+            }),
+            "why" => Self::Why(need("<id>")?),
+            "history" => Self::History(need("<id>")?),
+            "cancel" if rest.len() > 1 => Self::CancelJobs(rest, JobFilter::default(), false),
+            "cancel" => Self::Cancel(need("<id> [id...]")?),
+            "retry" => {
+                need("<id> [id...]")?;
+                Self::RetryJobs(rest, JobFilter::default())
+            }
+            "pause" => Self::Pause(arg.map(String::from)),
+            "resume" => Self::Resume(arg.map(String::from)),
+            "drain" => Self::Drain(need("<worker>")?),
+            "undrain" => Self::Undrain(need("<worker>")?),
+            "expand-limit" => Self::ExpandLimit(
+                need("<n>")?
+                    .parse()
+                    .map_err(|_| anyhow!("expand-limit expects a number"))?,
+            ),
+            "add-edge" => {
+                let (p, c) = pair()?;
+                Self::AddEdge(p, c)
+            }
+            "remove-edge" => {
+                let (p, c) = pair()?;
+                Self::RemoveEdge(p, c)
+            }
+            "add-node" => Self::AddNode(need("<file.json> [parent...]")?, rest[1..].to_vec()),
+            "help" | "?" => Self::Help,
+            "quit" | "exit" => Self::Quit,
+            other => return Err(anyhow!("unknown command '{}' (try 'help')", other)),
+        })
+    }
+}
+
+// ============================================================================
+// 2. TAB COMPLETION
+// ============================================================================
+
+struct ConsoleHelper;
+
+impl Completer for ConsoleHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let head = &line[..pos];
+        let start = head.rfind(' ').map(|i| i + 1).unwrap_or(0);
+        let word = &head[start..];
+
+        let pool: &[&str] = if start == 0 {
+            COMMANDS
+        } else if head.starts_with("jobs ") {
+            STATUSES
+        } else {
+            &[]
+        };
+
+        let hits = pool
+            .iter()
+            .filter(|c| c.starts_with(word))
+            .map(|c| c.to_string())
+            .collect();
+        Ok((start, hits))
+    }
+}
+
+impl Hinter for ConsoleHelper {
+    type Hint = String;
+}
+impl Highlighter for ConsoleHelper {}
+impl Validator for ConsoleHelper {}
+impl Helper for ConsoleHelper {}
+
+// ============================================================================
+// 3. THE CONSOLE
+// ============================================================================
+
+pub struct Console {
+    store: CheckpointStore,
+    transport: Box<dyn Transport>,
+}
+
+impl Console {
+    /// `transport` should already be positioned at the end of the broadcast
+    /// log so only fresh acknowledgements are scanned.
+    pub fn new(store: CheckpointStore, transport: Box<dyn Transport>) -> Self {
+        Self { store, transport }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let mut rl: Editor<ConsoleHelper, DefaultHistory> = Editor::new()?;
+        rl.set_helper(Some(ConsoleHelper));
+        println!("UnifiedLab console. Type 'help' for commands, Tab to complete.");
+
+        loop {
+            let line = match rl.readline("ulab> ") {
+                Ok(l) => l,
+                Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(e.into()),
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let _ = rl.add_history_entry(line);
+
+            let cmd = match ConsoleCommand::parse(line) {
+                Ok(c) => c,
+                Err(e) => {
+                    println!("{}", e);
+                    continue;
+                }
+            };
+            if cmd == ConsoleCommand::Quit {
+                break;
+            }
+            if let Err(e) = self.execute(cmd).await {
+                println!("error: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs one command (the REPL, or `unifiedlab graph` for a single edit).
+    pub async fn execute(&mut self, cmd: ConsoleCommand) -> Result<()> {
+        match cmd {
+            ConsoleCommand::Jobs(filter) => self.print_jobs(filter.as_deref()),
+            ConsoleCommand::Workers => self.print_workers(),
+            ConsoleCommand::Memo(top) => {
+                report::print_memo(&report::memo_from_store(&self.store, top)?);
+                Ok(())
+            }
+            ConsoleCommand::Why(id) => {
+                let job = self.lookup(&id)?;
+                for reason in self.explain(&job)? {
+                    println!("  {}", reason);
+                }
+                Ok(())
+            }
+            ConsoleCommand::History(id) => {
+                let full = self.store.resolve_job_id(&id)?;
+                self.print_history(&full)
+            }
+            ConsoleCommand::Cancel(id) => {
+                let job = self.lookup(&id)?;
+                self.control(ControlCommand::Cancel { job_id: job.id })
+                    .await
+            }
+            ConsoleCommand::CancelJobs(ids, mut filter, dry_run) => {
+                for id in &ids {
+                    filter.job_ids.push(self.resolve(id)?);
+                }
+                if dry_run {
+                    return self.print_cancellable(&filter);
+                }
+                self.control(ControlCommand::CancelJobs { filter }).await
+            }
+            ConsoleCommand::RetryJobs(ids, mut filter) => {
+                for id in &ids {
+                    filter.job_ids.push(self.resolve(id)?);
+                }
+                self.control(ControlCommand::RetryJobs { filter }).await
+            }
+            ConsoleCommand::Pause(workflow) => {
+                self.control(match workflow {
+                    Some(workflow) => ControlCommand::PauseWorkflow { workflow },
+                    None => ControlCommand::Pause,
+                })
+                .await
+            }
+            ConsoleCommand::Resume(workflow) => {
+                self.control(match workflow {
+                    Some(workflow) => ControlCommand::ResumeWorkflow { workflow },
+                    None => ControlCommand::Resume,
+                })
+                .await
+            }
+            ConsoleCommand::Drain(worker_id) => self.drain(worker_id, false).await,
+            ConsoleCommand::Undrain(worker_id) => self.drain(worker_id, true).await,
+            ConsoleCommand::ExpandLimit(limit) => {
+                self.control(ControlCommand::SetExpandLimit { limit }).await
+            }
+            ConsoleCommand::AddEdge(p, c) => {
+                let (parent, child) = (self.resolve(&p)?, self.resolve(&c)?);
+                self.control(ControlCommand::AddEdge { parent, child })
+                    .await
+            }
+            ConsoleCommand::RemoveEdge(p, c) => {
+                let (parent, child) = (self.resolve(&p)?, self.resolve(&c)?);
+                self.control(ControlCommand::RemoveEdge { parent, child })
+                    .await
+            }
+            ConsoleCommand::AddNode(file, parents) => {
+                let text = std::fs::read_to_string(&file)
+                    .map_err(|e| anyhow!("cannot read {}: {}", file, e))?;
+                let job: Job = serde_json::from_str(&text)
+                    .map_err(|e| anyhow!("{} is not a job: {}", file, e))?;
+                let parents = parents
+                    .iter()
+                    .map(|p| self.resolve(p))
+                    .collect::<Result<_>>()?;
+                self.control(ControlCommand::AddNode {
+                    job: Box::new(job),
+                    parents,
+                })
+                .await
+            }
+            ConsoleCommand::Help => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            ConsoleCommand::Quit => Ok(()),
+        }
+    }
+
+    fn lookup(&self, id: &str) -> Result<Job> {
+        let full = self.store.resolve_job_id(id)?;
+        self.store.get_job_details(&full)
+    }
+
+    /// Full ids pass through untouched (the job may be newer than the last
+    /// checkpoint); short ones are expanded from the DB.
+    fn resolve(&self, id: &str) -> Result<Uuid> {
+        if let Ok(full) = Uuid::parse_str(id) {
+            return Ok(full);
+        }
+        Ok(Uuid::parse_str(&self.store.resolve_job_id(id)?)?)
+    }
+
+    // ------------------------------------------------------------------------
+    // Queries (checkpoint DB)
+    // ------------------------------------------------------------------------
+
+    fn print_jobs(&self, filter: Option<&str>) -> Result<()> {
+        let jobs: Vec<_> = self
+            .store
+            .get_jobs_summary()?
+            .into_iter()
+            .filter(|j| filter.map_or(true, |f| j.status.to_lowercase() == f))
+            .collect();
+
+        println!(
+            "{:<10} {:<10} {:<18} {:<16} {:>8}",
+            "ID", "STATUS", "ENGINE", "NODE", "TIME(s)"
+        );
+        for j in jobs.iter().take(MAX_ROWS) {
+            println!(
+                "{:<10} {:<10} {:<18} {:<16} {:>8.1}",
+                j.id.chars().take(8).collect::<String>(),
+                j.status,
+                j.code,
+                j.node_id,
+                j.t_total / 1000.0
+            );
+        }
+        if jobs.len() > MAX_ROWS {
+            println!("... {} more", jobs.len() - MAX_ROWS);
+        }
+        println!("{} job(s)", jobs.len());
+        Ok(())
+    }
+
+    /// What `CancelJobs` would cancel, as of the last checkpoint.
+    fn print_cancellable(&self, filter: &JobFilter) -> Result<()> {
+        let (jobs, _) = self.store.restore_jobs_with_report()?;
+        let mut jobs: Vec<Job> = jobs
+            .into_values()
+            .filter(|j| {
+                !matches!(
+                    j.status,
+                    JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+                ) && filter.matches(j)
+            })
+            .collect();
+        jobs.sort_by_key(|j| j.created_at);
+
+        println!(
+            "{:<10} {:<10} {:<18} {:<16}",
+            "ID", "STATUS", "ENGINE", "WORKFLOW"
+        );
+        for j in jobs.iter().take(MAX_ROWS) {
+            println!(
+                "{:<10} {:<10} {:<18} {:<16}",
+                &j.id.to_string()[..8],
+                format!("{:?}", j.status),
+                j.config.engine.name(),
+                j.flow_context
+                    .get(FLOW_WORKFLOW)
+                    .and_then(|w| w.as_str())
+                    .unwrap_or("-")
+            );
+        }
+        if jobs.len() > MAX_ROWS {
+            println!("... {} more", jobs.len() - MAX_ROWS);
+        }
+        println!("{} job(s) would be cancelled", jobs.len());
+        Ok(())
+    }
+
+    fn print_workers(&self) -> Result<()> {
+        let now_ms = Utc::now().timestamp_millis();
+        let workers = report::workers_from_store(&self.store, DEFAULT_WORKER_TIMEOUT, now_ms)?;
+        report::print_workers(&workers, now_ms);
+        Ok(())
+    }
+
+    fn print_history(&self, id: &str) -> Result<()> {
+        let history = self.store.get_job_history(id)?;
+        let Some(last) = history.last() else {
+            println!("  no status changes recorded");
+            return Ok(());
+        };
+        println!(
+            "{:<24} {:<10} {:<10} {:<16}",
+            "TIME", "FROM", "TO", "WORKER"
+        );
+        for t in &history {
+            let ts = Utc
+                .timestamp_millis_opt(t.ts_ms)
+                .single()
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default();
+            println!(
+                "{:<24} {:<10} {:<10} {:<16}",
+                ts,
+                t.from_status.as_deref().unwrap_or("-"),
+                t.to_status,
+                t.worker_id.as_deref().unwrap_or("-")
+            );
+        }
+
+        // A finished job stops the clock at its last change
+        let done = matches!(
+            last.to_status.as_str(),
+            "Completed" | "Failed" | "Cancelled"
+        );
+        let until = if done {
+            last.ts_ms
+        } else {
+            Utc::now().timestamp_millis()
+        };
+        for (status, ms) in checkpoint::time_in_status(&history, until) {
+            if !(done && status == last.to_status) {
+                println!("  {:<10} {:>10.1}s", status, ms as f64 / 1000.0);
+            }
+        }
+        Ok(())
+    }
+
+    /// Human-readable reasons for a job's current state.
+    fn explain(&self, job: &Job) -> Result<Vec<String>> {
+        let mut out = vec![format!("Job {} is {:?}", job.id, job.status)];
+
+        match job.status {
+            JobStatus::Completed => {
+                if let Some(r) = &job.result {
+                    out.push(format!("Finished in {:.1}s", r.t_total_ms / 1000.0));
+                }
+                return Ok(out);
+            }
+            JobStatus::Failed | JobStatus::Cancelled => {
+                if let Some(e) = &job.error_log {
+                    out.push(e.clone());
+                }
+                return Ok(out);
+            }
+            JobStatus::Running | JobStatus::Queued => {
+                if let Some(n) = &job.node_id {
+                    out.push(format!("Granted to {}", n));
+                }
+                return Ok(out);
+            }
+            JobStatus::Pending | JobStatus::Blocked => {}
+        }
+
+        // 1. Parents
+        let mut waiting = false;
+        for pid in &job.parent_ids {
+            let status = self
+                .store
+                .get_job_details(&pid.to_string())
+                .map(|p| p.status)
+                .ok();
+            match status {
+                Some(JobStatus::Completed) => {}
+                Some(s @ (JobStatus::Failed | JobStatus::Cancelled)) => {
+                    waiting = true;
+                    out.push(format!(
+                        "Parent {} is {:?}; this job will not be released",
+                        pid, s
+                    ))
+                }
+                Some(s) => {
+                    waiting = true;
+                    let gate = match job.conditions.get(pid) {
+                        Some(pred) => format!("; runs only if {}", pred),
+                        None => String::new(),
+                    };
+                    out.push(format!("Waiting on parent {} ({:?}){}", pid, s, gate))
+                }
+                None => {
+                    waiting = true;
+                    out.push(format!("Parent {} is not in the checkpoint yet", pid))
+                }
+            }
+        }
+        if waiting {
+            return Ok(out);
+        }
+
+        // 2. Coordinator state
+        if self.store.get_meta(META_PAUSED)?.as_deref() == Some("true") {
+            out.push("Scheduling is paused (use 'resume')".into());
+        }
+        if let Some(workflow) = job_workflow(job) {
+            let paused: Vec<String> = self
+                .store
+                .get_meta(META_PAUSED_WORKFLOWS)?
+                .and_then(|v| serde_json::from_str(&v).ok())
+                .unwrap_or_default();
+            if paused.iter().any(|w| w == workflow) {
+                out.push(format!(
+                    "Workflow '{}' is paused (use 'resume {}')",
+                    workflow, workflow
+                ));
+            }
+        }
+
+        // 3. Capacity, as of the last checkpoint (so also right after a restart)
+        let req = &job.resources;
+        let workers = self.store.get_active_workers()?;
+        let eligible: Vec<_> = workers.iter().filter(|w| w.accepts(req)).collect();
+        if workers.is_empty() {
+            out.push("No workers have reported in yet".into());
+        } else if eligible.is_empty() {
+            let mut seen: Vec<&str> = workers
+                .iter()
+                .flat_map(|w| w.tags.iter().map(String::as_str))
+                .collect();
+            seen.sort();
+            seen.dedup();
+            let avoids = if req.avoid_tags.is_empty() {
+                String::new()
+            } else {
+                format!(" and none of {:?}", req.avoid_tags)
+            };
+            out.push(format!(
+                "Needs tags {:?}{}; no worker matches (workers carry {:?})",
+                req.required_tags, avoids, seen
+            ));
+        } else {
+            if !eligible.iter().any(|w| w.cores >= req.cores) {
+                out.push(format!(
+                    "Needs {} core(s); no eligible worker currently reports that many free",
+                    req.cores
+                ));
+            }
+            if req.gpus > 0 && !eligible.iter().any(|w| w.gpus >= req.gpus) {
+                out.push(format!(
+                    "Needs {} GPU(s); no eligible worker currently reports that many free",
+                    req.gpus
+                ));
+            }
+        }
+
+        // 4. Quotas, as of the last Coordinator heartbeat
+        let quotas: Vec<QuotaStatus> = self
+            .store
+            .get_meta(META_QUOTAS)?
+            .and_then(|q| serde_json::from_str(&q).ok())
+            .unwrap_or_default();
+        for q in quotas
+            .iter()
+            .filter(|q| q.covers(job) && !q.used.admits(job, &q.limit))
+        {
+            out.push(format!(
+                "Quota {} is used up ({} core(s), {} GPU(s), {} job(s) in flight)",
+                q.bucket, q.used.cores, q.used.gpus, q.used.jobs
+            ));
+        }
+
+        if job.opportunistic {
+            out.push("Opportunistic: only runs on a worker that has been idle for a while".into());
+        }
+
+        if out.len() == 1 {
+            out.push("Eligible; waiting for the next work request from a worker".into());
+        }
+        Ok(out)
+    }
+
+    // ------------------------------------------------------------------------
+    // Commands (transport)
+    // ------------------------------------------------------------------------
+
+    async fn control(&mut self, command: ControlCommand) -> Result<()> {
+        let req = ControlRequest {
+            request_id: Uuid::new_v4(),
+            command,
+        };
+        self.transport
+            .send_to_coordinator(MSG_CONTROL, serde_json::to_value(&req)?)
+            .await?;
+        self.await_ack(req.request_id).await
+    }
+
+    async fn drain(&mut self, worker_id: String, resume: bool) -> Result<()> {
+        let request_id = Uuid::new_v4();
+        let msg = WorkerDrain {
+            worker_id,
+            resume,
+            request_id: Some(request_id),
+        };
+        self.transport
+            .send_to_coordinator(MSG_WORKER_DRAIN, serde_json::to_value(&msg)?)
+            .await?;
+        self.await_ack(request_id).await
+    }
+
+    async fn await_ack(&mut self, request_id: Uuid) -> Result<()> {
+        let deadline = Instant::now() + ACK_TIMEOUT;
+        while Instant::now() < deadline {
+            for env in self.transport.recv_broadcasts().await? {
+                if env.record.kind != EV_CONTROL_ACK {
+                    continue;
+                }
+                if let Ok(ack) = serde_json::from_value::<ControlAck>(env.record.payload) {
+                    if ack.request_id == request_id {
+                        let tag = if ack.ok { "ok" } else { "rejected" };
+                        println!("{}: {}", tag, ack.message);
+                        return Ok(());
+                    }
+                }
+            }
+            sleep(Duration::from_millis(200)).await;
+        }
+
+        Err(anyhow!(
+            "sent, but no acknowledgement within {}s (is the coordinator running?)",
+            ACK_TIMEOUT.as_secs()
+        ))
+    }
+}
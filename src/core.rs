@@ -16,10 +16,34 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 use uuid::Uuid;
 
+// ============================================================================
+// 0. SCHEMA VERSIONS
+// ============================================================================
+
+// Bump when a stored layout changes, and add the upgrade step in `schema.rs`.
+pub const JOB_SCHEMA_VERSION: u32 = 1;
+pub const STRUCTURE_SCHEMA_VERSION: u32 = 1;
+pub const RESULT_SCHEMA_VERSION: u32 = 1;
+
+fn job_schema() -> u32 {
+    JOB_SCHEMA_VERSION
+}
+
+fn structure_schema() -> u32 {
+    STRUCTURE_SCHEMA_VERSION
+}
+
+fn result_schema() -> u32 {
+    RESULT_SCHEMA_VERSION
+}
+
 // ============================================================================
 // 1. TYPE-SAFE UNITS (The "Newtype" Pattern)
 // ============================================================================
@@ -86,6 +110,22 @@ pub struct Structure {
 
     #[serde(default)]
     pub metadata: HashMap<String, Value>,
+
+    /// Set when the atoms were offloaded to the artifact store for transfer.
+    /// While present, `atoms` is empty and the structure must be hydrated
+    /// (see `wire::StructureCodec`) before use.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<StructureBlob>,
+
+    #[serde(default = "structure_schema")]
+    pub schema_version: u32,
+}
+
+/// Reference to a binary-encoded Structure stored in the CAS.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StructureBlob {
+    pub hash: String,
+    pub n_atoms: usize,
 }
 
 impl Structure {
@@ -96,9 +136,16 @@ impl Structure {
             lattice,
             source,
             metadata: HashMap::new(),
+            blob: None,
+            schema_version: STRUCTURE_SCHEMA_VERSION,
         }
     }
 
+    /// True if the atoms live in the artifact store rather than inline.
+    pub fn is_offloaded(&self) -> bool {
+        self.blob.is_some()
+    }
+
     /// Approximate mass calculation for density checks.
     pub fn mass(&self) -> f64 {
         // Placeholder: Real implementation would use a lookup table.
@@ -154,6 +201,41 @@ pub enum Engine {
         script_path: String,
         strategy: String, // "autoemulate", "bayesian_opt"
     },
+
+    /// Several engines run back to back in one work dir ("relax -> static -> DOS").
+    /// Each step starts from the previous step's final structure.
+    /// Runs via the Composite Driver; the first failing step aborts the rest.
+    #[serde(rename = "pipeline")]
+    Pipeline { steps: Vec<Engine> },
+}
+
+impl Engine {
+    /// Short engine name as used on the wire ("janus", "vasp", ...).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Engine::Janus { .. } => "janus",
+            Engine::Gulp { .. } => "gulp",
+            Engine::Vasp { .. } => "vasp",
+            Engine::Cp2k { .. } => "cp2k",
+            Engine::Agent { .. } => "agent",
+            Engine::Pipeline { .. } => "pipeline",
+        }
+    }
+
+    /// Display code with the distinguishing detail ("janus:mace_mp", "vasp:4p").
+    pub fn code(&self) -> String {
+        match self {
+            Engine::Janus { arch, .. } => format!("janus:{}", arch),
+            Engine::Gulp { .. } => "gulp".to_string(),
+            Engine::Vasp { mpi_ranks, .. } => format!("vasp:{}p", mpi_ranks),
+            Engine::Cp2k { mpi_ranks, .. } => format!("cp2k:{}p", mpi_ranks),
+            Engine::Agent { strategy, .. } => format!("agent:{}", strategy),
+            Engine::Pipeline { steps } => {
+                let codes: Vec<String> = steps.iter().map(|e| e.code()).collect();
+                format!("pipeline:{}", codes.join("+"))
+            }
+        }
+    }
 }
 
 impl Default for Engine {
@@ -179,6 +261,81 @@ pub struct JobConfig {
     /// GULP -> Keywords.
     /// Janus -> Inference settings.
     pub params: Value,
+
+    /// Run a failed job again instead of failing its branch.
+    /// Not part of the memoization fingerprint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+
+    /// Where the engine's programs run. None runs them straight on the host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
+
+    /// Env vars the engine's programs get from secrets of the Guardian that
+    /// runs the job, by name. Only where to find each value is stored; see
+    /// `secrets.rs`. Not part of the memoization fingerprint.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub secrets: BTreeMap<String, SecretRef>,
+}
+
+/// Where a job's secret is read from, on the Guardian that runs it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretRef {
+    /// An env var of the Guardian's process.
+    Env(String),
+    /// An entry of the OS keyring, under the service `unifiedlab`.
+    Keyring(String),
+}
+
+/// A compute environment the drivers start a job's programs in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Environment {
+    /// `uv run --project <project>`.
+    Uv { project: String },
+    /// `docker run` in this image, with the work dir mounted.
+    Docker { image: String },
+    /// `apptainer exec` in this image (a .sif path or e.g. `docker://...`).
+    Apptainer { image: String },
+    /// `module load` these first, in a login shell.
+    Modules { modules: Vec<String> },
+}
+
+/// When and how often the Coordinator re-queues a Failed job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Runs in total, the first one included.
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubles for each one after.
+    #[serde(default)]
+    pub backoff_s: f64,
+    /// Case-insensitive substrings of the error worth a retry (e.g. "MPI_ABORT").
+    /// Empty retries any failure.
+    #[serde(default)]
+    pub retry_on: Vec<String>,
+}
+
+impl RetryPolicy {
+    /// Backoff before the next run, given how many runs have failed so far
+    /// (this one included). None once the attempts are used up or the error
+    /// is not a retryable one.
+    pub fn retry_delay(&self, failures: u32, error: &str) -> Option<Duration> {
+        if failures >= self.max_attempts {
+            return None;
+        }
+        let error = error.to_lowercase();
+        if !self.retry_on.is_empty()
+            && !self
+                .retry_on
+                .iter()
+                .any(|p| error.contains(&p.to_lowercase()))
+        {
+            return None;
+        }
+        let factor = 2f64.powi(failures.saturating_sub(1).min(16) as i32);
+        Some(Duration::from_secs_f64((self.backoff_s * factor).max(0.0)))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -186,9 +343,29 @@ pub struct ResourceReq {
     pub nodes: usize,
     pub cores: usize,
     pub gpus: usize,
+    /// Memory the job needs on its worker, in MB; 0: no requirement.
+    #[serde(default)]
+    pub memory_mb: u64,
     pub time_limit_min: usize,
     #[serde(default)]
     pub required_tags: Vec<String>,
+    /// Soft: a worker with any of these is picked over one without.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prefer_tags: Vec<String>,
+    /// Hard: never run on a worker carrying any of these.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub avoid_tags: Vec<String>,
+    /// Run on the host where this job ran (e.g. to read its scratch files).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_node_as: Option<Uuid>,
+}
+
+impl ResourceReq {
+    /// True if a worker carrying the tags `has` reports may run this: all of
+    /// `required_tags` and none of `avoid_tags`.
+    pub fn tags_allow(&self, has: impl Fn(&str) -> bool) -> bool {
+        self.required_tags.iter().all(|t| has(t)) && !self.avoid_tags.iter().any(|t| has(t))
+    }
 }
 
 impl Default for ResourceReq {
@@ -197,8 +374,12 @@ impl Default for ResourceReq {
             nodes: 1,
             cores: 1,
             gpus: 0,
+            memory_mb: 0,
             time_limit_min: 60,
             required_tags: vec![],
+            prefer_tags: vec![],
+            avoid_tags: vec![],
+            same_node_as: None,
         }
     }
 }
@@ -215,6 +396,28 @@ pub struct Provenance {
     pub binary_hash: Option<String>, // SHA256 of executable or model weights
     pub exit_code: i32,
     pub sandbox_info: String, // e.g., "Rank 0, Cores 0-7, GPU 0"
+
+    // Files the job read and wrote in its work dir, each stored in the CAS
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<WorkFile>,
+}
+
+/// One file of a job's work dir; `unifiedlab repro` rebuilds the dir from these.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkFile {
+    pub path: String, // Relative to the work dir, '/'-separated
+    pub sha256: String,
+    pub size: u64,
+    pub role: FileRole,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileRole {
+    /// Written before the engine ran (by the adapter's write phase).
+    Input,
+    /// Created or changed by the run.
+    Output,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -235,6 +438,55 @@ pub struct CalculationResult {
 
     // Active Learning Specifics
     pub next_generation: Option<Vec<Value>>,
+
+    // Measured consumption (None if the driver could not sample it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsage>,
+
+    // Per-step record of a pipeline job (empty for single-engine jobs)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<StepRecord>,
+
+    #[serde(default = "result_schema")]
+    pub schema_version: u32,
+}
+
+/// One finished step of an `Engine::Pipeline` job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub engine: String, // Engine::code() of the step
+    pub energy: Option<ElectronVolts>,
+    pub t_total_ms: f64,
+    pub provenance: Provenance,
+}
+
+/// What a job actually consumed, sampled while it ran.
+/// Compared against its `ResourceReq` by `unifiedlab report efficiency`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub cpu_seconds: f64,
+    pub wall_seconds: f64,
+    pub peak_rss_mb: f64,
+    pub samples: u32,
+}
+
+impl ResourceUsage {
+    /// Average number of cores kept busy.
+    pub fn avg_cores(&self) -> f64 {
+        if self.wall_seconds > 0.0 {
+            self.cpu_seconds / self.wall_seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Folds in a later run of the same job (pipeline steps run one after another).
+    pub fn absorb(&mut self, next: &ResourceUsage) {
+        self.cpu_seconds += next.cpu_seconds;
+        self.wall_seconds += next.wall_seconds;
+        self.peak_rss_mb = self.peak_rss_mb.max(next.peak_rss_mb);
+        self.samples += next.samples;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -288,6 +540,36 @@ pub struct Job {
     // Workflow Metadata (DAG logic)
     #[serde(default)]
     pub flow_context: HashMap<String, Value>,
+
+    // Observability: correlates Rust logs with adapter/daemon logs.
+    // Empty for jobs stored before trace ids were recorded.
+    #[serde(default)]
+    pub trace_id: String,
+
+    // Scheduling class: runs only on idle workers, preempted by normal work
+    #[serde(default)]
+    pub opportunistic: bool,
+
+    // Failed runs re-queued so far under `JobConfig::retry`
+    #[serde(default)]
+    pub attempts: u32,
+
+    // Parent id -> what its result must satisfy for this job to run
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub conditions: BTreeMap<Uuid, ResultPredicate>,
+
+    // Parent id -> param -> field of its result, copied into `config.params`
+    // when this job is dispatched
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dataflow: BTreeMap<Uuid, BTreeMap<String, String>>,
+
+    #[serde(default = "job_schema")]
+    pub schema_version: u32,
+}
+
+/// 64-bit hex trace identifier (short enough to grep, unique enough per campaign).
+pub fn new_trace_id() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_string()
 }
 
 impl Job {
@@ -305,6 +587,135 @@ impl Job {
             parent_ids: Vec::new(),
             node_id: None,
             flow_context: HashMap::new(),
+            trace_id: new_trace_id(),
+            opportunistic: false,
+            attempts: 0,
+            conditions: BTreeMap::new(),
+            dataflow: BTreeMap::new(),
+            schema_version: JOB_SCHEMA_VERSION,
+        }
+    }
+}
+
+/// A test on a parent's result, written `<field> <op> <number>`, e.g.
+/// `energy < -5.0`. The field is a dotted path into the result's JSON; a
+/// leading `parent.` is allowed. A missing or non-numeric field fails.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ResultPredicate {
+    pub field: String,
+    pub op: CmpOp,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl CmpOp {
+    const ALL: [(&'static str, CmpOp); 6] = [
+        ("<=", CmpOp::Le),
+        (">=", CmpOp::Ge),
+        ("==", CmpOp::Eq),
+        ("!=", CmpOp::Ne),
+        ("<", CmpOp::Lt),
+        (">", CmpOp::Gt),
+    ];
+
+    fn symbol(self) -> &'static str {
+        Self::ALL.iter().find(|(_, op)| *op == self).unwrap().0
+    }
+}
+
+/// The value at a dotted path into a result's JSON, such as `energy` or
+/// `usage.max_rss_mb`; numbers index arrays (`steps.0.energy`). A leading
+/// `parent.` is allowed.
+pub fn result_field(result: &CalculationResult, path: &str) -> Option<Value> {
+    json_field(&serde_json::to_value(result).ok()?, path)
+}
+
+/// [`result_field`] on a result already turned into JSON.
+pub fn json_field(result: &Value, path: &str) -> Option<Value> {
+    let path = path.strip_prefix("parent.").unwrap_or(path);
+    result
+        .pointer(&format!("/{}", path.replace('.', "/")))
+        .cloned()
+}
+
+impl ResultPredicate {
+    pub fn holds(&self, result: Option<&CalculationResult>) -> bool {
+        result
+            .and_then(|r| serde_json::to_value(r).ok())
+            .is_some_and(|v| self.holds_for(&v))
+    }
+
+    /// [`Self::holds`] on a result already turned into JSON.
+    pub fn holds_for(&self, result: &Value) -> bool {
+        let Some(found) = json_field(result, &self.field).and_then(|v| v.as_f64()) else {
+            return false;
+        };
+        match self.op {
+            CmpOp::Lt => found < self.value,
+            CmpOp::Le => found <= self.value,
+            CmpOp::Gt => found > self.value,
+            CmpOp::Ge => found >= self.value,
+            CmpOp::Eq => found == self.value,
+            CmpOp::Ne => found != self.value,
         }
     }
 }
+
+impl FromStr for ResultPredicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let bad = || {
+            format!(
+                "expected '<field> <op> <number>', e.g. 'energy < -5.0', got '{}'",
+                s
+            )
+        };
+        let (at, symbol, op) = CmpOp::ALL
+            .iter()
+            .filter_map(|(symbol, op)| s.find(symbol).map(|at| (at, *symbol, *op)))
+            .min_by_key(|(at, symbol, _)| (*at, std::cmp::Reverse(symbol.len())))
+            .ok_or_else(bad)?;
+        let field = s[..at].trim();
+        let field = field.strip_prefix("parent.").unwrap_or(field);
+        let value: f64 = s[at + symbol.len()..].trim().parse().map_err(|_| bad())?;
+        if field.is_empty() || !value.is_finite() {
+            return Err(bad());
+        }
+        Ok(Self {
+            field: field.to_string(),
+            op,
+            value,
+        })
+    }
+}
+
+impl fmt::Display for ResultPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.field, self.op.symbol(), self.value)
+    }
+}
+
+impl TryFrom<String> for ResultPredicate {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse()
+    }
+}
+
+impl From<ResultPredicate> for String {
+    fn from(p: ResultPredicate) -> String {
+        p.to_string()
+    }
+}
@@ -0,0 +1,163 @@
+// src/deadline.rs
+//
+// =============================================================================
+// UNIFIEDLAB: DEADLINES (v 0.1 )
+// =============================================================================
+//
+// The Clock.
+//
+// Soft deadlines on workflows, turned into a dispatch order:
+// 1. A job carries the deadline of its workflow in
+//    `Job::flow_context["deadline"]` (RFC 3339, from `deploy --deadline`);
+//    expansions inherit it from the job that spawned them.
+// 2. The Coordinator estimates the work left below each such job from the
+//    `time_limit_min` of its longest chain of unfinished descendants, and
+//    stamps the latest time it can start (`flow_context["latest_start"]`).
+// 3. Within a priority, the ready queue hands out the job with the least
+//    slack first; jobs without a deadline come after those with one.
+// 4. Deadlines that can no longer be met are logged and written to the
+//    checkpoint (`META_DEADLINES`) for the TUI.
+
+use crate::core::{Job, JobStatus};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use uuid::Uuid;
+
+/// `Job::flow_context` key holding the job's deadline (RFC 3339).
+pub const FLOW_DEADLINE: &str = "deadline";
+
+/// `Job::flow_context` key of the latest start (ms since the epoch) that
+/// still meets the deadline, stamped by the Coordinator.
+pub const FLOW_LATEST_START: &str = "latest_start";
+
+/// The deadline `job` must be done by, if it has one.
+pub fn job_deadline(job: &Job) -> Option<DateTime<Utc>> {
+    job.flow_context
+        .get(FLOW_DEADLINE)
+        .and_then(|v| v.as_str())
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// The latest start stamped on `job`, in ms since the epoch.
+pub fn latest_start(job: &Job) -> Option<i64> {
+    job.flow_context.get(FLOW_LATEST_START)?.as_i64()
+}
+
+/// A deadline as given on the command line: RFC 3339, or a delay from
+/// `now` such as `90m`, `36h` or `2d`.
+pub fn parse_deadline(spec: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let spec = spec.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(spec) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let unit = spec.chars().last().unwrap_or('?');
+    let n: i64 = spec
+        .strip_suffix(unit)
+        .unwrap_or(spec)
+        .parse()
+        .map_err(|_| format!("'{}' is neither RFC 3339 nor a delay like 36h", spec))?;
+    let delay = match unit {
+        'm' => Duration::minutes(n),
+        'h' => Duration::hours(n),
+        'd' => Duration::days(n),
+        _ => return Err(format!("Unknown unit in '{}' (use m, h or d)", spec)),
+    };
+    Ok(now + delay)
+}
+
+fn unfinished(job: &Job) -> bool {
+    !matches!(
+        job.status,
+        JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+    )
+}
+
+/// Minutes of work from each unfinished job to the end of its longest chain
+/// of unfinished descendants, its own `time_limit_min` included. Jobs on a
+/// cycle get no estimate.
+pub fn critical_paths<'a>(jobs: impl IntoIterator<Item = &'a Job>) -> HashMap<Uuid, u64> {
+    let open: HashMap<Uuid, &Job> = jobs
+        .into_iter()
+        .filter(|j| unfinished(j))
+        .map(|j| (j.id, j))
+        .collect();
+    let mut children_left: HashMap<Uuid, usize> = open.keys().map(|id| (*id, 0)).collect();
+    for job in open.values() {
+        for pid in job.parent_ids.iter().filter(|p| open.contains_key(p)) {
+            *children_left.entry(*pid).or_default() += 1;
+        }
+    }
+
+    // Leaves first, each parent once all its children are known
+    let mut ready: VecDeque<Uuid> = children_left
+        .iter()
+        .filter(|(_, n)| **n == 0)
+        .map(|(id, _)| *id)
+        .collect();
+    let mut below: HashMap<Uuid, u64> = HashMap::new();
+    let mut paths = HashMap::new();
+    while let Some(id) = ready.pop_front() {
+        let job = open[&id];
+        let path = job.resources.time_limit_min as u64 + below.get(&id).copied().unwrap_or(0);
+        paths.insert(id, path);
+        for pid in job.parent_ids.iter().filter(|p| open.contains_key(p)) {
+            let longest = below.entry(*pid).or_default();
+            *longest = (*longest).max(path);
+            if let Some(n) = children_left.get_mut(pid) {
+                *n -= 1;
+                if *n == 0 {
+                    ready.push_back(*pid);
+                }
+            }
+        }
+    }
+    paths
+}
+
+/// How one deadline is faring, as written to `META_DEADLINES`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadlineStatus {
+    pub deadline: DateTime<Utc>,
+    /// Unfinished jobs due by then.
+    pub jobs: usize,
+    /// Least slack among them, in seconds; negative once it cannot be met.
+    pub slack_s: i64,
+}
+
+impl DeadlineStatus {
+    pub fn is_late(&self) -> bool {
+        self.slack_s < 0
+    }
+}
+
+/// One entry per deadline that still has unfinished jobs, soonest first.
+/// A job not started yet has until its latest start; a running one until
+/// the deadline itself.
+pub fn deadline_status<'a>(
+    jobs: impl IntoIterator<Item = &'a Job>,
+    now: DateTime<Utc>,
+) -> Vec<DeadlineStatus> {
+    let now_ms = now.timestamp_millis();
+    let mut by_deadline: BTreeMap<DateTime<Utc>, DeadlineStatus> = BTreeMap::new();
+    for job in jobs.into_iter().filter(|j| unfinished(j)) {
+        let Some(deadline) = job_deadline(job) else {
+            continue;
+        };
+        let start_by = match job.status {
+            JobStatus::Running => None,
+            _ => latest_start(job),
+        }
+        .unwrap_or(deadline.timestamp_millis());
+        let slack_s = (start_by - now_ms).div_euclid(1000);
+        let entry = by_deadline.entry(deadline).or_insert(DeadlineStatus {
+            deadline,
+            jobs: 0,
+            slack_s,
+        });
+        entry.jobs += 1;
+        entry.slack_s = entry.slack_s.min(slack_s);
+    }
+    by_deadline.into_values().collect()
+}
@@ -10,16 +10,28 @@
 // 1. Define the `CodeDriver` trait (The Contract).
 // 2. Dispatch `Engine` enums to concrete implementations.
 // 3. Provide standardized utilities for process isolation (Sandboxing).
+// 4. Template per-atom charges and moments into engine inputs (`electronic`).
+// 5. Carry intermediate results of a running job out (`ProgressSink`).
+// 6. Start programs inside a job's compute environment (uv, Docker,
+//    Apptainer, modules).
+// 7. Hand programs the job's secrets, resolved on this host (`secrets.rs`).
+// 8. Keep the tail of a program's stdout/stderr in the work dir, stored with
+//    the job's outputs for `unifiedlab logs`.
 
 use crate::core::{CalculationResult, Engine, Job};
+use crate::logs::TraceContext;
 use crate::resources::Sandbox;
 use anyhow::Result;
 use async_trait::async_trait;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 // Declare the concrete implementations
+pub mod electronic;
 pub mod external;
 pub mod janus;
+pub mod pipeline;
 
 // ============================================================================
 // 1. THE DRIVER TRAIT (The Contract)
@@ -33,6 +45,8 @@ pub trait CodeDriver: Send + Sync {
     /// - `job`: Contains the Structure (atoms) and JobConfig (params).
     /// - `sandbox`: Contains the assigned Cores/GPUs (Isolation).
     /// - `work_dir`: Where to write input/output files (Isolation).
+    /// - `trace`: Correlation IDs to hand to child processes (Observability).
+    /// - `progress`: Where to send snapshots before the final result (optional).
     ///
     /// Returns:
     /// - `CalculationResult`: The standardized scientific output + provenance.
@@ -41,9 +55,41 @@ pub trait CodeDriver: Send + Sync {
         job: &Job,
         sandbox: &Sandbox,
         work_dir: &Path,
+        trace: &TraceContext,
+        progress: &ProgressSink,
     ) -> Result<CalculationResult>;
 }
 
+/// File in the work dir a running program may append snapshots to, one
+/// `CalculationResult` JSON per line (missing provenance is filled in).
+pub const PROGRESS_FILE: &str = "ulab_progress.jsonl";
+
+/// How often `PROGRESS_FILE` is checked while the program runs.
+pub const PROGRESS_POLL_EVERY: Duration = Duration::from_secs(5);
+
+/// Receives a running job's intermediate results (e.g. the energy every N
+/// optimisation steps). Sending never blocks; a driver that has nothing to
+/// report just never calls it.
+#[derive(Clone)]
+pub struct ProgressSink(Arc<dyn Fn(CalculationResult) + Send + Sync>);
+
+impl ProgressSink {
+    pub fn new(f: impl Fn(CalculationResult) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn emit(&self, snapshot: CalculationResult) {
+        (self.0)(snapshot)
+    }
+}
+
+/// A sink that drops everything, for tools that run a driver directly.
+impl Default for ProgressSink {
+    fn default() -> Self {
+        Self::new(|_| {})
+    }
+}
+
 // ============================================================================
 // 2. THE DISPATCHER (The Factory)
 // ============================================================================
@@ -107,6 +153,12 @@ impl DriverFactory {
                     args: vec![format!("--strategy={}", strategy)],
                 },
             ))),
+
+            // 6. Multi-step Pipeline
+            // Each step dispatched through this factory, sharing one work dir
+            Engine::Pipeline { steps } => {
+                Ok(Box::new(pipeline::PipelineDriver::new(steps.clone())))
+            }
         }
     }
 }
@@ -119,6 +171,11 @@ impl DriverFactory {
 /// This ensures consistent application of affinity/env vars across all drivers.
 pub mod utils {
     use super::*;
+    use crate::core::{Environment, Provenance};
+    use chrono::Utc;
+    use serde_json::Value;
+    use std::ffi::OsString;
+    use std::io::{Read, Seek, SeekFrom};
     use tokio::process::Command; // FIXED: Using Tokio Command
 
     pub fn apply_sandbox(cmd: &mut Command, sandbox: &Sandbox) {
@@ -126,11 +183,135 @@ pub mod utils {
         sandbox.apply(cmd);
     }
 
+    /// Wraps a prepared `cmd` so it runs inside `env`; None leaves it as is.
+    ///
+    /// The program, args, env vars and working dir carry over. Containers
+    /// get the working dir (or this process's) mounted at the same path, and
+    /// Docker gets the sandbox's cores, GPUs and memory limit as flags. Docker
+    /// is told the env vars by name only, so no value (a job's secrets) is
+    /// ever a command-line argument.
+    /// Call before setting up pipes, which do not carry over.
+    pub fn in_environment(cmd: Command, env: Option<&Environment>, sandbox: &Sandbox) -> Command {
+        let Some(env) = env else { return cmd };
+        let inner = cmd.as_std();
+        let program = inner.get_program().to_os_string();
+        let args: Vec<OsString> = inner.get_args().map(Into::into).collect();
+        let envs: Vec<(OsString, Option<OsString>)> = inner
+            .get_envs()
+            .map(|(k, v)| (k.into(), v.map(Into::into)))
+            .collect();
+        let dir = inner.get_current_dir().map(Path::to_path_buf);
+        let mount = dir.clone().or_else(|| std::env::current_dir().ok());
+
+        let mut outer = match env {
+            Environment::Uv { project } => {
+                let mut c = Command::new("uv");
+                c.args(["run", "--project", project.as_str(), "--"]);
+                c
+            }
+            Environment::Docker { image } => {
+                let mut c = Command::new("docker");
+                c.args(["run", "--rm", "-i"]);
+                if let Some(d) = &mount {
+                    c.arg("-v").arg(format!("{0}:{0}", d.display()));
+                    c.arg("-w").arg(d);
+                }
+                let list = |ids: &[usize]| {
+                    ids.iter()
+                        .map(|id| id.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                };
+                if !sandbox.cores.is_empty() {
+                    c.arg("--cpuset-cpus").arg(list(&sandbox.cores));
+                }
+                if !sandbox.gpus.is_empty() {
+                    c.arg("--gpus")
+                        .arg(format!("\"device={}\"", list(&sandbox.gpus)));
+                }
+                if let Some(mb) = sandbox.memory_mb_limit {
+                    c.arg("--memory").arg(format!("{}m", mb));
+                }
+                for (k, v) in &envs {
+                    // --gpus renumbers the devices it passes from 0
+                    let renumbered = k == "CUDA_VISIBLE_DEVICES" || k == "ROCR_VISIBLE_DEVICES";
+                    if v.is_some() && (!renumbered || sandbox.gpus.is_empty()) {
+                        // The value comes from docker's own env, set below
+                        c.arg("-e").arg(k);
+                    }
+                }
+                c.arg(image);
+                c
+            }
+            Environment::Apptainer { image } => {
+                let mut c = Command::new("apptainer");
+                c.arg("exec");
+                if !sandbox.gpus.is_empty() {
+                    c.arg("--nv");
+                }
+                if let Some(d) = &mount {
+                    c.arg("--bind").arg(d).arg("--pwd").arg(d);
+                }
+                c.arg(image);
+                c
+            }
+            Environment::Modules { modules } => {
+                let mut c = Command::new("bash");
+                c.arg("-lc")
+                    .arg(r#"module load $ULAB_MODULES && exec "$0" "$@""#);
+                c.env("ULAB_MODULES", modules.join(" "));
+                c
+            }
+        };
+        outer.arg(program).args(args);
+        for (k, v) in envs {
+            match v {
+                Some(v) => outer.env(k, v),
+                None => outer.env_remove(k),
+            };
+        }
+        if let Some(d) = dir {
+            outer.current_dir(d);
+        }
+        outer
+    }
+
+    /// Exports the trace IDs so Python adapters/agents can tag their logs.
+    pub fn apply_trace(cmd: &mut Command, trace: &TraceContext) {
+        for (k, v) in trace.env_vars() {
+            cmd.env(k, v);
+        }
+    }
+
+    /// Work dir file holding the end of the compute program's stdout.
+    pub const STDOUT_FILE: &str = "ulab_stdout.log";
+    /// Work dir file holding the end of the compute program's stderr.
+    pub const STDERR_FILE: &str = "ulab_stderr.log";
+    /// How much of each stream is kept; engines like VASP print megabytes.
+    pub const KEPT_OUTPUT_BYTES: usize = 64 * 1024;
+
+    /// Writes the last `KEPT_OUTPUT_BYTES` of each non-empty stream of
+    /// `output` to `STDOUT_FILE` / `STDERR_FILE` in `work_dir`, secrets masked.
+    pub fn keep_output(work_dir: &Path, output: &std::process::Output) -> Result<()> {
+        for (name, bytes) in [(STDOUT_FILE, &output.stdout), (STDERR_FILE, &output.stderr)] {
+            if bytes.is_empty() {
+                continue;
+            }
+            let tail = &bytes[bytes.len().saturating_sub(KEPT_OUTPUT_BYTES)..];
+            let text = String::from_utf8_lossy(tail);
+            std::fs::write(
+                work_dir.join(name),
+                crate::secrets::redact(&text).as_bytes(),
+            )?;
+        }
+        Ok(())
+    }
+
     /// Helper to capture Stdout/Stderr and format errors nicely.
     /// Used by ExternalDriver.
     pub async fn wait_with_output_logging(
         child: tokio::process::Child,
-        job_id: uuid::Uuid,
+        trace: &TraceContext,
     ) -> Result<std::process::Output> {
         let output = child.wait_with_output().await?;
 
@@ -140,8 +321,9 @@ pub mod utils {
 
             // Log a snippet for visibility in the TUI
             log::error!(
-                "Job {} Failed. Exit: {:?}\nSTDERR tail:\n{}",
-                job_id,
+                "{} Job {} Failed. Exit: {:?}\nSTDERR tail:\n{}",
+                trace,
+                trace.job_id,
                 output.status.code(),
                 stderr.lines().rev().take(10).collect::<Vec<_>>().join("\n")
             );
@@ -156,4 +338,70 @@ pub mod utils {
 
         Ok(output)
     }
+
+    /// Reads the snapshots appended to `PROGRESS_FILE` since the last poll.
+    pub struct ProgressTail {
+        path: std::path::PathBuf,
+        offset: u64,
+        base: Provenance,
+    }
+
+    impl ProgressTail {
+        /// Starts at the file's current end: in a pipeline, earlier steps'
+        /// lines are already there. `base` stands in for the provenance a
+        /// line leaves out.
+        pub fn new(work_dir: &Path, base: Provenance) -> Self {
+            let path = work_dir.join(PROGRESS_FILE);
+            let offset = std::fs::metadata(&path).map_or(0, |m| m.len());
+            Self { path, offset, base }
+        }
+
+        /// Sends every complete new line to `progress`; a line still being
+        /// written waits for the next poll. Returns the number sent.
+        pub fn poll(&mut self, progress: &ProgressSink) -> usize {
+            let mut new = Vec::new();
+            let read = std::fs::File::open(&self.path).and_then(|mut f| {
+                f.seek(SeekFrom::Start(self.offset))?;
+                f.read_to_end(&mut new)
+            });
+            let Some(end) = read
+                .ok()
+                .and_then(|_| new.iter().rposition(|b| *b == b'\n'))
+            else {
+                return 0;
+            };
+            self.offset += end as u64 + 1;
+
+            let mut sent = 0;
+            for line in String::from_utf8_lossy(&new[..end]).lines() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match self.snapshot(line) {
+                    Ok(snapshot) => {
+                        progress.emit(snapshot);
+                        sent += 1;
+                    }
+                    Err(e) => log::warn!("Ignoring line of {:?}: {}", self.path, e),
+                }
+            }
+            sent
+        }
+
+        fn snapshot(&self, line: &str) -> Result<CalculationResult> {
+            let mut raw: Value = serde_json::from_str(line)?;
+            if let Some(obj) = raw.as_object_mut() {
+                let now = Utc::now();
+                if !obj.contains_key("provenance") {
+                    let mut prov = self.base.clone();
+                    prov.end_time = now;
+                    obj.insert("provenance".into(), serde_json::to_value(prov)?);
+                }
+                obj.entry("t_total_ms").or_insert_with(|| {
+                    ((now - self.base.start_time).num_milliseconds() as f64).into()
+                });
+            }
+            Ok(serde_json::from_value(raw)?)
+        }
+    }
 }
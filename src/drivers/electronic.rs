@@ -0,0 +1,170 @@
+// src/drivers/electronic.rs
+//
+// =============================================================================
+// UNIFIEDLAB: CHARGE & SPIN TEMPLATING (v 0.1 )
+// =============================================================================
+//
+// The Electronic State.
+//
+// Responsibilities:
+// 1. Turn per-atom `charge` / `magnetic_moment` into the settings each engine
+//    expects (MAGMOM/ISPIN for VASP, site charges for GULP, CHARGE and
+//    MULTIPLICITY for CP2K). The adapter receives them as `electronic`.
+// 2. Read per-atom moments and charges that an adapter parsed from the
+//    engine output back into `final_structure`.
+//
+// Structures without any charge or moment send nothing, so engines keep
+// their own defaults. Moments are in Bohr magnetons, charges in |e|.
+
+use crate::core::{CalculationResult, Structure};
+use serde_json::{json, Value};
+
+// ============================================================================
+// 1. INPUTS (Structure -> Engine)
+// ============================================================================
+
+/// Engine settings for the structure's charges and moments, keyed as the
+/// adapter writes them. `None` when no atom sets either, or when the engine
+/// (e.g. an agent script) has no such settings.
+///
+/// - `vasp`: `MAGMOM` (one value per atom, in structure order) and `ISPIN`,
+///   plus `net_charge`. VASP takes charge as NELECT, which depends on the
+///   POTCARs, so the adapter sets NELECT = sum(ZVAL) - net_charge.
+/// - `gulp`: `charges` per atom, `null` where the potential library decides.
+/// - `cp2k`: `CHARGE`, `MULTIPLICITY` and `UKS` for open shells.
+pub fn engine_inputs(engine: &str, structure: &Structure) -> Option<Value> {
+    let atoms = &structure.atoms;
+    let has_charge = atoms.iter().any(|a| a.charge.is_some());
+    let has_moment = atoms.iter().any(|a| a.magnetic_moment.is_some());
+    if !has_charge && !has_moment {
+        return None;
+    }
+
+    let net_charge = net_charge(structure);
+    let moments: Vec<f64> = atoms
+        .iter()
+        .map(|a| a.magnetic_moment.unwrap_or(0.0))
+        .collect();
+
+    match engine {
+        "vasp" => {
+            let mut tags = json!({ "net_charge": net_charge });
+            if has_moment {
+                tags["MAGMOM"] = json!(magmom_tag(&moments));
+                tags["ISPIN"] = json!(if moments.iter().any(|m| *m != 0.0) {
+                    2
+                } else {
+                    1
+                });
+            }
+            Some(tags)
+        }
+        "gulp" => has_charge.then(|| {
+            json!({
+                "charges": atoms.iter().map(|a| a.charge).collect::<Vec<_>>(),
+                "net_charge": net_charge,
+            })
+        }),
+        "cp2k" => {
+            let multiplicity = multiplicity(structure);
+            Some(json!({
+                "CHARGE": net_charge.round() as i64,
+                "MULTIPLICITY": multiplicity,
+                "UKS": multiplicity > 1,
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Sum of the per-atom charges; atoms without one count as neutral.
+pub fn net_charge(structure: &Structure) -> f64 {
+    structure.atoms.iter().filter_map(|a| a.charge).sum()
+}
+
+/// Spin multiplicity 2S+1 from the total moment, taking one unpaired
+/// electron per Bohr magneton.
+pub fn multiplicity(structure: &Structure) -> u32 {
+    let total: f64 = structure
+        .atoms
+        .iter()
+        .filter_map(|a| a.magnetic_moment)
+        .sum();
+    total.abs().round() as u32 + 1
+}
+
+/// VASP's MAGMOM value, with runs of equal moments written as `n*m`
+/// (e.g. `2*3.0 0.0`).
+pub fn magmom_tag(moments: &[f64]) -> String {
+    let mut parts = vec![];
+    let mut i = 0;
+    while i < moments.len() {
+        let run = moments[i..]
+            .iter()
+            .take_while(|m| **m == moments[i])
+            .count();
+        parts.push(match run {
+            1 => format!("{:?}", moments[i]),
+            n => format!("{}*{:?}", n, moments[i]),
+        });
+        i += run;
+    }
+    parts.join(" ")
+}
+
+// ============================================================================
+// 2. OUTPUTS (Engine -> Structure)
+// ============================================================================
+
+/// Copies `magnetic_moments` / `charges` from the adapter's parse response
+/// onto `result.final_structure`, one value per atom.
+///
+/// When the adapter returned no final structure, the input structure is
+/// used: a static run keeps its geometry, and a pipeline's next step then
+/// starts from the converged moments. Lists of the wrong length are ignored
+/// with a warning. Returns whether anything was applied.
+pub fn apply_site_properties(
+    result: &mut CalculationResult,
+    input: &Structure,
+    parsed: &Value,
+) -> bool {
+    let list = |key: &str| -> Option<Vec<f64>> {
+        parsed
+            .get(key)?
+            .as_array()?
+            .iter()
+            .map(Value::as_f64)
+            .collect()
+    };
+    let n = result
+        .final_structure
+        .as_ref()
+        .map_or(input.atoms.len(), |s| s.atoms.len());
+    let mut found = vec![];
+    for key in ["magnetic_moments", "charges"] {
+        match list(key) {
+            Some(values) if values.len() == n => found.push((key, values)),
+            Some(values) => log::warn!(
+                "Adapter returned {} {} for {} atoms; ignoring them",
+                values.len(),
+                key,
+                n
+            ),
+            None => {}
+        }
+    }
+    if found.is_empty() {
+        return false;
+    }
+
+    let structure = result.final_structure.get_or_insert_with(|| input.clone());
+    for (key, values) in found {
+        for (atom, v) in structure.atoms.iter_mut().zip(values) {
+            match key {
+                "magnetic_moments" => atom.magnetic_moment = Some(v),
+                _ => atom.charge = Some(v),
+            }
+        }
+    }
+    true
+}
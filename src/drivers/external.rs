@@ -10,17 +10,37 @@
 // 1. "The Sandwich": Python Write -> Rust Execute -> Python Parse.
 // 2. Environment Scrubbing: Remove outer MPI context to allow nested execution.
 // 3. Provenance: Capture binary SHA256 and exit codes.
+//    Files the write phase creates are recorded as the job's inputs.
+//    Trace IDs are exported (ULAB_TRACE_ID/ULAB_SPAN_ID) to every child process.
+//    The compute phase is sampled for actual CPU/RSS usage.
+//    Snapshots the program appends to `ulab_progress.jsonl` are sent on as
+//    progress while it runs.
 // 4. Path Safety: Resolves scripts/binaries to absolute paths.
 // 5. Cross-Platform: Handles macOS vs Linux MPI arguments gracefully.
-
-use crate::core::{CalculationResult, Job, Provenance};
-use crate::drivers::utils::{apply_sandbox, wait_with_output_logging};
-use crate::drivers::CodeDriver;
-use crate::resources::Sandbox;
+// 6. Electronic State: Charges/moments go to the adapter as `electronic`;
+//    moments it parses come back into `final_structure`.
+// 7. Compute Environment: The compute phase runs inside the job's
+//    environment (uv, Docker, Apptainer, modules); adapters stay on the host.
+// 8. Secrets: Adapters and the compute phase get the job's secrets as env
+//    vars, resolved here on the Guardian.
+// 9. Output: The end of the compute phase's stdout/stderr is left in the
+//    work dir (`keep_output`), so it is stored with the job's outputs.
+
+use crate::core::{CalculationResult, FileRole, Job, JobConfig, Provenance, ResourceUsage};
+use crate::drivers::electronic;
+use crate::drivers::utils::{
+    apply_sandbox, apply_trace, in_environment, keep_output, wait_with_output_logging, ProgressTail,
+};
+use crate::drivers::{CodeDriver, ProgressSink, PROGRESS_POLL_EVERY};
+use crate::logs::TraceContext;
+use crate::provenance::manifest_dir;
+use crate::resources::{Sandbox, UsageSampler, USAGE_SAMPLE_INTERVAL};
+use crate::secrets;
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use chrono::Utc;
+use serde::Deserialize;
 use serde_json::Value;
 use std::path::Path;
 use std::process::Stdio;
@@ -59,45 +79,69 @@ impl CodeDriver for ExternalDriver {
         job: &Job,
         sandbox: &Sandbox,
         work_dir: &Path,
+        trace: &TraceContext,
+        progress: &ProgressSink,
     ) -> Result<CalculationResult> {
         let t0 = Utc::now();
+        let host = hostname::get()?.to_string_lossy().to_string();
+        let sandbox_info = format!("Cores: {:?}, GPUs: {:?}", sandbox.cores, sandbox.gpus);
 
         // A. ADAPTER PHASE: WRITE INPUTS
         // Rust sends the Job JSON to Python via Stdin.
-        self.call_adapter("write", job, work_dir)
+        // What the adapter writes is recorded as the job's inputs (a pipeline
+        // step finds earlier steps' files already there).
+        let before = manifest_dir(work_dir, FileRole::Input)?;
+        self.call_adapter("write", job, work_dir, trace)
             .await
             .context("Adapter Write Phase failed")?;
+        let mut inputs = manifest_dir(work_dir, FileRole::Input)?;
+        inputs.retain(|f| !before.contains(f));
 
         // B. COMPUTE PHASE: RUN BINARY
         // Rust manages the heavy process directly for isolation/monitoring.
         // This returns the exit code and (optionally) the binary hash.
-        let (exit_code, bin_hash) = self
-            .run_heavy_compute(sandbox, work_dir)
+        let tail = ProgressTail::new(
+            work_dir,
+            Provenance {
+                execution_host: host.clone(),
+                start_time: t0,
+                end_time: t0,
+                binary_hash: None,
+                exit_code: 0,
+                sandbox_info: sandbox_info.clone(),
+                files: Vec::new(),
+            },
+        );
+        let (exit_code, bin_hash, usage) = self
+            .run_heavy_compute(sandbox, &job.config, work_dir, trace, tail, progress)
             .await
             .context("Compute Phase failed")?;
 
         // C. ADAPTER PHASE: PARSE OUTPUTS
         // Python parses OUTCAR/logs and returns the CalculationResult JSON.
         let result_json = self
-            .call_adapter("parse", job, work_dir)
+            .call_adapter("parse", job, work_dir, trace)
             .await
             .context("Adapter Parse Phase failed")?;
 
         // D. FINALIZE
         // Deserialize the Python result
-        let mut result: CalculationResult = serde_json::from_value(result_json)
+        let mut result = CalculationResult::deserialize(&result_json)
             .context("Failed to deserialize result from Adapter")?;
+        electronic::apply_site_properties(&mut result, &job.structure, &result_json);
 
         // Hydrate Provenance (Rust knows the truth about execution time and hardware)
         result.provenance = Provenance {
-            execution_host: hostname::get()?.to_string_lossy().to_string(),
+            execution_host: host,
             start_time: t0,
             end_time: Utc::now(),
             binary_hash: bin_hash,
             exit_code,
-            sandbox_info: format!("Cores: {:?}, GPUs: {:?}", sandbox.cores, sandbox.gpus),
+            sandbox_info,
+            files: inputs,
         };
         result.t_total_ms = (Utc::now() - t0).num_milliseconds() as f64;
+        result.usage = usage;
 
         Ok(result)
     }
@@ -115,7 +159,13 @@ impl ExternalDriver {
 
     // --- PHASE A/C: ADAPTER CALLS ---
 
-    async fn call_adapter(&self, mode: &str, job: &Job, work_dir: &Path) -> Result<Value> {
+    async fn call_adapter(
+        &self,
+        mode: &str,
+        job: &Job,
+        work_dir: &Path,
+        trace: &TraceContext,
+    ) -> Result<Value> {
         let mut cmd = Command::new("python");
 
         // FIX: Use absolute path for the CLI wrapper too, just in case
@@ -129,28 +179,41 @@ impl ExternalDriver {
         cmd.arg(mode);
         cmd.arg(self.engine_name());
         cmd.arg(work_dir);
+        apply_trace(&mut cmd, trace);
+        secrets::apply(&mut cmd, &job.config.secrets)?;
 
-        // Setup pipes
+        // Setup pipes (killed if the job is preempted)
+        cmd.kill_on_drop(true);
         cmd.stdin(Stdio::piped());
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
         let mut child = cmd.spawn().context("Failed to spawn Adapter")?;
 
-        // Write Job JSON to Stdin
+        // Write Job JSON to Stdin, with the engine's charge/spin settings
         if let Some(mut stdin) = child.stdin.take() {
-            let json_bytes = serde_json::to_vec(job)?;
+            let mut payload = serde_json::to_value(job)?;
+            if let Some(inputs) = electronic::engine_inputs(self.engine_name(), &job.structure) {
+                payload["electronic"] = inputs;
+            }
+            let json_bytes = serde_json::to_vec(&payload)?;
             tokio::io::AsyncWriteExt::write_all(&mut stdin, &json_bytes).await?;
         }
 
         // Wait and capture output
-        let output = wait_with_output_logging(child, job.id).await?;
+        let output = wait_with_output_logging(child, trace).await?;
 
         // If parsing, we expect JSON on stdout. If writing, we expect empty/logs.
         if mode == "parse" {
             let out_str = String::from_utf8_lossy(&output.stdout);
             let json: Value =
                 serde_json::from_str(out_str.trim()).context("Adapter returned invalid JSON")?;
+
+            // Adapters must echo the trace so their logs can be joined with ours
+            match json.get("trace_id").and_then(|v| v.as_str()) {
+                Some(t) if t == trace.trace_id => {}
+                other => log::warn!("{} Adapter did not echo trace id (got {:?})", trace, other),
+            }
             Ok(json)
         } else {
             Ok(Value::Null)
@@ -162,8 +225,12 @@ impl ExternalDriver {
     async fn run_heavy_compute(
         &self,
         sandbox: &Sandbox,
+        config: &JobConfig,
         work_dir: &Path,
-    ) -> Result<(i32, Option<String>)> {
+        trace: &TraceContext,
+        mut tail: ProgressTail,
+        progress: &ProgressSink,
+    ) -> Result<(i32, Option<String>, Option<ResourceUsage>)> {
         let (binary, args, needs_mpi) = self.resolve_command(sandbox);
 
         let mut cmd = Command::new(&binary);
@@ -172,6 +239,8 @@ impl ExternalDriver {
 
         // 1. ISOLATION (Affinity & Env Vars)
         apply_sandbox(&mut cmd, sandbox);
+        apply_trace(&mut cmd, trace);
+        secrets::apply(&mut cmd, &config.secrets)?;
 
         // 2. ENVIRONMENT SCRUBBING (The "Clean Slate")
         if needs_mpi {
@@ -195,7 +264,9 @@ impl ExternalDriver {
             }
         }
 
-        // 3. EXECUTION
+        // 3. EXECUTION (inside the job's environment; killed if the job is preempted)
+        let mut cmd = in_environment(cmd, config.environment.as_ref(), sandbox);
+        cmd.kill_on_drop(true);
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
@@ -207,17 +278,38 @@ impl ExternalDriver {
             .spawn()
             .with_context(|| format!("Failed to spawn binary '{}' in '{:?}'", binary, work_dir))?;
 
+        let sampler = child
+            .id()
+            .map(|pid| UsageSampler::start(pid, USAGE_SAMPLE_INTERVAL));
+
         // We don't use the logging helper here because GULP/VASP output can be massive.
         // We assume the binary writes to files (OUTCAR/output.gin) in work_dir.
         // We only capture stderr for crashes.
-        let output = child.wait_with_output().await?;
+        let wait = child.wait_with_output();
+        tokio::pin!(wait);
+        let mut poll = tokio::time::interval(PROGRESS_POLL_EVERY);
+        let output = loop {
+            tokio::select! {
+                out = &mut wait => break out?,
+                _ = poll.tick() => {
+                    tail.poll(progress);
+                }
+            }
+        };
+        let usage = match sampler {
+            Some(s) => s.finish().await,
+            None => None,
+        };
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            log::warn!("Compute Binary stderr: {}", stderr);
+            log::warn!("{} Compute Binary stderr: {}", trace, stderr);
+        }
+        if let Err(e) = keep_output(work_dir, &output) {
+            log::warn!("{} Failed to keep compute output: {}", trace, e);
         }
 
-        Ok((output.status.code().unwrap_or(-1), bin_hash))
+        Ok((output.status.code().unwrap_or(-1), bin_hash, usage))
     }
 
     /// Resolves the binary/script string to a usable command.
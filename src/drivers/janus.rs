@@ -11,12 +11,20 @@
 // 2. Stream requests via Stdin/Stdout (JSON-RPC style).
 // 3. Reboot the kernel if the assigned Sandbox changes (Context Switch).
 // 4. Capture Stderr in real-time for debugging ("Glass Box").
-
-use crate::core::{CalculationResult, ElectronVolts, Force, Job, Provenance, Structure};
-use crate::drivers::CodeDriver;
+// 5. Boot the kernel inside the job's compute environment, if it has one,
+//    with the job's secrets as env vars.
+
+use crate::core::{
+    CalculationResult, ElectronVolts, Force, Job, JobConfig, Provenance, Structure,
+    RESULT_SCHEMA_VERSION,
+};
+use crate::drivers::utils::in_environment;
+use crate::drivers::{CodeDriver, ProgressSink};
+use crate::logs::TraceContext;
 use crate::physics::SanityCheck; // The Validator
 use crate::provenance::{sha256_bytes, ModelNotary};
 use crate::resources::Sandbox; // The Notary
+use crate::secrets;
 
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
@@ -86,6 +94,8 @@ impl CodeDriver for JanusDriver {
         job: &Job,
         sandbox: &Sandbox,
         _work_dir: &Path,
+        trace: &TraceContext,
+        _progress: &ProgressSink,
     ) -> Result<CalculationResult> {
         let t0 = Utc::now();
 
@@ -110,7 +120,8 @@ impl CodeDriver for JanusDriver {
         if needs_reboot {
             if let Some(mut old_k) = kernel_guard.take() {
                 log::info!(
-                    "🔄 Rebooting Janus Kernel (Context Switch: {} -> {})",
+                    "{} 🔄 Rebooting Janus Kernel (Context Switch: {} -> {})",
+                    trace,
                     old_k.sandbox_signature,
                     sandbox_sig
                 );
@@ -118,7 +129,7 @@ impl CodeDriver for JanusDriver {
             }
 
             // Boot new kernel bound to THIS sandbox
-            let new_k = self.boot_kernel(sandbox, &sandbox_sig).await?;
+            let new_k = self.boot_kernel(sandbox, &job.config, &sandbox_sig).await?;
             *kernel_guard = Some(new_k);
         }
 
@@ -129,6 +140,9 @@ impl CodeDriver for JanusDriver {
         let req_json = serde_json::to_string(&JanusRequest {
             structure: job.structure.clone(),
             calc_mode: "single_point".into(),
+            // The daemon outlives jobs, so IDs travel per request (not env)
+            trace_id: trace.trace_id.clone(),
+            span_id: trace.span_id.clone(),
         })?;
 
         // 2. Write to Stdin
@@ -162,6 +176,14 @@ impl CodeDriver for JanusDriver {
         let resp: JanusResponse = serde_json::from_str(&resp_line)
             .with_context(|| format!("Invalid JSON from daemon: '{}'", resp_line.trim()))?;
 
+        if resp.trace_id.as_deref() != Some(trace.trace_id.as_str()) {
+            log::warn!(
+                "{} Janus daemon did not echo trace id (got {:?})",
+                trace,
+                resp.trace_id
+            );
+        }
+
         if let Some(err) = resp.error {
             return Err(anyhow!("Janus Logic Error: {}", err));
         }
@@ -191,14 +213,23 @@ impl CodeDriver for JanusDriver {
                 binary_hash: bin_hash,
                 exit_code: 0,
                 sandbox_info: sandbox_sig,
+                files: vec![],
             },
             next_generation: None,
+            usage: None, // Runs inside the shared daemon; not attributable per job
+            steps: vec![],
+            schema_version: RESULT_SCHEMA_VERSION,
         })
     }
 }
 
 impl JanusDriver {
-    async fn boot_kernel(&self, sandbox: &Sandbox, sig: &str) -> Result<JanusKernel> {
+    async fn boot_kernel(
+        &self,
+        sandbox: &Sandbox,
+        config: &JobConfig,
+        sig: &str,
+    ) -> Result<JanusKernel> {
         // Expected location of the python driver
         let script_path = "unifiedlab_drivers/janus_daemon.py";
 
@@ -215,6 +246,8 @@ impl JanusDriver {
         // 2. Apply Isolation (Env vars: CUDA_VISIBLE_DEVICES, etc.)
         // This is crucial: The Python process only sees the GPUs we give it.
         sandbox.apply(&mut cmd);
+        secrets::apply(&mut cmd, &config.secrets)?;
+        let mut cmd = in_environment(cmd, config.environment.as_ref(), sandbox);
 
         // 3. Setup Pipes
         cmd.stdin(Stdio::piped())
@@ -286,6 +319,8 @@ impl JanusDriver {
 struct JanusRequest {
     structure: Structure,
     calc_mode: String,
+    trace_id: String,
+    span_id: String,
 }
 
 #[derive(Deserialize)]
@@ -294,4 +329,6 @@ struct JanusResponse {
     forces: Option<Vec<[f64; 3]>>,
     stress: Option<[[f64; 3]; 3]>,
     error: Option<String>,
+    #[serde(default)]
+    trace_id: Option<String>,
 }
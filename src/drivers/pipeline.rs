@@ -0,0 +1,129 @@
+// src/drivers/pipeline.rs
+//
+// =============================================================================
+// UNIFIEDLAB: PIPELINE DRIVER (v 0.1 )
+// =============================================================================
+//
+// The Composite Driver.
+//
+// Responsibilities:
+// 1. Run the steps of an `Engine::Pipeline` one after another in ONE work dir,
+//    so later steps can read what earlier ones left behind (WAVECAR, restarts).
+// 2. Chain structures: each step starts from the previous `final_structure`.
+// 3. Keep a `StepRecord` (provenance, energy, timing) per step.
+// 4. Abort on the first failing step, whether its driver errs or its program
+//    exits nonzero; the error names the step.
+// 5. Report each finished step but the last as a progress snapshot; steps
+//    pass on their own snapshots too.
+//
+// Step parameters: if `params.steps` is an array with one entry per step,
+// step i gets `params.steps[i]`; otherwise every step shares `params`.
+
+use crate::core::{CalculationResult, Engine, Job, JobConfig, ResourceUsage, StepRecord};
+use crate::drivers::{CodeDriver, DriverFactory, ProgressSink};
+use crate::logs::TraceContext;
+use crate::resources::Sandbox;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::Path;
+
+pub struct PipelineDriver {
+    steps: Vec<Engine>,
+}
+
+impl PipelineDriver {
+    pub fn new(steps: Vec<Engine>) -> Self {
+        Self { steps }
+    }
+
+    /// Parameters for step `i` (see header).
+    pub fn step_params(params: &Value, i: usize, n_steps: usize) -> Value {
+        match params.get("steps").and_then(Value::as_array) {
+            Some(per_step) if per_step.len() == n_steps => per_step[i].clone(),
+            _ => params.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl CodeDriver for PipelineDriver {
+    async fn execute(
+        &self,
+        job: &Job,
+        sandbox: &Sandbox,
+        work_dir: &Path,
+        trace: &TraceContext,
+        progress: &ProgressSink,
+    ) -> Result<CalculationResult> {
+        if self.steps.is_empty() {
+            return Err(anyhow!("Pipeline has no steps"));
+        }
+
+        let n = self.steps.len();
+        let mut step_job = job.clone();
+        let mut records = Vec::with_capacity(n);
+        let mut usage: Option<ResourceUsage> = None;
+        let mut last = None;
+
+        for (i, engine) in self.steps.iter().enumerate() {
+            let code = engine.code();
+            log::info!("{} Pipeline step {}/{}: {}", trace, i + 1, n, code);
+
+            step_job.config = JobConfig {
+                engine: engine.clone(),
+                params: Self::step_params(&job.config.params, i, n),
+                retry: None,
+                environment: job.config.environment.clone(),
+                secrets: job.config.secrets.clone(),
+            };
+            let driver = DriverFactory::get(engine)?;
+            let result: CalculationResult = driver
+                .execute(&step_job, sandbox, work_dir, trace, progress)
+                .await
+                .with_context(|| format!("Pipeline step {}/{} ({}) failed", i + 1, n, code))?;
+            if result.provenance.exit_code != 0 {
+                return Err(anyhow!(
+                    "Pipeline step {}/{} ({}) failed: exit code {}",
+                    i + 1,
+                    n,
+                    code,
+                    result.provenance.exit_code
+                ));
+            }
+
+            if let Some(s) = &result.final_structure {
+                step_job.structure = s.clone();
+            }
+            if let Some(u) = &result.usage {
+                usage.get_or_insert_with(Default::default).absorb(u);
+            }
+            records.push(StepRecord {
+                engine: code,
+                energy: result.energy,
+                t_total_ms: result.t_total_ms,
+                provenance: result.provenance.clone(),
+            });
+            if i + 1 < n {
+                progress.emit(result.clone());
+            }
+            last = Some(result);
+        }
+
+        // The job's outcome is the last step's, timed over the whole pipeline
+        let mut result = last.expect("pipeline has steps");
+        result.provenance.start_time = records[0].provenance.start_time;
+        result.t_total_ms = records.iter().map(|r| r.t_total_ms).sum();
+        result.usage = usage;
+        // Each step's adapter inputs; the guardian adds the outputs
+        result.provenance.files = Vec::new();
+        for file in records.iter().flat_map(|r| &r.provenance.files) {
+            if !result.provenance.files.iter().any(|f| f.path == file.path) {
+                result.provenance.files.push(file.clone());
+            }
+        }
+        result.steps = records;
+        Ok(result)
+    }
+}
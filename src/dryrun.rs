@@ -0,0 +1,353 @@
+// src/dryrun.rs
+//
+// =============================================================================
+// UNIFIEDLAB: DEPLOY DRY RUN (v 0.1 )
+// =============================================================================
+//
+// What `deploy --dry-run` prints instead of submitting.
+//
+// 1. Takes the live workers from checkpoint.db (not silent, not draining).
+//    Each counts with what it has free plus what its running jobs hold.
+// 2. Finds the jobs no worker could ever take: tags it lacks or carries,
+//    more cores or GPUs than any one worker has. Their descendants would
+//    wait forever.
+// 3. Schedules the rest like the Coordinator does: ready jobs by priority,
+//    each on a worker whose tags allow it and whose room fits it. A job runs
+//    as long as its engine's median runtime in the DB, or its time limit for
+//    an engine that never finished a job. The last finish is the makespan.
+// 4. Bounds what each generator may grow to under its `governor`, its
+//    `gen_limit` and the Coordinator's expansion limit.
+//
+// Switch branches are all assumed taken and generators add nothing during
+// the simulation: the makespan is that of the blueprint as written.
+
+use crate::checkpoint::{CheckpointStore, WorkerInfo};
+use crate::core::{JobStatus, ResourceReq};
+use crate::marketplace::{DEFAULT_EXPAND_LIMIT, DEFAULT_WORKER_TIMEOUT, META_EXPAND_LIMIT};
+use crate::report::{human_ms, median};
+use crate::workflow::{ExpansionGovernor, NodeType, WorkflowEngine, BATCH_CORES};
+use anyhow::{anyhow, Result};
+use petgraph::graph::NodeIndex;
+use petgraph::Direction;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+
+/// A job no live worker could ever take.
+#[derive(Debug, Clone)]
+pub struct Unplaceable {
+    pub node: String,
+    pub code: String,
+    pub reason: String,
+}
+
+/// How far one generator of the blueprint may grow.
+#[derive(Debug, Clone)]
+pub struct GrowthBound {
+    pub node: String,
+    /// Candidates one expansion may turn into jobs.
+    pub per_expansion: usize,
+    /// Expansions in its lineage.
+    pub expansions: u64,
+    /// Compute jobs all expansions together may spawn.
+    pub descendants: u64,
+    /// Why the jobs an expansion spawns could never be placed, if so.
+    pub batch_unplaceable: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DryRun {
+    pub jobs: usize,
+    pub workers: usize,
+    /// From the first dispatch to the last finish of the jobs that run.
+    pub makespan_ms: i64,
+    pub unplaceable: Vec<Unplaceable>,
+    /// Jobs that would wait forever on an unplaceable ancestor.
+    pub blocked: usize,
+    /// Engines without a finished job, timed by their jobs' time limits.
+    pub unmeasured: Vec<String>,
+    pub expand_limit: usize,
+    pub growth: Vec<GrowthBound>,
+}
+
+/// Dry run of `graph` against the live workers and runtimes in `store`.
+pub fn dry_run_from_store(
+    graph: &WorkflowEngine,
+    store: &CheckpointStore,
+    now_ms: i64,
+) -> Result<DryRun> {
+    let timeout_ms = DEFAULT_WORKER_TIMEOUT.as_millis() as i64;
+    let mut workers: Vec<WorkerInfo> = store
+        .get_active_workers()?
+        .into_iter()
+        .filter(|w| !w.draining && now_ms - w.last_seen_ms <= timeout_ms)
+        .collect();
+    if workers.is_empty() {
+        return Err(anyhow!(
+            "No live workers in {:?} to schedule against",
+            store.path()
+        ));
+    }
+
+    // What a worker reports is what it has free; its running jobs hold the rest
+    for job in store.restore_jobs()?.values() {
+        if job.status != JobStatus::Running {
+            continue;
+        }
+        if let Some(w) = workers
+            .iter_mut()
+            .find(|w| job.node_id.as_deref() == Some(w.worker_id.as_str()))
+        {
+            w.cores += job.resources.cores;
+            w.gpus += job.resources.gpus;
+        }
+    }
+
+    let mut samples: HashMap<String, Vec<i64>> = HashMap::new();
+    for r in store.get_finished_since(0)? {
+        if let (false, Some(ms)) = (r.failed, r.runtime_ms) {
+            samples.entry(r.code).or_default().push(ms);
+        }
+    }
+    let runtimes = samples
+        .into_iter()
+        .filter_map(|(code, ms)| Some((code, median(ms)?)))
+        .collect();
+
+    let expand_limit = store
+        .get_meta(META_EXPAND_LIMIT)?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_EXPAND_LIMIT);
+
+    simulate(graph, &workers, &runtimes, expand_limit)
+}
+
+/// Schedules `graph` on `workers`, whose `cores` and `gpus` are taken as
+/// their capacity. `runtimes` maps an engine code to its runtime in ms.
+pub fn simulate(
+    graph: &WorkflowEngine,
+    workers: &[WorkerInfo],
+    runtimes: &HashMap<String, i64>,
+    expand_limit: usize,
+) -> Result<DryRun> {
+    let g = &graph.graph;
+    let mut unmeasured = BTreeSet::new();
+    let mut unplaceable = Vec::new();
+    let mut never = vec![false; g.node_count()];
+    let mut duration = vec![0; g.node_count()];
+    for idx in g.node_indices() {
+        let job = &g[idx].job;
+        let code = job.config.engine.code();
+        duration[idx.index()] = match runtimes.get(&code) {
+            Some(ms) => *ms,
+            None => {
+                unmeasured.insert(code.clone());
+                job.resources.time_limit_min as i64 * 60_000
+            }
+        };
+        if let Some(reason) = never_placed(&job.resources, workers) {
+            never[idx.index()] = true;
+            unplaceable.push(Unplaceable {
+                node: node_name(graph, idx),
+                code,
+                reason,
+            });
+        }
+    }
+
+    // Parents left per job; a job is ready at zero
+    let mut waiting: Vec<usize> = g
+        .node_indices()
+        .map(|i| g.neighbors_directed(i, Direction::Incoming).count())
+        .collect();
+    let mut ready: Vec<NodeIndex> = g
+        .node_indices()
+        .filter(|i| waiting[i.index()] == 0 && !never[i.index()])
+        .collect();
+    let mut room: Vec<(usize, usize)> = workers.iter().map(|w| (w.cores, w.gpus)).collect();
+    // (finish, job, worker), earliest first
+    let mut running = BinaryHeap::new();
+    let mut now = 0;
+    let mut done = 0;
+
+    loop {
+        ready.sort_by_key(|i| (Reverse(g[*i].priority), i.index()));
+        ready.retain(|&idx| {
+            let req = &g[idx].job.resources;
+            match place(req, workers, &room) {
+                Some(w) => {
+                    room[w].0 -= req.cores;
+                    room[w].1 -= req.gpus;
+                    running.push(Reverse((now + duration[idx.index()], idx.index(), w)));
+                    false
+                }
+                None => true,
+            }
+        });
+
+        let Some(Reverse((finish, _, _))) = running.peek().copied() else {
+            break;
+        };
+        now = finish;
+        while let Some(Reverse((_, i, w))) = running.peek().copied().filter(|r| r.0 .0 == now) {
+            running.pop();
+            done += 1;
+            let req = &g[NodeIndex::new(i)].job.resources;
+            room[w].0 += req.cores;
+            room[w].1 += req.gpus;
+            for child in g.neighbors_directed(NodeIndex::new(i), Direction::Outgoing) {
+                waiting[child.index()] -= 1;
+                if waiting[child.index()] == 0 && !never[child.index()] {
+                    ready.push(child);
+                }
+            }
+        }
+    }
+
+    let batch = ResourceReq {
+        cores: BATCH_CORES,
+        gpus: 1,
+        ..Default::default()
+    };
+    let mut growth = Vec::new();
+    for idx in g.node_indices() {
+        if !matches!(g[idx].node_type, NodeType::Generator { .. }) {
+            continue;
+        }
+        let params = &g[idx].job.config.params;
+        let governor = ExpansionGovernor::from_params(params)
+            .map_err(|e| anyhow!("node '{}': {}", node_name(graph, idx), e))?;
+        let per_expansion = governor.max_children.unwrap_or(expand_limit);
+        // gen_limit counts the generations after the first
+        let by_gen_limit = params
+            .get("gen_limit")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+            + 1;
+        let expansions = governor
+            .max_generations
+            .map_or(by_gen_limit, |m| m.min(by_gen_limit));
+        let descendants = (per_expansion as u64).saturating_mul(expansions);
+        growth.push(GrowthBound {
+            node: node_name(graph, idx),
+            per_expansion,
+            expansions,
+            descendants: governor
+                .max_descendants
+                .map_or(descendants, |m| m.min(descendants)),
+            batch_unplaceable: never_placed(&batch, workers),
+        });
+    }
+
+    Ok(DryRun {
+        jobs: g.node_count(),
+        workers: workers.len(),
+        makespan_ms: now,
+        blocked: g.node_count() - done - unplaceable.len(),
+        unplaceable,
+        unmeasured: unmeasured.into_iter().collect(),
+        expand_limit,
+        growth,
+    })
+}
+
+pub fn print_dry_run(file: &str, run: &DryRun) {
+    println!(
+        "{}: {} job(s) on {} live worker(s); nothing was submitted",
+        file, run.jobs, run.workers
+    );
+    println!("Estimated makespan: {}", human_ms(run.makespan_ms));
+    if !run.unmeasured.is_empty() {
+        println!(
+            "  timed by their time limits (no finished jobs yet): {}",
+            run.unmeasured.join(", ")
+        );
+    }
+    if !run.unplaceable.is_empty() {
+        println!("Never placed ({}):", run.unplaceable.len());
+        for u in &run.unplaceable {
+            println!("  {} ({}): {}", u.node, u.code, u.reason);
+        }
+    }
+    if run.blocked > 0 {
+        println!(
+            "  {} job(s) downstream of them would wait forever",
+            run.blocked
+        );
+    }
+    if !run.growth.is_empty() {
+        println!("Expansion bounds (expand limit {}):", run.expand_limit);
+    }
+    for b in &run.growth {
+        println!(
+            "  {}: {} job(s) per expansion, {} expansion(s), {} job(s) in all",
+            b.node, b.per_expansion, b.expansions, b.descendants
+        );
+        if let Some(why) = &b.batch_unplaceable {
+            println!(
+                "  {}: its expansions' jobs could never be placed: {}",
+                b.node, why
+            );
+        }
+    }
+}
+
+/// Index of the first worker the job may run on now, one with a tag it
+/// prefers if any.
+fn place(req: &ResourceReq, workers: &[WorkerInfo], room: &[(usize, usize)]) -> Option<usize> {
+    let fits =
+        |w: &usize| workers[*w].accepts(req) && req.cores <= room[*w].0 && req.gpus <= room[*w].1;
+    let prefers = |w: &usize| req.prefer_tags.iter().any(|t| workers[*w].tags.contains(t));
+    (0..workers.len())
+        .filter(fits)
+        .max_by_key(|w| (prefers(w), Reverse(*w)))
+}
+
+/// Why no worker could take `req` even with nothing else running; None if
+/// one could.
+fn never_placed(req: &ResourceReq, workers: &[WorkerInfo]) -> Option<String> {
+    let allowed: Vec<&WorkerInfo> = workers.iter().filter(|w| w.accepts(req)).collect();
+    if allowed.is_empty() {
+        let mut tags: Vec<String> = req.required_tags.clone();
+        tags.extend(req.avoid_tags.iter().map(|t| format!("!{}", t)));
+        return Some(format!(
+            "no worker has the tags it needs ({})",
+            tags.join(", ")
+        ));
+    }
+    if allowed
+        .iter()
+        .any(|w| req.cores <= w.cores && req.gpus <= w.gpus)
+    {
+        return None;
+    }
+    let most_cores = allowed.iter().map(|w| w.cores).max().unwrap_or(0);
+    let most_gpus = allowed.iter().map(|w| w.gpus).max().unwrap_or(0);
+    let mut short = Vec::new();
+    if req.cores > most_cores {
+        short.push(format!(
+            "{} core(s), the largest worker has {}",
+            req.cores, most_cores
+        ));
+    }
+    if req.gpus > most_gpus {
+        short.push(format!(
+            "{} GPU(s), the most on a worker is {}",
+            req.gpus, most_gpus
+        ));
+    }
+    if short.is_empty() {
+        short.push(format!(
+            "{} core(s) and {} GPU(s), no worker has both",
+            req.cores, req.gpus
+        ));
+    }
+    Some(format!("asks {}", short.join("; ")))
+}
+
+fn node_name(graph: &WorkflowEngine, idx: NodeIndex) -> String {
+    let job = &graph.graph[idx].job;
+    match job.structure.source.as_str() {
+        "" => job.id.to_string()[..8].to_string(),
+        source => source.to_string(),
+    }
+}
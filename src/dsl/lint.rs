@@ -0,0 +1,504 @@
+//! Workflow linting: everything [`validate`](super::validate) rejects, plus
+//! warnings for workflows that load but probably do not do what was meant,
+//! each placed at a line and column of the YAML file.
+//!
+//! Locations come from a second, span-preserving parse of the file: every
+//! problem carries a dotted document path (`nodes.2.retry`), which is looked
+//! up in an index of where each path starts. Problems in nodes that only
+//! exist after expansion (macro output) point at the nearest enclosing path
+//! that does exist in the file.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+
+use super::{
+    edge_node, expand_macros, node_map, parse_yaml, resolve_relative, resolve_subworkflows,
+    split_port, validation_errors, DslError, EngineSpec, MacroKind, NodeKind, Vars, WorkflowSpec,
+};
+use crate::structure_io;
+
+/// Engine names a macro's `engine` param understands.
+const MACRO_ENGINES: &[&str] = &["janus", "gulp", "vasp", "cp2k", "agent"];
+/// Macro sizes past this are assumed to be typos.
+const MACRO_SIZE_LIMIT: u64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One problem found in a workflow file. Lines and columns count from 1.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, severity, self.message
+        )
+    }
+}
+
+/// Lint a workflow file, with `overrides` as for
+/// [`load_yaml`](super::load_yaml). Diagnostics come back sorted by
+/// position; only an unreadable file is an `Err`.
+pub fn lint_file(path: impl AsRef<Path>, overrides: &Vars) -> Result<Vec<Diagnostic>, DslError> {
+    let path = path.as_ref();
+    let raw = fs::read_to_string(path).map_err(|e| DslError::io(e, path.display().to_string()))?;
+
+    let spans = match SpanIndex::build(&raw) {
+        Ok(spans) => spans,
+        Err(e) => {
+            return Ok(vec![Diagnostic {
+                severity: Severity::Error,
+                line: e.marker().line(),
+                column: e.marker().col() + 1,
+                message: format!("invalid YAML: {}", e.info()),
+            }])
+        }
+    };
+    let mut lint = Lint {
+        spans,
+        found: Vec::new(),
+    };
+
+    match parse_yaml(&raw, overrides, path) {
+        Ok(spec) => lint.check(&spec, path),
+        Err(e) => lint.error(&e, ""),
+    }
+
+    let mut found = lint.found;
+    found.sort_by_key(|d| (d.line, d.column, d.severity));
+    Ok(found)
+}
+
+struct Lint {
+    spans: SpanIndex,
+    found: Vec<Diagnostic>,
+}
+
+impl Lint {
+    fn report(&mut self, severity: Severity, path: &str, message: String) {
+        let (line, column) = self.spans.locate(path);
+        self.found.push(Diagnostic {
+            severity,
+            line,
+            column,
+            message,
+        });
+    }
+
+    fn warn(&mut self, path: &str, message: String) {
+        self.report(Severity::Warning, path, message);
+    }
+
+    /// Report a DSL error at its own path, or at `fallback` if it has none.
+    fn error(&mut self, e: &DslError, fallback: &str) {
+        let path = e.path.clone().unwrap_or_else(|| fallback.to_string());
+        self.report(Severity::Error, &path, e.context.join("; "));
+    }
+
+    fn check(&mut self, spec: &WorkflowSpec, file: &Path) {
+        let errors = validation_errors(spec);
+        let valid = errors.is_empty();
+        for e in &errors {
+            self.error(e, "nodes");
+        }
+        self.check_macros(spec);
+        if !valid {
+            return;
+        }
+        self.check_structures(spec, file);
+
+        // Expansion can still fail; later checks want the expanded graph, in
+        // which the file's own nodes keep their indices.
+        let expanded = match expand_macros(spec) {
+            Ok(x) => x.spec,
+            Err(e) => return self.error(&e, "macros"),
+        };
+        if let Err(e) = resolve_subworkflows(&expanded, file) {
+            self.error(&e, "nodes");
+        }
+        self.check_resources(&expanded);
+        self.check_sources(&expanded);
+        self.check_reachability(&expanded);
+    }
+
+    /// Macro params that are ignored or silently defaulted by expansion.
+    fn check_macros(&mut self, spec: &WorkflowSpec) {
+        for (i, m) in spec.macros.iter().enumerate() {
+            let at = |field: &str| format!("macros.{}.params.{}", i, field);
+            // The params expansion reads, the one giving the size first
+            let known: &[&str] = match m.macro_type {
+                MacroKind::Chain => &["length", "engine"],
+                MacroKind::Fanout => &["width", "engine"],
+                MacroKind::MapReduce => &["items", "as", "engine", "collect", "reduce"],
+                MacroKind::ConvergenceLoop => &[
+                    "max_iters",
+                    "until",
+                    "script",
+                    "strategy",
+                    "engine",
+                    "compute",
+                ],
+            };
+            let size_key = known[0];
+            let Some(params) = m.params.as_object() else {
+                if !m.params.is_null() {
+                    self.warn(
+                        &format!("macros.{}.params", i),
+                        format!(
+                            "macro '{}' params must be a mapping; they are ignored",
+                            m.id
+                        ),
+                    );
+                }
+                continue;
+            };
+
+            let expected = known[..known.len() - 1].join(", ");
+            for key in params.keys() {
+                if !known.contains(&key.as_str()) {
+                    self.warn(
+                        &at(key),
+                        format!(
+                            "macro '{}' ignores param '{}' (expected {} and {})",
+                            m.id,
+                            key,
+                            expected,
+                            known[known.len() - 1]
+                        ),
+                    );
+                }
+            }
+            let size = match (params.get(size_key), &m.macro_type) {
+                (Some(serde_json::Value::Array(items)), MacroKind::MapReduce)
+                    if !items.is_empty() =>
+                {
+                    Some(Some(items.len() as u64))
+                }
+                // Expansion rejects a map_reduce without a list of items, and
+                // a loop's iterations are not nodes
+                (_, MacroKind::MapReduce | MacroKind::ConvergenceLoop) => None,
+                (v, _) => v.map(|v| v.as_u64()),
+            };
+            match size {
+                None => {}
+                Some(None) => self.warn(
+                    &at(size_key),
+                    format!(
+                        "macro '{}' {} is not a whole number; 1 is used",
+                        m.id, size_key
+                    ),
+                ),
+                Some(Some(0)) => self.warn(
+                    &at(size_key),
+                    format!("macro '{}' {} is 0, so it creates no nodes", m.id, size_key),
+                ),
+                Some(Some(n)) if n > MACRO_SIZE_LIMIT => self.warn(
+                    &at(size_key),
+                    format!("macro '{}' creates {} nodes; is that intended?", m.id, n),
+                ),
+                Some(Some(_)) => {}
+            }
+            match params.get("engine") {
+                None => {}
+                Some(engine) => match engine.as_str().map(str::to_lowercase) {
+                    Some(name) if MACRO_ENGINES.contains(&name.as_str()) => {
+                        if (name == "vasp" || name == "cp2k") && spec.resources.is_none() {
+                            self.warn(
+                                &at("engine"),
+                                format!(
+                                    "macro '{}' makes {} nodes without resources; they get the defaults",
+                                    m.id, name
+                                ),
+                            );
+                        }
+                    }
+                    _ => self.warn(
+                        &at("engine"),
+                        format!(
+                            "macro '{}' engine '{}' is not one of {}; janus is used",
+                            m.id,
+                            engine
+                                .as_str()
+                                .map_or_else(|| engine.to_string(), str::to_string),
+                            MACRO_ENGINES.join(", ")
+                        ),
+                    ),
+                },
+            }
+        }
+    }
+
+    /// Structure files must read, as deployment reads them.
+    fn check_structures(&mut self, spec: &WorkflowSpec, file: &Path) {
+        for (i, n) in spec.nodes.iter().enumerate() {
+            let Some(st) = &n.structure else { continue };
+            if let Err(e) = structure_io::read(&resolve_relative(file, &st.file), st.format) {
+                self.report(
+                    Severity::Error,
+                    &format!("nodes.{}.structure.file", i),
+                    format!("node '{}' structure {}: {:#}", n.id, st.file, e),
+                );
+            }
+        }
+    }
+
+    /// Heavy engines left on the default one core and 30 minutes.
+    fn check_resources(&mut self, spec: &WorkflowSpec) {
+        if spec.resources.is_some() {
+            return; // Every node has resources
+        }
+        for (i, n) in spec.nodes.iter().enumerate() {
+            let engine = match n.engine {
+                Some(EngineSpec::Vasp) => "vasp",
+                Some(EngineSpec::Cp2k) => "cp2k",
+                _ => continue,
+            };
+            // Macro-made nodes were already reported at their macro.
+            if n.resources.is_none() && i < self.spans.node_count {
+                self.warn(
+                    &format!("nodes.{}.engine", i),
+                    format!(
+                        "node '{}' runs {} with no resources; it gets 1 core for 30 min",
+                        n.id, engine
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Port sources must read `<node>.outputs.<port>` of a node that exists
+    /// and, if it declares outputs, declares that one.
+    fn check_sources(&mut self, spec: &WorkflowSpec) {
+        let nodes = node_map(spec);
+        for (i, n) in spec.nodes.iter().enumerate() {
+            for (j, p) in n.inputs.iter().enumerate() {
+                let Some(source) = &p.source else { continue };
+                let at = format!("nodes.{}.inputs.{}.source", i, j);
+                let Some((node, port)) = split_port(source, "outputs") else {
+                    self.warn(
+                        &at,
+                        format!(
+                            "node '{}' port '{}' source '{}' is not <node>.outputs.<port>",
+                            n.id, p.name, source
+                        ),
+                    );
+                    continue;
+                };
+                match nodes.get(node) {
+                    None => self.warn(
+                        &at,
+                        format!(
+                            "node '{}' port '{}' source names unknown node '{}'",
+                            n.id, p.name, node
+                        ),
+                    ),
+                    Some(from) if from.node_type == NodeKind::Subworkflow => {}
+                    Some(from) => {
+                        if !from.outputs.is_empty() && !from.outputs.iter().any(|o| o.name == port)
+                        {
+                            self.warn(
+                                &at,
+                                format!(
+                                    "node '{}' port '{}' source names output '{}', which node '{}' does not declare",
+                                    n.id, p.name, port, node
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Nodes on or behind a cycle, which loading rejects, and nodes that are
+    /// probably miswired: cut off from every edge, or switches with nothing
+    /// below them to prune.
+    fn check_reachability(&mut self, spec: &WorkflowSpec) {
+        let nodes = node_map(spec);
+        let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut indegree: HashMap<&str, usize> = HashMap::new();
+        let mut touched = HashSet::new();
+        for e in &spec.edges {
+            let from = edge_node(&e.from, "outputs", &nodes);
+            let to = edge_node(&e.to, "inputs", &nodes);
+            if let (Some(from), Some(to)) = (from, to) {
+                children.entry(from).or_default().push(to);
+                *indegree.entry(to).or_default() += 1;
+                touched.insert(from);
+                touched.insert(to);
+            }
+        }
+
+        // Kahn's walk: whatever it never reaches waits on a cycle.
+        let mut queue: VecDeque<&str> = spec
+            .nodes
+            .iter()
+            .map(|n| n.id.as_str())
+            .filter(|id| !indegree.contains_key(id))
+            .collect();
+        let mut reached = HashSet::new();
+        while let Some(id) = queue.pop_front() {
+            reached.insert(id);
+            for child in children.get(id).into_iter().flatten() {
+                let left = indegree.get_mut(child).expect("counted above");
+                *left -= 1;
+                if *left == 0 {
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        for (i, n) in spec.nodes.iter().enumerate() {
+            let at = format!("nodes.{}", i);
+            if !reached.contains(n.id.as_str()) {
+                self.report(
+                    Severity::Error,
+                    &at,
+                    format!("node '{}' can never run: it is on or behind a cycle", n.id),
+                );
+            } else if spec.nodes.len() > 1 && !touched.contains(n.id.as_str()) {
+                self.warn(
+                    &at,
+                    format!("node '{}' is not connected to any other node", n.id),
+                );
+            } else if n.node_type == NodeKind::Switch && !children.contains_key(n.id.as_str()) {
+                self.warn(
+                    &at,
+                    format!("switch '{}' has nothing downstream to prune", n.id),
+                );
+            }
+        }
+    }
+}
+
+/// Where each dotted document path starts in the file.
+struct SpanIndex {
+    spans: HashMap<String, (usize, usize)>,
+    /// How many nodes the file itself lists.
+    node_count: usize,
+}
+
+impl SpanIndex {
+    fn build(raw: &str) -> Result<Self, yaml_rust2::ScanError> {
+        let mut recv = SpanRecorder::default();
+        Parser::new_from_str(raw).load(&mut recv, false)?;
+        Ok(Self {
+            node_count: recv.node_count,
+            spans: recv.spans,
+        })
+    }
+
+    /// The start of `path`, or of its nearest ancestor found in the file.
+    fn locate(&self, path: &str) -> (usize, usize) {
+        let mut path = path;
+        loop {
+            if let Some(&span) = self.spans.get(path) {
+                return span;
+            }
+            match path.rsplit_once('.') {
+                Some((parent, _)) => path = parent,
+                None => return (1, 1),
+            }
+        }
+    }
+}
+
+enum Frame {
+    /// A mapping, holding the key whose value comes next.
+    Map(Option<String>),
+    /// A sequence, holding the index of the next item.
+    Seq(usize),
+}
+
+#[derive(Default)]
+struct SpanRecorder {
+    spans: HashMap<String, (usize, usize)>,
+    /// Open collections, each with its own path.
+    stack: Vec<(Frame, String)>,
+    node_count: usize,
+}
+
+impl SpanRecorder {
+    /// Path of the value starting at `mark`, recording it; `None` if this
+    /// event is a mapping key rather than a value.
+    fn value_path(&mut self, ev: &Event, mark: Marker) -> Option<String> {
+        let pos = (mark.line(), mark.col() + 1);
+        let Some((frame, parent)) = self.stack.last_mut() else {
+            return Some(String::new());
+        };
+        let segment = match frame {
+            Frame::Map(key @ None) => {
+                // Mapping values are located at their key.
+                let name = match ev {
+                    Event::Scalar(name, ..) => name.clone(),
+                    _ => String::new(),
+                };
+                self.spans.insert(join(parent, &name), pos);
+                *key = Some(name);
+                return None;
+            }
+            Frame::Map(key) => key.take().unwrap_or_default(),
+            Frame::Seq(next) => {
+                *next += 1;
+                let segment = (*next - 1).to_string();
+                self.spans.entry(join(parent, &segment)).or_insert(pos);
+                segment
+            }
+        };
+        if parent == "nodes" {
+            self.node_count += 1;
+        }
+        Some(join(parent, &segment))
+    }
+}
+
+impl MarkedEventReceiver for SpanRecorder {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        match ev {
+            Event::Scalar(..) | Event::Alias(_) => {
+                self.value_path(&ev, mark);
+            }
+            // A collection used as a key gets a path nothing refers to.
+            Event::MappingStart(..) => {
+                let path = self.value_path(&ev, mark).unwrap_or_else(|| "?".into());
+                self.stack.push((Frame::Map(None), path));
+            }
+            Event::SequenceStart(..) => {
+                let path = self.value_path(&ev, mark).unwrap_or_else(|| "?".into());
+                self.stack.push((Frame::Seq(0), path));
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn join(parent: &str, segment: &str) -> String {
+    if parent.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}.{}", parent, segment)
+    }
+}
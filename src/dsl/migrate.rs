@@ -0,0 +1,125 @@
+//! Upgrades of workflow files written for an older DSL version.
+//!
+//! [`parse_yaml`](super::parse_yaml) upgrades every document it reads, so
+//! old files keep loading; `unifiedlab migrate` writes the upgraded YAML out.
+//! Steps work on the raw document, before imports and vars, so `use`,
+//! `${vars.x}` references and everything else they do not touch is kept.
+//!
+//! Version 2 made dataflow and switch conditions first-class:
+//! - An edge's `kind: !dataflow { map: M }` became `map: M` on the edge.
+//! - A switch's `params.condition`, a `LogicCondition` in its serde form
+//!   (`{ EnergyBelow: -5.0 }`), became its `condition`.
+
+use serde_yaml::{Mapping, Value as Y};
+
+use super::{DslError, SUPPORTED_DSL_VERSION};
+
+/// Upgrade a raw document written for an older version to
+/// [`SUPPORTED_DSL_VERSION`]. Returns whether it was older; an unknown
+/// version is left for the parse to report.
+pub fn upgrade(doc: &mut Y) -> Result<bool, DslError> {
+    match doc.get("version").and_then(Y::as_u64) {
+        Some(1) => migrate_v1_to_v2(doc)?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Rewrite a version 1 document as version 2.
+pub fn migrate_v1_to_v2(doc: &mut Y) -> Result<(), DslError> {
+    if let Some(Y::Sequence(edges)) = doc.get_mut("edges") {
+        for edge in edges.iter_mut() {
+            let Y::Mapping(edge) = edge else {
+                continue;
+            };
+            let map = match edge.get("kind").and_then(variant) {
+                Some((name, body)) if name == "dataflow" => body.get("map").cloned(),
+                _ => continue,
+            };
+            edge.remove("kind");
+            // A dataflow edge that hands over nothing is a hard edge
+            match map {
+                Some(Y::Mapping(map)) if map.is_empty() => {}
+                Some(map) => {
+                    edge.insert("map".into(), map);
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let Some(Y::Sequence(nodes)) = doc.get_mut("nodes") {
+        for (i, node) in nodes.iter_mut().enumerate() {
+            let Y::Mapping(node) = node else {
+                continue;
+            };
+            if node.get("type").and_then(Y::as_str) != Some("switch") {
+                continue;
+            }
+            let Some(Y::Mapping(params)) = node.get_mut("params") else {
+                continue;
+            };
+            let Some(old) = params.remove("condition") else {
+                continue;
+            };
+            if params.is_empty() {
+                node.remove("params");
+            }
+            // Version 1 tested `condition` first and ignored the param then
+            if node.contains_key("condition") {
+                continue;
+            }
+            let condition = switch_condition(&old).ok_or_else(|| {
+                DslError::validation(format!(
+                    "switch '{}': cannot migrate params.condition {}; write it as 'condition'",
+                    node.get("id").and_then(Y::as_str).unwrap_or("?"),
+                    serde_json::to_string(&old).unwrap_or_default()
+                ))
+                .at(format!("nodes.{}.params.condition", i))
+            })?;
+            if let Some(condition) = condition {
+                node.insert("condition".into(), condition);
+            }
+        }
+    }
+
+    if let Y::Mapping(top) = doc {
+        top.insert("version".into(), SUPPORTED_DSL_VERSION.into());
+    }
+    Ok(())
+}
+
+/// A `ConditionSpec` for a serialized `LogicCondition`; `Some(None)` for
+/// `AlwaysTrue`, which is a switch without a condition.
+fn switch_condition(old: &Y) -> Option<Option<Y>> {
+    if old.as_str() == Some("AlwaysTrue") {
+        return Some(None);
+    }
+    let (name, value) = variant(old)?;
+    let field = match name.as_str() {
+        "EnergyBelow" => "energy_below",
+        "BandGapAbove" => "band_gap_above",
+        "Expression" => "expression",
+        "ExternalScript" => "script",
+        _ => return None,
+    };
+    let mut condition = Mapping::new();
+    condition.insert(field.into(), value.clone());
+    Some(Some(Y::Mapping(condition)))
+}
+
+/// The name and body of an externally tagged enum value, written either as
+/// a YAML tag (`!dataflow { ... }`) or as a single-key mapping.
+fn variant(value: &Y) -> Option<(String, &Y)> {
+    match value {
+        Y::Tagged(tagged) => {
+            let tag = tagged.tag.to_string();
+            Some((tag.trim_start_matches('!').to_string(), &tagged.value))
+        }
+        Y::Mapping(m) if m.len() == 1 => {
+            let (k, v) = m.iter().next()?;
+            Some((k.as_str()?.to_string(), v))
+        }
+        _ => None,
+    }
+}
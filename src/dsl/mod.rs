@@ -10,8 +10,11 @@
 //! environments).
 //!
 //! # Notes
-//! - In this iteration we provide: YAML schema types, parsing, validation, and
-//!   deterministic macro expansion.
+//! - In this iteration we provide: YAML schema types, parsing, `${vars.x}`
+//!   substitution, validation, deterministic matrix and macro expansion,
+//!   flattening of subworkflows, linting ([`lint`]), a JSON Schema export
+//!   ([`schema`]) and upgrades of files written for an older version
+//!   ([`migrate`]).
 //! - Draw.io conversion is added in a later iteration as a separate module to
 //!   keep concerns clean and allow strict testing.
 
@@ -20,10 +23,27 @@ use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-/// DSL schema version supported by this implementation.
-pub const SUPPORTED_DSL_VERSION: u32 = 1;
+use crate::core::ResultPredicate;
+use crate::structure_io::StructureFormat;
+use crate::workflow::{ExpansionGovernor, UNTIL_PARAM};
+
+pub mod lint;
+pub mod migrate;
+pub mod schema;
+
+/// DSL schema version supported by this implementation. Files written for
+/// an older one are upgraded as they load (see [`migrate`]).
+pub const SUPPORTED_DSL_VERSION: u32 = 2;
+
+/// Values of a workflow's `vars:`, by name.
+pub type Vars = BTreeMap<String, serde_yaml::Value>;
+
+/// Joins a subworkflow node's id to the ids of the nodes it brings in, e.g.
+/// `screen/relax`.
+pub const SUBWORKFLOW_SEP: char = '/';
 
 // =============================================================================
 // Errors
@@ -36,6 +56,9 @@ pub const SUPPORTED_DSL_VERSION: u32 = 1;
 pub struct DslError {
     pub kind: DslErrorKind,
     pub context: Vec<String>,
+    /// Where in the document the problem is, as a dotted path such as
+    /// `nodes.2.retry.max_attempts`, when known.
+    pub path: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -51,6 +74,7 @@ impl DslError {
         let path = path.into();
         Self {
             kind: DslErrorKind::Io,
+            path: None,
             context: vec![format!("I/O error while reading {path}: {err}")],
         }
     }
@@ -58,6 +82,7 @@ impl DslError {
     pub fn parse(err: impl fmt::Display) -> Self {
         Self {
             kind: DslErrorKind::Parse,
+            path: None,
             context: vec![format!("Failed to parse workflow YAML: {err}")],
         }
     }
@@ -65,8 +90,9 @@ impl DslError {
     pub fn version(found: u32) -> Self {
         Self {
             kind: DslErrorKind::Version,
+            path: None,
             context: vec![format!(
-                "Unsupported DSL version: {found}. This UnifiedLab build supports version {SUPPORTED_DSL_VERSION}."
+                "Unsupported DSL version: {found}. This UnifiedLab build supports versions 1 to {SUPPORTED_DSL_VERSION}."
             )],
         }
     }
@@ -74,10 +100,17 @@ impl DslError {
     pub fn validation(msg: impl Into<String>) -> Self {
         Self {
             kind: DslErrorKind::Validation,
+            path: None,
             context: vec![msg.into()],
         }
     }
 
+    /// Locates the error at a dotted document path (see [`DslError::path`]).
+    pub fn at(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
     pub fn push_context(mut self, msg: impl Into<String>) -> Self {
         self.context.push(msg.into());
         self
@@ -105,12 +138,31 @@ impl std::error::Error for DslError {}
 // =============================================================================
 
 /// Top-level YAML document.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkflowSpec {
     pub version: u32,
     pub metadata: Metadata,
+    /// Values for `${vars.<name>}` references elsewhere in the file. Already
+    /// substituted once loaded, overrides included.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(with = "BTreeMap<String, serde_json::Value>")]
+    pub vars: Vars,
+    /// Node libraries whose `templates` nodes may `use`, relative to this
+    /// file. Already merged into the nodes once loaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imports: Vec<String>,
     #[serde(default)]
     pub environment: Option<EnvironmentSpec>,
+    /// Named resource sets that `resources: { profile: <name> }` starts
+    /// from. Already applied once loaded.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ResourceSpec>,
+    /// Resources of every node that sets none.
+    #[serde(default)]
+    pub resources: Option<ResourceSpec>,
+    /// Secrets of every node; a node's own replace these by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub secrets: BTreeMap<String, SecretSpec>,
     #[serde(default)]
     pub types: BTreeMap<String, TypeSpec>,
     pub nodes: Vec<NodeSpec>,
@@ -118,9 +170,24 @@ pub struct WorkflowSpec {
     pub edges: Vec<EdgeSpec>,
     #[serde(default)]
     pub macros: Vec<MacroSpec>,
+    /// Ports of the workflow as a whole, for use as a subworkflow.
+    #[serde(default)]
+    pub inputs: Vec<InterfacePort>,
+    #[serde(default)]
+    pub outputs: Vec<InterfacePort>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A port of a whole workflow: the nodes an input feeds, or the nodes an
+/// output is read from.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InterfacePort {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: PortTypeRef,
+    pub nodes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Metadata {
     pub name: String,
     #[serde(default)]
@@ -129,7 +196,7 @@ pub struct Metadata {
     pub authors: Vec<Author>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Author {
     pub name: String,
     #[serde(default)]
@@ -140,7 +207,7 @@ pub struct Author {
 ///
 /// This is intentionally generic: UnifiedLab can interpret this as
 /// Conda/uv, Docker, Apptainer, module-load stacks, etc.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 pub enum EnvironmentSpec {
     /// A uv-managed project directory (contains pyproject.toml + uv.lock).
@@ -153,8 +220,31 @@ pub enum EnvironmentSpec {
     Modules { modules: Vec<String> },
 }
 
+impl EnvironmentSpec {
+    /// Resolves the paths in this environment (a uv project, a local
+    /// Apptainer image) relative to the workflow file naming it.
+    pub fn relative_to(self, workflow_file: &Path) -> Self {
+        let resolve = |p: &str| {
+            resolve_relative(workflow_file, p)
+                .to_string_lossy()
+                .into_owned()
+        };
+        match self {
+            EnvironmentSpec::UvProject { path } => EnvironmentSpec::UvProject {
+                path: resolve(&path),
+            },
+            EnvironmentSpec::ApptainerImage { image } if !image.contains("://") => {
+                EnvironmentSpec::ApptainerImage {
+                    image: resolve(&image),
+                }
+            }
+            env => env,
+        }
+    }
+}
+
 /// A declared type for ports.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 pub enum TypeSpec {
     /// A file path (relative to work dir) or handle into artifact store.
@@ -174,9 +264,13 @@ pub enum TypeSpec {
 }
 
 /// A workflow node.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct NodeSpec {
     pub id: String,
+    /// The imported template this node starts from; its own fields override
+    /// the template's.
+    #[serde(default, rename = "use", skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
     #[serde(rename = "type")]
     pub node_type: NodeKind,
     #[serde(default)]
@@ -185,19 +279,91 @@ pub struct NodeSpec {
     pub engine: Option<EngineSpec>,
     #[serde(default)]
     pub params: serde_json::Value,
+    /// The file the node's jobs take their atoms from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structure: Option<StructureSpec>,
     #[serde(default)]
     pub resources: Option<ResourceSpec>,
     #[serde(default)]
     pub environment: Option<EnvironmentSpec>,
+    /// Env vars the node's programs get from secrets of the Guardian that
+    /// runs it, by var name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub secrets: BTreeMap<String, SecretSpec>,
     #[serde(default)]
     pub inputs: Vec<PortSpec>,
     #[serde(default)]
     pub outputs: Vec<PortSpec>,
     #[serde(default)]
     pub cache: Option<bool>,
+    #[serde(default)]
+    pub retry: Option<RetrySpec>,
+    /// The workflow file a subworkflow node stands for, relative to this one.
+    #[serde(default)]
+    pub workflow: Option<String>,
+    /// What a Switch node tests on its result.
+    #[serde(default)]
+    pub condition: Option<ConditionSpec>,
+    /// What a Generator node's candidates run as.
+    #[serde(default)]
+    pub batch: Option<BatchSpec>,
+    /// Param values by name; the node stands for one copy per combination of
+    /// them, gathered by an Aggregator. Already expanded once loaded.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub matrix: BTreeMap<String, Vec<serde_json::Value>>,
+}
+
+/// A Switch node's test; exactly one field is set. If the test fails, the
+/// branch below the Switch is pruned.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ConditionSpec {
+    /// Passes if the result's energy is below this (eV).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy_below: Option<f64>,
+    /// Passes if the result's `band_gap` is above this (eV).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub band_gap_above: Option<f64>,
+    /// A test like an edge's `when`, e.g. `energy < -5.0`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+    /// A script deciding on the result, relative to the workflow file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+}
+
+/// A structure file, relative to the workflow file.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StructureSpec {
+    pub file: String,
+    /// The file's format, if its name does not tell (see
+    /// [`StructureFormat::from_path`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<StructureFormat>,
+}
+
+/// Where a secret is read on the Guardian that runs the node; exactly one
+/// field is set. Only this reference is stored, never the value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SecretSpec {
+    /// An env var of the Guardian's process.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// An entry of the OS keyring under the service `unifiedlab`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyring: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// The job each candidate of a Generator node becomes, with the candidate
+/// under `params.candidate`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct BatchSpec {
+    #[serde(default)]
+    pub engine: Option<EngineSpec>,
+    #[serde(default)]
+    pub params: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum NodeKind {
     Compute,
@@ -210,7 +376,7 @@ pub enum NodeKind {
 }
 
 /// Engine configuration at DSL level.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 pub enum EngineSpec {
     Janus,
@@ -226,20 +392,47 @@ pub enum EngineSpec {
 }
 
 /// Resource requirements for a node.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ResourceSpec {
+    /// The profile these resources start from; fields set here override it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
     #[serde(default = "default_one")]
     pub nodes: u32,
     #[serde(default = "default_one")]
     pub cores: u32,
     #[serde(default)]
     pub gpus: u32,
+    /// Memory the node's job needs on its worker, in MB.
+    #[serde(default)]
+    pub memory_mb: u64,
     #[serde(default = "default_time_limit")]
     pub time_limit_min: u64,
     #[serde(default)]
     pub required_tags: Vec<String>,
+    #[serde(default)]
+    pub prefer_tags: Vec<String>,
+    #[serde(default)]
+    pub avoid_tags: Vec<String>,
+    /// Id of another node whose host this one must run on.
+    #[serde(default)]
+    pub same_node_as: Option<String>,
+}
+
+/// Retry policy for a node's failed runs.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RetrySpec {
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default)]
+    pub backoff_s: f64,
+    #[serde(default)]
+    pub retry_on: Vec<String>,
 }
 
+fn default_max_attempts() -> u32 {
+    3
+}
 fn default_one() -> u32 {
     1
 }
@@ -248,7 +441,7 @@ fn default_time_limit() -> u64 {
 }
 
 /// A typed port.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PortSpec {
     pub name: String,
     #[serde(rename = "type")]
@@ -259,7 +452,7 @@ pub struct PortSpec {
 }
 
 /// References either an inline type or a named type.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum PortTypeRef {
     Named(String),
@@ -267,26 +460,29 @@ pub enum PortTypeRef {
 }
 
 /// An edge (dependency) between nodes.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EdgeSpec {
     pub from: String,
     pub to: String,
     #[serde(default)]
     pub kind: EdgeKind,
+    /// Makes this a dataflow edge, which also hands over values: takes a
+    /// param of the child to a field of the parent's result, e.g.
+    /// `{ start_energy: energy }`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub map: BTreeMap<String, String>,
+    /// Run `to` only if `from`'s result passes this test, e.g. `energy < -5.0`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EdgeKind {
     #[serde(alias = "hard")]
     Hard,
     #[serde(alias = "soft")]
     Soft,
-    /// A dataflow edge with optional parameter mappings.
-    Dataflow {
-        #[serde(default)]
-        map: BTreeMap<String, String>,
-    },
 }
 
 impl Default for EdgeKind {
@@ -300,7 +496,7 @@ impl Default for EdgeKind {
 // =============================================================================
 
 /// High-level graph generator.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MacroSpec {
     pub id: String,
     #[serde(rename = "type")]
@@ -313,13 +509,30 @@ pub struct MacroSpec {
     pub params: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum MacroKind {
     /// Create a chain of N nodes.
     Chain,
     /// Create fanout of N parallel nodes from an anchor.
     Fanout,
+    /// Map each entry of a list onto a node of its own, then gather their
+    /// results in one Aggregator.
+    MapReduce,
+    /// A Generator whose batches repeat until their feedback passes a test,
+    /// at most a given number of times.
+    ConvergenceLoop,
+}
+
+/// A file of node templates and resource profiles for workflows to import.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeLibrary {
+    /// Partial nodes by name.
+    #[serde(default)]
+    templates: BTreeMap<String, serde_yaml::Value>,
+    #[serde(default)]
+    profiles: BTreeMap<String, serde_yaml::Value>,
 }
 
 /// Result of expanding macros into concrete nodes/edges.
@@ -334,115 +547,693 @@ pub struct ExpandedWorkflow {
 // Public API
 // =============================================================================
 
-/// Load and parse a YAML workflow file.
+/// Load and parse a YAML workflow file, substituting its `${vars.<name>}`
+/// references. `overrides` replace the defaults declared under `vars:`.
 ///
 /// This does **not** perform macro expansion; call [`expand_macros`] after.
-pub fn load_yaml(path: impl AsRef<Path>) -> Result<WorkflowSpec, DslError> {
+pub fn load_yaml(path: impl AsRef<Path>, overrides: &Vars) -> Result<WorkflowSpec, DslError> {
     let path = path.as_ref();
     let raw = fs::read_to_string(path).map_err(|e| DslError::io(e, path.display().to_string()))?;
 
-    let spec: WorkflowSpec = serde_yaml::from_str(&raw).map_err(DslError::parse)?;
+    let spec = parse_yaml(&raw, overrides, path)
+        .map_err(|e| e.push_context(format!("in file: {}", path.display())))?;
+    validate(&spec).map_err(|e| e.push_context(format!("in file: {}", path.display())))?;
+    Ok(spec)
+}
+
+/// Parse a workflow document, merge in its imports and substitute its vars,
+/// without validating it. `workflow_file` is where `raw` was read from.
+pub fn parse_yaml(
+    raw: &str,
+    overrides: &Vars,
+    workflow_file: &Path,
+) -> Result<WorkflowSpec, DslError> {
+    let mut doc: serde_yaml::Value = serde_yaml::from_str(raw).map_err(DslError::parse)?;
+    // Error paths below come from text; an old file's is its upgraded form
+    let upgraded_text;
+    let raw = if migrate::upgrade(&mut doc)? {
+        upgraded_text = serde_yaml::to_string(&doc).map_err(DslError::parse)?;
+        upgraded_text.as_str()
+    } else {
+        raw
+    };
+    let imported = resolve_imports(&mut doc, workflow_file)?;
+    resolve_profiles(&mut doc)?;
+    substitute_vars(&mut doc, overrides)?;
+    let spec: WorkflowSpec = serde_yaml::from_value(doc).map_err(|e| {
+        // Only errors from text name the field, e.g. `nodes[2].retry: invalid
+        // type`; reparse the unsubstituted text for one where we can. Not
+        // once templates are merged in: the text alone may lack their fields.
+        let msg = Some(raw)
+            .filter(|_| !imported)
+            .and_then(|raw| serde_yaml::from_str::<WorkflowSpec>(raw).err())
+            .unwrap_or(e)
+            .to_string();
+        let err = DslError::parse(&msg);
+        match msg.split_once(": ") {
+            Some((field, _)) if !field.contains(' ') => {
+                err.at(field.replace('[', ".").replace(']', ""))
+            }
+            _ => err,
+        }
+    })?;
 
     if spec.version != SUPPORTED_DSL_VERSION {
-        return Err(DslError::version(spec.version));
+        return Err(DslError::version(spec.version).at("version"));
     }
-
-    validate(&spec).map_err(|e| e.push_context(format!("in file: {}", path.display())))?;
     Ok(spec)
 }
 
+/// Merge the templates of a raw document's `imports:` into the nodes that
+/// `use` them, before vars are substituted, so templates may refer to the
+/// workflow's vars. A node's own fields win: mappings are merged key by key,
+/// except that one with another `kind` (an engine, an environment) replaces
+/// the template's; anything else is replaced whole. Returns whether any node
+/// used a template.
+fn resolve_imports(doc: &mut serde_yaml::Value, workflow_file: &Path) -> Result<bool, DslError> {
+    use serde_yaml::Value as Y;
+
+    // Template or profile name -> (definition, the import it came from)
+    let mut templates: BTreeMap<String, (Y, String)> = BTreeMap::new();
+    let mut profiles: BTreeMap<String, (Y, String)> = BTreeMap::new();
+    let imports = match doc.get("imports") {
+        Some(Y::Sequence(imports)) => imports.clone(),
+        _ => Vec::new(), // A malformed list is reported by the parse
+    };
+    for (k, import) in imports.iter().enumerate() {
+        let Some(rel) = import.as_str() else {
+            continue;
+        };
+        let at = format!("imports.{}", k);
+        let file = resolve_relative(workflow_file, rel);
+        let raw = fs::read_to_string(&file)
+            .map_err(|e| DslError::io(e, file.display().to_string()).at(&at))?;
+        let library: NodeLibrary = serde_yaml::from_str(&raw).map_err(|e| {
+            DslError::parse(e)
+                .push_context(format!("in import: {}", file.display()))
+                .at(&at)
+        })?;
+        for (name, template) in library.templates {
+            if !template.is_mapping() {
+                return Err(DslError::validation(format!(
+                    "template '{}' in '{}' must be a mapping of node fields",
+                    name, rel
+                ))
+                .at(&at));
+            }
+            if let Some((_, first)) = templates.get(&name) {
+                return Err(DslError::validation(format!(
+                    "template '{}' is defined by both '{}' and '{}'",
+                    name, first, rel
+                ))
+                .at(&at));
+            }
+            templates.insert(name, (template, rel.to_string()));
+        }
+        for (name, profile) in library.profiles {
+            if let Some((_, first)) = profiles.get(&name) {
+                return Err(DslError::validation(format!(
+                    "profile '{}' is defined by both '{}' and '{}'",
+                    name, first, rel
+                ))
+                .at(&at));
+            }
+            profiles.insert(name, (profile, rel.to_string()));
+        }
+    }
+
+    // The workflow's own profiles win over imported ones
+    if !profiles.is_empty() {
+        if let Y::Mapping(top) = &mut *doc {
+            let own = top
+                .entry("profiles".into())
+                .or_insert_with(|| Y::Mapping(Default::default()));
+            if let Y::Mapping(own) = own {
+                for (name, (profile, _)) in profiles {
+                    own.entry(name.into()).or_insert(profile);
+                }
+            }
+        }
+    }
+
+    let mut used = false;
+    let Some(Y::Sequence(nodes)) = doc.get_mut("nodes") else {
+        return Ok(used);
+    };
+    for (i, node) in nodes.iter_mut().enumerate() {
+        let Some(name) = node.get("use").and_then(Y::as_str) else {
+            continue;
+        };
+        let Some((template, _)) = templates.get(name) else {
+            let known: Vec<&str> = templates.keys().map(String::as_str).collect();
+            return Err(DslError::validation(format!(
+                "node '{}' uses unknown template '{}' (imported: {})",
+                node.get("id").and_then(Y::as_str).unwrap_or_default(),
+                name,
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            ))
+            .at(format!("nodes.{}.use", i)));
+        };
+        let mut merged = template.clone();
+        merge_over(&mut merged, std::mem::take(node));
+        *node = merged;
+        used = true;
+    }
+    Ok(used)
+}
+
+/// Lay every `resources: { profile: <name>, ... }` of a raw document, the
+/// workflow's own and its nodes', over the named entry of `profiles:`, in
+/// the way [`resolve_imports`] lays nodes over templates.
+fn resolve_profiles(doc: &mut serde_yaml::Value) -> Result<(), DslError> {
+    use serde_yaml::Value as Y;
+
+    let profiles = match doc.get("profiles") {
+        Some(Y::Mapping(profiles)) => profiles.clone(),
+        _ => Default::default(), // A malformed map is reported by the parse
+    };
+    let apply = |resources: &mut Y, at: String| -> Result<(), DslError> {
+        let Some(name) = resources.get("profile").and_then(Y::as_str) else {
+            return Ok(());
+        };
+        let Some(profile) = profiles.get(name) else {
+            let known: Vec<&str> = profiles.keys().filter_map(Y::as_str).collect();
+            return Err(DslError::validation(format!(
+                "unknown resource profile '{}' (defined: {})",
+                name,
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            ))
+            .at(format!("{}.profile", at)));
+        };
+        let mut merged = profile.clone();
+        merge_over(&mut merged, std::mem::take(resources));
+        *resources = merged;
+        Ok(())
+    };
+
+    if let Some(resources) = doc.get_mut("resources") {
+        apply(resources, "resources".into())?;
+    }
+    if let Some(Y::Sequence(nodes)) = doc.get_mut("nodes") {
+        for (i, node) in nodes.iter_mut().enumerate() {
+            if let Some(resources) = node.get_mut("resources") {
+                apply(resources, format!("nodes.{}.resources", i))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Lays `over` on top of `base` (see [`resolve_imports`]).
+fn merge_over(base: &mut serde_yaml::Value, over: serde_yaml::Value) {
+    use serde_yaml::Value as Y;
+    match (base, over) {
+        (Y::Mapping(base), Y::Mapping(over))
+            if over.get("kind").is_none() || base.get("kind") == over.get("kind") =>
+        {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(slot) => merge_over(slot, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+/// Replace `${vars.<name>}` references in every string of a raw document,
+/// except under `vars:` itself. A string that is one reference takes the
+/// var's value as is (a number stays a number); a reference inside longer
+/// text is spliced in and must be a scalar.
+fn substitute_vars(doc: &mut serde_yaml::Value, overrides: &Vars) -> Result<(), DslError> {
+    let mut vars: Vars = match doc.get("vars") {
+        Some(v) => serde_yaml::from_value(v.clone()).map_err(|e| {
+            DslError::validation(format!("vars must map names to values: {}", e)).at("vars")
+        })?,
+        None => Vars::new(),
+    };
+    for (name, value) in overrides {
+        if !vars.contains_key(name) {
+            return Err(DslError::validation(format!(
+                "cannot set var '{}': the workflow does not declare it under vars",
+                name
+            )));
+        }
+        vars.insert(name.clone(), value.clone());
+    }
+
+    if let serde_yaml::Value::Mapping(top) = doc {
+        for (key, value) in top.iter_mut() {
+            match key.as_str() {
+                Some("vars") => {}
+                Some(key) => substitute_in(value, &vars, key)?,
+                None => substitute_in(value, &vars, "")?,
+            }
+        }
+        if !vars.is_empty() {
+            top.insert(
+                "vars".into(),
+                serde_yaml::to_value(&vars).map_err(DslError::parse)?,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `path` is where `value` sits in the document, for error locations.
+fn substitute_in(value: &mut serde_yaml::Value, vars: &Vars, path: &str) -> Result<(), DslError> {
+    use serde_yaml::Value as Y;
+    match value {
+        Y::Mapping(m) => m.iter_mut().try_for_each(|(k, v)| {
+            let key = k.as_str().map(str::to_string).unwrap_or_default();
+            substitute_in(v, vars, &format!("{}.{}", path, key))
+        }),
+        Y::Sequence(s) => s
+            .iter_mut()
+            .enumerate()
+            .try_for_each(|(i, v)| substitute_in(v, vars, &format!("{}.{}", path, i))),
+        Y::Tagged(t) => substitute_in(&mut t.value, vars, path),
+        Y::String(text) if text.contains("${vars.") => {
+            let lookup = |name: &str| {
+                vars.get(name).ok_or_else(|| {
+                    DslError::validation(format!("unknown var '{}'; declare it under vars", name))
+                        .at(path)
+                })
+            };
+            if let Some(name) = text
+                .strip_prefix("${vars.")
+                .and_then(|rest| rest.strip_suffix('}'))
+                .filter(|name| !name.contains('}'))
+            {
+                *value = lookup(name)?.clone();
+                return Ok(());
+            }
+            let mut out = String::new();
+            let mut rest = text.as_str();
+            while let Some(at) = rest.find("${vars.") {
+                out.push_str(&rest[..at]);
+                let after = &rest[at + "${vars.".len()..];
+                let end = after.find('}').ok_or_else(|| {
+                    DslError::validation(format!("unclosed var reference in '{}'", text)).at(path)
+                })?;
+                match lookup(&after[..end])? {
+                    Y::String(s) => out.push_str(s),
+                    Y::Number(n) => out.push_str(&n.to_string()),
+                    Y::Bool(b) => out.push_str(&b.to_string()),
+                    _ => {
+                        return Err(DslError::validation(format!(
+                            "var '{}' is not a scalar and cannot be spliced into '{}'",
+                            &after[..end],
+                            text
+                        ))
+                        .at(path))
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            out.push_str(rest);
+            *text = out;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Validate a workflow spec (IDs, references, types).
 ///
 /// This is intentionally strict: we prefer failing fast with actionable errors
 /// rather than letting malformed workflows reach the scheduler.
 pub fn validate(spec: &WorkflowSpec) -> Result<(), DslError> {
+    match validation_errors(spec).into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Every problem [`validate`] would report, in document order by section,
+/// each with the path of the offending field.
+pub fn validation_errors(spec: &WorkflowSpec) -> Vec<DslError> {
+    let mut errors = Vec::new();
+    let mut fail = |path: String, msg: String| errors.push(DslError::validation(msg).at(path));
+
     if spec.metadata.name.trim().is_empty() {
-        return Err(DslError::validation("metadata.name must not be empty"));
+        fail(
+            "metadata.name".into(),
+            "metadata.name must not be empty".into(),
+        );
     }
     if spec.nodes.is_empty() {
-        return Err(DslError::validation(
-            "workflow must contain at least one node",
-        ));
+        fail(
+            "nodes".into(),
+            "workflow must contain at least one node".into(),
+        );
+    }
+    for (var, secret) in &spec.secrets {
+        if let Err(msg) = validate_secret(var, secret) {
+            fail(
+                format!("secrets.{}", var),
+                format!("secret '{}': {}", var, msg),
+            );
+        }
     }
 
     // Node ID uniqueness.
     let mut ids = HashSet::new();
-    for n in &spec.nodes {
+    for (i, n) in spec.nodes.iter().enumerate() {
+        let at = |field: &str| format!("nodes.{}.{}", i, field);
         if n.id.trim().is_empty() {
-            return Err(DslError::validation("node.id must not be empty"));
+            fail(at("id"), "node.id must not be empty".into());
+        } else if !ids.insert(n.id.clone()) {
+            fail(at("id"), format!("duplicate node id: '{}'", n.id));
         }
-        if !ids.insert(n.id.clone()) {
-            return Err(DslError::validation(format!(
-                "duplicate node id: '{}'",
-                n.id
-            )));
+        if n.node_type == NodeKind::Generator {
+            if let Err(msg) = ExpansionGovernor::from_params(&n.params) {
+                fail(at("params"), format!("node '{}': {}", n.id, msg));
+            }
+            if let Some(until) = n.params.get(UNTIL_PARAM) {
+                if let Err(e) = serde_json::from_value::<ResultPredicate>(until.clone()) {
+                    fail(at("params.until"), format!("node '{}' until: {}", n.id, e));
+                }
+            }
+        } else if n.batch.is_some() {
+            fail(
+                at("batch"),
+                format!("node '{}' sets 'batch' but is not a generator", n.id),
+            );
+        }
+        match (&n.node_type, &n.workflow) {
+            (NodeKind::Subworkflow, None) => fail(
+                at("type"),
+                format!("subworkflow node '{}' must name its 'workflow' file", n.id),
+            ),
+            (NodeKind::Subworkflow, Some(_)) | (_, None) => {}
+            (_, Some(_)) => fail(
+                at("workflow"),
+                format!("node '{}' sets 'workflow' but is not a subworkflow", n.id),
+            ),
+        }
+        if n.node_type == NodeKind::Switch && n.params.get("condition").is_some() {
+            fail(
+                at("params.condition"),
+                format!(
+                    "switch '{}' sets params.condition, which version {} no longer reads; set 'condition'",
+                    n.id, SUPPORTED_DSL_VERSION
+                ),
+            );
+        }
+        if let Some(c) = &n.condition {
+            if let Err(msg) = validate_condition(c, n) {
+                fail(
+                    at("condition"),
+                    format!("node '{}' condition: {}", n.id, msg),
+                );
+            }
+        }
+        if let Some(st) = &n.structure {
+            if st.file.trim().is_empty() {
+                fail(
+                    at("structure.file"),
+                    format!("node '{}' structure.file must not be empty", n.id),
+                );
+            } else if st.format.is_none()
+                && StructureFormat::from_path(Path::new(&st.file)).is_none()
+            {
+                fail(
+                    at("structure.file"),
+                    format!(
+                        "node '{}': cannot tell the format of '{}'; set structure.format to cif, poscar or xyz",
+                        n.id, st.file
+                    ),
+                );
+            }
+        }
+        for (axis, values) in &n.matrix {
+            if axis.trim().is_empty() {
+                fail(
+                    at("matrix"),
+                    format!("node '{}' matrix axis names must not be empty", n.id),
+                );
+            } else if values.is_empty() {
+                fail(
+                    at(&format!("matrix.{}", axis)),
+                    format!(
+                        "node '{}' matrix axis '{}' must list at least one value",
+                        n.id, axis
+                    ),
+                );
+            }
+        }
+        for (var, secret) in &n.secrets {
+            if let Err(msg) = validate_secret(var, secret) {
+                fail(
+                    at(&format!("secrets.{}", var)),
+                    format!("node '{}' secret '{}': {}", n.id, var, msg),
+                );
+            }
         }
     }
+    let nodes = node_map(spec);
 
-    // Validate edges reference known nodes.
-    for e in &spec.edges {
-        if !ids.contains(&e.from) {
-            return Err(DslError::validation(format!(
-                "edge.from references unknown node: '{}'",
-                e.from
-            )));
+    // Validate edges reference known nodes and ports, of matching types.
+    for (i, e) in spec.edges.iter().enumerate() {
+        let at = |field: &str| format!("edges.{}.{}", i, field);
+        let from = edge_node(&e.from, "outputs", &nodes);
+        if from.is_none() {
+            fail(
+                at("from"),
+                unknown_end("edge.from", &e.from, "outputs", &nodes),
+            );
         }
-        if !ids.contains(&e.to) {
-            return Err(DslError::validation(format!(
-                "edge.to references unknown node: '{}'",
-                e.to
-            )));
+        let to = edge_node(&e.to, "inputs", &nodes);
+        if to.is_none() {
+            fail(at("to"), unknown_end("edge.to", &e.to, "inputs", &nodes));
         }
-        if e.from == e.to {
-            return Err(DslError::validation(format!(
-                "self-edge is not allowed: '{}' -> '{}'",
-                e.from, e.to
-            )));
+        if let (Some(out), Some(inp)) = (
+            declared_port(&e.from, "outputs", &nodes),
+            declared_port(&e.to, "inputs", &nodes),
+        ) {
+            if let Err(msg) = check_flow(&spec.types, out, inp) {
+                fail(
+                    at("to"),
+                    format!("edge '{}' -> '{}': {}", e.from, e.to, msg),
+                );
+            }
+        }
+        if from.is_some() && from == to {
+            fail(
+                at("to"),
+                format!("self-edge is not allowed: '{}' -> '{}'", e.from, e.to),
+            );
+        }
+        if matches!(e.kind, EdgeKind::Soft) && !e.map.is_empty() {
+            fail(
+                at("map"),
+                format!(
+                    "soft edge '{}' -> '{}' cannot hand over values",
+                    e.from, e.to
+                ),
+            );
+        } else if e
+            .map
+            .iter()
+            .any(|(param, field)| param.is_empty() || field.is_empty())
+        {
+            fail(
+                at("map"),
+                format!(
+                    "dataflow edge '{}' -> '{}' maps an empty param or field",
+                    e.from, e.to
+                ),
+            );
+        }
+        if let Some(when) = &e.when {
+            if matches!(e.kind, EdgeKind::Soft) {
+                fail(
+                    at("when"),
+                    format!(
+                        "soft edge '{}' -> '{}' cannot have a 'when' condition",
+                        e.from, e.to
+                    ),
+                );
+            } else if let Err(msg) = when.parse::<ResultPredicate>() {
+                fail(
+                    at("when"),
+                    format!("edge '{}' -> '{}' when: {}", e.from, e.to, msg),
+                );
+            }
         }
     }
 
-    // Validate port type refs: named types must exist.
-    for n in &spec.nodes {
-        for p in n.inputs.iter().chain(n.outputs.iter()) {
+    if spec
+        .resources
+        .as_ref()
+        .is_some_and(|r| r.same_node_as.is_some())
+    {
+        fail(
+            "resources.same_node_as".into(),
+            "resources.same_node_as applies to a node, not to the whole workflow".into(),
+        );
+    }
+    for (i, n) in spec.nodes.iter().enumerate() {
+        let at = |field: &str| format!("nodes.{}.{}", i, field);
+
+        // Validate co-location targets.
+        if let Some(target) = n.resources.as_ref().and_then(|r| r.same_node_as.as_ref()) {
+            if !ids.contains(target) || *target == n.id {
+                fail(
+                    at("resources.same_node_as"),
+                    format!(
+                        "node '{}' resources.same_node_as must name another node: '{}'",
+                        n.id, target
+                    ),
+                );
+            }
+        }
+
+        // Validate retry policies.
+        if let Some(r) = &n.retry {
+            if r.max_attempts == 0 {
+                fail(
+                    at("retry.max_attempts"),
+                    format!("node '{}' retry.max_attempts must be at least 1", n.id),
+                );
+            }
+            if !(r.backoff_s.is_finite() && r.backoff_s >= 0.0) {
+                fail(
+                    at("retry.backoff_s"),
+                    format!(
+                        "node '{}' retry.backoff_s must be a non-negative number",
+                        n.id
+                    ),
+                );
+            }
+        }
+
+        // Validate port sources that name a declared output.
+        for (j, p) in n.inputs.iter().enumerate() {
+            let Some(source) = &p.source else { continue };
+            if let Some(out) = declared_port(source, "outputs", &nodes) {
+                if let Err(msg) = check_flow(&spec.types, out, p) {
+                    fail(
+                        at(&format!("inputs.{}.source", j)),
+                        format!(
+                            "node '{}' port '{}' source '{}': {}",
+                            n.id, p.name, source, msg
+                        ),
+                    );
+                }
+            }
+        }
+
+        // Validate port type refs: named types must exist.
+        for (dir, ports) in [("inputs", &n.inputs), ("outputs", &n.outputs)] {
+            for (j, p) in ports.iter().enumerate() {
+                if let PortTypeRef::Named(name) = &p.ty {
+                    if !spec.types.contains_key(name) {
+                        fail(
+                            at(&format!("{}.{}.type", dir, j)),
+                            format!(
+                                "node '{}' port '{}' references unknown type '{}'",
+                                n.id, p.name, name
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Validate the workflow's own ports.
+    for (dir, ports) in [("inputs", &spec.inputs), ("outputs", &spec.outputs)] {
+        let mut names = HashSet::new();
+        for (j, p) in ports.iter().enumerate() {
+            let at = |field: &str| format!("{}.{}.{}", dir, j, field);
+            if !names.insert(&p.name) {
+                fail(
+                    at("name"),
+                    format!("duplicate workflow {} port: '{}'", dir, p.name),
+                );
+            }
+            if p.nodes.is_empty() {
+                fail(
+                    at("nodes"),
+                    format!(
+                        "workflow {} port '{}' must name at least one node",
+                        dir, p.name
+                    ),
+                );
+            }
+            if let Some(unknown) = p.nodes.iter().find(|id| !ids.contains(*id)) {
+                fail(
+                    at("nodes"),
+                    format!(
+                        "workflow {} port '{}' references unknown node '{}'",
+                        dir, p.name, unknown
+                    ),
+                );
+            }
             if let PortTypeRef::Named(name) = &p.ty {
                 if !spec.types.contains_key(name) {
-                    return Err(DslError::validation(format!(
-                        "node '{}' port '{}' references unknown type '{}'",
-                        n.id, p.name, name
-                    )));
+                    fail(
+                        at("type"),
+                        format!(
+                            "workflow {} port '{}' references unknown type '{}'",
+                            dir, p.name, name
+                        ),
+                    );
                 }
             }
         }
     }
 
     // Validate macro anchors.
-    for m in &spec.macros {
+    for (i, m) in spec.macros.iter().enumerate() {
         if m.id.trim().is_empty() {
-            return Err(DslError::validation("macro.id must not be empty"));
+            fail(
+                format!("macros.{}.id", i),
+                "macro.id must not be empty".into(),
+            );
         }
         if let Some(anchor) = &m.anchor {
             if !ids.contains(anchor) {
-                return Err(DslError::validation(format!(
-                    "macro '{}' references unknown anchor node '{}'",
-                    m.id, anchor
-                )));
+                fail(
+                    format!("macros.{}.anchor", i),
+                    format!(
+                        "macro '{}' references unknown anchor node '{}'",
+                        m.id, anchor
+                    ),
+                );
             }
         }
     }
 
-    Ok(())
+    errors
 }
 
-/// Expand macros into concrete nodes/edges.
+/// Expand node matrices and macros into concrete nodes/edges.
 ///
 /// Macro expansion is deterministic and VCS-friendly: generated node IDs are stable.
+/// Matrices are expanded first (see [`expand_matrices`]), so macros may
+/// anchor on a matrix node's Aggregator.
 pub fn expand_macros(spec: &WorkflowSpec) -> Result<ExpandedWorkflow, DslError> {
     let mut out = spec.clone();
     let mut macro_map: HashMap<String, Vec<String>> = HashMap::new();
 
     let mut existing: HashSet<String> = out.nodes.iter().map(|n| n.id.clone()).collect();
+    expand_matrices(&mut out, &mut existing)?;
 
-    for m in &spec.macros {
+    for (i, m) in spec.macros.iter().enumerate() {
         match m.macro_type {
             MacroKind::Chain => {
                 let len = m.params.get("length").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
@@ -457,35 +1248,19 @@ pub fn expand_macros(spec: &WorkflowSpec) -> Result<ExpandedWorkflow, DslError>
 
                 let mut created = Vec::new();
                 let mut prev = anchor;
-                for i in 0..len {
-                    let id = format!("{}_{}", m.id, i + 1);
-                    if existing.contains(&id) {
-                        return Err(DslError::validation(format!(
-                            "macro '{}' would create duplicate node id '{}'",
-                            m.id, id
-                        )));
-                    }
-                    existing.insert(id.clone());
-
-                    let node = NodeSpec {
-                        id: id.clone(),
-                        node_type: NodeKind::Compute,
-                        title: Some(format!("{} step {}", m.id, i + 1)),
-                        engine: Some(parse_engine(engine)),
-                        params: serde_json::Value::Object(serde_json::Map::new()),
-                        resources: None,
-                        environment: None,
-                        inputs: Vec::new(),
-                        outputs: Vec::new(),
-                        cache: None,
-                    };
-                    out.nodes.push(node);
+                for k in 0..len {
+                    let id = format!("{}_{}", m.id, k + 1);
+                    claim_id(&mut existing, m, &id)?;
+                    out.nodes
+                        .push(macro_node(&id, format!("{} step {}", m.id, k + 1), engine));
 
                     if let Some(p) = prev.clone() {
                         out.edges.push(EdgeSpec {
                             from: p,
                             to: id.clone(),
                             kind: EdgeKind::Hard,
+                            map: BTreeMap::new(),
+                            when: None,
                         });
                     }
                     prev = Some(id.clone());
@@ -505,40 +1280,146 @@ pub fn expand_macros(spec: &WorkflowSpec) -> Result<ExpandedWorkflow, DslError>
                 })?;
 
                 let mut created = Vec::new();
-                for i in 0..width {
-                    let id = format!("{}_{}", m.id, i + 1);
-                    if existing.contains(&id) {
-                        return Err(DslError::validation(format!(
-                            "macro '{}' would create duplicate node id '{}'",
-                            m.id, id
-                        )));
-                    }
-                    existing.insert(id.clone());
-
-                    let node = NodeSpec {
-                        id: id.clone(),
-                        node_type: NodeKind::Compute,
-                        title: Some(format!("{} task {}", m.id, i + 1)),
-                        engine: Some(parse_engine(engine)),
-                        params: serde_json::Value::Object(serde_json::Map::new()),
-                        resources: None,
-                        environment: None,
-                        inputs: Vec::new(),
-                        outputs: Vec::new(),
-                        cache: None,
-                    };
-                    out.nodes.push(node);
+                for k in 0..width {
+                    let id = format!("{}_{}", m.id, k + 1);
+                    claim_id(&mut existing, m, &id)?;
+                    out.nodes
+                        .push(macro_node(&id, format!("{} task {}", m.id, k + 1), engine));
 
                     out.edges.push(EdgeSpec {
                         from: anchor.clone(),
                         to: id.clone(),
                         kind: EdgeKind::Hard,
+                        map: BTreeMap::new(),
+                        when: None,
                     });
 
                     created.push(id);
                 }
                 macro_map.insert(m.id.clone(), created);
             }
+            MacroKind::MapReduce => {
+                let items = match m.params.get("items") {
+                    Some(serde_json::Value::Array(items)) if !items.is_empty() => items,
+                    _ => {
+                        return Err(DslError::validation(format!(
+                            "macro '{}' map_reduce requires a non-empty 'items' list",
+                            m.id
+                        ))
+                        .at(format!("macros.{}.params.items", i)))
+                    }
+                };
+                let str_param = |key: &str, default: &'static str| {
+                    m.params
+                        .get(key)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(default)
+                        .to_string()
+                };
+                let param = str_param("as", "item");
+                let engine = str_param("engine", "janus");
+                let field = str_param("collect", "energy");
+                let reduce_id = format!("{}_reduce", m.id);
+
+                let mut created = Vec::new();
+                for (k, item) in items.iter().enumerate() {
+                    let id = format!("{}_{}", m.id, k + 1);
+                    claim_id(&mut existing, m, &id)?;
+                    let mut node = macro_node(&id, format!("{} item {}", m.id, k + 1), &engine);
+                    node.params = serde_json::json!({ param.as_str(): item });
+                    out.nodes.push(node);
+
+                    if let Some(anchor) = &m.anchor {
+                        out.edges.push(EdgeSpec {
+                            from: anchor.clone(),
+                            to: id.clone(),
+                            kind: EdgeKind::Hard,
+                            map: BTreeMap::new(),
+                            when: None,
+                        });
+                    }
+                    // The reducer gets each result's field as `<field>_<k>`
+                    out.edges.push(EdgeSpec {
+                        from: id.clone(),
+                        to: reduce_id.clone(),
+                        kind: EdgeKind::Hard,
+                        map: BTreeMap::from([(format!("{}_{}", field, k + 1), field.clone())]),
+                        when: None,
+                    });
+                    created.push(id);
+                }
+
+                claim_id(&mut existing, m, &reduce_id)?;
+                let mut reduce = macro_node(&reduce_id, format!("{} reduce", m.id), "agent");
+                reduce.node_type = NodeKind::Aggregator;
+                if let Some(script) = m.params.get("reduce").and_then(|v| v.as_str()) {
+                    reduce.engine = Some(EngineSpec::Agent {
+                        script: script.to_string(),
+                        strategy: None,
+                    });
+                }
+                reduce.params = serde_json::json!({ "items": items });
+                out.nodes.push(reduce);
+                created.push(reduce_id);
+                macro_map.insert(m.id.clone(), created);
+            }
+            MacroKind::ConvergenceLoop => {
+                let at = |key: &str| format!("macros.{}.params.{}", i, key);
+                let max_iters = match m.params.get("max_iters").and_then(|v| v.as_u64()) {
+                    Some(n) if n > 0 => n,
+                    _ => {
+                        return Err(DslError::validation(format!(
+                            "macro '{}' convergence_loop requires 'max_iters' of at least 1",
+                            m.id
+                        ))
+                        .at(at("max_iters")))
+                    }
+                };
+                let until = m.params.get("until").cloned().ok_or_else(|| {
+                    DslError::validation(format!(
+                        "macro '{}' convergence_loop requires 'until', e.g. 'energy.min < -5.0'",
+                        m.id
+                    ))
+                    .at(format!("macros.{}.params", i))
+                })?;
+                if let Err(e) = serde_json::from_value::<ResultPredicate>(until.clone()) {
+                    return Err(
+                        DslError::validation(format!("macro '{}' until: {}", m.id, e))
+                            .at(at("until")),
+                    );
+                }
+                let str_param = |key: &str| m.params.get(key).and_then(|v| v.as_str());
+
+                let id = format!("{}_generate", m.id);
+                claim_id(&mut existing, m, &id)?;
+                let mut node = macro_node(&id, format!("{} loop", m.id), "agent");
+                node.node_type = NodeKind::Generator;
+                node.engine = Some(EngineSpec::Agent {
+                    script: str_param("script").unwrap_or(AGENT_SHIM).to_string(),
+                    strategy: str_param("strategy").map(str::to_string),
+                });
+                // Generation 0 is the first iteration
+                node.params = serde_json::json!({
+                    "gen_limit": max_iters - 1,
+                    UNTIL_PARAM: until,
+                });
+                node.batch = Some(BatchSpec {
+                    engine: Some(parse_engine(str_param("engine").unwrap_or("janus"))),
+                    params: m.params.get("compute").cloned().unwrap_or_default(),
+                });
+                out.nodes.push(node);
+
+                if let Some(anchor) = &m.anchor {
+                    out.edges.push(EdgeSpec {
+                        from: anchor.clone(),
+                        to: id.clone(),
+                        kind: EdgeKind::Hard,
+                        map: BTreeMap::new(),
+                        when: None,
+                    });
+                }
+                macro_map.insert(m.id.clone(), vec![id]);
+            }
         }
     }
 
@@ -549,6 +1430,623 @@ pub fn expand_macros(spec: &WorkflowSpec) -> Result<ExpandedWorkflow, DslError>
     })
 }
 
+/// Replace each node with a `matrix` by one node per combination of its
+/// axes, with the axes' values set in its params, and an Aggregator below
+/// them that takes over the node's id.
+///
+/// Axes are taken in name order, so a combination of `arch: [mace_mp]` and
+/// `device: [cuda]` on `screen` becomes `screen_mace_mp_cuda`; values that
+/// are not a string, number or bool go by their position on the axis. Edges
+/// into the node go to every copy, with the same port. Edges out of it, and
+/// anything else naming it, stay with the Aggregator, which has no ports.
+/// The Aggregator keeps the node's place in `spec.nodes` and the copies go
+/// after the file's own nodes, whose indices lint still points at.
+fn expand_matrices(
+    spec: &mut WorkflowSpec,
+    existing: &mut HashSet<String>,
+) -> Result<(), DslError> {
+    let mut copies: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut nodes = Vec::with_capacity(spec.nodes.len());
+    let mut copy_nodes = Vec::new();
+    for (i, node) in std::mem::take(&mut spec.nodes).into_iter().enumerate() {
+        if node.matrix.is_empty() {
+            nodes.push(node);
+            continue;
+        }
+        let at = |field: &str| format!("nodes.{}.{}", i, field);
+        let base = match &node.params {
+            serde_json::Value::Null => serde_json::Map::new(),
+            serde_json::Value::Object(params) => params.clone(),
+            _ => {
+                return Err(DslError::validation(format!(
+                    "node '{}': params of a node with a matrix must be a mapping",
+                    node.id
+                ))
+                .at(at("params")))
+            }
+        };
+        let title = node.title.clone().unwrap_or_else(|| node.id.clone());
+
+        // Every combination, as one index per axis; the last axis varies fastest
+        let mut combinations: Vec<Vec<usize>> = vec![Vec::new()];
+        for values in node.matrix.values() {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|c| {
+                    (0..values.len()).map(move |k| {
+                        let mut c = c.clone();
+                        c.push(k);
+                        c
+                    })
+                })
+                .collect();
+        }
+
+        let mut created = Vec::new();
+        let mut gathered = serde_json::Map::new();
+        for combination in combinations {
+            let mut params = base.clone();
+            let mut axes = serde_json::Map::new();
+            let (mut labels, mut shown) = (Vec::new(), Vec::new());
+            for ((axis, values), k) in node.matrix.iter().zip(combination) {
+                let value = &values[k];
+                params.insert(axis.clone(), value.clone());
+                axes.insert(axis.clone(), value.clone());
+                labels.push(matrix_label(value, k));
+                shown.push(match value {
+                    serde_json::Value::String(s) => format!("{}={}", axis, s),
+                    _ => format!("{}={}", axis, value),
+                });
+            }
+            let id = format!("{}_{}", node.id, labels.join("_"));
+            if !existing.insert(id.clone()) {
+                return Err(DslError::validation(format!(
+                    "node '{}' matrix would create duplicate node id '{}'",
+                    node.id, id
+                ))
+                .at(at("matrix")));
+            }
+            copy_nodes.push(NodeSpec {
+                id: id.clone(),
+                title: Some(format!("{} ({})", title, shown.join(", "))),
+                params: serde_json::Value::Object(params),
+                matrix: BTreeMap::new(),
+                ..node.clone()
+            });
+            gathered.insert(id.clone(), serde_json::Value::Object(axes));
+            created.push(id);
+        }
+
+        let mut gather = macro_node(&node.id, format!("{} matrix", title), "agent");
+        gather.node_type = NodeKind::Aggregator;
+        gather.params = serde_json::json!({ "matrix": gathered });
+        nodes.push(gather);
+        copies.insert(node.id.clone(), created);
+    }
+    nodes.append(&mut copy_nodes);
+    spec.nodes = nodes;
+    if copies.is_empty() {
+        return Ok(());
+    }
+
+    let mut edges = Vec::with_capacity(spec.edges.len());
+    for mut e in std::mem::take(&mut spec.edges) {
+        if let Some((node, _)) = split_port(&e.from, "outputs") {
+            if copies.contains_key(node) {
+                e.from = node.to_string();
+            }
+        }
+        let (node, port) = match split_port(&e.to, "inputs") {
+            Some((node, port)) => (node, Some(port)),
+            None => (e.to.as_str(), None),
+        };
+        let Some(ids) = copies.get(node) else {
+            edges.push(e);
+            continue;
+        };
+        for id in ids {
+            let to = match port {
+                Some(port) => format!("{}.inputs.{}", id, port),
+                None => id.clone(),
+            };
+            edges.push(EdgeSpec { to, ..e.clone() });
+        }
+    }
+    for (node, ids) in &copies {
+        for id in ids {
+            edges.push(EdgeSpec {
+                from: id.clone(),
+                to: node.clone(),
+                kind: EdgeKind::Hard,
+                map: BTreeMap::new(),
+                when: None,
+            });
+        }
+    }
+    spec.edges = edges;
+    Ok(())
+}
+
+/// How a matrix value shows in node ids: a string, number or bool as
+/// written, anything else by its 1-based position `k + 1` on its axis.
+fn matrix_label(value: &serde_json::Value, k: usize) -> String {
+    let raw = match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::Bool(b) => b.to_string(),
+        _ => (k + 1).to_string(),
+    };
+    raw.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Reserves `id` for a node made by macro `m`.
+fn claim_id(existing: &mut HashSet<String>, m: &MacroSpec, id: &str) -> Result<(), DslError> {
+    if !existing.insert(id.to_string()) {
+        return Err(DslError::validation(format!(
+            "macro '{}' would create duplicate node id '{}'",
+            m.id, id
+        )));
+    }
+    Ok(())
+}
+
+/// A Compute node made by a macro: an engine and a title, nothing else.
+fn macro_node(id: &str, title: String, engine: &str) -> NodeSpec {
+    NodeSpec {
+        id: id.to_string(),
+        template: None,
+        node_type: NodeKind::Compute,
+        title: Some(title),
+        engine: Some(parse_engine(engine)),
+        params: serde_json::Value::Object(serde_json::Map::new()),
+        structure: None,
+        resources: None,
+        environment: None,
+        secrets: BTreeMap::new(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        cache: None,
+        retry: None,
+        workflow: None,
+        condition: None,
+        batch: None,
+        matrix: BTreeMap::new(),
+    }
+}
+
+/// Replace each subworkflow node by the nodes of the workflow it names.
+///
+/// Call after [`expand_macros`]; `workflow_file` is the file `spec` came
+/// from. Each child workflow is loaded, expanded and flattened in turn, and
+/// its node ids are prefixed with the subworkflow node's id and
+/// [`SUBWORKFLOW_SEP`]. Parent edges are rewired through the child's ports:
+/// an edge into `sub.inputs.<port>` goes to the nodes behind that input, and
+/// one into `sub` to the nodes behind every input, or to the child's roots
+/// if it declares none. Edges out of `sub.outputs.<port>` or `sub` leave
+/// from outputs (or leaves) the same way. Edge ends naming a port of a plain
+/// node, such as `relax.outputs.energy`, become that node's id.
+///
+/// Macros are dropped from the result: they are expanded by then, and may
+/// be anchored on a subworkflow node that no longer exists.
+pub fn resolve_subworkflows(
+    spec: &WorkflowSpec,
+    workflow_file: &Path,
+) -> Result<WorkflowSpec, DslError> {
+    let mut stack = vec![canonical(workflow_file)];
+    let mut out = flatten(spec, workflow_file, &mut stack)?;
+    out.macros.clear();
+    validate(&out)?;
+    Ok(out)
+}
+
+/// Where edges into or out of a flattened subworkflow attach.
+struct Side {
+    /// For an edge naming the subworkflow node itself.
+    all: Vec<String>,
+    by_port: HashMap<String, Vec<String>>,
+}
+
+impl Side {
+    fn new(
+        ports: &[InterfacePort],
+        fallback: Vec<String>,
+        prefix: impl Fn(&str) -> String,
+    ) -> Self {
+        let by_port: HashMap<String, Vec<String>> = ports
+            .iter()
+            .map(|p| (p.name.clone(), p.nodes.iter().map(|n| prefix(n)).collect()))
+            .collect();
+        let mut all: Vec<String> = Vec::new();
+        for p in ports {
+            for n in &by_port[&p.name] {
+                if !all.contains(n) {
+                    all.push(n.clone());
+                }
+            }
+        }
+        if ports.is_empty() {
+            all = fallback.iter().map(|n| prefix(n)).collect();
+        }
+        Side { all, by_port }
+    }
+}
+
+fn flatten(
+    spec: &WorkflowSpec,
+    workflow_file: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> Result<WorkflowSpec, DslError> {
+    let mut out = spec.clone();
+    out.nodes.clear();
+    out.edges.clear();
+
+    // (inputs, outputs) of each subworkflow node
+    let mut attach: HashMap<&str, (Side, Side)> = HashMap::new();
+    for node in &spec.nodes {
+        if node.node_type != NodeKind::Subworkflow {
+            out.nodes.push(node.clone());
+            continue;
+        }
+        let context = || format!("in subworkflow node '{}'", node.id);
+        let path = canonical(&resolve_relative(
+            workflow_file,
+            node.workflow.as_deref().unwrap_or_default(),
+        ));
+        if stack.contains(&path) {
+            return Err(DslError::validation(format!(
+                "subworkflow node '{}' includes {}, which includes it",
+                node.id,
+                path.display()
+            )));
+        }
+        // The node's params set the child's vars
+        let vars: Vars = match &node.params {
+            serde_json::Value::Null => Vars::new(),
+            params => serde_json::from_value(params.clone()).map_err(|e| {
+                DslError::validation(format!(
+                    "subworkflow node '{}' params must map var names to values: {}",
+                    node.id, e
+                ))
+            })?,
+        };
+        let child = load_yaml(&path, &vars).map_err(|e| e.push_context(context()))?;
+        let child = expand_macros(&child)
+            .map_err(|e| e.push_context(context()))?
+            .spec;
+        stack.push(path.clone());
+        let child = flatten(&child, &path, stack).map_err(|e| e.push_context(context()))?;
+        stack.pop();
+        check_ports(node, &spec.types, &child)?;
+
+        let prefix = |id: &str| format!("{}{}{}", node.id, SUBWORKFLOW_SEP, id);
+        for (name, ty) in &child.types {
+            out.types.insert(prefix(name), ty.clone());
+        }
+        for n in &child.nodes {
+            let mut n = n.clone();
+            n.id = prefix(&n.id);
+            if let Some(target) = n.resources.as_mut().and_then(|r| r.same_node_as.as_mut()) {
+                *target = prefix(target);
+            }
+            // Scripts stay relative to the file that names them
+            if let Some(EngineSpec::Agent { script, .. }) = n.engine.as_mut() {
+                *script = resolve_relative(&path, script)
+                    .to_string_lossy()
+                    .into_owned();
+            }
+            if let Some(st) = n.structure.as_mut() {
+                st.file = resolve_relative(&path, &st.file)
+                    .to_string_lossy()
+                    .into_owned();
+            }
+            if let Some(script) = n.condition.as_mut().and_then(|c| c.script.as_mut()) {
+                *script = resolve_relative(&path, script)
+                    .to_string_lossy()
+                    .into_owned();
+            }
+            for p in n.inputs.iter_mut().chain(n.outputs.iter_mut()) {
+                if let PortTypeRef::Named(name) = &mut p.ty {
+                    *name = prefix(name);
+                }
+                if let Some(source) = p.source.as_mut() {
+                    *source = prefix(source);
+                }
+            }
+            if n.environment.is_none() {
+                n.environment = child.environment.clone();
+            }
+            if n.resources.is_none() {
+                n.resources = child.resources.clone();
+            }
+            for (var, secret) in &child.secrets {
+                n.secrets
+                    .entry(var.clone())
+                    .or_insert_with(|| secret.clone());
+            }
+            n.environment = n.environment.map(|env| env.relative_to(&path));
+            out.nodes.push(n);
+        }
+        for e in &child.edges {
+            out.edges.push(EdgeSpec {
+                from: prefix(&e.from),
+                to: prefix(&e.to),
+                ..e.clone()
+            });
+        }
+
+        let (mut has_parent, mut has_child) = (HashSet::new(), HashSet::new());
+        for e in child
+            .edges
+            .iter()
+            .filter(|e| !matches!(e.kind, EdgeKind::Soft))
+        {
+            has_child.insert(e.from.as_str());
+            has_parent.insert(e.to.as_str());
+        }
+        let ids = |skip: &HashSet<&str>| -> Vec<String> {
+            child
+                .nodes
+                .iter()
+                .filter(|n| !skip.contains(n.id.as_str()))
+                .map(|n| n.id.clone())
+                .collect()
+        };
+        attach.insert(
+            &node.id,
+            (
+                Side::new(&child.inputs, ids(&has_parent), prefix),
+                Side::new(&child.outputs, ids(&has_child), prefix),
+            ),
+        );
+    }
+
+    // The nodes an edge end stands for once subworkflows are flattened; a
+    // port of a plain node is just that node
+    let ends = |end: &str, dir: &str| -> Result<Vec<String>, DslError> {
+        let (node, port) = match split_port(end, dir) {
+            Some((node, port)) if !attach.contains_key(end) => (node, Some(port)),
+            _ => (end, None),
+        };
+        let Some((inputs, outputs)) = attach.get(node) else {
+            return Ok(vec![node.to_string()]);
+        };
+        let side = if dir == "inputs" { inputs } else { outputs };
+        match port {
+            None => Ok(side.all.clone()),
+            Some(port) => side.by_port.get(port).cloned().ok_or_else(|| {
+                DslError::validation(format!(
+                    "subworkflow node '{}' has no {} port '{}'",
+                    node, dir, port
+                ))
+            }),
+        }
+    };
+    for e in &spec.edges {
+        for from in ends(&e.from, "outputs")? {
+            for to in ends(&e.to, "inputs")? {
+                out.edges.push(EdgeSpec {
+                    from: from.clone(),
+                    to,
+                    ..e.clone()
+                });
+            }
+        }
+    }
+    for p in out.inputs.iter_mut() {
+        p.nodes = p
+            .nodes
+            .iter()
+            .map(|n| ends(n, "inputs"))
+            .collect::<Result<Vec<_>, _>>()?
+            .concat();
+    }
+    for p in out.outputs.iter_mut() {
+        p.nodes = p
+            .nodes
+            .iter()
+            .map(|n| ends(n, "outputs"))
+            .collect::<Result<Vec<_>, _>>()?
+            .concat();
+    }
+    for n in &spec.nodes {
+        if let Some(target) = n.resources.as_ref().and_then(|r| r.same_node_as.as_ref()) {
+            if attach.contains_key(target.as_str()) {
+                return Err(DslError::validation(format!(
+                    "node '{}' resources.same_node_as cannot name subworkflow node '{}'",
+                    n.id, target
+                )));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Ports a subworkflow node declares must exist in its workflow, with the
+/// same type.
+fn check_ports(
+    node: &NodeSpec,
+    types: &BTreeMap<String, TypeSpec>,
+    child: &WorkflowSpec,
+) -> Result<(), DslError> {
+    for (dir, declared, offered) in [
+        ("inputs", &node.inputs, &child.inputs),
+        ("outputs", &node.outputs, &child.outputs),
+    ] {
+        for p in declared {
+            let Some(theirs) = offered.iter().find(|o| o.name == p.name) else {
+                return Err(DslError::validation(format!(
+                    "subworkflow node '{}' declares {} port '{}', which its workflow lacks",
+                    node.id, dir, p.name
+                )));
+            };
+            if resolve_type(&p.ty, types) != resolve_type(&theirs.ty, &child.types) {
+                return Err(DslError::validation(format!(
+                    "subworkflow node '{}' {} port '{}' has a different type in its workflow",
+                    node.id, dir, p.name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_condition(c: &ConditionSpec, node: &NodeSpec) -> Result<(), String> {
+    if node.node_type != NodeKind::Switch {
+        return Err("only switch nodes take a condition".into());
+    }
+    let set = [
+        c.energy_below.is_some(),
+        c.band_gap_above.is_some(),
+        c.expression.is_some(),
+        c.script.is_some(),
+    ];
+    if set.iter().filter(|s| **s).count() != 1 {
+        return Err("set exactly one of energy_below, band_gap_above, expression or script".into());
+    }
+    if let Some(t) = c.energy_below.or(c.band_gap_above) {
+        if !t.is_finite() {
+            return Err("threshold must be a finite number".into());
+        }
+    }
+    if let Some(expr) = &c.expression {
+        expr.parse::<ResultPredicate>()?;
+    }
+    if c.script.as_ref().is_some_and(|s| s.trim().is_empty()) {
+        return Err("script must not be empty".into());
+    }
+    Ok(())
+}
+
+fn validate_secret(var: &str, s: &SecretSpec) -> Result<(), String> {
+    if var.is_empty() || var.contains(['=', '\0']) {
+        return Err("not a valid env var name".into());
+    }
+    match (&s.env, &s.keyring) {
+        (Some(name), None) | (None, Some(name)) if !name.trim().is_empty() => Ok(()),
+        (Some(_), None) | (None, Some(_)) => Err("the name to read must not be empty".into()),
+        _ => Err("set exactly one of env or keyring".into()),
+    }
+}
+
+fn node_map(spec: &WorkflowSpec) -> HashMap<&str, &NodeSpec> {
+    spec.nodes.iter().map(|n| (n.id.as_str(), n)).collect()
+}
+
+/// The node an edge end attaches to: a node id, or `<node>.<dir>.<port>`
+/// for a port the node declares. Subworkflow nodes take any port name,
+/// which is checked against their workflow once it is loaded.
+fn edge_node<'a>(end: &'a str, dir: &str, nodes: &HashMap<&str, &NodeSpec>) -> Option<&'a str> {
+    if nodes.contains_key(end) {
+        return Some(end);
+    }
+    let (node, _) = split_port(end, dir)?;
+    let n = nodes.get(node)?;
+    (n.node_type == NodeKind::Subworkflow || declared_port(end, dir, nodes).is_some())
+        .then_some(node)
+}
+
+/// Why [`edge_node`] rejected `end`, for the `side` (`edge.from`, `edge.to`)
+/// it is on.
+fn unknown_end(side: &str, end: &str, dir: &str, nodes: &HashMap<&str, &NodeSpec>) -> String {
+    match split_port(end, dir) {
+        Some((node, port)) if nodes.contains_key(node) => format!(
+            "{} references {} port '{}' of node '{}', which it does not declare",
+            side, dir, port, node
+        ),
+        _ => format!("{} references unknown node: '{}'", side, end),
+    }
+}
+
+/// The port `<node>.<dir>.<port>` refers to, if that node declares it.
+fn declared_port<'a>(
+    end: &str,
+    dir: &str,
+    nodes: &HashMap<&str, &'a NodeSpec>,
+) -> Option<&'a PortSpec> {
+    let (node, port) = split_port(end, dir)?;
+    let n = nodes.get(node)?;
+    let ports = if dir == "inputs" {
+        &n.inputs
+    } else {
+        &n.outputs
+    };
+    ports.iter().find(|p| p.name == port)
+}
+
+fn resolve_type<'a>(
+    ty: &'a PortTypeRef,
+    types: &'a BTreeMap<String, TypeSpec>,
+) -> Option<&'a TypeSpec> {
+    match ty {
+        PortTypeRef::Named(name) => types.get(name),
+        PortTypeRef::Inline(t) => Some(t),
+    }
+}
+
+/// Output port `out` may feed input port `inp` if their types agree once
+/// names are resolved; an int also fits a float, element-wise in arrays.
+/// Unknown type names are reported elsewhere and pass here.
+fn check_flow(
+    types: &BTreeMap<String, TypeSpec>,
+    out: &PortSpec,
+    inp: &PortSpec,
+) -> Result<(), String> {
+    fn fits(from: &TypeSpec, to: &TypeSpec) -> bool {
+        match (from, to) {
+            (TypeSpec::Int, TypeSpec::Float) => true,
+            (TypeSpec::Array { of: from }, TypeSpec::Array { of: to }) => fits(from, to),
+            _ => from == to,
+        }
+    }
+    let (Some(from), Some(to)) = (resolve_type(&out.ty, types), resolve_type(&inp.ty, types))
+    else {
+        return Ok(());
+    };
+    if fits(from, to) {
+        return Ok(());
+    }
+    Err(format!(
+        "output '{}' is {} but input '{}' takes {}",
+        out.name,
+        type_name(from),
+        inp.name,
+        type_name(to)
+    ))
+}
+
+fn type_name(t: &TypeSpec) -> String {
+    match t {
+        TypeSpec::File => "file".into(),
+        TypeSpec::Float => "float".into(),
+        TypeSpec::Int => "int".into(),
+        TypeSpec::Bool => "bool".into(),
+        TypeSpec::String => "string".into(),
+        TypeSpec::Structure => "structure".into(),
+        TypeSpec::Json => "json".into(),
+        TypeSpec::Array { of } => format!("array of {}", type_name(of)),
+    }
+}
+
+fn split_port<'a>(end: &'a str, dir: &str) -> Option<(&'a str, &'a str)> {
+    let (node, port) = end.split_once(&format!(".{}.", dir))?;
+    (!port.is_empty()).then_some((node, port))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Script of the agent macros use when none is given.
+const AGENT_SHIM: &str = "unifiedlab_drivers/agent_shim.py";
+
 fn parse_engine(s: &str) -> EngineSpec {
     match s.to_lowercase().as_str() {
         "janus" => EngineSpec::Janus,
@@ -556,7 +2054,7 @@ fn parse_engine(s: &str) -> EngineSpec {
         "vasp" => EngineSpec::Vasp,
         "cp2k" => EngineSpec::Cp2k,
         "agent" => EngineSpec::Agent {
-            script: "unifiedlab_drivers/agent_shim.py".to_string(),
+            script: AGENT_SHIM.to_string(),
             strategy: None,
         },
         _ => EngineSpec::Janus,
@@ -0,0 +1,74 @@
+//! JSON Schema for workflow YAML, generated from the DSL types, so editors
+//! and CI can check a file before `deploy` does.
+//!
+//! The schema describes the file as written, so two things are widened:
+//! - Numbers and booleans also accept a whole `${vars.x}` reference, which
+//!   is only substituted at load time.
+//! - A node may leave out `type` for the template it `use`s to give.
+//!
+//! It is the schema of the current version; older files are upgraded as
+//! they load rather than checked against it.
+
+use schemars::generate::SchemaSettings;
+use schemars::transform::transform_subschemas;
+use schemars::Schema;
+
+use super::{WorkflowSpec, SUPPORTED_DSL_VERSION};
+
+/// What a whole-value `${vars.x}` reference looks like.
+const VAR_REF_PATTERN: &str = r"^\$\{vars\.[^}]+\}$";
+
+/// The JSON Schema (draft 2020-12) of a workflow file.
+pub fn json_schema() -> serde_json::Value {
+    let mut schema = SchemaSettings::draft2020_12()
+        .with_transform(allow_var_refs)
+        .into_generator()
+        .into_root_schema_for::<WorkflowSpec>();
+    schema.insert(
+        "title".into(),
+        format!(
+            "UnifiedLab workflow (DSL version {})",
+            SUPPORTED_DSL_VERSION
+        )
+        .into(),
+    );
+    let mut schema = schema.to_value();
+    if let Some(required) = schema
+        .pointer_mut("/$defs/NodeSpec/required")
+        .and_then(|r| r.as_array_mut())
+    {
+        required.retain(|field| field != "type");
+    }
+    schema
+}
+
+/// Lets every number or boolean be a `${vars.x}` reference instead.
+fn allow_var_refs(schema: &mut Schema) {
+    transform_subschemas(&mut allow_var_refs, schema);
+    let scalar =
+        |t: &serde_json::Value| matches!(t.as_str(), Some("integer" | "number" | "boolean"));
+    let takes_scalar = match schema.get("type") {
+        Some(serde_json::Value::Array(types)) => types.iter().any(scalar),
+        Some(t) => scalar(t),
+        None => false,
+    };
+    if !takes_scalar {
+        return;
+    }
+    let Some(obj) = schema.as_object_mut() else {
+        return;
+    };
+    let mut inner = std::mem::take(obj);
+    let mut outer = serde_json::Map::new();
+    // Annotations editors show go on the outside
+    for key in ["description", "default", "title"] {
+        if let Some(v) = inner.remove(key) {
+            outer.insert(key.into(), v);
+        }
+    }
+    outer.insert(
+        "anyOf".into(),
+        serde_json::json!([inner, { "type": "string", "pattern": VAR_REF_PATTERN }]),
+    );
+    *obj = outer;
+}
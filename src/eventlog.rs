@@ -13,17 +13,59 @@
 // Defensive Features:
 // - Magic Headers: 0x554C4142 ("ULAB") anchors every record.
 // - CRC32 Checksums: Detects bit-rot and partial writes.
-// - Self-Healing: Reader scans byte-by-byte to recover from corruption.
+// - Self-Healing: Reader scans byte-by-byte to recover from corruption,
+//   counting what it skipped (`ReaderStats`, `take_corruptions`).
 // - Size Limits: Rejects records > 128MB to prevent OOM.
 // - Path Access: Exposes file path for external metadata diagnostics.
+// - Compression: Optional per-record zlib or zstd, signalled by a flag bit
+//   in LEN, so compressed and raw records can share one file.
+// - Rotation Awareness: Readers can tell when their path was rolled away
+//   underneath them (see FileTransport inbox rotation).
+// - Segments: Optionally rolls to `events.0001.log`, ... past a size limit,
+//   listed in `events.manifest.json`. Offsets stay global (segment base +
+//   local offset), so stored cursors work across segments.
+// - Index: Optional `events.idx` sidecar mapping record numbers and
+//   timestamps to offsets (see `index`), so restarts and `seek` need not
+//   scan from byte 0.
+// - Encryption: Optional XChaCha20-Poly1305 sealing of `payload_json` (key
+//   from ULAB_EVENTLOG_KEY / ULAB_EVENTLOG_KEYFILE). The frame (magic, CRC,
+//   LEN) and the record kind stay plaintext, so self-healing still works.
+// - Kind Filter: `next_matching` reads the record kind straight from the
+//   container and passes over unwanted records before their payload is
+//   copied, unsealed or parsed.
+// - Memory Mapping: With the `mmap` feature, readers map the file instead of
+//   issuing a seek and read per record; magic scans and CRC checks then run
+//   over memory. The mapping is redone when the file grows.
+// - Group Commit: `append_batch` writes several records behind one flush
+//   and one fsync.
+// - Fsync Policy: every record, every N records, on a timer (a background
+//   thread syncs whatever was flushed since its last pass) or never. Slow
+//   parallel filesystems can trade a bounded window of loss for latency.
+// - Replay: `replay` prints any log as filtered JSON lines (the `replay`
+//   command).
+// - Schema Versions: each record carries the event schema version it was
+//   written with. Readers bring older payloads (and the Jobs inside them) up
+//   to the current layout (`schema::upgrade_event`) and refuse newer ones.
+// - Idempotency Keys: a record may carry an `event_id`, so a message sent
+//   twice (a worker retrying after a failed write) is applied once.
 
+use crate::schema;
 use anyhow::{anyhow, Context, Result};
+use bincode::Options;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use crc32fast::Hasher;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::Duration;
+use uuid::Uuid;
 
 // -----------------------------------------------------------------------------
 // CONSTANTS
@@ -35,6 +77,41 @@ const MAGIC_BYTES: u32 = 0x554C4142;
 // Hard limit to prevent memory exhaustion on corrupted length reads
 const MAX_RECORD_SIZE: u32 = 128 * 1024 * 1024; // 128 MB
 
+// Frame flags ride in the top bits of LEN (MAX_RECORD_SIZE needs only 27 bits).
+// Old records have all flag bits clear, so they read unchanged.
+const LEN_MASK: u32 = 0x07FF_FFFF;
+const FLAG_ZLIB: u32 = 1 << 31;
+const FLAG_SEALED: u32 = 1 << 30;
+const FLAG_ZSTD: u32 = 1 << 29;
+const KNOWN_FLAGS: u32 = FLAG_ZLIB | FLAG_SEALED | FLAG_ZSTD;
+
+// zstd's default; higher levels cost far more CPU for little gain on JSON
+const ZSTD_LEVEL: i32 = 3;
+
+// Sealed payload_json: [INNER FLAGS: 1][NONCE: 24][CIPHERTEXT + TAG]
+const SEAL_NONCE_LEN: usize = 24;
+const SEAL_INNER_ZLIB: u8 = 1; // JSON was compressed before sealing
+const SEAL_INNER_ZSTD: u8 = 2;
+
+// Corruption reports kept until drained; beyond this only the counters grow
+const MAX_PENDING_CORRUPTIONS: usize = 256;
+
+/// Event schema written by this build. Records from before versioning read
+/// as v0. Bump it with an upgrade step in `schema` when a payload changes.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+pub const KEY_ENV: &str = "ULAB_EVENTLOG_KEY";
+pub const KEYFILE_ENV: &str = "ULAB_EVENTLOG_KEYFILE";
+
+pub mod index;
+pub use index::EventIndex;
+
+pub mod replay;
+
+// Library-only: corrupted-log generators for tests and the fuzz target
+#[allow(dead_code)]
+pub mod testing;
+
 // -----------------------------------------------------------------------------
 // DATA STRUCTURES
 // -----------------------------------------------------------------------------
@@ -45,16 +122,64 @@ pub struct EventRecord {
     pub ts_ms: i64,
     pub kind: String,
     pub payload: Value,
+    /// Idempotency key set by the sender. The same id twice is one message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<Uuid>,
 }
 
 /// The low-level struct stored on disk.
 /// We store the payload as raw JSON bytes to prevent Bincode from crashing
 /// on dynamic `serde_json::Value` types.
-#[derive(Serialize, Deserialize)]
+/// Fields added since v0.1 go last: older readers ignore trailing bytes,
+/// `DiskRecord::decode` defaults whichever are missing, and `kind` stays
+/// where `peek_kind` looks for it.
+#[derive(Serialize)]
 struct DiskRecord {
     ts_ms: i64,
     kind: String,
     payload_json: Vec<u8>,
+    schema_version: u32,
+    event_id: Option<Uuid>,
+}
+
+/// `DiskRecord` as v0.1 wrote it. Every later container starts the same.
+#[derive(Deserialize)]
+struct LegacyDiskRecord {
+    ts_ms: i64,
+    kind: String,
+    payload_json: Vec<u8>,
+}
+
+impl DiskRecord {
+    /// Same encoding as `bincode::serialize`, but no length prefix may claim
+    /// more bytes than the record holds (no huge allocations from crafted
+    /// input). A v0.1 container ends before `schema_version`, one written
+    /// before idempotency keys before `event_id`.
+    fn decode(bytes: &[u8]) -> bincode::Result<Self> {
+        let options = bincode::options()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(bytes.len() as u64);
+        let mut rest = bytes;
+        let head: LegacyDiskRecord = options.deserialize_from(&mut rest)?;
+        let schema_version = if rest.is_empty() {
+            0
+        } else {
+            options.deserialize_from(&mut rest)?
+        };
+        let event_id = if rest.is_empty() {
+            None
+        } else {
+            options.deserialize_from(&mut rest)?
+        };
+        Ok(Self {
+            ts_ms: head.ts_ms,
+            kind: head.kind,
+            payload_json: head.payload_json,
+            schema_version,
+            event_id,
+        })
+    }
 }
 
 /// A wrapper returned to the reader containing position info.
@@ -65,18 +190,408 @@ pub struct EventEnvelope {
     pub record: EventRecord,
 }
 
+/// Why the reader gave up on a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorruptionKind {
+    /// No magic where a frame should start (garbage or a torn frame).
+    BadMagic,
+    /// Implausible LEN or unknown flag bits.
+    BadHeader,
+    /// Payload does not match its CRC.
+    BadCrc,
+    /// Frame cut short, with complete frames after it.
+    Truncated,
+    /// CRC fine, but the contents do not decode (zlib, zstd, bincode or JSON).
+    Undecodable,
+}
+
+/// One damaged region the reader skipped over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorruptionEvent {
+    /// Global offset where the damaged region starts.
+    pub offset: u64,
+    pub skipped_bytes: u64,
+    pub kind: CorruptionKind,
+}
+
+/// Counters of one reader since it was opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReaderStats {
+    pub records: u64,
+    pub corruptions: u64,
+    pub skipped_bytes: u64,
+    /// Intact records `next_matching` passed over for their kind.
+    pub filtered: u64,
+    /// Records written with an older event schema and upgraded on read.
+    pub upgraded: u64,
+}
+
+/// Per-record compression applied by the writer.
+/// Readers detect it from the frame flag, no configuration needed.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Zlib,
+    /// Better ratio and much faster to inflate than zlib; suits multi-MB
+    /// generator expansions. Readers built before this flag existed reject
+    /// `FLAG_ZSTD` frames.
+    Zstd,
+}
+
+impl std::str::FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "zlib" => Ok(Self::Zlib),
+            "zstd" => Ok(Self::Zstd),
+            other => Err(format!(
+                "unknown compression '{}' (expected none|zlib|zstd)",
+                other
+            )),
+        }
+    }
+}
+
+impl Compression {
+    /// Frame flag of records stored this way.
+    fn flag(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Zlib => FLAG_ZLIB,
+            Self::Zstd => FLAG_ZSTD,
+        }
+    }
+
+    /// Compressed `bytes`, or None when that would not shrink them.
+    fn pack(self, bytes: &[u8]) -> Result<Option<Vec<u8>>> {
+        let packed = match self {
+            Self::None => return Ok(None),
+            Self::Zlib => {
+                let mut enc = ZlibEncoder::new(Vec::new(), flate2::Compression::fast());
+                enc.write_all(bytes)?;
+                enc.finish()?
+            }
+            Self::Zstd => zstd::bulk::compress(bytes, ZSTD_LEVEL)?,
+        };
+        Ok((packed.len() < bytes.len()).then_some(packed))
+    }
+
+    /// Reverses `pack`, refusing to inflate past the record limit.
+    fn unpack(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let limit = MAX_RECORD_SIZE as u64 + 1;
+        let mut out = Vec::new();
+        match self {
+            Self::None => return Ok(bytes.to_vec()),
+            Self::Zlib => ZlibDecoder::new(bytes).take(limit).read_to_end(&mut out)?,
+            Self::Zstd => zstd::stream::read::Decoder::new(bytes)?
+                .take(limit)
+                .read_to_end(&mut out)?,
+        };
+        if out.len() > MAX_RECORD_SIZE as usize {
+            return Err(anyhow!("Record inflates past the 128MB limit"));
+        }
+        Ok(out)
+    }
+}
+
+/// When appended records are synced to disk. Every policy flushes to the
+/// OS after each append, so other processes see the record at once; the
+/// policy only decides how much a power or node failure can take back.
+///
+/// Written as `never`, `always`, `every_n=64` or `interval_ms=200`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum FsyncPolicy {
+    /// Leave it to the OS.
+    #[default]
+    Never,
+    /// Sync once at least this many records are unsynced (and on close).
+    EveryN(u32),
+    /// A background thread syncs this often if anything was written.
+    IntervalMs(u64),
+    /// Sync after every append (or batch).
+    Always,
+}
+
+impl std::str::FromStr for FsyncPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let bad = |e: std::num::ParseIntError| format!("fsync '{}': {}", s, e);
+        match s.split_once('=') {
+            None if s == "never" => Ok(Self::Never),
+            None if s == "always" => Ok(Self::Always),
+            Some(("every_n", n)) => n.trim().parse().map(Self::EveryN).map_err(bad),
+            Some(("interval_ms", ms)) => ms.trim().parse().map(Self::IntervalMs).map_err(bad),
+            _ => Err(format!(
+                "unknown fsync policy '{}' (never, always, every_n=N, interval_ms=MS)",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for FsyncPolicy {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<FsyncPolicy> for String {
+    fn from(p: FsyncPolicy) -> Self {
+        match p {
+            FsyncPolicy::Never => "never".into(),
+            FsyncPolicy::Always => "always".into(),
+            FsyncPolicy::EveryN(n) => format!("every_n={}", n),
+            FsyncPolicy::IntervalMs(ms) => format!("interval_ms={}", ms),
+        }
+    }
+}
+
 /// Configuration options for the writer.
 #[derive(Debug, Clone)]
 pub struct EventLogConfig {
-    /// If true, calls `fsync` after every append.
-    /// Recommended for Coordinators (Data Safety), optional for Workers (Speed).
-    pub fsync: bool,
+    /// Durability versus latency (see `FsyncPolicy`).
+    /// `Always` is recommended for Coordinators, `Never` is fine for tests.
+    pub fsync: FsyncPolicy,
+    pub compression: Compression,
+    /// Records smaller than this stay raw (compression would not pay off).
+    pub compress_min_bytes: usize,
+    /// Start a new segment once the current one would exceed this size.
+    /// None keeps a single file (unless the log is already segmented).
+    pub segment_bytes: Option<u64>,
+    /// Seal payloads with this key. None falls back to the environment
+    /// (`LogKey::from_env`), so every writer in a process agrees.
+    pub key: Option<LogKey>,
+    /// Keep an `.idx` sidecar next to the log (see `EventIndex`).
+    pub index: bool,
 }
 
 impl Default for EventLogConfig {
     fn default() -> Self {
-        Self { fsync: false }
+        Self {
+            fsync: FsyncPolicy::Never,
+            compression: Compression::None,
+            compress_min_bytes: 16 * 1024,
+            segment_bytes: None,
+            key: None,
+            index: false,
+        }
+    }
+}
+
+// =============================================================================
+// ENCRYPTION (Payload Sealing)
+// =============================================================================
+
+/// 256-bit symmetric key for sealing record payloads.
+#[derive(Clone, PartialEq)]
+pub struct LogKey([u8; 32]);
+
+impl std::fmt::Debug for LogKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LogKey(..)")
+    }
+}
+
+impl LogKey {
+    /// 64 hex characters (e.g. the output of `openssl rand -hex 32`).
+    pub fn from_hex(text: &str) -> Result<Self> {
+        let bytes = hex::decode(text.trim()).context("Event log key is not valid hex")?;
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|b: Vec<u8>| anyhow!("Event log key must be 32 bytes, got {}", b.len()))?;
+        Ok(Self(bytes))
+    }
+
+    /// A key file holds either the hex form or the 32 raw bytes.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data =
+            std::fs::read(path).with_context(|| format!("Reading event log key {:?}", path))?;
+        warn_if_shared(path);
+        match <[u8; 32]>::try_from(data.as_slice()) {
+            Ok(raw) => Ok(Self(raw)),
+            Err(_) => Self::from_hex(&String::from_utf8_lossy(&data))
+                .with_context(|| format!("Invalid event log key file {:?}", path)),
+        }
+    }
+
+    /// The key configured for this process: `ULAB_EVENTLOG_KEY` (hex) or
+    /// `ULAB_EVENTLOG_KEYFILE`. Read once; None if neither is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        static ENV_KEY: OnceLock<std::result::Result<Option<LogKey>, String>> = OnceLock::new();
+        let key = ENV_KEY.get_or_init(|| {
+            let key = match (std::env::var(KEY_ENV), std::env::var(KEYFILE_ENV)) {
+                (Ok(hex), _) => Self::from_hex(&hex).map(Some),
+                (_, Ok(path)) => Self::load(path).map(Some),
+                _ => Ok(None),
+            };
+            key.map_err(|e| format!("{:#}", e))
+        });
+        key.clone().map_err(|e| anyhow!(e))
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new((&self.0).into())
+    }
+
+    /// Encrypts `json` (compressed with `packed`), binding it to the
+    /// record's timestamp and kind.
+    fn seal(&self, ts_ms: i64, kind: &str, json: &[u8], packed: Compression) -> Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = seal_aad(ts_ms, kind);
+        let ct = self
+            .cipher()
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: json,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow!("Event payload encryption failed"))?;
+
+        let mut out = Vec::with_capacity(1 + SEAL_NONCE_LEN + ct.len());
+        out.push(match packed {
+            Compression::None => 0,
+            Compression::Zlib => SEAL_INNER_ZLIB,
+            Compression::Zstd => SEAL_INNER_ZSTD,
+        });
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ct);
+        Ok(out)
+    }
+
+    /// Reverses `seal`. Fails on a wrong key or a tampered record.
+    fn open(&self, ts_ms: i64, kind: &str, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < 1 + SEAL_NONCE_LEN {
+            return Err(anyhow!("Sealed payload is truncated"));
+        }
+        let (inner, rest) = sealed.split_at(1);
+        let (nonce, ct) = rest.split_at(SEAL_NONCE_LEN);
+        let aad = seal_aad(ts_ms, kind);
+        let plain = self
+            .cipher()
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ct, aad: &aad })
+            .map_err(|_| anyhow!("Cannot decrypt event payload (wrong key?)"))?;
+
+        match inner[0] {
+            0 => Ok(plain),
+            SEAL_INNER_ZLIB => Compression::Zlib.unpack(&plain),
+            SEAL_INNER_ZSTD => Compression::Zstd.unpack(&plain),
+            other => Err(anyhow!("Unknown sealed payload flags {:#x}", other)),
+        }
+    }
+}
+
+fn seal_aad(ts_ms: i64, kind: &str) -> Vec<u8> {
+    let mut aad = ts_ms.to_le_bytes().to_vec();
+    aad.extend_from_slice(kind.as_bytes());
+    aad
+}
+
+#[cfg(unix)]
+fn warn_if_shared(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Ok(meta) = std::fs::metadata(path) {
+        if meta.permissions().mode() & 0o077 != 0 {
+            log::warn!(
+                "Event log key {:?} is readable by other users (chmod 600 it)",
+                path
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_shared(_path: &Path) {}
+
+// =============================================================================
+// SEGMENTS (Manifest)
+// =============================================================================
+
+/// One file of a segmented log; `base` is the global offset of its first byte.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Segment {
+    pub file: String,
+    pub base: u64,
+}
+
+/// `events.manifest.json`: the segments of `events.log`, oldest first.
+/// Only the writer changes it, and only by appending a segment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub segments: Vec<Segment>,
+}
+
+impl Manifest {
+    /// `root/events.log` -> `root/events.manifest.json`
+    pub fn path_for(log: &Path) -> PathBuf {
+        log.with_extension("manifest.json")
+    }
+
+    pub fn load(log: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(log);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Reading manifest {:?}", path))?;
+        let m: Self =
+            serde_json::from_str(&text).with_context(|| format!("Parsing manifest {:?}", path))?;
+        if m.segments.is_empty() {
+            return Err(anyhow!("Manifest {:?} lists no segments", path));
+        }
+        Ok(Some(m))
+    }
+
+    /// Atomic replace, so readers never see a half-written manifest.
+    fn save(&self, log: &Path) -> Result<()> {
+        let path = Self::path_for(log);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
     }
+
+    /// Index of the segment holding global `offset`.
+    fn locate(&self, offset: u64) -> usize {
+        self.segments
+            .iter()
+            .rposition(|s| s.base <= offset)
+            .unwrap_or(0)
+    }
+}
+
+/// `events.log` + 3 -> `events.0003.log`
+fn segment_name(log: &Path, idx: usize) -> String {
+    let stem = log.file_stem().and_then(|s| s.to_str()).unwrap_or("events");
+    format!("{}.{:04}.log", stem, idx)
+}
+
+fn sibling(log: &Path, file: &str) -> PathBuf {
+    log.with_file_name(file)
+}
+
+/// Global offset where the next record of a (possibly segmented) log will start.
+/// Readers that only want new records seek here.
+pub fn log_end(log: &Path) -> Result<u64> {
+    let (file, base) = match Manifest::load(log)? {
+        Some(m) => {
+            let last = m.segments.last().expect("manifest is non-empty");
+            (sibling(log, &last.file), last.base)
+        }
+        None => (log.to_path_buf(), 0),
+    };
+    Ok(base + std::fs::metadata(file).map(|m| m.len()).unwrap_or(0))
 }
 
 // =============================================================================
@@ -84,9 +599,15 @@ impl Default for EventLogConfig {
 // =============================================================================
 
 pub struct EventLogWriter {
-    path: PathBuf,
+    path: PathBuf, // The log's name (e.g. root/events.log), even when segmented
     writer: BufWriter<File>,
     cfg: EventLogConfig,
+    len: u64,                   // Bytes in the current file
+    base: u64,                  // Global offset of the current file's first byte
+    manifest: Option<Manifest>, // Some when segmented
+    index: Option<index::IndexWriter>,
+    unsynced: u32,          // Records flushed since the last sync (EveryN)
+    syncer: Option<Syncer>, // IntervalMs
 }
 
 impl EventLogWriter {
@@ -99,117 +620,630 @@ impl EventLogWriter {
             std::fs::create_dir_all(parent).ok();
         }
 
+        // Once segmented, always segmented (even if rollover is now off).
+        // A plain log that becomes segmented keeps its file as segment 0.
+        let manifest = match (Manifest::load(&path)?, cfg.segment_bytes) {
+            (Some(m), _) => Some(m),
+            (None, Some(_)) => {
+                let first = match path.exists() {
+                    true => path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into(),
+                    false => segment_name(&path, 0),
+                };
+                let m = Manifest {
+                    segments: vec![Segment {
+                        file: first,
+                        base: 0,
+                    }],
+                };
+                m.save(&path)?;
+                Some(m)
+            }
+            (None, None) => None,
+        };
+        let (file_path, base) = match &manifest {
+            Some(m) => {
+                let last = m.segments.last().expect("manifest is non-empty");
+                (sibling(&path, &last.file), last.base)
+            }
+            None => (path.clone(), 0),
+        };
+
         // Open in Append mode.
         // Note: On HPC filesystems (Lustre/GPFS), O_APPEND is atomic for single-writer.
         let file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open(&path)
-            .with_context(|| format!("Failed to open log writer: {:?}", path))?;
+            .open(&file_path)
+            .with_context(|| format!("Failed to open log writer: {:?}", file_path))?;
+        let len = file.metadata()?.len();
+
+        let mut cfg = cfg;
+        if cfg.key.is_none() {
+            cfg.key = LogKey::from_env()?;
+        }
+
+        // The index is a convenience: without it the log is still complete
+        let index = match cfg.index {
+            true => match index::IndexWriter::open(&path, base + len, cfg.key.as_ref()) {
+                Ok(w) => Some(w),
+                Err(e) => {
+                    log::warn!("Event log index for {:?} disabled: {:#}", path, e);
+                    None
+                }
+            },
+            false => None,
+        };
+
+        let syncer = match cfg.fsync {
+            FsyncPolicy::IntervalMs(ms) => Some(Syncer::spawn(
+                file.try_clone()?,
+                Duration::from_millis(ms.max(1)),
+            )?),
+            _ => None,
+        };
 
         Ok(Self {
             path,
             writer: BufWriter::new(file),
             cfg,
+            len,
+            base,
+            manifest,
+            index,
+            unsynced: 0,
+            syncer,
         })
     }
 
+    /// Starts the next segment. The file exists before the manifest names it.
+    fn roll(&mut self) -> Result<()> {
+        let Some(manifest) = self.manifest.as_mut() else {
+            return Ok(());
+        };
+        self.writer.flush()?;
+        let durable = self.cfg.fsync != FsyncPolicy::Never;
+
+        let file = segment_name(&self.path, manifest.segments.len());
+        let next = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(sibling(&self.path, &file))
+            .with_context(|| format!("Failed to open log segment: {}", file))?;
+
+        self.base += self.len;
+        self.len = 0;
+        manifest.segments.push(Segment {
+            file: file.clone(),
+            base: self.base,
+        });
+        manifest.save(&self.path)?;
+        // The finished segment is synced now, whatever the policy's pace
+        if durable {
+            self.writer.get_ref().sync_data().ok();
+            self.unsynced = 0;
+        }
+        if let Some(syncer) = &self.syncer {
+            syncer.follow(next.try_clone()?);
+        }
+        self.writer = BufWriter::new(next);
+        log::info!("Event log rolled over to {} (offset {})", file, self.base);
+        Ok(())
+    }
+
     /// Appends a new record to the log.
     /// Returns the offset where the record started.
     pub fn append(&mut self, kind: &str, payload: Value) -> Result<u64> {
+        self.append_one(chrono::Utc::now().timestamp_millis(), kind, &payload, None)
+    }
+
+    /// Appends with an explicit timestamp.
+    pub fn append_at(&mut self, ts_ms: i64, kind: &str, payload: Value) -> Result<u64> {
+        self.append_one(ts_ms, kind, &payload, None)
+    }
+
+    /// Appends a record stamped with the sender's idempotency key.
+    pub fn append_with_id(&mut self, kind: &str, payload: Value, event_id: Uuid) -> Result<u64> {
         let ts_ms = chrono::Utc::now().timestamp_millis();
+        self.append_one(ts_ms, kind, &payload, Some(event_id))
+    }
+
+    /// Appends a record as read from another log, keeping its timestamp and
+    /// idempotency key.
+    pub fn append_record(&mut self, record: &EventRecord) -> Result<u64> {
+        self.append_one(record.ts_ms, &record.kind, &record.payload, record.event_id)
+    }
+
+    fn append_one(
+        &mut self,
+        ts_ms: i64,
+        kind: &str,
+        payload: &Value,
+        event_id: Option<Uuid>,
+    ) -> Result<u64> {
+        let (bytes, flags) = self.encode(ts_ms, kind, payload, event_id)?;
+        let entry = self.write_frame(ts_ms, kind, &bytes, flags)?;
+        self.commit(&[entry])?;
+        Ok(entry.offset)
+    }
+
+    /// Appends several records with one flush (and one `fsync`) for all.
+    /// Every record is encoded before any is written, so a record that
+    /// cannot be encoded fails the batch without writing any of it.
+    /// Returns the offset of each record.
+    pub fn append_batch(&mut self, records: &[(&str, Value)]) -> Result<Vec<u64>> {
+        let ts_ms = chrono::Utc::now().timestamp_millis();
+        let encoded = records
+            .iter()
+            .map(|(kind, payload)| self.encode(ts_ms, kind, payload, None))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut entries = Vec::with_capacity(records.len());
+        for ((kind, _), (bytes, flags)) in records.iter().zip(encoded) {
+            entries.push(self.write_frame(ts_ms, kind, &bytes, flags)?);
+        }
+        self.commit(&entries)?;
+        Ok(entries.iter().map(|e| e.offset).collect())
+    }
 
+    /// The frame body and flags for one record (steps 1-3).
+    fn encode(
+        &self,
+        ts_ms: i64,
+        kind: &str,
+        payload: &Value,
+        event_id: Option<Uuid>,
+    ) -> Result<(Vec<u8>, u32)> {
         // 1. Flatten JSON payload to bytes (Solves Bincode compatibility)
-        let payload_bytes =
-            serde_json::to_vec(&payload).context("Failed to serialize payload to JSON bytes")?;
+        let mut payload_bytes =
+            serde_json::to_vec(payload).context("Failed to serialize payload to JSON bytes")?;
+
+        // 1b. Optional Sealing. Ciphertext does not compress, so large
+        // payloads are compressed first (inside the seal).
+        let mut flags = 0;
+        if let Some(key) = &self.cfg.key {
+            let mut packed_with = Compression::None;
+            if payload_bytes.len() >= self.cfg.compress_min_bytes {
+                if let Some(packed) = self.cfg.compression.pack(&payload_bytes)? {
+                    payload_bytes = packed;
+                    packed_with = self.cfg.compression;
+                }
+            }
+            payload_bytes = key.seal(ts_ms, kind, &payload_bytes, packed_with)?;
+            flags |= FLAG_SEALED;
+        }
 
         // 2. Create intermediate Disk Record
         let disk_rec = DiskRecord {
             ts_ms,
             kind: kind.to_string(),
             payload_json: payload_bytes,
+            schema_version: EVENT_SCHEMA_VERSION,
+            event_id,
         };
 
         // 3. Serialize Container to Binary (Bincode)
-        let bytes = bincode::serialize(&disk_rec).context("Bincode serialization failed")?;
+        let raw = bincode::serialize(&disk_rec).context("Bincode serialization failed")?;
+        if raw.len() > MAX_RECORD_SIZE as usize {
+            return Err(anyhow!("Event exceeds 128MB limit: {} bytes", raw.len()));
+        }
 
-        let len = bytes.len() as u32;
-        if len > MAX_RECORD_SIZE {
-            return Err(anyhow!("Event exceeds 128MB limit: {} bytes", len));
+        // 3b. Optional Compression (only kept if it actually shrinks the record)
+        let mut bytes = raw;
+        if flags & FLAG_SEALED == 0 && bytes.len() >= self.cfg.compress_min_bytes {
+            if let Some(packed) = self.cfg.compression.pack(&bytes)? {
+                bytes = packed;
+                flags |= self.cfg.compression.flag();
+            }
         }
+        Ok((bytes, flags))
+    }
+
+    /// Buffers one frame (steps 4-5). Nothing reaches the file until `commit`.
+    fn write_frame(
+        &mut self,
+        ts_ms: i64,
+        kind: &str,
+        bytes: &[u8],
+        flags: u32,
+    ) -> Result<index::IndexEntry> {
+        let len = bytes.len() as u32;
 
         // 4. Calculate Integrity Checksum (CRC32)
         let mut hasher = Hasher::new();
-        hasher.update(&bytes);
+        hasher.update(bytes);
         let crc = hasher.finalize();
 
         // 5. Write Frame: [MAGIC][CRC][LEN][DATA]
-        let offset = self.writer.stream_position().unwrap_or(0);
+        // (a record never straddles segments; an oversized one gets its own)
+        let frame_len = 12 + bytes.len() as u64;
+        if let Some(limit) = self.cfg.segment_bytes {
+            if self.len > 0 && self.len + frame_len > limit {
+                self.roll()?;
+            }
+        }
+        let offset = self.base + self.len;
 
         self.writer.write_all(&MAGIC_BYTES.to_le_bytes())?;
         self.writer.write_all(&crc.to_le_bytes())?;
-        self.writer.write_all(&len.to_le_bytes())?;
-        self.writer.write_all(&bytes)?;
+        self.writer.write_all(&(len | flags).to_le_bytes())?;
+        self.writer.write_all(bytes)?;
+        self.len += frame_len;
+
+        Ok(index::IndexEntry {
+            offset,
+            ts_ms,
+            kind_hash: index::kind_hash(kind),
+            len: frame_len as u32,
+        })
+    }
 
+    /// Makes buffered frames durable and indexes them (steps 6-8).
+    fn commit(&mut self, entries: &[index::IndexEntry]) -> Result<()> {
         // 6. Flush to OS Cache
         self.writer.flush()?;
 
-        // 7. Hardware Sync (Optional)
-        if self.cfg.fsync {
-            self.writer.get_ref().sync_data().ok();
+        // 7. Hardware Sync (per policy)
+        match self.cfg.fsync {
+            FsyncPolicy::Never => {}
+            FsyncPolicy::Always => self.sync(),
+            FsyncPolicy::EveryN(n) => {
+                self.unsynced = self.unsynced.saturating_add(entries.len() as u32);
+                if self.unsynced >= n {
+                    self.sync();
+                }
+            }
+            FsyncPolicy::IntervalMs(_) => {
+                if let Some(syncer) = &self.syncer {
+                    syncer.mark_dirty();
+                }
+            }
         }
 
-        Ok(offset)
+        // 8. Index entries (after the frames, so they never point past the log)
+        if let Some(idx) = &mut self.index {
+            for entry in entries {
+                if let Err(e) = idx.push(*entry) {
+                    log::warn!("Event log index for {:?} disabled: {:#}", self.path, e);
+                    self.index = None;
+                    break;
+                }
+            }
+        }
+        Ok(())
     }
 
     pub fn path(&self) -> &Path {
         &self.path
     }
+
+    /// Global offset where the next record will start.
+    pub fn end(&self) -> u64 {
+        self.base + self.len
+    }
+
+    /// Syncs everything appended so far, whatever the policy.
+    pub fn sync(&mut self) {
+        self.writer.get_ref().sync_data().ok();
+        self.unsynced = 0;
+    }
+}
+
+impl Drop for EventLogWriter {
+    // Records held back by EveryN are synced on close; the syncer thread
+    // does the same for IntervalMs when it is dropped after this
+    fn drop(&mut self) {
+        if self.unsynced > 0 && self.writer.flush().is_ok() {
+            self.sync();
+        }
+    }
+}
+
+/// The background half of `FsyncPolicy::IntervalMs`. Holds a handle to the
+/// file being written (swapped on rollover) and syncs it when the writer has
+/// flushed since the last pass. Dropping it stops the thread after a final
+/// pass.
+struct Syncer {
+    shared: Arc<SyncShared>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+struct SyncShared {
+    file: Mutex<File>,
+    dirty: AtomicBool,
+    stop: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl Syncer {
+    fn spawn(file: File, every: Duration) -> Result<Self> {
+        let shared = Arc::new(SyncShared {
+            file: Mutex::new(file),
+            dirty: AtomicBool::new(false),
+            stop: Mutex::new(false),
+            wake: Condvar::new(),
+        });
+        let worker = shared.clone();
+        let thread = std::thread::Builder::new()
+            .name("ulab-fsync".into())
+            .spawn(move || loop {
+                let stopping = match worker.stop.lock() {
+                    Ok(stop) => match worker.wake.wait_timeout(stop, every) {
+                        Ok((stop, _)) => *stop,
+                        Err(_) => true,
+                    },
+                    Err(_) => true,
+                };
+                if worker.dirty.swap(false, Ordering::AcqRel) {
+                    if let Ok(file) = worker.file.lock() {
+                        file.sync_data().ok();
+                    }
+                }
+                if stopping {
+                    break;
+                }
+            })
+            .context("Failed to start the event log fsync thread")?;
+        Ok(Self {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    fn mark_dirty(&self) {
+        self.shared.dirty.store(true, Ordering::Release);
+    }
+
+    /// Sync the new segment from now on (the old one was synced by `roll`).
+    fn follow(&self, file: File) {
+        if let Ok(mut current) = self.shared.file.lock() {
+            *current = file;
+        }
+    }
+}
+
+impl Drop for Syncer {
+    fn drop(&mut self) {
+        if let Ok(mut stop) = self.shared.stop.lock() {
+            *stop = true;
+        }
+        self.shared.wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
 }
 
 // =============================================================================
 // READER (Tailing + Self-Healing)
 // =============================================================================
 
+/// The bytes behind a reader: buffered file reads, or (feature `mmap`) a
+/// mapping of the file as long as it was when last mapped.
+enum LogFile {
+    Buffered(BufReader<File>),
+    #[cfg(feature = "mmap")]
+    Mapped(File, std::io::Cursor<memmap2::Mmap>),
+}
+
+impl LogFile {
+    fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .with_context(|| format!("Failed to open log reader: {:?}", path))?;
+        Self::from_file(file)
+    }
+
+    fn from_file(file: File) -> Result<Self> {
+        #[cfg(feature = "mmap")]
+        if file.metadata()?.len() > 0 {
+            // Safety: logs only ever grow (rotation renames, it does not
+            // truncate), so mapped bytes are never taken away underneath us
+            let map = unsafe { memmap2::Mmap::map(&file)? };
+            return Ok(Self::Mapped(file, std::io::Cursor::new(map)));
+        }
+        Ok(Self::Buffered(BufReader::new(file)))
+    }
+
+    fn file(&self) -> &File {
+        match self {
+            Self::Buffered(r) => r.get_ref(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(f, _) => f,
+        }
+    }
+
+    /// Bytes readable right now; for a mapping, what was mapped.
+    fn len(&self) -> Result<u64> {
+        match self {
+            Self::Buffered(r) => Ok(r.get_ref().metadata()?.len()),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(_, m) => Ok(m.get_ref().len() as u64),
+        }
+    }
+
+    /// The readable bytes as one slice, when mapped.
+    fn mapped(&self) -> Option<&[u8]> {
+        match self {
+            Self::Buffered(_) => None,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(_, m) => Some(m.get_ref()),
+        }
+    }
+
+    /// Maps the file again if it grew past the mapping. True when that
+    /// made more bytes readable (always false for buffered reads).
+    fn refresh(&mut self) -> Result<bool> {
+        #[cfg(feature = "mmap")]
+        {
+            let on_disk = self.file().metadata()?.len();
+            if on_disk > self.len()? {
+                let pos = self.stream_position()?;
+                *self = Self::from_file(self.file().try_clone()?)?;
+                self.seek(SeekFrom::Start(pos))?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Read for LogFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Buffered(r) => r.read(buf),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(_, m) => m.read(buf),
+        }
+    }
+}
+
+impl Seek for LogFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            Self::Buffered(r) => r.seek(pos),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(_, m) => m.seek(pos),
+        }
+    }
+}
+
 pub struct EventLogReader {
-    reader: BufReader<File>,
-    cursor: u64,
+    reader: LogFile,
+    cursor: u64, // Within the current file
     path: PathBuf,
+    base: u64,                         // Global offset of the current file's first byte
+    segment: Option<usize>,            // Manifest index when segmented
+    manifest_len: u64,                 // Manifest size last time we looked (it only grows)
+    manifest: Option<(u64, Manifest)>, // Last one `seek` loaded, and its size
+    index: Option<EventIndex>,         // Opened once; `seek` picks up new entries
+    key: Option<LogKey>,               // For sealed records
+    stats: ReaderStats,
+    corruptions: Vec<CorruptionEvent>, // Not yet taken
 }
 
 impl EventLogReader {
-    /// Opens a log file for reading.
+    /// Opens a log file for reading (the first segment, if segmented).
     /// Defensive: Creates an empty file if it doesn't exist to prevent "File Not Found" errors.
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
 
-        if !path.exists() {
+        let (file_path, segment) = match Manifest::load(path)? {
+            Some(m) => (sibling(path, &m.segments[0].file), Some(0)),
+            None => (path.to_path_buf(), None),
+        };
+
+        if !file_path.exists() {
             // Create empty file if missing so we can tail it immediately
-            OpenOptions::new().create(true).write(true).open(path)?;
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .open(&file_path)?;
         }
 
-        let file = OpenOptions::new()
-            .read(true)
-            .open(path)
-            .with_context(|| format!("Failed to open log reader: {:?}", path))?;
-
         Ok(Self {
-            reader: BufReader::new(file),
+            reader: Self::open_file(&file_path)?,
             cursor: 0,
             path: path.to_path_buf(),
+            base: 0,
+            segment,
+            manifest_len: 0,
+            manifest: None,
+            index: EventIndex::open(path)?,
+            key: LogKey::from_env()?,
+            stats: ReaderStats::default(),
+            corruptions: Vec::new(),
         })
     }
 
-    /// Moves the read head to a specific absolute offset.
+    /// Uses `key` for sealed records instead of the environment's.
+    pub fn with_key(mut self, key: LogKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    fn open_file(path: &Path) -> Result<LogFile> {
+        LogFile::open(path)
+    }
+
+    /// Moves the read head to a specific absolute (global) offset.
+    /// With an index, an offset inside a record (a stale or hand-edited
+    /// cursor) moves on to the next record boundary.
     pub fn seek(&mut self, offset: u64) -> Result<()> {
-        self.reader.seek(SeekFrom::Start(offset))?;
-        self.cursor = offset;
+        let offset = match self.index()? {
+            Some(idx) => {
+                let snapped = idx.boundary(offset)?;
+                if snapped != offset {
+                    log::warn!(
+                        "Offset {} of {:?} is not a record boundary, resuming at {}",
+                        offset,
+                        self.path,
+                        snapped
+                    );
+                }
+                snapped
+            }
+            None => offset,
+        };
+        let located = self.manifest()?.map(|m| {
+            let idx = m.locate(offset);
+            (idx, m.segments[idx].file.clone(), m.segments[idx].base)
+        });
+        if let Some((idx, file, base)) = located {
+            self.reader = Self::open_file(&sibling(&self.path, &file))?;
+            self.base = base;
+            self.segment = Some(idx);
+        }
+        let local = offset.saturating_sub(self.base);
+        self.reader.seek(SeekFrom::Start(local))?;
+        self.cursor = local;
         Ok(())
     }
 
-    /// Accessor for the current read cursor position.
+    /// Moves the read head to the first record stamped at or after `ts_ms`
+    /// (or to the end of the indexed records). False without an index.
+    pub fn seek_time(&mut self, ts_ms: i64) -> Result<bool> {
+        let Some(idx) = self.index()? else {
+            return Ok(false);
+        };
+        let offset = match idx.get(idx.first_since(ts_ms)?)? {
+            Some(entry) => entry.offset,
+            None => idx.end()?,
+        };
+        self.seek(offset)?;
+        Ok(true)
+    }
+
+    /// The index this reader was opened with, with any entries the writer
+    /// has appended since.
+    fn index(&mut self) -> Result<Option<&EventIndex>> {
+        if let Some(idx) = self.index.as_mut() {
+            idx.refresh()?;
+        }
+        Ok(self.index.as_ref())
+    }
+
+    /// The manifest, reloaded only when it has grown since last time.
+    fn manifest(&mut self) -> Result<Option<&Manifest>> {
+        let len = std::fs::metadata(Manifest::path_for(&self.path))
+            .map(|m| m.len())
+            .unwrap_or(0);
+        if len == 0 {
+            return Ok(None);
+        }
+        if self.manifest.as_ref().map(|(l, _)| *l) != Some(len) {
+            self.manifest = Manifest::load(&self.path)?.map(|m| (len, m));
+        }
+        Ok(self.manifest.as_ref().map(|(_, m)| m))
+    }
+
+    /// Accessor for the current (global) read cursor position.
     pub fn cursor(&self) -> u64 {
-        self.cursor
+        self.base + self.cursor
     }
 
     /// Accessor for the file path (Diagnostic Feature).
@@ -217,12 +1251,104 @@ impl EventLogReader {
         &self.path
     }
 
-    /// Tries to read the next record.
+    /// Records read and damage skipped so far.
+    pub fn stats(&self) -> ReaderStats {
+        self.stats
+    }
+
+    /// Damaged regions skipped since the last call (at most 256 are kept).
+    pub fn take_corruptions(&mut self) -> Vec<CorruptionEvent> {
+        std::mem::take(&mut self.corruptions)
+    }
+
+    /// True once `path` names a different file than the one being read,
+    /// i.e. the writer rotated it away (or it was removed).
+    /// Always false for segmented logs, which never move.
+    pub fn is_replaced(&self) -> bool {
+        if self.segment.is_some() {
+            return false;
+        }
+        let Ok(on_disk) = std::fs::metadata(&self.path) else {
+            return true;
+        };
+        match self.reader.file().metadata() {
+            Ok(open) => !same_file(&open, &on_disk),
+            Err(_) => false,
+        }
+    }
+
+    /// Tries to read the next record, moving on to the next segment at the
+    /// end of a finished one. Offsets in the envelope are global.
     /// Returns:
     /// - `Ok(Some(Envelope))`: Valid record found.
     /// - `Ok(None)`: Reached End-Of-File (EOF).
     /// - `Ok(None)` (via Resync): Corruption found, skipped, but hit EOF before finding next valid record.
     pub fn next(&mut self) -> Result<Option<EventEnvelope>> {
+        self.next_of(None)
+    }
+
+    /// Like `next`, but only returns records of one of `kinds`. Others are
+    /// passed over before their payload is unsealed or parsed.
+    pub fn next_matching(&mut self, kinds: &[&str]) -> Result<Option<EventEnvelope>> {
+        self.next_of(Some(kinds))
+    }
+
+    fn next_of(&mut self, kinds: Option<&[&str]>) -> Result<Option<EventEnvelope>> {
+        loop {
+            if let Some(mut env) = self.next_in_file(kinds)? {
+                env.offset += self.base;
+                env.next_offset += self.base;
+                self.stats.records += 1;
+                return Ok(Some(env));
+            }
+            if self.reader.refresh()? {
+                continue;
+            }
+            if !self.advance_segment()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// At EOF: switch to the following segment, if the writer has started one.
+    /// A non-last segment is complete, so its EOF is final.
+    fn advance_segment(&mut self) -> Result<bool> {
+        // Cheap check first: the manifest only changes when a segment is added
+        let len = std::fs::metadata(Manifest::path_for(&self.path))
+            .map(|m| m.len())
+            .unwrap_or(0);
+        if len == 0 || len == self.manifest_len {
+            return Ok(false);
+        }
+        let Some(m) = Manifest::load(&self.path)? else {
+            return Ok(false);
+        };
+
+        let idx = match self.segment {
+            Some(i) => i,
+            // A plain log that has since become segment 0
+            None if m.segments[0].file.as_str() == self.path.file_name().unwrap_or_default() => {
+                self.segment = Some(0);
+                0
+            }
+            None => {
+                self.manifest_len = len;
+                return Ok(false);
+            }
+        };
+        let Some(next) = m.segments.get(idx + 1) else {
+            self.manifest_len = len; // Nothing new until the writer rolls again
+            return Ok(false);
+        };
+
+        self.reader = Self::open_file(&sibling(&self.path, &next.file))?;
+        self.base = next.base;
+        self.cursor = 0;
+        self.segment = Some(idx + 1);
+        Ok(true)
+    }
+
+    fn next_in_file(&mut self, kinds: Option<&[&str]>) -> Result<Option<EventEnvelope>> {
         loop {
             // A. Mark Start Position
             let start_pos = self.cursor;
@@ -247,8 +1373,7 @@ impl EventLogReader {
                     magic
                 );
                 // Self-Healing: Scan forward to find next valid record
-                if let Some(new_offset) = self.scan_for_magic(start_pos + 1)? {
-                    self.cursor = new_offset;
+                if self.skip_damaged(start_pos, CorruptionKind::BadMagic)? {
                     continue; // Retry read at new location
                 } else {
                     return Ok(None); // Hit EOF while scanning
@@ -261,67 +1386,136 @@ impl EventLogReader {
                 return Ok(None); // Partial write at EOF
             }
             let expected_crc = u32::from_le_bytes(meta_buf[0..4].try_into()?);
-            let len = u32::from_le_bytes(meta_buf[4..8].try_into()?);
+            let len_field = u32::from_le_bytes(meta_buf[4..8].try_into()?);
+            let flags = len_field & !LEN_MASK;
+            let len = len_field & LEN_MASK;
 
-            // E. Sanity Check Length
-            if len > MAX_RECORD_SIZE {
+            // E. Sanity Check Length (and flags we don't understand)
+            let both_codecs = flags & FLAG_ZLIB != 0 && flags & FLAG_ZSTD != 0;
+            if len > MAX_RECORD_SIZE || flags & !KNOWN_FLAGS != 0 || both_codecs {
                 log::error!(
-                    "Implausible record length {} at {}. Header corrupt.",
-                    len,
+                    "Implausible record header {:#x} at {}. Header corrupt.",
+                    len_field,
                     start_pos
                 );
-                if let Some(new_offset) = self.scan_for_magic(start_pos + 1)? {
-                    self.cursor = new_offset;
+                if self.skip_damaged(start_pos, CorruptionKind::BadHeader)? {
                     continue;
                 } else {
                     return Ok(None);
                 }
             }
 
-            // F. Read Payload
-            let mut payload = vec![0u8; len as usize];
-            if self.reader.read_exact(&mut payload).is_err() {
-                return Ok(None); // Partial payload write
+            // F. Read Payload. Short usually means the writer is mid-append;
+            // but if complete frames follow, this one is torn for good (or its
+            // LEN is corrupt) and waiting would stall the reader forever.
+            let eof = self.reader.len()?;
+            if start_pos + 12 + len as u64 > eof {
+                let Some(next) = self.next_valid_frame(start_pos + 1, eof)? else {
+                    return Ok(None); // Partial payload write
+                };
+                log::error!("Torn record at {} in {:?}. Skipping.", start_pos, self.path);
+                self.note_corruption(start_pos, next - start_pos, CorruptionKind::Truncated);
+                self.cursor = next;
+                continue;
             }
+            let mut payload = vec![0u8; len as usize];
+            self.reader.read_exact(&mut payload)?;
 
             // G. Validate Integrity (CRC32)
             let mut hasher = Hasher::new();
             hasher.update(&payload);
             if hasher.finalize() != expected_crc {
                 log::error!("CRC Mismatch at {}. Data corrupted.", start_pos);
-                if let Some(new_offset) = self.scan_for_magic(start_pos + 1)? {
-                    self.cursor = new_offset;
+                if self.skip_damaged(start_pos, CorruptionKind::BadCrc)? {
                     continue;
                 } else {
                     return Ok(None);
                 }
             }
 
-            // H. Deserialize Container (Bincode)
-            let disk_rec: DiskRecord = match bincode::deserialize(&payload) {
+            // G2. Decompress (CRC covers the bytes as stored)
+            let codec = if flags & FLAG_ZSTD != 0 {
+                Compression::Zstd
+            } else if flags & FLAG_ZLIB != 0 {
+                Compression::Zlib
+            } else {
+                Compression::None
+            };
+            if codec != Compression::None {
+                match codec.unpack(&payload) {
+                    Ok(inflated) => payload = inflated,
+                    Err(_) => {
+                        log::error!("Undecompressable record at {}. Skipping.", start_pos);
+                        self.skip_frame(start_pos, len);
+                        continue;
+                    }
+                }
+            }
+
+            // G3. Filter by kind, read in place from the container, so
+            // unwanted records never get their payload copied or parsed
+            if let Some(kinds) = kinds {
+                if let Some(kind) = peek_kind(&payload) {
+                    if !kinds.contains(&kind) {
+                        self.stats.filtered += 1;
+                        self.cursor = start_pos + 12 + len as u64;
+                        continue;
+                    }
+                }
+            }
+
+            // H. Deserialize Container (Bincode), whichever version wrote it
+            let disk_rec = match DiskRecord::decode(&payload) {
                 Ok(r) => r,
                 Err(e) => {
                     log::error!("Bincode Error at {}: {}. Skipping.", start_pos, e);
-                    self.cursor = start_pos + 12 + len as u64;
+                    self.skip_frame(start_pos, len);
                     continue;
                 }
             };
 
+            // H2. Unseal. The CRC passed, so a failure here is a key problem,
+            // not corruption: stop rather than skip the record.
+            let mut payload_json = disk_rec.payload_json;
+            if flags & FLAG_SEALED != 0 {
+                let key = self.key.as_ref().ok_or_else(|| {
+                    anyhow!(
+                        "Encrypted record at {} in {:?}, but no key is set ({} or {})",
+                        start_pos,
+                        self.path,
+                        KEY_ENV,
+                        KEYFILE_ENV
+                    )
+                })?;
+                payload_json = key
+                    .open(disk_rec.ts_ms, &disk_rec.kind, &payload_json)
+                    .with_context(|| format!("Record at {} in {:?}", start_pos, self.path))?;
+            }
+
             // I. Inflate Payload (JSON Bytes -> Value)
             // Safe because we produced it in `append` via serde_json::to_vec
-            let val: Value = match serde_json::from_slice(&disk_rec.payload_json) {
+            let mut val: Value = match serde_json::from_slice(&payload_json) {
                 Ok(v) => v,
                 Err(e) => {
                     log::error!("Inner JSON Corrupt at {}: {}. Skipping.", start_pos, e);
-                    self.cursor = start_pos + 12 + len as u64;
+                    self.skip_frame(start_pos, len);
                     continue;
                 }
             };
 
+            // I2. Upgrade to the current schema. Like a missing key, a record
+            // from a newer build is not corruption: stop rather than skip it.
+            let upgraded = schema::upgrade_event(&disk_rec.kind, disk_rec.schema_version, &mut val)
+                .with_context(|| format!("Record at {} in {:?}", start_pos, self.path))?;
+            if upgraded {
+                self.stats.upgraded += 1;
+            }
+
             let record = EventRecord {
                 ts_ms: disk_rec.ts_ms,
                 kind: disk_rec.kind,
                 payload: val,
+                event_id: disk_rec.event_id,
             };
 
             // Success: Update cursor to end of this record
@@ -336,9 +1530,88 @@ impl EventLogReader {
         }
     }
 
+    /// Gives up on the frame at `start_pos` and resumes at the next magic.
+    /// Without one, parks just short of EOF: the last 3 bytes may be the
+    /// start of a magic still being written. Returns false at EOF.
+    fn skip_damaged(&mut self, start_pos: u64, kind: CorruptionKind) -> Result<bool> {
+        let (resume, found) = match self.scan_for_magic(start_pos + 1)? {
+            Some(next) => (next, true),
+            None => {
+                let eof = self.reader.len()?;
+                (eof.saturating_sub(3).max(start_pos + 1), false)
+            }
+        };
+        self.note_corruption(start_pos, resume - start_pos, kind);
+        self.cursor = resume;
+        Ok(found)
+    }
+
+    /// Skips a well-framed record whose contents do not decode.
+    fn skip_frame(&mut self, start_pos: u64, len: u32) {
+        let frame_len = 12 + len as u64;
+        self.note_corruption(start_pos, frame_len, CorruptionKind::Undecodable);
+        self.cursor = start_pos + frame_len;
+    }
+
+    fn note_corruption(&mut self, start_pos: u64, skipped_bytes: u64, kind: CorruptionKind) {
+        self.stats.corruptions += 1;
+        self.stats.skipped_bytes += skipped_bytes;
+        if self.corruptions.len() < MAX_PENDING_CORRUPTIONS {
+            self.corruptions.push(CorruptionEvent {
+                offset: self.base + start_pos,
+                skipped_bytes,
+                kind,
+            });
+        }
+    }
+
+    /// First position at or after `from` holding a complete frame whose CRC
+    /// checks out.
+    fn next_valid_frame(&mut self, mut from: u64, eof: u64) -> Result<Option<u64>> {
+        while let Some(pos) = self.scan_for_magic(from)? {
+            if self.frame_ok_at(pos, eof)? {
+                return Ok(Some(pos));
+            }
+            from = pos + 1;
+        }
+        Ok(None)
+    }
+
+    fn frame_ok_at(&mut self, pos: u64, eof: u64) -> Result<bool> {
+        self.reader.seek(SeekFrom::Start(pos + 4))?;
+        let mut meta_buf = [0u8; 8];
+        if self.reader.read_exact(&mut meta_buf).is_err() {
+            return Ok(false);
+        }
+        let expected_crc = u32::from_le_bytes(meta_buf[0..4].try_into()?);
+        let len_field = u32::from_le_bytes(meta_buf[4..8].try_into()?);
+        let len = len_field & LEN_MASK;
+        if len > MAX_RECORD_SIZE
+            || len_field & !LEN_MASK & !KNOWN_FLAGS != 0
+            || pos + 12 + len as u64 > eof
+        {
+            return Ok(false);
+        }
+        if let Some(bytes) = self.reader.mapped() {
+            let start = pos as usize + 12;
+            return Ok(crc32fast::hash(&bytes[start..start + len as usize]) == expected_crc);
+        }
+        let mut payload = vec![0u8; len as usize];
+        self.reader.read_exact(&mut payload)?;
+        Ok(crc32fast::hash(&payload) == expected_crc)
+    }
+
     /// Brute-force scan: Moves forward 1 byte at a time looking for `0x554C4142`.
     /// Essential for recovering from partial writes during power loss/crash.
     fn scan_for_magic(&mut self, start_scan: u64) -> Result<Option<u64>> {
+        if let Some(bytes) = self.reader.mapped() {
+            let from = (start_scan as usize).min(bytes.len());
+            let magic = MAGIC_BYTES.to_le_bytes();
+            return Ok(bytes[from..]
+                .windows(4)
+                .position(|w| w == magic)
+                .map(|i| start_scan + i as u64));
+        }
         self.reader.seek(SeekFrom::Start(start_scan))?;
 
         let mut byte = [0u8; 1];
@@ -376,3 +1649,24 @@ impl EventLogReader {
         }
     }
 }
+
+/// The kind of a serialized `DiskRecord`: [TS_MS: i64][KIND LEN: u64][KIND].
+/// None if the container is too short or the kind is not UTF-8.
+fn peek_kind(container: &[u8]) -> Option<&str> {
+    let len = u64::from_le_bytes(container.get(8..16)?.try_into().ok()?);
+    let kind = container.get(16..16usize.checked_add(len.try_into().ok()?)?)?;
+    std::str::from_utf8(kind).ok()
+}
+
+/// Whether two metadata snapshots are of the same file (not a replacement).
+#[cfg(unix)]
+pub(crate) fn same_file(a: &std::fs::Metadata, b: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+// Without inodes: a rotated-in file starts out smaller than the old one.
+#[cfg(not(unix))]
+pub(crate) fn same_file(a: &std::fs::Metadata, b: &std::fs::Metadata) -> bool {
+    b.len() >= a.len()
+}
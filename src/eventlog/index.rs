@@ -0,0 +1,296 @@
+// src/eventlog/index.rs
+//
+// =============================================================================
+// UNIFIEDLAB: EVENT LOG INDEX (v 0.1 )
+// =============================================================================
+//
+// The Table of Contents.
+//
+// `events.idx` sits next to `events.log` and lists where each record starts,
+// so nobody has to scan a 100k-record log from byte 0:
+// 1. Layout: an 8-byte header ("ULIX" + version), then one fixed 24-byte
+//    entry per record: [OFFSET: u64][TS_MS: i64][KIND CRC32: u32][FRAME LEN: u32].
+//    Offsets are global, so one index covers every segment.
+// 2. Lookups: record number n is entry n; offsets and (non-decreasing)
+//    timestamps are found by binary search, a handful of small reads.
+// 3. Upkeep: only the writer touches it. On open it drops entries past the
+//    end of the log and indexes records written without it (crash between
+//    frame and entry, or an older build).
+//
+// The log stays the source of truth: a missing or stale index only makes
+// lookups slower, never wrong. Damaged regions of the log have no entries.
+
+use super::{EventLogReader, LogKey};
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const INDEX_MAGIC: &[u8; 4] = b"ULIX";
+const INDEX_VERSION: u32 = 1;
+const HEADER_LEN: u64 = 8;
+const ENTRY_LEN: u64 = 24;
+
+// ============================================================================
+// 1. ENTRIES
+// ============================================================================
+
+/// Where one record sits in the log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Global offset of the frame's magic.
+    pub offset: u64,
+    pub ts_ms: i64,
+    /// CRC32 of the record kind (see `kind_hash`).
+    pub kind_hash: u32,
+    /// Whole frame, header included.
+    pub len: u32,
+}
+
+impl IndexEntry {
+    /// Offset of the record that follows.
+    pub fn next_offset(&self) -> u64 {
+        self.offset + self.len as u64
+    }
+
+    fn to_bytes(self) -> [u8; ENTRY_LEN as usize] {
+        let mut buf = [0u8; ENTRY_LEN as usize];
+        buf[0..8].copy_from_slice(&self.offset.to_le_bytes());
+        buf[8..16].copy_from_slice(&self.ts_ms.to_le_bytes());
+        buf[16..20].copy_from_slice(&self.kind_hash.to_le_bytes());
+        buf[20..24].copy_from_slice(&self.len.to_le_bytes());
+        buf
+    }
+
+    fn from_bytes(buf: &[u8; ENTRY_LEN as usize]) -> Self {
+        let u64_at = |i: usize| u64::from_le_bytes(buf[i..i + 8].try_into().unwrap());
+        let u32_at = |i: usize| u32::from_le_bytes(buf[i..i + 4].try_into().unwrap());
+        Self {
+            offset: u64_at(0),
+            ts_ms: u64_at(8) as i64,
+            kind_hash: u32_at(16),
+            len: u32_at(20),
+        }
+    }
+}
+
+/// Kinds are stored hashed to keep entries fixed-size. Different kinds can
+/// collide, so check `record.kind` after reading.
+pub fn kind_hash(kind: &str) -> u32 {
+    crc32fast::hash(kind.as_bytes())
+}
+
+/// `root/events.log` -> `root/events.idx`
+pub fn path_for(log: &Path) -> PathBuf {
+    log.with_extension("idx")
+}
+
+fn header() -> [u8; HEADER_LEN as usize] {
+    let mut buf = [0u8; HEADER_LEN as usize];
+    buf[0..4].copy_from_slice(INDEX_MAGIC);
+    buf[4..8].copy_from_slice(&INDEX_VERSION.to_le_bytes());
+    buf
+}
+
+fn has_header(file: &mut File) -> Result<bool> {
+    let mut buf = [0u8; HEADER_LEN as usize];
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file.read_exact(&mut buf).is_ok() && buf == header())
+}
+
+/// Whole entries in an index file of `len` bytes (a torn last one is ignored).
+fn entries_in(len: u64) -> u64 {
+    len.saturating_sub(HEADER_LEN) / ENTRY_LEN
+}
+
+fn entry_pos(n: u64) -> u64 {
+    HEADER_LEN + n * ENTRY_LEN
+}
+
+// ============================================================================
+// 2. LOOKUPS (Readers)
+// ============================================================================
+
+/// Read-only view of a log's index, as of when it was opened.
+pub struct EventIndex {
+    file: File,
+    count: u64,
+}
+
+impl EventIndex {
+    /// None when the log has no index or the index does not fit it
+    /// (e.g. it outlived a log that was deleted).
+    pub fn open(log: &Path) -> Result<Option<Self>> {
+        let path = path_for(log);
+        let mut file = match File::open(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Opening index {:?}", path)),
+        };
+        if !has_header(&mut file)? {
+            return Ok(None);
+        }
+        let count = entries_in(file.metadata()?.len());
+        let index = Self { file, count };
+        if index.end()? > super::log_end(log)? {
+            return Ok(None);
+        }
+        Ok(Some(index))
+    }
+
+    /// Records indexed.
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Entry of record `n` (0-based).
+    pub fn get(&self, n: u64) -> Result<Option<IndexEntry>> {
+        if n >= self.count {
+            return Ok(None);
+        }
+        let mut buf = [0u8; ENTRY_LEN as usize];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(entry_pos(n)))?;
+        file.read_exact(&mut buf)?;
+        Ok(Some(IndexEntry::from_bytes(&buf)))
+    }
+
+    pub fn last(&self) -> Result<Option<IndexEntry>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        self.get(self.count - 1)
+    }
+
+    /// Takes in entries appended since this view was opened (or last
+    /// refreshed).
+    pub(super) fn refresh(&mut self) -> Result<()> {
+        self.count = entries_in(self.file.metadata()?.len());
+        Ok(())
+    }
+
+    /// Offset just past the last indexed record (0 when empty).
+    pub fn end(&self) -> Result<u64> {
+        Ok(self.last()?.map_or(0, |e| e.next_offset()))
+    }
+
+    /// Number of the first record for which `before` is false (entries
+    /// are ordered by offset, and by timestamp for live appends).
+    fn partition_point(&self, before: impl Fn(&IndexEntry) -> bool) -> Result<u64> {
+        let (mut lo, mut hi) = (0, self.count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.get(mid)? {
+                Some(e) if before(&e) => lo = mid + 1,
+                _ => hi = mid,
+            }
+        }
+        Ok(lo)
+    }
+
+    /// First record starting at or after `offset`.
+    pub fn at_or_after(&self, offset: u64) -> Result<Option<IndexEntry>> {
+        let n = self.partition_point(|e| e.offset < offset)?;
+        self.get(n)
+    }
+
+    /// Number of the first record stamped at or after `ts_ms`.
+    pub fn first_since(&self, ts_ms: i64) -> Result<u64> {
+        self.partition_point(|e| e.ts_ms < ts_ms)
+    }
+
+    /// Start of the first record at or after `offset`, so an offset inside a
+    /// record moves on to the next one. Offsets past the indexed part are
+    /// returned as is.
+    pub fn boundary(&self, offset: u64) -> Result<u64> {
+        let end = self.end()?;
+        if offset >= end {
+            return Ok(offset);
+        }
+        Ok(self.at_or_after(offset)?.map_or(end, |e| e.offset))
+    }
+}
+
+// ============================================================================
+// 3. UPKEEP (Writer)
+// ============================================================================
+
+pub(super) struct IndexWriter {
+    writer: BufWriter<File>,
+}
+
+impl IndexWriter {
+    /// Opens (or creates) the index of `log` and brings it level with the
+    /// log's `end`. Sealed records need `key` to be indexed.
+    pub(super) fn open(log: &Path, end: u64, key: Option<&LogKey>) -> Result<Self> {
+        let path = path_for(log);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open log index: {:?}", path))?;
+
+        // Unknown or foreign contents: start over
+        if !has_header(&mut file)? {
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&header())?;
+        }
+
+        // Keep only whole entries that lie within the log
+        let mut index = EventIndex {
+            file: file.try_clone()?,
+            count: entries_in(file.metadata()?.len()),
+        };
+        index.count = index.partition_point(|e| e.next_offset() <= end)?;
+        file.set_len(entry_pos(index.count))?;
+        let indexed_end = index.end()?;
+
+        let mut writer = Self {
+            writer: BufWriter::new(file),
+        };
+        writer.writer.seek(SeekFrom::End(0))?;
+        if indexed_end < end {
+            writer.catch_up(log, indexed_end, end, key)?;
+        }
+        Ok(writer)
+    }
+
+    /// Indexes the records in `[from, end)` from the log itself.
+    fn catch_up(&mut self, log: &Path, from: u64, end: u64, key: Option<&LogKey>) -> Result<()> {
+        let mut reader = EventLogReader::open(log)?;
+        if let Some(key) = key {
+            reader = reader.with_key(key.clone());
+        }
+        reader.seek(from)?;
+        let mut added = 0;
+        while let Some(env) = reader.next()? {
+            if env.offset >= end {
+                break;
+            }
+            self.push(IndexEntry {
+                offset: env.offset,
+                ts_ms: env.record.ts_ms,
+                kind_hash: kind_hash(&env.record.kind),
+                len: (env.next_offset - env.offset) as u32,
+            })?;
+            added += 1;
+        }
+        if added > 0 {
+            log::info!("Indexed {} record(s) of {:?}", added, log);
+        }
+        Ok(())
+    }
+
+    pub(super) fn push(&mut self, entry: IndexEntry) -> Result<()> {
+        self.writer.write_all(&entry.to_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
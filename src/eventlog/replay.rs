@@ -0,0 +1,138 @@
+// src/eventlog/replay.rs
+//
+// =============================================================================
+// UNIFIEDLAB: EVENT LOG REPLAY (v 0.1 )
+// =============================================================================
+//
+// The Flight Recorder.
+//
+// Backs `unifiedlab replay`: reads any log (events.log, an inbox or an
+// outbox), decodes the frames and hands back the records that pass a filter,
+// with their offsets and timestamps:
+// 1. Kind: only the listed kinds; others are passed over unparsed
+//    (`next_matching`).
+// 2. Time: `since` seeks through `events.idx` when there is one, `until`
+//    drops later records.
+// 3. Job: records whose payload mentions a job id (or a prefix of one)
+//    anywhere, e.g. `job_id`, `jobs[].id` or `job_ids[]`.
+
+use super::{EventLogReader, EventRecord, ReaderStats};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+// ============================================================================
+// 1. FILTER
+// ============================================================================
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayFilter {
+    /// Record kinds to keep; empty keeps all.
+    pub kinds: Vec<String>,
+    pub since_ms: Option<i64>,
+    pub until_ms: Option<i64>,
+    /// Job id or a prefix of it (as the console and logs abbreviate them).
+    pub job: Option<String>,
+}
+
+impl ReplayFilter {
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&record.kind))
+            && self.since_ms.map_or(true, |t| record.ts_ms >= t)
+            && self.until_ms.map_or(true, |t| record.ts_ms <= t)
+            && self
+                .job
+                .as_deref()
+                .map_or(true, |id| mentions_job(&record.payload, &id.to_lowercase()))
+    }
+}
+
+/// Whether any string in `value` is a uuid starting with `prefix`.
+fn mentions_job(value: &Value, prefix: &str) -> bool {
+    match value {
+        Value::String(s) => s.starts_with(prefix) && Uuid::parse_str(s).is_ok(),
+        Value::Array(items) => items.iter().any(|v| mentions_job(v, prefix)),
+        Value::Object(map) => map.values().any(|v| mentions_job(v, prefix)),
+        _ => false,
+    }
+}
+
+/// `--since` / `--until`: RFC 3339 (`2024-05-01T12:00:00Z`) or epoch ms.
+pub fn parse_time(text: &str) -> Result<i64> {
+    if let Ok(ms) = text.parse::<i64>() {
+        return Ok(ms);
+    }
+    DateTime::parse_from_rfc3339(text)
+        .map(|t| t.timestamp_millis())
+        .map_err(|e| anyhow!("'{}' is neither RFC 3339 nor epoch ms: {}", text, e))
+}
+
+// ============================================================================
+// 2. READING
+// ============================================================================
+
+/// One record as printed, a JSON line each.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayLine {
+    pub offset: u64,
+    pub next_offset: u64,
+    pub ts_ms: i64,
+    pub ts: String,
+    pub kind: String,
+    pub payload: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<Uuid>,
+}
+
+/// Feeds every record of `reader` that passes `filter` to `emit`, from the
+/// reader's position to the end of the log. Returns what the reader saw,
+/// including damage it skipped.
+pub fn replay(
+    reader: &mut EventLogReader,
+    filter: &ReplayFilter,
+    mut emit: impl FnMut(ReplayLine) -> Result<()>,
+) -> Result<ReaderStats> {
+    if let Some(since) = filter.since_ms {
+        // Without an index the filter alone skips the earlier records
+        reader.seek_time(since)?;
+    }
+    let kinds: Vec<&str> = filter.kinds.iter().map(String::as_str).collect();
+    loop {
+        let next = if kinds.is_empty() {
+            reader.next()
+        } else {
+            reader.next_matching(&kinds)
+        };
+        let env = match next {
+            Ok(Some(env)) => env,
+            Ok(None) => break,
+            Err(e) => {
+                return Err(e.context(format!(
+                    "Reading {:?} at offset {}",
+                    reader.path(),
+                    reader.cursor()
+                )))
+            }
+        };
+        if !filter.matches(&env.record) {
+            continue;
+        }
+        let ts = Utc
+            .timestamp_millis_opt(env.record.ts_ms)
+            .single()
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        emit(ReplayLine {
+            offset: env.offset,
+            next_offset: env.next_offset,
+            ts_ms: env.record.ts_ms,
+            ts,
+            kind: env.record.kind,
+            payload: env.record.payload,
+            event_id: env.record.event_id,
+        })?;
+    }
+    Ok(reader.stats())
+}
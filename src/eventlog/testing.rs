@@ -0,0 +1,293 @@
+// src/eventlog/testing.rs
+//
+// =============================================================================
+// UNIFIEDLAB: EVENT LOG TEST DATA (v 0.1 )
+// =============================================================================
+//
+// The Saboteur.
+//
+// Writes realistic logs, then damages them the way crashes and bad disks do:
+// 1. Truncation: the file ends inside a frame (power loss mid-append).
+// 2. Bit flips: one bit anywhere (bit-rot, bad RAM on the way to disk).
+// 3. Interleaved garbage: foreign bytes between or inside frames (two
+//    writers on one file, a half-overwritten block).
+//
+// `run_scenario` checks the reader against that damage and is what both
+// tests/eventlog_fuzz.rs and the fuzz targets in fuzz/ drive.
+
+use super::{Compression, EventLogConfig, EventLogReader, EventLogWriter, ReaderStats};
+use anyhow::{ensure, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Frames read per scenario before the harness calls the reader stuck.
+const MAX_READS: usize = 100_000;
+
+// ============================================================================
+// 1. DETERMINISTIC RANDOMNESS
+// ============================================================================
+
+/// SplitMix64: enough randomness for test data, reproducible from a seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n` (0 when `n` is 0).
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            self.next_u64() % n
+        }
+    }
+}
+
+// ============================================================================
+// 2. GENERATION
+// ============================================================================
+
+/// A record as written, with the frame it occupies in the clean file.
+#[derive(Debug, Clone)]
+pub struct Written {
+    pub offset: u64,
+    pub next_offset: u64,
+    pub kind: String,
+    pub payload: Value,
+}
+
+/// Appends `n` records of varied size to `path`. Some payloads are large
+/// enough to be compressed when `cfg` asks for it. Payload text is lowercase,
+/// so it never contains the frame magic ("BALU" on disk).
+pub fn write_log(
+    path: &Path,
+    n: usize,
+    rng: &mut Rng,
+    cfg: EventLogConfig,
+) -> Result<Vec<Written>> {
+    let mut writer = EventLogWriter::open(path, cfg)?;
+    let mut written = Vec::with_capacity(n);
+    for seq in 0..n {
+        let size = match rng.below(10) {
+            0 => 20_000 + rng.below(20_000),
+            _ => rng.below(400),
+        };
+        let text: String = (0..size)
+            .map(|_| (b'a' + rng.below(26) as u8) as char)
+            .collect();
+        let kind = format!("test.{}", rng.below(4));
+        let payload = json!({ "seq": seq, "text": text });
+        let offset = writer.append(&kind, payload.clone())?;
+        written.push(Written {
+            offset,
+            next_offset: writer.end(),
+            kind,
+            payload,
+        });
+    }
+    Ok(written)
+}
+
+// ============================================================================
+// 3. CORRUPTION
+// ============================================================================
+
+/// Damage to a log file. Positions refer to the clean file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Corruption {
+    /// The file ends at `at`.
+    Truncate { at: u64 },
+    /// Bit `bit` of the byte at `at` is inverted.
+    FlipBit { at: u64, bit: u8 },
+    /// `bytes` are inserted before position `at`.
+    Garbage { at: u64, bytes: Vec<u8> },
+}
+
+impl Corruption {
+    /// One random corruption of a file of `len` bytes.
+    pub fn random(rng: &mut Rng, len: u64) -> Self {
+        match rng.below(3) {
+            0 => Self::Truncate { at: rng.below(len) },
+            1 => Self::FlipBit {
+                at: rng.below(len),
+                bit: rng.below(8) as u8,
+            },
+            _ => {
+                let n = 1 + rng.below(64) as usize;
+                Self::Garbage {
+                    at: rng.below(len + 1),
+                    bytes: (0..n).map(|_| rng.below(256) as u8).collect(),
+                }
+            }
+        }
+    }
+
+    fn at(&self) -> u64 {
+        match self {
+            Self::Truncate { at } | Self::FlipBit { at, .. } | Self::Garbage { at, .. } => *at,
+        }
+    }
+
+    /// Whether the clean frame `[offset, next_offset)` is affected.
+    /// Garbage exactly at a frame boundary leaves both neighbours intact.
+    pub fn damages(&self, offset: u64, next_offset: u64) -> bool {
+        match self {
+            Self::Truncate { at } => next_offset > *at,
+            Self::FlipBit { at, .. } => (offset..next_offset).contains(at),
+            Self::Garbage { at, .. } => offset < *at && *at < next_offset,
+        }
+    }
+
+    fn apply(&self, data: &mut Vec<u8>) {
+        match self {
+            Self::Truncate { at } => data.truncate(*at as usize),
+            Self::FlipBit { at, bit } => {
+                if let Some(b) = data.get_mut(*at as usize) {
+                    *b ^= 1 << bit;
+                }
+            }
+            Self::Garbage { at, bytes } => {
+                let at = (*at as usize).min(data.len());
+                data.splice(at..at, bytes.iter().copied());
+            }
+        }
+    }
+}
+
+/// Applies `corruptions` to the file at `path`, back to front so that
+/// positions in the clean file stay valid.
+pub fn corrupt_file(path: &Path, corruptions: &[Corruption]) -> Result<()> {
+    let mut data = std::fs::read(path)?;
+    let mut ordered: Vec<&Corruption> = corruptions.iter().collect();
+    ordered.sort_by_key(|c| std::cmp::Reverse(c.at()));
+    for c in ordered {
+        c.apply(&mut data);
+    }
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+// ============================================================================
+// 4. CHECKS
+// ============================================================================
+
+/// Reads `path` to the end and checks what must hold for any input:
+/// offsets only move forward, every byte up to the cursor was either a
+/// record or counted as skipped, and a second pass at EOF changes nothing.
+/// Stops quietly at sealed records (no key is an error, not corruption).
+pub fn read_checked(path: &Path) -> Result<(Vec<Value>, ReaderStats)> {
+    let len = std::fs::metadata(path)?.len();
+    let mut reader = EventLogReader::open(path)?;
+    let mut payloads = Vec::new();
+    let mut record_bytes = 0;
+    let mut last_end = 0;
+    loop {
+        ensure!(payloads.len() < MAX_READS, "reader does not reach EOF");
+        let env = match reader.next() {
+            Ok(Some(env)) => env,
+            Ok(None) | Err(_) => break,
+        };
+        ensure!(
+            env.offset >= last_end && env.next_offset > env.offset && env.next_offset <= len,
+            "record at {}..{} after {} in a {} byte file",
+            env.offset,
+            env.next_offset,
+            last_end,
+            len
+        );
+        last_end = env.next_offset;
+        record_bytes += env.next_offset - env.offset;
+        payloads.push(env.record.payload);
+    }
+
+    let stats = reader.stats();
+    ensure!(reader.cursor() <= len, "cursor past EOF");
+    ensure!(
+        reader.cursor() == record_bytes + stats.skipped_bytes,
+        "cursor {} but {} record + {} skipped bytes",
+        reader.cursor(),
+        record_bytes,
+        stats.skipped_bytes
+    );
+    let taken = reader.take_corruptions();
+    ensure!(
+        taken.iter().map(|c| c.skipped_bytes).sum::<u64>() == stats.skipped_bytes
+            || stats.corruptions > taken.len() as u64,
+        "corruption events do not add up to the skipped bytes"
+    );
+    ensure!(
+        matches!(reader.next(), Ok(None) | Err(_)) && reader.stats() == stats,
+        "reader changed state after EOF"
+    );
+    Ok((payloads, stats))
+}
+
+/// Raw fuzz input as a log file: anything goes except panics, stalls and
+/// broken invariants.
+pub fn fuzz_reader(data: &[u8]) -> Result<()> {
+    let path = scratch_path();
+    std::fs::write(&path, data)?;
+    let res = read_checked(&path);
+    std::fs::remove_file(&path).ok();
+    res.map(|_| ())
+}
+
+/// One reproducible round: write a log, damage it 1-4 times, read it back.
+/// Every record whose frame no corruption touched must come back.
+pub fn run_scenario(seed: u64) -> Result<()> {
+    let mut rng = Rng::new(seed);
+    let path = scratch_path();
+    let cfg = EventLogConfig {
+        compression: match seed % 3 {
+            0 => Compression::None,
+            1 => Compression::Zlib,
+            _ => Compression::Zstd,
+        },
+        ..Default::default()
+    };
+    let n = 1 + rng.below(40) as usize;
+    let res = write_log(&path, n, &mut rng, cfg).and_then(|written| {
+        let len = written.last().map_or(0, |w| w.next_offset);
+        let corruptions: Vec<Corruption> = (0..1 + rng.below(4))
+            .map(|_| Corruption::random(&mut rng, len))
+            .collect();
+        corrupt_file(&path, &corruptions)?;
+
+        let (read, stats) = read_checked(&path)?;
+        let read_seqs: Vec<&Value> = read.iter().map(|p| &p["seq"]).collect();
+        for w in &written {
+            if corruptions
+                .iter()
+                .any(|c| c.damages(w.offset, w.next_offset))
+            {
+                continue;
+            }
+            ensure!(
+                read.contains(&w.payload),
+                "seed {}: intact record {} lost ({:?}; read {:?}; {:?})",
+                seed,
+                w.payload["seq"],
+                corruptions,
+                read_seqs,
+                stats
+            );
+        }
+        Ok(())
+    });
+    std::fs::remove_file(&path).ok();
+    res
+}
+
+fn scratch_path() -> PathBuf {
+    std::env::temp_dir().join(format!("ulab_fuzz_{}.log", uuid::Uuid::new_v4()))
+}
@@ -0,0 +1,237 @@
+// src/export.rs
+//
+// =============================================================================
+// UNIFIEDLAB: RESULTS EXPORT (v 0.1 )
+// =============================================================================
+//
+// Completed jobs as one table, for pandas (`unifiedlab export`).
+//
+// 1. Rows come from `CheckpointStore::get_completed_results`, which reads
+//    only the fields below out of each job's JSON.
+// 2. Columns: id, engine, blueprint node, workflow, energy, largest atomic
+//    force, timing, provenance, then one `params.<key>` column per param of
+//    interest (by default every scalar top-level param any job has).
+// 3. Written as CSV, a JSON array of records, or Parquet (feature "parquet").
+//    Parquet columns are typed: a column whose values are all integers,
+//    numbers or booleans gets that type, any other is text.
+
+use crate::checkpoint::ResultRecord;
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Name of the file written into the output directory, before the extension.
+pub const RESULTS_FILE_STEM: &str = "results";
+
+/// Prefix of the columns holding job params.
+pub const PARAM_COLUMN_PREFIX: &str = "params.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "parquet" => Ok(Self::Parquet),
+            other => Err(anyhow!(
+                "Unknown export format '{}' (expected csv|json|parquet)",
+                other
+            )),
+        }
+    }
+}
+
+/// Reads one fixed column's value out of a record.
+type Cell = fn(&ResultRecord) -> Value;
+
+/// One column of the table; `Null` where a job has no value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub values: Vec<Value>,
+}
+
+/// The records as columns. `params` names the params to keep; empty: every
+/// scalar top-level param of any record, sorted.
+pub fn result_columns(records: &[ResultRecord], params: &[String]) -> Vec<Column> {
+    let params: Vec<String> = if params.is_empty() {
+        records
+            .iter()
+            .flat_map(|r| r.params.iter())
+            .filter(|(_, v)| !v.is_object() && !v.is_array())
+            .map(|(k, _)| k.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    } else {
+        params.to_vec()
+    };
+
+    let fixed: [(&str, Cell); 15] = [
+        ("id", |r| json!(r.id)),
+        ("engine", |r| json!(r.engine)),
+        ("node", |r| json!(r.node)),
+        ("workflow", |r| json!(r.workflow)),
+        ("energy_ev", |r| json!(r.energy_ev)),
+        ("max_force", |r| json!(r.max_force)),
+        ("t_total_ms", |r| json!(r.t_total_ms)),
+        // A memoized copy never waited or ran; its times are the source's
+        ("queue_ms", |r| match r.memoized_from {
+            Some(_) => Value::Null,
+            None => json!((r.started_at - r.created_at).num_milliseconds().max(0)),
+        }),
+        ("runtime_ms", |r| match r.memoized_from {
+            Some(_) => Value::Null,
+            None => json!((r.finished_at - r.started_at).num_milliseconds()),
+        }),
+        ("started_at", |r| json!(r.started_at.to_rfc3339())),
+        ("finished_at", |r| json!(r.finished_at.to_rfc3339())),
+        ("host", |r| json!(r.host)),
+        ("exit_code", |r| json!(r.exit_code)),
+        ("binary_hash", |r| json!(r.binary_hash)),
+        ("memoized_from", |r| json!(r.memoized_from)),
+    ];
+    let mut columns: Vec<Column> = fixed
+        .iter()
+        .map(|(name, value)| Column {
+            name: name.to_string(),
+            values: records.iter().map(value).collect(),
+        })
+        .collect();
+    columns.extend(params.iter().map(|key| {
+        Column {
+            name: format!("{}{}", PARAM_COLUMN_PREFIX, key),
+            values: records
+                .iter()
+                .map(|r| r.params.get(key).cloned().unwrap_or(Value::Null))
+                .collect(),
+        }
+    }));
+    columns
+}
+
+/// Writes `columns` into `dir` (created if missing) as
+/// `results.<extension>`; returns the file's path.
+pub fn write_table(columns: &[Column], format: ExportFormat, dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!("{}.{}", RESULTS_FILE_STEM, format.extension()));
+    match format {
+        ExportFormat::Csv => write_csv(columns, &path),
+        ExportFormat::Json => write_json(columns, &path),
+        ExportFormat::Parquet => write_parquet(columns, &path),
+    }
+    .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn rows(columns: &[Column]) -> usize {
+    columns.first().map_or(0, |c| c.values.len())
+}
+
+/// A cell as text: strings as they are, null as nothing, anything else as JSON.
+fn cell_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn write_csv(columns: &[Column], path: &Path) -> Result<()> {
+    let mut out = csv::Writer::from_path(path)?;
+    out.write_record(columns.iter().map(|c| c.name.as_str()))?;
+    for i in 0..rows(columns) {
+        out.write_record(
+            columns
+                .iter()
+                .map(|c| cell_text(&c.values[i]).unwrap_or_default()),
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn write_json(columns: &[Column], path: &Path) -> Result<()> {
+    let records: Vec<Map<String, Value>> = (0..rows(columns))
+        .map(|i| {
+            columns
+                .iter()
+                .map(|c| (c.name.clone(), c.values[i].clone()))
+                .collect()
+        })
+        .collect();
+    let mut out = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut out, &records)?;
+    out.write_all(b"\n")?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+fn write_parquet(columns: &[Column], path: &Path) -> Result<()> {
+    use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    let mut fields = Vec::new();
+    let mut arrays: Vec<ArrayRef> = Vec::new();
+    for c in columns {
+        let set = || c.values.iter().filter(|v| !v.is_null());
+        let (data_type, array): (DataType, ArrayRef) = if set().next().is_none() {
+            (
+                DataType::Utf8,
+                Arc::new(StringArray::from(vec![None::<String>; c.values.len()])),
+            )
+        } else if set().all(|v| v.is_i64()) {
+            let values: Vec<Option<i64>> = c.values.iter().map(Value::as_i64).collect();
+            (DataType::Int64, Arc::new(Int64Array::from(values)))
+        } else if set().all(|v| v.is_number()) {
+            let values: Vec<Option<f64>> = c.values.iter().map(Value::as_f64).collect();
+            (DataType::Float64, Arc::new(Float64Array::from(values)))
+        } else if set().all(|v| v.is_boolean()) {
+            let values: Vec<Option<bool>> = c.values.iter().map(Value::as_bool).collect();
+            (DataType::Boolean, Arc::new(BooleanArray::from(values)))
+        } else {
+            let values: Vec<Option<String>> = c.values.iter().map(cell_text).collect();
+            (DataType::Utf8, Arc::new(StringArray::from(values)))
+        };
+        fields.push(Field::new(&c.name, data_type, true));
+        arrays.push(array);
+    }
+
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?;
+    let mut out = ArrowWriter::try_new(File::create(path)?, batch.schema(), None)?;
+    out.write(&batch)?;
+    out.close()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_parquet(_columns: &[Column], _path: &Path) -> Result<()> {
+    Err(anyhow!(
+        "this build has no Parquet support (feature \"parquet\"); use --format csv or json"
+    ))
+}
@@ -0,0 +1,817 @@
+// src/federation.rs
+//
+// =============================================================================
+// UNIFIEDLAB: FEDERATION LIGHTHOUSE (v 0.1 )
+// =============================================================================
+//
+// The Coordinator of Coordinators.
+//
+// One blueprint, several clusters (e.g. a Slurm machine + a GPU workstation):
+// 1. Architects deploy to the Federation root exactly as to a normal cluster
+//    (transactional submissions included).
+// 2. Each job is routed to a member cluster (`JobSubmit.routing`, then tags).
+// 3. A job is forwarded once every parent is Completed or already forwarded
+//    to the same cluster. Cross-cluster edges are held back here, and a job
+//    whose parent Failed there fails here, with everything downstream of it.
+// 4. Completion reports (and generator expansions) from every member's
+//    broadcasts are folded into the Federation checkpoint, so the TUI and
+//    console work against the Federation root.
+//
+// Logic nodes (Switch / Generator) act within the cluster that runs them.
+
+use crate::checkpoint::{CheckpointStore, WorkerInfo};
+use crate::core::{Job, JobStatus};
+use crate::eventlog::EventEnvelope;
+use crate::marketplace::{
+    ControlAck, ControlCommand, ControlRequest, JobArray, JobCompleteReport, JobSubmit, Routing,
+    SubmitAck, SubmitEnd, SubmitTxns, DEADLOCK_REASON_PREFIX, DEFAULT_SUBMIT_TIMEOUT,
+    EV_CONTROL_ACK, EV_JOB_COMPLETE, EV_JOB_SUBMIT, EV_SUBMIT_ACK, META_PAUSED, MSG_CONTROL,
+    MSG_JOB_ARRAY, MSG_SUBMIT_END,
+};
+use crate::transport::{
+    Role, Transport, TransportConfig, TransportFactory, TransportKind, TRANSPORT_CONFIG_FILE,
+};
+use crate::wire::StructureCodec;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Picked up from the Federation root when `--config` is not given.
+pub const FEDERATION_CONFIG_FILE: &str = "federation.yaml";
+
+/// flow_context key recording the routing decision (survives restarts).
+const CTX_CLUSTER: &str = "cluster";
+
+// ============================================================================
+// 1. CONFIGURATION
+// ============================================================================
+
+/// One member cluster, e.g. in `root/federation.yaml`:
+///
+/// ```yaml
+/// clusters:
+///   - name: archer
+///     root: /work/ulab          # that cluster's --root (shared filesystem)
+///     tags: [muscle]
+///   - name: gpubox
+///     tags: [gpu]
+///     transport:                # remote member, no shared filesystem
+///       kind: grpc
+///       grpc: { addr: "gpubox:7443", tls_ca: ca.pem, tls_cert: fed.pem, tls_key: fed.key }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClusterConfig {
+    pub name: String,
+    /// Required for file transport.
+    #[serde(default)]
+    pub root: Option<PathBuf>,
+    /// Default: `<root>/transport.yaml` if present, else file transport.
+    #[serde(default)]
+    pub transport: Option<TransportConfig>,
+    /// Capabilities used to place jobs that have no explicit route.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FederationConfig {
+    pub clusters: Vec<ClusterConfig>,
+}
+
+impl FederationConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Reading federation config {:?}", path))?;
+        let cfg: Self = serde_yaml::from_str(&text)
+            .with_context(|| format!("Parsing federation config {:?}", path))?;
+
+        if cfg.clusters.is_empty() {
+            return Err(anyhow!("Federation config {:?} lists no clusters", path));
+        }
+        let mut seen = HashSet::new();
+        for c in &cfg.clusters {
+            if !seen.insert(&c.name) {
+                return Err(anyhow!("Duplicate cluster name '{}'", c.name));
+            }
+        }
+        Ok(cfg)
+    }
+}
+
+// ============================================================================
+// 2. MEMBER CLUSTERS
+// ============================================================================
+
+struct Cluster {
+    name: String,
+    tags: HashSet<String>,
+    /// Worker-role connection: submits to, and tails broadcasts of, the member.
+    transport: Box<dyn Transport>,
+    codec: Option<StructureCodec>,
+    cursor: u64,
+    /// When a read of the member's broadcasts last succeeded.
+    last_contact: Instant,
+}
+
+impl Cluster {
+    async fn connect(cfg: &ClusterConfig, store: &CheckpointStore) -> Result<Self> {
+        let tcfg = match (&cfg.transport, &cfg.root) {
+            (Some(t), _) => t.clone(),
+            (None, Some(r)) if r.join(TRANSPORT_CONFIG_FILE).exists() => {
+                TransportConfig::load(r.join(TRANSPORT_CONFIG_FILE))?
+            }
+            (None, Some(r)) => TransportConfig::detect(r),
+            _ => TransportConfig::default(),
+        };
+        if tcfg.kind == TransportKind::File && cfg.root.is_none() {
+            return Err(anyhow!(
+                "Cluster '{}' uses file transport but has no root",
+                cfg.name
+            ));
+        }
+
+        let root = cfg.root.clone().unwrap_or_default();
+        let worker_id = format!("federation_{}", cfg.name);
+        let mut transport = TransportFactory::open(&tcfg, &root, Role::Worker, Some(&worker_id))
+            .await
+            .with_context(|| format!("Connecting to cluster '{}'", cfg.name))?;
+
+        let cursor = store
+            .get_meta(&cursor_key(&cfg.name))?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        transport.seek(cursor).await?;
+
+        let codec = match &cfg.root {
+            Some(r) if transport.shares_filesystem() => Some(StructureCodec::new(r)?),
+            _ => None,
+        };
+
+        Ok(Self {
+            name: cfg.name.clone(),
+            tags: cfg.tags.iter().cloned().collect(),
+            transport,
+            codec,
+            cursor,
+            last_contact: Instant::now(),
+        })
+    }
+
+    fn accepts(&self, job: &Job) -> bool {
+        let req = &job.resources;
+        req.tags_allow(|t| self.tags.contains(t)) && (req.gpus == 0 || self.tags.contains("gpu"))
+    }
+}
+
+fn cursor_key(cluster: &str) -> String {
+    format!("federation_cursor.{}", cluster)
+}
+
+// ============================================================================
+// 3. THE FEDERATION LIGHTHOUSE
+// ============================================================================
+
+struct FedJob {
+    job: Job,
+    cluster: String,
+    forwarded: bool,
+}
+
+pub struct FederationLighthouse {
+    transport: Box<dyn Transport>,
+    store: CheckpointStore,
+    clusters: Vec<Cluster>,
+    jobs: HashMap<Uuid, FedJob>,
+    codec: Option<StructureCodec>,
+    dirty_jobs: HashSet<Uuid>,
+    last_ckpt: Instant,
+    global_cursor: u64,
+    paused: bool,
+    /// Cancels relayed to a member: request_id -> job_id.
+    relayed_cancels: HashMap<Uuid, Uuid>,
+    submit_txns: SubmitTxns,
+}
+
+impl FederationLighthouse {
+    pub async fn open(
+        root: impl AsRef<Path>,
+        cfg: &FederationConfig,
+        transport: Box<dyn Transport>,
+        store: CheckpointStore,
+    ) -> Result<Self> {
+        let mut clusters = Vec::new();
+        for c in &cfg.clusters {
+            clusters.push(Cluster::connect(c, &store).await?);
+            log::info!("🌐 Member cluster '{}' connected", c.name);
+        }
+
+        let jobs = store
+            .restore_jobs()?
+            .into_values()
+            .filter_map(|job| {
+                let cluster = job.flow_context.get(CTX_CLUSTER)?.as_str()?.to_string();
+                Some((
+                    job.id,
+                    FedJob {
+                        forwarded: job.node_id.is_some(),
+                        cluster,
+                        job,
+                    },
+                ))
+            })
+            .collect();
+
+        let codec = if transport.shares_filesystem() {
+            Some(StructureCodec::new(root)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            transport,
+            paused: store.get_meta(META_PAUSED)?.as_deref() == Some("true"),
+            global_cursor: store.get_cursor()?,
+            store,
+            clusters,
+            jobs,
+            codec,
+            dirty_jobs: HashSet::new(),
+            last_ckpt: Instant::now(),
+            relayed_cancels: HashMap::new(),
+            submit_txns: SubmitTxns::default(),
+        })
+    }
+
+    pub async fn tick(&mut self) -> Result<()> {
+        for env in self.transport.recv_worker_messages().await? {
+            self.handle_message(env).await?;
+        }
+        for ack in self.submit_txns.expire(DEFAULT_SUBMIT_TIMEOUT) {
+            log::warn!("📦 {}", ack.message);
+            self.transport
+                .broadcast(EV_SUBMIT_ACK, serde_json::to_value(&ack)?)
+                .await?;
+        }
+
+        // A member being down must not stall the others
+        for i in 0..self.clusters.len() {
+            match self.clusters[i].transport.recv_broadcasts().await {
+                Ok(events) => {
+                    self.clusters[i].last_contact = Instant::now();
+                    for env in events {
+                        self.handle_cluster_event(i, env).await?;
+                    }
+                }
+                Err(e) => log::warn!("Cluster '{}' unreachable: {}", self.clusters[i].name, e),
+            }
+        }
+
+        self.fail_orphans();
+        if !self.paused {
+            self.forward_ready().await?;
+        }
+        self.maybe_checkpoint()
+    }
+
+    // ------------------------------------------------------------------------
+    // Inbound: architects / console
+    // ------------------------------------------------------------------------
+
+    async fn handle_message(&mut self, env: EventEnvelope) -> Result<()> {
+        if env.next_offset > self.global_cursor {
+            self.global_cursor = env.next_offset;
+        }
+
+        match env.record.kind.as_str() {
+            EV_JOB_SUBMIT => {
+                let staged = serde_json::from_value::<JobSubmit>(env.record.payload)
+                    .ok()
+                    .and_then(|sub| self.submit_txns.stage(sub));
+                if let Some(sub) = staged {
+                    if let Err(e) = self.accept_submission(sub) {
+                        log::error!("Dropping submission: {}", e);
+                    }
+                }
+            }
+            MSG_SUBMIT_END => {
+                if let Ok(end) = serde_json::from_value::<SubmitEnd>(env.record.payload) {
+                    let applied = self
+                        .submit_txns
+                        .end(&end)
+                        .and_then(|sub| self.accept_submission(sub));
+                    let ack = match applied {
+                        Ok(jobs) => SubmitAck {
+                            txn_id: end.txn_id,
+                            ok: true,
+                            jobs,
+                            message: format!("Submission {} accepted: {} job(s)", end.txn_id, jobs),
+                        },
+                        Err(e) => SubmitAck {
+                            txn_id: end.txn_id,
+                            ok: false,
+                            jobs: 0,
+                            message: e.to_string(),
+                        },
+                    };
+                    log::info!("📦 {}", ack.message);
+                    self.transport
+                        .broadcast(EV_SUBMIT_ACK, serde_json::to_value(&ack)?)
+                        .await?;
+                }
+            }
+            MSG_JOB_ARRAY => {
+                // Members are made where they run; deploy arrays to a cluster directly
+                if let Ok(array) = serde_json::from_value::<JobArray>(env.record.payload) {
+                    let ack = SubmitAck {
+                        txn_id: array.id,
+                        ok: false,
+                        jobs: 0,
+                        message: "Job arrays go to a cluster Coordinator, not the Lighthouse"
+                            .into(),
+                    };
+                    log::warn!("📦 {}", ack.message);
+                    self.transport
+                        .broadcast(EV_SUBMIT_ACK, serde_json::to_value(&ack)?)
+                        .await?;
+                }
+            }
+            MSG_CONTROL => {
+                if let Ok(req) = serde_json::from_value::<ControlRequest>(env.record.payload) {
+                    self.apply_control(req).await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Hydrates and routes a whole submission. Returns the number of jobs.
+    fn accept_submission(&mut self, mut sub: JobSubmit) -> Result<usize> {
+        if let Some(codec) = &self.codec {
+            codec
+                .hydrate_jobs(&mut sub.jobs)
+                .context("Unreadable structures")?;
+        }
+        let jobs = sub.jobs.len();
+        self.ingest_submission(sub);
+        Ok(jobs)
+    }
+
+    fn ingest_submission(&mut self, sub: JobSubmit) {
+        let mut accepted = 0;
+        for mut job in sub.jobs {
+            // Inboxes are re-read from the start after a restart
+            if self.jobs.contains_key(&job.id) {
+                continue;
+            }
+            let cluster = self.route(&job, &sub.routing);
+            job.flow_context.insert(CTX_CLUSTER.into(), json!(cluster));
+            self.dirty_jobs.insert(job.id);
+            self.jobs.insert(
+                job.id,
+                FedJob {
+                    job,
+                    cluster,
+                    forwarded: false,
+                },
+            );
+            accepted += 1;
+        }
+
+        for (pid, cid) in sub.deps {
+            if let Some(child) = self.jobs.get_mut(&cid) {
+                if !child.job.parent_ids.contains(&pid) {
+                    child.job.parent_ids.push(pid);
+                }
+            }
+        }
+        log::info!("📥 Federation accepted {} job(s)", accepted);
+    }
+
+    fn route(&self, job: &Job, routing: &Routing) -> String {
+        let explicit = routing
+            .jobs
+            .get(&job.id)
+            .or_else(|| routing.engines.get(job.config.engine.name()))
+            .or(routing.default.as_ref());
+
+        if let Some(name) = explicit {
+            if self.clusters.iter().any(|c| &c.name == name) {
+                return name.clone();
+            }
+            log::warn!(
+                "Job {} routed to unknown cluster '{}'; placing by tags",
+                job.id,
+                name
+            );
+        }
+
+        // Config loading guarantees at least one member
+        self.clusters
+            .iter()
+            .find(|c| c.accepts(job))
+            .unwrap_or(&self.clusters[0])
+            .name
+            .clone()
+    }
+
+    async fn apply_control(&mut self, req: ControlRequest) -> Result<()> {
+        let outcome = match req.command {
+            ControlCommand::Pause => {
+                self.paused = true;
+                self.store.set_meta(META_PAUSED, "true")?;
+                Ok("Forwarding paused (member clusters keep running)".to_string())
+            }
+            ControlCommand::Resume => {
+                self.paused = false;
+                self.store.set_meta(META_PAUSED, "false")?;
+                Ok("Forwarding resumed".to_string())
+            }
+            ControlCommand::SetExpandLimit { .. } => Err(anyhow!(
+                "expand-limit is per cluster; open a console on the member's root"
+            )),
+            ControlCommand::AddEdge { .. }
+            | ControlCommand::RemoveEdge { .. }
+            | ControlCommand::AddNode { .. } => Err(anyhow!(
+                "graph edits are per cluster; run them against the member's root"
+            )),
+            ControlCommand::CancelJobs { .. } => Err(anyhow!(
+                "bulk cancel is per cluster; run it against the member's root"
+            )),
+            ControlCommand::RetryJobs { .. } => Err(anyhow!(
+                "retry is per cluster; run it against the member's root"
+            )),
+            ControlCommand::PauseWorkflow { .. } | ControlCommand::ResumeWorkflow { .. } => Err(
+                anyhow!("workflow pause is per cluster; run it against the member's root"),
+            ),
+            ControlCommand::Cancel { job_id } => match self.jobs.get_mut(&job_id) {
+                None => Err(anyhow!("Unknown job {}", job_id)),
+                Some(f) if f.forwarded => {
+                    // The member owns it now; its ack is relayed back to the caller
+                    let name = f.cluster.clone();
+                    if let Some(c) = self.clusters.iter_mut().find(|c| c.name == name) {
+                        c.transport
+                            .send_to_coordinator(MSG_CONTROL, serde_json::to_value(&req)?)
+                            .await?;
+                        self.relayed_cancels.insert(req.request_id, job_id);
+                        return Ok(());
+                    }
+                    Err(anyhow!(
+                        "Job {} belongs to unknown cluster '{}'",
+                        job_id,
+                        name
+                    ))
+                }
+                Some(f) if matches!(f.job.status, JobStatus::Cancelled) => {
+                    Err(anyhow!("Job {} is already Cancelled", job_id))
+                }
+                Some(f) => {
+                    f.job.status = JobStatus::Cancelled;
+                    f.job.error_log = Some("Cancelled by operator".into());
+                    f.job.updated_at = chrono::Utc::now();
+                    self.dirty_jobs.insert(job_id);
+                    Ok(format!("Job {} cancelled before forwarding", job_id))
+                }
+            },
+        };
+
+        let ack = match outcome {
+            Ok(message) => ControlAck {
+                request_id: req.request_id,
+                ok: true,
+                message,
+            },
+            Err(e) => ControlAck {
+                request_id: req.request_id,
+                ok: false,
+                message: e.to_string(),
+            },
+        };
+        log::info!("🎛️ Control: {}", ack.message);
+        self.transport
+            .broadcast(EV_CONTROL_ACK, serde_json::to_value(&ack)?)
+            .await?;
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------------
+    // Inbound: member clusters
+    // ------------------------------------------------------------------------
+
+    async fn handle_cluster_event(&mut self, idx: usize, env: EventEnvelope) -> Result<()> {
+        self.clusters[idx].cursor = env.next_offset;
+
+        match env.record.kind.as_str() {
+            EV_JOB_COMPLETE => {
+                if let Ok(rep) = serde_json::from_value::<JobCompleteReport>(env.record.payload) {
+                    self.apply_job_complete(&rep);
+                    self.transport
+                        .broadcast(EV_JOB_COMPLETE, serde_json::to_value(&rep)?)
+                        .await?;
+                }
+            }
+            EV_JOB_SUBMIT => {
+                if let Ok(sub) = serde_json::from_value::<JobSubmit>(env.record.payload) {
+                    self.adopt_expansion(idx, sub);
+                }
+            }
+            EV_CONTROL_ACK => {
+                if let Ok(ack) = serde_json::from_value::<ControlAck>(env.record.payload) {
+                    if let Some(job_id) = self.relayed_cancels.remove(&ack.request_id) {
+                        if ack.ok {
+                            if let Some(f) = self.jobs.get_mut(&job_id) {
+                                f.job.status = JobStatus::Cancelled;
+                                f.job.error_log = Some("Cancelled by operator".into());
+                                self.dirty_jobs.insert(job_id);
+                            }
+                        }
+                        self.transport
+                            .broadcast(EV_CONTROL_ACK, serde_json::to_value(&ack)?)
+                            .await?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn apply_job_complete(&mut self, rep: &JobCompleteReport) {
+        let Some(f) = self.jobs.get_mut(&rep.job_id) else {
+            return;
+        };
+        if f.job.status == JobStatus::Cancelled {
+            return;
+        }
+        f.job.status = rep.status.clone();
+        f.job.result = rep.result.clone();
+        f.job.error_log = rep.error.clone();
+        f.job.updated_at = chrono::Utc::now();
+        self.dirty_jobs.insert(rep.job_id);
+    }
+
+    /// Jobs created on a member (generator expansions, or our own forwards
+    /// echoed back) become part of the federated view.
+    fn adopt_expansion(&mut self, idx: usize, mut sub: JobSubmit) {
+        sub.jobs.retain(|j| !self.jobs.contains_key(&j.id));
+        if sub.jobs.is_empty() {
+            return;
+        }
+
+        let cluster = &self.clusters[idx];
+        if let Some(codec) = &cluster.codec {
+            if let Err(e) = codec.hydrate_jobs(&mut sub.jobs) {
+                log::warn!("Expansion from '{}' kept by reference: {}", cluster.name, e);
+            }
+        }
+
+        log::info!(
+            "🌱 Cluster '{}' expanded {} job(s)",
+            cluster.name,
+            sub.jobs.len()
+        );
+        for mut job in sub.jobs {
+            job.flow_context
+                .insert(CTX_CLUSTER.into(), json!(cluster.name));
+            job.node_id = Some(format!("cluster:{}", cluster.name));
+            if matches!(job.status, JobStatus::Pending | JobStatus::Blocked) {
+                job.status = JobStatus::Queued;
+            }
+            self.dirty_jobs.insert(job.id);
+            self.jobs.insert(
+                job.id,
+                FedJob {
+                    job,
+                    cluster: cluster.name.clone(),
+                    forwarded: true,
+                },
+            );
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Outbound: forwarding
+    // ------------------------------------------------------------------------
+
+    fn releasable(&self, f: &FedJob) -> bool {
+        !f.forwarded
+            && matches!(f.job.status, JobStatus::Pending | JobStatus::Blocked)
+            && f.job.parent_ids.iter().all(|pid| match self.jobs.get(pid) {
+                Some(p) => {
+                    p.job.status == JobStatus::Completed || (p.forwarded && p.cluster == f.cluster)
+                }
+                None => true,
+            })
+    }
+
+    /// Fails the jobs held back here by a Failed or Cancelled parent, then
+    /// the ones waiting on those, until nothing changes. They would stay
+    /// Blocked for good otherwise. The reason chains back to the root cause,
+    /// as in a member's deadlock check.
+    fn fail_orphans(&mut self) {
+        loop {
+            let dead: Vec<(Uuid, String)> = self
+                .jobs
+                .values()
+                .filter(|f| {
+                    !f.forwarded && matches!(f.job.status, JobStatus::Pending | JobStatus::Blocked)
+                })
+                .filter_map(|f| {
+                    f.job.parent_ids.iter().find_map(|pid| {
+                        let p = &self.jobs.get(pid)?.job;
+                        if !matches!(p.status, JobStatus::Failed | JobStatus::Cancelled) {
+                            return None;
+                        }
+                        let cause = match p.error_log.as_deref() {
+                            Some(e) => match e.strip_prefix(DEADLOCK_REASON_PREFIX) {
+                                Some(chain) => format!(" <- {}", chain),
+                                None => format!(": {}", e),
+                            },
+                            None => String::new(),
+                        };
+                        let short = &p.id.simple().to_string()[..8];
+                        Some((f.job.id, format!("{} {:?}{}", short, p.status, cause)))
+                    })
+                })
+                .collect();
+
+            if dead.is_empty() {
+                return;
+            }
+            for (id, chain) in dead {
+                log::warn!("✂️ {} can never run: {}", id, chain);
+                if let Some(f) = self.jobs.get_mut(&id) {
+                    f.job.status = JobStatus::Failed;
+                    f.job.error_log = Some(format!("{}{}", DEADLOCK_REASON_PREFIX, chain));
+                    f.job.updated_at = chrono::Utc::now();
+                    self.dirty_jobs.insert(id);
+                }
+            }
+        }
+    }
+
+    /// Hands every releasable job to its cluster, one JobSubmit per cluster.
+    /// Parents still running on the same member travel as ordinary deps;
+    /// completed (or foreign) parents are dropped from the forwarded copy.
+    async fn forward_ready(&mut self) -> Result<()> {
+        let mut batches: HashMap<String, JobSubmit> = HashMap::new();
+
+        loop {
+            let ready: Vec<Uuid> = self
+                .jobs
+                .values()
+                .filter(|f| self.releasable(f))
+                .map(|f| f.job.id)
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+
+            for id in ready {
+                let live_parents: Vec<Uuid> = self.jobs[&id]
+                    .job
+                    .parent_ids
+                    .iter()
+                    .filter(|pid| {
+                        self.jobs
+                            .get(pid)
+                            .is_some_and(|p| p.job.status != JobStatus::Completed)
+                    })
+                    .copied()
+                    .collect();
+
+                let Some(f) = self.jobs.get_mut(&id) else {
+                    continue;
+                };
+                f.forwarded = true;
+                f.job.status = JobStatus::Queued;
+                f.job.node_id = Some(format!("cluster:{}", f.cluster));
+                f.job.updated_at = chrono::Utc::now();
+                self.dirty_jobs.insert(id);
+
+                let mut wire = f.job.clone();
+                wire.status = JobStatus::Pending;
+                wire.node_id = None;
+                wire.parent_ids = live_parents.clone();
+
+                let batch = batches
+                    .entry(f.cluster.clone())
+                    .or_insert_with(|| JobSubmit {
+                        jobs: Vec::new(),
+                        deps: Vec::new(),
+                        routing: Routing::default(),
+                        txn: None,
+                    });
+                batch
+                    .deps
+                    .extend(live_parents.into_iter().map(|pid| (pid, id)));
+                batch.jobs.push(wire);
+            }
+        }
+
+        for (name, mut sub) in batches {
+            let sent = match self.clusters.iter_mut().find(|c| c.name == name) {
+                Some(c) => {
+                    let res = match &c.codec {
+                        Some(codec) => codec.offload_jobs(&mut sub.jobs),
+                        None => Ok(()),
+                    };
+                    match res {
+                        Ok(()) => {
+                            c.transport
+                                .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub)?)
+                                .await
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                None => Err(anyhow!("no such cluster")),
+            };
+
+            match sent {
+                Ok(()) => log::info!("🌐 Forwarded {} job(s) to '{}'", sub.jobs.len(), name),
+                Err(e) => {
+                    // Retry on the next tick
+                    log::warn!("Forwarding to '{}' failed: {}", name, e);
+                    for j in &sub.jobs {
+                        if let Some(f) = self.jobs.get_mut(&j.id) {
+                            f.forwarded = false;
+                            f.job.status = JobStatus::Pending;
+                            f.job.node_id = None;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Whatever is still waiting here is held back by a parent
+        for f in self.jobs.values_mut() {
+            if !f.forwarded && f.job.status == JobStatus::Pending {
+                f.job.status = JobStatus::Blocked;
+                self.dirty_jobs.insert(f.job.id);
+            }
+        }
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------------
+    // Persistence
+    // ------------------------------------------------------------------------
+
+    fn maybe_checkpoint(&mut self) -> Result<()> {
+        if self.last_ckpt.elapsed() < Duration::from_secs(5) || self.dirty_jobs.is_empty() {
+            return Ok(());
+        }
+        self.checkpoint_now()
+    }
+
+    /// Writes dirty jobs and the members to the store without waiting for the 5s pace.
+    pub fn checkpoint_now(&mut self) -> Result<()> {
+        let refs: Vec<&Job> = self
+            .dirty_jobs
+            .iter()
+            .filter_map(|id| self.jobs.get(id).map(|f| &f.job))
+            .collect();
+
+        // Members show up as pseudo-workers (TUI / console `workers`), last
+        // seen when their log was last read
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let members: Vec<WorkerInfo> = self
+            .clusters
+            .iter()
+            .map(|c| WorkerInfo {
+                worker_id: format!("cluster:{}", c.name),
+                cores: 0,
+                tasks: self
+                    .jobs
+                    .values()
+                    .filter(|f| f.cluster == c.name && f.job.status == JobStatus::Queued)
+                    .count(),
+                last_seen_ms: now_ms - c.last_contact.elapsed().as_millis() as i64,
+                read_lag: None,
+                gpus: 0,
+                tags: vec![],
+                hostname: None,
+                draining: false,
+                total_cores: None,
+                total_gpus: None,
+            })
+            .collect();
+
+        self.store
+            .apply_batch(self.global_cursor, &refs, &members)?;
+        for c in &self.clusters {
+            self.store
+                .set_meta(&cursor_key(&c.name), &c.cursor.to_string())?;
+        }
+        self.dirty_jobs.clear();
+        self.last_ckpt = Instant::now();
+        Ok(())
+    }
+}
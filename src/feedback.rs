@@ -0,0 +1,243 @@
+// src/feedback.rs
+//
+// =============================================================================
+// UNIFIEDLAB: GENERATION FEEDBACK (v 0.1 )
+// =============================================================================
+//
+// The Mirror.
+//
+// When a generator's physics batch has finished, its successor agent needs
+// to know how the candidates did. The Coordinator summarizes them and puts
+// the summary into the agent's `params.feedback`:
+// 1. Outcome: how many completed, failed or never finished.
+// 2. Ranking: completed candidates by energy (lowest first), each with a
+//    Boltzmann weight exp(-(E - E_min) / kT) normalized over the batch.
+// 3. Failures: which candidates failed and why.
+// 4. Diversity: distinct candidates and the spread of every numeric field.
+//
+// Agents get structured feedback and do not need to read the DB themselves.
+
+use crate::core::{Job, JobStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use uuid::Uuid;
+
+/// Param key the summary is injected under.
+pub const FEEDBACK_PARAM: &str = "feedback";
+
+/// Generator param overriding the ranking temperature (eV).
+pub const KT_PARAM: &str = "feedback_kt";
+
+/// Default ranking temperature: a few kT at room temperature, so near-ties
+/// share weight while clearly worse candidates drop out.
+pub const DEFAULT_KT_EV: f64 = 0.05;
+
+/// Entries kept per list, so a big batch does not blow up the agent's params.
+const MAX_RANKED: usize = 50;
+const MAX_FAILURES: usize = 20;
+const MAX_ERROR_CHARS: usize = 200;
+
+// ============================================================================
+// 1. SUMMARY TYPES
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationFeedback {
+    pub generator_id: Uuid,
+    /// `gen_counter` of the generator that proposed the batch.
+    pub generation: u64,
+    pub candidates: usize,
+    pub completed: usize,
+    /// Failed or cancelled.
+    pub failed: usize,
+    pub unfinished: usize,
+    /// None when no completed candidate reported an energy.
+    pub energy: Option<EnergyStats>,
+    /// Best first, at most 50.
+    pub ranked: Vec<RankedCandidate>,
+    /// At most 20.
+    pub failures: Vec<FailedCandidate>,
+    pub diversity: Diversity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnergyStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedCandidate {
+    pub job_id: Uuid,
+    pub candidate: Value,
+    pub energy: f64,
+    /// Boltzmann weight over all ranked candidates (they sum to 1).
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedCandidate {
+    pub job_id: Uuid,
+    pub candidate: Value,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Diversity {
+    /// Candidates that differ in at least one value.
+    pub distinct: usize,
+    /// Standard deviation of each top-level numeric field across candidates.
+    pub spread: BTreeMap<String, f64>,
+}
+
+// ============================================================================
+// 2. SUMMARIZING
+// ============================================================================
+
+/// The generator that proposed `job`, if it came out of an expansion.
+pub fn generated_by(job: &Job) -> Option<Uuid> {
+    job.config
+        .params
+        .get("generated_by")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+/// Summary of the jobs one generator spawned. `kt` is in eV.
+pub fn summarize(
+    generator_id: Uuid,
+    generation: u64,
+    jobs: &[&Job],
+    kt: f64,
+) -> GenerationFeedback {
+    let candidate = |j: &Job| {
+        j.config
+            .params
+            .get("candidate")
+            .cloned()
+            .unwrap_or(Value::Null)
+    };
+
+    let mut scored: Vec<(&Job, f64)> = jobs
+        .iter()
+        .filter(|j| j.status == JobStatus::Completed)
+        .filter_map(|j| Some((*j, j.result.as_ref()?.energy?.0)))
+        .filter(|(_, e)| e.is_finite())
+        .collect();
+    scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let energies: Vec<f64> = scored.iter().map(|(_, e)| *e).collect();
+    let energy = energy_stats(&energies);
+    let weights = boltzmann_weights(&energies, kt);
+
+    let ranked = scored
+        .iter()
+        .zip(&weights)
+        .take(MAX_RANKED)
+        .map(|((j, e), w)| RankedCandidate {
+            job_id: j.id,
+            candidate: candidate(j),
+            energy: *e,
+            weight: *w,
+        })
+        .collect();
+
+    let failed: Vec<&&Job> = jobs
+        .iter()
+        .filter(|j| matches!(j.status, JobStatus::Failed | JobStatus::Cancelled))
+        .collect();
+    let failures = failed
+        .iter()
+        .take(MAX_FAILURES)
+        .map(|j| FailedCandidate {
+            job_id: j.id,
+            candidate: candidate(j),
+            error: j
+                .error_log
+                .as_ref()
+                .map(|e| e.chars().take(MAX_ERROR_CHARS).collect()),
+        })
+        .collect();
+
+    let completed = jobs
+        .iter()
+        .filter(|j| j.status == JobStatus::Completed)
+        .count();
+    let candidates: Vec<Value> = jobs.iter().map(|j| candidate(j)).collect();
+
+    GenerationFeedback {
+        generator_id,
+        generation,
+        candidates: jobs.len(),
+        completed,
+        failed: failed.len(),
+        unfinished: jobs.len() - completed - failed.len(),
+        energy,
+        ranked,
+        failures,
+        diversity: diversity(&candidates),
+    }
+}
+
+fn energy_stats(energies: &[f64]) -> Option<EnergyStats> {
+    let n = energies.len() as f64;
+    let mean = energies.iter().sum::<f64>() / n;
+    Some(EnergyStats {
+        min: *energies.first()?,
+        max: *energies.last()?,
+        mean,
+        std: (energies.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / n).sqrt(),
+    })
+}
+
+/// exp(-(E - E_min) / kT), normalized. `energies` is sorted ascending.
+/// A non-positive kT puts all weight on the best candidate(s).
+fn boltzmann_weights(energies: &[f64], kt: f64) -> Vec<f64> {
+    let Some(&min) = energies.first() else {
+        return Vec::new();
+    };
+    let raw: Vec<f64> = energies
+        .iter()
+        .map(|e| {
+            if kt > 0.0 {
+                (-(e - min) / kt).exp()
+            } else if *e == min {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let total: f64 = raw.iter().sum();
+    raw.iter().map(|w| w / total).collect()
+}
+
+fn diversity(candidates: &[Value]) -> Diversity {
+    let distinct = candidates
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<HashSet<_>>()
+        .len();
+
+    let mut fields: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for obj in candidates.iter().filter_map(Value::as_object) {
+        for (k, v) in obj {
+            if let Some(x) = v.as_f64() {
+                fields.entry(k.clone()).or_default().push(x);
+            }
+        }
+    }
+    let spread = fields
+        .into_iter()
+        .map(|(k, xs)| {
+            let n = xs.len() as f64;
+            let mean = xs.iter().sum::<f64>() / n;
+            let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+            (k, var.sqrt())
+        })
+        .collect();
+
+    Diversity { distinct, spread }
+}
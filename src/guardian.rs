@@ -8,22 +8,38 @@
 //
 // Responsibilities:
 // 1. Owns the hardware (ResourceLedger).
-// 2. Plays "Tetris" with jobs (fitting them onto available cores/GPUs).
+// 2. Plays "Tetris" with jobs (fitting them onto available cores/GPUs/memory).
 // 3. Manages the lifecycle of Drivers (Setup -> Run -> Teardown).
-// 4. Updates the Checkpoint DB with final results.
-
+// 4. Updates the Checkpoint DB with final results; the work dir's files go
+//    to the CAS with a manifest in the provenance.
+// 5. Queues a JobCompleteReport per finished job for the Coordinator, each
+//    under its own idempotency key; a report whose send failed is queued
+//    again under the same key.
+// 6. Stops preempted (opportunistic), cancelled and timed-out jobs on request.
+// 7. Under `--chaos`, kills some drivers mid-run (see chaos.rs).
+// 8. Queues the progress snapshots drivers emit (`JobProgress`), sent best
+//    effort: a lost one is superseded by the next.
+// 9. Masks the job's secrets in the error a failed job stores and reports
+//    (a program's stderr may echo them).
+
+use crate::chaos::Chaos;
 use crate::checkpoint::CheckpointStore;
 use crate::core::{Job, JobStatus};
-use crate::drivers::DriverFactory;
+use crate::drivers::{DriverFactory, ProgressSink};
+use crate::logs::TraceContext;
+use crate::marketplace::{JobCompleteReport, JobProgress};
 use crate::provenance::ArtifactStore;
 use crate::resources::{ResourceLedger, Sandbox};
+use crate::secrets;
 
 use anyhow::Result;
 use chrono::Utc;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs;
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::{oneshot, Mutex, Semaphore};
+use uuid::Uuid;
 
 // ============================================================================
 // 1. THE GUARDIAN
@@ -45,6 +61,19 @@ pub struct NodeGuardian {
     // Prevents the OS from OOMing if we try to spawn 10,000 threads for
     // 10,000 tiny jobs. Limits active tasks to roughly 2x core count.
     task_limiter: Arc<Semaphore>,
+
+    // Outcomes not yet sent to the Coordinator (drained by the main loop)
+    reports: Arc<std::sync::Mutex<Vec<(Uuid, JobCompleteReport)>>>,
+
+    // Snapshots of running jobs not yet sent (drained by the main loop)
+    progress: Arc<std::sync::Mutex<Vec<JobProgress>>>,
+
+    // Stop switches for running jobs (preemption, cancellation); the value
+    // sent is what the log calls it
+    stops: Arc<std::sync::Mutex<HashMap<Uuid, oneshot::Sender<&'static str>>>>,
+
+    // Fault injection (resilience testing only)
+    chaos: Option<Arc<Chaos>>,
 }
 
 impl NodeGuardian {
@@ -75,19 +104,103 @@ impl NodeGuardian {
             artifact_store: Arc::new(artifact_store),
             db_store: Arc::new(db_store),
             task_limiter: Arc::new(Semaphore::new(max_tasks)),
+            reports: Arc::new(std::sync::Mutex::new(Vec::new())),
+            progress: Arc::new(std::sync::Mutex::new(Vec::new())),
+            stops: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            chaos: None,
         })
     }
 
+    pub fn with_chaos(mut self, chaos: Arc<Chaos>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
     /// **NEW:** Helper to get current resource availability for Heartbeats.
     /// This prevents the "Lying Heartbeat" bug by reporting ACTUAL free count.
-    pub async fn get_capacity(&self) -> (usize, usize) {
+    /// Free cores, GPUs and memory (MB).
+    pub async fn get_capacity(&self) -> (usize, usize, u64) {
         let ledger = self.ledger.lock().await;
-        (ledger.free_cores(), ledger.free_gpus())
+        (
+            ledger.free_cores(),
+            ledger.free_gpus(),
+            ledger.free_mem_mb(),
+        )
+    }
+
+    /// Cores and GPUs of the machine, busy or free.
+    pub async fn get_totals(&self) -> (usize, usize) {
+        let ledger = self.ledger.lock().await;
+        (ledger.total_cores(), ledger.total_gpus())
+    }
+
+    /// The host this Guardian runs on, as detected at startup.
+    pub async fn hostname(&self) -> String {
+        self.ledger.lock().await.hostname.clone()
+    }
+
+    /// Completion reports gathered since the last call, with the event id
+    /// to send each under.
+    pub fn drain_reports(&self) -> Vec<(Uuid, JobCompleteReport)> {
+        match self.reports.lock() {
+            Ok(mut q) => std::mem::take(&mut *q),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Puts back a report whose send failed. It keeps its event id, so the
+    /// Coordinator applies it once even if the failed send got through.
+    pub fn requeue_report(&self, event_id: Uuid, rep: JobCompleteReport) {
+        if let Ok(mut q) = self.reports.lock() {
+            q.push((event_id, rep));
+        }
+    }
+
+    /// Progress snapshots emitted since the last call, oldest first.
+    pub fn drain_progress(&self) -> Vec<JobProgress> {
+        match self.progress.lock() {
+            Ok(mut q) => std::mem::take(&mut *q),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Where the driver of `job_id` sends its snapshots.
+    fn progress_sink(&self, job_id: Uuid) -> ProgressSink {
+        let (queue, worker_id) = (self.progress.clone(), self.id.clone());
+        ProgressSink::new(move |snapshot| {
+            if let Ok(mut q) = queue.lock() {
+                q.push(JobProgress {
+                    job_id,
+                    worker_id: worker_id.clone(),
+                    snapshot,
+                });
+            }
+        })
+    }
+
+    fn queue_report(&self, job: &Job) {
+        let rep = JobCompleteReport {
+            job_id: job.id,
+            worker_id: self.id.clone(),
+            status: job.status.clone(),
+            result: job.result.clone(),
+            error: job.error_log.clone(),
+        };
+        if let Ok(mut q) = self.reports.lock() {
+            q.push((Uuid::new_v4(), rep));
+        }
     }
 
     /// The Main Entry Point.
     /// Tries to accept a job. Returns true if accepted (spawned), false if rejected (no resources).
     pub async fn try_accept_job(&self, job: Job) -> bool {
+        // 0. A restarted Coordinator may grant a job that is still running
+        // here; the running attempt will report it
+        if self.stops.lock().is_ok_and(|s| s.contains_key(&job.id)) {
+            log::debug!("Job {} is already running here", job.id);
+            return true;
+        }
+
         // 1. Check Concurrency Limit (Fail fast if system overloaded)
         let permit = match self.task_limiter.clone().try_acquire_owned() {
             Ok(p) => p,
@@ -97,21 +210,39 @@ impl NodeGuardian {
         // 2. Check Hardware Resources (The Tetris Step)
         let sandbox = {
             let mut ledger = self.ledger.lock().await;
-            ledger.try_allocate(job.resources.cores, job.resources.gpus)
+            ledger.try_allocate(
+                job.resources.cores,
+                job.resources.gpus,
+                job.resources.memory_mb,
+            )
         };
 
         match sandbox {
             Some(sb) => {
+                // Fresh span per attempt; every log line of the run carries it.
+                let trace = TraceContext::for_job(&job);
                 log::info!(
-                    "✅ Job {} accepted. Assigned: {}",
+                    "{} ✅ Job {} accepted. Assigned: {}",
+                    trace,
                     job.id.to_string().chars().take(8).collect::<String>(),
                     self.fmt_sandbox(&sb)
                 );
 
                 // Spawn the execution task detached from the main loop
                 let guardian_ref = self.clone();
+                let job_id = job.id;
+                let (stop_tx, stop_rx) = oneshot::channel();
+                if let Ok(mut stops) = self.stops.lock() {
+                    stops.insert(job_id, stop_tx);
+                }
                 tokio::spawn(async move {
-                    guardian_ref.execute_lifecycle(job, sb).await;
+                    tokio::select! {
+                        _ = guardian_ref.execute_lifecycle(job, sb.clone(), trace) => {}
+                        Ok(why) = stop_rx => guardian_ref.abandon(job_id, &sb, why).await,
+                    }
+                    if let Ok(mut stops) = guardian_ref.stops.lock() {
+                        stops.remove(&job_id);
+                    }
                     drop(permit); // Release semaphore only after job finishes
                 });
 
@@ -125,6 +256,30 @@ impl NodeGuardian {
         }
     }
 
+    /// Stops running jobs the Coordinator took back. Dropping the lifecycle
+    /// kills the engine process; no completion report is sent.
+    pub fn preempt(&self, job_ids: &[Uuid]) -> usize {
+        self.stop(job_ids, "preempted")
+    }
+
+    /// Stops running jobs the Coordinator settled without them (cancelled,
+    /// or past their time limit), the same way as `preempt`.
+    pub fn cancel(&self, job_ids: &[Uuid]) -> usize {
+        self.stop(job_ids, "stopped")
+    }
+
+    fn stop(&self, job_ids: &[Uuid], why: &'static str) -> usize {
+        let mut stops = match self.stops.lock() {
+            Ok(s) => s,
+            Err(_) => return 0,
+        };
+        job_ids
+            .iter()
+            .filter_map(|id| stops.remove(id))
+            .filter_map(|tx| tx.send(why).ok())
+            .count()
+    }
+
     fn fmt_sandbox(&self, sb: &Sandbox) -> String {
         let c = if sb.cores.len() > 4 {
             format!(
@@ -135,7 +290,10 @@ impl NodeGuardian {
         } else {
             format!("Cores{:?}", sb.cores)
         };
-        format!("{} GPUs{:?}", c, sb.gpus)
+        match sb.memory_mb_limit {
+            Some(mb) => format!("{} GPUs{:?} Mem[{}MB]", c, sb.gpus, mb),
+            None => format!("{} GPUs{:?}", c, sb.gpus),
+        }
     }
 }
 
@@ -144,17 +302,16 @@ impl NodeGuardian {
 // ============================================================================
 
 impl NodeGuardian {
-    async fn execute_lifecycle(&self, mut job: Job, sandbox: Sandbox) {
+    async fn execute_lifecycle(&self, mut job: Job, sandbox: Sandbox, trace: TraceContext) {
         let job_id = job.id;
 
         // A. SETUP WORKSPACE
         // Use a temp directory for the execution duration.
         // On HPC, this usually maps to /tmp or $TMPDIR (often local NVMe).
-        let work_dir_name = format!("ulab_{}", job_id);
-        let work_dir = std::env::temp_dir().join(&work_dir_name);
+        let work_dir = workspace(job_id);
 
         if let Err(e) = fs::create_dir_all(&work_dir).await {
-            self.fail_job(job, "Workspace Creation Failed", e.to_string())
+            self.fail_job(job, &trace, "Workspace Creation Failed", e.to_string())
                 .await;
             self.free_resources(&sandbox).await;
             return;
@@ -167,36 +324,66 @@ impl NodeGuardian {
         job.updated_at = Utc::now();
 
         if let Err(e) = self.db_store.apply_batch(0, &[&job], &[]) {
-            log::warn!("Failed to mark job {} as running: {}", job_id, e);
+            log::warn!("{} Failed to mark job {} as running: {}", trace, job_id, e);
         }
 
         // B. EXECUTE DRIVER
-        let result = async {
+        let progress = self.progress_sink(job_id);
+        let run = async {
             let driver = DriverFactory::get(&job.config.engine)?;
-            driver.execute(&job, &sandbox, &work_dir).await
-        }
-        .await;
+            driver
+                .execute(&job, &sandbox, &work_dir, &trace, &progress)
+                .await
+        };
+        let result = match self.chaos.as_ref().and_then(|c| c.driver_kill_after()) {
+            Some(after) => tokio::select! {
+                res = run => res,
+                _ = tokio::time::sleep(after) => {
+                    Err(anyhow::anyhow!("chaos: driver killed after {:?}", after))
+                }
+            },
+            None => run.await,
+        };
 
         // C. FINALIZE & CLEANUP
         match result {
-            Ok(calc_res) => {
+            Ok(mut calc_res) => {
+                // Keep the work dir's files for `unifiedlab repro`
+                let files = &mut calc_res.provenance.files;
+                if let Err(e) = self.artifact_store.capture_workdir(&work_dir, files) {
+                    log::warn!(
+                        "{} Failed to store work dir of Job {}: {}",
+                        trace,
+                        job_id,
+                        e
+                    );
+                }
+
                 job.status = JobStatus::Completed;
                 job.result = Some(calc_res);
                 job.updated_at = Utc::now();
 
                 // Save complete state to DB
                 if let Err(e) = self.db_store.apply_batch(0, &[&job], &[]) {
-                    log::error!("Failed to save result for Job {} to DB: {}", job_id, e);
+                    log::error!(
+                        "{} Failed to save result for Job {} to DB: {}",
+                        trace,
+                        job_id,
+                        e
+                    );
                 } else {
                     log::info!(
-                        "🏁 Job {} Finished. Time: {:.2}s",
+                        "{} 🏁 Job {} Finished. Time: {:.2}s",
+                        trace,
                         job_id,
                         job.result.as_ref().unwrap().t_total_ms / 1000.0
                     );
                 }
+                self.queue_report(&job);
             }
             Err(e) => {
-                self.fail_job(job, "Driver Error", e.to_string()).await;
+                self.fail_job(job, &trace, "Driver Error", e.to_string())
+                    .await;
             }
         }
 
@@ -207,33 +394,57 @@ impl NodeGuardian {
         // 2. Remove Workspace (Cleanup)
         // We only clean up if successful or if configured to always clean.
         if let Err(e) = fs::remove_dir_all(&work_dir).await {
-            log::warn!("Failed to cleanup workspace {:?}: {}", work_dir, e);
+            log::warn!(
+                "{} Failed to cleanup workspace {:?}: {}",
+                trace,
+                work_dir,
+                e
+            );
         }
     }
 
+    /// Cleanup for a lifecycle that was dropped mid-flight.
+    async fn abandon(&self, job_id: Uuid, sandbox: &Sandbox, why: &str) {
+        self.free_resources(sandbox).await;
+        let _ = fs::remove_dir_all(workspace(job_id)).await;
+        log::info!(
+            "⏏️ Job {} {}",
+            job_id.to_string().chars().take(8).collect::<String>(),
+            why
+        );
+    }
+
     async fn free_resources(&self, sandbox: &Sandbox) {
         let mut ledger = self.ledger.lock().await;
         ledger.free(sandbox);
     }
 
-    async fn fail_job(&self, mut job: Job, reason: &str, details: String) {
+    async fn fail_job(&self, mut job: Job, trace: &TraceContext, reason: &str, details: String) {
         log::error!(
-            "💥 Job {} Failed: {} - {}",
+            "{} 💥 Job {} Failed: {} - {}",
+            trace,
             job.id.to_string().chars().take(8).collect::<String>(),
             reason,
             details
         );
 
         job.status = JobStatus::Failed;
-        job.error_log = Some(format!("{}: {}", reason, details));
+        job.error_log = Some(secrets::redact(&format!("{}: {}", reason, details)).into_owned());
         job.updated_at = Utc::now();
 
         if let Err(e) = self.db_store.apply_batch(0, &[&job], &[]) {
             log::error!(
-                "Failed to save failure state for Job {} to DB: {}",
+                "{} Failed to save failure state for Job {} to DB: {}",
+                trace,
                 job.id,
                 e
             );
         }
+        self.queue_report(&job);
     }
 }
+
+/// Per-job scratch directory under the system temp dir.
+fn workspace(job_id: Uuid) -> PathBuf {
+    std::env::temp_dir().join(format!("ulab_{}", job_id))
+}
@@ -7,18 +7,32 @@
 // This file declares the module tree and exports public types.
 
 // 1. Declare Modules
+pub mod benchmark;
+pub mod chaos;
 pub mod checkpoint;
+pub mod console;
 pub mod core;
+pub mod deadline;
 pub mod drivers;
+pub mod dryrun;
 pub mod eventlog;
+pub mod export;
+pub mod federation;
+pub mod feedback;
 pub mod guardian;
 pub mod logs;
 pub mod marketplace;
 pub mod physics;
 pub mod provenance;
+pub mod quota;
+pub mod report;
 pub mod resources;
+pub mod schema;
+pub mod secrets;
+pub mod structure_io;
 pub mod transport;
 pub mod tui;
+pub mod wire;
 pub mod workflow;
 
 pub mod dsl;
@@ -8,11 +8,17 @@
 // and stores them for display in the TUI (Dashboard).
 //
 // It decouples log generation (Drivers/Guardian) from log rendering (TUI).
+// Both it and the stderr logger mask job secrets (`secrets::redact`).
 
+use crate::core::Job;
+use crate::secrets;
 use chrono::Local;
-use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::borrow::Cow;
 use std::collections::VecDeque;
+use std::fmt;
 use std::sync::{Arc, Mutex};
+use uuid::Uuid;
 
 // ============================================================================
 // 1. THE BUFFER (State)
@@ -66,6 +72,51 @@ impl TuiLogger {
     }
 }
 
+// ============================================================================
+// 3. TRACE CONTEXT (Cross-Process Correlation)
+// ============================================================================
+
+/// Env var names exported to adapters, binaries and agents.
+pub const ENV_TRACE_ID: &str = "ULAB_TRACE_ID";
+pub const ENV_SPAN_ID: &str = "ULAB_SPAN_ID";
+pub const ENV_JOB_ID: &str = "ULAB_JOB_ID";
+
+/// Identifies one execution attempt of a job.
+/// `trace_id` is stable for the job; `span_id` is fresh per attempt, so a
+/// retried job can be told apart in Python stack traces.
+#[derive(Debug, Clone)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub span_id: String,
+    pub job_id: Uuid,
+}
+
+impl TraceContext {
+    pub fn for_job(job: &Job) -> Self {
+        Self {
+            trace_id: job.trace_id.clone(),
+            span_id: Uuid::new_v4().simple().to_string()[..8].to_string(),
+            job_id: job.id,
+        }
+    }
+
+    /// Key/value pairs to export into child process environments.
+    pub fn env_vars(&self) -> [(&'static str, String); 3] {
+        [
+            (ENV_TRACE_ID, self.trace_id.clone()),
+            (ENV_SPAN_ID, self.span_id.clone()),
+            (ENV_JOB_ID, self.job_id.to_string()),
+        ]
+    }
+}
+
+/// Renders as the log tag `[trace=<id> span=<id>]`.
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[trace={} span={}]", self.trace_id, self.span_id)
+    }
+}
+
 impl log::Log for TuiLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
         // Only capture Info and above (Warn, Error) to prevent noise
@@ -81,8 +132,8 @@ impl log::Log for TuiLogger {
             let target = target_full.split("::").last().unwrap_or(target_full);
 
             // Color/Format hints could be added here, but raw strings are safer for now
-            self.buffer
-                .push(format!("[{} {}] {}", timestamp, target, record.args()));
+            let line = format!("[{} {}] {}", timestamp, target, record.args());
+            self.buffer.push(secrets::redact(&line).into_owned());
         }
     }
 
@@ -90,3 +141,45 @@ impl log::Log for TuiLogger {
         // No-op: Memory buffer flushes immediately
     }
 }
+
+// ============================================================================
+// 4. THE STDERR LOGGER
+// ============================================================================
+
+/// Installs `env_logger` (RUST_LOG, else `default_filter`) as the global
+/// logger, with job secrets masked in every line.
+pub fn init_env_logger(default_filter: &str) -> Result<(), SetLoggerError> {
+    let inner =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter))
+            .build();
+    let max_level = inner.filter();
+    log::set_boxed_logger(Box::new(Redacting(inner))).map(|()| log::set_max_level(max_level))
+}
+
+struct Redacting<L>(L);
+
+impl<L: Log> Log for Redacting<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let text = record.args().to_string();
+        match secrets::redact(&text) {
+            Cow::Borrowed(_) => self.0.log(record),
+            Cow::Owned(masked) => self.0.log(
+                &Record::builder()
+                    .args(format_args!("{}", masked))
+                    .metadata(record.metadata().clone())
+                    .module_path(record.module_path())
+                    .file(record.file())
+                    .line(record.line())
+                    .build(),
+            ),
+        }
+    }
+
+    fn flush(&self) {
+        self.0.flush()
+    }
+}
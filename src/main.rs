@@ -8,8 +8,29 @@
 //
 // Modes:
 // 1. START:  Boots the NodeGuardian (Resource Manager) and Coordinator (Lighthouse).
-// 2. DEPLOY: Parses Blueprint (.drawio), injects params, submits to Cluster.
+// 2. DEPLOY: Parses Blueprint (.drawio / .yaml), injects params, submits to Cluster.
+//           --dry-run simulates its scheduling on the live workers instead.
 // 3. TUI:    Launches the Terminal Dashboard.
+// 4. CONSOLE: Interactive REPL against a running Coordinator.
+// 5. FEDERATE: Top-level Lighthouse forwarding to several cluster Coordinators.
+// 6. REPORT: Offline summaries from the checkpoint DB (e.g. efficiency).
+// 7. BENCHMARK: Synthetic suite against a live cluster, timed and reported.
+// 8. GRAPH:  Edits the live DAG of a running Coordinator (add/remove edges, nodes).
+// 9. RUN:    Coordinator + Guardian in one process, one workflow to completion.
+// 10. REPLAY: Prints an event log, inbox or outbox as filtered JSON lines.
+// 11. REPRO:  Rebuilds a finished job's work dir from the artifact store.
+// 12. LINT:   Checks a YAML workflow, reporting problems by line and column.
+// 13. SCHEMA: Prints the JSON Schema of the YAML workflow format.
+// 14. MIGRATE: Rewrites a YAML workflow in the current DSL version.
+// 15. CANCEL: Cancels jobs of a running Coordinator by id, workflow or filter.
+// 16. STATUS: One-shot campaign status (jobs, workers, throughput), or JSON.
+// 17. LOGS:   A job's error, provenance and the tail of its captured output.
+// 18. VALIDATE: Loads a blueprint as deploy would and checks it fits this machine.
+// 19. EXPORT: Completed jobs as a CSV, JSON or Parquet table for pandas.
+// 20. RETRY:  Queues failed (or cancelled) jobs of a running Coordinator again.
+// 21. PAUSE/RESUME: Holds back (or releases) new grants, for all or one workflow.
+// 22. PURGE:  Deletes old finished jobs and, optionally, the artifacts they freed.
+// 23. WORKERS: Lists the workers that joined, their capacity and which went stale.
 //
 // Key Features:
 // - Auto-Detection of Roles (Rank 0 vs Rank N).
@@ -18,9 +39,10 @@
 // - True Capacity Heartbeats (Prevent over-scheduling).
 
 use anyhow::{anyhow, Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use serde_json::Value;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -31,32 +53,69 @@ use tokio::signal;
 use tokio::time::sleep;
 
 // --- MODULES ---
+mod benchmark;
+mod chaos;
 mod checkpoint;
+mod console;
 mod core;
+mod deadline;
 mod drivers;
+mod dryrun;
+// The binary only loads workflows; writing them back is library-only
+#[allow(dead_code)]
+mod dsl;
 mod eventlog;
+mod export;
+mod federation;
+mod feedback;
 mod guardian;
 mod logs;
 mod marketplace;
 mod physics;
 mod provenance;
+mod quota;
+mod report;
 mod resources;
+mod schema;
+mod secrets;
+mod structure_io;
 mod transport;
 mod tui;
+mod wire;
 mod workflow;
 
-use crate::checkpoint::CheckpointStore;
-use crate::core::{Job, JobStatus};
+use crate::benchmark::{BenchmarkReport, ScenarioReport, DEFAULT_SUITE};
+use crate::chaos::{Chaos, ChaosConfig, ChaosTransport, DEFAULT_CHAOS};
+use crate::checkpoint::{CheckpointStore, MemoCache, RetentionPolicy, StoreConfig};
+use crate::console::{Console, ConsoleCommand};
+use crate::core::{FileRole, Job, JobStatus, JobSummary};
+use crate::deadline::{parse_deadline, FLOW_DEADLINE};
+use crate::drivers::utils::{STDERR_FILE, STDOUT_FILE};
+use crate::eventlog::replay::{self, ReplayFilter};
+use crate::eventlog::{Compression, EventLogReader, Manifest};
+use crate::export::ExportFormat;
+use crate::federation::{FederationConfig, FederationLighthouse, FEDERATION_CONFIG_FILE};
 use crate::guardian::NodeGuardian;
 use crate::logs::{LogBuffer, TuiLogger};
 use crate::marketplace::{
-    JobSubmit, MarketplaceCoordinator, WorkGrant, WorkRequest, EV_JOB_SUBMIT, EV_WORK_GRANT,
-    MSG_WORK_REQUEST,
+    ControlCommand, ControlRequest, GrantAck, JobArray, JobCancel, JobFilter, JobSubmit,
+    MarketplaceCoordinator, Routing, SubmitAck, SubmitEnd, WorkGrant, WorkPreempt, WorkRequest,
+    WorkYield, WorkerDrain, EV_JOB_CANCEL, EV_JOB_SUBMIT, EV_SUBMIT_ACK, EV_WORK_GRANT,
+    EV_WORK_PREEMPT, MSG_CONTROL, MSG_GRANT_ACK, MSG_JOB_ARRAY, MSG_JOB_COMPLETE, MSG_JOB_PROGRESS,
+    MSG_SUBMIT_END, MSG_WORKER_DRAIN, MSG_WORK_REQUEST, MSG_WORK_YIELD, SUBMIT_CHUNK_JOBS,
+    WORKER_HEARTBEAT_EVERY,
 };
+use crate::provenance::{ArtifactStore, ContentType};
+use crate::quota::{QuotaConfig, FLOW_PROJECT, QUOTA_CONFIG_FILE};
 use crate::resources::{ClusterType, ResourceLedger};
-use crate::transport::{FileTransport, Role, Transport};
+use crate::transport::{
+    mem::MemNetwork, Role, Transport, TransportConfig, TransportFactory, TransportKind,
+    TRANSPORT_CONFIG_FILE,
+};
+use crate::wire::StructureCodec;
+use crate::workflow::blueprint::{build_submission, load_blueprint};
 use crate::workflow::importer::DrawIoLoader;
-use crate::workflow::NodeType;
+use crate::workflow::{NodeType, WorkflowEngine};
 
 // ============================================================================
 // 1. CLI DEFINITION
@@ -93,11 +152,21 @@ enum Commands {
         /// Use: --tags brain --tags muscle
         #[arg(long, num_args = 1..)]
         tags: Vec<String>,
+
+        #[command(flatten)]
+        scheduler: SchedulerOpts,
+
+        #[command(flatten)]
+        transport: TransportOpts,
+
+        /// Fault injection for resilience testing (see chaos.rs).
+        #[arg(long, hide = true, num_args = 0..=1, default_missing_value = DEFAULT_CHAOS)]
+        chaos: Option<ChaosConfig>,
     },
 
-    /// Deploy a Blueprint (.drawio) to the cluster.
+    /// Deploy a Blueprint (.drawio or .yaml) to the cluster.
     Deploy {
-        /// Path to .drawio file.
+        /// Path to .drawio or .yaml file.
         #[arg(long)]
         file: String,
 
@@ -108,323 +177,2828 @@ enum Commands {
         /// JSON string to override params (e.g. '{"gen_limit": 50}').
         #[arg(long)]
         params: Option<String>,
+
+        /// YAML only: set one of the workflow's vars (e.g. --set temperature=600).
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        set: Vec<String>,
+
+        /// Federation only: default member cluster for every job.
+        #[arg(long)]
+        cluster: Option<String>,
+
+        /// Federation only: send one engine to one cluster (e.g. --route janus=gpubox).
+        #[arg(long, value_name = "ENGINE=CLUSTER")]
+        route: Vec<String>,
+
+        #[command(flatten)]
+        submit: SubmitOpts,
+
+        #[command(flatten)]
+        transport: TransportOpts,
     },
 
     /// Launch Monitoring Dashboard.
     Tui {
+        /// Checkpoint DB to watch; repeat to switch between deployments with [ and ].
         #[arg(long, default_value = "checkpoint.db")]
-        checkpoint: String,
+        checkpoint: Vec<String>,
     },
-}
 
-// ============================================================================
-// 2. ENTRY POINT
-// ============================================================================
+    /// Interactive console for a running Coordinator (jobs, why, cancel, pause...).
+    Console {
+        /// Root directory of the running cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+        #[command(flatten)]
+        transport: TransportOpts,
+    },
 
-    // Init Logger (standard env_logger unless TUI mode)
-    if !matches!(cli.command, Commands::Tui { .. }) {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-    }
+    /// Edit the live DAG of a running Coordinator (forgotten dependencies, extra jobs).
+    Graph {
+        /// Root directory of the running cluster.
+        #[arg(long, default_value = ".", global = true)]
+        root: String,
 
-    match cli.command {
-        Commands::Start {
-            root,
-            force_local,
-            id,
-            tags,
-        } => run_node_service(root, force_local, id, tags).await,
-        Commands::Deploy { file, root, params } => run_deployer(file, root, params).await,
-        Commands::Tui { checkpoint } => run_tui(checkpoint),
-    }
-}
+        #[command(subcommand)]
+        op: GraphOp,
 
-// ============================================================================
-// 3. RUNTIME: NODE SERVICE (Guardian + Coordinator)
-// ============================================================================
+        #[command(flatten)]
+        transport: TransportOpts,
+    },
 
-async fn run_node_service(
-    root: String,
-    force_local: bool,
-    manual_id: Option<String>,
-    manual_tags: Vec<String>,
-) -> Result<()> {
-    let root_path = PathBuf::from(&root);
-    let shutdown_signal = Arc::new(AtomicBool::new(false));
+    /// Cancel jobs of a running Coordinator: by id, a whole workflow, or a filter.
+    Cancel {
+        /// Job ids (or unique prefixes).
+        ids: Vec<String>,
 
-    // A. DETECT ENVIRONMENT & TOPOLOGY
-    // We use the Ledger to see where we are (Slurm vs Local).
-    let ledger = ResourceLedger::detect();
+        /// Root directory of the running cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
 
-    // Safety: Prevent accidental heavy runs on laptops without --force-local
-    if ledger.cluster_type == ClusterType::Local && !force_local {
-        return Err(anyhow!(
-            "SAFETY: Local execution detected. Use --force-local to run on workstation/laptop."
-        ));
-    }
+        /// Jobs deployed from this workflow (its metadata.name, or the
+        /// .drawio file name without extension).
+        #[arg(long)]
+        workflow: Option<String>,
 
-    // Identify Rank (0 = Coordinator/Lighthouse)
-    // In Slurm, SLURM_PROCID is reliable. Locally, we default to 0.
-    let rank = std::env::var("SLURM_PROCID").unwrap_or_else(|_| "0".into());
-    let is_coordinator = rank == "0";
+        /// Jobs counted against this project (`deploy --project`).
+        #[arg(long)]
+        project: Option<String>,
 
-    let worker_id = manual_id.unwrap_or_else(|| format!("{}_r{}", ledger.hostname, rank));
+        /// Jobs run by this engine (e.g. vasp, lammps).
+        #[arg(long)]
+        engine: Option<String>,
 
-    // B. SMART TAGGING STRATEGY
-    // Brain = Can run Agents/Generators. Muscle = Can run heavy physics.
-    let tags = if !manual_tags.is_empty() {
-        manual_tags
-    } else if ledger.cluster_type == ClusterType::Local {
-        // Local: Must be everything
-        vec!["brain".into(), "muscle".into(), "gpu".into()]
-    } else if is_coordinator {
-        // Rank 0: The Brain (manages DB, runs light Agents)
-        vec!["brain".into()]
-    } else {
-        // Rank N: The Muscle (runs heavy Physics)
-        vec!["muscle".into(), "gpu".into()] // Assumes GPU nodes
-    };
+        /// Jobs in this status (repeatable).
+        #[arg(long, value_enum)]
+        status: Vec<CancelStatus>,
 
-    log::info!(
-        "🚀 Booting Node {} | Role: Guardian {}",
-        worker_id,
-        if is_coordinator {
-            "+ Lighthouse 👑"
-        } else {
-            ""
-        }
-    );
-    log::info!("🏷️  Capabilities: {:?}", tags);
+        /// Select every unfinished job when nothing else narrows it.
+        #[arg(long)]
+        all: bool,
 
-    // C. BOOT COORDINATOR (If Rank 0)
-    let db_path = root_path.join("checkpoint.db");
-    let store = CheckpointStore::open(&db_path).context("DB Init")?;
+        /// List the jobs that would be cancelled (as of the last checkpoint).
+        #[arg(long)]
+        dry_run: bool,
 
-    if is_coordinator {
-        let coord_root = root_path.clone();
-        let coord_sig = shutdown_signal.clone();
-        let coord_store = CheckpointStore::open(&db_path)?; // Clone connection
+        #[command(flatten)]
+        transport: TransportOpts,
+    },
 
-        tokio::spawn(async move {
-            log::info!("👑 Lighthouse Service Starting...");
-            if let Err(e) = run_coordinator_loop(coord_root, coord_store, coord_sig).await {
-                log::error!("👑 Lighthouse CRASHED: {}", e);
-                std::process::exit(1); // Fatal
-            }
-        });
-        // Give DB a moment to settle
-        sleep(Duration::from_millis(500)).await;
-    }
+    /// Queue failed (or cancelled) jobs of a running Coordinator again.
+    Retry {
+        /// Job ids (or unique prefixes).
+        ids: Vec<String>,
 
-    // D. BOOT GUARDIAN (The Local Scheduler)
-    let guardian = NodeGuardian::boot(worker_id.clone(), &root_path, store).await?;
+        /// Root directory of the running cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
 
-    // Transport for this worker (Inbox Reader)
-    let mut transport = FileTransport::new(&root_path, Role::Worker, Some(&worker_id)).await?;
+        /// Jobs in this status (repeatable).
+        #[arg(long, value_enum, default_value = "failed")]
+        status: Vec<RetryStatus>,
 
-    // E. SIGNAL HANDLING
-    let sig_term = shutdown_signal.clone();
-    tokio::spawn(async move {
-        signal::ctrl_c().await.ok();
-        log::warn!("🛑 Interrupt received. Stopping...");
-        sig_term.store(true, Ordering::SeqCst);
-    });
+        /// Narrow by workflow=<name>, project=<name> or engine=<name> (repeatable).
+        #[arg(long)]
+        filter: Vec<String>,
 
-    // F. MAIN EVENT LOOP
-    log::info!("🛡️ Guardian Active. Polling inbox...");
+        #[command(flatten)]
+        transport: TransportOpts,
+    },
 
-    // Local Backlog: Jobs accepted by protocol but waiting for Guardian resources
-    let mut backlog: VecDeque<Job> = VecDeque::new();
-    let mut last_heartbeat = Instant::now();
-    let hb_interval = Duration::from_secs(10);
+    /// Stop a running Coordinator handing out new work; running jobs finish.
+    Pause {
+        /// Only jobs deployed from this workflow.
+        #[arg(long)]
+        workflow: Option<String>,
 
-    while !shutdown_signal.load(Ordering::SeqCst) {
-        // 1. HEARTBEAT
-        if last_heartbeat.elapsed() > hb_interval {
-            // FIX: Ask Guardian for REAL capacity.
-            // This ensures we report what is actually free in the Ledger bitmask.
-            let (free_cores, free_gpus) = guardian.get_capacity().await;
+        /// Root directory of the running cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
 
-            let req = WorkRequest {
-                worker_id: worker_id.clone(),
-                available_cores: free_cores,
-                available_gpus: free_gpus,
-                max_jobs: 64, // Queue depth limit
-                tags: tags.clone(),
-            };
+        #[command(flatten)]
+        transport: TransportOpts,
+    },
 
-            // We write to our own output log which Coordinator reads
-            if let Err(e) = transport
-                .send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req)?)
-                .await
-            {
-                log::error!("Heartbeat failed: {}", e);
-            }
-            last_heartbeat = Instant::now();
-        }
+    /// Let a paused Coordinator (or workflow) hand out work again.
+    Resume {
+        /// Only jobs deployed from this workflow.
+        #[arg(long)]
+        workflow: Option<String>,
 
-        // 2. PROCESS BACKLOG (Try to shove queued jobs into Guardian)
-        let mut rotated = 0;
-        let q_len = backlog.len();
-        while rotated < q_len {
-            if let Some(job) = backlog.pop_front() {
-                if guardian.try_accept_job(job.clone()).await {
-                    // Success: Guardian took it
-                } else {
-                    // Fail: Resources still full, rotate back
-                    backlog.push_back(job);
-                }
-            }
-            rotated += 1;
-        }
+        /// Root directory of the running cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
 
-        // 3. CHECK INBOX (New Grants)
-        let events = transport.recv_broadcasts().await.unwrap_or_default();
-        for env in events {
-            if env.record.kind == EV_WORK_GRANT {
-                if let Ok(grant) = serde_json::from_value::<WorkGrant>(env.record.payload) {
-                    if grant.worker_id == worker_id {
-                        log::info!(
-                            "📨 Received Grant {} ({} jobs)",
-                            grant.grant_id,
-                            grant.jobs.len()
-                        );
+        #[command(flatten)]
+        transport: TransportOpts,
+    },
 
-                        for job in grant.jobs {
-                            if !guardian.try_accept_job(job.clone()).await {
-                                log::debug!("⏳ Job {} queued locally (Busy)", job.id);
-                                backlog.push_back(job);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    /// Run a Federation Lighthouse that forwards to several cluster Coordinators.
+    Federate {
+        /// Root directory of the Federation (DB, inboxes, events.log).
+        #[arg(long, default_value = ".")]
+        root: String,
 
-        // 4. PREVENT BUSY LOOP
-        sleep(Duration::from_millis(200)).await; // this section is critical as it defines how long each operation awaits for min
-    }
+        /// Member clusters (default: <root>/federation.yaml).
+        #[arg(long)]
+        config: Option<PathBuf>,
 
-    log::info!("👋 Node Shutdown Complete.");
-    Ok(())
-}
+        #[command(flatten)]
+        transport: TransportOpts,
+    },
 
-// Logic for Rank 0
-async fn run_coordinator_loop(
-    root: PathBuf,
-    store: CheckpointStore,
-    stop_signal: Arc<AtomicBool>,
-) -> Result<()> {
-    let transport = FileTransport::new(&root, Role::Coordinator, None)
-        .await
-        .context("Coord Transport")?;
+    /// Job counts, worker load, throughput and the oldest pending job, without the TUI.
+    Status {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
 
-    let mut coord = MarketplaceCoordinator::open(Box::new(transport), store).await?;
-    log::info!("✅ Coordinator Logic Active.");
+        /// Hours of finished jobs to measure throughput over.
+        #[arg(long, default_value_t = report::DEFAULT_STATUS_WINDOW_H)]
+        hours: u64,
 
-    while !stop_signal.load(Ordering::SeqCst) {
-        if let Err(e) = coord.tick().await {
-            log::error!("Coordinator Tick Error: {}", e);
-        }
-        sleep(Duration::from_millis(100)).await;
-    }
-    Ok(())
-}
+        /// Print JSON instead of tables.
+        #[arg(long)]
+        json: bool,
+    },
 
-// ============================================================================
-// 4. DEPLOYER: THE ARCHITECT
-// ============================================================================
+    /// Workers that joined the cluster: tags, free/total cores and GPUs, last seen.
+    Workers {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
 
-async fn run_deployer(file: String, root: String, overrides: Option<String>) -> Result<()> {
-    let root_path = PathBuf::from(&root);
-    log::info!("📐 Parsing Blueprint: {}", file);
+        /// Seconds without a heartbeat before a worker counts as stale
+        /// (match `start --worker-timeout`).
+        #[arg(long, default_value_t = 60)]
+        stale_after: u64,
 
-    // 1. Load Blueprint
-    let mut loader = DrawIoLoader::load_from_file(&file).context("Failed to load Draw.io")?;
+        /// Fail unless at least this many workers are live (e.g. $SLURM_NNODES).
+        #[arg(long)]
+        expect: Option<usize>,
 
-    // FIX: Access internal graph structure via .graph.graph
-    log::info!("   Found {} nodes.", loader.graph.graph.node_count());
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
 
-    // 2. Apply Overrides
-    if let Some(ov) = overrides {
-        let ov_json: Value = serde_json::from_str(&ov).context("Invalid overrides JSON")?;
-        log::info!("   Applying overrides: {}", ov);
-
-        for idx in loader.graph.graph.node_indices() {
-            let node = &mut loader.graph.graph[idx];
-            if matches!(node.node_type, NodeType::Generator { .. }) {
-                if let Some(params) = node.job.config.params.as_object_mut() {
-                    if let Some(ov_obj) = ov_json.as_object() {
-                        for (k, v) in ov_obj {
-                            params.insert(k.clone(), v.clone());
-                        }
-                    }
-                }
-            }
-        }
-    }
+    /// Completed jobs (engine, params, energy, forces, timing, provenance) as one table.
+    Export {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
 
-    // 3. Setup Transport (As Architect)
-    // The architect acts like a "Worker" who only sends EV_JOB_SUBMIT
-    let arch_id = format!(
-        "architect_{}",
-        uuid::Uuid::new_v4()
-            .to_string()
-            .chars()
-            .take(8)
-            .collect::<String>()
-    );
-    let mut transport = FileTransport::new(&root_path, Role::Worker, Some(&arch_id)).await?;
+        /// csv, json (an array of records) or parquet (needs feature "parquet").
+        #[arg(long, default_value = "csv")]
+        format: ExportFormat,
 
-    // 4. Construct Payload
-    let mut jobs = Vec::new();
-    let mut deps = Vec::new();
+        /// Directory to write results.<format> into.
+        #[arg(long, default_value = "results")]
+        out: PathBuf,
 
-    for idx in loader.graph.graph.node_indices() {
-        let node = &loader.graph.graph[idx];
-        let mut job = node.job.clone();
+        /// Param to keep as a column; repeatable (default: every scalar param).
+        #[arg(long)]
+        param: Vec<String>,
+    },
 
-        // Critical: Inject Flow Context so Coordinator knows Node Type
-        job.flow_context
-            .insert("node_type".into(), serde_json::to_value(&node.node_type)?);
-        job.status = JobStatus::Pending;
-        jobs.push(job);
-    }
+    /// Delete finished jobs older than a cutoff from the checkpoint DB.
+    Purge {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
 
-    // Extract Edges
-    use petgraph::visit::EdgeRef;
-    for edge in loader.graph.graph.edge_references() {
-        let src = loader.graph.graph[edge.source()].job.id;
-        let dst = loader.graph.graph[edge.target()].job.id;
-        deps.push((src, dst));
-    }
+        /// Jobs not updated for this many hours.
+        #[arg(long)]
+        older_than_hours: u64,
 
-    // 5. Submit
-    let submit = JobSubmit { jobs, deps };
-    transport
-        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&submit)?)
-        .await?;
+        /// Jobs in this status (repeatable).
+        #[arg(
+            long,
+            value_enum,
+            default_value = "completed,failed",
+            value_delimiter = ','
+        )]
+        status: Vec<PurgeStatus>,
 
-    log::info!("🚀 Blueprint Deployed to Inbox!");
-    Ok(())
-}
+        /// Also delete artifact store objects no remaining job refers to.
+        #[arg(long)]
+        artifacts: bool,
 
-// ============================================================================
-// 5. TUI: THE DASHBOARD
-// ============================================================================
+        /// Append purged jobs to this JSONL file before deleting them.
+        #[arg(long)]
+        archive: Option<PathBuf>,
 
-fn run_tui(checkpoint: String) -> Result<()> {
-    if !Path::new(&checkpoint).exists() {
-        return Err(anyhow!("DB not found at: {}", checkpoint));
-    }
+        /// List what would be deleted instead of deleting it.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Summaries of a campaign, read from the checkpoint DB.
+    Report {
+        #[command(subcommand)]
+        kind: ReportKind,
+    },
+
+    /// Run a synthetic scenario suite against the running cluster and time it.
+    Benchmark {
+        /// Root directory of the running cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Scenario signatures (default: mixed_40 fanout_20_janus starve).
+        #[arg(long, num_args = 1..)]
+        scenario: Vec<String>,
+
+        /// Give up on a scenario after this many seconds.
+        #[arg(long, default_value_t = 600)]
+        timeout: u64,
+
+        /// Stop waiting once only pending/blocked jobs remain and nothing changed for this long.
+        #[arg(long, default_value_t = 30)]
+        stall: u64,
+
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+
+        /// Also write the JSON report to this file (for comparing releases).
+        #[arg(long)]
+        out: Option<PathBuf>,
+
+        #[command(flatten)]
+        transport: TransportOpts,
+    },
+
+    /// Run one workflow to completion on this machine and print a summary.
+    Run {
+        /// Workflow: .yaml, .drawio or a scenario signature (e.g. chain_5_janus).
+        #[arg(long)]
+        file: String,
+
+        /// Coordinator, one Guardian and an in-memory transport in this process
+        /// (the only mode so far), with a throwaway DB.
+        #[arg(long)]
+        local: bool,
+
+        /// JSON string to override generator params (e.g. '{"gen_limit": 5}').
+        #[arg(long)]
+        params: Option<String>,
+
+        /// YAML only: set one of the workflow's vars (e.g. --set temperature=600).
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        set: Vec<String>,
+
+        /// Give up after this many seconds (0 = never).
+        #[arg(long, default_value_t = 0)]
+        timeout: u64,
+
+        /// Stop waiting once nothing runs and nothing changed for this long.
+        #[arg(long, default_value_t = 30)]
+        stall: u64,
+
+        /// Keep the scratch directory (DB, artifact store) and print its path.
+        #[arg(long)]
+        keep: bool,
+
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+
+        /// Fault injection; fails only if a job is lost or completed twice.
+        #[arg(long, hide = true, num_args = 0..=1, default_missing_value = DEFAULT_CHAOS)]
+        chaos: Option<ChaosConfig>,
+    },
+
+    /// Check a YAML workflow without running it; prints FILE:LINE:COL diagnostics.
+    /// Exits non-zero if any is an error; warnings alone pass.
+    Lint {
+        /// Path to the .yaml workflow.
+        file: PathBuf,
+
+        /// Set one of the workflow's vars, as for deploy (e.g. --set temperature=600).
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        set: Vec<String>,
+    },
+
+    /// Check a blueprint without submitting it: DSL, expansion, cycles, and
+    /// whether every job fits the detected machine.
+    Validate {
+        /// Path to the .yaml or .drawio workflow.
+        file: String,
+
+        /// Set one of the workflow's vars, as for deploy (e.g. --set temperature=600).
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        set: Vec<String>,
+    },
+
+    /// Print a schema of the YAML workflow format, for editors and CI.
+    Schema {
+        /// Schema language (only json-schema so far).
+        #[arg(long, value_enum, default_value_t = SchemaFormat::JsonSchema)]
+        format: SchemaFormat,
+
+        /// Write the schema here instead of to stdout.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Print a YAML workflow upgraded to the current DSL version.
+    Migrate {
+        /// Path to the .yaml workflow.
+        file: PathBuf,
+
+        /// Write the upgraded workflow here instead of to stdout (may be FILE itself).
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Print an event log as JSON lines (offset, timestamp, kind, payload).
+    Replay {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Log file to read (default: <root>/events.log).
+        #[arg(long)]
+        log: Option<PathBuf>,
+
+        /// Read what this worker sent the Coordinator (inbox/worker_<ID>.log).
+        #[arg(long, value_name = "ID")]
+        inbox: Option<String>,
+
+        /// Read what the Coordinator sent this worker, e.g. grants (outbox/worker_<ID>.log).
+        #[arg(long, value_name = "ID")]
+        outbox: Option<String>,
+
+        /// Only these record kinds (e.g. --kind work.grant --kind work.grant_ack).
+        #[arg(long)]
+        kind: Vec<String>,
+
+        /// Only records at or after this time (RFC 3339 or epoch ms).
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only records at or before this time (RFC 3339 or epoch ms).
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Only records mentioning this job (full id or a prefix).
+        #[arg(long)]
+        job: Option<String>,
+    },
+
+    /// Find jobs by words in their engine, params or error log.
+    Search {
+        /// Words that must all appear, e.g. `cuda out of memory` (end a word with * for a prefix).
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Only jobs with this status (e.g. failed).
+        #[arg(long)]
+        status: Option<String>,
+
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Move the checkpoint DB between machines as a JSONL snapshot.
+    Db {
+        #[command(subcommand)]
+        op: DbOp,
+    },
+
+    /// Rebuild a finished job's work dir from the artifact store, to run it again locally.
+    Repro {
+        /// Job id (full or a prefix).
+        job: String,
+
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Directory to write (default: ./repro_<short id>); must be empty or missing.
+        #[arg(long)]
+        out: Option<PathBuf>,
+
+        /// Also restore what the run wrote, for comparison.
+        #[arg(long)]
+        outputs: bool,
+    },
+
+    /// Show a job's error, provenance and the end of its stdout/stderr.
+    Logs {
+        /// Job id (full or a prefix).
+        job: String,
+
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Lines shown from the end of each output.
+        #[arg(long, default_value_t = DEFAULT_LOG_LINES)]
+        lines: usize,
+
+        /// Also show the end of this work dir file (e.g. OUTCAR); repeatable.
+        #[arg(long)]
+        file: Vec<String>,
+
+        /// Print JSON instead of text.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum GraphOp {
+    /// Make CHILD wait on PARENT (CHILD must not have started yet).
+    AddEdge { parent: String, child: String },
+
+    /// Drop the dependency of CHILD on PARENT.
+    RemoveEdge { parent: String, child: String },
+
+    /// Add the job described by a JSON file (same shape as the checkpoint's jobs).
+    AddNode {
+        #[arg(long)]
+        json: PathBuf,
+
+        /// Existing jobs the new one waits on.
+        #[arg(long)]
+        parent: Vec<String>,
+    },
+}
+
+impl From<GraphOp> for ConsoleCommand {
+    fn from(op: GraphOp) -> Self {
+        match op {
+            GraphOp::AddEdge { parent, child } => Self::AddEdge(parent, child),
+            GraphOp::RemoveEdge { parent, child } => Self::RemoveEdge(parent, child),
+            GraphOp::AddNode { json, parent } => {
+                Self::AddNode(json.to_string_lossy().into_owned(), parent)
+            }
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum DbOp {
+    /// Write every row of checkpoint.db to a JSONL file, sorted for diffing.
+    Export {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Snapshot file to write.
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Load a snapshot into <root>/checkpoint.db, which must hold no jobs yet.
+    Import {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Snapshot file written by `db export`.
+        #[arg(long)]
+        file: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SchemaFormat {
+    /// JSON Schema, draft 2020-12.
+    JsonSchema,
+}
+
+/// The statuses a job can still be cancelled from.
+#[derive(Clone, Copy, ValueEnum)]
+enum CancelStatus {
+    Pending,
+    Blocked,
+    Queued,
+    Running,
+}
+
+impl From<CancelStatus> for JobStatus {
+    fn from(s: CancelStatus) -> Self {
+        match s {
+            CancelStatus::Pending => JobStatus::Pending,
+            CancelStatus::Blocked => JobStatus::Blocked,
+            CancelStatus::Queued => JobStatus::Queued,
+            CancelStatus::Running => JobStatus::Running,
+        }
+    }
+}
+
+/// The statuses a job can be retried from.
+#[derive(Clone, Copy, ValueEnum)]
+enum RetryStatus {
+    Failed,
+    Cancelled,
+}
+
+impl From<RetryStatus> for JobStatus {
+    fn from(s: RetryStatus) -> Self {
+        match s {
+            RetryStatus::Failed => JobStatus::Failed,
+            RetryStatus::Cancelled => JobStatus::Cancelled,
+        }
+    }
+}
+
+/// The statuses a job can be purged in.
+#[derive(Clone, Copy, ValueEnum)]
+enum PurgeStatus {
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl From<PurgeStatus> for JobStatus {
+    fn from(s: PurgeStatus) -> Self {
+        match s {
+            PurgeStatus::Completed => JobStatus::Completed,
+            PurgeStatus::Failed => JobStatus::Failed,
+            PurgeStatus::Cancelled => JobStatus::Cancelled,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum ReportKind {
+    /// Requested vs measured cores/memory/time per engine, with right-sizing hints.
+    Efficiency {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Objects in the artifact store with their content type and preview.
+    Artifacts {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Only list one content type (trajectory|dos|log|model|other).
+        #[arg(long = "type")]
+        content_type: Option<ContentType>,
+
+        /// Show one object (hash or prefix) with its full preview.
+        #[arg(long)]
+        hash: Option<String>,
+
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Memoization hits and misses per engine and campaign, and the most
+    /// duplicated job fingerprints.
+    Memo {
+        /// Root directory of the cluster.
+        #[arg(long, default_value = ".")]
+        root: String,
+
+        /// Duplicated fingerprints to list.
+        #[arg(long, default_value_t = report::DEFAULT_MEMO_TOP)]
+        top: usize,
+
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Transport selection. Precedence: flags > --transport-config > <root>/transport.yaml
+/// > the local socket if a Coordinator listens on it > file.
+#[derive(Args, Clone)]
+struct TransportOpts {
+    /// Backend: "file" (shared filesystem) or "grpc" (needs `--features grpc`).
+    #[arg(long)]
+    transport: Option<TransportKind>,
+
+    /// YAML transport settings (kind, grpc.addr, grpc.tls_*).
+    #[arg(long)]
+    transport_config: Option<PathBuf>,
+
+    /// gRPC Coordinator address "host:port". Rank 0 listens here, everyone else connects.
+    #[arg(long)]
+    grpc: Option<String>,
+
+    /// CA certificate (PEM) that signed every node certificate.
+    #[arg(long)]
+    tls_ca: Option<PathBuf>,
+
+    /// This node's certificate (PEM).
+    #[arg(long)]
+    tls_cert: Option<PathBuf>,
+
+    /// This node's private key (PEM).
+    #[arg(long)]
+    tls_key: Option<PathBuf>,
+
+    /// Compress large event-log records: none (default), zlib or zstd.
+    #[arg(long)]
+    compression: Option<Compression>,
+}
+
+/// How `deploy` submits the blueprint's jobs.
+#[derive(Args, Clone)]
+struct SubmitOpts {
+    /// Mark every job opportunistic: runs only on idle workers and yields to normal work.
+    #[arg(long)]
+    opportunistic: bool,
+
+    /// Project the jobs are charged to under the coordinator's quotas.
+    #[arg(long)]
+    project: Option<String>,
+
+    /// When the workflow should be done (RFC 3339, or from now: 90m, 36h, 2d).
+    /// Jobs with the least slack are dispatched first.
+    #[arg(long)]
+    deadline: Option<String>,
+
+    /// Submit the blueprint's one job as a job array over these indices
+    /// (START..END, or a count from 0).
+    #[arg(long, value_name = "START..END", conflicts_with_all = ["cluster", "route"])]
+    array: Option<String>,
+
+    /// JSON Lines file; line k is merged into the params of array index START+k.
+    #[arg(long, requires = "array")]
+    array_params: Option<PathBuf>,
+
+    /// Submit nothing: simulate scheduling on the live workers in
+    /// checkpoint.db and report the makespan, unplaceable jobs and growth bounds.
+    #[arg(long, conflicts_with = "array")]
+    dry_run: bool,
+}
+
+impl SubmitOpts {
+    /// `submit` as a job array, if `--array` was given.
+    fn job_array(&self, submit: &JobSubmit) -> Result<Option<JobArray>> {
+        let Some(spec) = &self.array else {
+            return Ok(None);
+        };
+        let (start, end) = match spec.split_once("..") {
+            Some((start, end)) => (start.trim().parse(), end.trim().parse()),
+            None => (Ok(0), spec.trim().parse()),
+        };
+        let (Ok(start), Ok(end)) = (start, end) else {
+            return Err(anyhow!(
+                "--array expects START..END or a count, got '{}'",
+                spec
+            ));
+        };
+        let [template] = submit.jobs.as_slice() else {
+            return Err(anyhow!(
+                "--array needs a blueprint with exactly one job, found {}",
+                submit.jobs.len()
+            ));
+        };
+        let params = match &self.array_params {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Reading {:?}", path))?
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .enumerate()
+                .map(|(k, l)| {
+                    serde_json::from_str(l).with_context(|| format!("Line {} of {:?}", k + 1, path))
+                })
+                .collect::<Result<Vec<Value>>>()?,
+            None => Vec::new(),
+        };
+        let array = JobArray {
+            id: uuid::Uuid::new_v4(),
+            template: template.clone(),
+            start,
+            end,
+            params,
+        };
+        if array.is_empty() {
+            return Err(anyhow!("--array {} holds no indices", spec));
+        }
+        Ok(Some(array))
+    }
+}
+
+/// Coordinator tuning; only rank 0 reads these.
+#[derive(Args, Clone)]
+struct SchedulerOpts {
+    /// Seconds a worker must be idle before it runs opportunistic jobs.
+    #[arg(long, default_value_t = 60)]
+    opportunistic_idle: u64,
+
+    /// Seconds a worker has to acknowledge a grant before its jobs go elsewhere.
+    #[arg(long, default_value_t = 30)]
+    grant_ack_timeout: u64,
+
+    /// Seconds without a heartbeat before a worker is declared dead and its
+    /// running jobs are re-queued.
+    #[arg(long, default_value_t = 60)]
+    worker_timeout: u64,
+
+    /// Seconds a job may run past its time_limit_min before it is failed and killed.
+    #[arg(long, default_value_t = 300)]
+    timeout_grace: u64,
+
+    /// Seconds between scans for Blocked jobs whose parents can never complete.
+    #[arg(long, default_value_t = 10)]
+    deadlock_scan: u64,
+
+    /// Seconds a deploy may go without sending more before it is discarded.
+    #[arg(long, default_value_t = 300)]
+    submit_timeout: u64,
+
+    /// Prune Completed/Failed jobs from checkpoint.db after this many hours.
+    #[arg(long)]
+    retain_hours: Option<u64>,
+
+    /// Append pruned jobs to this JSONL file instead of only deleting them.
+    #[arg(long, requires = "retain_hours")]
+    retain_archive: Option<PathBuf>,
+
+    /// Per-project / per-tag limits (default: <root>/quotas.toml if present).
+    #[arg(long)]
+    quotas: Option<PathBuf>,
+
+    /// SQLite file of completed results shared with other campaigns.
+    #[arg(long)]
+    memo_cache: Option<PathBuf>,
+
+    /// Duplicate jobs running this many times longer than the median of
+    /// their engine's finished jobs onto idle workers; the first copy wins.
+    #[arg(long)]
+    speculate: Option<f64>,
+}
+
+impl SchedulerOpts {
+    fn apply(&self, coord: MarketplaceCoordinator) -> MarketplaceCoordinator {
+        let coord = coord
+            .with_opportunistic_idle(Duration::from_secs(self.opportunistic_idle))
+            .with_grant_ack_timeout(Duration::from_secs(self.grant_ack_timeout))
+            .with_worker_timeout(Duration::from_secs(self.worker_timeout))
+            .with_timeout_grace(Duration::from_secs(self.timeout_grace))
+            .with_deadlock_scan(Duration::from_secs(self.deadlock_scan))
+            .with_submit_timeout(Duration::from_secs(self.submit_timeout));
+        let coord = match self.speculate {
+            Some(factor) => coord.with_speculation(factor),
+            None => coord,
+        };
+        match self.retain_hours {
+            Some(hours) => {
+                let mut policy = RetentionPolicy::new(Duration::from_secs(hours * 3600));
+                if let Some(path) = &self.retain_archive {
+                    policy = policy.with_archive(path);
+                }
+                coord.with_retention(policy)
+            }
+            None => coord,
+        }
+    }
+
+    /// The quota config given or found under `root`, if any.
+    fn quota_config(&self, root: &Path) -> Result<Option<QuotaConfig>> {
+        let default_file = root.join(QUOTA_CONFIG_FILE);
+        Ok(match &self.quotas {
+            Some(p) => Some(QuotaConfig::load(p)?),
+            None if default_file.exists() => Some(QuotaConfig::load(&default_file)?),
+            None => None,
+        })
+    }
+}
+
+impl TransportOpts {
+    fn resolve(&self, root: &Path) -> Result<TransportConfig> {
+        let default_file = root.join(TRANSPORT_CONFIG_FILE);
+        let mut cfg = match &self.transport_config {
+            Some(p) => TransportConfig::load(p)?,
+            None if default_file.exists() => TransportConfig::load(&default_file)?,
+            None => TransportConfig::detect(root),
+        };
+
+        if let Some(addr) = &self.grpc {
+            cfg.kind = TransportKind::Grpc;
+            cfg.grpc.addr = Some(addr.clone());
+        }
+        if let Some(kind) = self.transport {
+            cfg.kind = kind;
+        }
+        if let Some(compression) = self.compression {
+            cfg.compression = compression;
+        }
+        let g = &mut cfg.grpc;
+        g.tls_ca = self.tls_ca.clone().or(g.tls_ca.take());
+        g.tls_cert = self.tls_cert.clone().or(g.tls_cert.take());
+        g.tls_key = self.tls_key.clone().or(g.tls_key.take());
+        Ok(cfg)
+    }
+
+    /// True when nothing (flag, config file) picked a transport.
+    fn is_implicit(&self, root: &Path) -> bool {
+        self.transport.is_none()
+            && self.transport_config.is_none()
+            && self.grpc.is_none()
+            && !root.join(TRANSPORT_CONFIG_FILE).exists()
+    }
+}
+
+// ============================================================================
+// 2. ENTRY POINT
+// ============================================================================
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // Init Logger (standard env_logger unless TUI mode; quiet for the console prompt)
+    match cli.command {
+        Commands::Tui { .. } => {}
+        Commands::Console { .. }
+        | Commands::Graph { .. }
+        | Commands::Cancel { .. }
+        | Commands::Retry { .. }
+        | Commands::Pause { .. }
+        | Commands::Resume { .. }
+        | Commands::Status { .. }
+        | Commands::Workers { .. }
+        | Commands::Validate { .. } => logs::init_env_logger("warn").expect("logger already set"),
+        _ => logs::init_env_logger("info").expect("logger already set"),
+    }
+
+    match cli.command {
+        Commands::Start {
+            root,
+            force_local,
+            id,
+            tags,
+            scheduler,
+            transport,
+            chaos,
+        } => run_node_service(root, force_local, id, tags, scheduler, transport, chaos).await,
+        Commands::Deploy {
+            file,
+            root,
+            params,
+            set,
+            cluster,
+            route,
+            submit,
+            transport,
+        } => {
+            let routing = parse_routing(cluster, &route)?;
+            let vars = parse_vars(&set)?;
+            run_deployer(file, root, params, vars, routing, submit, transport).await
+        }
+        Commands::Tui { checkpoint } => run_tui(checkpoint),
+        Commands::Console { root, transport } => open_console(root, transport).await?.run().await,
+        Commands::Graph {
+            root,
+            op,
+            transport,
+        } => {
+            open_console(root, transport)
+                .await?
+                .execute(op.into())
+                .await
+        }
+        Commands::Cancel {
+            ids,
+            root,
+            workflow,
+            project,
+            engine,
+            status,
+            all,
+            dry_run,
+            transport,
+        } => {
+            let filter = JobFilter {
+                job_ids: vec![],
+                workflow,
+                project,
+                engine,
+                statuses: status.into_iter().map(JobStatus::from).collect(),
+            };
+            if ids.is_empty() && filter.is_empty() && !all {
+                return Err(anyhow!(
+                    "nothing selected: name jobs, filter them (--workflow, --project, --engine, --status), or pass --all"
+                ));
+            }
+            let cmd = match ids.as_slice() {
+                [id] if filter.is_empty() && !dry_run => ConsoleCommand::Cancel(id.clone()),
+                _ => ConsoleCommand::CancelJobs(ids, filter, dry_run),
+            };
+            open_console(root, transport).await?.execute(cmd).await
+        }
+        Commands::Retry {
+            ids,
+            root,
+            status,
+            filter,
+            transport,
+        } => {
+            let mut selected = JobFilter {
+                statuses: status.into_iter().map(JobStatus::from).collect(),
+                ..Default::default()
+            };
+            for pair in &filter {
+                selected.set(pair)?;
+            }
+            open_console(root, transport)
+                .await?
+                .execute(ConsoleCommand::RetryJobs(ids, selected))
+                .await
+        }
+        Commands::Pause {
+            workflow,
+            root,
+            transport,
+        } => {
+            open_console(root, transport)
+                .await?
+                .execute(ConsoleCommand::Pause(workflow))
+                .await
+        }
+        Commands::Resume {
+            workflow,
+            root,
+            transport,
+        } => {
+            open_console(root, transport)
+                .await?
+                .execute(ConsoleCommand::Resume(workflow))
+                .await
+        }
+        Commands::Federate {
+            root,
+            config,
+            transport,
+        } => run_federation(root, config, transport).await,
+        Commands::Status { root, hours, json } => run_status(&root, hours, json),
+        Commands::Workers {
+            root,
+            stale_after,
+            expect,
+            json,
+        } => run_workers(&root, Duration::from_secs(stale_after), expect, json),
+        Commands::Export {
+            root,
+            format,
+            out,
+            param,
+        } => run_export(&root, format, &out, &param),
+        Commands::Purge {
+            root,
+            older_than_hours,
+            status,
+            artifacts,
+            archive,
+            dry_run,
+        } => {
+            let mut policy = RetentionPolicy::new(Duration::from_secs(older_than_hours * 3600))
+                .with_statuses(status.into_iter().map(JobStatus::from).collect());
+            if let Some(path) = archive {
+                policy = policy.with_archive(path);
+            }
+            run_purge(&root, &policy, artifacts, dry_run)
+        }
+        Commands::Report { kind } => run_report(kind),
+        Commands::Benchmark {
+            root,
+            scenario,
+            timeout,
+            stall,
+            json,
+            out,
+            transport,
+        } => run_benchmark(root, scenario, timeout, stall, json, out, transport).await,
+        Commands::Run {
+            file,
+            local,
+            params,
+            set,
+            timeout,
+            stall,
+            keep,
+            json,
+            chaos,
+        } => {
+            if !local {
+                return Err(anyhow!(
+                    "run needs --local for now; on a cluster use `start` and `deploy`"
+                ));
+            }
+            let mut graph = load_blueprint(&file, &parse_vars(&set)?)?;
+            if let Some(ov) = &params {
+                apply_overrides(&mut graph, ov)?;
+            }
+            run_local(file, graph, timeout, stall, keep, json, chaos).await
+        }
+        Commands::Lint { file, set } => run_lint(&file, &parse_vars(&set)?),
+        Commands::Validate { file, set } => run_validate(&file, &parse_vars(&set)?),
+        Commands::Schema { format, out } => run_schema(format, out),
+        Commands::Migrate { file, out } => run_migrate(&file, out),
+        Commands::Replay {
+            root,
+            log,
+            inbox,
+            outbox,
+            kind,
+            since,
+            until,
+            job,
+        } => {
+            let filter = ReplayFilter {
+                kinds: kind,
+                since_ms: since.as_deref().map(replay::parse_time).transpose()?,
+                until_ms: until.as_deref().map(replay::parse_time).transpose()?,
+                job,
+            };
+            run_replay(Path::new(&root), log, inbox, outbox, &filter)
+        }
+        Commands::Search {
+            query,
+            root,
+            status,
+            json,
+        } => run_search(Path::new(&root), &query.join(" "), status.as_deref(), json),
+        Commands::Db { op } => run_db(op),
+        Commands::Repro {
+            job,
+            root,
+            out,
+            outputs,
+        } => run_repro(Path::new(&root), &job, out, outputs),
+        Commands::Logs {
+            job,
+            root,
+            lines,
+            file,
+            json,
+        } => run_logs(Path::new(&root), &job, lines, &file, json),
+    }
+}
+
+// ============================================================================
+// 3. RUNTIME: NODE SERVICE (Guardian + Coordinator)
+// ============================================================================
+
+async fn run_node_service(
+    root: String,
+    force_local: bool,
+    manual_id: Option<String>,
+    manual_tags: Vec<String>,
+    scheduler: SchedulerOpts,
+    transport_opts: TransportOpts,
+    chaos: Option<ChaosConfig>,
+) -> Result<()> {
+    let root_path = PathBuf::from(&root);
+    let shutdown_signal = Arc::new(AtomicBool::new(false));
+    let chaos = chaos.map(|cfg| Arc::new(Chaos::new(cfg)));
+    let mut transport_cfg = transport_opts.resolve(&root_path)?;
+
+    // A. DETECT ENVIRONMENT & TOPOLOGY
+    // We use the Ledger to see where we are (Slurm vs Local).
+    let ledger = ResourceLedger::detect();
+
+    // One machine: talk over a Unix socket instead of polling files
+    if ledger.cluster_type == ClusterType::Local
+        && transport_opts.is_implicit(&root_path)
+        && cfg!(unix)
+    {
+        transport_cfg.kind = TransportKind::Uds;
+    }
+
+    // Safety: Prevent accidental heavy runs on laptops without --force-local
+    if ledger.cluster_type == ClusterType::Local && !force_local {
+        return Err(anyhow!(
+            "SAFETY: Local execution detected. Use --force-local to run on workstation/laptop."
+        ));
+    }
+
+    // Identify Rank (0 = Coordinator/Lighthouse)
+    // In Slurm, SLURM_PROCID is reliable. Locally, we default to 0.
+    let rank = std::env::var("SLURM_PROCID").unwrap_or_else(|_| "0".into());
+    let is_coordinator = rank == "0";
+
+    let worker_id = manual_id.unwrap_or_else(|| format!("{}_r{}", ledger.hostname, rank));
+
+    // B. SMART TAGGING STRATEGY
+    // Brain = Can run Agents/Generators. Muscle = Can run heavy physics.
+    let tags = if !manual_tags.is_empty() {
+        manual_tags
+    } else if ledger.cluster_type == ClusterType::Local {
+        // Local: Must be everything
+        vec!["brain".into(), "muscle".into(), "gpu".into()]
+    } else if is_coordinator {
+        // Rank 0: The Brain (manages DB, runs light Agents)
+        vec!["brain".into()]
+    } else {
+        // Rank N: The Muscle (runs heavy Physics)
+        vec!["muscle".into(), "gpu".into()] // Assumes GPU nodes
+    };
+
+    log::info!(
+        "🚀 Booting Node {} | Role: Guardian {}",
+        worker_id,
+        if is_coordinator {
+            "+ Lighthouse 👑"
+        } else {
+            ""
+        }
+    );
+    log::info!("🏷️  Capabilities: {:?}", tags);
+
+    // C. BOOT COORDINATOR (If Rank 0)
+    // WAL on a workstation; conservative journaling on shared filesystems
+    let db_path = root_path.join("checkpoint.db");
+    let store_cfg = StoreConfig::for_cluster(ledger.cluster_type);
+    let store = CheckpointStore::open_with(&db_path, store_cfg.clone()).context("DB Init")?;
+
+    if is_coordinator {
+        let coord_root = root_path.clone();
+        let coord_sig = shutdown_signal.clone();
+        let coord_store = CheckpointStore::open_with(&db_path, store_cfg)?; // Clone connection
+        let coord_transport = transport_cfg.clone();
+        let coord_chaos = chaos.clone();
+
+        tokio::spawn(async move {
+            log::info!("👑 Lighthouse Service Starting...");
+            if let Err(e) = run_coordinator_loop(
+                coord_root,
+                coord_store,
+                coord_sig,
+                coord_transport,
+                scheduler,
+                coord_chaos,
+            )
+            .await
+            {
+                log::error!("👑 Lighthouse CRASHED: {}", e);
+                std::process::exit(1); // Fatal
+            }
+        });
+        // Give DB a moment to settle
+        sleep(Duration::from_millis(500)).await;
+    }
+
+    // D. BOOT GUARDIAN (The Local Scheduler)
+    let mut guardian = NodeGuardian::boot(worker_id.clone(), &root_path, store).await?;
+
+    // Transport for this worker (Inbox Reader)
+    let mut transport =
+        TransportFactory::open(&transport_cfg, &root_path, Role::Worker, Some(&worker_id)).await?;
+    if let Some(c) = &chaos {
+        guardian = guardian.with_chaos(c.clone());
+        transport = ChaosTransport::wrap(transport, c.clone());
+    }
+
+    // Large structures arrive by reference (CAS under root/store)
+    let codec = StructureCodec::new(&root_path)?;
+
+    // E. SIGNAL HANDLING
+    // Slurm sends SIGTERM when it takes nodes back (preemption, shrinking jobs)
+    let sig_term = shutdown_signal.clone();
+    tokio::spawn(async move {
+        stop_requested().await;
+        log::warn!("🛑 Interrupt received. Stopping...");
+        sig_term.store(true, Ordering::SeqCst);
+    });
+
+    // SIGUSR1 takes the node out of service once its running jobs finish
+    let drain_signal = Arc::new(AtomicBool::new(false));
+    tokio::spawn(watch_drain_signal(drain_signal.clone()));
+
+    // F. MAIN EVENT LOOP
+    let backlog = run_guardian_loop(
+        &worker_id,
+        &tags,
+        &guardian,
+        transport.as_mut(),
+        &codec,
+        (&shutdown_signal, &drain_signal),
+        WORKER_HEARTBEAT_EVERY,
+    )
+    .await?;
+
+    // G. RETIRE: hand unstarted jobs back so the remaining nodes absorb them
+    // (rank 0 takes the Coordinator down with it, so there is no one to tell)
+    if !is_coordinator {
+        let mut jobs: Vec<Job> = backlog.into_iter().collect();
+        codec.offload_jobs(&mut jobs)?;
+        let n_jobs = jobs.len();
+        let retire = WorkYield {
+            worker_id: worker_id.clone(),
+            jobs,
+            reason: "node shutting down".into(),
+        };
+        match transport
+            .send_to_coordinator(MSG_WORK_YIELD, serde_json::to_value(&retire)?)
+            .await
+        {
+            Ok(()) => log::info!("🧳 Returned {} queued job(s) to the Coordinator", n_jobs),
+            Err(e) => log::error!("Could not return {} queued job(s): {}", n_jobs, e),
+        }
+    }
+
+    log::info!("👋 Node Shutdown Complete.");
+    Ok(())
+}
+
+/// The Guardian's side of the protocol until `stop` is set: heartbeats (which
+/// ask for work), grants, preemptions, cancellations and completion reports.
+/// Setting `drain` asks the Coordinator to drain this worker.
+/// Returns the jobs it accepted but never started.
+async fn run_guardian_loop(
+    worker_id: &str,
+    tags: &[String],
+    guardian: &NodeGuardian,
+    transport: &mut dyn Transport,
+    codec: &StructureCodec,
+    (stop, drain): (&AtomicBool, &AtomicBool),
+    hb_interval: Duration,
+) -> Result<VecDeque<Job>> {
+    log::info!("🛡️ Guardian Active. Polling inbox...");
+    let hostname = guardian.hostname().await;
+    let (total_cores, total_gpus) = guardian.get_totals().await;
+
+    // Local Backlog: Jobs accepted by protocol but waiting for Guardian resources
+    let mut backlog: VecDeque<Job> = VecDeque::new();
+    let mut last_heartbeat = Instant::now();
+
+    while !stop.load(Ordering::SeqCst) {
+        if drain.swap(false, Ordering::SeqCst) {
+            let msg = WorkerDrain {
+                worker_id: worker_id.to_string(),
+                resume: false,
+                request_id: None,
+            };
+            if let Err(e) = transport
+                .send_to_coordinator(MSG_WORKER_DRAIN, serde_json::to_value(&msg)?)
+                .await
+            {
+                log::error!("Drain request failed: {}", e);
+                drain.store(true, Ordering::SeqCst);
+            }
+        }
+
+        // 1. HEARTBEAT
+        if last_heartbeat.elapsed() > hb_interval {
+            // FIX: Ask Guardian for REAL capacity.
+            // This ensures we report what is actually free in the Ledger bitmask.
+            let (free_cores, free_gpus, free_mem_mb) = guardian.get_capacity().await;
+
+            let req = WorkRequest {
+                worker_id: worker_id.to_string(),
+                available_cores: free_cores,
+                available_gpus: free_gpus,
+                available_memory_mb: Some(free_mem_mb),
+                max_jobs: 64, // Queue depth limit
+                tags: tags.to_vec(),
+                read_lag: transport.stats().read_lag,
+                hostname: Some(hostname.clone()),
+                total_cores: Some(total_cores),
+                total_gpus: Some(total_gpus),
+            };
+
+            // We write to our own output log which Coordinator reads
+            if let Err(e) = transport
+                .send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req)?)
+                .await
+            {
+                log::error!("Heartbeat failed: {}", e);
+            }
+            last_heartbeat = Instant::now();
+        }
+
+        // 2. PROCESS BACKLOG (Try to shove queued jobs into Guardian)
+        let mut rotated = 0;
+        let q_len = backlog.len();
+        while rotated < q_len {
+            if let Some(job) = backlog.pop_front() {
+                if guardian.try_accept_job(job.clone()).await {
+                    // Success: Guardian took it
+                } else {
+                    // Fail: Resources still full, rotate back
+                    backlog.push_back(job);
+                }
+            }
+            rotated += 1;
+        }
+
+        // 3. CHECK INBOX (New Grants / Preemptions)
+        let events = transport.recv_broadcasts().await.unwrap_or_default();
+        for env in events {
+            if env.record.kind == EV_WORK_GRANT {
+                if let Ok(mut grant) = serde_json::from_value::<WorkGrant>(env.record.payload) {
+                    if grant.worker_id == worker_id {
+                        // Read too late: the Coordinator has taken these jobs back
+                        let now_ms = chrono::Utc::now().timestamp_millis();
+                        if grant.expired(env.record.ts_ms, now_ms) {
+                            log::warn!(
+                                "⌛ Grant {} expired before it was read, skipping {} job(s)",
+                                grant.grant_id,
+                                grant.jobs.len()
+                            );
+                            continue;
+                        }
+                        if let Err(e) = codec.hydrate_jobs(&mut grant.jobs) {
+                            log::error!("Grant {} unreadable: {}", grant.grant_id, e);
+                            continue;
+                        }
+                        log::info!(
+                            "📨 Received Grant {} ({} jobs)",
+                            grant.grant_id,
+                            grant.jobs.len()
+                        );
+                        let grant_id = grant.grant_id;
+
+                        for job in grant.jobs {
+                            // Re-granted after a Coordinator restart
+                            if backlog.iter().any(|j| j.id == job.id) {
+                                continue;
+                            }
+                            if !guardian.try_accept_job(job.clone()).await {
+                                log::debug!("⏳ Job {} queued locally (Busy)", job.id);
+                                backlog.push_back(job);
+                            }
+                        }
+
+                        // Only once the Guardian holds the jobs: a crash before
+                        // this lets the grant expire and the jobs go elsewhere
+                        let ack = GrantAck {
+                            worker_id: worker_id.to_string(),
+                            grant_id: grant_id.clone(),
+                        };
+                        if let Err(e) = transport
+                            .send_to_coordinator(MSG_GRANT_ACK, serde_json::to_value(&ack)?)
+                            .await
+                        {
+                            log::warn!("Could not ack grant {}: {}", grant_id, e);
+                        }
+                    }
+                }
+            } else if env.record.kind == EV_WORK_PREEMPT {
+                if let Ok(p) = serde_json::from_value::<WorkPreempt>(env.record.payload) {
+                    if p.worker_id == worker_id {
+                        backlog.retain(|j| !p.job_ids.contains(&j.id));
+                        let stopped = guardian.preempt(&p.job_ids);
+                        log::info!(
+                            "⏏️ Yielding to normal work: {} opportunistic job(s) stopped",
+                            stopped
+                        );
+                    }
+                }
+            } else if env.record.kind == EV_JOB_CANCEL {
+                if let Ok(c) = serde_json::from_value::<JobCancel>(env.record.payload) {
+                    if c.worker_id == worker_id {
+                        backlog.retain(|j| !c.job_ids.contains(&j.id));
+                        let stopped = guardian.cancel(&c.job_ids);
+                        log::info!("🚫 {}: {} job(s) stopped", c.reason, stopped);
+                    }
+                }
+            }
+        }
+
+        // 4. REPORT COMPLETIONS (unblocks children; federations aggregate these)
+        for (event_id, rep) in guardian.drain_reports() {
+            if let Err(e) = transport
+                .send_to_coordinator_with_id(
+                    MSG_JOB_COMPLETE,
+                    serde_json::to_value(&rep)?,
+                    event_id,
+                )
+                .await
+            {
+                log::error!(
+                    "Completion report for {} failed, retrying: {}",
+                    rep.job_id,
+                    e
+                );
+                guardian.requeue_report(event_id, rep);
+            }
+        }
+        for p in guardian.drain_progress() {
+            let job_id = p.job_id;
+            if let Err(e) = transport
+                .send_to_coordinator(MSG_JOB_PROGRESS, serde_json::to_value(&p)?)
+                .await
+            {
+                log::debug!("Progress of {} not sent: {}", job_id, e);
+            }
+        }
+
+        // 5. PREVENT BUSY LOOP (socket transports wake early on new messages)
+        transport.wait_for_traffic(Duration::from_millis(200)).await; // this section is critical as it defines how long each operation awaits for min
+    }
+
+    Ok(backlog)
+}
+
+/// Ctrl-C, or SIGTERM from the batch system.
+async fn stop_requested() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::SignalKind;
+        if let Ok(mut term) = signal::unix::signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = signal::ctrl_c() => {}
+                _ = term.recv() => {}
+            }
+            return;
+        }
+    }
+    signal::ctrl_c().await.ok();
+}
+
+/// Sets `drain` on every SIGUSR1 (Unix only).
+async fn watch_drain_signal(drain: Arc<AtomicBool>) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::SignalKind;
+        let Ok(mut usr1) = signal::unix::signal(SignalKind::user_defined1()) else {
+            return;
+        };
+        while usr1.recv().await.is_some() {
+            log::warn!("🚧 SIGUSR1 received. Draining...");
+            drain.store(true, Ordering::SeqCst);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = drain;
+}
+
+// Logic for Rank 0
+async fn run_coordinator_loop(
+    root: PathBuf,
+    store: CheckpointStore,
+    stop_signal: Arc<AtomicBool>,
+    transport_cfg: TransportConfig,
+    scheduler: SchedulerOpts,
+    chaos: Option<Arc<Chaos>>,
+) -> Result<()> {
+    let open_transport = || async {
+        let transport = TransportFactory::open(&transport_cfg, &root, Role::Coordinator, None)
+            .await
+            .context("Coord Transport")?;
+        anyhow::Ok(match &chaos {
+            Some(c) => ChaosTransport::wrap(transport, c.clone()),
+            None => transport,
+        })
+    };
+
+    let coord = MarketplaceCoordinator::open(open_transport().await?, store)
+        .await?
+        .with_structure_codec(StructureCodec::new(&root)?);
+    let mut coord = scheduler.apply(coord);
+    if let Some(quotas) = scheduler.quota_config(&root)? {
+        log::info!(
+            "⚖️ Quotas on {} project(s) and {} tag(s)",
+            quotas.project.len(),
+            quotas.tag.len()
+        );
+        coord = coord.with_quotas(quotas);
+    }
+    if let Some(path) = &scheduler.memo_cache {
+        let cache = MemoCache::open(path)?;
+        log::info!(
+            "♻️ Memo cache {:?}: {} shared result(s)",
+            path,
+            cache.entries()?
+        );
+        coord = coord.with_memo_cache(cache);
+    }
+    log::info!("✅ Coordinator Logic Active.");
+
+    while !stop_signal.load(Ordering::SeqCst) {
+        if let Err(e) = coord.tick().await {
+            log::error!("Coordinator Tick Error: {}", e);
+        }
+        if chaos.as_ref().is_some_and(|c| c.crash_tick()) {
+            log::warn!("🐒 Chaos: Coordinator crashed, restarting from checkpoint");
+            coord.crash_restart(open_transport().await?).await?;
+        }
+        coord.wait_for_traffic(Duration::from_millis(100)).await;
+    }
+    coord.checkpoint_now()?;
+    if let Some(c) = &chaos {
+        log::warn!("{}", c.check(coord.jobs()).summary());
+    }
+    Ok(())
+}
+
+// ============================================================================
+// 4. DEPLOYER: THE ARCHITECT
+// ============================================================================
+
+async fn run_deployer(
+    file: String,
+    root: String,
+    overrides: Option<String>,
+    vars: dsl::Vars,
+    routing: Routing,
+    submit_opts: SubmitOpts,
+    transport_opts: TransportOpts,
+) -> Result<()> {
+    let root_path = PathBuf::from(&root);
+    let transport_cfg = transport_opts.resolve(&root_path)?;
+    log::info!("📐 Parsing Blueprint: {}", file);
+
+    // 1. Load Blueprint
+    let mut graph = load_blueprint(&file, &vars)?;
+    log::info!("   Found {} nodes.", graph.graph.node_count());
+
+    // 2. Apply Overrides
+    if let Some(ov) = overrides {
+        apply_overrides(&mut graph, &ov)?;
+    }
+    if submit_opts.dry_run {
+        let db = root_path.join("checkpoint.db");
+        if !db.exists() {
+            return Err(anyhow!("DB not found at: {:?}", db));
+        }
+        let store = CheckpointStore::open(&db)?;
+        let run =
+            dryrun::dry_run_from_store(&graph, &store, chrono::Utc::now().timestamp_millis())?;
+        dryrun::print_dry_run(&file, &run);
+        return Ok(());
+    }
+
+    // 3. Setup Transport (As Architect). Only the verdict on this
+    // submission matters, so skip the broadcast history
+    let mut transport = open_architect(&transport_cfg, &root_path, "architect").await?;
+    transport
+        .seek(eventlog::log_end(&root_path.join("events.log"))?)
+        .await?;
+
+    // 4. Construct Payload
+    let mut submit = build_submission(&graph, submit_opts.opportunistic, routing)?;
+    if let Some(project) = &submit_opts.project {
+        for job in &mut submit.jobs {
+            job.flow_context
+                .insert(FLOW_PROJECT.into(), project.clone().into());
+        }
+    }
+    if let Some(spec) = &submit_opts.deadline {
+        let due = parse_deadline(spec, chrono::Utc::now()).map_err(|e| anyhow!(e))?;
+        log::info!("   Deadline: {}", due.to_rfc3339());
+        for job in &mut submit.jobs {
+            job.flow_context
+                .insert(FLOW_DEADLINE.into(), due.to_rfc3339().into());
+        }
+    }
+    let txn_id = match submit_opts.job_array(&submit)? {
+        Some(array) => submit_array(transport.as_mut(), &root_path, array).await?,
+        None => submit_blueprint(transport.as_mut(), &root_path, submit).await?,
+    };
+
+    // 5. All or nothing: wait for the Coordinator to apply or discard it
+    match await_submit_ack(transport.as_mut(), txn_id).await? {
+        Some(ack) if ack.ok => log::info!("🚀 Blueprint deployed: {}", ack.message),
+        Some(ack) => return Err(anyhow!("Deployment rejected: {}", ack.message)),
+        None => log::warn!(
+            "🚀 Blueprint sent, but no verdict within {}s. The coordinator applies it when it reads its inbox.",
+            DEPLOY_ACK_TIMEOUT.as_secs()
+        ),
+    }
+    Ok(())
+}
+
+/// How long `deploy` waits for the Coordinator to apply its submission.
+const DEPLOY_ACK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The Coordinator's verdict on submission `txn_id`, or None if it did not
+/// come within `DEPLOY_ACK_TIMEOUT` (e.g. the Coordinator is not running).
+async fn await_submit_ack(
+    transport: &mut dyn Transport,
+    txn_id: uuid::Uuid,
+) -> Result<Option<SubmitAck>> {
+    let deadline = Instant::now() + DEPLOY_ACK_TIMEOUT;
+    while Instant::now() < deadline {
+        for env in transport.recv_broadcasts().await? {
+            if env.record.kind != EV_SUBMIT_ACK {
+                continue;
+            }
+            if let Ok(ack) = serde_json::from_value::<SubmitAck>(env.record.payload) {
+                if ack.txn_id == txn_id {
+                    return Ok(Some(ack));
+                }
+            }
+        }
+        transport.wait_for_traffic(Duration::from_millis(200)).await;
+    }
+    Ok(None)
+}
+
+/// Merges the JSON object `overrides` into every generator's params.
+fn apply_overrides(graph: &mut WorkflowEngine, overrides: &str) -> Result<()> {
+    let ov_json: Value = serde_json::from_str(overrides).context("Invalid overrides JSON")?;
+    log::info!("   Applying overrides: {}", overrides);
+
+    for idx in graph.graph.node_indices() {
+        let node = &mut graph.graph[idx];
+        if matches!(node.node_type, NodeType::Generator { .. }) {
+            if let Some(params) = node.job.config.params.as_object_mut() {
+                if let Some(ov_obj) = ov_json.as_object() {
+                    for (k, v) in ov_obj {
+                        params.insert(k.clone(), v.clone());
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Connects as a submit-only client (a "Worker" that never asks for work).
+async fn open_architect(
+    cfg: &TransportConfig,
+    root: &Path,
+    prefix: &str,
+) -> Result<Box<dyn Transport>> {
+    let arch_id = format!(
+        "{}_{}",
+        prefix,
+        uuid::Uuid::new_v4()
+            .to_string()
+            .chars()
+            .take(8)
+            .collect::<String>()
+    );
+    TransportFactory::open(cfg, root, Role::Worker, Some(&arch_id)).await
+}
+
+/// Job id -> the ids it waits on, for `benchmark::summarize`.
+fn parent_map(submit: &JobSubmit) -> HashMap<String, Vec<String>> {
+    let mut parents: HashMap<String, Vec<String>> = submit
+        .jobs
+        .iter()
+        .map(|j| (j.id.to_string(), Vec::new()))
+        .collect();
+    for (src, dst) in &submit.deps {
+        if let Some(p) = parents.get_mut(&dst.to_string()) {
+            p.push(src.to_string());
+        }
+    }
+    parents
+}
+
+/// Sends `submit` as one transaction: chunks of up to `SUBMIT_CHUNK_JOBS`
+/// jobs, then the end marker. Returns the transaction id. If a chunk cannot
+/// be sent, the Coordinator is told to discard the ones before it.
+async fn submit_blueprint(
+    transport: &mut dyn Transport,
+    root: &Path,
+    mut submit: JobSubmit,
+) -> Result<uuid::Uuid> {
+    // Large structures travel via the CAS, not inline JSON
+    if transport.shares_filesystem() {
+        StructureCodec::new(root)?
+            .offload_jobs(&mut submit.jobs)
+            .context("Submission not sent")?;
+    }
+    let (txn_id, chunks) = submit.into_chunks(SUBMIT_CHUNK_JOBS);
+    let mut end = SubmitEnd {
+        txn_id,
+        chunks: chunks.len(),
+        abort: false,
+    };
+    for chunk in chunks {
+        if let Err(e) = transport
+            .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&chunk)?)
+            .await
+        {
+            end.abort = true;
+            let _ = transport
+                .send_to_coordinator(MSG_SUBMIT_END, serde_json::to_value(&end)?)
+                .await;
+            return Err(e.context("Submission aborted; none of it will be applied"));
+        }
+    }
+    transport
+        .send_to_coordinator(MSG_SUBMIT_END, serde_json::to_value(&end)?)
+        .await?;
+    Ok(txn_id)
+}
+
+/// Sends `array` as one message. Returns its id, which the verdict carries.
+async fn submit_array(
+    transport: &mut dyn Transport,
+    root: &Path,
+    mut array: JobArray,
+) -> Result<uuid::Uuid> {
+    if transport.shares_filesystem() {
+        StructureCodec::new(root)?
+            .offload_jobs(std::slice::from_mut(&mut array.template))
+            .with_context(|| format!("Array {} not sent", array.id))?;
+    }
+    log::info!("   Array of {} job(s).", array.len());
+    transport
+        .send_to_coordinator(MSG_JOB_ARRAY, serde_json::to_value(&array)?)
+        .await?;
+    Ok(array.id)
+}
+
+fn parse_routing(cluster: Option<String>, routes: &[String]) -> Result<Routing> {
+    let mut routing = Routing {
+        default: cluster,
+        ..Default::default()
+    };
+    for r in routes {
+        let (engine, cluster) = r
+            .split_once('=')
+            .ok_or_else(|| anyhow!("--route expects ENGINE=CLUSTER, got '{}'", r))?;
+        routing
+            .engines
+            .insert(engine.trim().to_lowercase(), cluster.trim().to_string());
+    }
+    Ok(routing)
+}
+
+/// `--set KEY=VALUE` pairs; values are read as YAML, so `600` is a number.
+fn parse_vars(sets: &[String]) -> Result<dsl::Vars> {
+    let mut vars = dsl::Vars::new();
+    for s in sets {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("--set expects KEY=VALUE, got '{}'", s))?;
+        let value = match value.trim() {
+            "" => serde_yaml::Value::String(String::new()),
+            v => serde_yaml::from_str(v).with_context(|| format!("--set {}", s))?,
+        };
+        vars.insert(key.trim().to_string(), value);
+    }
+    Ok(vars)
+}
+
+fn run_lint(file: &Path, vars: &dsl::Vars) -> Result<()> {
+    let found = dsl::lint::lint_file(file, vars).map_err(|e| anyhow!("{}", e))?;
+    for d in &found {
+        println!("{}:{}", file.display(), d);
+    }
+    let errors = found
+        .iter()
+        .filter(|d| d.severity == dsl::lint::Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(anyhow!("{}: {} error(s)", file.display(), errors));
+    }
+    if found.is_empty() {
+        println!("{}: ok", file.display());
+    }
+    Ok(())
+}
+
+/// Lint (YAML), then the load `deploy` does (macros, sub-workflows, graph),
+/// then every job against this machine. Problems are printed like lint's.
+fn run_validate(file: &str, vars: &dsl::Vars) -> Result<()> {
+    let path = Path::new(file);
+    if matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml" | "yml")
+    ) {
+        let found = dsl::lint::lint_file(path, vars).map_err(|e| anyhow!("{}", e))?;
+        for d in &found {
+            println!("{}:{}", file, d);
+        }
+        let errors = found
+            .iter()
+            .filter(|d| d.severity == dsl::lint::Severity::Error)
+            .count();
+        if errors > 0 {
+            return Err(anyhow!("{}: {} error(s)", file, errors));
+        }
+    }
+
+    let graph = load_blueprint(file, vars)?;
+    let mut errors = 0;
+    if petgraph::algo::is_cyclic_directed(&graph.graph) {
+        println!("{}: error: the jobs depend on each other in a cycle", file);
+        errors += 1;
+    }
+
+    let ledger = ResourceLedger::detect();
+    println!(
+        "{}: {} job(s), {} dependency edge(s); checking against {} ({:?}: {} cores, {} GPU(s), {} MB)",
+        file,
+        graph.graph.node_count(),
+        graph.graph.edge_count(),
+        ledger.hostname,
+        ledger.cluster_type,
+        ledger.free_cores(),
+        ledger.total_gpus(),
+        ledger.total_mem_mb()
+    );
+    for node in graph.graph.node_weights() {
+        if let Some(why) = ledger.never_fits(&node.job.resources) {
+            let name = match node.job.structure.source.as_str() {
+                "" => node.job.id.to_string()[..8].to_string(),
+                source => source.to_string(),
+            };
+            println!("{}: error: node '{}' {}", file, name, why);
+            errors += 1;
+        }
+    }
+
+    if errors > 0 {
+        return Err(anyhow!("{}: {} error(s)", file, errors));
+    }
+    println!("{}: ok", file);
+    Ok(())
+}
+
+fn run_schema(format: SchemaFormat, out: Option<PathBuf>) -> Result<()> {
+    let text = match format {
+        SchemaFormat::JsonSchema => serde_json::to_string_pretty(&dsl::schema::json_schema())?,
+    };
+    match out {
+        Some(path) => std::fs::write(&path, text + "\n")
+            .with_context(|| format!("Failed to write {}", path.display())),
+        None => {
+            println!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/// Old files are rewritten; one already in the current version is printed as
+/// it is, comments and all.
+fn run_migrate(file: &Path, out: Option<PathBuf>) -> Result<()> {
+    let raw = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let mut doc: serde_yaml::Value = serde_yaml::from_str(&raw)
+        .with_context(|| format!("Failed to parse {}", file.display()))?;
+    let upgraded = dsl::migrate::upgrade(&mut doc).map_err(|e| anyhow!("{}", e))?;
+    let text = match doc.get("version").and_then(serde_yaml::Value::as_u64) {
+        _ if upgraded => serde_yaml::to_string(&doc)?,
+        Some(v) if v == dsl::SUPPORTED_DSL_VERSION as u64 => {
+            eprintln!("{}: already DSL version {}", file.display(), v);
+            raw
+        }
+        found => {
+            return Err(anyhow!(
+                "{}: cannot migrate DSL version {}",
+                file.display(),
+                found.map_or_else(|| "(none)".to_string(), |v| v.to_string())
+            ))
+        }
+    };
+    match out {
+        Some(path) => std::fs::write(&path, text)
+            .with_context(|| format!("Failed to write {}", path.display())),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+// ============================================================================
+// 5. TUI: THE DASHBOARD
+// ============================================================================
+
+fn run_tui(checkpoints: Vec<String>) -> Result<()> {
+    for checkpoint in &checkpoints {
+        if !Path::new(checkpoint).exists() {
+            return Err(anyhow!("DB not found at: {}", checkpoint));
+        }
+    }
 
     // Redirect logs to memory buffer so they don't break TUI
     let log_buf = LogBuffer::new(200); // does this have to match with 200 ms timing default?
     TuiLogger::init(log_buf.clone()).ok();
 
-    crate::tui::TuiApp::new(&checkpoint, log_buf).run()?;
+    crate::tui::TuiApp::new(&checkpoints, log_buf).run()?;
+    Ok(())
+}
+
+// ============================================================================
+// 6. CONSOLE: THE CONTROL ROOM
+// ============================================================================
+
+/// A console attached to the Coordinator at `root` (the REPL and `graph` use it).
+async fn open_console(root: String, transport_opts: TransportOpts) -> Result<Console> {
+    let root_path = PathBuf::from(&root);
+    let transport_cfg = transport_opts.resolve(&root_path)?;
+    let db_path = root_path.join("checkpoint.db");
+    if !db_path.exists() {
+        return Err(anyhow!("DB not found at: {:?}", db_path));
+    }
+
+    // Like the architect, the console talks to the Coordinator as a pseudo-worker
+    let console_id = format!(
+        "console_{}",
+        &uuid::Uuid::new_v4().simple().to_string()[..8]
+    );
+    let mut transport =
+        TransportFactory::open(&transport_cfg, &root_path, Role::Worker, Some(&console_id)).await?;
+
+    // Skip history: only acknowledgements sent after we connect matter
+    transport
+        .seek(eventlog::log_end(&root_path.join("events.log"))?)
+        .await?;
+
+    Ok(Console::new(CheckpointStore::open(&db_path)?, transport))
+}
+
+// ============================================================================
+// 7. FEDERATION: THE COORDINATOR OF COORDINATORS
+// ============================================================================
+
+async fn run_federation(
+    root: String,
+    config: Option<PathBuf>,
+    transport_opts: TransportOpts,
+) -> Result<()> {
+    let root_path = PathBuf::from(&root);
+    std::fs::create_dir_all(&root_path)?;
+    let transport_cfg = transport_opts.resolve(&root_path)?;
+    let fed_cfg =
+        FederationConfig::load(config.unwrap_or_else(|| root_path.join(FEDERATION_CONFIG_FILE)))?;
+
+    let store = CheckpointStore::open(root_path.join("checkpoint.db")).context("DB Init")?;
+    let transport = TransportFactory::open(&transport_cfg, &root_path, Role::Coordinator, None)
+        .await
+        .context("Federation Transport")?;
+    let mut lighthouse = FederationLighthouse::open(&root_path, &fed_cfg, transport, store).await?;
+    log::info!(
+        "🌐 Federation Lighthouse active ({} clusters)",
+        fed_cfg.clusters.len()
+    );
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let sig = stop.clone();
+    tokio::spawn(async move {
+        signal::ctrl_c().await.ok();
+        log::warn!("🛑 Interrupt received. Stopping...");
+        sig.store(true, Ordering::SeqCst);
+    });
+
+    while !stop.load(Ordering::SeqCst) {
+        if let Err(e) = lighthouse.tick().await {
+            log::error!("Federation Tick Error: {}", e);
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// 8. REPORTS: LOOKING BACK
+// ============================================================================
+
+fn run_status(root: &str, hours: u64, json: bool) -> Result<()> {
+    if hours == 0 {
+        return Err(anyhow!("--hours must be at least 1"));
+    }
+    let db_path = Path::new(root).join("checkpoint.db");
+    if !db_path.exists() {
+        return Err(anyhow!("DB not found at: {:?}", db_path));
+    }
+    let store = CheckpointStore::open(&db_path)?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let status = report::status_from_store(&store, hours, now_ms)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+    } else {
+        report::print_status(&status, now_ms);
+    }
+    Ok(())
+}
+
+fn run_workers(root: &str, stale_after: Duration, expect: Option<usize>, json: bool) -> Result<()> {
+    let db_path = Path::new(root).join("checkpoint.db");
+    if !db_path.exists() {
+        return Err(anyhow!("DB not found at: {:?}", db_path));
+    }
+    let store = CheckpointStore::open(&db_path)?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let workers = report::workers_from_store(&store, stale_after, now_ms)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&workers)?);
+    } else {
+        report::print_workers(&workers, now_ms);
+    }
+    let live = workers.iter().filter(|w| !w.silent).count();
+    match expect {
+        Some(n) if live < n => Err(anyhow!("{} of {} expected worker(s) live", live, n)),
+        _ => Ok(()),
+    }
+}
+
+fn run_export(root: &str, format: ExportFormat, out: &Path, params: &[String]) -> Result<()> {
+    let db_path = Path::new(root).join("checkpoint.db");
+    if !db_path.exists() {
+        return Err(anyhow!("DB not found at: {:?}", db_path));
+    }
+    let records = CheckpointStore::open(&db_path)?.get_completed_results()?;
+    let columns = export::result_columns(&records, params);
+    let path = export::write_table(&columns, format, out)?;
+    println!(
+        "Exported {} completed job(s), {} column(s) to {}",
+        records.len(),
+        columns.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn run_purge(root: &str, policy: &RetentionPolicy, artifacts: bool, dry_run: bool) -> Result<()> {
+    let db_path = Path::new(root).join("checkpoint.db");
+    if !db_path.exists() {
+        return Err(anyhow!("DB not found at: {:?}", db_path));
+    }
+    let store = CheckpointStore::open(&db_path)?;
+    let objects = if artifacts {
+        let store_path = Path::new(root).join("store");
+        if !store_path.exists() {
+            return Err(anyhow!("Artifact store not found at: {:?}", store_path));
+        }
+        Some(ArtifactStore::new(&store_path)?)
+    } else {
+        None
+    };
+    if dry_run {
+        let plan = policy.plan(&store, objects.as_ref())?;
+        report::print_purge_plan(&plan, chrono::Utc::now().timestamp_millis());
+        return Ok(());
+    }
+    let (jobs, blobs) = policy.purge(&store, objects.as_ref())?;
+    let freed: u64 = blobs.iter().map(|b| b.size_bytes).sum();
+    if artifacts {
+        println!(
+            "Purged {} job(s) and {} unreferenced object(s), {}",
+            jobs,
+            blobs.len(),
+            report::human_bytes(freed)
+        );
+    } else {
+        println!("Purged {} job(s)", jobs);
+    }
+    Ok(())
+}
+
+fn run_report(kind: ReportKind) -> Result<()> {
+    match kind {
+        ReportKind::Efficiency { root, json } => {
+            let db_path = Path::new(&root).join("checkpoint.db");
+            if !db_path.exists() {
+                return Err(anyhow!("DB not found at: {:?}", db_path));
+            }
+            let store = CheckpointStore::open(&db_path)?;
+            let rows = report::efficiency(&store.get_usage_records()?);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+            } else {
+                report::print_efficiency(&rows);
+            }
+            Ok(())
+        }
+        ReportKind::Artifacts {
+            root,
+            content_type,
+            hash,
+            json,
+        } => {
+            let store_path = Path::new(&root).join("store");
+            if !store_path.exists() {
+                return Err(anyhow!("Artifact store not found at: {:?}", store_path));
+            }
+            let store = ArtifactStore::new(&store_path)?;
+            let added = store.reindex()?;
+            if added > 0 {
+                log::info!("Indexed {} artifact(s) missing from the index", added);
+            }
+            if let Some(hash) = hash {
+                let info = store
+                    .info(&hash)?
+                    .ok_or_else(|| anyhow!("No artifact matches '{}'", hash))?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&info)?);
+                } else {
+                    report::print_artifact(&info);
+                }
+                return Ok(());
+            }
+            let rows = store.list(content_type)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+            } else {
+                report::print_artifacts(&rows);
+            }
+            Ok(())
+        }
+        ReportKind::Memo { root, top, json } => {
+            let db_path = Path::new(&root).join("checkpoint.db");
+            if !db_path.exists() {
+                return Err(anyhow!("DB not found at: {:?}", db_path));
+            }
+            let store = CheckpointStore::open(&db_path)?;
+            let memo = report::memo_from_store(&store, top)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&memo)?);
+            } else {
+                report::print_memo(&memo);
+            }
+            Ok(())
+        }
+    }
+}
+
+// ============================================================================
+// 9. BENCHMARK: THE STOPWATCH
+// ============================================================================
+
+async fn run_benchmark(
+    root: String,
+    scenarios: Vec<String>,
+    timeout: u64,
+    stall: u64,
+    json: bool,
+    out: Option<PathBuf>,
+    transport_opts: TransportOpts,
+) -> Result<()> {
+    let root_path = PathBuf::from(&root);
+    let db_path = root_path.join("checkpoint.db");
+    if !db_path.exists() {
+        return Err(anyhow!(
+            "DB not found at: {:?} (is a node running on this root?)",
+            db_path
+        ));
+    }
+    let transport_cfg = transport_opts.resolve(&root_path)?;
+    let mut transport = open_architect(&transport_cfg, &root_path, "bench").await?;
+    let store = CheckpointStore::open(&db_path)?;
+
+    let suite: Vec<String> = if scenarios.is_empty() {
+        DEFAULT_SUITE.iter().map(|s| s.to_string()).collect()
+    } else {
+        scenarios
+    };
+    let mut report = BenchmarkReport::new(0);
+
+    for sig in suite {
+        let loader = DrawIoLoader::load_from_file(&sig)?;
+        let submit = build_submission(&loader.graph, false, Routing::default())?;
+        let ids: Vec<String> = submit.jobs.iter().map(|j| j.id.to_string()).collect();
+        let parents = parent_map(&submit);
+
+        let log_before = benchmark::eventlog_bytes(&root_path);
+        let db_before = benchmark::file_bytes(&db_path);
+        let submitted_ms = chrono::Utc::now().timestamp_millis();
+        let started = Instant::now();
+        submit_blueprint(transport.as_mut(), &root_path, submit).await?;
+        log::info!("⏱️  {}: submitted {} job(s)", sig, ids.len());
+
+        // Poll the DB (checkpointed every ~5s) until the scenario settles
+        let mut rows = Vec::new();
+        let mut last_change = Instant::now();
+        loop {
+            sleep(Duration::from_millis(500)).await;
+            let now = store.get_jobs_summary_for(&ids)?;
+            let changed = now.len() != rows.len()
+                || now.iter().any(|n| {
+                    !rows.iter().any(|r: &JobSummary| {
+                        r.id == n.id && r.status == n.status && r.updated_at == n.updated_at
+                    })
+                });
+            if changed {
+                last_change = Instant::now();
+            }
+            rows = now;
+
+            let open: Vec<&JobSummary> = rows
+                .iter()
+                .filter(|r| !benchmark::is_finished(&r.status))
+                .collect();
+            if rows.len() == ids.len() && open.is_empty() {
+                break;
+            }
+            let busy = open
+                .iter()
+                .any(|r| r.status == "Running" || r.status == "Queued");
+            if started.elapsed() >= Duration::from_secs(timeout)
+                || (!busy && last_change.elapsed() >= Duration::from_secs(stall))
+            {
+                log::warn!(
+                    "{}: {} job(s) did not finish, cancelling them",
+                    sig,
+                    ids.len() - (rows.len() - open.len())
+                );
+                break;
+            }
+        }
+
+        // Leave the cluster as we found it: stuck jobs would block later runs
+        for id in &ids {
+            let done = rows
+                .iter()
+                .any(|r| &r.id == id && benchmark::is_finished(&r.status));
+            if !done {
+                let req = ControlRequest {
+                    request_id: uuid::Uuid::new_v4(),
+                    command: ControlCommand::Cancel {
+                        job_id: id.parse()?,
+                    },
+                };
+                transport
+                    .send_to_coordinator(MSG_CONTROL, serde_json::to_value(&req)?)
+                    .await?;
+            }
+        }
+
+        report.scenarios.push(benchmark::summarize(
+            &sig,
+            submitted_ms,
+            started.elapsed().as_secs_f64(),
+            &parents,
+            &rows,
+            benchmark::eventlog_bytes(&root_path).saturating_sub(log_before),
+            benchmark::file_bytes(&db_path).saturating_sub(db_before),
+        ));
+    }
+
+    // Read last: a freshly started cluster has not checkpointed its workers yet
+    report.workers = store.get_active_workers()?.len();
+    if let Some(path) = &out {
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("writing {:?}", path))?;
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        benchmark::print_report(&report);
+    }
+    Ok(())
+}
+
+// ============================================================================
+// 10. LOCAL RUN: EVERYTHING IN ONE PROCESS
+// ============================================================================
+
+/// Worker id of the in-process Guardian.
+const LOCAL_WORKER: &str = "local";
+
+/// The in-process Guardian asks for work this often (a cluster node: 10s).
+const LOCAL_HEARTBEAT: Duration = Duration::from_secs(1);
+
+/// Under `--chaos`, dropped grants are handed out again this quickly.
+const CHAOS_GRANT_ACK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A fresh Coordinator transport, for restarts after a simulated crash.
+type Reopen<'a> = &'a dyn Fn() -> Box<dyn Transport>;
+
+/// Runs `graph`, loaded from `file`, to completion on this machine.
+async fn run_local(
+    file: String,
+    graph: WorkflowEngine,
+    timeout: u64,
+    stall: u64,
+    keep: bool,
+    json: bool,
+    chaos: Option<ChaosConfig>,
+) -> Result<()> {
+    let chaos = chaos.map(|cfg| Arc::new(Chaos::new(cfg)));
+    let submit = build_submission(&graph, false, Routing::default())?;
+    let parents = parent_map(&submit);
+
+    // Scratch root: checkpoint DB, artifact store
+    let root = std::env::temp_dir().join(format!("ulab_run_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root)?;
+    let db_path = root.join("checkpoint.db");
+
+    let net = MemNetwork::new();
+    let coordinator_transport = || -> Box<dyn Transport> {
+        match &chaos {
+            Some(c) => ChaosTransport::wrap(Box::new(net.coordinator()), c.clone()),
+            None => Box::new(net.coordinator()),
+        }
+    };
+    let open_store = || CheckpointStore::open_with(&db_path, StoreConfig::local());
+    let mut coord = MarketplaceCoordinator::open(coordinator_transport(), open_store()?).await?;
+    let mut guardian = NodeGuardian::boot(LOCAL_WORKER.into(), &root, open_store()?).await?;
+    let codec = StructureCodec::new(&root)?;
+    let mut worker: Box<dyn Transport> = Box::new(net.worker(Some(LOCAL_WORKER)));
+    if let Some(c) = &chaos {
+        coord = coord.with_grant_ack_timeout(CHAOS_GRANT_ACK_TIMEOUT);
+        guardian = guardian.with_chaos(c.clone());
+        worker = ChaosTransport::wrap(worker, c.clone());
+    }
+    let tags = vec!["brain".to_string(), "muscle".into(), "gpu".into()];
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let sig = stop.clone();
+    tokio::spawn(async move {
+        stop_requested().await;
+        log::warn!("🛑 Interrupt received. Stopping...");
+        sig.store(true, Ordering::SeqCst);
+    });
+
+    let submitted_ms = chrono::Utc::now().timestamp_millis();
+    let started = Instant::now();
+    let mut architect = net.worker(None);
+    submit_blueprint(&mut architect, &root, submit).await?;
+    log::info!("🏃 Running {} locally in {:?}", file, root);
+
+    // Coordinator and Guardian take turns on this task; the Coordinator
+    // side decides when the run is over
+    let coordinate = async {
+        let reopen: Reopen = &coordinator_transport;
+        let crash = chaos.as_ref().map(|c| (c.as_ref(), reopen));
+        let outcome = drive_to_completion(&mut coord, &stop, timeout, stall, crash).await;
+        stop.store(true, Ordering::SeqCst);
+        outcome
+    };
+    let no_drain = AtomicBool::new(false);
+    let guard = run_guardian_loop(
+        LOCAL_WORKER,
+        &tags,
+        &guardian,
+        worker.as_mut(),
+        &codec,
+        (&stop, &no_drain),
+        LOCAL_HEARTBEAT,
+    );
+    let (outcome, guarded) = tokio::join!(coordinate, guard);
+    outcome?;
+    guarded?;
+    coord.checkpoint_now()?;
+
+    let rows = CheckpointStore::open(&db_path)?.get_jobs_summary()?;
+    let report = benchmark::summarize(
+        &file,
+        submitted_ms,
+        started.elapsed().as_secs_f64(),
+        &parents,
+        &rows,
+        0, // the in-memory transport keeps no event log
+        benchmark::file_bytes(&db_path),
+    );
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_run_summary(&report, coord.jobs());
+        let memo = coord.memo_stats();
+        if memo.total.lookups > 0 {
+            println!(
+                "Memoization: {} of {} lookups hit, {} cached results",
+                memo.total.hits, memo.total.lookups, memo.entries
+            );
+        }
+    }
+
+    if keep {
+        println!("Run directory kept: {}", root.display());
+    } else {
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    // Under chaos, failures are expected; losing or repeating work is not
+    if let Some(c) = &chaos {
+        let check = c.check(coord.jobs());
+        if json {
+            eprintln!("{}", serde_json::to_string_pretty(&check)?);
+        } else {
+            println!("{}", check.summary());
+        }
+        if !check.holds() {
+            return Err(anyhow!(
+                "invariants violated: lost {:?}, completed twice {:?}",
+                check.lost,
+                check.double_completed
+            ));
+        }
+        return Ok(());
+    }
+
+    let incomplete = report.jobs - report.completed;
+    if incomplete > 0 {
+        return Err(anyhow!(
+            "{} of {} job(s) did not complete",
+            incomplete,
+            report.jobs
+        ));
+    }
+    Ok(())
+}
+
+/// Ticks the Coordinator until every job has finished, or until it stalls
+/// (nothing running, nothing changed for `stall` seconds), times out or is
+/// interrupted.
+async fn drive_to_completion(
+    coord: &mut MarketplaceCoordinator,
+    stop: &AtomicBool,
+    timeout: u64,
+    stall: u64,
+    crash: Option<(&Chaos, Reopen<'_>)>,
+) -> Result<()> {
+    let started = Instant::now();
+    let mut last_change = Instant::now();
+    let mut last_seen = (0, 0, 0);
+
+    while !stop.load(Ordering::SeqCst) {
+        if let Err(e) = coord.tick().await {
+            log::error!("Coordinator Tick Error: {}", e);
+        }
+
+        let (mut total, mut finished, mut busy) = (0, 0, 0);
+        for job in coord.jobs() {
+            total += 1;
+            match job.status {
+                JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled => finished += 1,
+                JobStatus::Running | JobStatus::Queued => busy += 1,
+                _ => {}
+            }
+        }
+        if total > 0 && finished == total {
+            return Ok(());
+        }
+        if (total, finished, busy) != last_seen {
+            last_seen = (total, finished, busy);
+            last_change = Instant::now();
+        }
+        if timeout > 0 && started.elapsed() >= Duration::from_secs(timeout) {
+            log::warn!(
+                "⌛ Timed out with {} of {} job(s) finished",
+                finished,
+                total
+            );
+            return Ok(());
+        }
+        if busy == 0 && last_change.elapsed() >= Duration::from_secs(stall) {
+            log::warn!(
+                "🧊 Nothing runnable for {}s, {} job(s) left unfinished",
+                stall,
+                total - finished
+            );
+            return Ok(());
+        }
+
+        // After the checks: a restarted Coordinator knows only the
+        // checkpoint until its next tick replays the inbox
+        if let Some((chaos, reopen)) = crash {
+            if chaos.crash_tick() {
+                log::warn!("🐒 Chaos: Coordinator crashed, restarting from checkpoint");
+                coord.crash_restart(reopen()).await?;
+            }
+        }
+        coord.wait_for_traffic(Duration::from_millis(100)).await;
+    }
+    Ok(())
+}
+
+fn print_run_summary<'a>(report: &ScenarioReport, jobs: impl Iterator<Item = &'a Job>) {
+    println!(
+        "{}: {} job(s) in {:.1}s | {} completed, {} failed, {} cancelled, {} unfinished",
+        report.scenario,
+        report.jobs,
+        report.wall_s,
+        report.completed,
+        report.failed,
+        report.cancelled,
+        report.unfinished
+    );
+    if let Some(l) = &report.latency {
+        println!(
+            "Scheduling latency p50 {:.0}ms, p95 {:.0}ms | {:.2} jobs/s",
+            l.p50_ms, l.p95_ms, report.throughput_jobs_per_s
+        );
+    }
+
+    let mut open: Vec<&Job> = jobs.filter(|j| j.status != JobStatus::Completed).collect();
+    open.sort_by(|a, b| a.structure.source.cmp(&b.structure.source));
+    for job in open {
+        let detail = job
+            .error_log
+            .as_deref()
+            .and_then(|e| e.lines().next())
+            .unwrap_or("");
+        println!(
+            "  {:<10} {:<24} {:<14} {}",
+            format!("{:?}", job.status).to_uppercase(),
+            job.structure.source,
+            job.config.engine.code(),
+            detail
+        );
+    }
+}
+
+// ============================================================================
+// 11. REPLAY: THE FLIGHT RECORDER
+// ============================================================================
+
+fn run_replay(
+    root: &Path,
+    log: Option<PathBuf>,
+    inbox: Option<String>,
+    outbox: Option<String>,
+    filter: &ReplayFilter,
+) -> Result<()> {
+    let path = match (log, inbox, outbox) {
+        (Some(p), None, None) => p,
+        (None, Some(id), None) => root.join("inbox").join(format!("worker_{}.log", id)),
+        (None, None, Some(id)) => root.join("outbox").join(format!("worker_{}.log", id)),
+        (None, None, None) => root.join("events.log"),
+        _ => return Err(anyhow!("Pick one of --log, --inbox and --outbox")),
+    };
+    // The reader would create a missing file; a typo should not
+    if !path.exists() && !Manifest::path_for(&path).exists() {
+        return Err(anyhow!("No log at {:?}", path));
+    }
+
+    let mut reader = EventLogReader::open(&path)?;
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let mut printed = 0u64;
+    let stats = replay::replay(&mut reader, filter, |line| {
+        printed += 1;
+        writeln!(out, "{}", serde_json::to_string(&line)?)?;
+        Ok(())
+    })?;
+    out.flush()?;
+
+    eprintln!(
+        "{}: {} record(s) printed, {} read{}",
+        path.display(),
+        printed,
+        stats.records + stats.filtered,
+        match stats.corruptions {
+            0 => String::new(),
+            n => format!(
+                ", {} damaged region(s) skipped ({} bytes)",
+                n, stats.skipped_bytes
+            ),
+        }
+    );
+    Ok(())
+}
+
+// ============================================================================
+// 12. REPRO: THE TIME MACHINE
+// ============================================================================
+
+fn run_repro(root: &Path, job: &str, out: Option<PathBuf>, outputs: bool) -> Result<()> {
+    let db_path = root.join("checkpoint.db");
+    if !db_path.exists() {
+        return Err(anyhow!("DB not found at: {:?}", db_path));
+    }
+    let db = CheckpointStore::open(&db_path)?;
+    let id = db.resolve_job_id(job)?;
+    let mut job = db.get_job_details(&id)?;
+
+    let files = job
+        .result
+        .take()
+        .map(|r| r.provenance.files)
+        .unwrap_or_default();
+    if files.is_empty() {
+        return Err(anyhow!(
+            "Job {} has no work dir manifest (not finished, or its driver keeps no files)",
+            id
+        ));
+    }
+
+    let out = out.unwrap_or_else(|| PathBuf::from(format!("repro_{}", &id[..8])));
+    if out.exists() && std::fs::read_dir(&out)?.next().is_some() {
+        return Err(anyhow!("{:?} is not empty", out));
+    }
+    std::fs::create_dir_all(&out)?;
+    let store = ArtifactStore::new(root.join("store"))?;
+    let roles = if outputs {
+        vec![FileRole::Input, FileRole::Output]
+    } else {
+        vec![FileRole::Input]
+    };
+    let written = store.restore_workdir(&files, &roles, &out)?;
+
+    // What the adapter was handed on stdin, and the full manifest
+    std::fs::write(
+        out.join("ulab_job.json"),
+        serde_json::to_string_pretty(&job)?,
+    )?;
+    std::fs::write(
+        out.join("ulab_manifest.json"),
+        serde_json::to_string_pretty(&files)?,
+    )?;
+
+    println!(
+        "Job {} ({}): {} of {} file(s) restored to {}",
+        id,
+        job.config.engine.code(),
+        written,
+        files.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+// ============================================================================
+// 13. DB SNAPSHOTS: THE MOVING VAN
+// ============================================================================
+
+fn run_db(op: DbOp) -> Result<()> {
+    match op {
+        DbOp::Export { root, out } => {
+            let db_path = Path::new(&root).join("checkpoint.db");
+            if !db_path.exists() {
+                return Err(anyhow!("DB not found at: {:?}", db_path));
+            }
+            let counts = CheckpointStore::open(&db_path)?.export_jsonl(&out)?;
+            println!(
+                "Exported {} job(s), {} status change(s), {} edge(s), {} memo result(s), {} worker(s), {} meta key(s) to {}",
+                counts.jobs,
+                counts.job_events,
+                counts.edges,
+                counts.memo,
+                counts.workers,
+                counts.meta,
+                out.display()
+            );
+        }
+        DbOp::Import { root, file } => {
+            std::fs::create_dir_all(&root)?;
+            let db_path = Path::new(&root).join("checkpoint.db");
+            let counts = CheckpointStore::open(&db_path)?.import_jsonl(&file)?;
+            println!(
+                "Imported {} job(s), {} status change(s), {} edge(s), {} memo result(s), {} worker(s), {} meta key(s) into {:?}",
+                counts.jobs,
+                counts.job_events,
+                counts.edges,
+                counts.memo,
+                counts.workers,
+                counts.meta,
+                db_path
+            );
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// 14. SEARCH: THE CARD CATALOGUE
+// ============================================================================
+
+fn run_search(root: &Path, query: &str, status: Option<&str>, json: bool) -> Result<()> {
+    let db_path = root.join("checkpoint.db");
+    if !db_path.exists() {
+        return Err(anyhow!("DB not found at: {:?}", db_path));
+    }
+    let hits: Vec<_> = CheckpointStore::open(&db_path)?
+        .search_jobs(query)?
+        .into_iter()
+        .filter(|h| status.map_or(true, |s| h.job.status.eq_ignore_ascii_case(s)))
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+        return Ok(());
+    }
+    println!("{:<10} {:<10} {:<18} MATCH", "ID", "STATUS", "ENGINE");
+    for h in &hits {
+        println!(
+            "{:<10} {:<10} {:<18} {}",
+            h.job.id.chars().take(8).collect::<String>(),
+            h.job.status,
+            h.job.code,
+            h.snippet.replace('\n', " ")
+        );
+    }
+    println!("{} job(s)", hits.len());
+    Ok(())
+}
+
+// ============================================================================
+// 15. LOGS: THE BLACK BOX
+// ============================================================================
+
+/// Lines `logs` shows from the end of each output by default.
+const DEFAULT_LOG_LINES: usize = 40;
+
+fn run_logs(root: &Path, job: &str, lines: usize, extra: &[String], json: bool) -> Result<()> {
+    let db_path = root.join("checkpoint.db");
+    if !db_path.exists() {
+        return Err(anyhow!("DB not found at: {:?}", db_path));
+    }
+    let db = CheckpointStore::open(&db_path)?;
+    let id = db.resolve_job_id(job)?;
+    let job = db.get_job_details(&id)?;
+    let provenance = job.result.as_ref().map(|r| &r.provenance);
+    let manifest = provenance.map_or(&[][..], |p| p.files.as_slice());
+
+    // Captured stdout/stderr if the run left any, then what was asked for
+    for name in extra {
+        if !manifest.iter().any(|f| &f.path == name) {
+            return Err(anyhow!("Job {} has no stored work dir file '{}'", id, name));
+        }
+    }
+    let wanted = [STDOUT_FILE, STDERR_FILE]
+        .into_iter()
+        .chain(extra.iter().map(String::as_str));
+    let files: Vec<_> = wanted
+        .filter_map(|name| manifest.iter().rev().find(|f| f.path == name))
+        .collect();
+    let mut tails: Vec<(String, String)> = Vec::new();
+    // A job with nothing captured never touches the store
+    if !files.is_empty() {
+        let store = ArtifactStore::new(root.join("store"))?;
+        for file in files {
+            let bytes = store.read_workfile(file)?;
+            let text = String::from_utf8_lossy(&bytes);
+            let mut tail: Vec<&str> = text.lines().rev().take(lines).collect();
+            tail.reverse();
+            tails.push((file.path.clone(), tail.join("\n")));
+        }
+    }
+
+    if json {
+        let out = serde_json::json!({
+            "id": id,
+            "status": job.status,
+            "engine": job.config.engine.code(),
+            "node_id": job.node_id,
+            "error_log": job.error_log,
+            "provenance": provenance,
+            "tails": tails.iter().cloned().collect::<std::collections::BTreeMap<_, _>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+
+    println!(
+        "Job {} ({}): {:?} on {}",
+        id,
+        job.config.engine.code(),
+        job.status,
+        job.node_id.as_deref().unwrap_or("-")
+    );
+    if let Some(err) = &job.error_log {
+        println!("Error: {}", err);
+    }
+    match provenance {
+        Some(p) => {
+            let ms = (p.end_time - p.start_time).num_milliseconds();
+            println!(
+                "Ran on {} from {} for {}, exit code {}",
+                p.execution_host,
+                p.start_time.format("%Y-%m-%d %H:%M:%S"),
+                report::human_ms(ms),
+                p.exit_code
+            );
+            println!("Sandbox: {}", p.sandbox_info);
+            if let Some(hash) = &p.binary_hash {
+                println!("Binary: {}", hash);
+            }
+            let outputs = p
+                .files
+                .iter()
+                .filter(|f| f.role == FileRole::Output)
+                .count();
+            println!(
+                "Work dir: {} input(s), {} output(s) stored (unifiedlab repro)",
+                p.files.len() - outputs,
+                outputs
+            );
+        }
+        None => {
+            println!("No provenance: the job has not completed (failed jobs keep only their error)")
+        }
+    }
+    if tails.is_empty() && provenance.is_some() {
+        println!("No stdout/stderr was captured for this job.");
+    }
+    for (name, text) in &tails {
+        println!();
+        println!("== {} (last {} lines) ==", name, lines);
+        println!("{}", text);
+    }
     Ok(())
 }
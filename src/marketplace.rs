@@ -8,19 +8,31 @@
 // Manages the DAG, matches jobs to workers, and handles dynamic expansion.
 // **TODO** write a detailed expansion plan
 
-use crate::checkpoint::{CheckpointStore, WorkerInfo};
-use crate::core::{CalculationResult, Job, JobConfig, JobStatus};
+use crate::checkpoint::{
+    CheckpointStore, EdgeDelta, EdgeKind, MemoCache, ProgressRecord, RetentionPolicy, WorkerInfo,
+};
+use crate::core::{
+    result_field, CalculationResult, Job, JobConfig, JobStatus, ResourceReq, ResultPredicate,
+};
+use crate::deadline::{self, FLOW_DEADLINE, FLOW_LATEST_START};
 use crate::eventlog::EventEnvelope;
-use crate::transport::Transport;
-use crate::workflow::{NodeType, WorkflowEngine};
-
-use anyhow::{anyhow, Result};
+use crate::feedback::{self, GenerationFeedback, DEFAULT_KT_EV, FEEDBACK_PARAM, KT_PARAM};
+use crate::quota::{QuotaBook, QuotaConfig, FLOW_PROJECT};
+use crate::transport::{Transport, TransportStats, READ_LAG_WARN_BYTES};
+use crate::wire::StructureCodec;
+use crate::workflow::{
+    EdgeType, NodeType, WorkflowEngine, DESCENDANTS_PARAM, EARLY_SWITCH_PARAM, UNTIL_PARAM,
+};
+
+use anyhow::{anyhow, Context, Result};
 use petgraph::graph::NodeIndex;
 use petgraph::Direction;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
@@ -33,11 +45,222 @@ pub const EV_JOB_COMPLETE: &str = "job.complete";
 pub const EV_WORK_GRANT: &str = "work.grant";
 pub const MSG_WORK_REQUEST: &str = "work.request";
 pub const MSG_JOB_COMPLETE: &str = "job.complete_report";
+pub const MSG_CONTROL: &str = "control.command";
+pub const EV_CONTROL_ACK: &str = "control.ack";
+pub const EV_WORK_PREEMPT: &str = "work.preempt";
+pub const EV_JOB_CANCEL: &str = "job.cancel";
+pub const MSG_GRANT_ACK: &str = "work.grant_ack";
+pub const MSG_WORK_YIELD: &str = "work.yield";
+pub const MSG_WORKER_DRAIN: &str = "worker.drain";
+/// Journal of live DAG edits (the applied `ControlCommand`), broadcast after the change.
+pub const EV_GRAPH_EDIT: &str = "graph.edit";
+/// Closes a transactional submission (`JobSubmit.txn`).
+pub const MSG_SUBMIT_END: &str = "submit.end";
+/// Outcome of a transactional submission, matched by `txn_id`.
+pub const EV_SUBMIT_ACK: &str = "submit.ack";
+/// A `JobArray`; answered with an `EV_SUBMIT_ACK` under the array's id.
+pub const MSG_JOB_ARRAY: &str = "job.array";
+/// A running job's intermediate result (`JobProgress`), sent best effort.
+pub const MSG_JOB_PROGRESS: &str = "job.progress";
+
+/// Inbox kinds `handle_worker_message` acts on. The file transport opened
+/// by `TransportFactory` passes over any other record without parsing it.
+pub const WORKER_MESSAGE_KINDS: &[&str] = &[
+    MSG_WORK_REQUEST,
+    MSG_GRANT_ACK,
+    MSG_WORK_YIELD,
+    MSG_WORKER_DRAIN,
+    MSG_JOB_COMPLETE,
+    EV_JOB_SUBMIT,
+    MSG_CONTROL,
+    MSG_SUBMIT_END,
+    MSG_JOB_ARRAY,
+    MSG_JOB_PROGRESS,
+];
+
+/// `Job::flow_context` key carrying the workflow node's priority; higher
+/// goes first out of the ready queue.
+pub const FLOW_PRIORITY: &str = "priority";
+
+/// `Job::flow_context` key naming the workflow a job was deployed from
+/// (its `metadata.name`, or the file stem of a Draw.io diagram).
+pub const FLOW_WORKFLOW: &str = "workflow";
+
+/// Priority of jobs submitted without `FLOW_PRIORITY` (that of a Compute node).
+pub const DEFAULT_PRIORITY: u32 = 50;
+
+/// Default cap on children accepted from a single generator expansion.
+pub const DEFAULT_EXPAND_LIMIT: usize = 100;
+
+/// How long a worker must sit without normal work before it gets opportunistic jobs.
+pub const DEFAULT_OPPORTUNISTIC_IDLE: Duration = Duration::from_secs(60);
+
+/// How long a grant may go unacknowledged before its jobs are offered elsewhere.
+pub const DEFAULT_GRANT_ACK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a Guardian sends its work request (its heartbeat).
+pub const WORKER_HEARTBEAT_EVERY: Duration = Duration::from_secs(10);
+
+/// How long a worker may stay silent (six missed heartbeats) before it is
+/// declared dead and its in-flight jobs are re-queued.
+pub const DEFAULT_WORKER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long past its `time_limit_min` a job may run before it is failed
+/// as timed out and killed on its worker.
+pub const DEFAULT_TIMEOUT_GRACE: Duration = Duration::from_secs(300);
+
+/// `error_log` prefix of jobs failed for running past their time limit.
+pub const TIMEOUT_REASON_PREFIX: &str = "Timeout: ";
+
+/// Finished runs of an engine needed before its stragglers are duplicated,
+/// so only large fanouts are speculated on.
+pub const SPECULATION_MIN_SAMPLES: usize = 10;
+
+/// Runtimes remembered per engine for the speculation median.
+const SPECULATION_WINDOW: usize = 256;
+
+/// `JobCancel` reason for the copies of a job that finished elsewhere.
+pub const SPECULATION_LOST: &str = "Lost speculative race";
+
+/// How often Blocked jobs are checked for parents that can never complete.
+pub const DEFAULT_DEADLOCK_SCAN: Duration = Duration::from_secs(10);
+
+/// How often the Coordinator stamps `META_HEARTBEAT`, busy or idle.
+pub const HEARTBEAT_EVERY: Duration = Duration::from_secs(10);
+
+/// How often the retention policy (if any) runs.
+pub const RETENTION_EVERY: Duration = Duration::from_secs(600);
+
+/// How often the Coordinator logs its transport counters and lagging workers.
+const STATS_LOG_EVERY: Duration = Duration::from_secs(60);
+
+/// `error_log` prefix of jobs cancelled by the deadlock detector.
+pub const DEADLOCK_REASON_PREFIX: &str = "Unsatisfiable: ";
+
+// Meta keys so runtime control state survives Coordinator restarts
+pub const META_PAUSED: &str = "paused";
+/// Workflows (`FLOW_WORKFLOW`) whose jobs get no new grants, as a JSON array.
+pub const META_PAUSED_WORKFLOWS: &str = "paused_workflows";
+pub const META_EXPAND_LIMIT: &str = "expand_limit";
+/// Running total of jobs cancelled as deadlocked (read by the TUI).
+pub const META_DEADLOCKED: &str = "deadlocked_jobs";
+/// Memoization counters as JSON `MemoStats` (read by the TUI and reports).
+pub const META_MEMO: &str = "memo_stats";
+/// Wall clock (ms) of the Coordinator's last heartbeat; lets the TUI tell a
+/// quiet cluster from a dead Coordinator.
+pub const META_HEARTBEAT: &str = "coordinator_heartbeat";
+/// Quota limits and current usage as a JSON `Vec<QuotaStatus>`, stamped
+/// with the heartbeat (read by the TUI and the console).
+pub const META_QUOTAS: &str = "quota_usage";
+/// Deadlines with unfinished jobs as a JSON `Vec<DeadlineStatus>`, stamped
+/// with the heartbeat (read by the TUI).
+pub const META_DEADLINES: &str = "deadline_status";
+/// Event ids of the last applied worker messages, as a JSON array.
+const META_SEEN_EVENTS: &str = "seen_events";
+/// Ids of the workers being drained, as a JSON array.
+const META_DRAINING: &str = "draining_workers";
+/// Job arrays with members still to be made, as JSON; written in the same
+/// transaction as the members already made.
+const META_ARRAYS: &str = "job_arrays";
+
+/// `Job::flow_context` keys of a job array member: the array's id and the
+/// member's index.
+pub const FLOW_ARRAY_ID: &str = "array_id";
+pub const FLOW_ARRAY_INDEX: &str = "array_index";
+
+/// How many event ids the Coordinator remembers to drop resent messages.
+pub const DEDUP_WINDOW: usize = 1024;
+
+/// How long a transactional submission may go without a new chunk or its
+/// end marker before it is discarded.
+pub const DEFAULT_SUBMIT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Jobs per chunk of a transactional submission.
+pub const SUBMIT_CHUNK_JOBS: usize = 500;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobSubmit {
     pub jobs: Vec<Job>,
     pub deps: Vec<(Uuid, Uuid)>,
+    /// Cluster placement, read by a Federation Lighthouse (cluster Coordinators ignore it).
+    #[serde(default, skip_serializing_if = "Routing::is_empty")]
+    pub routing: Routing,
+    /// Some: one chunk of a transaction. Nothing is applied until its
+    /// `SubmitEnd` arrives, then all chunks are applied together.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txn: Option<Uuid>,
+}
+
+impl JobSubmit {
+    /// Splits into chunks of at most `max_jobs` jobs under a new transaction
+    /// id. Each edge travels with its child; the routing with the first chunk.
+    pub fn into_chunks(self, max_jobs: usize) -> (Uuid, Vec<JobSubmit>) {
+        let txn = Uuid::new_v4();
+        let mut deps: HashMap<Uuid, Vec<(Uuid, Uuid)>> = HashMap::new();
+        for (pid, cid) in self.deps {
+            deps.entry(cid).or_default().push((pid, cid));
+        }
+        let mut routing = self.routing;
+        let mut chunks = Vec::new();
+        let mut jobs = self.jobs.into_iter().peekable();
+        while jobs.peek().is_some() {
+            let jobs: Vec<Job> = jobs.by_ref().take(max_jobs.max(1)).collect();
+            chunks.push(JobSubmit {
+                deps: jobs
+                    .iter()
+                    .flat_map(|j| deps.remove(&j.id).unwrap_or_default())
+                    .collect(),
+                jobs,
+                routing: std::mem::take(&mut routing),
+                txn: Some(txn),
+            });
+        }
+        // Edges into jobs outside the submission go last
+        let rest: Vec<_> = deps.into_values().flatten().collect();
+        match chunks.last_mut() {
+            Some(last) => last.deps.extend(rest),
+            None => chunks.push(JobSubmit {
+                jobs: vec![],
+                deps: rest,
+                routing,
+                txn: Some(txn),
+            }),
+        }
+        (txn, chunks)
+    }
+
+    /// Appends another chunk of the same transaction.
+    fn absorb(&mut self, chunk: JobSubmit) {
+        self.jobs.extend(chunk.jobs);
+        self.deps.extend(chunk.deps);
+        let Routing {
+            default,
+            engines,
+            jobs,
+        } = chunk.routing;
+        if default.is_some() {
+            self.routing.default = default;
+        }
+        self.routing.engines.extend(engines);
+        self.routing.jobs.extend(jobs);
+    }
+}
+
+/// Which cluster a job should run on. Most specific rule wins:
+/// `jobs` > `engines` > `default` > tag matching (see federation.rs).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Routing {
+    pub default: Option<String>,
+    /// Engine name ("janus", "vasp", ...) -> cluster.
+    pub engines: HashMap<String, String>,
+    pub jobs: HashMap<Uuid, String>,
+}
+
+impl Routing {
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.engines.is_empty() && self.jobs.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +268,50 @@ pub struct WorkGrant {
     pub worker_id: String,
     pub grant_id: String,
     pub jobs: Vec<Job>,
+    /// How long the grant stands without a `GrantAck`; after that the
+    /// Coordinator has re-queued its jobs. None from older Coordinators.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
+}
+
+impl WorkGrant {
+    /// True if the grant, broadcast at `sent_ms`, is past its TTL at `now_ms`
+    /// (both wall-clock ms). Its jobs may be running elsewhere by now.
+    pub fn expired(&self, sent_ms: i64, now_ms: i64) -> bool {
+        self.ttl_ms
+            .is_some_and(|ttl| now_ms.saturating_sub(sent_ms) >= ttl as i64)
+    }
+}
+
+/// Worker -> Coordinator: the grant arrived and its jobs are accepted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrantAck {
+    pub worker_id: String,
+    pub grant_id: String,
+}
+
+/// Worker -> Coordinator: this node is leaving (allocation shrinking). It hands
+/// back the granted jobs it has not started, as it received them; the
+/// Coordinator re-queues them first in line and stops granting to the node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkYield {
+    pub worker_id: String,
+    pub jobs: Vec<Job>,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// Operator (console or SIGUSR1 on the node) -> Coordinator: grant no new
+/// work to this worker, or take it back into service with `resume`. Its
+/// in-flight jobs run to completion either way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerDrain {
+    pub worker_id: String,
+    #[serde(default)]
+    pub resume: bool,
+    /// Set by the console to get a `ControlAck` back.
+    #[serde(default)]
+    pub request_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,19 +319,307 @@ pub struct WorkRequest {
     pub worker_id: String,
     pub available_cores: usize,
     pub available_gpus: usize,
+    /// Memory not promised to running jobs, in MB. None (older workers):
+    /// `ResourceReq::memory_mb` is not checked against this worker.
+    #[serde(default)]
+    pub available_memory_mb: Option<u64>,
     pub max_jobs: usize,
     #[serde(default)]
     pub tags: Vec<String>,
+    /// How far the worker trails the broadcast log (`TransportStats::read_lag`).
+    #[serde(default)]
+    pub read_lag: Option<u64>,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// Cores and GPUs of the whole machine, busy or free. None from older
+    /// workers.
+    #[serde(default)]
+    pub total_cores: Option<usize>,
+    #[serde(default)]
+    pub total_gpus: Option<usize>,
+}
+
+/// Coordinator -> worker: stop these opportunistic jobs, normal work needs the room.
+/// They are re-queued by the Coordinator; the worker sends no completion report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkPreempt {
+    pub worker_id: String,
+    pub job_ids: Vec<Uuid>,
+}
+
+/// Coordinator -> worker: kill these jobs and free their sandboxes (the
+/// operator cancelled them, or they ran past their time limit). The
+/// Coordinator has already settled them; the worker sends no completion report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobCancel {
+    pub worker_id: String,
+    pub job_ids: Vec<Uuid>,
+    /// Why, for the worker's log.
+    #[serde(default)]
+    pub reason: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobCompleteReport {
     pub job_id: Uuid,
+    /// Worker that ran this attempt, telling apart the two copies of a
+    /// speculated job. Empty in reports written before it was recorded.
+    #[serde(default)]
+    pub worker_id: String,
     pub status: JobStatus,
     pub result: Option<CalculationResult>,
     pub error: Option<String>,
 }
 
+/// Worker -> Coordinator: an intermediate result of a running job (see
+/// `ProgressSink`). Not resent: a lost snapshot is superseded by the next.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub job_id: Uuid,
+    pub worker_id: String,
+    pub snapshot: CalculationResult,
+}
+
+/// Operator commands (console / CLI) applied by the Coordinator at runtime.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ControlCommand {
+    Cancel {
+        job_id: Uuid,
+    },
+    Pause,
+    Resume,
+    /// Like `Pause`/`Resume`, for the jobs of one workflow (`FLOW_WORKFLOW`).
+    PauseWorkflow {
+        workflow: String,
+    },
+    ResumeWorkflow {
+        workflow: String,
+    },
+    SetExpandLimit {
+        limit: usize,
+    },
+    /// Live DAG edits: `child` waits on `parent` (or no longer does).
+    AddEdge {
+        parent: Uuid,
+        child: Uuid,
+    },
+    RemoveEdge {
+        parent: Uuid,
+        child: Uuid,
+    },
+    /// A new job, optionally waiting on existing ones.
+    AddNode {
+        job: Box<Job>,
+        parents: Vec<Uuid>,
+    },
+    /// Every unfinished job matching `filter`.
+    CancelJobs {
+        filter: JobFilter,
+    },
+    /// Every failed or cancelled job matching `filter`, back to Pending.
+    RetryJobs {
+        filter: JobFilter,
+    },
+}
+
+/// Selects jobs by every field that is set; an empty filter matches all.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobFilter {
+    /// Any of these jobs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub job_ids: Vec<Uuid>,
+    /// Deployed from this workflow (`FLOW_WORKFLOW`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<String>,
+    /// Counted against this project (`FLOW_PROJECT`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Run by this engine (`Engine::name`, any case).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine: Option<String>,
+    /// In one of these statuses.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub statuses: Vec<JobStatus>,
+}
+
+impl JobFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn matches(&self, job: &Job) -> bool {
+        let context = |key: &str| job.flow_context.get(key).and_then(|v| v.as_str());
+        (self.job_ids.is_empty() || self.job_ids.contains(&job.id))
+            && (self.workflow.is_none() || context(FLOW_WORKFLOW) == self.workflow.as_deref())
+            && (self.project.is_none() || context(FLOW_PROJECT) == self.project.as_deref())
+            && self
+                .engine
+                .as_ref()
+                .map_or(true, |e| job.config.engine.name().eq_ignore_ascii_case(e))
+            && (self.statuses.is_empty() || self.statuses.contains(&job.status))
+    }
+
+    /// Narrows the filter by one `key=value` pair (workflow, project or engine).
+    pub fn set(&mut self, pair: &str) -> Result<()> {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected key=value, got '{}'", pair))?;
+        let slot = match key.trim() {
+            "workflow" => &mut self.workflow,
+            "project" => &mut self.project,
+            "engine" => &mut self.engine,
+            other => {
+                return Err(anyhow!(
+                    "unknown filter '{}' (expected workflow|project|engine)",
+                    other
+                ))
+            }
+        };
+        *slot = Some(value.trim().to_string());
+        Ok(())
+    }
+}
+
+impl ControlCommand {
+    /// Changes the DAG (journaled as `graph.edit` once applied).
+    pub fn is_graph_edit(&self) -> bool {
+        matches!(
+            self,
+            Self::AddEdge { .. } | Self::RemoveEdge { .. } | Self::AddNode { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlRequest {
+    pub request_id: Uuid,
+    pub command: ControlCommand,
+}
+
+/// Broadcast reply to a `ControlRequest`, matched by `request_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlAck {
+    pub request_id: Uuid,
+    pub ok: bool,
+    pub message: String,
+}
+
+/// Deployer -> Coordinator: every chunk of `txn_id` has been sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitEnd {
+    pub txn_id: Uuid,
+    /// Chunks sent; fewer arrived means one was lost and nothing is applied.
+    pub chunks: usize,
+    /// The deployer gave up part way: discard what arrived.
+    #[serde(default)]
+    pub abort: bool,
+}
+
+/// Broadcast outcome of a transactional submission, or of a `JobArray`
+/// (`txn_id` is then the array's id).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitAck {
+    pub txn_id: Uuid,
+    /// True: every job was applied. False: none was.
+    pub ok: bool,
+    pub jobs: usize,
+    pub message: String,
+}
+
+/// Many near-identical jobs in one message. The Coordinator makes the
+/// members from `template` as workers ask for work, so the log holds one
+/// record and the checkpoint only the members made so far.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobArray {
+    pub id: Uuid,
+    /// Shared by every member; its id, status and parents are ignored.
+    pub template: Job,
+    /// Member indices, `start..end`.
+    pub start: u64,
+    pub end: u64,
+    /// Merged into the template's params for index `start + k`. Indices past
+    /// its end run with the template's params alone.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<Value>,
+}
+
+impl JobArray {
+    pub fn len(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The same for every call, so a member made twice is the same job.
+    pub fn member_id(&self, index: u64) -> Uuid {
+        Uuid::new_v5(&self.id, &index.to_be_bytes())
+    }
+
+    /// Member `index`, ready to queue.
+    pub fn member(&self, index: u64) -> Job {
+        let mut job = self.template.clone();
+        job.id = self.member_id(index);
+        job.status = JobStatus::Pending;
+        job.parent_ids.clear();
+        job.node_id = None;
+        job.result = None;
+        job.error_log = None;
+        job.attempts = 0;
+        job.created_at = chrono::Utc::now();
+        job.updated_at = job.created_at;
+        let extra = index
+            .checked_sub(self.start)
+            .and_then(|k| self.params.get(k as usize))
+            .and_then(|v| v.as_object());
+        if let (Some(params), Some(extra)) = (job.config.params.as_object_mut(), extra) {
+            params.extend(extra.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        job.flow_context
+            .insert(FLOW_ARRAY_ID.into(), self.id.to_string().into());
+        job.flow_context
+            .insert(FLOW_ARRAY_INDEX.into(), index.into());
+        job
+    }
+
+    /// Rejects per-index params that cannot be applied.
+    fn validate(&self) -> Result<()> {
+        if self.params.len() as u64 > self.len() {
+            return Err(anyhow!(
+                "{} per-index params for {} indices",
+                self.params.len(),
+                self.len()
+            ));
+        }
+        if !self.params.is_empty() && !self.template.config.params.is_object() {
+            return Err(anyhow!(
+                "Per-index params need template params that are an object"
+            ));
+        }
+        match self.params.iter().position(|p| !p.is_object()) {
+            Some(k) => Err(anyhow!(
+                "Params of index {} are not an object",
+                self.start + k as u64
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Decodes a message payload (already upgraded by the reader). One that still
+/// does not fit its type is logged rather than dropped without a trace.
+fn decode<T: DeserializeOwned>(kind: &str, offset: u64, payload: Value) -> Option<T> {
+    match serde_json::from_value(payload) {
+        Ok(msg) => Some(msg),
+        Err(e) => {
+            log::warn!("Ignoring '{}' record at offset {}: {}", kind, offset, e);
+            None
+        }
+    }
+}
+
 // =============================================================================
 // 2. INTERNAL STATE
 // =============================================================================
@@ -77,6 +632,10 @@ struct NodeState {
     inflight: bool,
     enqueued: bool,
     assigned_to: Option<String>,
+    /// A failed run waits out its retry backoff until then.
+    retry_at: Option<Instant>,
+    /// Worker running a speculative duplicate (see `with_speculation`).
+    speculative: Option<String>,
 }
 
 impl NodeState {
@@ -86,6 +645,7 @@ impl NodeState {
             && self.parents_done >= self.parents_total
             && self.job.status == JobStatus::Pending
             && !self.enqueued
+            && self.retry_at.is_none()
     }
 
     fn is_runnable_logic_only(&self) -> bool {
@@ -97,12 +657,359 @@ impl NodeState {
 }
 
 struct WorkerLive {
-    _last_seen: Instant,
+    last_seen: Instant,
     available_cores: usize,
     available_gpus: usize,
+    available_memory_mb: Option<u64>,
     inflight_jobs: usize,
     wants_work: bool,
     tags: HashSet<String>,
+    /// Since when no normal (non-opportunistic) job has been in flight here.
+    idle_since: Option<Instant>,
+    /// In-flight opportunistic jobs (preemptible).
+    opportunistic: HashSet<Uuid>,
+    read_lag: Option<u64>,
+    hostname: Option<String>,
+    total_cores: Option<usize>,
+    total_gpus: Option<usize>,
+}
+
+impl WorkerLive {
+    fn room(&self) -> Room {
+        Room {
+            cores: self.available_cores,
+            gpus: self.available_gpus,
+            memory_mb: self.available_memory_mb,
+        }
+    }
+}
+
+/// What a worker has free, as far as the Coordinator knows.
+#[derive(Debug, Clone, Copy)]
+struct Room {
+    cores: usize,
+    gpus: usize,
+    /// None: not reported, so not checked.
+    memory_mb: Option<u64>,
+}
+
+impl Room {
+    fn fits(&self, req: &ResourceReq) -> bool {
+        req.cores <= self.cores
+            && req.gpus <= self.gpus
+            && self.memory_mb.map_or(true, |m| req.memory_mb <= m)
+    }
+
+    fn take(&mut self, req: &ResourceReq) {
+        self.cores -= req.cores;
+        self.gpus -= req.gpus;
+        if let Some(m) = &mut self.memory_mb {
+            *m -= req.memory_mb;
+        }
+    }
+
+    fn give_back(&mut self, req: &ResourceReq) {
+        self.cores += req.cores;
+        self.gpus += req.gpus;
+        if let Some(m) = &mut self.memory_mb {
+            *m += req.memory_mb;
+        }
+    }
+
+    /// How many jobs of `req` fit at once.
+    fn copies_of(&self, req: &ResourceReq) -> usize {
+        let per = |free: u64, need: u64| free.checked_div(need).map_or(usize::MAX, |n| n as usize);
+        let by_mem = self.memory_mb.map_or(usize::MAX, |m| per(m, req.memory_mb));
+        per(self.cores as u64, req.cores.max(1) as u64)
+            .min(per(self.gpus as u64, req.gpus as u64))
+            .min(by_mem)
+    }
+}
+
+/// Memoization lookups made for expanded Compute jobs and how many found a
+/// completed job with the same config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoCounts {
+    pub lookups: u64,
+    pub hits: u64,
+}
+
+impl MemoCounts {
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups > 0 {
+            self.hits as f64 / self.lookups as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Running memoization counters, kept across restarts in `META_MEMO`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoStats {
+    pub total: MemoCounts,
+    /// Keyed by `Engine::code()`.
+    pub by_engine: BTreeMap<String, MemoCounts>,
+    /// Distinct fingerprints with a completed job (the cache size).
+    pub entries: usize,
+}
+
+/// Memoization key: SHA-256 of the serialized config (engine and params).
+/// A completed job answers for every later Compute job with the same key.
+pub fn job_fingerprint(config: &JobConfig) -> String {
+    // How often a job may be retried, which secrets it reads or which
+    // generator proposed it does not change its result
+    let stripped;
+    let config = if config.retry.is_some()
+        || !config.secrets.is_empty()
+        || config.params.get("generated_by").is_some()
+    {
+        let mut bare = JobConfig {
+            retry: None,
+            secrets: Default::default(),
+            ..config.clone()
+        };
+        if let Some(params) = bare.params.as_object_mut() {
+            params.remove("generated_by");
+        }
+        stripped = bare;
+        &stripped
+    } else {
+        config
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_string(config).unwrap_or_default().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// The job's `FLOW_PRIORITY`, or `DEFAULT_PRIORITY` if it has none.
+pub fn job_priority(job: &Job) -> u32 {
+    job.flow_context
+        .get(FLOW_PRIORITY)
+        .and_then(Value::as_u64)
+        .map_or(DEFAULT_PRIORITY, |p| p.min(u32::MAX as u64) as u32)
+}
+
+/// The workflow the job was deployed from (`FLOW_WORKFLOW`), if tagged.
+pub fn job_workflow(job: &Job) -> Option<&str> {
+    job.flow_context.get(FLOW_WORKFLOW).and_then(Value::as_str)
+}
+
+/// Dispatch order: higher priority first, then the earlier latest start
+/// (least slack before its deadline; none last), then the older submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct QueueKey {
+    priority: u32,
+    start_by: Reverse<i64>,
+    age: Reverse<i64>,
+}
+
+impl QueueKey {
+    fn of(job: &Job) -> Self {
+        Self {
+            priority: job_priority(job),
+            start_by: Reverse(deadline::latest_start(job).unwrap_or(i64::MAX)),
+            age: Reverse(job.created_at.timestamp_millis()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Queued {
+    key: QueueKey,
+    /// Insertion order breaks the remaining ties (FIFO).
+    seq: Reverse<u64>,
+    id: Uuid,
+}
+
+/// Runnable jobs waiting for a worker, popped by `QueueKey`.
+#[derive(Default)]
+struct ReadyQueue {
+    heap: BinaryHeap<Queued>,
+    next_seq: u64,
+}
+
+impl ReadyQueue {
+    fn push(&mut self, job: &Job) {
+        self.next_seq += 1;
+        self.heap.push(Queued {
+            key: QueueKey::of(job),
+            seq: Reverse(self.next_seq),
+            id: job.id,
+        });
+    }
+
+    fn pop(&mut self) -> Option<Queued> {
+        self.heap.pop()
+    }
+
+    /// Puts back an entry that was popped but not dispatched, in its old place.
+    fn requeue(&mut self, entry: Queued) {
+        self.heap.push(entry);
+    }
+
+    fn remove(&mut self, id: Uuid) {
+        self.heap.retain(|q| q.id != id);
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    fn clear(&mut self) {
+        self.heap.clear();
+    }
+
+    /// Queued ids in dispatch order.
+    fn ids(&self) -> Vec<Uuid> {
+        let mut entries: Vec<&Queued> = self.heap.iter().collect();
+        entries.sort_unstable_by(|a, b| b.cmp(a));
+        entries.into_iter().map(|q| q.id).collect()
+    }
+}
+
+/// A job array with members still to be made.
+#[derive(Serialize, Deserialize)]
+struct OpenArray {
+    array: JobArray,
+    /// Index of the next member to make.
+    next: u64,
+    /// Members made and not yet granted; rebuilt from the jobs on restore.
+    #[serde(skip)]
+    waiting: Vec<Uuid>,
+}
+
+/// Idempotency keys of the last `DEDUP_WINDOW` messages applied, oldest first.
+#[derive(Default)]
+struct SeenEvents {
+    order: VecDeque<Uuid>,
+    ids: HashSet<Uuid>,
+    /// Changed since the last checkpoint.
+    dirty: bool,
+}
+
+impl SeenEvents {
+    fn restore(order: VecDeque<Uuid>) -> Self {
+        Self {
+            ids: order.iter().copied().collect(),
+            order,
+            dirty: false,
+        }
+    }
+
+    /// False if `id` is already in the window.
+    fn insert(&mut self, id: Uuid) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        if self.order.len() > DEDUP_WINDOW {
+            if let Some(old) = self.order.pop_front() {
+                self.ids.remove(&old);
+            }
+        }
+        self.dirty = true;
+        true
+    }
+}
+
+/// Chunks of open transactional submissions, held until their `SubmitEnd`.
+/// Memory only: a transaction open across a restart ends as unknown, and
+/// the deployer sends it again.
+#[derive(Default)]
+pub(crate) struct SubmitTxns {
+    open: HashMap<Uuid, OpenTxn>,
+}
+
+struct OpenTxn {
+    sub: JobSubmit,
+    chunks: usize,
+    last_chunk: Instant,
+}
+
+impl SubmitTxns {
+    /// Holds a chunk back. A submission outside any transaction is handed
+    /// straight back, to be applied now.
+    pub(crate) fn stage(&mut self, sub: JobSubmit) -> Option<JobSubmit> {
+        let Some(txn) = sub.txn else {
+            return Some(sub);
+        };
+        match self.open.get_mut(&txn) {
+            Some(t) => {
+                t.sub.absorb(sub);
+                t.chunks += 1;
+                t.last_chunk = Instant::now();
+            }
+            None => {
+                self.open.insert(
+                    txn,
+                    OpenTxn {
+                        sub,
+                        chunks: 1,
+                        last_chunk: Instant::now(),
+                    },
+                );
+            }
+        }
+        None
+    }
+
+    /// Closes a transaction: the whole submission to apply, or why none of
+    /// it will be.
+    pub(crate) fn end(&mut self, end: &SubmitEnd) -> Result<JobSubmit> {
+        let t = self.open.remove(&end.txn_id).ok_or_else(|| {
+            anyhow!(
+                "Submission {} is unknown (lost in a restart?); nothing was applied",
+                end.txn_id
+            )
+        })?;
+        if end.abort {
+            return Err(anyhow!(
+                "Submission {} aborted by the deployer; {} chunk(s) discarded",
+                end.txn_id,
+                t.chunks
+            ));
+        }
+        if t.chunks != end.chunks {
+            return Err(anyhow!(
+                "Submission {}: {} of {} chunk(s) arrived; nothing was applied",
+                end.txn_id,
+                t.chunks,
+                end.chunks
+            ));
+        }
+        let mut sub = t.sub;
+        sub.txn = None;
+        Ok(sub)
+    }
+
+    /// Discards transactions that saw nothing for `timeout`.
+    pub(crate) fn expire(&mut self, timeout: Duration) -> Vec<SubmitAck> {
+        let mut acks = Vec::new();
+        self.open.retain(|&txn_id, t| {
+            let live = t.last_chunk.elapsed() < timeout;
+            if !live {
+                acks.push(SubmitAck {
+                    txn_id,
+                    ok: false,
+                    jobs: 0,
+                    message: format!(
+                        "Submission {} timed out after {} chunk(s) without an end marker; nothing was applied",
+                        txn_id, t.chunks
+                    ),
+                });
+            }
+            live
+        });
+        acks
+    }
+}
+
+/// A grant sent but not yet acknowledged by its worker.
+struct PendingGrant {
+    worker_id: String,
+    job_ids: Vec<Uuid>,
+    sent: Instant,
 }
 
 // =============================================================================
@@ -115,21 +1022,118 @@ pub struct MarketplaceCoordinator {
     workflow: WorkflowEngine,
     landscape_registry: HashMap<String, Uuid>,
     nodes: HashMap<Uuid, NodeState>,
-    ready_queue: VecDeque<Uuid>,
+    ready_queue: ReadyQueue,
+    /// Runnable opportunistic jobs, handed only to idle workers.
+    idle_queue: ReadyQueue,
     workers: HashMap<String, WorkerLive>,
     dirty_jobs: HashSet<Uuid>,
+    /// Written with the next checkpoint, alongside the jobs they belong to.
+    dirty_edges: EdgeDelta,
+    /// Broadcasts made while handling this tick's messages, written together.
+    outgoing: Vec<(&'static str, Value)>,
+    /// Jobs to kill where they run, by worker and reason; sent after the broadcasts.
+    cancels: BTreeMap<(String, &'static str), Vec<Uuid>>,
+    /// When each in-flight job was granted (tokio's clock, so tests can
+    /// pause it). Entries of jobs no longer in flight are dropped lazily.
+    dispatched: HashMap<Uuid, tokio::time::Instant>,
+    /// Jobs waiting out a retry backoff (see `NodeState::retry_at`).
+    retrying: HashSet<Uuid>,
     last_ckpt: Instant,
+    last_heartbeat: Option<Instant>,
     global_cursor: u64,
+    codec: Option<StructureCodec>,
+    paused: bool,
+    /// Workflows whose jobs get no new grants (`PauseWorkflow`).
+    paused_workflows: BTreeSet<String>,
+    /// Workers that get no new grants (`WorkerDrain`).
+    draining: BTreeSet<String>,
+    expand_limit: usize,
+    opportunistic_idle: Duration,
+    pending_grants: HashMap<String, PendingGrant>,
+    grant_ack_timeout: Duration,
+    worker_timeout: Duration,
+    timeout_grace: Duration,
+    deadlock_scan: Duration,
+    last_deadlock_scan: Instant,
+    deadlocked_total: u64,
+    last_stats_log: Instant,
+    last_stats: TransportStats,
+    memo: MemoStats,
+    seen_events: SeenEvents,
+    submit_txns: SubmitTxns,
+    submit_timeout: Duration,
+    retention: Option<RetentionPolicy>,
+    /// None until the first pass, which runs on the first tick.
+    last_retention: Option<Instant>,
+    quotas: QuotaBook,
+    /// Results shared with other campaigns, consulted on a local miss.
+    memo_cache: Option<MemoCache>,
+    /// Stragglers past this multiple of their engine's median get a duplicate.
+    speculation: Option<f64>,
+    /// Grant-to-report times of the last completed jobs, by engine code.
+    runtimes: HashMap<String, VecDeque<Duration>>,
+    /// Deadlines already reported as missed (see `check_deadlines`).
+    late_deadlines: BTreeSet<chrono::DateTime<chrono::Utc>>,
+    /// `META_DEADLINES` may list something: it did at the last heartbeat,
+    /// or it was written before a restart.
+    had_deadlines: bool,
+    /// Snapshots of running jobs, written with the next checkpoint.
+    progress: Vec<(Uuid, ProgressRecord)>,
+    /// Job arrays in arrival order, expanded by `expand_arrays`.
+    arrays: Vec<OpenArray>,
+    arrays_dirty: bool,
 }
 
 impl MarketplaceCoordinator {
     pub async fn open(transport: Box<dyn Transport>, store: CheckpointStore) -> Result<Self> {
-        let jobs_map = store.restore_jobs()?;
+        let mut jobs_map = store.restore_jobs()?;
+        // The edges table and `parent_ids` are written together; a job
+        // from before the table has its edges backfilled from the latter
+        let edges = store.get_edges()?;
+        for e in &edges {
+            if let Some(job) = jobs_map.get_mut(&e.child) {
+                if !job.parent_ids.contains(&e.parent) {
+                    job.parent_ids.push(e.parent);
+                }
+            }
+        }
         let cursor = store.get_cursor()?;
+        let paused = store.get_meta(META_PAUSED)?.as_deref() == Some("true");
+        let paused_workflows: BTreeSet<String> = store
+            .get_meta(META_PAUSED_WORKFLOWS)?
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default();
+        let draining: BTreeSet<String> = store
+            .get_meta(META_DRAINING)?
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default();
+        let expand_limit = store
+            .get_meta(META_EXPAND_LIMIT)?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_EXPAND_LIMIT);
+        let deadlocked_total = store
+            .get_meta(META_DEADLOCKED)?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let memo: MemoStats = store
+            .get_meta(META_MEMO)?
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default();
+        let mut arrays: Vec<OpenArray> = store
+            .get_meta(META_ARRAYS)?
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default();
+        let seen_events = SeenEvents::restore(
+            store
+                .get_meta(META_SEEN_EVENTS)?
+                .and_then(|v| serde_json::from_str(&v).ok())
+                .unwrap_or_default(),
+        );
 
         let mut nodes = HashMap::new();
         let mut workflow = WorkflowEngine::new();
-        let mut landscape_registry = HashMap::new();
+        // Pruned jobs still answer for their fingerprints
+        let mut landscape_registry = store.get_memo_index()?;
 
         for (id, job) in jobs_map {
             nodes.insert(
@@ -142,6 +1146,8 @@ impl MarketplaceCoordinator {
                     inflight: false,
                     enqueued: false,
                     assigned_to: None,
+                    retry_at: None,
+                    speculative: None,
                 },
             );
 
@@ -152,16 +1158,27 @@ impl MarketplaceCoordinator {
                 .unwrap_or(NodeType::Compute);
 
             if job.status == JobStatus::Completed {
-                let fingerprint = Self::fingerprint_job(&job.config);
+                let fingerprint = job_fingerprint(&job.config);
                 landscape_registry.insert(fingerprint, id);
             }
 
-            let _ = workflow.add_smart_node(job, n_type, vec![], 50, true);
+            let priority = job_priority(&job);
+            let _ = workflow.add_smart_node(job, n_type, vec![], priority, true);
+        }
+        // So Switch pruning and expansions see the restored graph
+        for e in &edges {
+            if let (Some(&p), Some(&c)) = (
+                workflow.id_map.get(&e.parent),
+                workflow.id_map.get(&e.child),
+            ) {
+                workflow.graph.add_edge(p, c, EdgeType::HardDependency);
+            }
         }
 
-        let completed_or_failed: HashSet<Uuid> = nodes
+        // Only completed parents release children; failed ones leave them to the deadlock scan
+        let completed: HashSet<Uuid> = nodes
             .values()
-            .filter(|n| matches!(n.job.status, JobStatus::Completed | JobStatus::Failed))
+            .filter(|n| n.job.status == JobStatus::Completed)
             .map(|n| n.job.id)
             .collect();
 
@@ -171,7 +1188,7 @@ impl MarketplaceCoordinator {
                 .job
                 .parent_ids
                 .iter()
-                .filter(|pid| completed_or_failed.contains(pid))
+                .filter(|pid| completed.contains(pid))
                 .count();
 
             if node.job.status == JobStatus::Pending && node.parents_total > node.parents_done {
@@ -183,33 +1200,189 @@ impl MarketplaceCoordinator {
             }
         }
 
+        for open in &mut arrays {
+            let id = open.array.id.to_string();
+            open.waiting = nodes
+                .values()
+                .filter(|n| n.is_runnable_logic_only())
+                .filter(|n| {
+                    n.job
+                        .flow_context
+                        .get(FLOW_ARRAY_ID)
+                        .and_then(|v| v.as_str())
+                        == Some(id.as_str())
+                })
+                .map(|n| n.job.id)
+                .collect();
+        }
+
         let mut coord = Self {
             transport,
             store,
             nodes,
             workflow,
             landscape_registry,
-            ready_queue: VecDeque::new(),
+            ready_queue: ReadyQueue::default(),
+            idle_queue: ReadyQueue::default(),
             workers: HashMap::new(),
             dirty_jobs: HashSet::new(),
+            dirty_edges: EdgeDelta::default(),
+            outgoing: Vec::new(),
+            cancels: BTreeMap::new(),
+            dispatched: HashMap::new(),
+            retrying: HashSet::new(),
             last_ckpt: Instant::now(),
+            last_heartbeat: None,
             global_cursor: cursor,
+            codec: None,
+            paused,
+            paused_workflows,
+            draining,
+            expand_limit,
+            opportunistic_idle: DEFAULT_OPPORTUNISTIC_IDLE,
+            pending_grants: HashMap::new(),
+            grant_ack_timeout: DEFAULT_GRANT_ACK_TIMEOUT,
+            worker_timeout: DEFAULT_WORKER_TIMEOUT,
+            timeout_grace: DEFAULT_TIMEOUT_GRACE,
+            deadlock_scan: DEFAULT_DEADLOCK_SCAN,
+            last_deadlock_scan: Instant::now(),
+            deadlocked_total,
+            last_stats_log: Instant::now(),
+            last_stats: TransportStats::default(),
+            memo,
+            seen_events,
+            submit_txns: SubmitTxns::default(),
+            submit_timeout: DEFAULT_SUBMIT_TIMEOUT,
+            retention: None,
+            last_retention: None,
+            quotas: QuotaBook::default(),
+            memo_cache: None,
+            speculation: None,
+            runtimes: HashMap::new(),
+            late_deadlines: BTreeSet::new(),
+            had_deadlines: true,
+            progress: Vec::new(),
+            arrays,
+            arrays_dirty: false,
         };
 
+        coord.plan_deadlines();
         coord.rebuild_ready_queue();
         coord.transport.seek(cursor).await?;
 
         Ok(coord)
     }
 
-    fn fingerprint_job(config: &JobConfig) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(
-            serde_json::to_string(&config)
-                .unwrap_or_default()
-                .as_bytes(),
-        );
-        format!("{:x}", hasher.finalize())
+    /// Enables CAS offloading of large structures in submit/grant broadcasts.
+    /// Ignored when the transport's peers cannot see our `root/store`.
+    pub fn with_structure_codec(mut self, codec: StructureCodec) -> Self {
+        if self.transport.shares_filesystem() {
+            self.codec = Some(codec);
+        } else {
+            log::info!("Structure offloading disabled: transport has no shared store");
+        }
+        self
+    }
+
+    /// Idle time a worker needs before it is offered opportunistic jobs.
+    pub fn with_opportunistic_idle(mut self, idle: Duration) -> Self {
+        self.opportunistic_idle = idle;
+        self
+    }
+
+    /// How long a worker has to acknowledge a grant before its jobs are re-queued.
+    pub fn with_grant_ack_timeout(mut self, timeout: Duration) -> Self {
+        self.grant_ack_timeout = timeout;
+        self
+    }
+
+    /// How long a worker may go without a work request before its in-flight
+    /// jobs are re-queued.
+    pub fn with_worker_timeout(mut self, timeout: Duration) -> Self {
+        self.worker_timeout = timeout;
+        self
+    }
+
+    /// How long a job may run past its `time_limit_min` before it is failed.
+    pub fn with_timeout_grace(mut self, grace: Duration) -> Self {
+        self.timeout_grace = grace;
+        self
+    }
+
+    /// How often to look for Blocked jobs that can never be released.
+    pub fn with_deadlock_scan(mut self, every: Duration) -> Self {
+        self.deadlock_scan = every;
+        self
+    }
+
+    /// How long a transactional submission may stall before it is discarded.
+    pub fn with_submit_timeout(mut self, timeout: Duration) -> Self {
+        self.submit_timeout = timeout;
+        self
+    }
+
+    /// Prunes old finished jobs from the checkpoint DB every `RETENTION_EVERY`.
+    /// Pruned jobs stay in memory until the Coordinator restarts.
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = Some(policy);
+        self
+    }
+
+    /// Caps what each project / tag may hold in flight at once.
+    pub fn with_quotas(mut self, config: QuotaConfig) -> Self {
+        self.quotas = QuotaBook::new(config);
+        self
+    }
+
+    /// Looks up fingerprints the local registry misses in `cache`, and
+    /// records completed jobs there at every checkpoint.
+    pub fn with_memo_cache(mut self, cache: MemoCache) -> Self {
+        self.memo_cache = Some(cache);
+        self
+    }
+
+    /// Duplicates jobs running longer than `factor` times the median runtime
+    /// of their engine (once `SPECULATION_MIN_SAMPLES` have finished) onto a
+    /// worker with room to spare. The first copy to finish wins; the other
+    /// is killed.
+    pub fn with_speculation(mut self, factor: f64) -> Self {
+        self.speculation = Some(factor);
+        self
+    }
+
+    /// Throws away everything not yet checkpointed and restores from the
+    /// store over a fresh transport, as a process restart would. Builder
+    /// settings carry over. Used by fault injection (`crate::chaos`).
+    pub async fn crash_restart(&mut self, transport: Box<dyn Transport>) -> Result<()> {
+        let store = CheckpointStore::open_with(self.store.path(), self.store.config().clone())?;
+        let mut fresh = Self::open(transport, store).await?;
+        fresh.codec = self.codec.take();
+        fresh.opportunistic_idle = self.opportunistic_idle;
+        fresh.grant_ack_timeout = self.grant_ack_timeout;
+        fresh.worker_timeout = self.worker_timeout;
+        fresh.timeout_grace = self.timeout_grace;
+        fresh.deadlock_scan = self.deadlock_scan;
+        fresh.submit_timeout = self.submit_timeout;
+        fresh.retention = self.retention.take();
+        fresh.quotas = std::mem::take(&mut self.quotas);
+        fresh.memo_cache = self.memo_cache.take();
+        fresh.speculation = self.speculation;
+        *self = fresh;
+        Ok(())
+    }
+
+    fn offload_for_wire(&self, jobs: &mut [Job]) -> Result<()> {
+        match &self.codec {
+            Some(c) => c.offload_jobs(jobs),
+            None => Ok(()),
+        }
+    }
+
+    fn hydrate_from_wire(&self, jobs: &mut [Job]) -> Result<()> {
+        match &self.codec {
+            Some(c) => c.hydrate_jobs(jobs),
+            None => Ok(()),
+        }
     }
 
     pub async fn tick(&mut self) -> Result<()> {
@@ -217,36 +1390,263 @@ impl MarketplaceCoordinator {
         for env in msgs {
             self.handle_worker_message(env).await?;
         }
+        for ack in self.submit_txns.expire(self.submit_timeout) {
+            log::warn!("📦 {}", ack.message);
+            self.queue_broadcast(EV_SUBMIT_ACK, serde_json::to_value(&ack)?);
+        }
+        self.fail_overdue().await?;
+        self.flush_broadcasts().await?;
+        self.send_cancels().await?;
+        self.redeliver_unacked();
+        self.reap_dead_workers();
+        self.release_retries();
         self.schedule_work().await?;
+        self.maybe_detect_deadlocks()?;
         self.maybe_checkpoint()?;
+        self.maybe_prune();
+        self.maybe_log_stats();
+        Ok(())
+    }
+
+    /// A failed pass (e.g. a full archive disk) is retried next time round
+    /// rather than stopping the Coordinator.
+    fn maybe_prune(&mut self) {
+        let Some(policy) = &self.retention else {
+            return;
+        };
+        if self
+            .last_retention
+            .is_some_and(|t| t.elapsed() < RETENTION_EVERY)
+        {
+            return;
+        }
+        self.last_retention = Some(Instant::now());
+
+        match policy.apply(&self.store) {
+            Ok(0) => {}
+            Ok(n) => log::info!("🧹 Pruned {} finished job(s) from the checkpoint DB", n),
+            Err(e) => log::warn!("Retention pass failed: {:#}", e),
+        }
+    }
+
+    /// Traffic since the last report, the Coordinator's own backlog, the
+    /// memoization hit rate, and any worker trailing the broadcast log.
+    fn maybe_log_stats(&mut self) {
+        let secs = self.last_stats_log.elapsed().as_secs_f64();
+        if secs < STATS_LOG_EVERY.as_secs_f64() {
+            return;
+        }
+        let now = self.transport.stats();
+        let prev = std::mem::replace(&mut self.last_stats, now);
+        self.last_stats_log = Instant::now();
+
+        log::info!(
+            "📶 Transport: in {:.1} msg/s ({} B), out {:.1} msg/s ({} B), inbox lag {} B, backlog {}, corrupt {} B",
+            (now.events_read - prev.events_read) as f64 / secs,
+            now.bytes_read - prev.bytes_read,
+            (now.events_written - prev.events_written) as f64 / secs,
+            now.bytes_written - prev.bytes_written,
+            now.read_lag.unwrap_or(0),
+            now.backlog.unwrap_or(0),
+            now.skipped_bytes - prev.skipped_bytes
+        );
+        if self.memo.total.lookups > 0 {
+            log::info!(
+                "♻️ Memo: {}/{} lookups hit ({:.0}%), {} cached results",
+                self.memo.total.hits,
+                self.memo.total.lookups,
+                self.memo.total.hit_rate() * 100.0,
+                self.landscape_registry.len()
+            );
+        }
+        if !self.arrays.is_empty() {
+            log::info!(
+                "🧮 Arrays: {} job(s) in {} array(s) not made yet",
+                self.array_backlog(),
+                self.arrays.len()
+            );
+        }
+        for (id, w) in &self.workers {
+            if let Some(lag) = w.read_lag.filter(|l| *l >= READ_LAG_WARN_BYTES) {
+                log::warn!("🐢 {} is {} B behind the broadcast log", id, lag);
+            }
+        }
+    }
+
+    /// Tells workers to kill the cancelled or timed-out jobs they are running.
+    async fn send_cancels(&mut self) -> Result<()> {
+        for ((wid, reason), job_ids) in std::mem::take(&mut self.cancels) {
+            let msg = JobCancel {
+                worker_id: wid.clone(),
+                job_ids,
+                reason: reason.to_string(),
+            };
+            self.transport
+                .send_to_worker(&wid, EV_JOB_CANCEL, serde_json::to_value(&msg)?)
+                .await?;
+        }
         Ok(())
     }
 
+    /// Sleeps until the next tick is due, or earlier if the transport sees traffic.
+    pub async fn wait_for_traffic(&mut self, max: Duration) {
+        self.transport.wait_for_traffic(max).await;
+    }
+
+    /// Queues a broadcast for `flush_broadcasts`, so one tick's worth of
+    /// echoes, acks and expansions costs one log flush (and fsync).
+    fn queue_broadcast(&mut self, kind: &'static str, payload: Value) {
+        self.outgoing.push((kind, payload));
+    }
+
+    /// Writes queued broadcasts in order. Runs before grants go out and
+    /// before the cursor is checkpointed, so workers see jobs before being
+    /// granted them, and a crash re-reads the messages behind a lost batch.
+    async fn flush_broadcasts(&mut self) -> Result<()> {
+        if self.outgoing.is_empty() {
+            return Ok(());
+        }
+        let batch: Vec<(&str, Value)> = std::mem::take(&mut self.outgoing);
+        self.transport.broadcast_batch(batch).await?;
+        Ok(())
+    }
+
+    /// New kinds must also be added to `WORKER_MESSAGE_KINDS`.
     async fn handle_worker_message(&mut self, env: EventEnvelope) -> Result<()> {
         if env.next_offset > self.global_cursor {
             self.global_cursor = env.next_offset;
         }
 
-        match env.record.kind.as_str() {
+        let EventEnvelope { offset, record, .. } = env;
+        let (kind, payload) = (record.kind.as_str(), record.payload);
+
+        // A worker resends what it could not confirm was delivered
+        if let Some(id) = record.event_id {
+            if !self.seen_events.insert(id) {
+                log::debug!("Dropping resent {} {} at offset {}", kind, id, offset);
+                return Ok(());
+            }
+        }
+        match kind {
             MSG_WORK_REQUEST => {
-                if let Ok(req) = serde_json::from_value::<WorkRequest>(env.record.payload) {
+                if let Some(req) = decode::<WorkRequest>(kind, offset, payload) {
                     self.update_worker_live(req);
                 }
             }
+            MSG_GRANT_ACK => {
+                if let Some(ack) = decode::<GrantAck>(kind, offset, payload) {
+                    if self.pending_grants.remove(&ack.grant_id).is_none() {
+                        log::debug!("Late ack for grant {} from {}", ack.grant_id, ack.worker_id);
+                    }
+                }
+            }
+            MSG_WORK_YIELD => {
+                if let Some(y) = decode::<WorkYield>(kind, offset, payload) {
+                    self.apply_work_yield(y);
+                }
+            }
+            MSG_WORKER_DRAIN => {
+                if let Some(d) = decode::<WorkerDrain>(kind, offset, payload) {
+                    let request_id = d.request_id;
+                    let message = self.apply_drain(d)?;
+                    log::info!("🚧 {}", message);
+                    if let Some(request_id) = request_id {
+                        let ack = ControlAck {
+                            request_id,
+                            ok: true,
+                            message,
+                        };
+                        self.queue_broadcast(EV_CONTROL_ACK, serde_json::to_value(&ack)?);
+                    }
+                }
+            }
             MSG_JOB_COMPLETE => {
-                if let Ok(rep) = serde_json::from_value::<JobCompleteReport>(env.record.payload) {
-                    self.transport
-                        .broadcast(EV_JOB_COMPLETE, serde_json::to_value(&rep)?)
-                        .await?;
+                if let Some(rep) = decode::<JobCompleteReport>(kind, offset, payload) {
+                    self.queue_broadcast(EV_JOB_COMPLETE, serde_json::to_value(&rep)?);
                     self.apply_job_complete(rep).await?;
                 }
             }
+            MSG_JOB_PROGRESS => {
+                if let Some(p) = decode::<JobProgress>(kind, offset, payload) {
+                    self.apply_job_progress(p);
+                }
+            }
             EV_JOB_SUBMIT => {
-                if let Ok(sub) = serde_json::from_value::<JobSubmit>(env.record.payload) {
-                    self.transport
-                        .broadcast(EV_JOB_SUBMIT, serde_json::to_value(&sub)?)
-                        .await?;
-                    self.ingest_submission(sub);
+                let staged = decode::<JobSubmit>(kind, offset, payload)
+                    .and_then(|sub| self.submit_txns.stage(sub));
+                if let Some(sub) = staged {
+                    if let Err(e) = self.accept_submission(sub) {
+                        log::error!("Dropping submission: {}", e);
+                    }
+                }
+            }
+            MSG_SUBMIT_END => {
+                if let Some(end) = decode::<SubmitEnd>(kind, offset, payload) {
+                    let applied = self
+                        .submit_txns
+                        .end(&end)
+                        .and_then(|sub| self.accept_submission(sub));
+                    let ack = match applied {
+                        Ok(jobs) => SubmitAck {
+                            txn_id: end.txn_id,
+                            ok: true,
+                            jobs,
+                            message: format!("Submission {} applied: {} job(s)", end.txn_id, jobs),
+                        },
+                        Err(e) => SubmitAck {
+                            txn_id: end.txn_id,
+                            ok: false,
+                            jobs: 0,
+                            message: e.to_string(),
+                        },
+                    };
+                    log::info!("📦 {}", ack.message);
+                    self.queue_broadcast(EV_SUBMIT_ACK, serde_json::to_value(&ack)?);
+                }
+            }
+            MSG_JOB_ARRAY => {
+                if let Some(array) = decode::<JobArray>(kind, offset, payload) {
+                    let txn_id = array.id;
+                    let ack = match self.accept_array(array) {
+                        Ok(jobs) => SubmitAck {
+                            txn_id,
+                            ok: true,
+                            jobs: jobs as usize,
+                            message: format!("Array {} accepted: {} job(s)", txn_id, jobs),
+                        },
+                        Err(e) => SubmitAck {
+                            txn_id,
+                            ok: false,
+                            jobs: 0,
+                            message: e.to_string(),
+                        },
+                    };
+                    log::info!("🧮 {}", ack.message);
+                    self.queue_broadcast(EV_SUBMIT_ACK, serde_json::to_value(&ack)?);
+                }
+            }
+            MSG_CONTROL => {
+                if let Some(req) = decode::<ControlRequest>(kind, offset, payload) {
+                    let edit = req.command.is_graph_edit().then(|| req.command.clone());
+                    let ack = match self.apply_control(req.command) {
+                        Ok(message) => {
+                            if let Some(edit) = edit {
+                                self.queue_broadcast(EV_GRAPH_EDIT, serde_json::to_value(&edit)?);
+                            }
+                            ControlAck {
+                                request_id: req.request_id,
+                                ok: true,
+                                message,
+                            }
+                        }
+                        Err(e) => ControlAck {
+                            request_id: req.request_id,
+                            ok: false,
+                            message: e.to_string(),
+                        },
+                    };
+                    log::info!("🎛️ Control: {}", ack.message);
+                    self.queue_broadcast(EV_CONTROL_ACK, serde_json::to_value(&ack)?);
                 }
             }
             _ => {}
@@ -254,32 +1654,560 @@ impl MarketplaceCoordinator {
         Ok(())
     }
 
+    /// Echoes a whole submission to the workers and adds it to the DAG.
+    /// Returns the number of jobs.
+    fn accept_submission(&mut self, mut sub: JobSubmit) -> Result<usize> {
+        let mut wire_sub = sub.clone();
+        self.offload_for_wire(&mut wire_sub.jobs)?;
+        self.hydrate_from_wire(&mut sub.jobs)
+            .context("Unreadable structures")?;
+        self.queue_broadcast(EV_JOB_SUBMIT, serde_json::to_value(&wire_sub)?);
+        let jobs = sub.jobs.len();
+        self.ingest_submission(sub, EdgeKind::Submitted);
+        Ok(jobs)
+    }
+
+    /// Records a job array; `expand_arrays` makes its members. Returns the
+    /// number of members.
+    fn accept_array(&mut self, mut array: JobArray) -> Result<u64> {
+        if self.arrays.iter().any(|a| a.array.id == array.id) {
+            return Ok(array.len());
+        }
+        array.validate()?;
+        self.hydrate_from_wire(std::slice::from_mut(&mut array.template))
+            .context("Unreadable structures")?;
+        let jobs = array.len();
+        self.arrays.push(OpenArray {
+            next: array.start,
+            array,
+            waiting: Vec::new(),
+        });
+        self.arrays_dirty = true;
+        Ok(jobs)
+    }
+
+    /// Members of job arrays not made yet.
+    pub fn array_backlog(&self) -> u64 {
+        self.arrays.iter().map(|a| a.array.end - a.next).sum()
+    }
+
+    fn apply_control(&mut self, cmd: ControlCommand) -> Result<String> {
+        match cmd {
+            ControlCommand::Pause => {
+                self.paused = true;
+                self.store.set_meta(META_PAUSED, "true")?;
+                Ok("Scheduling paused (running jobs continue)".into())
+            }
+            ControlCommand::Resume => {
+                self.paused = false;
+                self.store.set_meta(META_PAUSED, "false")?;
+                Ok("Scheduling resumed".into())
+            }
+            ControlCommand::PauseWorkflow { workflow } => {
+                let changed = self.paused_workflows.insert(workflow.clone());
+                self.save_paused_workflows()?;
+                let running = self
+                    .nodes
+                    .values()
+                    .filter(|n| n.inflight && job_workflow(&n.job) == Some(workflow.as_str()))
+                    .count();
+                Ok(match changed {
+                    true => format!(
+                        "Workflow '{}' paused ({} running job(s) continue)",
+                        workflow, running
+                    ),
+                    false => format!("Workflow '{}' was already paused", workflow),
+                })
+            }
+            ControlCommand::ResumeWorkflow { workflow } => {
+                if !self.paused_workflows.remove(&workflow) {
+                    return Err(anyhow!("Workflow '{}' is not paused", workflow));
+                }
+                self.save_paused_workflows()?;
+                Ok(format!("Workflow '{}' resumed", workflow))
+            }
+            ControlCommand::SetExpandLimit { limit } => {
+                self.expand_limit = limit;
+                self.store.set_meta(META_EXPAND_LIMIT, &limit.to_string())?;
+                Ok(format!("Expansion limit set to {}", limit))
+            }
+            ControlCommand::Cancel { job_id } => self.cancel_job(job_id),
+            ControlCommand::AddEdge { parent, child } => self.add_edge(parent, child),
+            ControlCommand::RemoveEdge { parent, child } => self.remove_edge(parent, child),
+            ControlCommand::AddNode { job, parents } => self.add_node(*job, parents),
+            ControlCommand::CancelJobs { filter } => self.cancel_jobs(&filter),
+            ControlCommand::RetryJobs { filter } => self.retry_jobs(&filter),
+        }
+    }
+
+    fn save_paused_workflows(&self) -> Result<()> {
+        self.store.set_meta(
+            META_PAUSED_WORKFLOWS,
+            &serde_json::to_string(&self.paused_workflows)?,
+        )
+    }
+
+    // ------------------------------------------------------------------------
+    // Live DAG edits (operator fixes after deployment)
+    // ------------------------------------------------------------------------
+
+    /// Only jobs that have not started can gain or lose parents.
+    fn editable_child(&self, child: Uuid) -> Result<()> {
+        let node = self
+            .nodes
+            .get(&child)
+            .ok_or_else(|| anyhow!("Unknown job {}", child))?;
+        match &node.job.status {
+            JobStatus::Pending | JobStatus::Blocked if !node.inflight => Ok(()),
+            s => Err(anyhow!("Job {} is already {:?}", child, s)),
+        }
+    }
+
+    /// True if `ancestor` is reachable from `id` by following parent links.
+    fn has_ancestor(&self, id: Uuid, ancestor: Uuid) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(cur) = stack.pop() {
+            if cur == ancestor {
+                return true;
+            }
+            if seen.insert(cur) {
+                if let Some(n) = self.nodes.get(&cur) {
+                    stack.extend(n.job.parent_ids.iter().copied());
+                }
+            }
+        }
+        false
+    }
+
+    fn add_edge(&mut self, parent: Uuid, child: Uuid) -> Result<String> {
+        self.editable_child(child)?;
+        let p_status = self
+            .nodes
+            .get(&parent)
+            .map(|n| n.job.status.clone())
+            .ok_or_else(|| anyhow!("Unknown job {}", parent))?;
+        if matches!(p_status, JobStatus::Failed | JobStatus::Cancelled) {
+            return Err(anyhow!(
+                "Parent {} is {:?}; {} would never be released",
+                parent,
+                p_status,
+                child
+            ));
+        }
+        if self.nodes[&child].job.parent_ids.contains(&parent) {
+            return Err(anyhow!("{} already waits on {}", child, parent));
+        }
+        if self.has_ancestor(parent, child) {
+            return Err(anyhow!("{} -> {} would create a cycle", parent, child));
+        }
+
+        if let Some(n) = self.nodes.get_mut(&child) {
+            n.job.parent_ids.push(parent);
+        }
+        self.dirty_edges.add(parent, child, EdgeKind::Manual);
+        self.refresh_readiness(child);
+        Ok(format!(
+            "Edge {} -> {} added ({} is {:?})",
+            parent, child, child, self.nodes[&child].job.status
+        ))
+    }
+
+    fn remove_edge(&mut self, parent: Uuid, child: Uuid) -> Result<String> {
+        self.editable_child(child)?;
+        let node = self.nodes.get_mut(&child).expect("checked above");
+        let before = node.job.parent_ids.len();
+        node.job.parent_ids.retain(|p| *p != parent);
+        if node.job.parent_ids.len() == before {
+            return Err(anyhow!("{} does not wait on {}", child, parent));
+        }
+        self.dirty_edges.remove(parent, child);
+        self.refresh_readiness(child);
+        Ok(format!(
+            "Edge {} -> {} removed ({} is {:?})",
+            parent, child, child, self.nodes[&child].job.status
+        ))
+    }
+
+    fn add_node(&mut self, mut job: Job, parents: Vec<Uuid>) -> Result<String> {
+        if self.nodes.contains_key(&job.id) {
+            return Err(anyhow!("Job {} already exists", job.id));
+        }
+        for p in &parents {
+            if !self.nodes.contains_key(p) {
+                return Err(anyhow!("Unknown parent {}", p));
+            }
+        }
+        let id = job.id;
+        job.status = JobStatus::Pending;
+        job.parent_ids.clear();
+        job.updated_at = chrono::Utc::now();
+        self.ingest_submission(
+            JobSubmit {
+                jobs: vec![job],
+                deps: parents.into_iter().map(|p| (p, id)).collect(),
+                routing: Routing::default(),
+                txn: None,
+            },
+            EdgeKind::Manual,
+        );
+        Ok(format!(
+            "Job {} added ({:?})",
+            id, self.nodes[&id].job.status
+        ))
+    }
+
+    /// Recounts a job's parents after its edges changed and moves it between
+    /// Blocked and the ready queues accordingly.
+    fn refresh_readiness(&mut self, id: Uuid) {
+        let Some(parent_ids) = self.nodes.get(&id).map(|n| n.job.parent_ids.clone()) else {
+            return;
+        };
+        let done = parent_ids
+            .iter()
+            .filter(|p| {
+                self.nodes
+                    .get(p)
+                    .is_some_and(|n| n.job.status == JobStatus::Completed)
+            })
+            .count();
+
+        let Some(node) = self.nodes.get_mut(&id) else {
+            return;
+        };
+        node.parents_total = parent_ids.len();
+        node.parents_done = done;
+        node.job.updated_at = chrono::Utc::now();
+        self.dirty_jobs.insert(id);
+        if node.job.error_log.as_deref() == Some("Pruned by Logic Condition") {
+            return;
+        }
+
+        if node.parents_total > node.parents_done {
+            node.blocked = true;
+            node.job.status = JobStatus::Blocked;
+            node.enqueued = false;
+            self.ready_queue.remove(id);
+            self.idle_queue.remove(id);
+        } else {
+            node.blocked = false;
+            node.job.status = JobStatus::Pending;
+            if node.is_state_runnable() {
+                node.enqueued = true;
+                if node.job.opportunistic {
+                    self.idle_queue.push(&node.job);
+                } else {
+                    self.ready_queue.push(&node.job);
+                }
+            }
+        }
+    }
+
+    fn cancel_job(&mut self, job_id: Uuid) -> Result<String> {
+        let node = self
+            .nodes
+            .get_mut(&job_id)
+            .ok_or_else(|| anyhow!("Unknown job {}", job_id))?;
+
+        if matches!(
+            node.job.status,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        ) {
+            return Err(anyhow!("Job {} is already {:?}", job_id, node.job.status));
+        }
+
+        node.job.status = JobStatus::Cancelled;
+        node.job.error_log = Some("Cancelled by operator".into());
+        node.job.updated_at = chrono::Utc::now();
+        node.enqueued = false;
+        let running_on = node.assigned_to.take().filter(|_| node.inflight);
+        let copy = node.speculative.take();
+        node.inflight = false;
+
+        self.ready_queue.remove(job_id);
+        self.idle_queue.remove(job_id);
+        self.dirty_jobs.insert(job_id);
+        if let Some(dup) = copy {
+            self.release_slot(&dup, job_id);
+            self.cancels
+                .entry((dup, "Cancelled by operator"))
+                .or_default()
+                .push(job_id);
+        }
+
+        Ok(match running_on {
+            Some(wid) => {
+                // The worker kills it without reporting, so its slot is free now
+                self.release_slot(&wid, job_id);
+                self.cancels
+                    .entry((wid.clone(), "Cancelled by operator"))
+                    .or_default()
+                    .push(job_id);
+                format!("Job {} cancelled (stopping it on {})", job_id, wid)
+            }
+            None => format!("Job {} cancelled", job_id),
+        })
+    }
+
+    /// `cancel_job` on every unfinished job `filter` matches.
+    fn cancel_jobs(&mut self, filter: &JobFilter) -> Result<String> {
+        let selected: Vec<(Uuid, bool)> = self
+            .nodes
+            .values()
+            .filter(|n| {
+                !matches!(
+                    n.job.status,
+                    JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+                ) && filter.matches(&n.job)
+            })
+            .map(|n| (n.job.id, n.inflight))
+            .collect();
+        if selected.is_empty() {
+            return Err(anyhow!("No unfinished job matches"));
+        }
+        for (job_id, _) in &selected {
+            self.cancel_job(*job_id)?;
+        }
+        let running = selected.iter().filter(|(_, inflight)| *inflight).count();
+        Ok(format!(
+            "{} job(s) cancelled ({} stopping on workers)",
+            selected.len(),
+            running
+        ))
+    }
+
+    /// Puts every failed or cancelled job `filter` matches back to Pending
+    /// (or Blocked, behind unfinished parents) with its retry budget renewed.
+    /// Its earlier runs stay in `job_events`, and its last result until a new
+    /// run replaces it. Children cancelled as unsatisfiable because of it
+    /// wait on it again.
+    fn retry_jobs(&mut self, filter: &JobFilter) -> Result<String> {
+        let selected: Vec<Uuid> = self
+            .nodes
+            .values()
+            .filter(|n| {
+                matches!(n.job.status, JobStatus::Failed | JobStatus::Cancelled)
+                    && filter.matches(&n.job)
+            })
+            .map(|n| n.job.id)
+            .collect();
+        if selected.is_empty() {
+            return Err(anyhow!("No failed or cancelled job matches"));
+        }
+        for id in &selected {
+            self.reset_for_retry(*id);
+        }
+
+        // Revive descendants whose every parent can finish again
+        let mut revived = 0;
+        loop {
+            let ready: Vec<Uuid> = self
+                .nodes
+                .values()
+                .filter(|n| {
+                    n.job.status == JobStatus::Cancelled
+                        && n.job
+                            .error_log
+                            .as_deref()
+                            .is_some_and(|e| e.starts_with(DEADLOCK_REASON_PREFIX))
+                        && n.job.parent_ids.iter().all(|p| {
+                            self.nodes.get(p).is_some_and(|p| {
+                                !matches!(p.job.status, JobStatus::Failed | JobStatus::Cancelled)
+                            })
+                        })
+                })
+                .map(|n| n.job.id)
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+            revived += ready.len();
+            for id in ready {
+                self.reset_for_retry(id);
+            }
+        }
+
+        Ok(format!(
+            "{} job(s) queued again ({} dependent job(s) revived)",
+            selected.len(),
+            revived
+        ))
+    }
+
+    fn reset_for_retry(&mut self, id: Uuid) {
+        self.retrying.remove(&id);
+        self.ready_queue.remove(id);
+        self.idle_queue.remove(id);
+        if let Some(n) = self.nodes.get_mut(&id) {
+            n.job.error_log = None;
+            n.job.attempts = 0;
+            n.job.node_id = None;
+            n.inflight = false;
+            n.enqueued = false;
+            n.assigned_to = None;
+            n.retry_at = None;
+        }
+        self.refresh_readiness(id);
+    }
+
     fn update_worker_live(&mut self, req: WorkRequest) {
         let tags: HashSet<String> = req.tags.into_iter().collect();
         let entry = self
             .workers
             .entry(req.worker_id.clone())
             .or_insert_with(|| WorkerLive {
-                _last_seen: Instant::now(),
+                last_seen: Instant::now(),
                 available_cores: 0,
                 available_gpus: 0,
+                available_memory_mb: None,
                 inflight_jobs: 0,
                 wants_work: false,
                 tags: HashSet::new(),
+                idle_since: Some(Instant::now()),
+                opportunistic: HashSet::new(),
+                read_lag: None,
+                hostname: None,
+                total_cores: None,
+                total_gpus: None,
             });
 
-        entry._last_seen = Instant::now();
+        entry.last_seen = Instant::now();
         entry.available_cores = req.available_cores;
         entry.available_gpus = req.available_gpus;
+        entry.available_memory_mb = req.available_memory_mb;
         entry.wants_work = true;
         entry.tags = tags;
+        entry.read_lag = req.read_lag;
+        entry.hostname = req.hostname;
+        entry.total_cores = req.total_cores;
+        entry.total_gpus = req.total_gpus;
+    }
+
+    /// Keeps a snapshot from the worker running the job; one from an
+    /// attempt the Coordinator has moved on from is dropped. A Switch with
+    /// `EARLY_SWITCH_PARAM` set prunes its branch on the first snapshot
+    /// that fails its condition, without waiting for the job to finish.
+    fn apply_job_progress(&mut self, p: JobProgress) {
+        let Some(node) = self.nodes.get(&p.job_id) else {
+            return;
+        };
+        let sender = Some(p.worker_id.as_str());
+        if !node.inflight
+            || (node.assigned_to.as_deref() != sender && node.speculative.as_deref() != sender)
+        {
+            log::debug!(
+                "Dropping stale progress of {} from {}",
+                p.job_id,
+                p.worker_id
+            );
+            return;
+        }
+
+        let early = node.job.config.params.get(EARLY_SWITCH_PARAM) == Some(&Value::Bool(true));
+        if let Some(&wf_idx) = self.workflow.id_map.get(&p.job_id).filter(|_| early) {
+            let graph = &self.workflow.graph;
+            let undecided = graph
+                .neighbors_directed(wf_idx, Direction::Outgoing)
+                .any(|c| !graph[c].is_pruned);
+            if matches!(graph[wf_idx].node_type, NodeType::Switch { .. }) && undecided {
+                let val = serde_json::to_value(&p.snapshot).unwrap_or(Value::Null);
+                self.workflow.resolve_logic_branch(wf_idx, &val);
+                self.sync_pruning_to_scheduler();
+            }
+        }
+
+        self.progress.push((
+            p.job_id,
+            ProgressRecord {
+                ts_ms: chrono::Utc::now().timestamp_millis(),
+                worker_id: p.worker_id,
+                snapshot: p.snapshot,
+            },
+        ));
     }
 
     async fn apply_job_complete(&mut self, rep: JobCompleteReport) -> Result<()> {
         let job_id = rep.job_id;
 
+        // One copy of a speculated job failed; the other may still finish,
+        // and carries the job from here on
+        if rep.status != JobStatus::Completed {
+            let lost = self
+                .nodes
+                .get_mut(&job_id)
+                .filter(|n| n.inflight)
+                .and_then(|n| {
+                    let sender = Some(rep.worker_id.as_str());
+                    if n.speculative.as_deref() == sender {
+                        return n.speculative.take();
+                    }
+                    if n.assigned_to.as_deref() != sender {
+                        return None;
+                    }
+                    let dup = n.speculative.take()?;
+                    n.assigned_to = Some(dup.clone());
+                    n.job.node_id = Some(dup);
+                    Some(rep.worker_id.clone())
+                });
+            if let Some(wid) = lost {
+                log::info!(
+                    "🏇 The copy of {} on {} failed, waiting on the other: {}",
+                    job_id,
+                    wid,
+                    rep.error.as_deref().unwrap_or_default()
+                );
+                self.release_slot(&wid, job_id);
+                self.dirty_jobs.insert(job_id);
+                return Ok(());
+            }
+        }
+
         if let Some(node) = self.nodes.get_mut(&job_id) {
+            // Redelivered grants can run a job twice: the first report wins
+            if matches!(node.job.status, JobStatus::Completed | JobStatus::Failed) {
+                log::debug!("Ignoring duplicate report for {}", job_id);
+                return Ok(());
+            }
             node.inflight = false;
+
+            // Finished before the cancel reached the worker: drop the result
+            // (`cancel_job` already freed the slot)
+            if node.job.status == JobStatus::Cancelled {
+                return Ok(());
+            }
+
+            let error = rep.error.as_deref().unwrap_or_default();
+            let retry_in = match &node.job.config.retry {
+                Some(policy) if rep.status == JobStatus::Failed => {
+                    policy.retry_delay(node.job.attempts + 1, error)
+                }
+                _ => None,
+            };
+            if let Some(delay) = retry_in {
+                node.job.attempts += 1;
+                log::warn!(
+                    "🔁 Job {} failed (run {} of {}), retrying in {:?}: {}",
+                    job_id,
+                    node.job.attempts,
+                    node.job.config.retry.as_ref().map_or(0, |p| p.max_attempts),
+                    delay,
+                    error
+                );
+                node.job.status = JobStatus::Pending;
+                node.job.result = None;
+                node.job.error_log = rep.error;
+                node.job.updated_at = chrono::Utc::now();
+                node.assigned_to = None;
+                node.retry_at = Some(Instant::now() + delay);
+                let ran_on = node.job.node_id.take();
+                self.retrying.insert(job_id);
+                self.dirty_jobs.insert(job_id);
+                if let Some(wid) = ran_on {
+                    self.release_slot(&wid, job_id);
+                }
+                return Ok(());
+            }
+
             node.job.status = rep.status.clone();
             node.job.result = rep.result.clone();
             node.job.error_log = rep.error;
@@ -287,15 +2215,36 @@ impl MarketplaceCoordinator {
             self.dirty_jobs.insert(job_id);
 
             if rep.status == JobStatus::Completed {
-                let finger = Self::fingerprint_job(&node.job.config);
+                let finger = job_fingerprint(&node.job.config);
                 self.landscape_registry.insert(finger, job_id);
             }
 
-            if let Some(wid) = &node.job.node_id {
-                if let Some(w) = self.workers.get_mut(wid) {
-                    w.inflight_jobs = w.inflight_jobs.saturating_sub(1);
+            let copy = node.speculative.take();
+            let ran_on = node.job.node_id.clone();
+            if copy.as_deref() == Some(rep.worker_id.as_str()) {
+                node.job.node_id = copy.clone();
+            }
+            if let Some(wid) = &ran_on {
+                self.release_slot(wid, job_id);
+            }
+            // Only the losing copy is told to stop. A report that does not
+            // say where it ran stops both; the winner has nothing left to kill
+            if let Some(dup) = copy {
+                log::info!("🏇 {} finished first of two copies", job_id);
+                self.release_slot(&dup, job_id);
+                for wid in std::iter::once(dup).chain(ran_on) {
+                    if wid == rep.worker_id {
+                        continue;
+                    }
+                    self.cancels
+                        .entry((wid, SPECULATION_LOST))
+                        .or_default()
+                        .push(job_id);
                 }
             }
+            if rep.status == JobStatus::Completed {
+                self.record_runtime(job_id);
+            }
         } else {
             return Ok(());
         }
@@ -312,8 +2261,24 @@ impl MarketplaceCoordinator {
                         }
                     }
                     NodeType::Generator { .. } => {
-                        if let Some(res) = &rep.result {
+                        let converged = match self.loop_converged(job_id) {
+                            Ok(converged) => converged,
+                            Err(reason) => {
+                                self.fail_generator(job_id, reason);
+                                return Ok(());
+                            }
+                        };
+                        if let Some(res) = rep.result.as_ref().filter(|_| !converged) {
                             if let Some(next_gen) = &res.next_generation {
+                                let governed = self.workflow.govern_expansion(
+                                    wf_idx,
+                                    next_gen.len(),
+                                    self.expand_limit,
+                                );
+                                if let Err(reason) = governed {
+                                    self.fail_generator(job_id, reason);
+                                    return Ok(());
+                                }
                                 if let Err(e) = self
                                     .expand_generator_defensive(wf_idx, next_gen.clone())
                                     .await
@@ -328,9 +2293,21 @@ impl MarketplaceCoordinator {
             }
         }
 
+        // A failed parent never releases its children (see detect_deadlocks)
+        if rep.status != JobStatus::Completed {
+            return Ok(());
+        }
+
         let mut unblocked = Vec::new();
+        let mut gated_off = Vec::new();
         for (cid, cnode) in &mut self.nodes {
             if cnode.job.parent_ids.contains(&job_id) {
+                if let Some(pred) = cnode.job.conditions.get(&job_id) {
+                    if !pred.holds(rep.result.as_ref()) {
+                        gated_off.push((*cid, pred.to_string()));
+                        continue;
+                    }
+                }
                 cnode.parents_done += 1;
                 if cnode.parents_done >= cnode.parents_total {
                     if cnode.job.status == JobStatus::Blocked {
@@ -344,30 +2321,194 @@ impl MarketplaceCoordinator {
             }
         }
 
+        for (cid, pred) in gated_off {
+            log::info!("✂️ {} not met by {}: pruning {}", pred, job_id, cid);
+            self.prune_from(cid);
+        }
+
         for cid in unblocked {
+            self.attach_feedback(cid);
             self.dirty_jobs.insert(cid);
             if let Some(n) = self.nodes.get_mut(&cid) {
                 if n.is_state_runnable() {
                     n.enqueued = true;
-                    self.ready_queue.push_back(cid);
+                    if n.job.opportunistic {
+                        self.idle_queue.push(&n.job);
+                    } else {
+                        self.ready_queue.push(&n.job);
+                    }
                 }
             }
         }
         Ok(())
     }
 
+    /// Whether a generator's loop has converged: the feedback about the batch
+    /// before it passes its `until` test. Err is the reason the generator
+    /// fails with, for a test that does not parse.
+    fn loop_converged(&self, job_id: Uuid) -> std::result::Result<bool, String> {
+        let Some(params) = self.nodes.get(&job_id).map(|n| &n.job.config.params) else {
+            return Ok(false);
+        };
+        let Some(until) = params.get(UNTIL_PARAM) else {
+            return Ok(false);
+        };
+        let test: ResultPredicate = serde_json::from_value(until.clone())
+            .map_err(|e| format!("invalid '{}': {}", UNTIL_PARAM, e))?;
+        let converged = params
+            .get(FEEDBACK_PARAM)
+            .is_some_and(|fb| test.holds_for(fb));
+        if converged {
+            log::info!(
+                "🎯 Generator {}: '{}' holds for the last batch; the loop has converged",
+                job_id,
+                test
+            );
+        }
+        Ok(converged)
+    }
+
+    /// A generator its governor stopped, or whose `until` test does not
+    /// parse: Failed with the reason rather than Completed, so nothing
+    /// downstream of it is released.
+    fn fail_generator(&mut self, job_id: Uuid, reason: String) {
+        log::warn!("🛑 Generator {}: {}", job_id, reason);
+        let Some(node) = self.nodes.get_mut(&job_id) else {
+            return;
+        };
+        node.job.status = JobStatus::Failed;
+        node.job.error_log = Some(reason);
+        node.job.updated_at = chrono::Utc::now();
+        self.dirty_jobs.insert(job_id);
+        let finger = job_fingerprint(&node.job.config);
+        if self.landscape_registry.get(&finger) == Some(&job_id) {
+            self.landscape_registry.remove(&finger);
+        }
+    }
+
+    /// Frees a grant slot; a worker left without normal work starts its idle clock.
+    fn release_slot(&mut self, wid: &str, job_id: Uuid) {
+        if let Some(w) = self.workers.get_mut(wid) {
+            w.inflight_jobs = w.inflight_jobs.saturating_sub(1);
+            w.opportunistic.remove(&job_id);
+            if w.inflight_jobs <= w.opportunistic.len() && w.idle_since.is_none() {
+                w.idle_since = Some(Instant::now());
+            }
+            if w.inflight_jobs == 0 && self.draining.contains(wid) {
+                log::info!("🚧 {} is drained and can be taken down", wid);
+            }
+        }
+    }
+
+    /// Starts or ends draining a worker. A worker not seen yet is drained
+    /// from its first work request on.
+    fn apply_drain(&mut self, d: WorkerDrain) -> Result<String> {
+        let changed = if d.resume {
+            self.draining.remove(&d.worker_id)
+        } else {
+            self.draining.insert(d.worker_id.clone())
+        };
+        if changed {
+            self.store
+                .set_meta(META_DRAINING, &serde_json::to_string(&self.draining)?)?;
+        }
+        let running = self.workers.get(&d.worker_id).map(|w| w.inflight_jobs);
+        Ok(match (d.resume, running) {
+            (true, _) if !changed => format!("{} was not draining", d.worker_id),
+            (true, _) => format!("{} takes new work again", d.worker_id),
+            (false, Some(0)) => format!("{} is drained and can be taken down", d.worker_id),
+            (false, Some(n)) => format!(
+                "Draining {}: no new work, {} job(s) still running",
+                d.worker_id, n
+            ),
+            (false, None) => format!("Draining {}: not seen yet, it gets no work", d.worker_id),
+        })
+    }
+
+    /// Every job the Coordinator tracks, in no particular order.
+    pub fn jobs(&self) -> impl Iterator<Item = &Job> + '_ {
+        self.nodes.values().map(|n| &n.job)
+    }
+
+    /// Memoization counters since the campaign started (restarts included).
+    pub fn memo_stats(&self) -> MemoStats {
+        MemoStats {
+            entries: self.landscape_registry.len(),
+            ..self.memo.clone()
+        }
+    }
+
+    /// Outcome of the jobs `generator_id` spawned (its "generation"), or None
+    /// if it has not expanded yet.
+    pub fn generation_feedback(&self, generator_id: Uuid) -> Option<GenerationFeedback> {
+        let params = &self.nodes.get(&generator_id)?.job.config.params;
+        let generation = params
+            .get("gen_counter")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        let kt = params
+            .get(KT_PARAM)
+            .and_then(Value::as_f64)
+            .unwrap_or(DEFAULT_KT_EV);
+        let children: Vec<&Job> = self
+            .nodes
+            .values()
+            .map(|n| &n.job)
+            .filter(|j| feedback::generated_by(j) == Some(generator_id))
+            .collect();
+        if children.is_empty() {
+            return None;
+        }
+        Some(feedback::summarize(generator_id, generation, &children, kt))
+    }
+
+    /// A generator whose physics batch just finished gets the batch's
+    /// outcome in `params.feedback` before it is dispatched.
+    fn attach_feedback(&mut self, job_id: Uuid) {
+        let is_generator = self.workflow.id_map.get(&job_id).is_some_and(|&idx| {
+            matches!(
+                self.workflow.graph[idx].node_type,
+                NodeType::Generator { .. }
+            )
+        });
+        let Some(node) = self.nodes.get(&job_id).filter(|_| is_generator) else {
+            return;
+        };
+        let previous = node
+            .job
+            .parent_ids
+            .iter()
+            .filter_map(|p| self.nodes.get(p))
+            .find_map(|p| feedback::generated_by(&p.job));
+        let Some(fb) = previous.and_then(|g| self.generation_feedback(g)) else {
+            return;
+        };
+
+        log::info!(
+            "🪞 Feedback for generation {}: {}/{} completed, best {}",
+            fb.generation + 1,
+            fb.completed,
+            fb.candidates,
+            fb.energy
+                .as_ref()
+                .map_or("-".to_string(), |e| format!("{:.4} eV", e.min))
+        );
+        let value = serde_json::to_value(&fb).unwrap_or(Value::Null);
+        if let Some(obj) = self
+            .nodes
+            .get_mut(&job_id)
+            .and_then(|n| n.job.config.params.as_object_mut())
+        {
+            obj.insert(FEEDBACK_PARAM.to_string(), value);
+        }
+    }
+
     async fn expand_generator_defensive(
         &mut self,
         gen_idx: NodeIndex,
         payload: Vec<Value>,
     ) -> Result<()> {
-        log::info!("🧠 Evaluating Generator Output...");
-
-        if payload.len() > 100 {
-            return Err(anyhow!(
-                "Expansion Governor: Request > 100 children. Rejected."
-            ));
-        }
+        log::info!("🧠 Evaluating Generator Output...");
 
         let gen_node = &self.workflow.graph[gen_idx];
 
@@ -393,10 +2534,21 @@ impl MarketplaceCoordinator {
             .and_then(|v: &Value| v.as_u64())
             .unwrap_or(0);
 
+        let descendants = params
+            .get(DESCENDANTS_PARAM)
+            .and_then(|v: &Value| v.as_u64())
+            .unwrap_or(0);
+
         let next_agent_config = if gen_counter < gen_limit {
             let mut new_config = gen_node.job.config.clone();
             if let Some(obj) = new_config.params.as_object_mut() {
                 obj.insert("gen_counter".to_string(), json!(gen_counter + 1));
+                obj.insert(
+                    DESCENDANTS_PARAM.to_string(),
+                    json!(descendants + payload.len() as u64),
+                );
+                // Filled in fresh once this generation's batch has run
+                obj.remove(FEEDBACK_PARAM);
             }
             Some(new_config)
         } else {
@@ -423,21 +2575,68 @@ impl MarketplaceCoordinator {
                     "node_type".into(),
                     serde_json::to_value(&wf_node.node_type).unwrap(),
                 );
+                job.flow_context
+                    .insert(FLOW_PRIORITY.into(), json!(wf_node.priority));
+                // Expansions belong to the workflow, count against the
+                // project, and are due by the deadline of the job that
+                // spawned them
+                for key in [FLOW_PROJECT, FLOW_DEADLINE, FLOW_WORKFLOW] {
+                    if job.flow_context.contains_key(key) {
+                        continue;
+                    }
+                    let inherited = self
+                        .workflow
+                        .graph
+                        .neighbors_directed(idx, Direction::Incoming)
+                        .filter_map(|p| self.nodes.get(&self.workflow.graph[p].job.id))
+                        .find_map(|p| p.job.flow_context.get(key).cloned());
+                    if let Some(value) = inherited {
+                        job.flow_context.insert(key.into(), value);
+                    }
+                }
 
-                if matches!(wf_node.node_type, NodeType::Compute) {
-                    let fp = Self::fingerprint_job(&job.config);
+                // Params still to come from parents are not in the fingerprint
+                if matches!(wf_node.node_type, NodeType::Compute) && job.dataflow.is_empty() {
+                    let fp = job_fingerprint(&job.config);
+                    let mut hit = false;
+                    let mut cached = None;
                     if let Some(&existing_id) = self.landscape_registry.get(&fp) {
-                        if let Some(existing_node) = self.nodes.get(&existing_id) {
-                            if let Some(res) = &existing_node.job.result {
-                                log::info!("♻️ Memoization Hit! {}", job.id);
-                                job.status = JobStatus::Completed;
-                                job.result = Some(res.clone());
-                                job.flow_context
-                                    .insert("memoized_from".into(), json!(existing_id));
-                                cache_hits += 1;
-                            }
+                        cached = match self.nodes.get(&existing_id) {
+                            Some(existing_node) => existing_node.job.result.clone(),
+                            None => self.store.get_memo_result(&fp).unwrap_or_else(|e| {
+                                log::warn!("Memo lookup for {} failed: {}", existing_id, e);
+                                None
+                            }),
+                        }
+                        .map(|res| (existing_id, res));
+                    }
+                    if let (None, Some(cache)) = (&cached, &self.memo_cache) {
+                        cached = cache.get(&fp).unwrap_or_else(|e| {
+                            log::warn!("Memo cache {:?} lookup failed: {}", cache.path(), e);
+                            None
+                        });
+                        if cached.is_some() {
+                            log::info!("♻️ Memo cache hit from an earlier campaign: {}", job.id);
                         }
                     }
+                    if let Some((source, res)) = cached {
+                        log::info!("♻️ Memoization Hit! {}", job.id);
+                        job.status = JobStatus::Completed;
+                        job.result = Some(res);
+                        job.flow_context
+                            .insert("memoized_from".into(), json!(source));
+                        cache_hits += 1;
+                        hit = true;
+                    }
+                    let counts = self
+                        .memo
+                        .by_engine
+                        .entry(job.config.engine.code())
+                        .or_default();
+                    for c in [counts, &mut self.memo.total] {
+                        c.lookups += 1;
+                        c.hits += hit as u64;
+                    }
                 }
 
                 let parents: Vec<Uuid> = self
@@ -461,18 +2660,59 @@ impl MarketplaceCoordinator {
                 new_jobs.len(),
                 cache_hits
             );
+            self.memo.entries = self.landscape_registry.len();
+            self.store
+                .set_meta(META_MEMO, &serde_json::to_string(&self.memo)?)?;
             let submit = JobSubmit {
                 jobs: new_jobs,
                 deps: new_deps,
+                routing: Routing::default(),
+                txn: None,
             };
-            self.transport
-                .broadcast(EV_JOB_SUBMIT, serde_json::to_value(&submit)?)
-                .await?;
-            self.ingest_submission(submit);
+            let mut wire_submit = submit.clone();
+            self.offload_for_wire(&mut wire_submit.jobs)?;
+            self.queue_broadcast(EV_JOB_SUBMIT, serde_json::to_value(&wire_submit)?);
+            self.ingest_submission(submit, EdgeKind::Expansion);
         }
         Ok(())
     }
 
+    /// Prunes `root` and every unfinished job downstream of it, as a Switch
+    /// does for its branch.
+    fn prune_from(&mut self, root: Uuid) {
+        let mut children: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for n in self.nodes.values() {
+            for pid in &n.job.parent_ids {
+                children.entry(*pid).or_default().push(n.job.id);
+            }
+        }
+        let mut stack = vec![root];
+        let mut seen = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Some(n) = self.nodes.get_mut(&id) else {
+                continue;
+            };
+            if !matches!(n.job.status, JobStatus::Pending | JobStatus::Blocked) || n.inflight {
+                continue;
+            }
+            n.blocked = false;
+            n.enqueued = false;
+            n.job.status = JobStatus::Failed;
+            n.job.error_log = Some("Pruned by Logic Condition".into());
+            n.job.updated_at = chrono::Utc::now();
+            self.ready_queue.remove(id);
+            self.idle_queue.remove(id);
+            self.dirty_jobs.insert(id);
+            if let Some(&idx) = self.workflow.id_map.get(&id) {
+                self.workflow.graph[idx].is_pruned = true;
+            }
+            stack.extend(children.remove(&id).unwrap_or_default());
+        }
+    }
+
     fn sync_pruning_to_scheduler(&mut self) {
         for idx in self.workflow.graph.node_indices() {
             let wf_node = &self.workflow.graph[idx];
@@ -490,101 +2730,782 @@ impl MarketplaceCoordinator {
     }
 
     async fn schedule_work(&mut self) -> Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+        self.expand_arrays();
+        if !self.quotas.is_empty() {
+            self.quotas.reset();
+            for n in self.nodes.values().filter(|n| n.inflight) {
+                self.quotas.charge(&n.job);
+            }
+        }
+
         let worker_ids: Vec<String> = self.workers.keys().cloned().collect();
 
         for wid in worker_ids {
-            let (mut cap_cores, mut cap_gpus, worker_tags) = {
+            let (room, idle_for) = {
                 let w = self.workers.get(&wid).unwrap();
-                if !w.wants_work || w.inflight_jobs >= 64 {
+                if !w.wants_work || w.inflight_jobs >= 64 || self.draining.contains(&wid) {
                     continue;
                 }
-                (w.available_cores, w.available_gpus, w.tags.clone())
+                (w.room(), w.idle_since.map(|t| t.elapsed()))
             };
 
-            let mut grant_batch = Vec::new();
-            let mut rotated = 0;
-            let q_len = self.ready_queue.len();
+            // Normal work first; the idle queue only feeds workers idle long enough
+            let mut grant_batch = self.take_from_queue(false, &wid, room);
+            let opportunistic =
+                grant_batch.is_empty() && idle_for.is_some_and(|d| d >= self.opportunistic_idle);
+            if opportunistic {
+                grant_batch = self.take_from_queue(true, &wid, room);
+            }
 
-            while rotated < q_len && cap_cores > 0 {
-                if let Some(jid) = self.ready_queue.pop_front() {
-                    if let Some(node) = self.nodes.get_mut(&jid) {
-                        node.enqueued = false;
+            if !grant_batch.is_empty() {
+                if let Some(w) = self.workers.get_mut(&wid) {
+                    w.inflight_jobs += grant_batch.len();
+                    w.wants_work = false;
+                    if opportunistic {
+                        w.opportunistic.extend(grant_batch.iter().map(|j| j.id));
+                    } else {
+                        w.idle_since = None;
                     }
+                }
+                self.send_grant(&wid, grant_batch).await?;
+            }
+        }
 
-                    let (runnable, tag_match, req_cores, req_gpus) =
-                        if let Some(node) = self.nodes.get(&jid) {
-                            let is_valid = node.is_runnable_logic_only();
-                            if !is_valid {
-                                (false, false, 0, 0)
-                            } else {
-                                let req_tags = &node.job.resources.required_tags;
-                                let matches = req_tags.iter().all(|t| worker_tags.contains(t));
-                                (
-                                    true,
-                                    matches,
-                                    node.job.resources.cores,
-                                    node.job.resources.gpus,
-                                )
-                            }
-                        } else {
-                            (false, false, 0, 0)
-                        };
+        self.speculate().await?;
+        self.preempt_for_waiting().await
+    }
+
+    /// Sends `jobs` to `wid`, to be acknowledged within `grant_ack_timeout`.
+    async fn send_grant(&mut self, wid: &str, mut jobs: Vec<Job>) -> Result<()> {
+        self.offload_for_wire(&mut jobs)?;
+        let grant = WorkGrant {
+            worker_id: wid.to_string(),
+            grant_id: format!("g_{}", Uuid::new_v4()),
+            jobs,
+            ttl_ms: Some(self.grant_ack_timeout.as_millis() as u64),
+        };
+        self.pending_grants.insert(
+            grant.grant_id.clone(),
+            PendingGrant {
+                worker_id: wid.to_string(),
+                job_ids: grant.jobs.iter().map(|j| j.id).collect(),
+                sent: Instant::now(),
+            },
+        );
+        self.transport
+            .send_to_worker(wid, EV_WORK_GRANT, serde_json::to_value(&grant)?)
+            .await?;
+        Ok(())
+    }
 
-                    let fits = req_cores <= cap_cores && req_gpus <= cap_gpus;
+    /// Remembers how long a completed job took from grant to report, for
+    /// the medians `speculate` compares against.
+    fn record_runtime(&mut self, job_id: Uuid) {
+        if self.speculation.is_none() {
+            return;
+        }
+        let (Some(since), Some(node)) = (self.dispatched.get(&job_id), self.nodes.get(&job_id))
+        else {
+            return;
+        };
+        let took = since.elapsed();
+        let runs = self
+            .runtimes
+            .entry(node.job.config.engine.code())
+            .or_default();
+        if runs.len() >= SPECULATION_WINDOW {
+            runs.pop_front();
+        }
+        runs.push_back(took);
+    }
 
-                    let mut pushed_back = false;
-                    if runnable && tag_match && fits {
-                        if let Some(node) = self.nodes.get_mut(&jid) {
-                            node.inflight = true;
-                            node.assigned_to = Some(wid.clone());
-                            node.job.node_id = Some(wid.clone());
-                            node.job.status = JobStatus::Running;
+    /// Gives each straggler (see `with_speculation`) a duplicate on a worker
+    /// still asking for work once the queues were served, so speculation only
+    /// takes room nothing else wanted. Longest-running first.
+    async fn speculate(&mut self) -> Result<()> {
+        let Some(factor) = self.speculation else {
+            return Ok(());
+        };
+        let medians: HashMap<&str, Duration> = self
+            .runtimes
+            .iter()
+            .filter(|(_, runs)| runs.len() >= SPECULATION_MIN_SAMPLES)
+            .map(|(engine, runs)| {
+                let mut sorted: Vec<Duration> = runs.iter().copied().collect();
+                sorted.sort();
+                (engine.as_str(), sorted[sorted.len() / 2])
+            })
+            .collect();
+        if medians.is_empty() {
+            return Ok(());
+        }
 
-                            self.dirty_jobs.insert(jid);
-                            grant_batch.push(node.job.clone());
+        let mut stragglers: Vec<(Uuid, Duration, Duration)> = self
+            .nodes
+            .iter()
+            .filter(|(_, n)| n.inflight && n.speculative.is_none() && !n.job.opportunistic)
+            .filter_map(|(id, n)| {
+                let median = *medians.get(n.job.config.engine.code().as_str())?;
+                let ran = self.dispatched.get(id)?.elapsed();
+                (ran > median.mul_f64(factor)).then_some((*id, ran, median))
+            })
+            .collect();
+        stragglers.sort_by_key(|(_, ran, _)| Reverse(*ran));
 
-                            cap_cores -= req_cores;
-                            cap_gpus -= req_gpus;
-                        }
+        for (jid, ran, median) in stragglers {
+            let Some(node) = self.nodes.get(&jid) else {
+                continue;
+            };
+            let req = &node.job.resources;
+            let Some(wid) = self
+                .workers
+                .iter()
+                .filter(|(wid, w)| {
+                    w.wants_work
+                        && w.inflight_jobs < 64
+                        && node.assigned_to.as_ref() != Some(*wid)
+                        && w.room().fits(req)
+                        && self.can_place(req, wid)
+                })
+                .map(|(wid, _)| wid.clone())
+                .min()
+            else {
+                continue;
+            };
+            let job = node.job.clone();
+            log::info!(
+                "🏇 Job {} has run {:?} (median {:?}): duplicating it on {}",
+                jid,
+                ran,
+                median,
+                wid
+            );
+            if let Some(n) = self.nodes.get_mut(&jid) {
+                n.speculative = Some(wid.clone());
+            }
+            if let Some(w) = self.workers.get_mut(&wid) {
+                w.inflight_jobs += 1;
+                w.wants_work = false;
+                w.idle_since = None;
+            }
+            self.send_grant(&wid, vec![job]).await?;
+        }
+        Ok(())
+    }
+
+    /// Makes job array members until each array has one waiting for every
+    /// job's worth of room on the workers asking for work.
+    fn expand_arrays(&mut self) {
+        if self.arrays.is_empty() {
+            return;
+        }
+        let room: Vec<usize> = self
+            .arrays
+            .iter()
+            .map(|a| {
+                let req = &a.array.template.resources;
+                self.workers
+                    .iter()
+                    .filter(|(wid, w)| w.wants_work && self.can_place(req, wid))
+                    .map(|(_, w)| w.room().copies_of(req))
+                    .sum()
+            })
+            .collect();
+
+        let mut made = Vec::new();
+        for (open, room) in self.arrays.iter_mut().zip(room) {
+            let nodes = &self.nodes;
+            open.waiting
+                .retain(|id| nodes.get(id).is_some_and(|n| n.is_runnable_logic_only()));
+            while open.waiting.len() < room && open.next < open.array.end {
+                let job = open.array.member(open.next);
+                open.next += 1;
+                self.arrays_dirty = true;
+                if !nodes.contains_key(&job.id) {
+                    open.waiting.push(job.id);
+                    made.push(job);
+                }
+            }
+        }
+        self.arrays.retain(|a| a.next < a.array.end);
+        if made.is_empty() {
+            return;
+        }
+        log::debug!("🧮 Made {} job array member(s)", made.len());
+        let sub = JobSubmit {
+            jobs: made,
+            deps: vec![],
+            routing: Routing::default(),
+            txn: None,
+        };
+        self.ingest_submission(sub, EdgeKind::Submitted);
+    }
+
+    /// Grants nobody acknowledged in time: the worker likely died on receipt.
+    /// Their jobs go back to the queue and the silent worker is forgotten until
+    /// its next work request, so another worker picks them up.
+    fn redeliver_unacked(&mut self) {
+        let expired: Vec<String> = self
+            .pending_grants
+            .iter()
+            .filter(|(_, g)| g.sent.elapsed() >= self.grant_ack_timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for grant_id in expired {
+            let Some(grant) = self.pending_grants.remove(&grant_id) else {
+                continue;
+            };
+            let requeued = grant
+                .job_ids
+                .iter()
+                .filter(|id| self.requeue_from(id, &grant.worker_id))
+                .count();
+            self.workers.remove(&grant.worker_id);
+            log::warn!(
+                "📭 Grant {} not acknowledged by {}: {} job(s) re-queued",
+                grant_id,
+                grant.worker_id,
+                requeued
+            );
+        }
+    }
+
+    /// Workers silent for `worker_timeout` are taken for dead (node crash,
+    /// lost network). Everything still in flight there goes back to its
+    /// queue; a late report from the worker is still accepted if it comes
+    /// first (see `apply_job_complete`).
+    fn reap_dead_workers(&mut self) {
+        let dead: Vec<String> = self
+            .workers
+            .iter()
+            .filter(|(_, w)| w.last_seen.elapsed() >= self.worker_timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for wid in dead {
+            self.workers.remove(&wid);
+            self.pending_grants.retain(|_, g| g.worker_id != wid);
+            let held: Vec<Uuid> = self
+                .nodes
+                .iter()
+                .filter(|(_, n)| {
+                    n.inflight
+                        && (n.assigned_to.as_deref() == Some(wid.as_str())
+                            || n.speculative.as_deref() == Some(wid.as_str()))
+                })
+                .map(|(id, _)| *id)
+                .collect();
+            let requeued = held.iter().filter(|id| self.requeue_from(id, &wid)).count();
+            log::warn!(
+                "💀 {} silent for over {:?}, presumed dead: {} job(s) re-queued",
+                wid,
+                self.worker_timeout,
+                requeued
+            );
+        }
+    }
+
+    /// Jobs running longer than `time_limit_min` plus the grace period are
+    /// failed as if their worker had reported it (echo included, and a retry
+    /// policy still applies), and killed on the worker. A limit of 0 means none.
+    async fn fail_overdue(&mut self) -> Result<()> {
+        let mut overdue = Vec::new();
+        let nodes = &self.nodes;
+        let grace = self.timeout_grace;
+        self.dispatched.retain(|id, since| {
+            let Some(n) = nodes.get(id).filter(|n| n.inflight) else {
+                return false;
+            };
+            let limit = n.job.resources.time_limit_min as u64;
+            if limit == 0 || since.elapsed() <= Duration::from_secs(limit * 60) + grace {
+                return true;
+            }
+            if let Some(wid) = &n.assigned_to {
+                overdue.push((*id, wid.clone(), limit));
+            }
+            false
+        });
+
+        for (job_id, wid, limit) in overdue {
+            let error = format!(
+                "{}still running on {} after time_limit_min = {} (+{}s grace)",
+                TIMEOUT_REASON_PREFIX,
+                wid,
+                limit,
+                grace.as_secs()
+            );
+            log::warn!("⏰ Job {} {}", job_id, error);
+            // Its duplicate goes too, or the report below would wait on it
+            let copy = self
+                .nodes
+                .get_mut(&job_id)
+                .and_then(|n| n.speculative.take());
+            if let Some(dup) = copy {
+                self.release_slot(&dup, job_id);
+                self.cancels
+                    .entry((dup, "Wall time exceeded"))
+                    .or_default()
+                    .push(job_id);
+            }
+            self.cancels
+                .entry((wid.clone(), "Wall time exceeded"))
+                .or_default()
+                .push(job_id);
+            let rep = JobCompleteReport {
+                job_id,
+                worker_id: wid,
+                status: JobStatus::Failed,
+                result: None,
+                error: Some(error),
+            };
+            self.queue_broadcast(EV_JOB_COMPLETE, serde_json::to_value(&rep)?);
+            self.apply_job_complete(rep).await?;
+        }
+        Ok(())
+    }
+
+    /// Re-queues failed jobs whose retry backoff is over.
+    fn release_retries(&mut self) {
+        if self.retrying.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        let due: Vec<Uuid> = self
+            .retrying
+            .iter()
+            .copied()
+            .filter(|id| {
+                self.nodes
+                    .get(id)
+                    .and_then(|n| n.retry_at)
+                    .map_or(true, |t| t <= now)
+            })
+            .collect();
+        for id in due {
+            self.retrying.remove(&id);
+            let Some(n) = self.nodes.get_mut(&id) else {
+                continue;
+            };
+            n.retry_at = None;
+            if n.is_state_runnable() {
+                n.enqueued = true;
+                if n.job.opportunistic {
+                    self.idle_queue.push(&n.job);
+                } else {
+                    self.ready_queue.push(&n.job);
+                }
+            }
+        }
+    }
+
+    /// Puts a job back in its queue if it is in flight on `wid`. False if it
+    /// is not (already reported, or handed to someone else since), or if a
+    /// speculative copy elsewhere carries on in its place.
+    fn requeue_from(&mut self, id: &Uuid, wid: &str) -> bool {
+        let Some(n) = self.nodes.get_mut(id) else {
+            return false;
+        };
+        if n.speculative.as_deref() == Some(wid) {
+            n.speculative = None;
+            return false;
+        }
+        if !n.inflight || n.assigned_to.as_deref() != Some(wid) {
+            return false;
+        }
+        if let Some(dup) = n.speculative.take() {
+            n.assigned_to = Some(dup.clone());
+            n.job.node_id = Some(dup);
+            self.dirty_jobs.insert(*id);
+            return false;
+        }
+        n.inflight = false;
+        n.assigned_to = None;
+        n.job.node_id = None;
+        n.job.status = JobStatus::Pending;
+        n.enqueued = true;
+        let job = n.job.clone();
+        self.queue_mut(job.opportunistic).push(&job);
+        self.dirty_jobs.insert(*id);
+        true
+    }
+
+    /// A retiring worker returned its unstarted jobs. They go back into their
+    /// queue (ahead of newer jobs of the same priority), so the `schedule_work`
+    /// of this same tick hands them to the first worker with room. Grants it
+    /// never acknowledged are taken back too.
+    fn apply_work_yield(&mut self, y: WorkYield) {
+        let mut job_ids: Vec<Uuid> = y.jobs.iter().map(|j| j.id).collect();
+        let unacked: Vec<String> = self
+            .pending_grants
+            .iter()
+            .filter(|(_, g)| g.worker_id == y.worker_id)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for grant_id in unacked {
+            if let Some(g) = self.pending_grants.remove(&grant_id) {
+                job_ids.extend(g.job_ids);
+            }
+        }
+
+        let mut requeued = 0;
+        for id in &job_ids {
+            if !self.nodes.contains_key(id) {
+                log::warn!("Yielded job {} is unknown here, dropping it", id);
+                continue;
+            }
+            if self.requeue_from(id, &y.worker_id) {
+                requeued += 1;
+            }
+        }
+
+        // Gone until it sends a fresh work request (e.g. restarted with the same id)
+        self.workers.remove(&y.worker_id);
+        log::info!(
+            "🧳 {} is retiring{}: {} job(s) handed back for redistribution",
+            y.worker_id,
+            if y.reason.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", y.reason)
+            },
+            requeued
+        );
+    }
+
+    fn queue_mut(&mut self, opportunistic: bool) -> &mut ReadyQueue {
+        if opportunistic {
+            &mut self.idle_queue
+        } else {
+            &mut self.ready_queue
+        }
+    }
+
+    /// Pops every job from one queue that fits the worker, highest priority
+    /// first, marking it in flight.
+    fn take_from_queue(&mut self, opportunistic: bool, wid: &str, mut room: Room) -> Vec<Job> {
+        let mut grant_batch = Vec::new();
+        let mut skipped = Vec::new();
+        let q_len = self.queue_mut(opportunistic).len();
+
+        while skipped.len() + grant_batch.len() < q_len && room.cores > 0 {
+            if let Some(entry) = self.queue_mut(opportunistic).pop() {
+                let jid = entry.id;
+                if let Some(node) = self.nodes.get_mut(&jid) {
+                    node.enqueued = false;
+                }
+
+                let (runnable, tag_match, req) = if let Some(node) = self.nodes.get(&jid) {
+                    let is_valid = node.is_runnable_logic_only();
+                    if !is_valid {
+                        (false, false, None)
                     } else {
-                        pushed_back = true;
+                        let req = &node.job.resources;
+                        let matches = self.can_place(req, wid)
+                            && !self.prefers_elsewhere(req, wid)
+                            && self.quotas.admits(&node.job)
+                            && !job_workflow(&node.job)
+                                .is_some_and(|w| self.paused_workflows.contains(w));
+                        (true, matches, Some(req.clone()))
                     }
+                } else {
+                    (false, false, None)
+                };
+
+                let fits = req.as_ref().is_some_and(|r| room.fits(r));
 
-                    if pushed_back {
-                        if let Some(node) = self.nodes.get_mut(&jid) {
-                            node.enqueued = true;
+                let mut pushed_back = true;
+                if runnable && tag_match && fits {
+                    self.feed_dataflow(jid);
+                    if let Some(node) = self.nodes.get_mut(&jid) {
+                        node.inflight = true;
+                        node.assigned_to = Some(wid.to_string());
+                        node.job.node_id = Some(wid.to_string());
+                        node.job.status = JobStatus::Running;
+                        self.dispatched.insert(jid, tokio::time::Instant::now());
+                        self.quotas.charge(&node.job);
+
+                        self.dirty_jobs.insert(jid);
+                        grant_batch.push(node.job.clone());
+
+                        if let Some(r) = &req {
+                            room.take(r);
                         }
-                        self.ready_queue.push_back(jid);
+                        pushed_back = false;
                     }
-                    rotated += 1;
-                } else {
-                    break;
                 }
+
+                if pushed_back {
+                    if let Some(node) = self.nodes.get_mut(&jid) {
+                        node.enqueued = true;
+                    }
+                    skipped.push(entry);
+                }
+            } else {
+                break;
             }
+        }
+        let queue = self.queue_mut(opportunistic);
+        for entry in skipped {
+            queue.requeue(entry);
+        }
+        grant_batch
+    }
 
-            if !grant_batch.is_empty() {
-                if let Some(w) = self.workers.get_mut(&wid) {
-                    w.inflight_jobs += grant_batch.len();
-                    w.wants_work = false;
+    /// Copies the fields its dataflow edges map out of each parent's result
+    /// into the job's params. Done at dispatch, so a parent finished by any
+    /// route (a report, the memo, before a restart) feeds its children.
+    fn feed_dataflow(&mut self, job_id: Uuid) {
+        let Some(node) = self.nodes.get(&job_id) else {
+            return;
+        };
+        let mut inputs = Vec::new();
+        for (pid, map) in &node.job.dataflow {
+            let result = self.nodes.get(pid).and_then(|p| p.job.result.as_ref());
+            for (param, field) in map {
+                match result.and_then(|r| result_field(r, field)) {
+                    Some(value) => inputs.push((param.clone(), value)),
+                    None => log::warn!(
+                        "🔌 {} has no '{}' to give {} as '{}'",
+                        pid,
+                        field,
+                        job_id,
+                        param
+                    ),
                 }
-                let grant = WorkGrant {
-                    worker_id: wid.clone(),
-                    grant_id: format!("g_{}", Uuid::new_v4()),
-                    jobs: grant_batch,
-                };
-                self.transport
-                    .broadcast(EV_WORK_GRANT, serde_json::to_value(&grant)?)
-                    .await?;
             }
         }
+        if inputs.is_empty() {
+            return;
+        }
+        let Some(node) = self.nodes.get_mut(&job_id) else {
+            return;
+        };
+        if !node.job.config.params.is_object() {
+            node.job.config.params = json!({});
+        }
+        if let Some(params) = node.job.config.params.as_object_mut() {
+            params.extend(inputs);
+        }
+    }
+
+    /// Hard constraints: the worker is not draining, its tags and, for
+    /// `same_node_as`, its host.
+    /// The placement of a job that never ran, or ran on a worker no longer
+    /// known, does not hold anything back.
+    fn can_place(&self, req: &ResourceReq, wid: &str) -> bool {
+        let Some(w) = self.workers.get(wid) else {
+            return false;
+        };
+        if self.draining.contains(wid) || !req.tags_allow(|t| w.tags.contains(t)) {
+            return false;
+        }
+        let Some(there) = req
+            .same_node_as
+            .and_then(|id| self.nodes.get(&id))
+            .and_then(|n| n.job.node_id.as_deref())
+        else {
+            return true;
+        };
+        if there == wid {
+            return true;
+        }
+        match self.workers.get(there) {
+            Some(t) => t.hostname.is_some() && t.hostname == w.hostname,
+            None => true,
+        }
+    }
+
+    /// Soft constraint: `wid` carries none of `prefer_tags` while another
+    /// worker that does has room for the job right now.
+    fn prefers_elsewhere(&self, req: &ResourceReq, wid: &str) -> bool {
+        let preferred = |w: &WorkerLive| req.prefer_tags.iter().any(|t| w.tags.contains(t));
+        if req.prefer_tags.is_empty() || self.workers.get(wid).map_or(true, preferred) {
+            return false;
+        }
+        self.workers.iter().any(|(other, w)| {
+            other != wid && preferred(w) && w.room().fits(req) && self.can_place(req, other)
+        })
+    }
+
+    /// Normal work that found no room evicts opportunistic jobs from a worker
+    /// where it would fit once they are gone.
+    async fn preempt_for_waiting(&mut self) -> Result<()> {
+        let waiting: Vec<Uuid> = self
+            .ready_queue
+            .ids()
+            .into_iter()
+            .filter(|id| {
+                self.nodes
+                    .get(id)
+                    .is_some_and(|n| n.is_runnable_logic_only() && self.quotas.admits(&n.job))
+            })
+            .collect();
+
+        for jid in waiting {
+            let req = &self.nodes[&jid].job.resources;
+
+            // Free room somewhere already: it just has to wait for a work request
+            if self
+                .workers
+                .iter()
+                .any(|(wid, w)| self.can_place(req, wid) && w.room().fits(req))
+            {
+                continue;
+            }
+
+            let victim = self
+                .workers
+                .iter()
+                .find(|(wid, w)| {
+                    if w.opportunistic.is_empty() || !self.can_place(req, wid) {
+                        return false;
+                    }
+                    let mut room = w.room();
+                    for n in w.opportunistic.iter().filter_map(|id| self.nodes.get(id)) {
+                        room.give_back(&n.job.resources);
+                    }
+                    room.fits(req)
+                })
+                .map(|(wid, _)| wid.clone());
+
+            if let Some(wid) = victim {
+                self.preempt_worker(&wid).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes back every opportunistic job on `wid`; they wait in the idle queue again.
+    async fn preempt_worker(&mut self, wid: &str) -> Result<()> {
+        let Some(w) = self.workers.get_mut(wid) else {
+            return Ok(());
+        };
+        let job_ids: Vec<Uuid> = w.opportunistic.drain().collect();
+        w.inflight_jobs = w.inflight_jobs.saturating_sub(job_ids.len());
+        w.idle_since = None;
+
+        for id in &job_ids {
+            if let Some(n) = self.nodes.get_mut(id) {
+                if n.inflight {
+                    n.inflight = false;
+                    n.assigned_to = None;
+                    n.job.node_id = None;
+                    n.job.status = JobStatus::Pending;
+                    n.enqueued = true;
+                    self.idle_queue.push(&n.job);
+                    self.dirty_jobs.insert(*id);
+                }
+            }
+        }
+
+        log::info!(
+            "⏏️ Preempting {} opportunistic job(s) on {}",
+            job_ids.len(),
+            wid
+        );
+        let msg = WorkPreempt {
+            worker_id: wid.to_string(),
+            job_ids,
+        };
+        self.transport
+            .send_to_worker(wid, EV_WORK_PREEMPT, serde_json::to_value(&msg)?)
+            .await?;
+        Ok(())
+    }
+
+    fn maybe_detect_deadlocks(&mut self) -> Result<()> {
+        if self.last_deadlock_scan.elapsed() < self.deadlock_scan {
+            return Ok(());
+        }
+        self.last_deadlock_scan = Instant::now();
+
+        let found = self.detect_deadlocks();
+        if found > 0 {
+            self.deadlocked_total += found as u64;
+            self.store
+                .set_meta(META_DEADLOCKED, &self.deadlocked_total.to_string())?;
+            log::warn!(
+                "🪦 Cancelled {} deadlocked job(s) ({} so far)",
+                found,
+                self.deadlocked_total
+            );
+        }
         Ok(())
     }
 
+    /// Cancels Blocked jobs with a Failed or Cancelled parent, repeating until
+    /// nothing changes so a whole dead subtree goes in one pass. The reason
+    /// chains back to the root cause: "Unsatisfiable: <parent> Cancelled <- <its parent> Failed: ...".
+    fn detect_deadlocks(&mut self) -> usize {
+        let mut found = 0;
+        loop {
+            let dead: Vec<(Uuid, String)> = self
+                .nodes
+                .values()
+                .filter(|n| n.job.status == JobStatus::Blocked)
+                .filter_map(|n| {
+                    n.job.parent_ids.iter().find_map(|pid| {
+                        let p = &self.nodes.get(pid)?.job;
+                        if !matches!(p.status, JobStatus::Failed | JobStatus::Cancelled) {
+                            return None;
+                        }
+                        let cause = match p.error_log.as_deref() {
+                            Some(e) => match e.strip_prefix(DEADLOCK_REASON_PREFIX) {
+                                Some(chain) => format!(" <- {}", chain),
+                                None => format!(": {}", e),
+                            },
+                            None => String::new(),
+                        };
+                        let short = &p.id.simple().to_string()[..8];
+                        Some((n.job.id, format!("{} {:?}{}", short, p.status, cause)))
+                    })
+                })
+                .collect();
+
+            if dead.is_empty() {
+                return found;
+            }
+            for (id, chain) in dead {
+                if let Some(n) = self.nodes.get_mut(&id) {
+                    n.blocked = false;
+                    n.job.status = JobStatus::Cancelled;
+                    n.job.error_log = Some(format!("{}{}", DEADLOCK_REASON_PREFIX, chain));
+                    n.job.updated_at = chrono::Utc::now();
+                    self.dirty_jobs.insert(id);
+                    found += 1;
+                }
+            }
+        }
+    }
+
     fn maybe_checkpoint(&mut self) -> Result<()> {
-        if self.last_ckpt.elapsed() < Duration::from_secs(5) || self.dirty_jobs.is_empty() {
+        if self
+            .last_heartbeat
+            .map_or(true, |t| t.elapsed() >= HEARTBEAT_EVERY)
+        {
+            let now_ms = chrono::Utc::now().timestamp_millis();
+            self.store.set_meta(META_HEARTBEAT, &now_ms.to_string())?;
+            if !self.quotas.is_empty() {
+                self.store
+                    .set_meta(META_QUOTAS, &serde_json::to_string(&self.quotas.status())?)?;
+            }
+            self.check_deadlines()?;
+            self.last_heartbeat = Some(Instant::now());
+        }
+        if self.last_ckpt.elapsed() < Duration::from_secs(5)
+            || (self.dirty_jobs.is_empty() && !self.arrays_dirty && self.progress.is_empty())
+        {
             return Ok(());
         }
+        self.checkpoint_now()
+    }
 
+    /// Writes dirty jobs and worker state to the store without waiting for the 5s pace.
+    pub fn checkpoint_now(&mut self) -> Result<()> {
         let mut refs = Vec::new();
         for id in &self.dirty_jobs {
             if let Some(n) = self.nodes.get(id) {
@@ -592,35 +3513,134 @@ impl MarketplaceCoordinator {
             }
         }
 
+        let now_ms = chrono::Utc::now().timestamp_millis();
         let w_snap: Vec<WorkerInfo> = self
             .workers
             .iter()
-            .map(|(id, w)| WorkerInfo {
-                worker_id: id.clone(),
-                cores: w.available_cores,
-                tasks: w.inflight_jobs,
-                last_seen_ms: 0,
+            .map(|(id, w)| {
+                let mut tags: Vec<String> = w.tags.iter().cloned().collect();
+                tags.sort();
+                WorkerInfo {
+                    worker_id: id.clone(),
+                    cores: w.available_cores,
+                    tasks: w.inflight_jobs,
+                    last_seen_ms: now_ms - w.last_seen.elapsed().as_millis() as i64,
+                    read_lag: w.read_lag,
+                    gpus: w.available_gpus,
+                    tags,
+                    hostname: w.hostname.clone(),
+                    draining: self.draining.contains(id),
+                    total_cores: w.total_cores,
+                    total_gpus: w.total_gpus,
+                }
             })
             .collect();
 
-        self.store.apply_batch(self.global_cursor, &refs, &w_snap)?;
+        let mut meta = Vec::new();
+        if self.arrays_dirty {
+            meta.push((META_ARRAYS, serde_json::to_string(&self.arrays)?));
+        }
+        self.store.apply_batch_with_edges(
+            self.global_cursor,
+            &refs,
+            &w_snap,
+            &self.dirty_edges,
+            &meta,
+        )?;
+        if !self.progress.is_empty() {
+            self.store.record_progress(&self.progress)?;
+            self.progress.clear();
+        }
+        // Best effort: a shared file other campaigns hold must not stall this one
+        if let Some(cache) = &self.memo_cache {
+            if let Err(e) = cache.record(&refs) {
+                log::warn!("Memo cache {:?} not updated: {}", cache.path(), e);
+            }
+        }
+        // Only once the cursor is stored: an id on record for a message that
+        // will be read again would make the replay drop it
+        if self.seen_events.dirty {
+            self.store.set_meta(
+                META_SEEN_EVENTS,
+                &serde_json::to_string(&self.seen_events.order)?,
+            )?;
+            self.seen_events.dirty = false;
+        }
         self.dirty_jobs.clear();
+        self.dirty_edges.clear();
+        self.arrays_dirty = false;
         self.last_ckpt = Instant::now();
         Ok(())
     }
 
+    /// Stamps every unfinished job with a deadline with its latest start:
+    /// the deadline less the `time_limit_min` of its longest chain of
+    /// unfinished jobs (see `crate::deadline`). Run before the queues are
+    /// rebuilt, as the stamp orders them.
+    fn plan_deadlines(&mut self) {
+        if !self
+            .nodes
+            .values()
+            .any(|n| n.job.flow_context.contains_key(FLOW_DEADLINE))
+        {
+            return;
+        }
+        let paths = deadline::critical_paths(self.nodes.values().map(|n| &n.job));
+        for (id, node) in &mut self.nodes {
+            let (Some(due), Some(path)) = (deadline::job_deadline(&node.job), paths.get(id)) else {
+                continue;
+            };
+            let start_by = due.timestamp_millis() - *path as i64 * 60_000;
+            if deadline::latest_start(&node.job) != Some(start_by) {
+                node.job
+                    .flow_context
+                    .insert(FLOW_LATEST_START.into(), json!(start_by));
+                self.dirty_jobs.insert(*id);
+            }
+        }
+    }
+
+    /// Writes `META_DEADLINES` and warns once about each deadline that can
+    /// no longer be met.
+    fn check_deadlines(&mut self) -> Result<()> {
+        let status = deadline::deadline_status(self.jobs(), chrono::Utc::now());
+        if status.is_empty() && !self.had_deadlines {
+            return Ok(());
+        }
+        for d in status.iter().filter(|d| d.is_late()) {
+            if self.late_deadlines.insert(d.deadline) {
+                log::warn!(
+                    "⏳ Deadline {} cannot be met: {} job(s) left, {} min behind",
+                    d.deadline.to_rfc3339(),
+                    d.jobs,
+                    -d.slack_s / 60
+                );
+            }
+        }
+        self.late_deadlines
+            .retain(|late| status.iter().any(|d| d.deadline == *late));
+        self.had_deadlines = !status.is_empty();
+        self.store
+            .set_meta(META_DEADLINES, &serde_json::to_string(&status)?)
+    }
+
     fn rebuild_ready_queue(&mut self) {
         self.ready_queue.clear();
-        for (id, node) in &mut self.nodes {
+        self.idle_queue.clear();
+        for node in self.nodes.values_mut() {
             node.enqueued = false;
             if node.is_state_runnable() {
-                self.ready_queue.push_back(*id);
+                if node.job.opportunistic {
+                    self.idle_queue.push(&node.job);
+                } else {
+                    self.ready_queue.push(&node.job);
+                }
                 node.enqueued = true;
             }
         }
     }
 
-    fn ingest_submission(&mut self, sub: JobSubmit) {
+    fn ingest_submission(&mut self, sub: JobSubmit, kind: EdgeKind) {
         for job in sub.jobs {
             let completed = job.status == JobStatus::Completed;
             self.nodes.insert(
@@ -633,11 +3653,13 @@ impl MarketplaceCoordinator {
                     inflight: false,
                     enqueued: false,
                     assigned_to: None,
+                    retry_at: None,
+                    speculative: None,
                 },
             );
             self.dirty_jobs.insert(job.id);
             if completed {
-                let finger = Self::fingerprint_job(&job.config);
+                let finger = job_fingerprint(&job.config);
                 self.landscape_registry.insert(finger, job.id);
             }
             if !self.workflow.id_map.contains_key(&job.id) {
@@ -646,9 +3668,10 @@ impl MarketplaceCoordinator {
                     .get("node_type")
                     .and_then(|v| serde_json::from_value(v.clone()).ok())
                     .unwrap_or(NodeType::Compute);
+                let priority = job_priority(&job);
                 let _ = self
                     .workflow
-                    .add_smart_node(job.clone(), n_type, vec![], 50, true);
+                    .add_smart_node(job.clone(), n_type, vec![], priority, true);
             }
         }
         for (pid, cid) in sub.deps {
@@ -657,12 +3680,22 @@ impl MarketplaceCoordinator {
                 if !child.job.parent_ids.contains(&pid) {
                     child.job.parent_ids.push(pid);
                 }
+                self.dirty_edges.add(pid, cid, kind);
+            }
+            // As `open` does, so a Switch can prune what was submitted below it
+            if let (Some(&p), Some(&c)) = (
+                self.workflow.id_map.get(&pid),
+                self.workflow.id_map.get(&cid),
+            ) {
+                if self.workflow.graph.find_edge(p, c).is_none() {
+                    self.workflow.graph.add_edge(p, c, EdgeType::HardDependency);
+                }
             }
         }
-        let completed_or_failed: HashSet<Uuid> = self
+        let completed: HashSet<Uuid> = self
             .nodes
             .values()
-            .filter(|n| matches!(n.job.status, JobStatus::Completed | JobStatus::Failed))
+            .filter(|n| n.job.status == JobStatus::Completed)
             .map(|n| n.job.id)
             .collect();
         for (_id, node) in &mut self.nodes {
@@ -671,7 +3704,7 @@ impl MarketplaceCoordinator {
                     .job
                     .parent_ids
                     .iter()
-                    .filter(|pid| completed_or_failed.contains(pid))
+                    .filter(|pid| completed.contains(pid))
                     .count();
                 if node.parents_total > node.parents_done {
                     node.blocked = true;
@@ -682,6 +3715,7 @@ impl MarketplaceCoordinator {
                 }
             }
         }
+        self.plan_deadlines();
         self.rebuild_ready_queue();
     }
 }
@@ -11,12 +11,23 @@
 // 2. Atomic Renames: Data effectively "appears" instantly, never partial.
 // 3. Model Notarization: Verifies ML model weights match expected hashes.
 // 4. Durability: Explicit fsyncs to handle HPC filesystem (Lustre) lag.
+// 5. Index: A content-type tag and a small preview per object (`index.db`),
+//    so tools can describe artifacts without opening the raw files.
+// 6. Work Dir Manifests: every file a job read or wrote, hashed and stored,
+//    so `unifiedlab repro` can rebuild the work dir later.
+// 7. Garbage Collection: storing an object that already exists refreshes its
+//    mtime, so `unifiedlab purge` only removes objects nothing stored lately.
 
+use crate::core::{FileRole, WorkFile};
 use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fs::{self, File};
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
 
 // ============================================================================
 // 1. HASHING UTILITIES
@@ -73,11 +84,26 @@ impl ArtifactStore {
     /// 2. Construct `final_path = root / hash[0..2] / hash.ext`.
     /// 3. Atomic Rename (or Copy+Delete if cross-device).
     /// 4. fsync directory for Lustre safety.
+    ///
+    /// The content type is guessed from the temp file's name.
     pub fn commit(
         &self,
         temp_file: impl AsRef<Path>,
         extension: &str,
     ) -> Result<(String, PathBuf)> {
+        let content_type = ContentType::guess(temp_file.as_ref());
+        let info = self.commit_as(temp_file, extension, content_type)?;
+        let path = self.path_for(&info.hash, extension);
+        Ok((info.hash, path))
+    }
+
+    /// `commit` with an explicit content type. Returns the index entry.
+    pub fn commit_as(
+        &self,
+        temp_file: impl AsRef<Path>,
+        extension: &str,
+        content_type: ContentType,
+    ) -> Result<ArtifactInfo> {
         let temp_path = temp_file.as_ref();
 
         // 1. Hash it
@@ -100,7 +126,8 @@ impl ArtifactStore {
             // In a real system, we might want to verify the existing file's hash,
             // but for speed we assume CAS integrity.
             fs::remove_file(temp_path).ok();
-            return Ok((hash, final_path));
+            touch(&final_path);
+            return self.index(&hash, extension, content_type);
         }
 
         // 3. Move it
@@ -122,12 +149,434 @@ impl ArtifactStore {
             let _ = dir.sync_all();
         }
 
+        self.index(&hash, extension, content_type)
+    }
+
+    /// Stores an in-memory blob under its hash (same layout as `commit`).
+    /// Writes to a temp name inside the shard first, then renames atomically.
+    pub fn put_bytes(&self, data: &[u8], extension: &str) -> Result<(String, PathBuf)> {
+        let hash = sha256_bytes(data);
+        let final_path = self.path_for(&hash, extension);
+
+        if final_path.exists() {
+            touch(&final_path);
+            return Ok((hash, final_path));
+        }
+
+        let shard_dir = final_path
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid artifact path: {:?}", final_path))?;
+        fs::create_dir_all(shard_dir)?;
+
+        let tmp_path = shard_dir.join(format!(".{}.{}.tmp", hash, std::process::id()));
+        {
+            let mut f = File::create(&tmp_path)
+                .with_context(|| format!("Failed to create artifact temp file {:?}", tmp_path))?;
+            f.write_all(data)?;
+            f.sync_all()?;
+        }
+        fs::rename(&tmp_path, &final_path)?;
+
+        if let Ok(dir) = File::open(shard_dir) {
+            let _ = dir.sync_all();
+        }
+
+        // The blob is safely stored; a missing index row is rebuilt by `reindex`.
+        let content_type = ContentType::guess(Path::new(&format!("blob.{}", extension)));
+        if let Err(e) = self.index(&hash, extension, content_type) {
+            log::warn!("Failed to index artifact {}: {}", hash, e);
+        }
+
         Ok((hash, final_path))
     }
+
+    /// Reads a blob back and verifies its content hash.
+    pub fn get_bytes(&self, hash: &str, extension: &str) -> Result<Vec<u8>> {
+        let path = self.path_for(hash, extension);
+        let data =
+            fs::read(&path).with_context(|| format!("Artifact not found in store: {:?}", path))?;
+
+        let actual = sha256_bytes(&data);
+        if actual != hash {
+            return Err(anyhow!(
+                "Artifact Integrity Violation! Path: {:?}\nExpected: {}\nActual:   {}",
+                path,
+                hash,
+                actual
+            ));
+        }
+        Ok(data)
+    }
+
+    /// Copies a file into the store, leaving the original in place.
+    pub fn put_file(&self, path: &Path, extension: &str) -> Result<ArtifactInfo> {
+        let hash = sha256_file(path)?;
+        let final_path = self.path_for(&hash, extension);
+        let content_type = ContentType::guess(path);
+
+        if !final_path.exists() {
+            let shard_dir = final_path
+                .parent()
+                .ok_or_else(|| anyhow!("Invalid artifact path: {:?}", final_path))?;
+            fs::create_dir_all(shard_dir)?;
+
+            let tmp_path = shard_dir.join(format!(".{}.{}.tmp", hash, std::process::id()));
+            fs::copy(path, &tmp_path)
+                .with_context(|| format!("Failed to copy {:?} into the store", path))?;
+            File::open(&tmp_path)?.sync_all()?;
+            fs::rename(&tmp_path, &final_path)?;
+
+            if let Ok(dir) = File::open(shard_dir) {
+                let _ = dir.sync_all();
+            }
+        } else {
+            touch(&final_path);
+        }
+        self.index(&hash, extension, content_type)
+    }
+
+    /// Location of an object in the sharded layout (`root/ab/abcdef....ext`).
+    pub fn path_for(&self, hash: &str, extension: &str) -> PathBuf {
+        let shard = &hash[0..2.min(hash.len())];
+        self.root
+            .join(shard)
+            .join(format!("{}.{}", hash, extension))
+    }
+
+    /// Every object on disk, indexed or not (temp files of in-flight writes
+    /// are skipped).
+    pub fn objects(&self) -> Result<Vec<StoredObject>> {
+        let mut out = Vec::new();
+        for shard in fs::read_dir(&self.root)? {
+            let shard = shard?.path();
+            if !shard.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&shard)? {
+                let entry = entry?;
+                let name = entry.file_name();
+                let name = match name.to_str() {
+                    Some(n) if !n.starts_with('.') => n,
+                    _ => continue,
+                };
+                let (hash, ext) = name.split_once('.').unwrap_or((name, ""));
+                let meta = entry.metadata()?;
+                out.push(StoredObject {
+                    hash: hash.to_string(),
+                    extension: ext.to_string(),
+                    size_bytes: meta.len(),
+                    modified: meta.modified()?,
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    /// Deletes an object and its index row. Gone already is not an error.
+    pub fn remove(&self, hash: &str, extension: &str) -> Result<()> {
+        let path = self.path_for(hash, extension);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Failed to remove {:?}", path));
+            }
+            _ => {}
+        }
+        self.index_conn()?.execute(
+            "DELETE FROM artifacts WHERE hash = ?1 AND extension = ?2",
+            params![hash, extension],
+        )?;
+        Ok(())
+    }
+}
+
+/// One object file of the store, as `ArtifactStore::objects` found it.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredObject {
+    pub hash: String,
+    pub extension: String,
+    pub size_bytes: u64,
+    /// Last written, or last stored again as a duplicate.
+    pub modified: SystemTime,
+}
+
+/// Marks an object as just stored (see `ArtifactStore::objects`). Best
+/// effort: a read-only store keeps working, its objects just look older.
+fn touch(path: &Path) {
+    if let Ok(f) = File::options().write(true).open(path) {
+        let _ = f.set_modified(SystemTime::now());
+    }
+}
+
+// ============================================================================
+// 3. CONTENT TYPES & PREVIEWS (The Index)
+// ============================================================================
+
+const INDEX_FILE: &str = "index.db";
+/// Text previews keep at most this many bytes from the start of the object.
+pub const PREVIEW_BYTES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentType {
+    Trajectory,
+    Dos,
+    Log,
+    Model,
+    Other,
+}
+
+impl ContentType {
+    /// Best guess from a file name ("relax.xyz", "DOSCAR", "mace.model").
+    pub fn guess(path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match ext.as_str() {
+            "xyz" | "extxyz" | "traj" | "dcd" | "xtc" | "trr" | "lammpstrj" => Self::Trajectory,
+            "dos" | "pdos" => Self::Dos,
+            "log" | "out" | "err" => Self::Log,
+            "model" | "pt" | "pth" | "ckpt" | "onnx" => Self::Model,
+            _ if name.starts_with("xdatcar") => Self::Trajectory,
+            _ if name.starts_with("doscar") => Self::Dos,
+            _ if name.starts_with("outcar") => Self::Log,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trajectory => "trajectory",
+            Self::Dos => "dos",
+            Self::Log => "log",
+            Self::Model => "model",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl FromStr for ContentType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "trajectory" => Ok(Self::Trajectory),
+            "dos" => Ok(Self::Dos),
+            "log" => Ok(Self::Log),
+            "model" => Ok(Self::Model),
+            "other" => Ok(Self::Other),
+            other => Err(anyhow!(
+                "Unknown content type '{}' (expected trajectory|dos|log|model|other)",
+                other
+            )),
+        }
+    }
+}
+
+/// One row of the artifact index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactInfo {
+    pub hash: String,
+    pub extension: String,
+    pub content_type: ContentType,
+    pub size_bytes: u64,
+    /// First `PREVIEW_BYTES` of the object, if it is text.
+    pub preview: Option<String>,
+    /// Number of frames, for trajectories in (ext)XYZ format.
+    pub frames: Option<usize>,
+    pub created_at_ms: i64,
+}
+
+impl ArtifactStore {
+    fn index_conn(&self) -> Result<Connection> {
+        let conn = Connection::open(self.root.join(INDEX_FILE))
+            .context("Failed to open artifact index")?;
+        conn.execute_batch(
+            "PRAGMA journal_mode=DELETE;
+             PRAGMA busy_timeout=10000;
+             CREATE TABLE IF NOT EXISTS artifacts (
+                hash TEXT NOT NULL,
+                extension TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                size_bytes INTEGER,
+                preview TEXT,
+                frames INTEGER,
+                created_at_ms INTEGER,
+                PRIMARY KEY (hash, extension)
+             );
+             CREATE INDEX IF NOT EXISTS idx_artifacts_type ON artifacts(content_type);",
+        )?;
+        Ok(conn)
+    }
+
+    /// Extracts the preview of a stored object and records it in the index.
+    /// Re-indexing an object replaces its row (e.g. to correct the content type).
+    pub fn index(
+        &self,
+        hash: &str,
+        extension: &str,
+        content_type: ContentType,
+    ) -> Result<ArtifactInfo> {
+        let path = self.path_for(hash, extension);
+        let size_bytes = fs::metadata(&path)
+            .with_context(|| format!("Artifact not found in store: {:?}", path))?
+            .len();
+        let frames = match content_type {
+            ContentType::Trajectory => count_xyz_frames(&path)?,
+            _ => None,
+        };
+
+        let info = ArtifactInfo {
+            hash: hash.to_string(),
+            extension: extension.to_string(),
+            content_type,
+            size_bytes,
+            preview: text_preview(&path)?,
+            frames,
+            created_at_ms: chrono::Utc::now().timestamp_millis(),
+        };
+
+        self.index_conn()?.execute(
+            "INSERT INTO artifacts
+                (hash, extension, content_type, size_bytes, preview, frames, created_at_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(hash, extension) DO UPDATE SET
+                content_type=excluded.content_type,
+                size_bytes=excluded.size_bytes,
+                preview=excluded.preview,
+                frames=excluded.frames",
+            params![
+                info.hash,
+                info.extension,
+                info.content_type.as_str(),
+                info.size_bytes as i64,
+                info.preview,
+                info.frames.map(|f| f as i64),
+                info.created_at_ms
+            ],
+        )?;
+        Ok(info)
+    }
+
+    /// Index entry of an object, without touching the object itself.
+    /// Accepts a hash prefix (e.g. the 12 chars shown by `report artifacts`).
+    pub fn info(&self, hash: &str) -> Result<Option<ArtifactInfo>> {
+        let conn = self.index_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT hash, extension, content_type, size_bytes, preview, frames, created_at_ms
+             FROM artifacts WHERE hash LIKE ?1 || '%' LIMIT 2",
+        )?;
+        let mut rows: Vec<ArtifactInfo> = stmt
+            .query_map(params![hash], row_to_info)?
+            .collect::<rusqlite::Result<_>>()?;
+
+        match rows.len() {
+            0 | 1 => Ok(rows.pop()),
+            _ => Err(anyhow!("'{}' is ambiguous, type more characters", hash)),
+        }
+    }
+
+    /// All indexed objects, newest first, optionally of one content type.
+    pub fn list(&self, content_type: Option<ContentType>) -> Result<Vec<ArtifactInfo>> {
+        let conn = self.index_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT hash, extension, content_type, size_bytes, preview, frames, created_at_ms
+             FROM artifacts WHERE ?1 IS NULL OR content_type = ?1
+             ORDER BY created_at_ms DESC",
+        )?;
+        let rows = stmt.query_map(params![content_type.map(|c| c.as_str())], row_to_info)?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Indexes objects that have no row yet (stores written before the index
+    /// existed, or a deleted `index.db`). Returns how many were added.
+    pub fn reindex(&self) -> Result<usize> {
+        let known: std::collections::HashSet<(String, String)> = self
+            .list(None)?
+            .into_iter()
+            .map(|i| (i.hash, i.extension))
+            .collect();
+
+        let mut added = 0;
+        for obj in self.objects()? {
+            if known.contains(&(obj.hash.clone(), obj.extension.clone())) {
+                continue;
+            }
+            let path = self.path_for(&obj.hash, &obj.extension);
+            self.index(&obj.hash, &obj.extension, ContentType::guess(&path))?;
+            added += 1;
+        }
+        Ok(added)
+    }
+}
+
+fn row_to_info(r: &rusqlite::Row) -> rusqlite::Result<ArtifactInfo> {
+    let content_type: String = r.get(2)?;
+    Ok(ArtifactInfo {
+        hash: r.get(0)?,
+        extension: r.get(1)?,
+        content_type: content_type.parse().unwrap_or(ContentType::Other),
+        size_bytes: r.get::<_, i64>(3)? as u64,
+        preview: r.get(4)?,
+        frames: r.get::<_, Option<i64>>(5)?.map(|f| f as usize),
+        created_at_ms: r.get(6)?,
+    })
+}
+
+/// First `PREVIEW_BYTES` of a file, or None if it looks binary.
+fn text_preview(path: &Path) -> Result<Option<String>> {
+    let mut head = Vec::with_capacity(PREVIEW_BYTES);
+    File::open(path)?
+        .take(PREVIEW_BYTES as u64)
+        .read_to_end(&mut head)?;
+    if head.contains(&0) {
+        return Ok(None);
+    }
+    match std::str::from_utf8(&head) {
+        Ok(s) => Ok(Some(s.to_string())),
+        // Cut mid-character at the preview boundary: keep the valid part
+        Err(e) if e.error_len().is_none() => Ok(Some(
+            String::from_utf8_lossy(&head[..e.valid_up_to()]).into_owned(),
+        )),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Counts frames of an (ext)XYZ file: an atom count line, a comment line,
+/// then one line per atom. None if the file is not in that format.
+fn count_xyz_frames(path: &Path) -> Result<Option<usize>> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let mut frames = 0;
+    while let Some(line) = lines.next() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => return Ok(None), // not text
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let n_atoms: usize = match line.trim().parse() {
+            Ok(n) => n,
+            Err(_) => return Ok(None),
+        };
+        // Comment line + atom lines
+        for _ in 0..=n_atoms {
+            if lines.next().is_none() {
+                return Ok(None);
+            }
+        }
+        frames += 1;
+    }
+    Ok(Some(frames))
 }
 
 // ============================================================================
-// 3. MODEL NOTARY (ML Provenance)
+// 4. MODEL NOTARY (ML Provenance)
 // ============================================================================
 
 pub struct ModelNotary;
@@ -156,3 +605,111 @@ impl ModelNotary {
         Ok(actual_hash)
     }
 }
+
+// ============================================================================
+// 5. WORK DIR MANIFESTS (Reproducibility)
+// ============================================================================
+
+/// Every regular file under `dir`, sorted by path, all with the same role.
+/// Symlinks are not followed.
+pub fn manifest_dir(dir: &Path, role: FileRole) -> Result<Vec<WorkFile>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current).with_context(|| format!("Reading {:?}", current))? {
+            let entry = entry?;
+            let kind = entry.file_type()?;
+            if kind.is_dir() {
+                pending.push(entry.path());
+            } else if kind.is_file() {
+                let path = entry.path();
+                let rel = path.strip_prefix(dir)?;
+                files.push(WorkFile {
+                    path: rel
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/"),
+                    sha256: sha256_file(&path)?,
+                    size: entry.metadata()?.len(),
+                    role,
+                });
+            }
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Store extension for a work dir file: its own, or `dat` (INCAR, POSCAR...).
+fn workfile_ext(path: &str) -> &str {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| !e.is_empty() && e.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("dat")
+}
+
+impl ArtifactStore {
+    /// The stored content of one work dir file, checked against its hash.
+    pub fn read_workfile(&self, file: &WorkFile) -> Result<Vec<u8>> {
+        self.get_bytes(&file.sha256, workfile_ext(&file.path))
+    }
+
+    /// Where `capture_workdir` put the work dir file `path` with this hash.
+    pub fn workfile_path(&self, path: &str, hash: &str) -> PathBuf {
+        self.path_for(hash, workfile_ext(path))
+    }
+
+    /// Stores every file of a finished work dir and completes its manifest.
+    ///
+    /// `files` holds the inputs the driver recorded before the engine ran.
+    /// A file that still matches its input record is stored as that input;
+    /// anything new or changed is added as an output. An input the run
+    /// overwrote keeps its record, but its original content is not stored.
+    pub fn capture_workdir(&self, dir: &Path, files: &mut Vec<WorkFile>) -> Result<()> {
+        for file in manifest_dir(dir, FileRole::Output)? {
+            self.put_file(&dir.join(&file.path), workfile_ext(&file.path))?;
+            let unchanged = files.iter().any(|f| {
+                f.role == FileRole::Input && f.path == file.path && f.sha256 == file.sha256
+            });
+            if !unchanged {
+                files.push(file);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the files of a manifest with one of `roles` back under `dir`.
+    /// Every file is checked against its hash. Returns how many were written.
+    pub fn restore_workdir(
+        &self,
+        files: &[WorkFile],
+        roles: &[FileRole],
+        dir: &Path,
+    ) -> Result<usize> {
+        let mut written = 0;
+        for file in files.iter().filter(|f| roles.contains(&f.role)) {
+            let rel = Path::new(&file.path);
+            if !rel
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)))
+            {
+                return Err(anyhow!(
+                    "Refusing to restore outside the work dir: {}",
+                    file.path
+                ));
+            }
+            let data = self
+                .read_workfile(file)
+                .with_context(|| format!("Restoring {}", file.path))?;
+            let target = dir.join(rel);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, data).with_context(|| format!("Writing {:?}", target))?;
+            written += 1;
+        }
+        Ok(written)
+    }
+}
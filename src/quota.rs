@@ -0,0 +1,215 @@
+// src/quota.rs
+//
+// =============================================================================
+// UNIFIEDLAB: RESOURCE QUOTAS (v 0.1 )
+// =============================================================================
+//
+// The Rationing.
+//
+// Caps on what one project or tag may hold at once, so a single workflow
+// cannot starve a shared deployment:
+// 1. `quotas.toml` sets limits per project (`Job::flow_context["project"]`,
+//    from `deploy --project`) and per tag (a job's `required_tags`).
+// 2. The Coordinator sums the in-flight jobs of every bucket at the start of
+//    each scheduling pass and charges each grant as it makes it.
+// 3. A job is granted only while every bucket it falls in stays within its
+//    limit; otherwise it waits in the queue like a job that does not fit.
+// 4. Limits and usage go to the checkpoint (`META_QUOTAS`) for the TUI and
+//    the console.
+
+use crate::core::Job;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Picked up from the root when `start --quotas` is not given.
+pub const QUOTA_CONFIG_FILE: &str = "quotas.toml";
+
+/// `Job::flow_context` key naming the project a job is charged to.
+pub const FLOW_PROJECT: &str = "project";
+
+// ============================================================================
+// 1. CONFIGURATION
+// ============================================================================
+
+/// Most a bucket may hold at once; an unset field is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Quota {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cores: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpus: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+}
+
+/// `root/quotas.toml`, e.g.:
+///
+/// ```toml
+/// [project.screening]
+/// gpus = 4
+///
+/// [tag.brain]
+/// jobs = 2
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuotaConfig {
+    #[serde(default)]
+    pub project: BTreeMap<String, Quota>,
+    #[serde(default)]
+    pub tag: BTreeMap<String, Quota>,
+}
+
+impl QuotaConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Reading quota config {:?}", path))?;
+        toml::from_str(&text).with_context(|| format!("Parsing quota config {:?}", path))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.project.is_empty() && self.tag.is_empty()
+    }
+
+    /// The limited buckets `job` is charged to, as `project:<name>` / `tag:<name>`.
+    fn buckets(&self, job: &Job) -> Vec<(String, Quota)> {
+        let project = job_project(job)
+            .and_then(|p| self.project.get_key_value(p))
+            .map(|(p, q)| (format!("project:{}", p), *q));
+        let tags = job
+            .resources
+            .required_tags
+            .iter()
+            .filter_map(|t| self.tag.get_key_value(t))
+            .map(|(t, q)| (format!("tag:{}", t), *q));
+        project.into_iter().chain(tags).collect()
+    }
+}
+
+/// The project `job` is charged to, if it has one.
+pub fn job_project(job: &Job) -> Option<&str> {
+    job.flow_context.get(FLOW_PROJECT).and_then(|v| v.as_str())
+}
+
+// ============================================================================
+// 2. USAGE
+// ============================================================================
+
+/// What the in-flight jobs of one bucket hold.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub cores: usize,
+    pub gpus: usize,
+    pub jobs: usize,
+}
+
+impl QuotaUsage {
+    fn add(&mut self, job: &Job) {
+        self.cores += job.resources.cores;
+        self.gpus += job.resources.gpus;
+        self.jobs += 1;
+    }
+
+    /// True if `job` still fits under `limit` on top of this.
+    pub fn admits(&self, job: &Job, limit: &Quota) -> bool {
+        let within =
+            |cap: Option<usize>, used: usize, more: usize| cap.map_or(true, |c| used + more <= c);
+        within(limit.cores, self.cores, job.resources.cores)
+            && within(limit.gpus, self.gpus, job.resources.gpus)
+            && within(limit.jobs, self.jobs, 1)
+    }
+
+    /// True once any limited resource is used up.
+    pub fn at_limit(&self, limit: &Quota) -> bool {
+        limit.cores.is_some_and(|c| self.cores >= c)
+            || limit.gpus.is_some_and(|g| self.gpus >= g)
+            || limit.jobs.is_some_and(|j| self.jobs >= j)
+    }
+}
+
+/// One entry of `META_QUOTAS`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaStatus {
+    pub bucket: String,
+    pub limit: Quota,
+    pub used: QuotaUsage,
+}
+
+impl QuotaStatus {
+    /// True if `job` is charged to this bucket.
+    pub fn covers(&self, job: &Job) -> bool {
+        match self.bucket.split_once(':') {
+            Some(("project", p)) => job_project(job) == Some(p),
+            Some(("tag", t)) => job.resources.required_tags.iter().any(|r| r == t),
+            _ => false,
+        }
+    }
+}
+
+/// Limits and what is charged against them during a scheduling pass.
+#[derive(Debug, Default)]
+pub struct QuotaBook {
+    config: QuotaConfig,
+    used: BTreeMap<String, QuotaUsage>,
+}
+
+impl QuotaBook {
+    pub fn new(config: QuotaConfig) -> Self {
+        Self {
+            config,
+            used: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.config.is_empty()
+    }
+
+    /// Forgets all usage; the Coordinator charges the in-flight jobs again.
+    pub fn reset(&mut self) {
+        self.used.clear();
+    }
+
+    pub fn charge(&mut self, job: &Job) {
+        for (bucket, _) in self.config.buckets(job) {
+            self.used.entry(bucket).or_default().add(job);
+        }
+    }
+
+    /// True if granting `job` keeps every bucket it falls in within its limit.
+    pub fn admits(&self, job: &Job) -> bool {
+        self.config.buckets(job).iter().all(|(bucket, limit)| {
+            self.used
+                .get(bucket)
+                .copied()
+                .unwrap_or_default()
+                .admits(job, limit)
+        })
+    }
+
+    /// Every configured bucket with its usage, projects first.
+    pub fn status(&self) -> Vec<QuotaStatus> {
+        let projects = self
+            .config
+            .project
+            .iter()
+            .map(|(p, q)| (format!("project:{}", p), q));
+        let tags = self
+            .config
+            .tag
+            .iter()
+            .map(|(t, q)| (format!("tag:{}", t), q));
+        projects
+            .chain(tags)
+            .map(|(bucket, limit)| QuotaStatus {
+                used: self.used.get(&bucket).copied().unwrap_or_default(),
+                bucket,
+                limit: *limit,
+            })
+            .collect()
+    }
+}
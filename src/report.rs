@@ -0,0 +1,942 @@
+// src/report.rs
+//
+// =============================================================================
+// UNIFIEDLAB: REPORTS (v 0.1 )
+// =============================================================================
+//
+// Offline summaries computed from the checkpoint DB (`unifiedlab report ...`).
+//
+// Efficiency:
+// - Groups completed jobs by engine code ("vasp:4p", "janus:mace_mp").
+// - Compares measured cores (CPU time / wall time) and wall time against the
+//   `ResourceReq` the workflow asked for.
+// - Flags over- and under-provisioned groups with a suggested request.
+//
+// Artifacts:
+// - Lists the artifact index (content type, size, frames, first line of text)
+//   without opening the stored objects.
+//
+// Purge (`unifiedlab purge --dry-run`):
+// - The jobs a purge deletes and the artifact store objects it frees, with
+//   their age and total size.
+//
+// Memoization:
+// - Counts Compute jobs answered from the cache ("memoized_from") against
+//   those that ran, per engine and per campaign (the root generator of an
+//   expansion chain; hand-written jobs form the "static" campaign).
+// - Lists the fingerprints shared by most jobs. Several jobs that all ran
+//   mean the cache never got a chance (siblings in flight at once, or
+//   duplicates in the workflow itself).
+//
+// Throughput (the TUI's STATS tab):
+// - Per engine over the last N hours: jobs completed per hour, median queue
+//   time, median runtime and failure rate.
+// - Kept up to date incrementally: each refresh reads only the jobs that
+//   finished since the last one and forgets those that left the window.
+//
+// Status (`unifiedlab status`):
+// - Jobs per status, the load of each worker, throughput over the last
+//   hours and the job pending longest: what a cron job or a Slurm epilogue
+//   checks without opening the TUI.
+//
+// Workers (`unifiedlab workers`):
+// - Every worker the checkpoint DB knows, with free and total capacity and
+//   how long ago it was heard from; one silent past the worker timeout is
+//   stale (dead, or never restarted after the Coordinator was).
+//
+// Recent jobs (the TUI's job table):
+// - The newest 1000 job summaries, merged from the rows updated since the
+//   last refresh instead of re-parsing all of them every time.
+
+use crate::checkpoint::{CheckpointStore, FinishedRecord, PurgePlan, UsageRecord};
+use crate::core::{Engine, Job, JobStatus, JobSummary};
+use crate::feedback::generated_by;
+use crate::marketplace::{
+    job_fingerprint, MemoStats, DEFAULT_WORKER_TIMEOUT, META_MEMO, META_PAUSED,
+    META_PAUSED_WORKFLOWS,
+};
+use crate::provenance::ArtifactInfo;
+use crate::workflow::NodeType;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Below this share of the requested cores a group counts as over-provisioned.
+const OVER_THRESHOLD: f64 = 0.5;
+/// Above this share it is stealing cores from its neighbours.
+const UNDER_THRESHOLD: f64 = 1.1;
+/// Jobs finishing this close to their time limit risk being cut off.
+const TIME_MARGIN: f64 = 0.9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Ok,
+    Over,
+    Under,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EfficiencyRow {
+    pub code: String,
+    pub jobs: usize,
+    pub req_cores: f64,
+    pub used_cores: f64,
+    pub max_used_cores: f64,
+    pub peak_rss_mb: f64,
+    pub max_wall_min: f64,
+    pub time_limit_min: f64,
+    pub verdict: Verdict,
+    /// e.g. "cores 8 -> 2", empty when the request looks right.
+    pub suggestion: String,
+}
+
+impl EfficiencyRow {
+    /// Measured cores as a share of requested cores.
+    pub fn core_efficiency(&self) -> f64 {
+        if self.req_cores > 0.0 {
+            self.used_cores / self.req_cores
+        } else {
+            0.0
+        }
+    }
+}
+
+pub fn efficiency(records: &[UsageRecord]) -> Vec<EfficiencyRow> {
+    let mut groups: BTreeMap<&str, Vec<&UsageRecord>> = BTreeMap::new();
+    for r in records {
+        groups.entry(&r.code).or_default().push(r);
+    }
+
+    groups
+        .into_iter()
+        .map(|(code, rs)| {
+            let n = rs.len() as f64;
+            let mean = |f: fn(&UsageRecord) -> f64| rs.iter().map(|r| f(r)).sum::<f64>() / n;
+            let max = |f: fn(&UsageRecord) -> f64| rs.iter().map(|r| f(r)).fold(0.0, f64::max);
+
+            let mut row = EfficiencyRow {
+                code: code.to_string(),
+                jobs: rs.len(),
+                req_cores: mean(|r| r.requested.cores as f64),
+                used_cores: mean(|r| r.usage.avg_cores()),
+                max_used_cores: max(|r| r.usage.avg_cores()),
+                peak_rss_mb: max(|r| r.usage.peak_rss_mb),
+                max_wall_min: max(|r| r.usage.wall_seconds / 60.0),
+                time_limit_min: mean(|r| r.requested.time_limit_min as f64),
+                verdict: Verdict::Ok,
+                suggestion: String::new(),
+            };
+            judge(&mut row);
+            row
+        })
+        .collect()
+}
+
+fn judge(row: &mut EfficiencyRow) {
+    let mut hints = Vec::new();
+    let fit_cores = row.max_used_cores.ceil().max(1.0);
+
+    let eff = row.core_efficiency();
+    if eff > UNDER_THRESHOLD {
+        row.verdict = Verdict::Under;
+        hints.push(format!("cores {} -> {}", row.req_cores.round(), fit_cores));
+    } else if eff < OVER_THRESHOLD && fit_cores < row.req_cores {
+        row.verdict = Verdict::Over;
+        hints.push(format!("cores {} -> {}", row.req_cores.round(), fit_cores));
+    }
+
+    if row.time_limit_min > 0.0 && row.max_wall_min > TIME_MARGIN * row.time_limit_min {
+        row.verdict = Verdict::Under;
+        hints.push(format!(
+            "time_limit_min {} -> {}",
+            row.time_limit_min.round(),
+            (row.max_wall_min * 1.5).ceil()
+        ));
+    }
+    row.suggestion = hints.join(", ");
+}
+
+pub fn print_efficiency(rows: &[EfficiencyRow]) {
+    if rows.is_empty() {
+        println!("No completed jobs with measured usage yet.");
+        return;
+    }
+    println!(
+        "{:<18} {:>5} {:>9} {:>9} {:>6} {:>9} {:>9}  {:<6} SUGGEST",
+        "ENGINE", "JOBS", "REQ CORES", "USED", "EFF", "PEAK MB", "WALL(m)", "STATUS"
+    );
+    for r in rows {
+        println!(
+            "{:<18} {:>5} {:>9.1} {:>9.2} {:>5.0}% {:>9.0} {:>9.1}  {:<6} {}",
+            r.code,
+            r.jobs,
+            r.req_cores,
+            r.used_cores,
+            r.core_efficiency() * 100.0,
+            r.peak_rss_mb,
+            r.max_wall_min,
+            format!("{:?}", r.verdict).to_lowercase(),
+            r.suggestion
+        );
+    }
+    let over = rows.iter().filter(|r| r.verdict == Verdict::Over).count();
+    let under = rows.iter().filter(|r| r.verdict == Verdict::Under).count();
+    println!(
+        "{} engine group(s): {} over-provisioned, {} under-provisioned",
+        rows.len(),
+        over,
+        under
+    );
+}
+
+/// Duplicated fingerprints listed when the caller does not say.
+pub const DEFAULT_MEMO_TOP: usize = 10;
+
+/// Campaign of jobs that no generator proposed.
+pub const STATIC_CAMPAIGN: &str = "static";
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoRow {
+    /// Engine code or campaign name.
+    pub key: String,
+    /// Answered from the cache.
+    pub cached: usize,
+    /// Ran although they could have been cached (completed or failed).
+    pub computed: usize,
+}
+
+impl MemoRow {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.cached + self.computed;
+        if total > 0 {
+            self.cached as f64 / total as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateRow {
+    pub fingerprint: String,
+    pub code: String,
+    /// Compute jobs with this fingerprint, whatever their state.
+    pub jobs: usize,
+    pub cached: usize,
+    pub computed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoReport {
+    /// Distinct fingerprints with a completed job.
+    pub entries: usize,
+    pub engines: Vec<MemoRow>,
+    pub campaigns: Vec<MemoRow>,
+    /// Most shared first.
+    pub duplicates: Vec<DuplicateRow>,
+    /// The Coordinator's own lookup counters, when it has saved any.
+    pub live: Option<MemoStats>,
+}
+
+/// Memoization summary of `jobs`, with the `top` most duplicated fingerprints.
+pub fn memo(jobs: &[Job], live: Option<MemoStats>, top: usize) -> MemoReport {
+    let by_id: HashMap<Uuid, &Job> = jobs.iter().map(|j| (j.id, j)).collect();
+    let is_compute = |j: &Job| {
+        j.flow_context
+            .get("node_type")
+            .and_then(|v| serde_json::from_value::<NodeType>(v.clone()).ok())
+            .map_or(true, |t| t == NodeType::Compute)
+    };
+
+    let mut engines: BTreeMap<String, MemoRow> = BTreeMap::new();
+    let mut campaigns: BTreeMap<String, MemoRow> = BTreeMap::new();
+    let mut shared: HashMap<String, DuplicateRow> = HashMap::new();
+    let mut entries = HashSet::new();
+
+    for job in jobs {
+        let fp = job_fingerprint(&job.config);
+        if job.status == JobStatus::Completed {
+            entries.insert(fp.clone());
+        }
+        if !is_compute(job) {
+            continue;
+        }
+        let cached = job.flow_context.contains_key("memoized_from");
+        let computed = !cached && matches!(job.status, JobStatus::Completed | JobStatus::Failed);
+
+        let code = job.config.engine.code();
+        let dup = shared.entry(fp.clone()).or_insert_with(|| DuplicateRow {
+            fingerprint: fp,
+            code: code.clone(),
+            jobs: 0,
+            cached: 0,
+            computed: 0,
+        });
+        dup.jobs += 1;
+        if !cached && !computed {
+            continue;
+        }
+        dup.cached += cached as usize;
+        dup.computed += computed as usize;
+
+        for (groups, key) in [
+            (&mut engines, code),
+            (&mut campaigns, campaign_of(job, &by_id)),
+        ] {
+            let row = groups.entry(key.clone()).or_insert_with(|| MemoRow {
+                key,
+                ..Default::default()
+            });
+            row.cached += cached as usize;
+            row.computed += computed as usize;
+        }
+    }
+
+    let mut duplicates: Vec<DuplicateRow> = shared.into_values().filter(|d| d.jobs > 1).collect();
+    duplicates.sort_by(|a, b| {
+        b.jobs
+            .cmp(&a.jobs)
+            .then(b.computed.cmp(&a.computed))
+            .then(a.fingerprint.cmp(&b.fingerprint))
+    });
+    duplicates.truncate(top);
+
+    MemoReport {
+        entries: entries.len(),
+        engines: engines.into_values().collect(),
+        campaigns: campaigns.into_values().collect(),
+        duplicates,
+        live,
+    }
+}
+
+/// `memo` over every job in the checkpoint DB, with the Coordinator's
+/// last saved counters.
+pub fn memo_from_store(store: &CheckpointStore, top: usize) -> Result<MemoReport> {
+    let jobs: Vec<Job> = store.restore_jobs()?.into_values().collect();
+    let live = store
+        .get_meta(META_MEMO)?
+        .and_then(|v| serde_json::from_str(&v).ok());
+    Ok(memo(&jobs, live, top))
+}
+
+/// Walks `generated_by` back to the first generator of the chain. A later
+/// generation's agent is a child of the previous generation's candidates.
+fn campaign_of(job: &Job, by_id: &HashMap<Uuid, &Job>) -> String {
+    let mut generator = match generated_by(job).and_then(|g| by_id.get(&g)) {
+        Some(g) => *g,
+        None => return STATIC_CAMPAIGN.into(),
+    };
+    let mut seen = HashSet::new();
+    while seen.insert(generator.id) {
+        let previous = generator
+            .parent_ids
+            .iter()
+            .filter_map(|p| by_id.get(p))
+            .find_map(|p| generated_by(p).and_then(|g| by_id.get(&g)));
+        match previous {
+            Some(g) => generator = *g,
+            None => break,
+        }
+    }
+    let strategy = match &generator.config.engine {
+        Engine::Agent { strategy, .. } => strategy.as_str(),
+        _ => "generator",
+    };
+    format!("{}:{}", strategy, &generator.id.simple().to_string()[..8])
+}
+
+pub fn print_memo(report: &MemoReport) {
+    if let Some(live) = &report.live {
+        println!(
+            "Lookups: {} of {} hit ({:.0}%), {} cached results",
+            live.total.hits,
+            live.total.lookups,
+            live.total.hit_rate() * 100.0,
+            report.entries
+        );
+    } else {
+        println!("Lookups: none yet, {} cached results", report.entries);
+    }
+
+    for (title, rows) in [("ENGINE", &report.engines), ("CAMPAIGN", &report.campaigns)] {
+        if rows.is_empty() {
+            continue;
+        }
+        println!();
+        println!(
+            "{:<24} {:>7} {:>9} {:>6}",
+            title, "CACHED", "COMPUTED", "HIT"
+        );
+        for r in rows.iter() {
+            println!(
+                "{:<24} {:>7} {:>9} {:>5.0}%",
+                r.key,
+                r.cached,
+                r.computed,
+                r.hit_rate() * 100.0
+            );
+        }
+    }
+
+    println!();
+    if report.duplicates.is_empty() {
+        println!("No fingerprint is shared by two jobs.");
+        return;
+    }
+    println!(
+        "{:<14} {:<18} {:>5} {:>7} {:>9}",
+        "FINGERPRINT", "ENGINE", "JOBS", "CACHED", "COMPUTED"
+    );
+    for d in &report.duplicates {
+        println!(
+            "{:<14} {:<18} {:>5} {:>7} {:>9}",
+            &d.fingerprint[..12],
+            d.code,
+            d.jobs,
+            d.cached,
+            d.computed
+        );
+    }
+    let wasted: usize = report
+        .duplicates
+        .iter()
+        .map(|d| d.computed.saturating_sub(1))
+        .sum();
+    if wasted > 0 {
+        println!("{} run(s) above repeated one that had already run", wasted);
+    }
+}
+
+pub fn print_artifacts(rows: &[ArtifactInfo]) {
+    if rows.is_empty() {
+        println!("No artifacts in the store yet.");
+        return;
+    }
+    println!(
+        "{:<12} {:<10} {:>10} {:>7}  PREVIEW",
+        "HASH", "TYPE", "SIZE", "FRAMES"
+    );
+    for r in rows {
+        let first_line = r
+            .preview
+            .as_deref()
+            .and_then(|p| p.lines().find(|l| !l.trim().is_empty()))
+            .map(|l| l.trim().chars().take(48).collect::<String>())
+            .unwrap_or_else(|| "-".into());
+        println!(
+            "{:<12} {:<10} {:>10} {:>7}  {}",
+            r.hash.chars().take(12).collect::<String>(),
+            r.content_type.as_str(),
+            human_bytes(r.size_bytes),
+            r.frames
+                .map(|f| f.to_string())
+                .unwrap_or_else(|| "-".into()),
+            first_line
+        );
+    }
+    let total: u64 = rows.iter().map(|r| r.size_bytes).sum();
+    println!("{} artifact(s), {}", rows.len(), human_bytes(total));
+}
+
+pub fn print_artifact(info: &ArtifactInfo) {
+    println!("Hash:    {}", info.hash);
+    println!("Type:    {}", info.content_type.as_str());
+    println!("Size:    {}", human_bytes(info.size_bytes));
+    if let Some(frames) = info.frames {
+        println!("Frames:  {}", frames);
+    }
+    match &info.preview {
+        Some(p) => println!("Preview:\n{}", p),
+        None => println!("Preview: (binary)"),
+    }
+}
+
+pub fn print_purge_plan(plan: &PurgePlan, now_ms: i64) {
+    if plan.jobs.is_empty() {
+        println!("No job to purge.");
+    } else {
+        println!("{:<10} {:<10} {:<16} {:>8}", "JOB", "STATUS", "CODE", "AGE");
+        for j in &plan.jobs {
+            println!(
+                "{:<10} {:<10} {:<16} {:>8}",
+                j.id.chars().take(8).collect::<String>(),
+                j.status,
+                j.code,
+                human_ms(now_ms - j.updated_at)
+            );
+        }
+        println!("{} job(s)", plan.jobs.len());
+    }
+    if plan.blobs.is_empty() {
+        return;
+    }
+    println!();
+    println!("{:<12} {:<10} {:>10} {:>8}", "OBJECT", "EXT", "SIZE", "AGE");
+    let now = std::time::SystemTime::now();
+    for b in &plan.blobs {
+        let age = now.duration_since(b.modified).unwrap_or_default();
+        println!(
+            "{:<12} {:<10} {:>10} {:>8}",
+            b.hash.chars().take(12).collect::<String>(),
+            b.extension,
+            human_bytes(b.size_bytes),
+            human_ms(age.as_millis() as i64)
+        );
+    }
+    let total: u64 = plan.blobs.iter().map(|b| b.size_bytes).sum();
+    println!(
+        "{} unreferenced object(s), {}",
+        plan.blobs.len(),
+        human_bytes(total)
+    );
+}
+
+/// "812 B", "4.2 KB", "1.3 GB".
+pub fn human_bytes(n: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut v = n as f64;
+    let mut unit = 0;
+    while v >= 1024.0 && unit < UNITS.len() - 1 {
+        v /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", n)
+    } else {
+        format!("{:.1} {}", v, UNITS[unit])
+    }
+}
+
+/// Throughput of one engine code (or of all of them) over the window.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThroughputRow {
+    pub code: String,
+    pub completed: usize,
+    pub failed: usize,
+    pub per_hour: f64,
+    pub median_queue_ms: Option<i64>,
+    pub median_runtime_ms: Option<i64>,
+}
+
+impl ThroughputRow {
+    /// Failed jobs as a share of all finished ones.
+    pub fn failure_rate(&self) -> f64 {
+        match self.completed + self.failed {
+            0 => 0.0,
+            n => self.failed as f64 / n as f64,
+        }
+    }
+}
+
+/// Finished jobs of the last `window`, grown one DB read at a time.
+pub struct Throughput {
+    window: Duration,
+    /// Latest finish seen; the next read starts there.
+    watermark_ms: Option<i64>,
+    /// By job id, so a job written again is not counted twice.
+    jobs: HashMap<String, FinishedRecord>,
+}
+
+impl Throughput {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            watermark_ms: None,
+            jobs: HashMap::new(),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    fn window_start(&self, now_ms: i64) -> i64 {
+        now_ms - self.window.as_millis() as i64
+    }
+
+    /// Reads the jobs that finished since the last call.
+    pub fn refresh(&mut self, store: &CheckpointStore, now_ms: i64) -> Result<()> {
+        // Same-millisecond rows are read again; the id map absorbs them
+        let since = self
+            .watermark_ms
+            .unwrap_or_else(|| self.window_start(now_ms));
+        let rows = store.get_finished_since(since)?;
+        self.absorb(rows, now_ms);
+        Ok(())
+    }
+
+    /// Adds finished jobs and drops those older than the window.
+    pub fn absorb(&mut self, rows: Vec<FinishedRecord>, now_ms: i64) {
+        for r in rows {
+            self.watermark_ms = Some(self.watermark_ms.unwrap_or(i64::MIN).max(r.finished_ms));
+            self.jobs.insert(r.id.clone(), r);
+        }
+        let start = self.window_start(now_ms);
+        self.jobs.retain(|_, r| r.finished_ms >= start);
+    }
+
+    /// One row per engine code, then one for all of them together.
+    pub fn rows(&self) -> Vec<ThroughputRow> {
+        let mut groups: BTreeMap<&str, Vec<&FinishedRecord>> = BTreeMap::new();
+        for r in self.jobs.values() {
+            groups.entry(&r.code).or_default().push(r);
+        }
+        let mut rows: Vec<_> = groups
+            .into_iter()
+            .map(|(code, rs)| self.row(code, &rs))
+            .collect();
+        if rows.len() > 1 {
+            let all: Vec<_> = self.jobs.values().collect();
+            rows.push(self.row("all", &all));
+        }
+        rows
+    }
+
+    fn row(&self, code: &str, records: &[&FinishedRecord]) -> ThroughputRow {
+        let failed = records.iter().filter(|r| r.failed).count();
+        let completed = records.len() - failed;
+        let done = || records.iter().filter(|r| !r.failed);
+        ThroughputRow {
+            code: code.to_string(),
+            completed,
+            failed,
+            per_hour: completed as f64 / (self.window.as_secs_f64() / 3600.0),
+            median_queue_ms: median(done().filter_map(|r| r.queue_ms).collect()),
+            median_runtime_ms: median(done().filter_map(|r| r.runtime_ms).collect()),
+        }
+    }
+}
+
+/// Hours of finished jobs `status` measures throughput over.
+pub const DEFAULT_STATUS_WINDOW_H: u64 = 1;
+
+/// Every status, in lifecycle order.
+const STATUS_ORDER: &[&str] = &[
+    "Pending",
+    "Blocked",
+    "Queued",
+    "Running",
+    "Completed",
+    "Failed",
+    "Cancelled",
+];
+
+/// One worker as of its last heartbeat.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerLoad {
+    pub worker_id: String,
+    pub hostname: Option<String>,
+    pub tags: Vec<String>,
+    /// Jobs it is running.
+    pub tasks: usize,
+    pub free_cores: usize,
+    pub free_gpus: usize,
+    /// None from workers that do not report them.
+    pub total_cores: Option<usize>,
+    pub total_gpus: Option<usize>,
+    pub draining: bool,
+    pub last_seen_ms: i64,
+    /// Not heard from within `DEFAULT_WORKER_TIMEOUT`.
+    pub silent: bool,
+}
+
+/// The job that has waited longest for a worker.
+#[derive(Debug, Clone, Serialize)]
+pub struct OldestPending {
+    pub id: String,
+    pub code: String,
+    pub node_id: String,
+    pub waiting_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    /// Jobs per status, every status listed.
+    pub jobs: BTreeMap<String, u64>,
+    pub total: u64,
+    pub paused: bool,
+    /// Workflows paused on their own (`pause --workflow`).
+    pub paused_workflows: Vec<String>,
+    pub workers: Vec<WorkerLoad>,
+    pub window_hours: u64,
+    pub throughput: Vec<ThroughputRow>,
+    pub oldest_pending: Option<OldestPending>,
+}
+
+pub fn status_from_store(
+    store: &CheckpointStore,
+    window_hours: u64,
+    now_ms: i64,
+) -> Result<StatusReport> {
+    let mut jobs: BTreeMap<String, u64> = STATUS_ORDER.iter().map(|s| (s.to_string(), 0)).collect();
+    jobs.extend(store.count_by_status()?);
+
+    let workers = workers_from_store(store, DEFAULT_WORKER_TIMEOUT, now_ms)?;
+
+    let mut throughput = Throughput::new(Duration::from_secs(window_hours * 3600));
+    throughput.refresh(store, now_ms)?;
+
+    Ok(StatusReport {
+        total: jobs.values().sum(),
+        jobs,
+        paused: store.get_meta(META_PAUSED)?.as_deref() == Some("true"),
+        paused_workflows: store
+            .get_meta(META_PAUSED_WORKFLOWS)?
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default(),
+        workers,
+        window_hours,
+        throughput: throughput.rows(),
+        oldest_pending: store.get_oldest_pending()?.map(|j| OldestPending {
+            waiting_ms: (now_ms - j.updated_at).max(0),
+            id: j.id,
+            code: j.code,
+            node_id: j.node_id,
+        }),
+    })
+}
+
+/// Every worker in the store, most recently seen first. `silent` marks
+/// those not heard from within `timeout`.
+pub fn workers_from_store(
+    store: &CheckpointStore,
+    timeout: Duration,
+    now_ms: i64,
+) -> Result<Vec<WorkerLoad>> {
+    let timeout_ms = timeout.as_millis() as i64;
+    Ok(store
+        .get_active_workers()?
+        .into_iter()
+        .map(|w| WorkerLoad {
+            silent: now_ms - w.last_seen_ms > timeout_ms,
+            worker_id: w.worker_id,
+            hostname: w.hostname,
+            tags: w.tags,
+            tasks: w.tasks,
+            free_cores: w.cores,
+            free_gpus: w.gpus,
+            total_cores: w.total_cores,
+            total_gpus: w.total_gpus,
+            draining: w.draining,
+            last_seen_ms: w.last_seen_ms,
+        })
+        .collect())
+}
+
+/// One line per worker, stale ones flagged in the first column so they
+/// stand out in a long list.
+pub fn print_workers(workers: &[WorkerLoad], now_ms: i64) {
+    if workers.is_empty() {
+        println!("No workers have reported in yet.");
+        return;
+    }
+    let of = |free: usize, total: Option<usize>| match total {
+        Some(t) => format!("{}/{}", free, t),
+        None => format!("{}/?", free),
+    };
+    println!(
+        "  {:<24} {:<20} {:>9} {:>7} {:>6} {:>9}  TAGS",
+        "WORKER", "HOST", "CORES", "GPUS", "TASKS", "SEEN"
+    );
+    for w in workers {
+        println!(
+            "{} {:<24} {:<20} {:>9} {:>7} {:>6} {:>9}  {}{}{}",
+            if w.silent { "!" } else { " " },
+            w.worker_id,
+            w.hostname.as_deref().unwrap_or("-"),
+            of(w.free_cores, w.total_cores),
+            of(w.free_gpus, w.total_gpus),
+            w.tasks,
+            human_ms((now_ms - w.last_seen_ms).max(0)),
+            if w.tags.is_empty() {
+                "-".into()
+            } else {
+                w.tags.join(",")
+            },
+            if w.draining { "  (draining)" } else { "" },
+            if w.silent { "  (stale)" } else { "" }
+        );
+    }
+    let stale = workers.iter().filter(|w| w.silent).count();
+    println!(
+        "{} worker(s), {} live, {} stale",
+        workers.len(),
+        workers.len() - stale,
+        stale
+    );
+}
+
+pub fn print_status(report: &StatusReport, now_ms: i64) {
+    let counts: Vec<String> = STATUS_ORDER
+        .iter()
+        .map(|s| {
+            format!(
+                "{} {}",
+                report.jobs.get(*s).copied().unwrap_or(0),
+                s.to_lowercase()
+            )
+        })
+        .collect();
+    println!("Jobs: {} ({})", report.total, counts.join(", "));
+    if report.paused {
+        println!("Scheduling is paused");
+    }
+    if !report.paused_workflows.is_empty() {
+        println!("Paused workflows: {}", report.paused_workflows.join(", "));
+    }
+    match &report.oldest_pending {
+        Some(j) => println!(
+            "Oldest pending: {} ({}, {}) waiting {}",
+            &j.id[..8.min(j.id.len())],
+            j.code,
+            j.node_id,
+            human_ms(j.waiting_ms)
+        ),
+        None => println!("Oldest pending: none"),
+    }
+
+    println!();
+    if report.workers.is_empty() {
+        println!("No workers have reported in yet.");
+    } else {
+        println!(
+            "{:<24} {:>6} {:>10} {:>9} {:>9}  {:<20}",
+            "WORKER", "TASKS", "FREE CORES", "FREE GPUS", "SEEN", "HOST"
+        );
+        for w in &report.workers {
+            println!(
+                "{:<24} {:>6} {:>10} {:>9} {:>9}  {:<20}{}{}",
+                w.worker_id,
+                w.tasks,
+                w.free_cores,
+                w.free_gpus,
+                human_ms((now_ms - w.last_seen_ms).max(0)),
+                w.hostname.as_deref().unwrap_or("-"),
+                if w.draining { "  (draining)" } else { "" },
+                if w.silent { "  (silent)" } else { "" }
+            );
+        }
+    }
+
+    println!();
+    if report.throughput.is_empty() {
+        println!("No job finished in the last {}h.", report.window_hours);
+        return;
+    }
+    println!(
+        "{:<24} {:>9} {:>6} {:>8} {:>9} {:>9}",
+        format!("LAST {}H", report.window_hours),
+        "COMPLETED",
+        "FAILED",
+        "PER HOUR",
+        "QUEUE",
+        "RUNTIME"
+    );
+    for t in &report.throughput {
+        println!(
+            "{:<24} {:>9} {:>6} {:>8.1} {:>9} {:>9}",
+            t.code,
+            t.completed,
+            t.failed,
+            t.per_hour,
+            t.median_queue_ms.map_or("-".into(), human_ms),
+            t.median_runtime_ms.map_or("-".into(), human_ms)
+        );
+    }
+}
+
+/// Rows kept by `RecentJobs`, as many as `get_jobs_summary` returns.
+const RECENT_JOBS: usize = 1000;
+/// How far behind the watermark rows are read again. `updated_at` is
+/// stamped when a job changes, but the Coordinator writes it up to one
+/// checkpoint (5 s) later, so a row can land behind newer ones.
+const LATE_WRITE_MS: i64 = 15_000;
+/// Pruned or re-imported rows never show up as changes; a full read this
+/// often drops them.
+const RECENT_RESYNC: Duration = Duration::from_secs(60);
+
+/// The most recently updated jobs, grown one DB read at a time.
+#[derive(Default)]
+pub struct RecentJobs {
+    /// Latest `updated_at` seen; the next read starts a little before it.
+    watermark_ms: Option<i64>,
+    last_full: Option<Instant>,
+    /// Newest first.
+    jobs: Vec<JobSummary>,
+}
+
+impl RecentJobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the jobs updated since the last call, or all of them on the
+    /// first call and every `RECENT_RESYNC`.
+    pub fn refresh(&mut self, store: &CheckpointStore) -> Result<()> {
+        let due = self
+            .last_full
+            .map_or(true, |t| t.elapsed() >= RECENT_RESYNC);
+        match self.watermark_ms {
+            Some(w) if !due => {
+                let rows = store.get_jobs_summary_since(w - LATE_WRITE_MS)?;
+                self.absorb(rows);
+            }
+            _ => {
+                let rows = store.get_jobs_summary()?;
+                self.jobs.clear();
+                self.watermark_ms = None;
+                self.absorb(rows);
+                self.last_full = Some(Instant::now());
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges changed rows in, replacing the older copies of those jobs.
+    pub fn absorb(&mut self, rows: Vec<JobSummary>) {
+        if rows.is_empty() {
+            return;
+        }
+        let changed: HashSet<String> = rows.iter().map(|r| r.id.clone()).collect();
+        self.jobs.retain(|j| !changed.contains(&j.id));
+        for r in &rows {
+            self.watermark_ms = Some(self.watermark_ms.unwrap_or(i64::MIN).max(r.updated_at));
+        }
+        self.jobs.extend(rows);
+        self.jobs.sort_by_key(|j| std::cmp::Reverse(j.updated_at));
+        self.jobs.truncate(RECENT_JOBS);
+    }
+
+    /// Newest first.
+    pub fn jobs(&self) -> &[JobSummary] {
+        &self.jobs
+    }
+}
+
+pub(crate) fn median(mut values: Vec<i64>) -> Option<i64> {
+    values.sort_unstable();
+    let n = values.len();
+    match n {
+        0 => None,
+        _ if n % 2 == 1 => Some(values[n / 2]),
+        _ => Some((values[n / 2 - 1] + values[n / 2]) / 2),
+    }
+}
+
+/// "850ms", "12.3s", "4.2m", "1.5h".
+pub fn human_ms(ms: i64) -> String {
+    let s = ms as f64 / 1000.0;
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if s < 60.0 {
+        format!("{:.1}s", s)
+    } else if s < 3600.0 {
+        format!("{:.1}m", s / 60.0)
+    } else {
+        format!("{:.1}h", s / 3600.0)
+    }
+}
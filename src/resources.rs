@@ -8,17 +8,23 @@
 //
 // Responsibilities:
 // 1. Detect Topology (Local vs Slurm vs PBS).
-// 2. Manage Resource Bitmasks (Track specific Core/GPU IDs).
+// 2. Manage Resource Bitmasks (Track specific Core/GPU IDs) and the memory
+//    promised to running jobs.
 // 3. Issue "Sandboxes" (Allocations) to jobs.
 // 4. Generate Isolation Env Vars (CUDA_VISIBLE_DEVICES, OMP_NUM_THREADS).
+// 5. Measure what jobs actually use (CPU time, peak RSS) for right-sizing.
+// 6. Say which requests no amount of waiting would fit (`never_fits`).
 //
 // TO DO :
 //  A) Expansıon towards edge case sandbox environments
 //  B) Improve on who leads the MPI ranks and OMP / MPI Hybrid workflow management
 
+use crate::core::{ResourceReq, ResourceUsage};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
-use sysinfo::{MemoryRefreshKind, RefreshKind, System};
+use std::time::{Duration, Instant};
+use sysinfo::{MemoryRefreshKind, Pid, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System};
 use tokio::process::Command;
 
 // ============================================================================
@@ -90,12 +96,16 @@ pub struct ResourceLedger {
 
     // Inventory Limits
     total_cores: usize,
+    /// Cores jobs may use (one may be kept for the OS).
+    usable_cores: usize,
     total_gpus: usize,
     total_mem_mb: u64,
 
     // Bitmasks (True = Busy)
     core_mask: Vec<bool>,
     gpu_mask: Vec<bool>,
+    /// Sum of the running sandboxes' `memory_mb_limit`.
+    allocated_mem_mb: u64,
 }
 
 impl ResourceLedger {
@@ -107,8 +117,30 @@ impl ResourceLedger {
             .map(|h| h.to_string_lossy().to_string())
             .unwrap_or_else(|_| "localhost".into());
 
+        let ledger = Self::with_capacity(ctype, hostname, cores, gpus, mem);
+        log::info!(
+            "Detected resources on {}: Type={:?}, Cores={} (Usable={}), GPUs={}, Mem={}MB",
+            ledger.hostname,
+            ctype,
+            cores,
+            ledger.usable_cores,
+            gpus,
+            mem
+        );
+        ledger
+    }
+
+    /// An idle ledger for a machine of this size, reserving a core as
+    /// `detect` does.
+    pub fn with_capacity(
+        cluster_type: ClusterType,
+        hostname: String,
+        cores: usize,
+        gpus: usize,
+        mem_mb: u64,
+    ) -> Self {
         // In Local mode, reserve 1 core for the OS/Guardian if we have plenty
-        let usable_cores = if ctype == ClusterType::Local && cores > 4 {
+        let usable_cores = if cluster_type == ClusterType::Local && cores > 4 {
             cores - 1
         } else {
             cores
@@ -122,30 +154,58 @@ impl ResourceLedger {
             core_mask[cores - 1] = true;
         }
 
-        log::info!(
-            "Detected resources on {}: Type={:?}, Cores={} (Usable={}), GPUs={}, Mem={}MB",
-            hostname,
-            ctype,
-            cores,
-            usable_cores,
-            gpus,
-            mem
-        );
-
         Self {
-            cluster_type: ctype,
+            cluster_type,
             hostname,
             total_cores: cores,
+            usable_cores,
             total_gpus: gpus,
-            total_mem_mb: mem,
+            total_mem_mb: mem_mb,
             core_mask,
             gpu_mask: vec![false; gpus],
+            allocated_mem_mb: 0,
         }
     }
 
-    /// Try to allocate a specific amount of resources.
+    /// Why `req` could not be placed here even with nothing else running;
+    /// None if it fits.
+    pub fn never_fits(&self, req: &ResourceReq) -> Option<String> {
+        let mut short = Vec::new();
+        if req.cores > self.usable_cores {
+            short.push(format!(
+                "{} core(s), this machine has {}",
+                req.cores, self.usable_cores
+            ));
+        }
+        if req.gpus > self.total_gpus {
+            short.push(format!(
+                "{} GPU(s), this machine has {}",
+                req.gpus, self.total_gpus
+            ));
+        }
+        if req.memory_mb > self.total_mem_mb {
+            short.push(format!(
+                "{} MB of memory, this machine has {}",
+                req.memory_mb, self.total_mem_mb
+            ));
+        }
+        (!short.is_empty()).then(|| format!("asks {}", short.join("; ")))
+    }
+
+    /// Try to allocate a specific amount of resources (`req_mem_mb` 0: no
+    /// memory requirement).
     /// Returns a Sandbox if successful, None if not enough resources.
-    pub fn try_allocate(&mut self, req_cores: usize, req_gpus: usize) -> Option<Sandbox> {
+    pub fn try_allocate(
+        &mut self,
+        req_cores: usize,
+        req_gpus: usize,
+        req_mem_mb: u64,
+    ) -> Option<Sandbox> {
+        // 0. Check Memory (what running jobs were promised, not what they use)
+        if req_mem_mb > self.free_mem_mb() {
+            return None;
+        }
+
         // 1. Check GPU Availability
         let free_gpus = self.find_free_indices(&self.gpu_mask, req_gpus);
         if free_gpus.len() < req_gpus {
@@ -165,11 +225,12 @@ impl ResourceLedger {
         for &idx in &free_cores {
             self.core_mask[idx] = true;
         }
+        self.allocated_mem_mb += req_mem_mb;
 
         Some(Sandbox {
             cores: free_cores,
             gpus: free_gpus,
-            memory_mb_limit: None,
+            memory_mb_limit: (req_mem_mb > 0).then_some(req_mem_mb as usize),
         })
     }
 
@@ -185,12 +246,22 @@ impl ResourceLedger {
                 self.core_mask[idx] = false;
             }
         }
+        let mem = sandbox.memory_mb_limit.unwrap_or(0) as u64;
+        self.allocated_mem_mb = self.allocated_mem_mb.saturating_sub(mem);
     }
 
     pub fn total_cores(&self) -> usize {
         self.total_cores
     }
 
+    pub fn total_gpus(&self) -> usize {
+        self.total_gpus
+    }
+
+    pub fn total_mem_mb(&self) -> u64 {
+        self.total_mem_mb
+    }
+
     // --- ACCESSORS FOR HEARTBEAT ---
 
     /// Returns the count of currently available CPU cores.
@@ -203,6 +274,11 @@ impl ResourceLedger {
         self.gpu_mask.iter().filter(|&&busy| !busy).count()
     }
 
+    /// Returns the memory not promised to a running job, in MB.
+    pub fn free_mem_mb(&self) -> u64 {
+        self.total_mem_mb.saturating_sub(self.allocated_mem_mb)
+    }
+
     /// Helper: Find N contiguous free indices if possible, or fragmented.
     fn find_free_indices(&self, mask: &[bool], count: usize) -> Vec<usize> {
         let mut indices = Vec::with_capacity(count);
@@ -304,3 +380,91 @@ impl SystemMonitor {
         ResourceLedger::detect()
     }
 }
+
+// ============================================================================
+// 5. USAGE SAMPLING (Actual vs Requested)
+// ============================================================================
+
+/// How often a running job's process tree is measured.
+pub const USAGE_SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Samples CPU time and resident memory of a process and all its descendants
+/// (mpirun -> ranks, shells -> binaries) until `finish` is called.
+pub struct UsageSampler {
+    stop: std::sync::mpsc::Sender<()>,
+    handle: tokio::task::JoinHandle<ResourceUsage>,
+    t0: Instant,
+}
+
+impl UsageSampler {
+    pub fn start(pid: u32, interval: Duration) -> Self {
+        let (stop, stopped) = std::sync::mpsc::channel();
+        let root = Pid::from_u32(pid);
+
+        // sysinfo refreshes are blocking /proc walks; keep them off the runtime
+        let handle = tokio::task::spawn_blocking(move || {
+            let mut sys = System::new();
+            let mut usage = ResourceUsage::default();
+            // Last CPU time seen per process: children that exit between
+            // samples still count up to their last reading
+            let mut cpu_ms: HashMap<Pid, u64> = HashMap::new();
+
+            loop {
+                sys.refresh_processes_specifics(
+                    ProcessesToUpdate::All,
+                    true,
+                    ProcessRefreshKind::nothing().with_cpu().with_memory(),
+                );
+                let mut rss = 0u64;
+                for (pid, proc_) in sys.processes() {
+                    if !descends_from(&sys, *pid, root) {
+                        continue;
+                    }
+                    rss += proc_.memory();
+                    cpu_ms.insert(*pid, proc_.accumulated_cpu_time());
+                }
+                usage.peak_rss_mb = usage.peak_rss_mb.max(rss as f64 / (1024.0 * 1024.0));
+                usage.samples += 1;
+
+                match stopped.recv_timeout(interval) {
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            }
+            usage.cpu_seconds = cpu_ms.values().sum::<u64>() as f64 / 1000.0;
+            usage
+        });
+
+        Self {
+            stop,
+            handle,
+            t0: Instant::now(),
+        }
+    }
+
+    /// Stops sampling. None if nothing could be measured (e.g. no /proc).
+    pub async fn finish(self) -> Option<ResourceUsage> {
+        let wall_seconds = self.t0.elapsed().as_secs_f64();
+        self.stop.send(()).ok();
+        let mut usage = self.handle.await.ok()?;
+        if usage.cpu_seconds == 0.0 && usage.peak_rss_mb == 0.0 {
+            return None;
+        }
+        usage.wall_seconds = wall_seconds;
+        Some(usage)
+    }
+}
+
+fn descends_from(sys: &System, mut pid: Pid, root: Pid) -> bool {
+    // Bounded walk: guards against parent cycles from recycled PIDs
+    for _ in 0..64 {
+        if pid == root {
+            return true;
+        }
+        match sys.process(pid).and_then(|p| p.parent()) {
+            Some(parent) => pid = parent,
+            None => return false,
+        }
+    }
+    false
+}
@@ -0,0 +1,320 @@
+// src/schema.rs
+//
+// =============================================================================
+// UNIFIEDLAB: SCHEMA EVOLUTION (v 0.1 )
+// =============================================================================
+//
+// Keeps old campaigns readable after `core` types change.
+//
+// Design:
+// - `Job`, `Structure` and `CalculationResult` carry a `schema_version`.
+// - A stored record without one predates versioning and counts as version 0.
+// - Upgraders work on raw JSON, one version step at a time, before the typed
+//   deserialize. Each type is upgraded on its own, so a Job can nest an older
+//   Structure.
+// - A record written by a newer build is refused rather than guessed at.
+// - Event log records carry an event schema version in their container.
+//   `upgrade_event` steps the payload up, then upgrades the Jobs and results
+//   it carries, so an old log still replays after a field change.
+
+use crate::core::{
+    Job, ResourceReq, JOB_SCHEMA_VERSION, RESULT_SCHEMA_VERSION, STRUCTURE_SCHEMA_VERSION,
+};
+use crate::eventlog::EVENT_SCHEMA_VERSION;
+use crate::marketplace::{
+    EV_GRAPH_EDIT, EV_JOB_COMPLETE, EV_JOB_SUBMIT, EV_WORK_GRANT, MSG_CONTROL, MSG_JOB_ARRAY,
+    MSG_JOB_COMPLETE, MSG_JOB_PROGRESS,
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+const VERSION_KEY: &str = "schema_version";
+
+/// One upgrade step: rewrites a record of version N into version N + 1.
+type Step = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// Indexed by the version being upgraded *from*.
+const JOB_STEPS: &[Step] = &[job_v0_to_v1];
+const STRUCTURE_STEPS: &[Step] = &[structure_v0_to_v1];
+const RESULT_STEPS: &[Step] = &[result_v0_to_v1];
+
+// ============================================================================
+// 1. ENTRY POINTS
+// ============================================================================
+
+/// How a stored Job came back.
+#[derive(Debug)]
+pub enum Restored {
+    /// Already at the current versions.
+    Current(Job),
+    /// Upgraded from an older layout; the caller should write it back.
+    Upgraded(Job),
+}
+
+/// Parses a stored Job, upgrading it (and anything nested) to the current schema.
+pub fn restore_job(json: &str) -> Result<Restored> {
+    let mut raw: Value = serde_json::from_str(json).context("Not valid JSON")?;
+    let upgraded = upgrade_job(&mut raw)?;
+    let job: Job = serde_json::from_value(raw).context("Does not match the Job schema")?;
+    Ok(if upgraded {
+        Restored::Upgraded(job)
+    } else {
+        Restored::Current(job)
+    })
+}
+
+/// Upgrades a raw Job record in place. Returns true if anything changed.
+pub fn upgrade_job(raw: &mut Value) -> Result<bool> {
+    let obj = as_object(raw, "Job")?;
+    let mut changed = upgrade(obj, "Job", JOB_SCHEMA_VERSION, JOB_STEPS)?;
+
+    if let Some(s) = obj.get_mut("structure") {
+        changed |= upgrade_structure(s)?;
+    }
+    if let Some(r) = obj.get_mut("result").filter(|r| !r.is_null()) {
+        changed |= upgrade_result(r)?;
+    }
+    Ok(changed)
+}
+
+pub fn upgrade_structure(raw: &mut Value) -> Result<bool> {
+    upgrade(
+        as_object(raw, "Structure")?,
+        "Structure",
+        STRUCTURE_SCHEMA_VERSION,
+        STRUCTURE_STEPS,
+    )
+}
+
+pub fn upgrade_result(raw: &mut Value) -> Result<bool> {
+    let obj = as_object(raw, "CalculationResult")?;
+    let mut changed = upgrade(
+        obj,
+        "CalculationResult",
+        RESULT_SCHEMA_VERSION,
+        RESULT_STEPS,
+    )?;
+
+    if let Some(s) = obj.get_mut("final_structure").filter(|s| !s.is_null()) {
+        changed |= upgrade_structure(s)?;
+    }
+    Ok(changed)
+}
+
+fn as_object<'a>(raw: &'a mut Value, what: &str) -> Result<&'a mut Map<String, Value>> {
+    raw.as_object_mut()
+        .ok_or_else(|| anyhow!("{} record is not a JSON object", what))
+}
+
+fn upgrade(obj: &mut Map<String, Value>, what: &str, current: u32, steps: &[Step]) -> Result<bool> {
+    let mut version = match obj.get(VERSION_KEY) {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .ok_or_else(|| anyhow!("{} has a non-numeric schema_version", what))?
+            as u32,
+    };
+
+    if version > current {
+        return Err(anyhow!(
+            "{} schema v{} was written by a newer UnifiedLAB (this build reads up to v{})",
+            what,
+            version,
+            current
+        ));
+    }
+    if version == current {
+        return Ok(false);
+    }
+
+    while version < current {
+        steps[version as usize](obj)
+            .with_context(|| format!("Upgrading {} from schema v{}", what, version))?;
+        version += 1;
+    }
+    obj.insert(VERSION_KEY.into(), version.into());
+    Ok(true)
+}
+
+// ============================================================================
+// 2. UPGRADE STEPS
+// ============================================================================
+//
+// v0 is everything written before versioning, including hand-written or
+// agent-generated JSON. Optional fields already default on their own; these
+// steps fill in the required ones those writers tended to leave out.
+
+fn job_v0_to_v1(obj: &mut Map<String, Value>) -> Result<()> {
+    // Timestamps: borrow one from the other, or stamp "now"
+    let stamp = obj
+        .get("updated_at")
+        .or_else(|| obj.get("created_at"))
+        .cloned()
+        .unwrap_or_else(|| Value::String(Utc::now().to_rfc3339()));
+    for key in ["created_at", "updated_at"] {
+        obj.entry(key).or_insert_with(|| stamp.clone());
+    }
+
+    obj.entry("status").or_insert_with(|| "Pending".into());
+
+    // Partial resource requests are merged over the defaults
+    let mut resources = serde_json::to_value(ResourceReq::default())?;
+    if let (Some(Value::Object(given)), Value::Object(merged)) =
+        (obj.get("resources"), &mut resources)
+    {
+        for (k, v) in given {
+            merged.insert(k.clone(), v.clone());
+        }
+    }
+    obj.insert("resources".into(), resources);
+
+    if let Some(Value::Object(config)) = obj.get_mut("config") {
+        config
+            .entry("params")
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    Ok(())
+}
+
+fn structure_v0_to_v1(obj: &mut Map<String, Value>) -> Result<()> {
+    obj.entry("atoms").or_insert_with(|| Value::Array(vec![]));
+    Ok(())
+}
+
+fn result_v0_to_v1(obj: &mut Map<String, Value>) -> Result<()> {
+    obj.entry("t_total_ms").or_insert_with(|| 0.0.into());
+
+    if let Some(Value::Object(prov)) = obj.get_mut("provenance") {
+        let now = Value::String(Utc::now().to_rfc3339());
+        let start = prov.get("start_time").cloned().unwrap_or(now);
+        prov.entry("start_time").or_insert_with(|| start.clone());
+        prov.entry("end_time").or_insert(start);
+        prov.entry("execution_host")
+            .or_insert_with(|| "unknown".into());
+        prov.entry("exit_code").or_insert_with(|| 0.into());
+        prov.entry("sandbox_info").or_insert_with(|| "".into());
+    }
+    Ok(())
+}
+
+// ============================================================================
+// 3. RESTORE REPORT
+// ============================================================================
+
+/// What happened to each stored record during a restore.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub loaded: usize,
+    pub upgraded: usize,
+    pub dropped: Vec<DroppedRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DroppedRecord {
+    pub id: String,
+    pub reason: String,
+}
+
+impl RestoreReport {
+    pub fn log(&self) {
+        if self.upgraded > 0 {
+            log::info!(
+                "🧬 Upgraded {} stored job(s) to the current schema",
+                self.upgraded
+            );
+        }
+        for d in &self.dropped {
+            log::warn!("Dropped stored job {}: {}", d.id, d.reason);
+        }
+        if !self.dropped.is_empty() {
+            log::warn!(
+                "⚠️ {} of {} stored job(s) could not be restored; their rows are left untouched",
+                self.dropped.len(),
+                self.loaded + self.dropped.len()
+            );
+        }
+    }
+}
+
+// ============================================================================
+// 4. EVENT PAYLOADS
+// ============================================================================
+
+/// One upgrade step of an event payload, given its record kind.
+type EventStep = fn(&str, &mut Value) -> Result<()>;
+
+/// Indexed by the event schema version being upgraded *from*.
+const EVENT_STEPS: &[EventStep] = &[event_v0_to_v1];
+
+/// Upgrades the payload of an event record written at `version`, then the
+/// Jobs and results inside it (they carry their own versions). Returns true
+/// if anything changed.
+pub fn upgrade_event(kind: &str, version: u32, payload: &mut Value) -> Result<bool> {
+    if version > EVENT_SCHEMA_VERSION {
+        return Err(anyhow!(
+            "'{}' event schema v{} was written by a newer UnifiedLAB (this build reads up to v{})",
+            kind,
+            version,
+            EVENT_SCHEMA_VERSION
+        ));
+    }
+    for from in version..EVENT_SCHEMA_VERSION {
+        EVENT_STEPS[from as usize](kind, payload)
+            .with_context(|| format!("Upgrading '{}' event from schema v{}", kind, from))?;
+    }
+    let nested = upgrade_event_records(kind, payload)
+        .with_context(|| format!("Upgrading records inside a '{}' event", kind))?;
+    Ok(version < EVENT_SCHEMA_VERSION || nested)
+}
+
+/// The Jobs and results a payload carries, by kind. Anything that is not an
+/// object is left for the typed deserialize to reject.
+fn upgrade_event_records(kind: &str, payload: &mut Value) -> Result<bool> {
+    let mut changed = false;
+    match kind {
+        EV_JOB_SUBMIT | EV_WORK_GRANT => {
+            if let Some(Value::Array(jobs)) = payload.get_mut("jobs") {
+                for job in jobs.iter_mut().filter(|j| j.is_object()) {
+                    changed |= upgrade_job(job)?;
+                }
+            }
+        }
+        MSG_JOB_ARRAY => {
+            if let Some(job) = payload.get_mut("template").filter(|j| j.is_object()) {
+                changed |= upgrade_job(job)?;
+            }
+        }
+        MSG_JOB_COMPLETE | EV_JOB_COMPLETE => {
+            if let Some(r) = payload.get_mut("result").filter(|r| r.is_object()) {
+                changed |= upgrade_result(r)?;
+            }
+        }
+        MSG_JOB_PROGRESS => {
+            if let Some(r) = payload.get_mut("snapshot").filter(|r| r.is_object()) {
+                changed |= upgrade_result(r)?;
+            }
+        }
+        // `control.command` wraps a ControlCommand; `graph.edit` is one
+        MSG_CONTROL | EV_GRAPH_EDIT => {
+            let command = match kind {
+                MSG_CONTROL => payload.get_mut("command"),
+                _ => Some(payload),
+            };
+            if let Some(job) = command
+                .and_then(|c| c.get_mut("job"))
+                .filter(|j| j.is_object())
+            {
+                changed |= upgrade_job(job)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(changed)
+}
+
+/// v1 only started recording the version; the payloads did not change.
+fn event_v0_to_v1(_kind: &str, _payload: &mut Value) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,97 @@
+// src/secrets.rs
+//
+// =============================================================================
+// UNIFIEDLAB: SECRET REFERENCES (v 0.1 )
+// =============================================================================
+//
+// The Vault Door.
+//
+// API tokens and license server addresses that engines and agents need,
+// kept out of everything the lab stores or sends:
+// 1. Jobs carry only references (`JobConfig::secrets`: env var name ->
+//    `SecretRef`), so events.log, checkpoint.db and grants never hold a value.
+// 2. The Guardian that runs a job resolves them as it starts the job's
+//    programs, from its own env or the OS keyring (feature "keyring").
+// 3. Values resolved in this process are masked in every log line, on stderr
+//    and in the TUI, and in the error a failed job reports (`redact`).
+// 4. Containers get them by name (`docker run -e NAME`), never as a
+//    command-line argument.
+
+use crate::core::SecretRef;
+use anyhow::{anyhow, Result};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tokio::process::Command;
+
+/// Keyring service that `keyring` references are looked up under.
+pub const KEYRING_SERVICE: &str = "unifiedlab";
+
+/// What a masked value is replaced with.
+pub const REDACTED: &str = "[redacted]";
+
+/// Shorter values are not masked; they would mangle ordinary text.
+const MIN_REDACTED_LEN: usize = 4;
+
+/// Every value resolved in this process, longest first.
+static RESOLVED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Resolves `refs` and sets them as env vars of `cmd`. The error for a
+/// missing secret names it, never a value.
+pub fn apply(cmd: &mut Command, refs: &BTreeMap<String, SecretRef>) -> Result<()> {
+    for (var, source) in refs {
+        let value = resolve(source).map_err(|e| anyhow!("secret {}: {}", var, e))?;
+        cmd.env(var, value);
+    }
+    Ok(())
+}
+
+/// The value of one reference on this host, remembered for [`redact`].
+pub fn resolve(source: &SecretRef) -> Result<String> {
+    let value = match source {
+        SecretRef::Env(name) => {
+            std::env::var(name).map_err(|_| anyhow!("env var {} is not set on this host", name))?
+        }
+        SecretRef::Keyring(name) => from_keyring(name)?,
+    };
+    if value.len() >= MIN_REDACTED_LEN {
+        if let Ok(mut known) = RESOLVED.lock() {
+            if !known.contains(&value) {
+                known.push(value.clone());
+                // A value inside a longer one must not unmask part of it
+                known.sort_by_key(|v| std::cmp::Reverse(v.len()));
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// `text` with every value resolved in this process replaced by [`REDACTED`].
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let Ok(known) = RESOLVED.lock() else {
+        return Cow::Borrowed(text);
+    };
+    let mut out = Cow::Borrowed(text);
+    for value in known.iter() {
+        if out.contains(value.as_str()) {
+            out = Cow::Owned(out.replace(value.as_str(), REDACTED));
+        }
+    }
+    out
+}
+
+#[cfg(feature = "keyring")]
+fn from_keyring(name: &str) -> Result<String> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .and_then(|entry| entry.get_password())
+        .map_err(|e| anyhow!("keyring entry {}/{}: {}", KEYRING_SERVICE, name, e))
+}
+
+#[cfg(not(feature = "keyring"))]
+fn from_keyring(name: &str) -> Result<String> {
+    Err(anyhow!(
+        "keyring entry {}/{} cannot be read: this build has no keyring support (feature \"keyring\")",
+        KEYRING_SERVICE,
+        name
+    ))
+}
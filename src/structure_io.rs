@@ -0,0 +1,576 @@
+// src/structure_io.rs
+//
+// =============================================================================
+// UNIFIEDLAB: STRUCTURE FILES (v 0.1 )
+// =============================================================================
+//
+// The Loader.
+//
+// Responsibilities:
+// 1. Read the structure files groups already keep (CIF, VASP POSCAR/CONTCAR,
+//    XYZ and extended XYZ) into the canonical `core::Structure`, so a
+//    workflow can start from atoms without a Python pre-step.
+// 2. Tell the format from a file's name (`StructureFormat::from_path`).
+//
+// Positions come out Cartesian, in Angstroms. CIF sites are expanded by the
+// file's symmetry operations; occupancies, charges and other per-site data
+// are not read. Only the first data block of a CIF is used.
+
+use crate::core::{Atom, Lattice, Structure};
+use anyhow::{anyhow, bail, Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Fractional distance below which two CIF sites are the same site.
+const SITE_TOLERANCE: f64 = 1e-3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StructureFormat {
+    /// Crystallographic Information File.
+    Cif,
+    /// VASP 5 POSCAR or CONTCAR, with a species line.
+    Poscar,
+    /// XYZ; a `Lattice="..."` comment (extended XYZ) makes it periodic.
+    Xyz,
+}
+
+impl StructureFormat {
+    /// The format a file's name implies: `.cif`, `.xyz` / `.extxyz`, or
+    /// `.vasp` / `.poscar` and names starting with POSCAR or CONTCAR.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        match ext.as_deref() {
+            Some("cif") => Some(Self::Cif),
+            Some("xyz" | "extxyz") => Some(Self::Xyz),
+            Some("vasp" | "poscar") => Some(Self::Poscar),
+            _ if name.starts_with("poscar") || name.starts_with("contcar") => Some(Self::Poscar),
+            _ => None,
+        }
+    }
+}
+
+/// Read the structure in `path`, in `format` or the one its name implies.
+/// The structure's `source` is the path.
+pub fn read(path: &Path, format: Option<StructureFormat>) -> Result<Structure> {
+    let format = format
+        .or_else(|| StructureFormat::from_path(path))
+        .ok_or_else(|| anyhow!("cannot tell the format of {}", path.display()))?;
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let mut structure = parse(&text, format)?;
+    structure.source = path.display().to_string();
+    Ok(structure)
+}
+
+/// Parse a structure from the text of a file in `format`.
+pub fn parse(text: &str, format: StructureFormat) -> Result<Structure> {
+    let (atoms, lattice) = match format {
+        StructureFormat::Cif => parse_cif(text)?,
+        StructureFormat::Poscar => parse_poscar(text)?,
+        StructureFormat::Xyz => parse_xyz(text)?,
+    };
+    if atoms.is_empty() {
+        bail!("the file holds no atoms");
+    }
+    Ok(Structure::new(atoms, lattice, String::new()))
+}
+
+// ============================================================================
+// 1. XYZ
+// ============================================================================
+
+fn parse_xyz(text: &str) -> Result<(Vec<Atom>, Option<Lattice>)> {
+    let mut lines = text.lines();
+    let n: usize = lines
+        .next()
+        .and_then(|l| l.trim().parse().ok())
+        .ok_or_else(|| anyhow!("line 1: expected the number of atoms"))?;
+    let comment = lines.next().unwrap_or_default();
+
+    let mut atoms = Vec::with_capacity(n);
+    for (k, line) in lines.take(n).enumerate() {
+        let f: Vec<&str> = line.split_whitespace().collect();
+        if f.len() < 4 {
+            bail!("line {}: expected a symbol and x y z", k + 3);
+        }
+        let position = [number(f[1])?, number(f[2])?, number(f[3])?];
+        atoms.push(atom(f[0], position));
+    }
+    if atoms.len() != n {
+        bail!("expected {} atoms, found {}", n, atoms.len());
+    }
+
+    let lattice = match extxyz_value(comment, "Lattice") {
+        Some(values) => {
+            let v = values
+                .split_whitespace()
+                .map(number)
+                .collect::<Result<Vec<_>>>()?;
+            if v.len() != 9 {
+                bail!("line 2: Lattice needs 9 numbers, found {}", v.len());
+            }
+            let pbc = match extxyz_value(comment, "pbc") {
+                Some(flags) => {
+                    let flags: Vec<bool> = flags
+                        .split_whitespace()
+                        .map(|f| matches!(f, "T" | "t" | "True" | "true" | "1"))
+                        .collect();
+                    if flags.len() != 3 {
+                        bail!("line 2: pbc needs 3 flags");
+                    }
+                    [flags[0], flags[1], flags[2]]
+                }
+                None => [true; 3],
+            };
+            Some(Lattice {
+                vectors: [[v[0], v[1], v[2]], [v[3], v[4], v[5]], [v[6], v[7], v[8]]],
+                pbc,
+            })
+        }
+        None => None,
+    };
+    Ok((atoms, lattice))
+}
+
+/// The value of `key="..."` (or `key=value`) in an extended XYZ comment.
+fn extxyz_value<'a>(comment: &'a str, key: &str) -> Option<&'a str> {
+    let start = comment
+        .match_indices(&format!("{}=", key))
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || comment[..i].ends_with(char::is_whitespace))?
+        + key.len()
+        + 1;
+    let rest = &comment[start..];
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next(),
+        None => rest.split_whitespace().next(),
+    }
+}
+
+// ============================================================================
+// 2. POSCAR
+// ============================================================================
+
+fn parse_poscar(text: &str) -> Result<(Vec<Atom>, Option<Lattice>)> {
+    let lines: Vec<&str> = text.lines().collect();
+    let line = |i: usize| {
+        lines
+            .get(i)
+            .copied()
+            .ok_or_else(|| anyhow!("line {}: unexpected end of file", i + 1))
+    };
+
+    let scale: Vec<f64> = line(1)?
+        .split_whitespace()
+        .map(number)
+        .collect::<Result<_>>()
+        .context("line 2: bad scaling factor")?;
+    let mut vectors = [[0.0; 3]; 3];
+    for (i, v) in vectors.iter_mut().enumerate() {
+        *v = triple(line(2 + i)?).with_context(|| format!("line {}", 3 + i))?;
+    }
+    let scale = match scale.as_slice() {
+        [s] if *s < 0.0 => {
+            // A negative factor is the cell volume to scale to
+            let volume = Lattice {
+                vectors,
+                pbc: [true; 3],
+            }
+            .volume();
+            [(-s / volume).cbrt(); 3]
+        }
+        [s] => [*s; 3],
+        [a, b, c] => [*a, *b, *c],
+        _ => bail!("line 2: expected 1 or 3 scaling factors"),
+    };
+    for v in vectors.iter_mut() {
+        for (x, s) in v.iter_mut().zip(scale) {
+            *x *= s;
+        }
+    }
+
+    let species: Vec<&str> = line(5)?.split_whitespace().collect();
+    if species.iter().all(|s| s.parse::<usize>().is_ok()) {
+        bail!("line 6: no species line (VASP 4 format); add one naming the elements");
+    }
+    let counts = line(6)?
+        .split_whitespace()
+        .map(|c| c.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .context("line 7: bad atom counts")?;
+    if counts.len() != species.len() {
+        bail!(
+            "line 7: {} counts for {} species",
+            counts.len(),
+            species.len()
+        );
+    }
+
+    let mut next = 7;
+    if line(next)?.trim_start().starts_with(['S', 's']) {
+        next += 1; // Selective dynamics
+    }
+    let cartesian = line(next)?.trim_start().starts_with(['C', 'c', 'K', 'k']);
+    next += 1;
+
+    let mut atoms = Vec::new();
+    for (symbol, count) in species.iter().zip(counts) {
+        // VASP 6 may write `Fe_pv/3c1f...`; the element comes first
+        let symbol = symbol.split(['_', '/']).next().unwrap_or(symbol);
+        for _ in 0..count {
+            let p = triple(line(next)?).with_context(|| format!("line {}", next + 1))?;
+            let position = if cartesian {
+                [p[0] * scale[0], p[1] * scale[1], p[2] * scale[2]]
+            } else {
+                to_cartesian(p, &vectors)
+            };
+            atoms.push(atom(symbol, position));
+            next += 1;
+        }
+    }
+    Ok((
+        atoms,
+        Some(Lattice {
+            vectors,
+            pbc: [true; 3],
+        }),
+    ))
+}
+
+// ============================================================================
+// 3. CIF
+// ============================================================================
+
+/// Tags and loops of one CIF data block, tags lowercased.
+#[derive(Default)]
+struct CifBlock {
+    items: HashMap<String, String>,
+    loops: Vec<(Vec<String>, Vec<String>)>,
+}
+
+impl CifBlock {
+    /// The columns of the loop holding `tag`, by tag, if any loop does.
+    fn table(&self, tag: &str) -> Option<HashMap<&str, Vec<&str>>> {
+        let (tags, values) = self
+            .loops
+            .iter()
+            .find(|(t, _)| t.iter().any(|t| t == tag))?;
+        let mut columns: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (i, value) in values.iter().enumerate() {
+            columns
+                .entry(tags[i % tags.len()].as_str())
+                .or_default()
+                .push(value);
+        }
+        Some(columns)
+    }
+
+    fn number(&self, tag: &str) -> Result<f64> {
+        let value = self
+            .items
+            .get(tag)
+            .ok_or_else(|| anyhow!("no {} in the CIF", tag))?;
+        cif_number(value).with_context(|| tag.to_string())
+    }
+}
+
+fn parse_cif(text: &str) -> Result<(Vec<Atom>, Option<Lattice>)> {
+    let block = cif_block(text)?;
+
+    let [a, b, c] = [
+        block.number("_cell_length_a")?,
+        block.number("_cell_length_b")?,
+        block.number("_cell_length_c")?,
+    ];
+    let [alpha, beta, gamma] = [
+        block.number("_cell_angle_alpha")?.to_radians(),
+        block.number("_cell_angle_beta")?.to_radians(),
+        block.number("_cell_angle_gamma")?.to_radians(),
+    ];
+    // a along x, b in the xy plane
+    let cy = (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+    let vectors = [
+        [a, 0.0, 0.0],
+        [b * gamma.cos(), b * gamma.sin(), 0.0],
+        [
+            c * beta.cos(),
+            c * cy,
+            c * (1.0 - beta.cos().powi(2) - cy.powi(2)).max(0.0).sqrt(),
+        ],
+    ];
+
+    let sites = block
+        .table("_atom_site_fract_x")
+        .ok_or_else(|| anyhow!("no _atom_site_fract_x loop in the CIF"))?;
+    let column = |tag: &str| {
+        sites
+            .get(tag)
+            .ok_or_else(|| anyhow!("no {} in the CIF", tag))
+    };
+    let (xs, ys, zs) = (
+        column("_atom_site_fract_x")?,
+        column("_atom_site_fract_y")?,
+        column("_atom_site_fract_z")?,
+    );
+    let symbols = column("_atom_site_type_symbol").or_else(|_| column("_atom_site_label"))?;
+
+    let ops = [
+        "_space_group_symop_operation_xyz",
+        "_symmetry_equiv_pos_as_xyz",
+    ]
+    .iter()
+    .find_map(|tag| {
+        block
+            .table(tag)
+            .and_then(|t| t.get(tag).cloned())
+            .or_else(|| block.items.get(*tag).map(|op| vec![op.as_str()]))
+    })
+    .unwrap_or_else(|| vec!["x,y,z"])
+    .into_iter()
+    .map(|op| symop(op).with_context(|| format!("symmetry operation '{}'", op)))
+    .collect::<Result<Vec<_>>>()?;
+
+    let mut placed: Vec<(String, [f64; 3])> = Vec::new();
+    for (i, symbol) in symbols.iter().enumerate() {
+        let site = [cif_number(xs[i])?, cif_number(ys[i])?, cif_number(zs[i])?];
+        let symbol = element(symbol).ok_or_else(|| anyhow!("site {}: no element", symbol))?;
+        for op in &ops {
+            let mut f = [0.0; 3];
+            for (k, row) in op.iter().enumerate() {
+                let x = row[0] * site[0] + row[1] * site[1] + row[2] * site[2] + row[3];
+                f[k] = x - x.floor();
+            }
+            let seen = placed.iter().any(|(_, g)| {
+                (0..3).all(|k| {
+                    let d = f[k] - g[k];
+                    (d - d.round()).abs() < SITE_TOLERANCE
+                })
+            });
+            if !seen {
+                placed.push((symbol.clone(), f));
+            }
+        }
+    }
+
+    let atoms = placed
+        .into_iter()
+        .map(|(symbol, f)| atom(&symbol, to_cartesian(f, &vectors)))
+        .collect();
+    Ok((
+        atoms,
+        Some(Lattice {
+            vectors,
+            pbc: [true; 3],
+        }),
+    ))
+}
+
+/// The first data block of a CIF.
+fn cif_block(text: &str) -> Result<CifBlock> {
+    let mut block = CifBlock::default();
+    let mut tokens = cif_tokens(text).into_iter().peekable();
+    let is_tag = |t: &(String, bool)| !t.1 && t.0.starts_with('_');
+    let is_keyword = |t: &(String, bool)| {
+        let word = t.0.to_lowercase();
+        !t.1 && (word == "loop_" || word.starts_with("data_"))
+    };
+
+    let mut in_block = false;
+    while let Some(token) = tokens.next() {
+        let word = token.0.to_lowercase();
+        if !token.1 && word.starts_with("data_") {
+            if in_block {
+                break;
+            }
+            in_block = true;
+        } else if !token.1 && word == "loop_" {
+            let mut tags = Vec::new();
+            while let Some(tag) = tokens.next_if(is_tag) {
+                tags.push(tag.0.to_lowercase());
+            }
+            let mut values = Vec::new();
+            while let Some(value) = tokens.next_if(|t| !is_tag(t) && !is_keyword(t)) {
+                values.push(value.0);
+            }
+            if !tags.is_empty() {
+                // A row cut short would misalign every column after it
+                if values.len() % tags.len() != 0 {
+                    bail!(
+                        "loop of {} has {} values, not a multiple of its {} tags",
+                        tags[0],
+                        values.len(),
+                        tags.len()
+                    );
+                }
+                block.loops.push((tags, values));
+            }
+        } else if is_tag(&token) {
+            let value = tokens
+                .next_if(|t| !is_tag(t) && !is_keyword(t))
+                .map(|t| t.0)
+                .ok_or_else(|| anyhow!("{} has no value", token.0))?;
+            block.items.insert(word, value);
+        }
+    }
+    if !in_block {
+        bail!("no data_ block in the CIF");
+    }
+    Ok(block)
+}
+
+/// The words of a CIF, with whether each was quoted. Text fields (between
+/// lines starting with `;`) are one word.
+fn cif_tokens(text: &str) -> Vec<(String, bool)> {
+    let mut tokens = Vec::new();
+    let mut field: Option<String> = None;
+    for line in text.lines() {
+        if let Some(body) = field.as_mut() {
+            if line.starts_with(';') {
+                tokens.push((std::mem::take(body), true));
+                field = None;
+            } else {
+                body.push_str(line);
+                body.push('\n');
+            }
+            continue;
+        }
+        if let Some(first) = line.strip_prefix(';') {
+            field = Some(format!("{}\n", first));
+            continue;
+        }
+
+        let mut chars = line.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == '#' {
+                break;
+            } else if c == '\'' || c == '"' {
+                chars.next();
+                let mut word = String::new();
+                // A quote ends a value only where whitespace follows it
+                while let Some(q) = chars.next() {
+                    if q == c && chars.peek().map_or(true, |n| n.is_whitespace()) {
+                        break;
+                    }
+                    word.push(q);
+                }
+                tokens.push((word, true));
+            } else {
+                let mut word = String::new();
+                while let Some(&w) = chars.peek() {
+                    if w.is_whitespace() {
+                        break;
+                    }
+                    word.push(w);
+                    chars.next();
+                }
+                tokens.push((word, false));
+            }
+        }
+    }
+    tokens
+}
+
+/// A CIF number, without its standard uncertainty (`5.431(2)`).
+fn cif_number(value: &str) -> Result<f64> {
+    number(value.split('(').next().unwrap_or(value))
+}
+
+/// A symmetry operation such as `-x+1/2, y, z+1/2`, as one row per
+/// coordinate of coefficients of x, y and z and a translation.
+fn symop(op: &str) -> Result<[[f64; 4]; 3]> {
+    let parts: Vec<&str> = op.split(',').collect();
+    if parts.len() != 3 {
+        bail!("expected 3 comma-separated parts");
+    }
+    let mut rows = [[0.0; 4]; 3];
+    for (row, part) in rows.iter_mut().zip(parts) {
+        let mut sign = 1.0;
+        let mut digits = String::new();
+        let flush = |digits: &mut String, sign: f64, row: &mut [f64; 4]| -> Result<()> {
+            if !digits.is_empty() {
+                row[3] += sign * fraction(digits)?;
+                digits.clear();
+            }
+            Ok(())
+        };
+        for c in part.chars().filter(|c| !c.is_whitespace()) {
+            match c.to_ascii_lowercase() {
+                '+' | '-' => {
+                    flush(&mut digits, sign, row)?;
+                    sign = if c == '-' { -1.0 } else { 1.0 };
+                }
+                v @ ('x' | 'y' | 'z') => {
+                    let coefficient = match digits.trim_end_matches('*') {
+                        "" => 1.0,
+                        d => fraction(d)?,
+                    };
+                    digits.clear();
+                    row[(v as u8 - b'x') as usize] += sign * coefficient;
+                    sign = 1.0;
+                }
+                d if d.is_ascii_digit() || d == '.' || d == '/' || d == '*' => digits.push(d),
+                other => bail!("unexpected '{}'", other),
+            }
+        }
+        flush(&mut digits, sign, row)?;
+    }
+    Ok(rows)
+}
+
+fn fraction(s: &str) -> Result<f64> {
+    match s.split_once('/') {
+        Some((n, d)) => Ok(number(n)? / number(d)?),
+        None => number(s),
+    }
+}
+
+/// The element a CIF type symbol or label names: `Fe` for `Fe2+`, `FE1`.
+fn element(label: &str) -> Option<String> {
+    let mut letters = label.chars().take_while(char::is_ascii_alphabetic);
+    let mut symbol = letters.next()?.to_ascii_uppercase().to_string();
+    if let Some(second) = letters.next() {
+        symbol.push(second.to_ascii_lowercase());
+    }
+    Some(symbol)
+}
+
+// ============================================================================
+// 4. SHARED
+// ============================================================================
+
+fn atom(symbol: &str, position: [f64; 3]) -> Atom {
+    Atom {
+        symbol: symbol.to_string(),
+        position,
+        charge: None,
+        magnetic_moment: None,
+        tags: HashMap::new(),
+    }
+}
+
+fn number(s: &str) -> Result<f64> {
+    s.parse::<f64>()
+        .map_err(|_| anyhow!("'{}' is not a number", s))
+}
+
+fn triple(line: &str) -> Result<[f64; 3]> {
+    let f: Vec<&str> = line.split_whitespace().collect();
+    if f.len() < 3 {
+        bail!("expected 3 numbers");
+    }
+    Ok([number(f[0])?, number(f[1])?, number(f[2])?])
+}
+
+fn to_cartesian(f: [f64; 3], vectors: &[[f64; 3]; 3]) -> [f64; 3] {
+    let mut p = [0.0; 3];
+    for (k, x) in p.iter_mut().enumerate() {
+        *x = f[0] * vectors[0][k] + f[1] * vectors[1][k] + f[2] * vectors[2][k];
+    }
+    p
+}
@@ -5,23 +5,170 @@
 // Changes:
 // - Added file metadata checks to confirm data availability.
 // - Added verbose trace logging for the read loop.
+// - TransportFactory: backend chosen by config/CLI instead of hard-wired.
+// - Per-worker outboxes: grants go to `root/outbox/worker_<id>.log`, so a
+//   worker no longer parses every other worker's grants in events.log.
+// - Inbox rotation: a worker rolls its inbox to `worker_<id>.log.1` past a
+//   size threshold, once the Coordinator has drained the previous segment.
+//   The Coordinator follows the roll, then compacts the drained segment into
+//   `inbox/archive/` without heartbeats.
+// - Local mode: a Unix socket transport (uds.rs) replaces file polling when
+//   everything runs on one machine.
+// - MemTransport (mem.rs): coordinator and workers in one process, for tests
+//   and embedding.
+// - TransportStats: every backend counts what passed through it and how far
+//   its readers trail the writers, so lagging workers show up in the TUI.
+// - Idempotency keys: `send_to_coordinator_with_id` stamps a message with an
+//   `event_id`; the Coordinator drops repeats, so a worker may resend it.
 
-use crate::eventlog::{EventEnvelope, EventLogConfig, EventLogReader, EventLogWriter};
-use anyhow::{anyhow, Result};
+use crate::eventlog::{
+    Compression, EventEnvelope, EventLogConfig, EventLogReader, EventLogWriter, FsyncPolicy,
+};
+use crate::marketplace::{MSG_WORK_REQUEST, WORKER_MESSAGE_KINDS};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 use tokio::fs;
+use uuid::Uuid;
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+// In-process only: tests, embedding and `run --local`
+#[allow(dead_code)]
+pub mod mem;
+#[cfg(unix)]
+pub mod uds;
 
 #[async_trait]
 pub trait Transport: Send + Sync {
     async fn send_to_coordinator(&mut self, kind: &str, payload: Value) -> Result<()>;
+
+    /// Like `send_to_coordinator`, stamped with an idempotency key: the
+    /// Coordinator applies the message once however often it is sent.
+    /// Default: the key is dropped and repeats are applied again.
+    async fn send_to_coordinator_with_id(
+        &mut self,
+        kind: &str,
+        payload: Value,
+        event_id: Uuid,
+    ) -> Result<()> {
+        let _ = event_id;
+        self.send_to_coordinator(kind, payload).await
+    }
+
     async fn broadcast(&mut self, kind: &str, payload: Value) -> Result<u64>;
+
+    /// Several broadcasts in order, returning their offsets. Log-backed
+    /// transports write them with one flush/fsync.
+    async fn broadcast_batch(&mut self, records: Vec<(&str, Value)>) -> Result<Vec<u64>> {
+        let mut offsets = Vec::with_capacity(records.len());
+        for (kind, payload) in records {
+            offsets.push(self.broadcast(kind, payload).await?);
+        }
+        Ok(offsets)
+    }
+
+    /// Point-to-point delivery to one worker (e.g. WorkGrants).
+    /// Default: an addressed broadcast that workers filter by `worker_id`.
+    async fn send_to_worker(&mut self, worker_id: &str, kind: &str, payload: Value) -> Result<u64> {
+        let _ = worker_id;
+        self.broadcast(kind, payload).await
+    }
     async fn recv_broadcasts(&mut self) -> Result<Vec<EventEnvelope>>;
     async fn recv_worker_messages(&mut self) -> Result<Vec<EventEnvelope>>;
     async fn seek(&mut self, offset: u64) -> Result<()>;
+
+    /// Whether every participant sees the same `root` directory.
+    /// CAS offloading of large structures (wire.rs) depends on it.
+    fn shares_filesystem(&self) -> bool {
+        true
+    }
+
+    /// Waits up to `max` for incoming messages. Polling transports just sleep.
+    async fn wait_for_traffic(&mut self, max: Duration) {
+        tokio::time::sleep(max).await;
+    }
+
+    /// Traffic counters and read lag of this endpoint.
+    fn stats(&self) -> TransportStats {
+        TransportStats::default()
+    }
+}
+
+/// Read lag (bytes of broadcast log) past which a worker counts as falling behind.
+pub const READ_LAG_WARN_BYTES: u64 = 4 * 1024 * 1024;
+
+/// What passed through one endpoint since it was opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransportStats {
+    pub events_written: u64,
+    pub bytes_written: u64,
+    pub events_read: u64,
+    pub bytes_read: u64,
+    /// Writer end minus reader cursor over the logs this endpoint reads, in
+    /// log offsets (bytes; records for MemTransport). Workers: the broadcast
+    /// log. Coordinator: the worker inboxes. None when the log is remote.
+    pub read_lag: Option<u64>,
+    /// Messages already received but not yet handed out (socket, gRPC and
+    /// in-memory queues).
+    pub backlog: Option<u64>,
+    /// Corrupt log bytes the readers skipped over.
+    #[serde(default)]
+    pub skipped_bytes: u64,
+}
+
+impl TransportStats {
+    pub(crate) fn wrote(&mut self, bytes: u64) {
+        self.events_written += 1;
+        self.bytes_written += bytes;
+    }
+
+    /// Log-backed envelopes count their size on disk, the rest their JSON.
+    pub(crate) fn read(&mut self, events: &[EventEnvelope]) {
+        for env in events {
+            self.events_read += 1;
+            self.bytes_read += match env.next_offset.checked_sub(env.offset) {
+                Some(n) if n > 0 => n,
+                _ => payload_bytes(&env.record.payload),
+            };
+        }
+    }
+
+    /// Takes the damage `reader` skipped since the last call.
+    pub(crate) fn skipped(&mut self, reader: &mut EventLogReader) {
+        for c in reader.take_corruptions() {
+            self.skipped_bytes += c.skipped_bytes;
+        }
+    }
+}
+
+/// Encoded JSON size of a payload, without building the string.
+pub(crate) fn payload_bytes(payload: &Value) -> u64 {
+    struct Count(u64);
+    impl std::io::Write for Count {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len() as u64;
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut c = Count(0);
+    let _ = serde_json::to_writer(&mut c, payload);
+    c.0
+}
+
+/// Bytes of a plain (unsegmented) log not yet read by `reader`.
+fn unread_bytes(reader: &EventLogReader) -> u64 {
+    std::fs::metadata(reader.path())
+        .map(|m| m.len().saturating_sub(reader.cursor()))
+        .unwrap_or(0)
 }
 
 pub struct FileTransport {
@@ -31,6 +178,42 @@ pub struct FileTransport {
     global_reader: Option<EventLogReader>,
     inbox_readers: HashMap<String, EventLogReader>,
     next_discovery: Instant,
+    outbox_writers: HashMap<String, EventLogWriter>, // Coordinator: one per worker
+    outbox_path: Option<PathBuf>,                    // Worker: our own grants
+    outbox_reader: Option<EventLogReader>,           // Opened once the Coordinator creates it
+    log_cfg: EventLogConfig,
+    rotate_bytes: u64,         // Worker: roll the inbox past this size (0 = never)
+    draining: HashSet<String>, // Coordinator: inboxes still being read from their `.1`
+    inbox_kinds: Option<&'static [&'static str]>, // Coordinator: kinds worth parsing
+    stats: TransportStats,
+}
+
+/// Default events.log segment size for the Coordinator.
+pub const DEFAULT_EVENT_SEGMENT_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Default inbox size before a worker rolls it over.
+/// Heartbeats alone add a few MB per worker per day.
+pub const DEFAULT_INBOX_ROTATE_BYTES: u64 = 8 * 1024 * 1024;
+
+// Records compaction leaves out of the inbox archive: only the latest
+// heartbeat ever matters, and the Coordinator has already seen it.
+const COMPACT_DROP: &[&str] = &[MSG_WORK_REQUEST];
+
+/// `worker_X.log` -> `worker_X.log.1`
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".1");
+    PathBuf::from(name)
+}
+
+fn next_inbox_record(
+    reader: &mut EventLogReader,
+    kinds: Option<&[&str]>,
+) -> Result<Option<EventEnvelope>> {
+    match kinds {
+        Some(kinds) => reader.next_matching(kinds),
+        None => reader.next(),
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -44,25 +227,50 @@ impl FileTransport {
         root_path: impl AsRef<Path>,
         role: Role,
         worker_id: Option<&str>,
+    ) -> Result<Self> {
+        let log_cfg = EventLogConfig {
+            fsync: FsyncPolicy::Always,
+            ..Default::default()
+        };
+        Self::with_log_config(root_path, role, worker_id, log_cfg).await
+    }
+
+    /// Like `new`, but with explicit writer settings (e.g. compression).
+    /// `segment_bytes` and `index` apply to events.log only; inboxes rotate instead.
+    pub async fn with_log_config(
+        root_path: impl AsRef<Path>,
+        role: Role,
+        worker_id: Option<&str>,
+        log_cfg: EventLogConfig,
     ) -> Result<Self> {
         let root = root_path.as_ref().to_path_buf();
         let inbox_dir = root.join("inbox");
         fs::create_dir_all(&inbox_dir).await?;
 
-        let (writer, global_reader) = match role {
+        let outbox_dir = root.join("outbox");
+        fs::create_dir_all(&outbox_dir).await?;
+
+        let events_cfg = log_cfg.clone();
+        let log_cfg = EventLogConfig {
+            segment_bytes: None,
+            index: false,
+            ..log_cfg
+        };
+
+        let (writer, global_reader, outbox_path) = match role {
             Role::Coordinator => {
-                let w =
-                    EventLogWriter::open(root.join("events.log"), EventLogConfig { fsync: true })?;
-                (w, None)
+                let w = EventLogWriter::open(root.join("events.log"), events_cfg)?;
+                (w, None, None)
             }
             Role::Worker => {
                 let wid = worker_id.ok_or_else(|| anyhow!("Worker role requires worker_id"))?;
                 let w = EventLogWriter::open(
                     inbox_dir.join(format!("worker_{}.log", wid)),
-                    EventLogConfig { fsync: true },
+                    log_cfg.clone(),
                 )?;
                 let r = EventLogReader::open(root.join("events.log"))?;
-                (w, Some(r))
+                let o = outbox_dir.join(format!("worker_{}.log", wid));
+                (w, Some(r), Some(o))
             }
         };
 
@@ -73,8 +281,112 @@ impl FileTransport {
             global_reader,
             inbox_readers: HashMap::new(),
             next_discovery: Instant::now(),
+            outbox_writers: HashMap::new(),
+            outbox_path,
+            outbox_reader: None,
+            log_cfg,
+            rotate_bytes: DEFAULT_INBOX_ROTATE_BYTES,
+            draining: HashSet::new(),
+            inbox_kinds: None,
+            stats: TransportStats::default(),
         })
     }
+
+    /// Inbox size at which a worker rolls it over (0 disables rotation).
+    pub fn with_inbox_rotation(mut self, bytes: u64) -> Self {
+        self.rotate_bytes = bytes;
+        self
+    }
+
+    /// Coordinator side: only hand out inbox records of these kinds; the
+    /// rest are passed over without parsing their payload.
+    pub fn with_inbox_kinds(mut self, kinds: &'static [&'static str]) -> Self {
+        self.inbox_kinds = Some(kinds);
+        self
+    }
+
+    /// Worker side: roll the inbox once it is past the threshold.
+    /// Waits while the previous segment exists, i.e. until the Coordinator
+    /// has read it to the end, so no unread record is ever moved twice.
+    fn maybe_rotate_inbox(&mut self) -> Result<()> {
+        if self.rotate_bytes == 0 {
+            return Ok(());
+        }
+        let path = self.my_writer.path().to_path_buf();
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size < self.rotate_bytes {
+            return Ok(());
+        }
+        let segment = rotated_path(&path);
+        if segment.exists() {
+            log::debug!(
+                "Inbox {:?} is full but {:?} is not drained yet",
+                path,
+                segment
+            );
+            return Ok(());
+        }
+        std::fs::rename(&path, &segment).with_context(|| format!("Rotating inbox {:?}", path))?;
+        self.my_writer = EventLogWriter::open(&path, self.log_cfg.clone())?;
+        log::info!("Rotated inbox {:?} ({} bytes)", path, size);
+        Ok(())
+    }
+
+    /// Coordinator side: the segment behind `fname` has been read to the end.
+    /// Copies everything but heartbeats into `inbox/archive/` and removes it,
+    /// which lets the worker rotate again.
+    fn compact_segment(&self, fname: &str) -> Result<()> {
+        let inbox_dir = self.root_path.join("inbox");
+        let segment = rotated_path(&inbox_dir.join(fname));
+        if !segment.exists() {
+            return Ok(());
+        }
+
+        let mut reader = EventLogReader::open(&segment)?;
+        let mut archive =
+            EventLogWriter::open(inbox_dir.join("archive").join(fname), self.log_cfg.clone())?;
+        let (mut kept, mut dropped) = (0, 0);
+        while let Some(env) = reader.next()? {
+            if COMPACT_DROP.contains(&env.record.kind.as_str()) {
+                dropped += 1;
+                continue;
+            }
+            archive.append_record(&env.record)?;
+            kept += 1;
+        }
+        std::fs::remove_file(&segment)?;
+        log::info!(
+            "Compacted {:?}: kept {} record(s), dropped {} heartbeat(s)",
+            segment,
+            kept,
+            dropped
+        );
+        Ok(())
+    }
+
+    /// Coordinator side: called when an inbox reader hits EOF.
+    /// Moves it on to the live file if it was reading a finished segment.
+    fn follow_rotation(&mut self, fname: &str, events: &mut Vec<EventEnvelope>) -> Result<()> {
+        let live = self.root_path.join("inbox").join(fname);
+        let Some(reader) = self.inbox_readers.get_mut(fname) else {
+            return Ok(());
+        };
+
+        if !self.draining.remove(fname) {
+            if !reader.is_replaced() {
+                return Ok(());
+            }
+            // The worker renamed the file we hold open; pick up its last records
+            while let Some(env) = next_inbox_record(reader, self.inbox_kinds)? {
+                events.push(env);
+            }
+        }
+
+        self.compact_segment(fname)?;
+        self.inbox_readers
+            .insert(fname.to_string(), EventLogReader::open(&live)?);
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -83,15 +395,62 @@ impl Transport for FileTransport {
         if self.role == Role::Coordinator {
             return Err(anyhow!("Coordinator cannot send to self"));
         }
-        self.my_writer.append(kind, payload)?;
-        Ok(())
+        let offset = self.my_writer.append(kind, payload)?;
+        self.stats.wrote(self.my_writer.end() - offset);
+        self.maybe_rotate_inbox()
+    }
+
+    async fn send_to_coordinator_with_id(
+        &mut self,
+        kind: &str,
+        payload: Value,
+        event_id: Uuid,
+    ) -> Result<()> {
+        if self.role == Role::Coordinator {
+            return Err(anyhow!("Coordinator cannot send to self"));
+        }
+        let offset = self.my_writer.append_with_id(kind, payload, event_id)?;
+        self.stats.wrote(self.my_writer.end() - offset);
+        self.maybe_rotate_inbox()
     }
 
     async fn broadcast(&mut self, kind: &str, payload: Value) -> Result<u64> {
         if self.role == Role::Worker {
             return Err(anyhow!("Worker cannot broadcast"));
         }
-        Ok(self.my_writer.append(kind, payload)?)
+        let offset = self.my_writer.append(kind, payload)?;
+        self.stats.wrote(self.my_writer.end() - offset);
+        Ok(offset)
+    }
+
+    async fn broadcast_batch(&mut self, records: Vec<(&str, Value)>) -> Result<Vec<u64>> {
+        if self.role == Role::Worker {
+            return Err(anyhow!("Worker cannot broadcast"));
+        }
+        let offsets = self.my_writer.append_batch(&records)?;
+        if let Some(first) = offsets.first() {
+            self.stats.wrote(self.my_writer.end() - first);
+        }
+        Ok(offsets)
+    }
+
+    async fn send_to_worker(&mut self, worker_id: &str, kind: &str, payload: Value) -> Result<u64> {
+        if self.role == Role::Worker {
+            return Err(anyhow!("Worker cannot address other workers"));
+        }
+        let writer = match self.outbox_writers.entry(worker_id.to_string()) {
+            std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+            std::collections::hash_map::Entry::Vacant(e) => {
+                let path = self
+                    .root_path
+                    .join("outbox")
+                    .join(format!("worker_{}.log", worker_id));
+                e.insert(EventLogWriter::open(path, self.log_cfg.clone())?)
+            }
+        };
+        let offset = writer.append(kind, payload)?;
+        self.stats.wrote(writer.end() - offset);
+        Ok(offset)
     }
 
     async fn recv_broadcasts(&mut self) -> Result<Vec<EventEnvelope>> {
@@ -109,6 +468,25 @@ impl Transport for FileTransport {
                 break;
             }
         }
+        self.stats.skipped(reader);
+
+        // Addressed messages (grants) arrive on our private outbox.
+        // Opened lazily so submit-only clients (architect, console) leave no files.
+        if self.outbox_reader.is_none() {
+            if let Some(p) = self.outbox_path.as_ref().filter(|p| p.exists()) {
+                self.outbox_reader = Some(EventLogReader::open(p)?);
+            }
+        }
+        if let Some(outbox) = self.outbox_reader.as_mut() {
+            while let Ok(Some(env)) = outbox.next() {
+                events.push(env);
+                if events.len() > 2000 {
+                    break;
+                }
+            }
+            self.stats.skipped(outbox);
+        }
+        self.stats.read(&events);
         Ok(events)
     }
 
@@ -120,7 +498,8 @@ impl Transport for FileTransport {
         let mut events = Vec::new();
 
         // 1. Throttled Discovery
-        if Instant::now() >= self.next_discovery {
+        let rescan = Instant::now() >= self.next_discovery;
+        if rescan {
             let inbox_dir = self.root_path.join("inbox");
             if let Ok(mut entries) = fs::read_dir(&inbox_dir).await {
                 while let Ok(Some(entry)) = entries.next_entry().await {
@@ -136,7 +515,15 @@ impl Transport for FileTransport {
                             if let Ok(meta) = std::fs::metadata(&path) {
                                 log::info!("Inbox {} size on disk: {} bytes", fname, meta.len());
                             }
-                            if let Ok(r) = EventLogReader::open(&path) {
+                            // Older records sit in an undrained segment; read that first
+                            let segment = rotated_path(&path);
+                            let first = if segment.exists() {
+                                self.draining.insert(fname.to_string());
+                                segment
+                            } else {
+                                path.clone()
+                            };
+                            if let Ok(r) = EventLogReader::open(&first) {
                                 self.inbox_readers.insert(fname.to_string(), r);
                             }
                         }
@@ -147,6 +534,8 @@ impl Transport for FileTransport {
         }
 
         // 2. Harvest
+        let kinds = self.inbox_kinds;
+        let mut at_eof = Vec::new();
         for (wid, reader) in self.inbox_readers.iter_mut() {
             // DEBUG: Check if file has grown beyond our cursor
             // This is slightly expensive but necessary to debug "stuck" state
@@ -161,7 +550,7 @@ impl Transport for FileTransport {
 
             let mut count = 0;
             loop {
-                match reader.next() {
+                match next_inbox_record(reader, kinds) {
                     Ok(Some(env)) => {
                         log::info!("Read msg [{}] from {}", env.record.kind, wid); // LOG SUCCESS
                         events.push(env);
@@ -172,6 +561,7 @@ impl Transport for FileTransport {
                     }
                     Ok(None) => {
                         // EOF - this is where it stops silently
+                        at_eof.push(wid.clone());
                         break;
                     }
                     Err(e) => {
@@ -180,8 +570,19 @@ impl Transport for FileTransport {
                     }
                 }
             }
+            self.stats.skipped(reader);
+        }
+
+        // 3. Rotation (checked at discovery pace; a stat per inbox is not free)
+        if rescan {
+            for fname in at_eof {
+                if let Err(e) = self.follow_rotation(&fname, &mut events) {
+                    log::warn!("Inbox rotation for {} failed: {:#}", fname, e);
+                }
+            }
         }
 
+        self.stats.read(&events);
         Ok(events)
     }
 
@@ -195,4 +596,252 @@ impl Transport for FileTransport {
         }
         Ok(())
     }
+
+    fn stats(&self) -> TransportStats {
+        let lag = match self.role {
+            Role::Coordinator => self.inbox_readers.values().map(unread_bytes).sum(),
+            Role::Worker => {
+                let events_end = crate::eventlog::log_end(&self.root_path.join("events.log"));
+                let broadcast = match (&self.global_reader, events_end) {
+                    (Some(r), Ok(end)) => end.saturating_sub(r.cursor()),
+                    _ => 0,
+                };
+                broadcast + self.outbox_reader.as_ref().map_or(0, unread_bytes)
+            }
+        };
+        TransportStats {
+            read_lag: Some(lag),
+            ..self.stats
+        }
+    }
+}
+
+// ============================================================================
+// TRANSPORT SELECTION
+// ============================================================================
+
+/// Name of the per-deployment config picked up from `root` when present.
+pub const TRANSPORT_CONFIG_FILE: &str = "transport.yaml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// Shared-filesystem inboxes + events.log (default).
+    #[default]
+    File,
+    /// Mutual-TLS gRPC (needs `--features grpc`).
+    Grpc,
+    /// Unix socket in `root` (one machine; picked automatically in local mode).
+    Uds,
+}
+
+impl FromStr for TransportKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "file" => Ok(Self::File),
+            "grpc" => Ok(Self::Grpc),
+            "uds" => Ok(Self::Uds),
+            other => Err(format!(
+                "unknown transport '{}' (expected file|grpc|uds)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GrpcSettings {
+    /// Coordinator "host:port": rank 0 binds it, everyone else connects.
+    pub addr: Option<String>,
+    pub tls_ca: Option<PathBuf>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+}
+
+/// Deployment-level transport choice, e.g. `root/transport.yaml`:
+///
+/// ```yaml
+/// kind: grpc
+/// compression: zlib   # event-log records: none (default), zlib or zstd
+/// inbox_rotate_bytes: 8388608   # roll worker inboxes past this size (0 = never)
+/// event_segment_bytes: 268435456 # start a new events.NNNN.log past this size (0 = one file)
+/// event_index: true             # keep events.idx for fast seeks
+/// fsync: always                 # or never, every_n=64, interval_ms=200
+/// grpc:
+///   addr: lighthouse:7443
+///   tls_ca: /etc/ulab/ca.pem
+///   tls_cert: /etc/ulab/node.pem
+///   tls_key: /etc/ulab/node.key
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransportConfig {
+    pub kind: TransportKind,
+    /// Compression of large records in events.log / inbox / outbox logs.
+    pub compression: Compression,
+    pub inbox_rotate_bytes: u64,
+    pub event_segment_bytes: u64,
+    /// Keep the `events.idx` offset index next to events.log.
+    pub event_index: bool,
+    /// When log appends reach the disk (see `FsyncPolicy`).
+    pub fsync: FsyncPolicy,
+    pub grpc: GrpcSettings,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            kind: TransportKind::File,
+            compression: Compression::None,
+            inbox_rotate_bytes: DEFAULT_INBOX_ROTATE_BYTES,
+            event_segment_bytes: DEFAULT_EVENT_SEGMENT_BYTES,
+            event_index: true,
+            fsync: FsyncPolicy::Always,
+            grpc: GrpcSettings::default(),
+        }
+    }
+}
+
+impl TransportConfig {
+    fn log_config(&self) -> EventLogConfig {
+        EventLogConfig {
+            fsync: self.fsync,
+            compression: self.compression,
+            segment_bytes: (self.event_segment_bytes > 0).then_some(self.event_segment_bytes),
+            index: self.event_index,
+            ..Default::default()
+        }
+    }
+}
+
+impl TransportConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Reading transport config {:?}", path))?;
+        serde_yaml::from_str(&text).with_context(|| format!("Parsing transport config {:?}", path))
+    }
+
+    /// Default for a root without `transport.yaml`: the local socket when a
+    /// Coordinator is listening on it, files otherwise.
+    pub fn detect(root: impl AsRef<Path>) -> Self {
+        let mut cfg = Self::default();
+        #[cfg(unix)]
+        if uds::is_live(root.as_ref()) {
+            cfg.kind = TransportKind::Uds;
+        }
+        #[cfg(not(unix))]
+        let _ = root;
+        cfg
+    }
+}
+
+pub struct TransportFactory;
+
+impl TransportFactory {
+    pub async fn open(
+        cfg: &TransportConfig,
+        root_path: impl AsRef<Path>,
+        role: Role,
+        worker_id: Option<&str>,
+    ) -> Result<Box<dyn Transport>> {
+        let root = root_path.as_ref();
+        match cfg.kind {
+            TransportKind::File => Ok(Box::new(
+                FileTransport::with_log_config(root, role, worker_id, cfg.log_config())
+                    .await?
+                    .with_inbox_rotation(cfg.inbox_rotate_bytes)
+                    .with_inbox_kinds(WORKER_MESSAGE_KINDS),
+            )),
+            TransportKind::Grpc => Self::open_grpc(cfg, root, role).await,
+            TransportKind::Uds => Self::open_uds(cfg, root, role, worker_id).await,
+        }
+    }
+
+    #[cfg(unix)]
+    async fn open_uds(
+        cfg: &TransportConfig,
+        root: &Path,
+        role: Role,
+        worker_id: Option<&str>,
+    ) -> Result<Box<dyn Transport>> {
+        match role {
+            Role::Coordinator => Ok(Box::new(
+                uds::UdsTransport::serve(root, cfg.log_config()).await?,
+            )),
+            Role::Worker => Ok(Box::new(uds::UdsTransport::connect(root, worker_id))),
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn open_uds(
+        _cfg: &TransportConfig,
+        _root: &Path,
+        _role: Role,
+        _worker_id: Option<&str>,
+    ) -> Result<Box<dyn Transport>> {
+        Err(anyhow!(
+            "The Unix socket transport is not available on this platform"
+        ))
+    }
+
+    #[cfg(feature = "grpc")]
+    async fn open_grpc(
+        cfg: &TransportConfig,
+        root: &Path,
+        role: Role,
+    ) -> Result<Box<dyn Transport>> {
+        use grpc::{GrpcTransport, TlsFiles};
+        use std::net::ToSocketAddrs;
+
+        let settings = &cfg.grpc;
+
+        let addr = settings
+            .addr
+            .as_deref()
+            .ok_or_else(|| anyhow!("gRPC transport needs an address (grpc.addr / --grpc)"))?;
+
+        let tls = match (&settings.tls_ca, &settings.tls_cert, &settings.tls_key) {
+            (Some(ca), Some(cert), Some(key)) => TlsFiles {
+                ca: ca.clone(),
+                cert: cert.clone(),
+                key: key.clone(),
+            },
+            _ => {
+                return Err(anyhow!(
+                    "gRPC requires mutual TLS: set tls_ca, tls_cert and tls_key"
+                ))
+            }
+        };
+
+        match role {
+            Role::Coordinator => {
+                let sock = addr
+                    .to_socket_addrs()?
+                    .next()
+                    .ok_or_else(|| anyhow!("Cannot resolve gRPC address {}", addr))?;
+                Ok(Box::new(
+                    GrpcTransport::serve(root, sock, &tls, cfg.log_config()).await?,
+                ))
+            }
+            Role::Worker => Ok(Box::new(GrpcTransport::connect(
+                &format!("https://{}", addr),
+                &tls,
+            )?)),
+        }
+    }
+
+    #[cfg(not(feature = "grpc"))]
+    async fn open_grpc(
+        _cfg: &TransportConfig,
+        _root: &Path,
+        _role: Role,
+    ) -> Result<Box<dyn Transport>> {
+        Err(anyhow!(
+            "This binary was built without gRPC support (rebuild with --features grpc)"
+        ))
+    }
 }
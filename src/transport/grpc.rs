@@ -0,0 +1,502 @@
+// src/transport/grpc.rs
+//
+// =============================================================================
+// UNIFIEDLAB: GRPC TRANSPORT (v 0.1 )
+// =============================================================================
+//
+// The Long-Distance Nerve (feature = "grpc").
+//
+// FileTransport needs a shared filesystem. This variant lets nodes on
+// untrusted networks join the marketplace:
+//
+// 1. Mutual TLS: the Coordinator only accepts clients whose certificate is
+//    signed by the cluster CA (and clients verify the Coordinator likewise).
+// 2. Worker -> Coordinator: one unary RPC per message kind
+//    (SubmitJob / WorkRequest / JobComplete / Control).
+// 3. Coordinator -> Workers: broadcasts are still appended to events.log
+//    (durability + cursor semantics unchanged) and streamed to subscribers.
+//
+// Note: CAS offloading (wire.rs) assumes a shared `root/store`, so keep
+// structures inline when workers do not share the Coordinator's disk.
+
+use super::{Transport, TransportStats};
+use crate::eventlog::{EventEnvelope, EventLogConfig, EventLogReader, EventLogWriter, EventRecord};
+use crate::marketplace::{
+    EV_JOB_SUBMIT, MSG_CONTROL, MSG_GRANT_ACK, MSG_JOB_ARRAY, MSG_JOB_COMPLETE, MSG_JOB_PROGRESS,
+    MSG_SUBMIT_END, MSG_WORK_REQUEST, MSG_WORK_YIELD,
+};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity, Server, ServerTlsConfig};
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+pub mod proto {
+    tonic::include_proto!("unifiedlab.v1");
+}
+
+use proto::marketplace_client::MarketplaceClient;
+use proto::marketplace_server::{Marketplace, MarketplaceServer};
+use proto::{Ack, Broadcast, Payload, SubscribeRequest};
+
+/// How often a subscription polls events.log once it has caught up.
+const TAIL_INTERVAL: Duration = Duration::from_millis(100);
+/// Backoff before a worker re-subscribes after a dropped stream.
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+/// Buffered RPC messages before callers see backpressure.
+const INBOX_CAPACITY: usize = 4096;
+
+// ============================================================================
+// 1. TLS MATERIAL
+// ============================================================================
+
+/// PEM files for mutual TLS. Every node (Coordinator, workers, architects)
+/// holds its own cert/key pair, all signed by the same CA.
+#[derive(Debug, Clone)]
+pub struct TlsFiles {
+    pub ca: PathBuf,
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl TlsFiles {
+    fn load(&self) -> Result<(Certificate, Identity)> {
+        let read = |p: &Path| std::fs::read(p).with_context(|| format!("Reading TLS file {:?}", p));
+        let ca = Certificate::from_pem(read(&self.ca)?);
+        let identity = Identity::from_pem(read(&self.cert)?, read(&self.key)?);
+        Ok((ca, identity))
+    }
+}
+
+// ============================================================================
+// 2. SERVER SIDE (Coordinator)
+// ============================================================================
+
+struct MarketplaceService {
+    inbox: mpsc::Sender<EventEnvelope>,
+    events_path: PathBuf,
+}
+
+impl MarketplaceService {
+    /// Turns an RPC into the same envelope FileTransport would read from an inbox.
+    async fn enqueue(&self, kind: &str, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        let req = req.into_inner();
+        let payload: Value = serde_json::from_slice(&req.json)
+            .map_err(|e| Status::invalid_argument(format!("Payload is not JSON: {}", e)))?;
+        let event_id = match req.event_id.as_str() {
+            "" => None,
+            id => Some(
+                Uuid::parse_str(id)
+                    .map_err(|e| Status::invalid_argument(format!("Bad event_id: {}", e)))?,
+            ),
+        };
+
+        let env = EventEnvelope {
+            // RPC messages have no position in any log
+            offset: 0,
+            next_offset: 0,
+            record: EventRecord {
+                ts_ms: chrono::Utc::now().timestamp_millis(),
+                kind: kind.to_string(),
+                payload,
+                event_id,
+            },
+        };
+
+        self.inbox
+            .send(env)
+            .await
+            .map_err(|_| Status::unavailable("Coordinator is shutting down"))?;
+        Ok(Response::new(Ack {}))
+    }
+}
+
+#[tonic::async_trait]
+impl Marketplace for MarketplaceService {
+    async fn submit_job(&self, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        self.enqueue(EV_JOB_SUBMIT, req).await
+    }
+
+    async fn work_request(&self, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        self.enqueue(MSG_WORK_REQUEST, req).await
+    }
+
+    async fn job_complete(&self, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        self.enqueue(MSG_JOB_COMPLETE, req).await
+    }
+
+    async fn control(&self, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        self.enqueue(MSG_CONTROL, req).await
+    }
+
+    async fn grant_ack(&self, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        self.enqueue(MSG_GRANT_ACK, req).await
+    }
+
+    async fn work_yield(&self, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        self.enqueue(MSG_WORK_YIELD, req).await
+    }
+
+    async fn submit_end(&self, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        self.enqueue(MSG_SUBMIT_END, req).await
+    }
+
+    async fn submit_array(&self, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        self.enqueue(MSG_JOB_ARRAY, req).await
+    }
+
+    async fn job_progress(&self, req: Request<Payload>) -> Result<Response<Ack>, Status> {
+        self.enqueue(MSG_JOB_PROGRESS, req).await
+    }
+
+    type SubscribeStream = ReceiverStream<Result<Broadcast, Status>>;
+
+    async fn subscribe(
+        &self,
+        req: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let from = req.into_inner().from_offset;
+        let mut reader = EventLogReader::open(&self.events_path)
+            .and_then(|mut r| r.seek(from).map(|_| r))
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let (tx, rx) = mpsc::channel(256);
+        tokio::spawn(async move {
+            loop {
+                match reader.next() {
+                    Ok(Some(env)) => match to_broadcast(env) {
+                        Ok(b) => {
+                            if tx.send(Ok(b)).await.is_err() {
+                                return; // Subscriber went away
+                            }
+                        }
+                        Err(e) => log::warn!("Skipping unencodable broadcast: {}", e),
+                    },
+                    Ok(None) => {
+                        if tx.is_closed() {
+                            return;
+                        }
+                        sleep(TAIL_INTERVAL).await;
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(Status::internal(e.to_string()))).await;
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+fn to_broadcast(env: EventEnvelope) -> Result<Broadcast> {
+    Ok(Broadcast {
+        kind: env.record.kind,
+        ts_ms: env.record.ts_ms,
+        payload_json: serde_json::to_vec(&env.record.payload)?,
+        offset: env.offset,
+        next_offset: env.next_offset,
+    })
+}
+
+fn from_broadcast(b: Broadcast) -> Result<EventEnvelope> {
+    Ok(EventEnvelope {
+        offset: b.offset,
+        next_offset: b.next_offset,
+        record: EventRecord {
+            ts_ms: b.ts_ms,
+            kind: b.kind,
+            payload: serde_json::from_slice(&b.payload_json)?,
+            event_id: None,
+        },
+    })
+}
+
+// ============================================================================
+// 3. CLIENT SIDE (Workers / Architect)
+// ============================================================================
+
+/// Keeps a Subscribe stream alive, resuming from the last seen offset.
+fn spawn_feed(client: MarketplaceClient<Channel>, from: u64) -> mpsc::Receiver<EventEnvelope> {
+    let (tx, rx) = mpsc::channel(1024);
+    tokio::spawn(async move {
+        let mut cursor = from;
+        loop {
+            let req = SubscribeRequest {
+                from_offset: cursor,
+            };
+            match client.clone().subscribe(req).await {
+                Ok(resp) => {
+                    let mut stream = resp.into_inner();
+                    loop {
+                        match stream.message().await {
+                            Ok(Some(b)) => {
+                                cursor = b.next_offset;
+                                match from_broadcast(b) {
+                                    Ok(env) => {
+                                        if tx.send(env).await.is_err() {
+                                            return; // Transport dropped
+                                        }
+                                    }
+                                    Err(e) => log::warn!("Bad broadcast payload: {}", e),
+                                }
+                            }
+                            Ok(None) => break,
+                            Err(s) => {
+                                log::warn!("gRPC broadcast stream lost: {}", s);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(s) => log::warn!("gRPC subscribe failed: {}", s),
+            }
+
+            if tx.is_closed() {
+                return;
+            }
+            sleep(RECONNECT_DELAY).await;
+        }
+    });
+    rx
+}
+
+// ============================================================================
+// 4. THE TRANSPORT
+// ============================================================================
+
+enum Side {
+    Coordinator {
+        writer: EventLogWriter,
+        inbox: mpsc::Receiver<EventEnvelope>,
+    },
+    Worker {
+        client: MarketplaceClient<Channel>,
+        feed: Option<mpsc::Receiver<EventEnvelope>>,
+        cursor: u64,
+    },
+}
+
+pub struct GrpcTransport {
+    side: Side,
+    stats: TransportStats,
+}
+
+impl GrpcTransport {
+    /// Coordinator: binds `addr` and serves the Marketplace RPCs over mTLS.
+    /// Broadcasts are persisted to `root/events.log` exactly like FileTransport.
+    pub async fn serve(
+        root_path: impl AsRef<Path>,
+        addr: SocketAddr,
+        tls: &TlsFiles,
+        log_cfg: EventLogConfig,
+    ) -> Result<Self> {
+        let root = root_path.as_ref();
+        std::fs::create_dir_all(root)?;
+        let events_path = root.join("events.log");
+        let writer = EventLogWriter::open(&events_path, log_cfg)?;
+
+        let (ca, identity) = tls.load()?;
+        let tls_cfg = ServerTlsConfig::new().identity(identity).client_ca_root(ca);
+
+        // Bind eagerly so "address in use" surfaces here, not in a detached task
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Binding gRPC listener on {}", addr))?;
+
+        let (tx, rx) = mpsc::channel(INBOX_CAPACITY);
+        let service = MarketplaceService {
+            inbox: tx,
+            events_path,
+        };
+
+        let router = Server::builder()
+            .tls_config(tls_cfg)?
+            .add_service(MarketplaceServer::new(service));
+
+        tokio::spawn(async move {
+            if let Err(e) = router
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+            {
+                log::error!("gRPC server stopped: {}", e);
+            }
+        });
+
+        log::info!("📡 gRPC Marketplace listening on {} (mTLS)", addr);
+        Ok(Self {
+            side: Side::Coordinator { writer, inbox: rx },
+            stats: TransportStats::default(),
+        })
+    }
+
+    /// Worker/Architect: connects to the Coordinator at `endpoint`
+    /// (e.g. `https://lighthouse:7443`). The connection is lazy, so workers
+    /// may boot before the Coordinator is reachable.
+    pub fn connect(endpoint: &str, tls: &TlsFiles) -> Result<Self> {
+        let (ca, identity) = tls.load()?;
+        let tls_cfg = ClientTlsConfig::new().ca_certificate(ca).identity(identity);
+
+        let channel = Channel::from_shared(endpoint.to_string())
+            .map_err(|e| anyhow!("Invalid gRPC endpoint {}: {}", endpoint, e))?
+            .tls_config(tls_cfg)?
+            .connect_lazy();
+
+        Ok(Self {
+            side: Side::Worker {
+                client: MarketplaceClient::new(channel),
+                feed: None,
+                cursor: 0,
+            },
+            stats: TransportStats::default(),
+        })
+    }
+
+    async fn send_payload(
+        &mut self,
+        kind: &str,
+        payload: &Value,
+        event_id: Option<Uuid>,
+    ) -> Result<()> {
+        let client = match &mut self.side {
+            Side::Worker { client, .. } => client,
+            Side::Coordinator { .. } => return Err(anyhow!("Coordinator cannot send to self")),
+        };
+
+        let req = Payload {
+            json: serde_json::to_vec(payload)?,
+            event_id: event_id.map(|id| id.to_string()).unwrap_or_default(),
+        };
+        self.stats.wrote(req.json.len() as u64);
+        let res = match kind {
+            EV_JOB_SUBMIT => client.submit_job(req).await,
+            MSG_WORK_REQUEST => client.work_request(req).await,
+            MSG_JOB_COMPLETE => client.job_complete(req).await,
+            MSG_CONTROL => client.control(req).await,
+            MSG_GRANT_ACK => client.grant_ack(req).await,
+            MSG_WORK_YIELD => client.work_yield(req).await,
+            MSG_SUBMIT_END => client.submit_end(req).await,
+            MSG_JOB_ARRAY => client.submit_array(req).await,
+            MSG_JOB_PROGRESS => client.job_progress(req).await,
+            other => return Err(anyhow!("No gRPC route for message kind '{}'", other)),
+        };
+        res.map_err(|s| anyhow!("gRPC {} failed: {}", kind, s))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Transport for GrpcTransport {
+    async fn send_to_coordinator(&mut self, kind: &str, payload: Value) -> Result<()> {
+        self.send_payload(kind, &payload, None).await
+    }
+
+    async fn send_to_coordinator_with_id(
+        &mut self,
+        kind: &str,
+        payload: Value,
+        event_id: Uuid,
+    ) -> Result<()> {
+        self.send_payload(kind, &payload, Some(event_id)).await
+    }
+
+    async fn broadcast(&mut self, kind: &str, payload: Value) -> Result<u64> {
+        match &mut self.side {
+            Side::Coordinator { writer, .. } => {
+                let offset = writer.append(kind, payload)?;
+                self.stats.wrote(writer.end() - offset);
+                Ok(offset)
+            }
+            Side::Worker { .. } => Err(anyhow!("Worker cannot broadcast")),
+        }
+    }
+
+    async fn broadcast_batch(&mut self, records: Vec<(&str, Value)>) -> Result<Vec<u64>> {
+        match &mut self.side {
+            Side::Coordinator { writer, .. } => {
+                let offsets = writer.append_batch(&records)?;
+                if let Some(first) = offsets.first() {
+                    self.stats.wrote(writer.end() - first);
+                }
+                Ok(offsets)
+            }
+            Side::Worker { .. } => Err(anyhow!("Worker cannot broadcast")),
+        }
+    }
+
+    async fn recv_broadcasts(&mut self) -> Result<Vec<EventEnvelope>> {
+        let (client, feed, cursor) = match &mut self.side {
+            Side::Worker {
+                client,
+                feed,
+                cursor,
+            } => (client, feed, cursor),
+            Side::Coordinator { .. } => return Ok(vec![]),
+        };
+
+        let rx = feed.get_or_insert_with(|| spawn_feed(client.clone(), *cursor));
+        let mut events = Vec::new();
+        while let Ok(env) = rx.try_recv() {
+            *cursor = env.next_offset;
+            events.push(env);
+            if events.len() > 1000 {
+                break;
+            }
+        }
+        self.stats.read(&events);
+        Ok(events)
+    }
+
+    async fn recv_worker_messages(&mut self) -> Result<Vec<EventEnvelope>> {
+        let inbox = match &mut self.side {
+            Side::Coordinator { inbox, .. } => inbox,
+            Side::Worker { .. } => return Ok(vec![]),
+        };
+
+        let mut events = Vec::new();
+        while let Ok(env) = inbox.try_recv() {
+            log::info!("Read msg [{}] via gRPC", env.record.kind);
+            events.push(env);
+            if events.len() > 1000 {
+                break;
+            }
+        }
+        self.stats.read(&events);
+        Ok(events)
+    }
+
+    fn shares_filesystem(&self) -> bool {
+        false
+    }
+
+    async fn seek(&mut self, offset: u64) -> Result<()> {
+        // The Coordinator never reads broadcasts back; workers re-subscribe.
+        if let Side::Worker { feed, cursor, .. } = &mut self.side {
+            *cursor = offset;
+            *feed = None;
+        }
+        Ok(())
+    }
+
+    /// The broadcast log is on the Coordinator's host, so workers only see
+    /// what is queued locally.
+    fn stats(&self) -> TransportStats {
+        let backlog = match &self.side {
+            Side::Coordinator { inbox, .. } => inbox.len(),
+            Side::Worker { feed, .. } => feed.as_ref().map_or(0, |rx| rx.len()),
+        };
+        TransportStats {
+            backlog: Some(backlog as u64),
+            ..self.stats
+        }
+    }
+}
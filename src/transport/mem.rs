@@ -0,0 +1,291 @@
+// src/transport/mem.rs
+//
+// =============================================================================
+// UNIFIEDLAB: IN-MEMORY TRANSPORT (v 0.1 )
+// =============================================================================
+//
+// The Bench Nerve.
+//
+// Coordinator, workers and architects in one process (integration tests,
+// embedding UnifiedLAB as a library). Nothing touches the filesystem:
+//
+// 1. `MemNetwork` is the shared medium; endpoints are cut from it with
+//    `coordinator()` / `worker(id)` and each implements `Transport`.
+// 2. Broadcasts go to an in-memory log. Offsets are record indices, so
+//    cursors and `seek` behave like events.log (a late worker replays all).
+// 3. Addressed messages (grants) wait in a per-worker queue until read, even
+//    if that worker's endpoint does not exist yet.
+// 4. Messages to the Coordinator are kept like an inbox file: a Coordinator
+//    reopened on the same network replays them from its checkpointed cursor.
+// 5. `wait_for_traffic` wakes as soon as something is sent to the endpoint.
+//
+// Peers share no `root/store`, so large structures travel inline.
+
+use super::{payload_bytes, Role, Transport, TransportStats};
+use crate::eventlog::{EventEnvelope, EventRecord};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// Most messages handed out per `recv_*` call (same cap as the file transport).
+const MAX_BATCH: usize = 1000;
+
+// ============================================================================
+// 1. THE SHARED MEDIUM
+// ============================================================================
+
+#[derive(Default)]
+struct Shared {
+    broadcasts: Vec<EventRecord>,
+    inbox: Vec<EventEnvelope>,
+    outboxes: HashMap<String, VecDeque<EventEnvelope>>,
+    coordinator: Arc<Notify>,
+    /// Wake-ups of every worker endpoint, by worker id (None: architects).
+    listeners: Vec<(Option<String>, Arc<Notify>)>,
+}
+
+fn record(kind: &str, payload: Value) -> EventRecord {
+    EventRecord {
+        ts_ms: chrono::Utc::now().timestamp_millis(),
+        kind: kind.to_string(),
+        payload,
+        event_id: None,
+    }
+}
+
+/// Connects in-process endpoints. Cheap to clone; clones share one medium.
+#[derive(Clone, Default)]
+pub struct MemNetwork {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl MemNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The Coordinator's endpoint. Only one should be in use at a time.
+    pub fn coordinator(&self) -> MemTransport {
+        let notify = self
+            .lock()
+            .map(|s| s.coordinator.clone())
+            .unwrap_or_default();
+        MemTransport {
+            shared: self.shared.clone(),
+            role: Role::Coordinator,
+            worker_id: None,
+            cursor: 0,
+            notify,
+            stats: TransportStats::default(),
+        }
+    }
+
+    /// A worker's endpoint (None for submit-only clients such as architects).
+    pub fn worker(&self, worker_id: Option<&str>) -> MemTransport {
+        let notify = Arc::new(Notify::new());
+        if let Ok(mut s) = self.lock() {
+            s.listeners
+                .push((worker_id.map(str::to_string), notify.clone()));
+        }
+        MemTransport {
+            shared: self.shared.clone(),
+            role: Role::Worker,
+            worker_id: worker_id.map(str::to_string),
+            cursor: 0,
+            notify,
+            stats: TransportStats::default(),
+        }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Shared>> {
+        self.shared
+            .lock()
+            .map_err(|_| anyhow!("Memory transport lock poisoned"))
+    }
+}
+
+// ============================================================================
+// 2. THE TRANSPORT
+// ============================================================================
+
+pub struct MemTransport {
+    shared: Arc<Mutex<Shared>>,
+    role: Role,
+    worker_id: Option<String>,
+    cursor: usize, // Next broadcast (worker) or inbox message (Coordinator) to read
+    notify: Arc<Notify>,
+    stats: TransportStats,
+}
+
+impl MemTransport {
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Shared>> {
+        self.shared
+            .lock()
+            .map_err(|_| anyhow!("Memory transport lock poisoned"))
+    }
+
+    fn push_inbox(&mut self, record: EventRecord) -> Result<()> {
+        if self.role == Role::Coordinator {
+            return Err(anyhow!("Coordinator cannot send to self"));
+        }
+        self.stats.wrote(payload_bytes(&record.payload));
+        let mut s = self.lock()?;
+        let offset = s.inbox.len() as u64;
+        let env = EventEnvelope {
+            offset,
+            next_offset: offset + 1,
+            record,
+        };
+        s.inbox.push(env);
+        s.coordinator.notify_one();
+        Ok(())
+    }
+
+    fn coordinator_only(&self, what: &str) -> Result<()> {
+        match self.role {
+            Role::Coordinator => Ok(()),
+            Role::Worker => Err(anyhow!("Worker cannot {}", what)),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for MemTransport {
+    async fn send_to_coordinator(&mut self, kind: &str, payload: Value) -> Result<()> {
+        self.push_inbox(record(kind, payload))
+    }
+
+    async fn send_to_coordinator_with_id(
+        &mut self,
+        kind: &str,
+        payload: Value,
+        event_id: Uuid,
+    ) -> Result<()> {
+        self.push_inbox(EventRecord {
+            event_id: Some(event_id),
+            ..record(kind, payload)
+        })
+    }
+
+    async fn broadcast(&mut self, kind: &str, payload: Value) -> Result<u64> {
+        self.coordinator_only("broadcast")?;
+        self.stats.wrote(payload_bytes(&payload));
+        let mut s = self.lock()?;
+        s.broadcasts.push(record(kind, payload));
+        for (_, n) in &s.listeners {
+            n.notify_one();
+        }
+        Ok(s.broadcasts.len() as u64 - 1)
+    }
+
+    async fn send_to_worker(&mut self, worker_id: &str, kind: &str, payload: Value) -> Result<u64> {
+        self.coordinator_only("send to workers")?;
+        self.stats.wrote(payload_bytes(&payload));
+        let mut s = self.lock()?;
+        let env = EventEnvelope {
+            offset: 0,
+            next_offset: 0,
+            record: record(kind, payload),
+        };
+        s.outboxes
+            .entry(worker_id.to_string())
+            .or_default()
+            .push_back(env);
+        for (id, n) in &s.listeners {
+            if id.as_deref() == Some(worker_id) {
+                n.notify_one();
+            }
+        }
+        Ok(0)
+    }
+
+    async fn recv_broadcasts(&mut self) -> Result<Vec<EventEnvelope>> {
+        if self.role == Role::Coordinator {
+            return Ok(vec![]);
+        }
+        // Field borrow only: the cursor is updated while the lock is held
+        let mut s = self
+            .shared
+            .lock()
+            .map_err(|_| anyhow!("Memory transport lock poisoned"))?;
+        let end = s.broadcasts.len().min(self.cursor + MAX_BATCH);
+        let mut events: Vec<EventEnvelope> = (self.cursor..end)
+            .map(|i| EventEnvelope {
+                offset: i as u64,
+                next_offset: i as u64 + 1,
+                record: s.broadcasts[i].clone(),
+            })
+            .collect();
+        self.cursor = end.max(self.cursor);
+
+        if let Some(id) = &self.worker_id {
+            if let Some(q) = s.outboxes.get_mut(id) {
+                events.extend(q.drain(..));
+            }
+        }
+        self.stats.read(&events);
+        Ok(events)
+    }
+
+    async fn recv_worker_messages(&mut self) -> Result<Vec<EventEnvelope>> {
+        if self.role == Role::Worker {
+            return Ok(vec![]);
+        }
+        let events: Vec<EventEnvelope> = {
+            let s = self.lock()?;
+            let end = s.inbox.len().min(self.cursor + MAX_BATCH);
+            s.inbox[self.cursor.min(end)..end].to_vec()
+        };
+        self.cursor += events.len();
+        self.stats.read(&events);
+        Ok(events)
+    }
+
+    async fn seek(&mut self, offset: u64) -> Result<()> {
+        self.cursor = offset as usize;
+        Ok(())
+    }
+
+    fn shares_filesystem(&self) -> bool {
+        false
+    }
+
+    async fn wait_for_traffic(&mut self, max: Duration) {
+        let _ = tokio::time::timeout(max, self.notify.notified()).await;
+    }
+
+    fn stats(&self) -> TransportStats {
+        let Ok(s) = self.lock() else {
+            return self.stats;
+        };
+        let (lag, backlog) = match self.role {
+            Role::Coordinator => (0, s.inbox.len().saturating_sub(self.cursor)),
+            Role::Worker => (
+                s.broadcasts.len().saturating_sub(self.cursor),
+                self.worker_id
+                    .as_ref()
+                    .and_then(|id| s.outboxes.get(id))
+                    .map_or(0, |q| q.len()),
+            ),
+        };
+        TransportStats {
+            read_lag: Some(lag as u64),
+            backlog: Some(backlog as u64),
+            ..self.stats
+        }
+    }
+}
+
+impl Drop for MemTransport {
+    fn drop(&mut self) {
+        if let Ok(mut s) = self.shared.lock() {
+            s.listeners.retain(|(_, n)| !Arc::ptr_eq(n, &self.notify));
+        }
+    }
+}
@@ -0,0 +1,583 @@
+// src/transport/uds.rs
+//
+// =============================================================================
+// UNIFIEDLAB: UNIX SOCKET TRANSPORT (v 0.1 )
+// =============================================================================
+//
+// The Short Nerve (unix only).
+//
+// With `--force-local` every participant is on one machine, so polling files
+// is pure latency. This variant talks over `root/coordinator.sock`:
+//
+// 1. Framing: one JSON object per line. Every connection opens with a
+//    `Hello`; a `from_offset` makes it a subscription.
+// 2. Worker -> Coordinator: `Frame`s land in an in-memory inbox, like gRPC
+//    RPCs (no inbox files, no discovery loop).
+// 3. Coordinator -> Workers: broadcasts are still appended to events.log
+//    (durability + cursor semantics unchanged) and pushed to subscribers
+//    the moment they are written. A new subscriber first replays the log
+//    from its cursor; replay and live fan-out share a lock, so nothing falls
+//    in between.
+// 4. Addressed messages (grants) go to the matching subscriber only and are
+//    not persisted; a grant sent while its worker is reconnecting is
+//    redelivered by the ack timeout (marketplace.rs).
+// 5. `wait_for_traffic` wakes as soon as something arrives.
+//
+// Inbox files left by an earlier file-transport run are drained once at
+// startup, so a switch of transport loses nothing.
+
+use super::{payload_bytes, FileTransport, Role, Transport, TransportStats};
+use crate::eventlog::{EventEnvelope, EventLogConfig, EventLogReader, EventLogWriter, EventRecord};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, Notify};
+use tokio::time::sleep;
+use uuid::Uuid;
+
+/// Socket the Coordinator listens on, inside `root`.
+pub const SOCKET_FILE: &str = "coordinator.sock";
+
+/// Backoff before a worker re-subscribes after a dropped connection.
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+/// Buffered messages before connections see backpressure.
+const INBOX_CAPACITY: usize = 4096;
+
+pub fn socket_path(root: &Path) -> PathBuf {
+    root.join(SOCKET_FILE)
+}
+
+/// True if a Coordinator is accepting connections on `root`'s socket
+/// (a leftover socket file from a crashed run does not count).
+pub fn is_live(root: &Path) -> bool {
+    std::os::unix::net::UnixStream::connect(socket_path(root)).is_ok()
+}
+
+// ============================================================================
+// 1. WIRE FORMAT
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Hello {
+    worker_id: Option<String>,
+    /// Some: stream broadcasts from this events.log offset on this connection.
+    from_offset: Option<u64>,
+}
+
+/// Worker -> Coordinator.
+#[derive(Debug, Serialize, Deserialize)]
+struct Frame {
+    kind: String,
+    payload: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    event_id: Option<Uuid>,
+}
+
+/// Coordinator -> Worker. Addressed messages carry offset 0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Delivery {
+    offset: u64,
+    next_offset: u64,
+    record: EventRecord,
+}
+
+impl From<EventEnvelope> for Delivery {
+    fn from(env: EventEnvelope) -> Self {
+        Self {
+            offset: env.offset,
+            next_offset: env.next_offset,
+            record: env.record,
+        }
+    }
+}
+
+impl From<Delivery> for EventEnvelope {
+    fn from(d: Delivery) -> Self {
+        Self {
+            offset: d.offset,
+            next_offset: d.next_offset,
+            record: d.record,
+        }
+    }
+}
+
+async fn write_line<T: Serialize>(
+    stream: &mut (impl AsyncWriteExt + Unpin),
+    msg: &T,
+) -> Result<()> {
+    let mut line = serde_json::to_vec(msg)?;
+    line.push(b'\n');
+    stream.write_all(&line).await?;
+    Ok(())
+}
+
+// ============================================================================
+// 2. SERVER SIDE (Coordinator)
+// ============================================================================
+
+struct Subscriber {
+    worker_id: Option<String>,
+    tx: mpsc::UnboundedSender<Delivery>,
+}
+
+/// The events.log writer and its live subscribers, behind one lock.
+struct Hub {
+    writer: EventLogWriter,
+    events_path: PathBuf,
+    subscribers: Vec<Subscriber>,
+}
+
+impl Hub {
+    fn broadcast(&mut self, kind: &str, payload: Value) -> Result<u64> {
+        let ts_ms = chrono::Utc::now().timestamp_millis();
+        let offset = self.writer.append_at(ts_ms, kind, payload.clone())?;
+        let d = Delivery {
+            offset,
+            next_offset: self.writer.end(),
+            record: EventRecord {
+                ts_ms,
+                kind: kind.to_string(),
+                payload,
+                event_id: None,
+            },
+        };
+        self.subscribers.retain(|s| s.tx.send(d.clone()).is_ok());
+        Ok(offset)
+    }
+
+    fn send_to(&mut self, worker_id: &str, kind: &str, payload: Value) -> usize {
+        let d = Delivery {
+            offset: 0,
+            next_offset: 0,
+            record: EventRecord {
+                ts_ms: chrono::Utc::now().timestamp_millis(),
+                kind: kind.to_string(),
+                payload,
+                event_id: None,
+            },
+        };
+        let mut sent = 0;
+        self.subscribers.retain(|s| {
+            if s.worker_id.as_deref() != Some(worker_id) {
+                return true;
+            }
+            let ok = s.tx.send(d.clone()).is_ok();
+            sent += ok as usize;
+            ok
+        });
+        sent
+    }
+
+    /// Replays events.log from `from` into `tx`, then keeps it for live fan-out.
+    fn subscribe(
+        &mut self,
+        worker_id: Option<String>,
+        from: u64,
+    ) -> Result<mpsc::UnboundedReceiver<Delivery>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut reader = EventLogReader::open(&self.events_path)?;
+        reader.seek(from)?;
+        while let Some(env) = reader.next()? {
+            let _ = tx.send(env.into());
+        }
+        self.subscribers.push(Subscriber { worker_id, tx });
+        Ok(rx)
+    }
+}
+
+async fn serve_connection(
+    stream: UnixStream,
+    hub: Arc<Mutex<Hub>>,
+    inbox: mpsc::Sender<EventEnvelope>,
+    arrived: Arc<Notify>,
+) -> Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    let hello: Hello = match lines.next_line().await? {
+        Some(l) => serde_json::from_str(&l).context("Bad hello")?,
+        None => return Ok(()),
+    };
+
+    if let Some(from) = hello.from_offset {
+        let mut rx = hub
+            .lock()
+            .map_err(|_| anyhow!("Hub lock poisoned"))?
+            .subscribe(hello.worker_id.clone(), from)?;
+        tokio::spawn(async move {
+            while let Some(d) = rx.recv().await {
+                if write_line(&mut write, &d).await.is_err() {
+                    return; // Subscriber went away
+                }
+            }
+        });
+    }
+
+    while let Some(line) = lines.next_line().await? {
+        let frame: Frame = match serde_json::from_str(&line) {
+            Ok(f) => f,
+            Err(e) => {
+                log::warn!("Skipping bad frame from {:?}: {}", hello.worker_id, e);
+                continue;
+            }
+        };
+        let env = EventEnvelope {
+            // Socket messages have no position in any log
+            offset: 0,
+            next_offset: 0,
+            record: EventRecord {
+                ts_ms: chrono::Utc::now().timestamp_millis(),
+                kind: frame.kind,
+                payload: frame.payload,
+                event_id: frame.event_id,
+            },
+        };
+        if inbox.send(env).await.is_err() {
+            return Ok(()); // Coordinator is shutting down
+        }
+        arrived.notify_one();
+    }
+    Ok(())
+}
+
+// ============================================================================
+// 3. CLIENT SIDE (Workers / Architect)
+// ============================================================================
+
+/// Keeps a subscription alive, resuming from the last seen log offset.
+fn spawn_feed(
+    path: PathBuf,
+    worker_id: Option<String>,
+    from: u64,
+    arrived: Arc<Notify>,
+) -> mpsc::Receiver<EventEnvelope> {
+    let (tx, rx) = mpsc::channel(1024);
+    tokio::spawn(async move {
+        let mut cursor = from;
+        loop {
+            match UnixStream::connect(&path).await {
+                Ok(mut stream) => {
+                    let hello = Hello {
+                        worker_id: worker_id.clone(),
+                        from_offset: Some(cursor),
+                    };
+                    if write_line(&mut stream, &hello).await.is_ok() {
+                        let mut lines = BufReader::new(stream).lines();
+                        while let Ok(Some(line)) = lines.next_line().await {
+                            let d: Delivery = match serde_json::from_str(&line) {
+                                Ok(d) => d,
+                                Err(e) => {
+                                    log::warn!("Bad delivery on {:?}: {}", path, e);
+                                    continue;
+                                }
+                            };
+                            if d.next_offset > 0 {
+                                cursor = d.next_offset;
+                            }
+                            if tx.send(d.into()).await.is_err() {
+                                return; // Transport dropped
+                            }
+                            arrived.notify_one();
+                        }
+                    }
+                    log::warn!("Coordinator socket {:?} closed", path);
+                }
+                Err(e) => log::debug!("Coordinator socket {:?} not ready: {}", path, e),
+            }
+
+            if tx.is_closed() {
+                return;
+            }
+            sleep(RECONNECT_DELAY).await;
+        }
+    });
+    rx
+}
+
+// ============================================================================
+// 4. THE TRANSPORT
+// ============================================================================
+
+enum Side {
+    Coordinator {
+        hub: Arc<Mutex<Hub>>,
+        inbox: mpsc::Receiver<EventEnvelope>,
+    },
+    Worker {
+        worker_id: Option<String>,
+        sender: Option<UnixStream>,
+        feed: Option<mpsc::Receiver<EventEnvelope>>,
+        cursor: u64,
+    },
+}
+
+pub struct UdsTransport {
+    path: PathBuf,
+    side: Side,
+    arrived: Arc<Notify>,
+    stats: TransportStats,
+}
+
+impl UdsTransport {
+    /// Coordinator: binds `root/coordinator.sock`.
+    /// Broadcasts are persisted to `root/events.log` exactly like FileTransport.
+    pub async fn serve(root_path: impl AsRef<Path>, log_cfg: EventLogConfig) -> Result<Self> {
+        let root = root_path.as_ref();
+        std::fs::create_dir_all(root)?;
+        let path = socket_path(root);
+        if path.exists() {
+            if is_live(root) {
+                return Err(anyhow!("Another Coordinator is listening on {:?}", path));
+            }
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Removing stale socket {:?}", path))?;
+        }
+
+        let (tx, rx) = mpsc::channel(INBOX_CAPACITY);
+        let arrived = Arc::new(Notify::new());
+
+        // Pick up whatever a file-transport run left unread
+        if root.join("inbox").exists() {
+            let mut files = FileTransport::new(root, Role::Coordinator, None).await?;
+            for env in files.recv_worker_messages().await? {
+                tx.send(env).await?;
+            }
+        }
+
+        let events_path = root.join("events.log");
+        let hub = Arc::new(Mutex::new(Hub {
+            writer: EventLogWriter::open(&events_path, log_cfg)?,
+            events_path,
+            subscribers: Vec::new(),
+        }));
+
+        let listener =
+            UnixListener::bind(&path).with_context(|| format!("Binding socket {:?}", path))?;
+        let (accept_hub, accept_arrived) = (hub.clone(), arrived.clone());
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let (hub, inbox, arrived) =
+                            (accept_hub.clone(), tx.clone(), accept_arrived.clone());
+                        tokio::spawn(async move {
+                            if let Err(e) = serve_connection(stream, hub, inbox, arrived).await {
+                                log::warn!("Socket connection dropped: {:#}", e);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        log::error!("Socket listener stopped: {}", e);
+                        return;
+                    }
+                }
+            }
+        });
+
+        log::info!("🔌 Local Marketplace listening on {:?}", path);
+        Ok(Self {
+            path,
+            side: Side::Coordinator { hub, inbox: rx },
+            arrived,
+            stats: TransportStats::default(),
+        })
+    }
+
+    /// Worker/Architect: talks to the Coordinator on `root/coordinator.sock`.
+    /// Connections are lazy, so workers may boot before the Coordinator.
+    pub fn connect(root_path: impl AsRef<Path>, worker_id: Option<&str>) -> Self {
+        Self {
+            path: socket_path(root_path.as_ref()),
+            side: Side::Worker {
+                worker_id: worker_id.map(str::to_string),
+                sender: None,
+                feed: None,
+                cursor: 0,
+            },
+            arrived: Arc::new(Notify::new()),
+            stats: TransportStats::default(),
+        }
+    }
+
+    async fn open_sender(path: &Path, worker_id: &Option<String>) -> Result<UnixStream> {
+        let mut stream = UnixStream::connect(path)
+            .await
+            .with_context(|| format!("No Coordinator on {:?}", path))?;
+        let hello = Hello {
+            worker_id: worker_id.clone(),
+            from_offset: None,
+        };
+        write_line(&mut stream, &hello).await?;
+        Ok(stream)
+    }
+
+    async fn send_frame(&mut self, frame: Frame) -> Result<()> {
+        let (worker_id, sender) = match &mut self.side {
+            Side::Worker {
+                worker_id, sender, ..
+            } => (worker_id, sender),
+            Side::Coordinator { .. } => return Err(anyhow!("Coordinator cannot send to self")),
+        };
+        self.stats.wrote(payload_bytes(&frame.payload));
+
+        // One retry on a fresh connection covers a restarted Coordinator
+        if let Some(stream) = sender.as_mut() {
+            if write_line(stream, &frame).await.is_ok() {
+                return Ok(());
+            }
+        }
+        let mut stream = Self::open_sender(&self.path, worker_id).await?;
+        write_line(&mut stream, &frame).await?;
+        *sender = Some(stream);
+        Ok(())
+    }
+
+    fn hub(&self) -> Result<std::sync::MutexGuard<'_, Hub>> {
+        match &self.side {
+            Side::Coordinator { hub, .. } => hub.lock().map_err(|_| anyhow!("Hub lock poisoned")),
+            Side::Worker { .. } => Err(anyhow!("Worker cannot broadcast")),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for UdsTransport {
+    async fn send_to_coordinator(&mut self, kind: &str, payload: Value) -> Result<()> {
+        self.send_frame(Frame {
+            kind: kind.to_string(),
+            payload,
+            event_id: None,
+        })
+        .await
+    }
+
+    async fn send_to_coordinator_with_id(
+        &mut self,
+        kind: &str,
+        payload: Value,
+        event_id: Uuid,
+    ) -> Result<()> {
+        self.send_frame(Frame {
+            kind: kind.to_string(),
+            payload,
+            event_id: Some(event_id),
+        })
+        .await
+    }
+
+    async fn broadcast(&mut self, kind: &str, payload: Value) -> Result<u64> {
+        let (offset, end) = {
+            let mut hub = self.hub()?;
+            let offset = hub.broadcast(kind, payload)?;
+            (offset, hub.writer.end())
+        };
+        self.stats.wrote(end - offset);
+        Ok(offset)
+    }
+
+    async fn send_to_worker(&mut self, worker_id: &str, kind: &str, payload: Value) -> Result<u64> {
+        self.stats.wrote(payload_bytes(&payload));
+        if self.hub()?.send_to(worker_id, kind, payload) == 0 {
+            log::debug!("{} not connected; '{}' dropped", worker_id, kind);
+        }
+        Ok(0)
+    }
+
+    async fn recv_broadcasts(&mut self) -> Result<Vec<EventEnvelope>> {
+        let (worker_id, feed, cursor) = match &mut self.side {
+            Side::Worker {
+                worker_id,
+                feed,
+                cursor,
+                ..
+            } => (worker_id, feed, cursor),
+            Side::Coordinator { .. } => return Ok(vec![]),
+        };
+
+        let rx = feed.get_or_insert_with(|| {
+            spawn_feed(
+                self.path.clone(),
+                worker_id.clone(),
+                *cursor,
+                self.arrived.clone(),
+            )
+        });
+        let mut events = Vec::new();
+        while let Ok(env) = rx.try_recv() {
+            if env.next_offset > 0 {
+                *cursor = env.next_offset;
+            }
+            events.push(env);
+            if events.len() > 1000 {
+                break;
+            }
+        }
+        self.stats.read(&events);
+        Ok(events)
+    }
+
+    async fn recv_worker_messages(&mut self) -> Result<Vec<EventEnvelope>> {
+        let inbox = match &mut self.side {
+            Side::Coordinator { inbox, .. } => inbox,
+            Side::Worker { .. } => return Ok(vec![]),
+        };
+
+        let mut events = Vec::new();
+        while let Ok(env) = inbox.try_recv() {
+            log::debug!("Read msg [{}] via socket", env.record.kind);
+            events.push(env);
+            if events.len() > 1000 {
+                break;
+            }
+        }
+        self.stats.read(&events);
+        Ok(events)
+    }
+
+    async fn seek(&mut self, offset: u64) -> Result<()> {
+        // The Coordinator never reads broadcasts back; workers re-subscribe.
+        if let Side::Worker { feed, cursor, .. } = &mut self.side {
+            *cursor = offset;
+            *feed = None;
+        }
+        Ok(())
+    }
+
+    async fn wait_for_traffic(&mut self, max: Duration) {
+        let _ = tokio::time::timeout(max, self.arrived.notified()).await;
+    }
+
+    fn stats(&self) -> TransportStats {
+        let (read_lag, backlog) = match &self.side {
+            Side::Coordinator { inbox, .. } => (None, inbox.len()),
+            Side::Worker { feed, cursor, .. } => {
+                // Same machine: the log the Coordinator appends to is right here
+                let events = self.path.with_file_name("events.log");
+                let lag = crate::eventlog::log_end(&events)
+                    .ok()
+                    .map(|end| end.saturating_sub(*cursor));
+                (lag, feed.as_ref().map_or(0, |rx| rx.len()))
+            }
+        };
+        TransportStats {
+            read_lag,
+            backlog: Some(backlog as u64),
+            ..self.stats
+        }
+    }
+}
+
+impl Drop for UdsTransport {
+    fn drop(&mut self) {
+        if matches!(self.side, Side::Coordinator { .. }) {
+            std::fs::remove_file(&self.path).ok();
+        }
+    }
+}
@@ -9,17 +9,31 @@
 // Features:
 // 1. Cluster Metrics (Cores, Throughput).
 // 2. Job Table (Filterable by Engine/Status).
-// 3. Deep Inspector (Engine-specific details & Provenance).
+// 3. Deep Inspector (Engine-specific details & Provenance; progress
+//    snapshots while a job runs).
 // 4. Real-time Log Stream.
+// 5. Deployments: switch between checkpoint DBs at runtime, reconnect when a
+//    DB file is replaced, and raise a banner when the Coordinator goes silent.
+// 6. Stats tab: per-engine jobs/hour, median queue and run times and failure
+//    rate over the last N hours, read incrementally from the DB.
 //
 // TODO:
 //   general usability improvements
 //   at some point post processing module implementation?
 
-use crate::checkpoint::{CheckpointStore, WorkerInfo};
-use crate::core::{ElectronVolts, Engine, Job, JobStatus, JobSummary};
+use crate::checkpoint::{ArtifactRef, CheckpointStore, ProgressRecord, WorkerInfo};
+use crate::core::{ElectronVolts, Engine, FileRole, Job, JobStatus, JobSummary};
+use crate::deadline::DeadlineStatus;
+use crate::eventlog::{same_file, EventIndex};
 use crate::logs::LogBuffer;
+use crate::marketplace::{
+    MemoStats, HEARTBEAT_EVERY, META_DEADLINES, META_DEADLOCKED, META_HEARTBEAT, META_MEMO,
+    META_QUOTAS,
+};
+use crate::quota::QuotaStatus;
+use crate::report::{human_bytes, human_ms, RecentJobs, Throughput};
 use crate::resources::SystemMonitor;
+use crate::transport::READ_LAG_WARN_BYTES;
 
 use anyhow::Result;
 use crossterm::{
@@ -32,17 +46,26 @@ use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
-        Block, Borders, Cell, Clear, Gauge, List, ListItem, Paragraph, Row, Scrollbar,
-        ScrollbarOrientation, ScrollbarState, Sparkline, Table, TableState, Tabs, Wrap,
+        Bar, BarChart, Block, Borders, Cell, Clear, Gauge, List, ListItem, Paragraph, Row,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, Sparkline, Table, TableState, Tabs, Wrap,
     },
     Frame,
 };
 use std::{
+    fs::Metadata,
     io,
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::{Duration, Instant},
 };
 
+/// Heartbeats the Coordinator may miss before the stale banner goes up.
+const STALE_AFTER_HEARTBEATS: u32 = 3;
+
+/// Windows the stats tab cycles through with `h`, in hours.
+const STATS_WINDOWS_H: [u64; 4] = [1, 6, 24, 72];
+/// Index of the STATS tab.
+const STATS_TAB: usize = 5;
+
 // --- Metrics Snapshot ---
 #[derive(Default)]
 struct ClusterMetrics {
@@ -51,6 +74,17 @@ struct ClusterMetrics {
     completed: usize,
     failed: usize,
     pending: usize,
+    /// Jobs the Coordinator cancelled because a parent can never complete.
+    deadlocked: u64,
+    /// Records in events.log and when the last one was written, from the
+    /// index (None without one: counting would mean reading the whole log).
+    event_records: Option<(u64, i64)>,
+    /// Memoization counters the Coordinator saved at its last expansion.
+    memo: Option<MemoStats>,
+    /// Quota limits and usage at the Coordinator's last heartbeat.
+    quotas: Vec<QuotaStatus>,
+    /// Deadlines with unfinished jobs at the Coordinator's last heartbeat.
+    deadlines: Vec<DeadlineStatus>,
 
     // Hardware
     cores_allocated: usize,
@@ -60,14 +94,27 @@ struct ClusterMetrics {
 }
 
 pub struct TuiApp {
+    deployments: Vec<PathBuf>,
+    current_deployment: usize,
     ckpt_path: PathBuf,
     store: Option<CheckpointStore>,
+    /// The DB file the store was opened on, to notice it being replaced.
+    store_file: Option<Metadata>,
+    /// Last `META_HEARTBEAT` read from the DB (None if it never had one).
+    heartbeat_ms: Option<i64>,
     log_buffer: LogBuffer,
 
     // Data
-    jobs_summary: Vec<JobSummary>,
+    recent_jobs: RecentJobs,
     visible_jobs: Vec<JobSummary>,
     workers: Vec<WorkerInfo>,
+    /// Filter box text (`/`); applied with Enter, run again on every refresh.
+    search: String,
+    search_editing: bool,
+    /// Some while a search is applied: the matches replace `recent_jobs`
+    /// as the rows the tabs filter.
+    search_hits: Option<Vec<JobSummary>>,
+    search_error: Option<String>,
 
     // UI State
     table_state: TableState,
@@ -85,21 +132,33 @@ pub struct TuiApp {
     last_refresh: Instant,
     refresh_period: Duration,
     metrics: ClusterMetrics,
+    throughput: Throughput,
+    stats_window: usize,
 }
 
 impl TuiApp {
-    pub fn new(ckpt_path: &str, log_buffer: LogBuffer) -> Self {
+    /// `checkpoints` lists the deployments to switch between; the first is shown.
+    pub fn new(checkpoints: &[String], log_buffer: LogBuffer) -> Self {
         let mut sys = SystemMonitor::new();
         let env = sys.snapshot();
         let cluster_info = format!("{:?} ({})", env.cluster_type, env.hostname);
+        let deployments: Vec<PathBuf> = checkpoints.iter().map(PathBuf::from).collect();
 
         Self {
-            ckpt_path: PathBuf::from(ckpt_path),
+            ckpt_path: deployments.first().cloned().unwrap_or_default(),
+            deployments,
+            current_deployment: 0,
             store: None,
+            store_file: None,
+            heartbeat_ms: None,
             log_buffer,
-            jobs_summary: Vec::new(),
+            recent_jobs: RecentJobs::new(),
             visible_jobs: Vec::new(),
             workers: Vec::new(),
+            search: String::new(),
+            search_editing: false,
+            search_hits: None,
+            search_error: None,
             table_state: TableState::default(),
             scrollbar_state: ScrollbarState::default(),
             current_tab: 0,
@@ -113,9 +172,15 @@ impl TuiApp {
             last_refresh: Instant::now(),
             refresh_period: Duration::from_millis(500),
             metrics: ClusterMetrics::default(),
+            throughput: Throughput::new(Self::stats_window(0)),
+            stats_window: 0,
         }
     }
 
+    fn stats_window(i: usize) -> Duration {
+        Duration::from_secs(STATS_WINDOWS_H[i] * 3600)
+    }
+
     pub fn run(&mut self) -> Result<()> {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
@@ -148,12 +213,30 @@ impl TuiApp {
     // --- Data Management ---
 
     fn refresh_data(&mut self) {
+        // 0. Reconnect if the DB file was removed or replaced under us
+        if let Some(opened) = &self.store_file {
+            let replaced = match std::fs::metadata(&self.ckpt_path) {
+                Ok(on_disk) => !same_file(opened, &on_disk),
+                Err(_) => true,
+            };
+            if replaced {
+                log::warn!("Checkpoint {:?} was replaced; reconnecting", self.ckpt_path);
+                self.store = None;
+                self.store_file = None;
+                self.heartbeat_ms = None;
+                self.throughput = Throughput::new(self.throughput.window());
+            }
+        }
+
         // 1. Connect (Lazy)
         if self.store.is_none() {
             if self.ckpt_path.exists() {
                 match CheckpointStore::open(&self.ckpt_path) {
                     Ok(s) => {
                         self.store = Some(s);
+                        self.store_file = std::fs::metadata(&self.ckpt_path).ok();
+                        // A replaced file starts over; until now the old rows stayed up
+                        self.recent_jobs = RecentJobs::new();
                         self.status_msg = "ONLINE".into();
                         self.status_color = Color::Green;
                     }
@@ -172,20 +255,63 @@ impl TuiApp {
 
         // 2. Fetch
         let (fetched_workers, fetched_jobs) = if let Some(store) = &self.store {
-            (
-                store.get_active_workers().ok(),
-                store.get_jobs_summary().ok(),
-            )
+            if let Ok(Some(n)) = store.get_meta(META_DEADLOCKED) {
+                self.metrics.deadlocked = n.parse().unwrap_or(0);
+            }
+            if let Ok(Some(m)) = store.get_meta(META_MEMO) {
+                self.metrics.memo = serde_json::from_str(&m).ok();
+            }
+            if let Ok(Some(q)) = store.get_meta(META_QUOTAS) {
+                self.metrics.quotas = serde_json::from_str(&q).unwrap_or_default();
+            }
+            if let Ok(Some(d)) = store.get_meta(META_DEADLINES) {
+                self.metrics.deadlines = serde_json::from_str(&d).unwrap_or_default();
+            }
+            if let Ok(Some(h)) = store.get_meta(META_HEARTBEAT) {
+                self.heartbeat_ms = h.parse().ok();
+            }
+            let now_ms = chrono::Utc::now().timestamp_millis();
+            if let Err(e) = self.throughput.refresh(store, now_ms) {
+                log::debug!("Throughput refresh failed: {}", e);
+            }
+            // While the query is being edited, the last hits stay on screen
+            if !self.search.trim().is_empty() && !self.search_editing {
+                match store.search_jobs(&self.search) {
+                    Ok(hits) => {
+                        self.search_hits = Some(hits.into_iter().map(|h| h.job).collect());
+                        self.search_error = None;
+                    }
+                    Err(e) => {
+                        self.search_hits = Some(Vec::new());
+                        self.search_error = Some(format!("{:#}", e));
+                    }
+                }
+            }
+            // Only the rows updated since the last refresh are parsed
+            let jobs = match self.recent_jobs.refresh(store) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::debug!("Job summary refresh failed: {}", e);
+                    false
+                }
+            };
+            (store.get_active_workers().ok(), jobs)
         } else {
-            (None, None)
+            (None, false)
         };
 
+        if let Some(root) = self.ckpt_path.parent() {
+            self.metrics.event_records = EventIndex::open(&root.join("events.log"))
+                .ok()
+                .flatten()
+                .and_then(|idx| Some((idx.len(), idx.last().ok()??.ts_ms)));
+        }
+
         // 3. Update
         if let Some(w) = fetched_workers {
             self.workers = w;
         }
-        if let Some(j) = fetched_jobs {
-            self.jobs_summary = j;
+        if fetched_jobs {
             self.recalc_metrics();
             self.apply_tab_filter();
         }
@@ -208,21 +334,83 @@ impl TuiApp {
         if let Some(id) = id_to_fetch {
             if let Some(store) = &self.store {
                 if let Ok(job) = store.get_job_details(&id) {
-                    self.inspector_lines = Self::format_inspector(&job);
+                    let artifacts = store.get_job_artifacts(&id).unwrap_or_default();
+                    let progress = match job.status {
+                        JobStatus::Running => store.get_job_progress(&id).unwrap_or_default(),
+                        _ => Vec::new(),
+                    };
+                    self.inspector_lines =
+                        Self::format_inspector(&job, &artifacts, &progress, &self.workers);
                 }
             }
         }
     }
 
+    /// Shows another deployment, starting over as if the TUI had just launched.
+    fn switch_deployment(&mut self, delta: isize) {
+        let n = self.deployments.len();
+        if n < 2 {
+            return;
+        }
+        self.current_deployment =
+            (self.current_deployment as isize + delta).rem_euclid(n as isize) as usize;
+        self.ckpt_path = self.deployments[self.current_deployment].clone();
+        self.store = None;
+        self.store_file = None;
+        self.heartbeat_ms = None;
+        self.recent_jobs = RecentJobs::new();
+        self.visible_jobs.clear();
+        self.workers.clear();
+        self.selected_job_id.clear();
+        self.inspector_lines = vec![Line::from("Select a node to inspect payload")];
+        self.table_state.select(None);
+        self.metrics = ClusterMetrics::default();
+        self.throughput = Throughput::new(self.throughput.window());
+        self.refresh_data();
+    }
+
+    /// Next stats window; the jobs are read again for it.
+    fn cycle_stats_window(&mut self) {
+        self.stats_window = (self.stats_window + 1) % STATS_WINDOWS_H.len();
+        self.throughput = Throughput::new(Self::stats_window(self.stats_window));
+        self.refresh_data();
+    }
+
+    /// Short name of a deployment: the directory holding its checkpoint.
+    fn deployment_name(path: &Path) -> String {
+        path.parent()
+            .and_then(Path::file_name)
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string())
+    }
+
+    /// Why the data on screen may be out of date, if it is.
+    fn stale_banner(&self) -> Option<String> {
+        if self.store.is_none() && !self.recent_jobs.jobs().is_empty() {
+            return Some(format!(
+                "Checkpoint {} is gone; showing the last data read",
+                self.ckpt_path.display()
+            ));
+        }
+        let silent_ms = chrono::Utc::now().timestamp_millis() - self.heartbeat_ms?;
+        let limit = HEARTBEAT_EVERY * STALE_AFTER_HEARTBEATS;
+        (silent_ms > limit.as_millis() as i64).then(|| {
+            format!(
+                "Coordinator silent for {}s; it may have stopped. Data below is from its last write",
+                silent_ms / 1000
+            )
+        })
+    }
+
     fn recalc_metrics(&mut self) {
         let m = &mut self.metrics;
-        m.total_jobs = self.jobs_summary.len();
+        m.total_jobs = self.recent_jobs.jobs().len();
         m.running = 0;
         m.completed = 0;
         m.failed = 0;
         m.pending = 0;
 
-        for j in &self.jobs_summary {
+        for j in self.recent_jobs.jobs() {
             match j.status.as_str() {
                 "Running" => m.running += 1,
                 "Completed" => m.completed += 1,
@@ -250,7 +438,9 @@ impl TuiApp {
 
     fn apply_tab_filter(&mut self) {
         self.visible_jobs = self
-            .jobs_summary
+            .search_hits
+            .as_ref()
+            .map_or(self.recent_jobs.jobs(), |hits| hits.as_slice())
             .iter()
             .filter(|j| match self.current_tab {
                 0 => true,
@@ -258,6 +448,7 @@ impl TuiApp {
                 2 => j.status == "Completed",
                 3 => j.status == "Failed",
                 4 => j.code.contains("agent"),
+                STATS_TAB => false,
                 _ => true,
             })
             .cloned()
@@ -269,7 +460,23 @@ impl TuiApp {
     // --- UI Layout ---
 
     fn ui(&mut self, f: &mut Frame) {
-        let area = f.area();
+        let mut area = f.area();
+        if let Some(text) = self.stale_banner() {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(area);
+            f.render_widget(
+                Paragraph::new(format!(" ⚠ {} ", text)).style(
+                    Style::default()
+                        .bg(Color::Red)
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                rows[0],
+            );
+            area = rows[1];
+        }
         let layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([
@@ -289,17 +496,17 @@ impl TuiApp {
     }
 
     fn draw_sidebar(&self, f: &mut Frame, area: Rect) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(10),
-                Constraint::Length(3),
-                Constraint::Length(6),
-                Constraint::Min(0),
-            ])
-            .split(area);
+        // Alert slot: deadlocked jobs take the spacer line
+        let alert = if self.metrics.deadlocked > 0 {
+            Line::from(Span::styled(
+                format!("⚠ {} deadlocked", self.metrics.deadlocked),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ))
+        } else {
+            Line::from("")
+        };
 
-        let info_text = vec![
+        let mut info_text = vec![
             Line::from(Span::styled(
                 " UNIFIEDLAB v6 ",
                 Style::default()
@@ -315,7 +522,7 @@ impl TuiApp {
                 Span::raw("DB:    "),
                 Span::styled(&self.status_msg, Style::default().fg(self.status_color)),
             ]),
-            Line::from(""),
+            alert,
             Line::from(vec![
                 Span::raw("Total: "),
                 Span::styled(
@@ -344,7 +551,118 @@ impl TuiApp {
                     Style::default().fg(Color::Red),
                 ),
             ]),
+            Line::from(vec![
+                Span::raw("Log:   "),
+                match self.metrics.event_records {
+                    Some((n, ts_ms)) => Span::styled(
+                        format!(
+                            "{} ev, {}s ago",
+                            n,
+                            (chrono::Utc::now().timestamp_millis() - ts_ms).max(0) / 1000
+                        ),
+                        Style::default().fg(Color::Gray),
+                    ),
+                    None => Span::styled("-", Style::default().fg(Color::DarkGray)),
+                },
+            ]),
+            Line::from(vec![
+                Span::raw("Memo:  "),
+                match &self.metrics.memo {
+                    Some(m) if m.total.lookups > 0 => Span::styled(
+                        format!(
+                            "{}/{} hit ({:.0}%)",
+                            m.total.hits,
+                            m.total.lookups,
+                            m.total.hit_rate() * 100.0
+                        ),
+                        Style::default().fg(Color::Gray),
+                    ),
+                    _ => Span::styled("-", Style::default().fg(Color::DarkGray)),
+                },
+            ]),
         ];
+        if !self.metrics.quotas.is_empty() {
+            let full: Vec<&str> = self
+                .metrics
+                .quotas
+                .iter()
+                .filter(|q| q.used.at_limit(&q.limit))
+                .map(|q| q.bucket.as_str())
+                .collect();
+            info_text.push(Line::from(vec![
+                Span::raw("Quota: "),
+                if full.is_empty() {
+                    Span::styled(
+                        format!("{} ok", self.metrics.quotas.len()),
+                        Style::default().fg(Color::Gray),
+                    )
+                } else {
+                    Span::styled(
+                        format!("full {}", full.join(",")),
+                        Style::default().fg(Color::Yellow),
+                    )
+                },
+            ]));
+        }
+        if !self.metrics.deadlines.is_empty() {
+            let late: Vec<&DeadlineStatus> = self
+                .metrics
+                .deadlines
+                .iter()
+                .filter(|d| d.is_late())
+                .collect();
+            let worst = late.iter().map(|d| d.slack_s).min();
+            info_text.push(Line::from(vec![
+                Span::raw("Due:   "),
+                match worst {
+                    Some(slack) => Span::styled(
+                        format!("{} late, {}m behind", late.len(), -slack / 60),
+                        Style::default().fg(Color::Red),
+                    ),
+                    None => Span::styled(
+                        format!(
+                            "{} ok, {}m slack",
+                            self.metrics.deadlines.len(),
+                            self.metrics
+                                .deadlines
+                                .iter()
+                                .map(|d| d.slack_s)
+                                .min()
+                                .unwrap_or(0)
+                                / 60
+                        ),
+                        Style::default().fg(Color::Gray),
+                    ),
+                },
+            ]));
+        }
+        if self.deployments.len() > 1 {
+            info_text.insert(
+                3,
+                Line::from(vec![
+                    Span::raw("Dep:   "),
+                    Span::styled(
+                        format!(
+                            "{} ({}/{})",
+                            Self::deployment_name(&self.ckpt_path),
+                            self.current_deployment + 1,
+                            self.deployments.len()
+                        ),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                ]),
+            );
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(info_text.len() as u16 + 2),
+                Constraint::Length(3),
+                Constraint::Length(6),
+                Constraint::Min(0),
+            ])
+            .split(area);
         f.render_widget(
             Paragraph::new(info_text).block(Block::default().borders(Borders::ALL)),
             chunks[0],
@@ -380,16 +698,25 @@ impl TuiApp {
                 } else {
                     0.0
                 };
+                let lagging = w.read_lag.filter(|l| *l >= READ_LAG_WARN_BYTES);
                 let color = if load > 0.8 {
                     Color::Red
+                } else if lagging.is_some() {
+                    Color::Yellow
                 } else if load > 0.0 {
                     Color::Green
                 } else {
                     Color::Gray
                 };
                 let short_id = w.worker_id.split('_').next().unwrap_or("?");
-                ListItem::new(format!("{} [{}]", short_id, w.tasks))
-                    .style(Style::default().fg(color))
+                let mut label = match lagging {
+                    Some(lag) => format!("{} [{}] lag {} KiB", short_id, w.tasks, lag / 1024),
+                    None => format!("{} [{}]", short_id, w.tasks),
+                };
+                if !w.tags.is_empty() {
+                    label.push_str(&format!(" {}", w.tags.join(",")));
+                }
+                ListItem::new(label).style(Style::default().fg(color))
             })
             .collect();
         f.render_widget(
@@ -408,17 +735,38 @@ impl TuiApp {
             ])
             .split(area);
 
-        let tabs = Tabs::new(vec![" ALL ", " ACTIVE ", " DONE ", " FAILED ", " AGENTS "])
-            .block(Block::default().borders(Borders::ALL))
-            .select(self.current_tab)
-            .highlight_style(
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .divider("|");
+        let tabs = Tabs::new(vec![
+            " ALL ", " ACTIVE ", " DONE ", " FAILED ", " AGENTS ", " STATS ",
+        ])
+        .block(Block::default().borders(Borders::ALL))
+        .select(self.current_tab)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .divider("|");
         f.render_widget(tabs, chunks[0]);
 
+        if self.current_tab == STATS_TAB {
+            self.draw_stats(f, chunks[1]);
+        } else {
+            self.draw_jobs(f, chunks[1]);
+        }
+
+        let logs = self.log_buffer.get_lines();
+        let log_list = List::new(
+            logs.iter()
+                .rev()
+                .take(6)
+                .map(|s| ListItem::new(format!("> {}", s)))
+                .collect::<Vec<_>>(),
+        )
+        .block(Block::default().borders(Borders::TOP).title("Events"));
+        f.render_widget(log_list, chunks[2]);
+    }
+
+    fn draw_jobs(&mut self, f: &mut Frame, area: Rect) {
         let rows: Vec<Row> = self
             .visible_jobs
             .iter()
@@ -454,26 +802,137 @@ impl TuiApp {
             Row::new(vec!["ID", "Status", "Engine", "Time"])
                 .style(Style::default().fg(Color::Cyan)),
         )
-        .block(Block::default().borders(Borders::LEFT | Borders::RIGHT))
+        .block(self.search_block())
         .row_highlight_style(Style::default().bg(Color::Rgb(40, 40, 40)));
 
-        f.render_stateful_widget(table, chunks[1], &mut self.table_state);
+        f.render_stateful_widget(table, area, &mut self.table_state);
         f.render_stateful_widget(
             Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight),
-            chunks[1],
+            area,
             &mut self.scrollbar_state,
         );
+    }
 
-        let logs = self.log_buffer.get_lines();
-        let log_list = List::new(
-            logs.iter()
-                .rev()
-                .take(6)
-                .map(|s| ListItem::new(format!("> {}", s)))
-                .collect::<Vec<_>>(),
+    /// The jobs table's frame, titled with the filter box when one is open.
+    fn search_block(&self) -> Block<'static> {
+        let block = Block::default().borders(Borders::LEFT | Borders::RIGHT);
+        if !self.search_editing && self.search_hits.is_none() {
+            return block;
+        }
+        let cursor = if self.search_editing { "▏" } else { "" };
+        let (detail, color) = match (&self.search_error, &self.search_hits) {
+            (Some(e), _) => (format!(" — {}", e), Color::Red),
+            (None, Some(hits)) if !self.search_editing => {
+                (format!(" — {} match(es)", hits.len()), Color::Cyan)
+            }
+            _ => (String::new(), Color::Yellow),
+        };
+        block.title(Span::styled(
+            format!(" / {}{}{} ", self.search, cursor, detail),
+            Style::default().fg(color),
+        ))
+    }
+
+    /// Is the cluster keeping up? Per-engine throughput over the window.
+    fn draw_stats(&self, f: &mut Frame, area: Rect) {
+        let stats = self.throughput.rows();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(stats.len() as u16 + 3),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        let dash = || "-".to_string();
+        let rows: Vec<Row> = stats
+            .iter()
+            .map(|r| {
+                let fail_color = if r.failure_rate() > 0.1 {
+                    Color::Red
+                } else {
+                    Color::Gray
+                };
+                let row = Row::new(vec![
+                    Cell::from(r.code.clone()),
+                    Cell::from(format!("{:.1}", r.per_hour)),
+                    Cell::from(r.median_queue_ms.map(human_ms).unwrap_or_else(dash)),
+                    Cell::from(r.median_runtime_ms.map(human_ms).unwrap_or_else(dash)),
+                    Cell::from(format!("{:.0}%", r.failure_rate() * 100.0))
+                        .style(Style::default().fg(fail_color)),
+                    Cell::from((r.completed + r.failed).to_string()),
+                ]);
+                if r.code == "all" {
+                    row.style(Style::default().add_modifier(Modifier::BOLD))
+                } else {
+                    row
+                }
+            })
+            .collect();
+        let title = format!(
+            " Last {}h · {} waiting · h: window ",
+            self.throughput.window().as_secs() / 3600,
+            self.metrics.pending
+        );
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(15),
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(7),
+                Constraint::Length(6),
+            ],
         )
-        .block(Block::default().borders(Borders::TOP).title("Events"));
-        f.render_widget(log_list, chunks[2]);
+        .header(
+            Row::new(vec![
+                "Engine",
+                "Done/h",
+                "Queue p50",
+                "Run p50",
+                "Fail",
+                "Jobs",
+            ])
+            .style(Style::default().fg(Color::Cyan)),
+        )
+        .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(table, chunks[0]);
+
+        // Charts leave out the "all" row
+        let engines: Vec<_> = stats.iter().filter(|r| r.code != "all").collect();
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[1]);
+        let rate_bars: Vec<Bar> = engines
+            .iter()
+            .map(|r| {
+                Bar::with_label(r.code.clone(), (r.per_hour * 10.0).round() as u64)
+                    .text_value(format!("{:.1}", r.per_hour))
+            })
+            .collect();
+        f.render_widget(
+            BarChart::horizontal(rate_bars)
+                .block(Block::default().borders(Borders::ALL).title("Done / h"))
+                .bar_style(Style::default().fg(Color::Green)),
+            halves[0],
+        );
+        let fail_bars: Vec<Bar> = engines
+            .iter()
+            .map(|r| {
+                let pct = r.failure_rate() * 100.0;
+                Bar::with_label(r.code.clone(), pct.round() as u64)
+                    .text_value(format!("{:.0}%", pct))
+            })
+            .collect();
+        f.render_widget(
+            BarChart::horizontal(fail_bars)
+                .max(100)
+                .block(Block::default().borders(Borders::ALL).title("Failure rate"))
+                .bar_style(Style::default().fg(Color::Red)),
+            halves[1],
+        );
     }
 
     fn draw_inspector(&self, f: &mut Frame, area: Rect) {
@@ -486,7 +945,12 @@ impl TuiApp {
         );
     }
 
-    fn format_inspector(job: &Job) -> Vec<Line<'static>> {
+    fn format_inspector(
+        job: &Job,
+        artifacts: &[ArtifactRef],
+        progress: &[ProgressRecord],
+        workers: &[WorkerInfo],
+    ) -> Vec<Line<'static>> {
         let mut lines = Vec::new();
         let status_style = match job.status {
             JobStatus::Running => Style::default()
@@ -502,6 +966,10 @@ impl TuiApp {
             Span::styled("ID: ", Style::default().fg(Color::Cyan)),
             Span::raw(job.id.to_string()),
         ]));
+        lines.push(Line::from(vec![
+            Span::styled("Trace: ", Style::default().fg(Color::Cyan)),
+            Span::raw(job.trace_id.clone()),
+        ]));
         lines.push(Line::from(vec![
             Span::styled("St: ", Style::default().fg(Color::Cyan)),
             Span::styled(format!("{:?}", job.status), status_style),
@@ -512,6 +980,14 @@ impl TuiApp {
                 Span::raw(node.clone()),
             ]));
         }
+        if job.status == JobStatus::Pending {
+            if let Some(why) = Self::unmatched(job, workers) {
+                lines.push(Line::from(Span::styled(
+                    format!("⚠ {}", why),
+                    Style::default().fg(Color::Yellow),
+                )));
+            }
+        }
 
         lines.push(Line::from(""));
         lines.push(Line::from(Span::styled(
@@ -580,6 +1056,47 @@ impl TuiApp {
                     Span::raw(strategy.clone()),
                 ]));
             }
+            Engine::Pipeline { steps } => {
+                lines.push(Line::from(vec![
+                    Span::raw("Type: "),
+                    Span::styled("Pipeline", Style::default().fg(Color::Cyan)),
+                ]));
+                for (i, step) in steps.iter().enumerate() {
+                    lines.push(Line::from(vec![
+                        Span::raw(format!("  {}. ", i + 1)),
+                        Span::raw(step.code()),
+                    ]));
+                }
+            }
+        }
+
+        if let Some(latest) = progress.last() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                " PROGRESS ",
+                Style::default().bg(Color::DarkGray),
+            )));
+            let age = chrono::Utc::now().timestamp_millis() - latest.ts_ms;
+            lines.push(Line::from(vec![
+                Span::raw("Snapshots: "),
+                Span::raw(progress.len().to_string()),
+                Span::styled(
+                    format!("  last {} ago", human_ms(age.max(0))),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+            if let Some(ElectronVolts(ev)) = latest.snapshot.energy {
+                let previous = progress.iter().rev().nth(1);
+                let delta = previous
+                    .and_then(|p| p.snapshot.energy)
+                    .map(|ElectronVolts(before)| format!("  Δ {:+.4}", ev - before))
+                    .unwrap_or_default();
+                lines.push(Line::from(vec![
+                    Span::raw("Energy: "),
+                    Span::styled(format!("{:.4} eV", ev), Style::default().fg(Color::Yellow)),
+                    Span::styled(delta, Style::default().fg(Color::DarkGray)),
+                ]));
+            }
         }
 
         if let Some(res) = &job.result {
@@ -602,6 +1119,19 @@ impl TuiApp {
                     Style::default().fg(Color::Cyan),
                 ),
             ]));
+            for (i, step) in res.steps.iter().enumerate() {
+                let energy = step
+                    .energy
+                    .map(|ElectronVolts(ev)| format!("  {:.4} eV", ev))
+                    .unwrap_or_default();
+                lines.push(Line::from(vec![
+                    Span::raw(format!("  {}. {} ", i + 1, step.engine)),
+                    Span::styled(
+                        format!("{:.1}ms{}", step.t_total_ms, energy),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]));
+            }
 
             lines.push(Line::from(vec![
                 Span::raw("Host:   "),
@@ -629,6 +1159,39 @@ impl TuiApp {
             }
         }
 
+        // Outputs only: the inputs are what the job was submitted with
+        let outputs: Vec<_> = artifacts
+            .iter()
+            .filter(|a| a.role == FileRole::Output)
+            .collect();
+        if !outputs.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                " ARTIFACTS ",
+                Style::default().bg(Color::DarkGray),
+            )));
+            for a in outputs.iter().take(8) {
+                lines.push(Line::from(vec![
+                    Span::raw(format!("{} ", a.path)),
+                    Span::styled(
+                        format!(
+                            "{} {} {}",
+                            a.kind,
+                            human_bytes(a.size),
+                            &a.hash[..8.min(a.hash.len())]
+                        ),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]));
+            }
+            if outputs.len() > 8 {
+                lines.push(Line::from(Span::styled(
+                    format!("  +{} more", outputs.len() - 8),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+        }
+
         if let Some(err) = &job.error_log {
             lines.push(Line::from(""));
             lines.push(Line::from(Span::styled(
@@ -646,6 +1209,23 @@ impl TuiApp {
         lines
     }
 
+    /// Why no known worker could take `job`, from the tags and free GPUs
+    /// they last reported. None while no worker has reported at all.
+    fn unmatched(job: &Job, workers: &[WorkerInfo]) -> Option<String> {
+        let req = &job.resources;
+        if workers.is_empty() {
+            return None;
+        }
+        let eligible: Vec<_> = workers.iter().filter(|w| w.accepts(req)).collect();
+        if eligible.is_empty() {
+            return Some(format!("No worker tagged {}", req.required_tags.join(",")));
+        }
+        if req.gpus > 0 && !eligible.iter().any(|w| w.gpus >= req.gpus) {
+            return Some(format!("No worker with {} free GPU(s)", req.gpus));
+        }
+        None
+    }
+
     fn handle_input(&mut self, key: event::KeyEvent) {
         if key.kind != KeyEventKind::Press {
             return;
@@ -659,12 +1239,21 @@ impl TuiApp {
             }
             return;
         }
+        if self.search_editing {
+            self.handle_search_input(key.code);
+            return;
+        }
         match key.code {
+            KeyCode::Esc if self.search_hits.is_some() => self.clear_search(),
             KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char('/') => self.search_editing = true,
             KeyCode::Char('?') => self.show_help = true,
             KeyCode::Char('r') => self.refresh_data(),
+            KeyCode::Char(']') => self.switch_deployment(1),
+            KeyCode::Char('[') => self.switch_deployment(-1),
+            KeyCode::Char('h') => self.cycle_stats_window(),
             KeyCode::Tab => {
-                self.current_tab = (self.current_tab + 1) % 5;
+                self.current_tab = (self.current_tab + 1) % 6;
                 self.table_state.select(Some(0));
                 self.refresh_data();
             }
@@ -674,6 +1263,33 @@ impl TuiApp {
         }
     }
 
+    /// Typing in the filter box: Enter applies it, Esc drops it.
+    fn handle_search_input(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) => self.search.push(c),
+            KeyCode::Backspace => {
+                self.search.pop();
+            }
+            KeyCode::Enter if self.search.trim().is_empty() => self.clear_search(),
+            KeyCode::Enter => {
+                self.search_editing = false;
+                self.table_state.select(Some(0));
+                self.refresh_data();
+            }
+            KeyCode::Esc => self.clear_search(),
+            _ => {}
+        }
+    }
+
+    fn clear_search(&mut self) {
+        self.search.clear();
+        self.search_editing = false;
+        self.search_hits = None;
+        self.search_error = None;
+        self.table_state.select(Some(0));
+        self.refresh_data();
+    }
+
     fn move_selection(&mut self, delta: i32) {
         if self.visible_jobs.is_empty() {
             return;
@@ -694,7 +1310,7 @@ impl TuiApp {
             .title("Help")
             .borders(Borders::ALL)
             .style(Style::default().bg(Color::DarkGray));
-        let text = "[Keys]\nq: Quit\nr: Refresh\nTab: Switch View\nj/k: Nav\n?: Toggle Help";
+        let text = "[Keys]\nq: Quit\nr: Refresh\nTab: Switch View\nj/k: Nav\n[/]: Switch Deployment\nh: Stats Window\n/: Search (Enter apply, Esc clear)\n?: Toggle Help";
         f.render_widget(
             Paragraph::new(text)
                 .block(block)
@@ -0,0 +1,200 @@
+// src/wire.rs
+//
+// =============================================================================
+// UNIFIEDLAB: WIRE CODEC (v 0.1 )
+// =============================================================================
+//
+// The Freight Layer.
+//
+// Serializing large `Structure`s to JSON inside every grant/submit event is a
+// hotspot (and bloats events.log). This module moves heavy structures out of
+// the event payload:
+//
+// 1. Binary Encoding: Structures are packed with Bincode (positions as raw f64).
+// 2. CAS Transfer: The packed bytes go into the ArtifactStore, named by hash.
+// 3. By-Reference Payloads: The Job keeps an empty atom list plus a
+//    `StructureBlob { hash, n_atoms }` pointer.
+// 4. Small structures stay inline (simpler, no extra file round-trip).
+
+use crate::core::{Atom, Job, Lattice, Structure, StructureBlob, STRUCTURE_SCHEMA_VERSION};
+use crate::provenance::ArtifactStore;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Structures with more atoms than this are offloaded by default.
+pub const DEFAULT_INLINE_MAX_ATOMS: usize = 256;
+
+/// File extension used for packed structures in the CAS.
+pub const STRUCTURE_BLOB_EXT: &str = "ustruct";
+
+// ============================================================================
+// 1. BINARY ENCODING
+// ============================================================================
+
+/// Bincode-friendly mirror of `Structure`.
+/// `serde_json::Value` metadata cannot round-trip through Bincode, so it is
+/// carried as raw JSON bytes.
+#[derive(Serialize, Deserialize)]
+struct WireStructure {
+    id: Uuid,
+    symbols: Vec<String>,
+    positions: Vec<[f64; 3]>,
+    charges: Vec<Option<f64>>,
+    magnetic_moments: Vec<Option<f64>>,
+    tags: Vec<HashMap<String, String>>,
+    lattice: Option<Lattice>,
+    source: String,
+    metadata_json: Vec<u8>,
+}
+
+/// Packs a structure into the compact binary form.
+pub fn encode_structure(s: &Structure) -> Result<Vec<u8>> {
+    let n = s.atoms.len();
+    let mut wire = WireStructure {
+        id: s.id,
+        symbols: Vec::with_capacity(n),
+        positions: Vec::with_capacity(n),
+        charges: Vec::with_capacity(n),
+        magnetic_moments: Vec::with_capacity(n),
+        tags: Vec::with_capacity(n),
+        lattice: s.lattice.clone(),
+        source: s.source.clone(),
+        metadata_json: serde_json::to_vec(&s.metadata)?,
+    };
+
+    for a in &s.atoms {
+        wire.symbols.push(a.symbol.clone());
+        wire.positions.push(a.position);
+        wire.charges.push(a.charge);
+        wire.magnetic_moments.push(a.magnetic_moment);
+        wire.tags.push(a.tags.clone());
+    }
+
+    bincode::serialize(&wire).context("Structure binary encoding failed")
+}
+
+/// Unpacks a structure produced by `encode_structure`.
+pub fn decode_structure(bytes: &[u8]) -> Result<Structure> {
+    let wire: WireStructure =
+        bincode::deserialize(bytes).context("Structure binary decoding failed")?;
+
+    let n = wire.symbols.len();
+    if wire.positions.len() != n
+        || wire.charges.len() != n
+        || wire.magnetic_moments.len() != n
+        || wire.tags.len() != n
+    {
+        return Err(anyhow!("Packed structure has inconsistent column lengths"));
+    }
+
+    let atoms = wire
+        .symbols
+        .into_iter()
+        .zip(wire.positions)
+        .zip(wire.charges)
+        .zip(wire.magnetic_moments)
+        .zip(wire.tags)
+        .map(
+            |((((symbol, position), charge), magnetic_moment), tags)| Atom {
+                symbol,
+                position,
+                charge,
+                magnetic_moment,
+                tags,
+            },
+        )
+        .collect();
+
+    Ok(Structure {
+        id: wire.id,
+        atoms,
+        lattice: wire.lattice,
+        source: wire.source,
+        metadata: serde_json::from_slice(&wire.metadata_json)?,
+        blob: None,
+        schema_version: STRUCTURE_SCHEMA_VERSION,
+    })
+}
+
+// ============================================================================
+// 2. CAS-BACKED TRANSFER
+// ============================================================================
+
+/// Offloads/hydrates job structures around transport boundaries.
+///
+/// Both sides of a transport must point at the same `root/store` directory
+/// (shared filesystem), which is already true for FileTransport deployments.
+pub struct StructureCodec {
+    store: ArtifactStore,
+    inline_max_atoms: usize,
+}
+
+impl StructureCodec {
+    pub fn new(root: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self {
+            store: ArtifactStore::new(root.as_ref().join("store"))?,
+            inline_max_atoms: DEFAULT_INLINE_MAX_ATOMS,
+        })
+    }
+
+    /// Replaces a large inline structure with a CAS reference.
+    /// Small or already-offloaded structures are left untouched.
+    pub fn offload(&self, s: &mut Structure) -> Result<()> {
+        if s.is_offloaded() || s.atoms.len() <= self.inline_max_atoms {
+            return Ok(());
+        }
+
+        let bytes = encode_structure(s)?;
+        let (hash, _) = self.store.put_bytes(&bytes, STRUCTURE_BLOB_EXT)?;
+
+        s.blob = Some(StructureBlob {
+            hash,
+            n_atoms: s.atoms.len(),
+        });
+        s.atoms = Vec::new();
+        Ok(())
+    }
+
+    /// Restores the atoms of an offloaded structure from the CAS.
+    pub fn hydrate(&self, s: &mut Structure) -> Result<()> {
+        let blob = match &s.blob {
+            Some(b) => b.clone(),
+            None => return Ok(()),
+        };
+
+        let bytes = self.store.get_bytes(&blob.hash, STRUCTURE_BLOB_EXT)?;
+        let full = decode_structure(&bytes)?;
+
+        if full.atoms.len() != blob.n_atoms {
+            return Err(anyhow!(
+                "Structure blob {} holds {} atoms, reference says {}",
+                blob.hash,
+                full.atoms.len(),
+                blob.n_atoms
+            ));
+        }
+
+        *s = full;
+        Ok(())
+    }
+
+    pub fn offload_jobs(&self, jobs: &mut [Job]) -> Result<()> {
+        for job in jobs.iter_mut() {
+            self.offload(&mut job.structure)
+                .with_context(|| format!("Offloading structure of job {}", job.id))?;
+        }
+        Ok(())
+    }
+
+    pub fn hydrate_jobs(&self, jobs: &mut [Job]) -> Result<()> {
+        for job in jobs.iter_mut() {
+            self.hydrate(&mut job.structure)
+                .with_context(|| format!("Hydrating structure of job {}", job.id))?;
+        }
+        Ok(())
+    }
+}
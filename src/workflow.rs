@@ -12,7 +12,7 @@
 // 3. Expand Generators (Active Learning Recursion).
 // 4. Content Hashing for Deduplication.
 
-use crate::core::{Engine, Job, JobConfig, JobStatus, ResourceReq, Structure};
+use crate::core::{Engine, Job, JobConfig, JobStatus, ResourceReq, ResultPredicate, Structure};
 use anyhow::Result;
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::Bfs;
@@ -25,6 +25,10 @@ use uuid::Uuid;
 
 // Sub-module for parsing Draw.io XML
 pub mod importer;
+// Sub-module for loading YAML (DSL) workflows
+pub mod yaml;
+// Sub-module picking the loader for a workflow file
+pub mod blueprint;
 
 // ============================================================================
 // 1. NODE TYPES (Logic & Control Flow)
@@ -57,6 +61,7 @@ pub enum LogicCondition {
     EnergyBelow(f64),  // eV
     BandGapAbove(f64), // eV
     ExternalScript(String),
+    Expression(ResultPredicate), // e.g. `energy < -5.0`
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,6 +71,59 @@ pub enum EdgeType {
     DataFlow { param_map: HashMap<String, String> },
 }
 
+/// Generator param holding its `ExpansionGovernor`; handed down to every
+/// generation, like the rest of the generator's params.
+pub const GOVERNOR_PARAM: &str = "governor";
+
+/// Generator param counting the Compute jobs its lineage has spawned so far.
+pub const DESCENDANTS_PARAM: &str = "descendants";
+
+/// Switch param: when true, the branch is pruned as soon as a progress
+/// snapshot of the running job fails the condition (see `JobProgress`).
+pub const EARLY_SWITCH_PARAM: &str = "decide_on_progress";
+
+/// Generator param: a test on the feedback about the batch before it, such
+/// as `energy.min < -5.0`. Once it holds the loop has converged, and the
+/// generator expands no further.
+pub const UNTIL_PARAM: &str = "until";
+
+/// Cores of each job a generator's expansion spawns.
+pub const BATCH_CORES: usize = 8;
+
+/// `error_log` prefix of a generator failed by its governor.
+pub const GOVERNOR_REASON_PREFIX: &str = "Expansion Governor: ";
+
+/// Caps on what a generator lineage may spawn; unset fields are unlimited
+/// (`max_children` falls back to the Coordinator's expand limit).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpansionGovernor {
+    /// Candidates accepted from one expansion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_children: Option<usize>,
+    /// Compute jobs spawned by all generations together.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_descendants: Option<u64>,
+    /// Expansions in the lineage, the first one included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_generations: Option<u64>,
+}
+
+impl ExpansionGovernor {
+    /// The governor in a generator's params; the default if there is none.
+    pub fn from_params(params: &Value) -> std::result::Result<Self, String> {
+        match params.get(GOVERNOR_PARAM) {
+            Some(v) => serde_json::from_value(v.clone()).map_err(|e| {
+                format!(
+                    "{}invalid '{}': {}",
+                    GOVERNOR_REASON_PREFIX, GOVERNOR_PARAM, e
+                )
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SmartNode {
     pub job: Job,
@@ -170,6 +228,9 @@ impl WorkflowEngine {
                 strategy: strategy.clone(),
             },
             params,
+            retry: None,
+            environment: None,
+            secrets: Default::default(),
         };
 
         let job = Job::new(
@@ -181,6 +242,7 @@ impl WorkflowEngine {
                 gpus: 0,
                 time_limit_min: 30,
                 required_tags: vec!["brain".into()],
+                ..Default::default()
             },
         );
 
@@ -208,6 +270,7 @@ impl WorkflowEngine {
                         .unwrap_or(0.0)
                         > *threshold
                 }
+                LogicCondition::Expression(pred) => pred.holds_for(result_data),
                 LogicCondition::AlwaysTrue => true,
                 LogicCondition::ExternalScript(_) => true,
             };
@@ -239,6 +302,43 @@ impl WorkflowEngine {
     // 4. RECURSIVE EXPANSION (The Active Learning Loop)
     // ========================================================================
 
+    /// Checks one expansion of the generator at `generator_idx` against its
+    /// governor. `default_max_children` applies when it sets no
+    /// `max_children`. Err is the reason the generator fails with.
+    pub fn govern_expansion(
+        &self,
+        generator_idx: NodeIndex,
+        children: usize,
+        default_max_children: usize,
+    ) -> std::result::Result<(), String> {
+        let params = &self.graph[generator_idx].job.config.params;
+        let governor = ExpansionGovernor::from_params(params)?;
+        let counter = |key: &str| params.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+
+        let max_children = governor.max_children.unwrap_or(default_max_children);
+        if children > max_children {
+            return Err(format!(
+                "{}{} children > max_children {}",
+                GOVERNOR_REASON_PREFIX, children, max_children
+            ));
+        }
+        let generation = counter("gen_counter") + 1;
+        if let Some(max) = governor.max_generations.filter(|m| generation > *m) {
+            return Err(format!(
+                "{}generation {} > max_generations {}",
+                GOVERNOR_REASON_PREFIX, generation, max
+            ));
+        }
+        let descendants = counter(DESCENDANTS_PARAM) + children as u64;
+        if let Some(max) = governor.max_descendants.filter(|m| descendants > *m) {
+            return Err(format!(
+                "{}{} descendants > max_descendants {}",
+                GOVERNOR_REASON_PREFIX, descendants, max
+            ));
+        }
+        Ok(())
+    }
+
     pub fn expand_generator(
         &mut self,
         generator_idx: NodeIndex,
@@ -273,7 +373,7 @@ impl WorkflowEngine {
                 cfg,
                 // Using standard MLIP defaults (GPU) for Phase 6.
                 ResourceReq {
-                    cores: 8,
+                    cores: BATCH_CORES,
                     gpus: 1,
                     ..Default::default()
                 },
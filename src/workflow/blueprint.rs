@@ -0,0 +1,91 @@
+// src/workflow/blueprint.rs
+//
+// =============================================================================
+// UNIFIEDLAB: BLUEPRINT LOADING (v 0.1 )
+// =============================================================================
+//
+// The Front Door.
+//
+// One entry point for every command that takes a workflow file (`deploy`,
+// `run`, `validate`, `deploy --dry-run`):
+// 1. Dispatch on the extension: .yaml/.yml go through the DSL (`YamlLoader`),
+//    anything else (.drawio, scenario signatures) through the Draw.io
+//    importer. `--set` variables apply to YAML only.
+// 2. Tag every job with the workflow's name (`FLOW_WORKFLOW`): the YAML
+//    `metadata.name`, or the file name without extension, so
+//    `cancel --workflow` and `pause --workflow` find it.
+// 3. Compile the graph into one `JobSubmit`: each node becomes a Pending job
+//    carrying its `node_type` and priority in `flow_context`, each edge a
+//    dependency. The Coordinator rebuilds the DAG from exactly this.
+
+use crate::core::JobStatus;
+use crate::dsl;
+use crate::marketplace::{JobSubmit, Routing, FLOW_PRIORITY, FLOW_WORKFLOW};
+use crate::workflow::importer::DrawIoLoader;
+use crate::workflow::yaml::YamlLoader;
+use crate::workflow::WorkflowEngine;
+use anyhow::{anyhow, Context, Result};
+use petgraph::visit::EdgeRef;
+use std::path::Path;
+
+/// A blueprint as a graph, its jobs tagged with the workflow's name.
+pub fn load_blueprint(file: &str, vars: &dsl::Vars) -> Result<WorkflowEngine> {
+    let path = Path::new(file);
+    let (name, mut graph) = if matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml" | "yml")
+    ) {
+        let loaded = YamlLoader::load_from_file(file, vars)?;
+        (loaded.name, loaded.graph)
+    } else {
+        if !vars.is_empty() {
+            return Err(anyhow!("--set applies to YAML workflows only"));
+        }
+        let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+        let loaded = DrawIoLoader::load_from_file(file).context("Failed to load Draw.io")?;
+        (stem.unwrap_or_else(|| file.to_string()), loaded.graph)
+    };
+    for node in graph.graph.node_weights_mut() {
+        node.job
+            .flow_context
+            .insert(FLOW_WORKFLOW.into(), name.clone().into());
+    }
+    Ok(graph)
+}
+
+/// Jobs and edges of a loaded blueprint, ready for `EV_JOB_SUBMIT`.
+pub fn build_submission(
+    graph: &WorkflowEngine,
+    opportunistic: bool,
+    routing: Routing,
+) -> Result<JobSubmit> {
+    let mut jobs = Vec::new();
+    for node in graph.graph.node_weights() {
+        let mut job = node.job.clone();
+        // The Coordinator learns each node's role from its flow context
+        job.flow_context
+            .insert("node_type".into(), serde_json::to_value(&node.node_type)?);
+        job.flow_context
+            .insert(FLOW_PRIORITY.into(), node.priority.into());
+        job.status = JobStatus::Pending;
+        job.opportunistic = opportunistic;
+        jobs.push(job);
+    }
+    let deps = graph
+        .graph
+        .edge_references()
+        .map(|e| {
+            (
+                graph.graph[e.source()].job.id,
+                graph.graph[e.target()].job.id,
+            )
+        })
+        .collect();
+
+    Ok(JobSubmit {
+        jobs,
+        deps,
+        routing,
+        txn: None,
+    })
+}
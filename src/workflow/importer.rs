@@ -396,6 +396,9 @@ fn make_job(name: &str, engine: Engine, cores: usize, gpus: usize) -> Job {
         JobConfig {
             engine,
             params: serde_json::json!({"test_id": name}),
+            retry: None,
+            environment: None,
+            secrets: Default::default(),
         },
         ResourceReq {
             nodes: 1,
@@ -403,6 +406,7 @@ fn make_job(name: &str, engine: Engine, cores: usize, gpus: usize) -> Job {
             gpus,
             time_limit_min: 60,
             required_tags: vec![], // Tags handled by main.rs logic mostly
+            ..Default::default()
         },
     )
 }
@@ -0,0 +1,394 @@
+// src/workflow/yaml.rs
+//
+// =============================================================================
+// UNIFIEDLAB: YAML WORKFLOW LOADER (v 0.1 )
+// =============================================================================
+//
+// The Translator.
+//
+// Turns a DSL workflow (see `crate::dsl`) into the same WorkflowEngine graph
+// the Draw.io importer builds, so `deploy` and `run` treat both alike:
+// 1. Files of an older DSL version are upgraded, and `${vars.x}` references
+//    substituted, as the file is read. Node matrices and macros are
+//    expanded next, then subworkflow nodes are replaced by the nodes of the
+//    workflows they name (ids prefixed `sub/`); the result is validated
+//    again.
+// 2. Node types and engines map onto `NodeType` / `Engine`. Engine details
+//    (arch, binary, ...) come from the node's params; agent scripts are
+//    resolved relative to the workflow file.
+// 3. Nodes are added parents-first, because a node's content hash includes
+//    its parents' hashes.
+// 4. Hard and dataflow edges become dependencies. Soft edges are ordering
+//    hints the scheduler does not enforce, so they are dropped.
+// 5. `resources.same_node_as` node ids, and the parents of `when` and
+//    dataflow edges, become job ids once all jobs exist.
+// 6. A node's `environment`, or else the workflow's, becomes its jobs'
+//    `JobConfig.environment`, with paths relative to the workflow file.
+// 7. A generator's `batch` becomes the `physics_template` its candidates are
+//    expanded with, in the generator's environment.
+// 8. `secrets`, the workflow's merged under the node's, become references in
+//    `JobConfig.secrets`; values are only read by the Guardian running a job.
+// 9. A node's `structure` file (CIF, POSCAR or XYZ) is read into its job's
+//    structure, relative to the workflow file. Nodes without one start from
+//    an empty structure; either way the structure is named after the node.
+
+use crate::core::{
+    Engine, Environment, Job, JobConfig, ResourceReq, RetryPolicy, SecretRef, Structure,
+};
+use crate::dsl::{
+    self, ConditionSpec, EdgeKind, EngineSpec, EnvironmentSpec, NodeKind, NodeSpec, SecretSpec,
+    WorkflowSpec,
+};
+use crate::structure_io;
+use crate::workflow::{LogicCondition, NodeType, WorkflowEngine, BATCH_CORES};
+use anyhow::{anyhow, Result};
+use petgraph::graph::NodeIndex;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+
+/// Verifier tolerance (eV) when a node does not set `params.tolerance`.
+const DEFAULT_VERIFY_TOLERANCE: f64 = 0.01;
+
+pub struct YamlLoader {
+    /// `metadata.name` of the workflow.
+    pub name: String,
+    pub graph: WorkflowEngine,
+}
+
+impl YamlLoader {
+    /// `vars` override the defaults under the workflow's `vars:`.
+    pub fn load_from_file(path: impl AsRef<Path>, vars: &dsl::Vars) -> Result<Self> {
+        let path = path.as_ref();
+        let spec = dsl::load_yaml(path, vars)?;
+        let expanded = dsl::expand_macros(&spec)?;
+        let spec = dsl::resolve_subworkflows(&expanded.spec, path)?;
+
+        // Parents of each node, over the edges that order execution
+        let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for e in &spec.edges {
+            if matches!(e.kind, EdgeKind::Soft) {
+                log::debug!("Soft edge {} -> {} not enforced", e.from, e.to);
+                continue;
+            }
+            parents.entry(&e.to).or_default().push(&e.from);
+        }
+
+        let mut engine = WorkflowEngine::new();
+        let mut indices: HashMap<&str, NodeIndex> = HashMap::new();
+        for node in parents_first(&spec.nodes, &parents)? {
+            let node_parents = parents
+                .get(node.id.as_str())
+                .into_iter()
+                .flatten()
+                .map(|p| indices[p])
+                .collect();
+            let (job, node_type, priority) = node_job(node, &spec, path)?;
+            let idx = engine.add_smart_node(job, node_type, node_parents, priority, true)?;
+            indices.insert(&node.id, idx);
+        }
+
+        // `same_node_as` names a node; the coordinator needs its job id
+        for node in &spec.nodes {
+            if let Some(target) = node
+                .resources
+                .as_ref()
+                .and_then(|r| r.same_node_as.as_ref())
+            {
+                let target_id = engine.graph[indices[target.as_str()]].job.id;
+                engine.graph[indices[node.id.as_str()]]
+                    .job
+                    .resources
+                    .same_node_as = Some(target_id);
+            }
+        }
+        for e in &spec.edges {
+            let parent_id = engine.graph[indices[e.from.as_str()]].job.id;
+            let child = &mut engine.graph[indices[e.to.as_str()]].job;
+            if let Some(when) = &e.when {
+                let pred = when.parse().map_err(|msg| anyhow!("{}", msg))?;
+                child.conditions.insert(parent_id, pred);
+            }
+            if !e.map.is_empty() {
+                child
+                    .dataflow
+                    .entry(parent_id)
+                    .or_default()
+                    .extend(e.map.clone());
+            }
+        }
+
+        log::info!(
+            "📄 Parsed YAML workflow '{}': {} nodes, {} edges",
+            spec.metadata.name,
+            engine.graph.node_count(),
+            engine.graph.edge_count()
+        );
+        Ok(Self {
+            name: spec.metadata.name.clone(),
+            graph: engine,
+        })
+    }
+}
+
+/// Nodes ordered so that every node comes after its parents (Kahn).
+fn parents_first<'a>(
+    nodes: &'a [NodeSpec],
+    parents: &HashMap<&str, Vec<&str>>,
+) -> Result<Vec<&'a NodeSpec>> {
+    let mut waiting: HashMap<&str, usize> = nodes
+        .iter()
+        .map(|n| {
+            (
+                n.id.as_str(),
+                parents.get(n.id.as_str()).map_or(0, Vec::len),
+            )
+        })
+        .collect();
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (child, ps) in parents {
+        for p in ps {
+            children.entry(*p).or_default().push(*child);
+        }
+    }
+
+    let by_id: HashMap<&str, &NodeSpec> = nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let mut ready: VecDeque<&str> = nodes
+        .iter()
+        .map(|n| n.id.as_str())
+        .filter(|id| waiting[id] == 0)
+        .collect();
+    let mut ordered = Vec::with_capacity(nodes.len());
+    while let Some(id) = ready.pop_front() {
+        ordered.push(by_id[id]);
+        for child in children.get(id).into_iter().flatten() {
+            let n = waiting.get_mut(child).expect("edges are validated");
+            *n -= 1;
+            if *n == 0 {
+                ready.push_back(*child);
+            }
+        }
+    }
+
+    if ordered.len() < nodes.len() {
+        let mut stuck: Vec<&str> = waiting
+            .into_iter()
+            .filter(|(_, n)| *n > 0)
+            .map(|(id, _)| id)
+            .collect();
+        stuck.sort();
+        return Err(anyhow!(
+            "workflow has a dependency cycle through: {}",
+            stuck.join(", ")
+        ));
+    }
+    Ok(ordered)
+}
+
+/// The runtime form of a Switch node's `condition`.
+fn switch_condition(c: &ConditionSpec, workflow_file: &Path) -> Result<LogicCondition> {
+    if let Some(t) = c.energy_below {
+        return Ok(LogicCondition::EnergyBelow(t));
+    }
+    if let Some(t) = c.band_gap_above {
+        return Ok(LogicCondition::BandGapAbove(t));
+    }
+    if let Some(expr) = &c.expression {
+        return Ok(LogicCondition::Expression(
+            expr.parse().map_err(|msg| anyhow!("{}", msg))?,
+        ));
+    }
+    match &c.script {
+        Some(script) => Ok(LogicCondition::ExternalScript(
+            dsl::resolve_relative(workflow_file, script)
+                .to_string_lossy()
+                .into_owned(),
+        )),
+        None => Ok(LogicCondition::AlwaysTrue),
+    }
+}
+
+/// The runtime form of an `environment`.
+fn job_environment(env: &EnvironmentSpec, workflow_file: &Path) -> Environment {
+    match env.clone().relative_to(workflow_file) {
+        EnvironmentSpec::UvProject { path } => Environment::Uv { project: path },
+        EnvironmentSpec::DockerImage { image } => Environment::Docker { image },
+        EnvironmentSpec::ApptainerImage { image } => Environment::Apptainer { image },
+        EnvironmentSpec::Modules { modules } => Environment::Modules { modules },
+    }
+}
+
+/// The runtime form of a validated secret: only where to read it.
+fn job_secret(secret: &SecretSpec) -> SecretRef {
+    match (&secret.env, &secret.keyring) {
+        (Some(var), _) => SecretRef::Env(var.clone()),
+        (None, name) => SecretRef::Keyring(name.clone().unwrap_or_default()),
+    }
+}
+
+/// The runtime engine for a DSL one; details such as the binary come from
+/// `params`.
+fn job_engine(
+    engine: Option<&EngineSpec>,
+    params: &Value,
+    cores: usize,
+    workflow_file: &Path,
+) -> Engine {
+    let str_param = |key: &str| params.get(key).and_then(Value::as_str).map(str::to_string);
+    match engine {
+        None | Some(EngineSpec::Janus) => Engine::Janus {
+            arch: str_param("arch").unwrap_or_else(|| "mace_mp".into()),
+            device_preference: str_param("device"),
+            model_path: str_param("model_path").map(Into::into),
+        },
+        Some(EngineSpec::Gulp) => Engine::Gulp {
+            binary: str_param("binary").unwrap_or_else(|| "gulp".into()),
+            potential_library: str_param("potential").unwrap_or_else(|| "reaxff".into()),
+        },
+        Some(EngineSpec::Vasp) => Engine::Vasp {
+            binary: str_param("binary").unwrap_or_else(|| "vasp_std".into()),
+            mpi_ranks: cores,
+        },
+        Some(EngineSpec::Cp2k) => Engine::Cp2k {
+            binary: str_param("binary").unwrap_or_else(|| "cp2k.popt".into()),
+            mpi_ranks: cores,
+        },
+        Some(EngineSpec::Agent { script, strategy }) => Engine::Agent {
+            script_path: dsl::resolve_relative(workflow_file, script)
+                .to_string_lossy()
+                .into_owned(),
+            strategy: strategy.clone().unwrap_or_else(|| "default".into()),
+        },
+    }
+}
+
+/// The job for one node of `spec`, its graph type and its priority. The
+/// workflow's environment and resources stand in for the node's if it sets
+/// none, and its secrets are merged under the node's.
+fn node_job(
+    node: &NodeSpec,
+    spec: &WorkflowSpec,
+    workflow_file: &Path,
+) -> Result<(Job, NodeType, u32)> {
+    let mut params = match &node.params {
+        Value::Null => json!({}),
+        v => v.clone(),
+    };
+    let environment = node
+        .environment
+        .as_ref()
+        .or(spec.environment.as_ref())
+        .map(|env| job_environment(env, workflow_file));
+    let secrets: BTreeMap<String, SecretRef> = spec
+        .secrets
+        .iter()
+        .chain(&node.secrets)
+        .map(|(var, s)| (var.clone(), job_secret(s)))
+        .collect();
+
+    // A generator's candidates run as its batch
+    if let Some(batch) = &node.batch {
+        let batch_params = match &batch.params {
+            Value::Null => json!({}),
+            v => v.clone(),
+        };
+        let template = JobConfig {
+            engine: job_engine(
+                batch.engine.as_ref(),
+                &batch_params,
+                BATCH_CORES,
+                workflow_file,
+            ),
+            params: batch_params,
+            retry: None,
+            environment: environment.clone(),
+            secrets: secrets.clone(),
+        };
+        params
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("node '{}': params must be a mapping", node.id))?
+            .insert("physics_template".into(), serde_json::to_value(template)?);
+    }
+    let str_param = |key: &str| params.get(key).and_then(Value::as_str).map(str::to_string);
+
+    let node_resources = node.resources.as_ref().or(spec.resources.as_ref());
+    let cores = node_resources.map_or(1, |r| r.cores as usize);
+    let engine = job_engine(node.engine.as_ref(), &params, cores, workflow_file);
+
+    let (node_type, priority) = match node.node_type {
+        NodeKind::Compute => (NodeType::Compute, 50),
+        NodeKind::Generator => {
+            let strategy = match &engine {
+                Engine::Agent { strategy, .. } => strategy.clone(),
+                _ => str_param("strategy").unwrap_or_else(|| "default".into()),
+            };
+            (NodeType::Generator { strategy }, 100)
+        }
+        NodeKind::Switch => {
+            let condition = match &node.condition {
+                Some(c) => switch_condition(c, workflow_file)
+                    .map_err(|e| anyhow!("node '{}': invalid switch condition: {}", node.id, e))?,
+                None => LogicCondition::AlwaysTrue,
+            };
+            (NodeType::Switch { condition }, 50)
+        }
+        NodeKind::Aggregator => (NodeType::Aggregator, 10),
+        NodeKind::Verifier => {
+            let tolerance = params
+                .get("tolerance")
+                .and_then(Value::as_f64)
+                .unwrap_or(DEFAULT_VERIFY_TOLERANCE);
+            (NodeType::Verifier { tolerance }, 50)
+        }
+        NodeKind::Sentinel => (NodeType::Sentinel, 50),
+        NodeKind::Subworkflow => {
+            return Err(anyhow!("node '{}': subworkflow was not flattened", node.id))
+        }
+    };
+
+    let resources = match node_resources {
+        Some(r) => ResourceReq {
+            nodes: r.nodes as usize,
+            cores: r.cores as usize,
+            gpus: r.gpus as usize,
+            memory_mb: r.memory_mb,
+            time_limit_min: r.time_limit_min as usize,
+            required_tags: r.required_tags.clone(),
+            prefer_tags: r.prefer_tags.clone(),
+            avoid_tags: r.avoid_tags.clone(),
+            same_node_as: None, // Resolved once every node has its job
+        },
+        None => ResourceReq::default(),
+    };
+
+    let retry = node.retry.as_ref().map(|r| RetryPolicy {
+        max_attempts: r.max_attempts,
+        backoff_s: r.backoff_s,
+        retry_on: r.retry_on.clone(),
+    });
+    let structure = match &node.structure {
+        Some(st) => {
+            let file = dsl::resolve_relative(workflow_file, &st.file);
+            let mut structure = structure_io::read(&file, st.format)
+                .map_err(|e| anyhow!("node '{}': structure {}: {:#}", node.id, st.file, e))?;
+            structure.source = node.id.clone();
+            structure
+                .metadata
+                .insert("file".into(), json!(file.to_string_lossy()));
+            structure
+        }
+        None => Structure::new(vec![], None, node.id.clone()),
+    };
+    let job = Job::new(
+        structure,
+        JobConfig {
+            engine,
+            params,
+            retry,
+            environment,
+            secrets,
+        },
+        resources,
+    );
+    Ok((job, node_type, priority))
+}
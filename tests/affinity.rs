@@ -0,0 +1,120 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::ResourceReq;
+use unifiedlab::marketplace::{
+    JobSubmit, MarketplaceCoordinator, Routing, WorkGrant, WorkRequest, EV_JOB_SUBMIT,
+    EV_WORK_GRANT, MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn job(resources: ResourceReq) -> Job {
+    Job::new(
+        Structure::new(vec![], None, "affinity_test".into()),
+        common::config(Default::default(), serde_json::json!({})),
+        resources,
+    )
+}
+
+async fn submit(t: &mut MemTransport, jobs: Vec<Job>) {
+    let sub = JobSubmit {
+        jobs,
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(t, EV_JOB_SUBMIT, &sub).await;
+}
+
+/// A heartbeat from `worker_id` on `host`, with room for a job.
+fn heartbeat(worker_id: &str, tags: &[&str], host: &str) -> WorkRequest {
+    WorkRequest {
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        hostname: Some(host.into()),
+        ..common::work_request(worker_id, 4)
+    }
+}
+
+async fn granted(t: &mut MemTransport, worker_id: &str) -> Vec<Uuid> {
+    t.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_WORK_GRANT)
+        .map(|e| serde_json::from_value::<WorkGrant>(e.record.payload).unwrap())
+        .filter(|g| g.worker_id == worker_id)
+        .flat_map(|g| g.jobs)
+        .map(|j| j.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_placement_follows_tags_and_hosts() {
+    let root = std::env::temp_dir().join(format!("ulab_affinity_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut login = net.worker(Some("login"));
+    let mut big = net.worker(Some("big"));
+    let mut twin = net.worker(Some("twin"));
+    let login_hb = heartbeat("login", &["login"], "hostA");
+    let big_hb = heartbeat("big", &["bigmem"], "hostB");
+    let twin_hb = heartbeat("twin", &[], "hostA");
+
+    // login and twin share a host; big carries the tag everyone would rather have
+    let compute = job(ResourceReq {
+        avoid_tags: vec!["login".into()],
+        prefer_tags: vec!["bigmem".into()],
+        ..Default::default()
+    });
+    submit(&mut login, vec![compute.clone()]).await;
+    coord.tick().await.unwrap();
+    common::send(&mut login, MSG_WORK_REQUEST, &login_hb).await;
+    common::send(&mut twin, MSG_WORK_REQUEST, &twin_hb).await;
+    common::send(&mut big, MSG_WORK_REQUEST, &big_hb).await;
+    coord.tick().await.unwrap();
+    assert!(granted(&mut login, "login").await.is_empty());
+    assert!(granted(&mut twin, "twin").await.is_empty());
+    assert_eq!(granted(&mut big, "big").await, [compute.id]);
+
+    // The parse step must land on big's host, whoever asks first
+    let parse = job(ResourceReq {
+        same_node_as: Some(compute.id),
+        ..Default::default()
+    });
+    submit(&mut login, vec![parse.clone()]).await;
+    coord.tick().await.unwrap();
+    common::send(&mut twin, MSG_WORK_REQUEST, &twin_hb).await;
+    coord.tick().await.unwrap();
+    assert!(granted(&mut twin, "twin").await.is_empty());
+    common::send(&mut big, MSG_WORK_REQUEST, &big_hb).await;
+    coord.tick().await.unwrap();
+    assert_eq!(granted(&mut big, "big").await, [parse.id]);
+
+    // Preferences only steer: with big full, twin still gets the work
+    let spill = job(ResourceReq {
+        avoid_tags: vec!["login".into()],
+        prefer_tags: vec!["bigmem".into()],
+        ..Default::default()
+    });
+    submit(&mut login, vec![spill.clone()]).await;
+    coord.tick().await.unwrap();
+    let full = WorkRequest {
+        tags: vec!["bigmem".into()],
+        hostname: Some("hostB".into()),
+        ..common::work_request("big", 0)
+    };
+    common::send(&mut big, MSG_WORK_REQUEST, &full).await;
+    common::send(&mut twin, MSG_WORK_REQUEST, &twin_hb).await;
+    coord.tick().await.unwrap();
+    assert_eq!(granted(&mut twin, "twin").await, [spill.id]);
+
+    std::fs::remove_dir_all(&root).ok();
+}
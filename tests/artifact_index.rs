@@ -0,0 +1,50 @@
+use unifiedlab::provenance::{ArtifactStore, ContentType};
+
+#[test]
+fn test_index_tags_and_previews_artifacts() {
+    let dir = std::env::temp_dir().join(format!("ulab_art_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = ArtifactStore::new(dir.join("store")).unwrap();
+
+    // Three frames of a two-atom trajectory, extXYZ style
+    let frame = "2\nProperties=species:S:1:pos:R:3 energy=-1.0\nH 0 0 0\nH 0 0 0.74\n";
+    let traj = dir.join("relax.xyz");
+    std::fs::write(&traj, frame.repeat(3)).unwrap();
+    let (traj_hash, _) = store.commit(&traj, "xyz").unwrap();
+
+    // A long log: only the first KB is kept
+    let log = dir.join("run.out");
+    std::fs::write(&log, "SCF converged\n".repeat(500)).unwrap();
+    let (log_hash, _) = store.commit(&log, "out").unwrap();
+
+    // Binary objects get no preview; the type can be given explicitly
+    let weights = dir.join("weights.bin");
+    std::fs::write(&weights, [0u8, 1, 2, 3]).unwrap();
+    let model = store
+        .commit_as(&weights, "bin", ContentType::Model)
+        .unwrap();
+    assert!(model.preview.is_none());
+
+    let info = store.info(&traj_hash).unwrap().unwrap();
+    assert_eq!(info.content_type, ContentType::Trajectory);
+    assert_eq!(info.frames, Some(3));
+    assert!(info.preview.unwrap().starts_with("2\nProperties"));
+
+    let info = store.info(&log_hash).unwrap().unwrap();
+    assert_eq!(info.content_type, ContentType::Log);
+    assert_eq!(info.preview.unwrap().len(), 1024);
+    assert_eq!(info.frames, None);
+
+    let models = store.list(Some(ContentType::Model)).unwrap();
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].hash, model.hash);
+
+    // A lost index is rebuilt from the objects, types guessed from extensions
+    std::fs::remove_file(dir.join("store/index.db")).unwrap();
+    assert_eq!(store.reindex().unwrap(), 3);
+    assert_eq!(store.list(None).unwrap().len(), 3);
+    assert_eq!(store.info(&traj_hash).unwrap().unwrap().frames, Some(3));
+    assert_eq!(store.reindex().unwrap(), 0);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
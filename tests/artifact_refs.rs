@@ -0,0 +1,118 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::{CheckpointStore, META_SCHEMA_VERSION};
+use unifiedlab::core::{
+    CalculationResult, FileRole, JobStatus, Provenance, WorkFile, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::provenance::{manifest_dir, ArtifactStore};
+use unifiedlab::Job;
+
+mod common;
+
+fn completed(files: Vec<WorkFile>) -> Job {
+    let mut job = common::job("artifact_test");
+    let now = chrono::Utc::now();
+    job.status = JobStatus::Completed;
+    job.result = Some(CalculationResult {
+        energy: None,
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "w1".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files,
+        },
+        next_generation: None,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    });
+    job
+}
+
+#[test]
+fn test_job_files_are_listed_and_found_by_hash() {
+    let root = std::env::temp_dir().join(format!("ulab_artrefs_{}", uuid::Uuid::new_v4()));
+    let work = root.join("work");
+    std::fs::create_dir_all(&work).unwrap();
+    let artifacts = ArtifactStore::new(root.join("store")).unwrap();
+    let db = root.join("checkpoint.db");
+    let store = CheckpointStore::open(&db).unwrap();
+
+    // Two runs of the same input, each with its own output
+    std::fs::write(work.join("INCAR"), "ENCUT = 520\n").unwrap();
+    let inputs = manifest_dir(&work, FileRole::Input).unwrap();
+    let mut first = inputs.clone();
+    std::fs::write(work.join("OUTCAR"), "TOTEN = -10.8\n").unwrap();
+    std::fs::write(work.join("relax.xyz"), "1\n\nSi 0 0 0\n").unwrap();
+    artifacts.capture_workdir(&work, &mut first).unwrap();
+    let mut second = inputs.clone();
+    std::fs::write(work.join("OUTCAR"), "TOTEN = -10.9\n").unwrap();
+    std::fs::remove_file(work.join("relax.xyz")).unwrap();
+    artifacts.capture_workdir(&work, &mut second).unwrap();
+
+    let a = completed(first);
+    let b = completed(second);
+    store.apply_batch(0, &[&a, &b], &[]).unwrap();
+
+    let refs = store.get_job_artifacts(&a.id.to_string()).unwrap();
+    let listed: Vec<_> = refs
+        .iter()
+        .map(|r| (r.path.as_str(), r.kind.as_str(), r.role))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("INCAR", "other", FileRole::Input),
+            ("OUTCAR", "log", FileRole::Output),
+            ("relax.xyz", "trajectory", FileRole::Output),
+        ]
+    );
+    let outcar = &refs[1];
+    assert_eq!(outcar.size, 14);
+    assert_eq!(
+        std::fs::read_to_string(outcar.store_path(&artifacts)).unwrap(),
+        "TOTEN = -10.8\n"
+    );
+
+    // The shared input is referenced by both jobs, each output by one
+    let incar = store.get_artifact_refs(&inputs[0].sha256).unwrap();
+    assert_eq!(incar.len(), 2);
+    assert_eq!(store.get_artifact_refs(&outcar.hash).unwrap().len(), 1);
+
+    // A retried job loses its old files
+    let mut retried = b.clone();
+    retried.status = JobStatus::Pending;
+    retried.result = None;
+    store.apply_batch(0, &[&retried], &[]).unwrap();
+    assert!(store
+        .get_job_artifacts(&b.id.to_string())
+        .unwrap()
+        .is_empty());
+
+    // A DB from before the table gets the rows of its stored jobs
+    store.set_meta(META_SCHEMA_VERSION, "3").unwrap();
+    drop(store);
+    let conn = rusqlite::Connection::open(&db).unwrap();
+    conn.execute("DELETE FROM artifacts", []).unwrap();
+    drop(conn);
+    let store = CheckpointStore::open(&db).unwrap();
+    assert_eq!(store.get_job_artifacts(&a.id.to_string()).unwrap(), refs);
+
+    // Pruned jobs take their references with them
+    std::thread::sleep(Duration::from_millis(5));
+    store
+        .prune(Duration::ZERO, &[JobStatus::Completed])
+        .unwrap();
+    assert!(store
+        .get_artifact_refs(&inputs[0].sha256)
+        .unwrap()
+        .is_empty());
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use unifiedlab::benchmark::summarize;
+use unifiedlab::core::JobSummary;
+
+fn row(id: &str, status: &str, updated_at: i64, t_total: f64) -> JobSummary {
+    JobSummary {
+        id: id.into(),
+        status: status.into(),
+        code: "gulp".into(),
+        node_id: "w1".into(),
+        updated_at,
+        t_total,
+    }
+}
+
+#[test]
+fn test_latency_counts_from_ready_not_submit() {
+    // a -> b, plus c which never got a worker
+    let parents: HashMap<String, Vec<String>> = [
+        ("a".to_string(), vec![]),
+        ("b".to_string(), vec!["a".to_string()]),
+        ("c".to_string(), vec![]),
+    ]
+    .into_iter()
+    .collect();
+    let rows = vec![
+        // Dispatched 100ms after submit (t=1000), ran 400ms
+        row("a", "Completed", 1500, 400.0),
+        // Ready when a finished (1500), dispatched at 1800
+        row("b", "Completed", 2000, 200.0),
+        row("c", "Pending", 1000, 0.0),
+    ];
+
+    let s = summarize("chain_2", 1000, 3.0, &parents, &rows, 3000, 300);
+    assert_eq!((s.jobs, s.completed, s.unfinished), (3, 2, 1));
+
+    let lat = s.latency.unwrap();
+    assert_eq!(lat.samples, 2);
+    assert_eq!(lat.max_ms, 300.0);
+    assert_eq!(lat.p50_ms, 300.0);
+    assert_eq!(lat.mean_ms, 200.0);
+
+    // Two jobs finished within one second of the submission
+    assert_eq!(s.throughput_jobs_per_s, 2.0);
+    assert_eq!(s.eventlog_bytes_per_job, 1000.0);
+    assert_eq!(s.db_bytes_per_job, 100.0);
+}
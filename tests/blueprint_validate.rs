@@ -0,0 +1,29 @@
+use unifiedlab::core::ResourceReq;
+use unifiedlab::resources::{ClusterType, ResourceLedger};
+
+fn req(cores: usize, gpus: usize, memory_mb: u64) -> ResourceReq {
+    ResourceReq {
+        cores,
+        gpus,
+        memory_mb,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_requests_larger_than_the_machine_never_fit() {
+    let node = ResourceLedger::with_capacity(ClusterType::Slurm, "gpu01".into(), 64, 4, 256_000);
+    assert_eq!(node.never_fits(&req(64, 4, 256_000)), None);
+    assert_eq!(
+        node.never_fits(&req(8, 100, 0)).as_deref(),
+        Some("asks 100 GPU(s), this machine has 4")
+    );
+    let why = node.never_fits(&req(128, 0, 512_000)).unwrap();
+    assert!(why.contains("128 core(s), this machine has 64"), "{}", why);
+    assert!(why.contains("512000 MB of memory"), "{}", why);
+
+    // A workstation keeps one core for itself
+    let laptop = ResourceLedger::with_capacity(ClusterType::Local, "laptop".into(), 8, 0, 16_000);
+    assert_eq!(laptop.free_cores(), 7);
+    assert!(laptop.never_fits(&req(8, 0, 0)).is_some());
+}
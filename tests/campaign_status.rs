@@ -0,0 +1,108 @@
+use chrono::{Duration as Span, Utc};
+use unifiedlab::checkpoint::{CheckpointStore, WorkerInfo};
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{MarketplaceCoordinator, MSG_WORK_REQUEST};
+use unifiedlab::report::status_from_store;
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+use unifiedlab::Job;
+
+mod common;
+
+fn job(status: JobStatus, minutes_ago: i64) -> Job {
+    let mut j = common::job("status_test");
+    j.status = status;
+    j.updated_at = Utc::now() - Span::minutes(minutes_ago);
+    j
+}
+
+fn worker(id: &str, tasks: usize, seconds_ago: i64) -> WorkerInfo {
+    let last_seen_ms = (Utc::now() - Span::seconds(seconds_ago)).timestamp_millis();
+    WorkerInfo {
+        tasks,
+        hostname: Some(format!("{}.cluster", id)),
+        ..common::worker_info(id, 8 - tasks, last_seen_ms)
+    }
+}
+
+#[test]
+fn test_status_counts_load_and_oldest_pending() {
+    let root = std::env::temp_dir().join(format!("ulab_status_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let oldest = job(JobStatus::Pending, 45);
+    let jobs = [
+        job(JobStatus::Pending, 5),
+        oldest.clone(),
+        job(JobStatus::Running, 3),
+        job(JobStatus::Failed, 10),
+        // Blocked longer, but waiting on parents rather than a worker
+        job(JobStatus::Blocked, 90),
+    ];
+    let workers = [worker("w1", 1, 5), worker("w2", 0, 600)];
+    store
+        .apply_batch(0, &jobs.iter().collect::<Vec<_>>(), &workers)
+        .unwrap();
+
+    let now_ms = Utc::now().timestamp_millis();
+    let status = status_from_store(&store, 1, now_ms).unwrap();
+    assert_eq!(status.total, 5);
+    assert_eq!(status.jobs["Pending"], 2);
+    assert_eq!(status.jobs["Completed"], 0);
+    assert!(!status.paused);
+
+    let pending = status.oldest_pending.as_ref().unwrap();
+    assert_eq!(pending.id, oldest.id.to_string());
+    assert!(pending.waiting_ms >= 45 * 60_000);
+
+    let w1 = status.workers.iter().find(|w| w.worker_id == "w1").unwrap();
+    assert_eq!((w1.tasks, w1.free_cores, w1.silent), (1, 7, false));
+    assert!(status
+        .workers
+        .iter()
+        .any(|w| w.worker_id == "w2" && w.silent));
+
+    // The failure inside the window counts against throughput
+    assert_eq!(status.throughput.len(), 1);
+    assert_eq!(status.throughput[0].failed, 1);
+
+    // The JSON shape scripts read
+    let wire = serde_json::to_value(&status).unwrap();
+    assert_eq!(wire["jobs"]["Running"], 1);
+    assert_eq!(wire["window_hours"], 1);
+    assert_eq!(wire["oldest_pending"]["id"], oldest.id.to_string());
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_status_sees_workers_the_coordinator_checkpointed() {
+    let root = std::env::temp_dir().join(format!("ulab_status_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+    let req = common::work_request("w1", 4);
+    w1.send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    coord.checkpoint_now().unwrap();
+
+    // A worker that just sent a heartbeat is live, not silent since 1970
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let now_ms = Utc::now().timestamp_millis();
+    let status = status_from_store(&store, 1, now_ms).unwrap();
+    assert_eq!(status.workers.len(), 1);
+    let w1 = &status.workers[0];
+    assert_eq!((w1.worker_id.as_str(), w1.free_cores), ("w1", 4));
+    assert!(now_ms - w1.last_seen_ms < 5_000, "{}", w1.last_seen_ms);
+    assert!(!w1.silent);
+
+    std::fs::remove_dir_all(&root).ok();
+}
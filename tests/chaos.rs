@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use unifiedlab::chaos::{Chaos, ChaosConfig, ChaosTransport};
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{CalculationResult, JobStatus, Provenance, RESULT_SCHEMA_VERSION};
+use unifiedlab::marketplace::{
+    GrantAck, JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, WorkGrant,
+    EV_JOB_COMPLETE, EV_JOB_SUBMIT, EV_WORK_GRANT, MSG_GRANT_ACK, MSG_JOB_COMPLETE,
+    MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+use unifiedlab::Job;
+
+mod common;
+
+/// A finished run; each call is a distinct execution.
+fn completed(job_id: uuid::Uuid) -> JobCompleteReport {
+    let now = chrono::Utc::now();
+    JobCompleteReport {
+        job_id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(CalculationResult {
+            energy: None,
+            forces: None,
+            stress: None,
+            t_total_ms: 1.0,
+            final_structure: None,
+            provenance: Provenance {
+                execution_host: "w1".into(),
+                start_time: now,
+                end_time: now,
+                binary_hash: None,
+                exit_code: 0,
+                sandbox_info: String::new(),
+                files: vec![],
+            },
+            next_generation: None,
+            usage: None,
+            steps: vec![],
+            schema_version: RESULT_SCHEMA_VERSION,
+        }),
+        error: None,
+    }
+}
+
+#[test]
+fn test_chaos_config_parses_probabilities_and_timings() {
+    let cfg: ChaosConfig = "drop=0.5, crash=0.01,kill_ms=10".parse().unwrap();
+    assert_eq!(cfg.drop_grant, 0.5);
+    assert_eq!(cfg.crash_tick, 0.01);
+    assert_eq!(cfg.kill_ms, 10);
+    // Left out: off, or the default timing
+    assert_eq!(cfg.delay_write, 0.0);
+    assert_eq!(cfg.delay_ms, ChaosConfig::default().delay_ms);
+
+    assert!("crash=1.5".parse::<ChaosConfig>().is_err());
+    assert!("flood=0.1".parse::<ChaosConfig>().is_err());
+    assert!("drop".parse::<ChaosConfig>().is_err());
+}
+
+#[tokio::test]
+async fn test_no_job_lost_or_repeated_across_crashes_and_dropped_grants() {
+    let root = std::env::temp_dir().join(format!("ulab_chaos_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let db = root.join("checkpoint.db");
+
+    // Grants are dropped at random; crashes are forced below
+    let chaos = Arc::new(Chaos::new("drop=0.3".parse().unwrap()));
+    let net = MemNetwork::new();
+    let reopen = || ChaosTransport::wrap(Box::new(net.coordinator()), chaos.clone());
+    let mut coord = MarketplaceCoordinator::open(reopen(), CheckpointStore::open(&db).unwrap())
+        .await
+        .unwrap()
+        .with_grant_ack_timeout(Duration::from_millis(50));
+
+    // a -> b -> c, plus three loose jobs
+    let jobs: Vec<Job> = (0..6).map(|_| common::job("chaos_test")).collect();
+    let sub = JobSubmit {
+        jobs: jobs.clone(),
+        deps: vec![(jobs[0].id, jobs[1].id), (jobs[1].id, jobs[2].id)],
+        routing: Routing::default(),
+        txn: None,
+    };
+    let mut architect = net.worker(None);
+    common::send(&mut architect, EV_JOB_SUBMIT, &sub).await;
+
+    // The worker runs each job once; a re-grant of a finished job is only
+    // acked, so its completion has to come from the replayed inbox
+    let mut worker = net.worker(Some("w1"));
+    let mut ran = HashSet::new();
+    let mut restarts = 0;
+    let started = Instant::now();
+    for tick in 1.. {
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "stuck: {:?}",
+            chaos.check(coord.jobs()).unfinished
+        );
+        let req = common::work_request("w1", 4);
+        common::send(&mut worker, MSG_WORK_REQUEST, &req).await;
+
+        coord.tick().await.unwrap();
+        if coord.jobs().all(|j| j.status == JobStatus::Completed) {
+            break;
+        }
+        // Crash every other tick, checkpointing now and then in between
+        if tick % 2 == 1 {
+            coord.crash_restart(reopen()).await.unwrap();
+            restarts += 1;
+        } else if tick % 4 == 0 {
+            coord.checkpoint_now().unwrap();
+        }
+
+        for env in worker.recv_broadcasts().await.unwrap() {
+            if env.record.kind != EV_WORK_GRANT {
+                continue;
+            }
+            let grant: WorkGrant = serde_json::from_value(env.record.payload).unwrap();
+            let ack = GrantAck {
+                worker_id: "w1".into(),
+                grant_id: grant.grant_id,
+            };
+            common::send(&mut worker, MSG_GRANT_ACK, &ack).await;
+            for job in grant.jobs {
+                if ran.insert(job.id) {
+                    common::send(&mut worker, MSG_JOB_COMPLETE, &completed(job.id)).await;
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    coord.checkpoint_now().unwrap();
+
+    let faults = chaos.faults();
+    assert_eq!(faults.crashed_ticks, 0, "crashes were forced, not rolled");
+    assert!(restarts >= 2);
+    let stored = CheckpointStore::open(&db).unwrap().restore_jobs().unwrap();
+    let report = chaos.check(stored.values());
+    assert!(report.holds(), "{}", report.summary());
+    assert_eq!(report.submitted, 6);
+    assert!(report.unfinished.is_empty());
+    assert_eq!(ran.len(), 6);
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_checker_flags_lost_and_twice_completed_jobs() {
+    let chaos = Arc::new(Chaos::new(ChaosConfig::default()));
+    let net = MemNetwork::new();
+    let mut t = ChaosTransport::wrap(Box::new(net.coordinator()), chaos.clone());
+
+    let (kept, forgotten) = (common::job("chaos_test"), common::job("chaos_test"));
+    let sub = JobSubmit {
+        jobs: vec![kept.clone(), forgotten.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    t.broadcast(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+
+    // The same report echoed twice is one execution
+    let once = serde_json::to_value(completed(kept.id)).unwrap();
+    t.broadcast_batch(vec![
+        (EV_JOB_COMPLETE, once.clone()),
+        (EV_JOB_COMPLETE, once),
+    ])
+    .await
+    .unwrap();
+    let report = chaos.check([&kept]);
+    assert_eq!(report.lost, [forgotten.id]);
+    assert!(report.double_completed.is_empty());
+
+    // A second run of the same job is not
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    t.broadcast(
+        EV_JOB_COMPLETE,
+        serde_json::to_value(completed(kept.id)).unwrap(),
+    )
+    .await
+    .unwrap();
+    let report = chaos.check([&kept, &forgotten]);
+    assert!(report.lost.is_empty());
+    assert_eq!(report.double_completed, [kept.id]);
+    assert!(!report.holds());
+}
@@ -0,0 +1,32 @@
+use unifiedlab::checkpoint::{CheckpointStore, StoreConfig};
+use unifiedlab::resources::ClusterType;
+
+#[test]
+fn test_journal_mode_follows_the_cluster_type() {
+    let root = std::env::temp_dir().join(format!("ulab_wal_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let db = root.join("checkpoint.db");
+
+    let store =
+        CheckpointStore::open_with(&db, StoreConfig::for_cluster(ClusterType::Local)).unwrap();
+    assert_eq!(store.journal_mode().unwrap(), "wal");
+    store.set_meta("probe", "1").unwrap();
+
+    // A reader keeps whatever the Coordinator chose
+    let reader = CheckpointStore::open(&db).unwrap();
+    assert_eq!(reader.journal_mode().unwrap(), "wal");
+    assert_eq!(reader.get_meta("probe").unwrap().as_deref(), Some("1"));
+
+    // Leaving WAL waits until nobody else has the DB open
+    let shared = StoreConfig::for_cluster(ClusterType::Slurm);
+    let busy = CheckpointStore::open_with(&db, shared.clone()).unwrap();
+    assert_eq!(busy.journal_mode().unwrap(), "wal");
+    drop((store, reader, busy));
+
+    // The same DB moved onto a shared filesystem goes back to DELETE
+    let store = CheckpointStore::open_with(&db, shared).unwrap();
+    assert_eq!(store.journal_mode().unwrap(), "delete");
+    assert!(!root.join("checkpoint.db-wal").exists());
+
+    std::fs::remove_dir_all(&root).ok();
+}
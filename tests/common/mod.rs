@@ -0,0 +1,112 @@
+// Fixtures shared by the integration tests. Each test file that needs them
+// declares `mod common;`, so a new field of a shared type is added here once.
+#![allow(dead_code)]
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use unifiedlab::checkpoint::WorkerInfo;
+use unifiedlab::core::{Engine, JobConfig, ResourceReq};
+use unifiedlab::marketplace::{
+    MarketplaceCoordinator, WorkGrant, WorkRequest, EV_WORK_GRANT, MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::Transport;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+/// A config running `engine` with `params`; no retry policy, environment
+/// or secrets.
+pub fn config(engine: Engine, params: Value) -> JobConfig {
+    JobConfig {
+        engine,
+        params,
+        retry: None,
+        environment: None,
+        secrets: Default::default(),
+    }
+}
+
+/// A job with the default engine and no params, on an empty structure
+/// named `name`.
+pub fn job(name: &str) -> Job {
+    Job::new(
+        Structure::new(vec![], None, name.into()),
+        config(Default::default(), json!({})),
+        ResourceReq::default(),
+    )
+}
+
+/// A heartbeat from `worker_id` with `cores` free and nothing else to
+/// report: no GPUs, memory, tags or totals, and the default job slots.
+pub fn work_request(worker_id: &str, cores: usize) -> WorkRequest {
+    WorkRequest {
+        worker_id: worker_id.into(),
+        available_cores: cores,
+        available_gpus: 0,
+        available_memory_mb: None,
+        max_jobs: 64,
+        tags: vec![],
+        read_lag: None,
+        hostname: None,
+        total_cores: None,
+        total_gpus: None,
+    }
+}
+
+/// A checkpointed worker with `cores` free, last heard from at
+/// `last_seen_ms`; idle, untagged, and reporting no GPUs or totals.
+pub fn worker_info(worker_id: &str, cores: usize, last_seen_ms: i64) -> WorkerInfo {
+    WorkerInfo {
+        worker_id: worker_id.into(),
+        cores,
+        tasks: 0,
+        last_seen_ms,
+        read_lag: None,
+        gpus: 0,
+        tags: vec![],
+        hostname: None,
+        draining: false,
+        total_cores: None,
+        total_gpus: None,
+    }
+}
+
+/// Sends `msg` to the Coordinator as a `kind` message.
+pub async fn send<T: Serialize>(t: &mut impl Transport, kind: &str, msg: &T) {
+    t.send_to_coordinator(kind, serde_json::to_value(msg).unwrap())
+        .await
+        .unwrap();
+}
+
+/// Sends the heartbeat `req`, lets the Coordinator tick, and returns the
+/// jobs granted in reply. Other broadcasts read on the way are dropped.
+pub async fn grants(
+    coord: &mut MarketplaceCoordinator,
+    w: &mut impl Transport,
+    req: &WorkRequest,
+) -> Vec<Job> {
+    send(w, MSG_WORK_REQUEST, req).await;
+    coord.tick().await.unwrap();
+    w.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_WORK_GRANT)
+        .flat_map(|e| {
+            serde_json::from_value::<WorkGrant>(e.record.payload)
+                .unwrap()
+                .jobs
+        })
+        .collect()
+}
+
+/// Asks for work as `worker_id` with `cores` free (see `work_request`);
+/// the ids of the jobs granted.
+pub async fn request(
+    coord: &mut MarketplaceCoordinator,
+    w: &mut impl Transport,
+    worker_id: &str,
+    cores: usize,
+) -> Vec<Uuid> {
+    let req = work_request(worker_id, cores);
+    grants(coord, w, &req).await.iter().map(|j| j.id).collect()
+}
@@ -0,0 +1,214 @@
+use std::ffi::OsStr;
+
+use tokio::process::Command;
+use unifiedlab::core::{Environment, JobConfig};
+use unifiedlab::drivers::utils::in_environment;
+use unifiedlab::marketplace::job_fingerprint;
+use unifiedlab::resources::Sandbox;
+use unifiedlab::workflow::yaml::YamlLoader;
+
+mod common;
+
+fn sandbox(gpus: Vec<usize>) -> Sandbox {
+    Sandbox {
+        cores: vec![2, 3],
+        gpus,
+        memory_mb_limit: Some(4096),
+    }
+}
+
+/// `vasp_std -n 2` in `dir`, with the sandbox's env vars.
+fn vasp(dir: &str, sandbox: &Sandbox) -> Command {
+    let mut cmd = Command::new("vasp_std");
+    cmd.args(["-n", "2"]).current_dir(dir);
+    sandbox.apply(&mut cmd);
+    cmd.env_remove("SLURM_JOBID");
+    cmd
+}
+
+fn argv(cmd: &Command) -> Vec<String> {
+    let cmd = cmd.as_std();
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect()
+}
+
+fn env_of(cmd: &Command, key: &str) -> Option<Option<String>> {
+    cmd.as_std()
+        .get_envs()
+        .find(|(k, _)| *k == OsStr::new(key))
+        .map(|(_, v)| v.map(|v| v.to_string_lossy().into_owned()))
+}
+
+#[test]
+fn test_environment_wraps_the_command() {
+    let sb = sandbox(vec![]);
+
+    // No environment: the command is left alone
+    let cmd = in_environment(vasp("/w", &sb), None, &sb);
+    assert_eq!(argv(&cmd), ["vasp_std", "-n", "2"]);
+
+    let uv = Environment::Uv {
+        project: "/envs/relax".into(),
+    };
+    let cmd = in_environment(vasp("/w", &sb), Some(&uv), &sb);
+    assert_eq!(
+        argv(&cmd),
+        [
+            "uv",
+            "run",
+            "--project",
+            "/envs/relax",
+            "--",
+            "vasp_std",
+            "-n",
+            "2"
+        ]
+    );
+    assert_eq!(cmd.as_std().get_current_dir().unwrap(), OsStr::new("/w"));
+    assert_eq!(env_of(&cmd, "OMP_NUM_THREADS"), Some(Some("2".into())));
+    assert_eq!(env_of(&cmd, "SLURM_JOBID"), Some(None));
+
+    let apptainer = Environment::Apptainer {
+        image: "/images/vasp.sif".into(),
+    };
+    let gpu = sandbox(vec![1]);
+    let cmd = in_environment(vasp("/w", &gpu), Some(&apptainer), &gpu);
+    assert_eq!(
+        argv(&cmd),
+        [
+            "apptainer",
+            "exec",
+            "--nv",
+            "--bind",
+            "/w",
+            "--pwd",
+            "/w",
+            "/images/vasp.sif",
+            "vasp_std",
+            "-n",
+            "2"
+        ]
+    );
+
+    let modules = Environment::Modules {
+        modules: vec!["vasp/6.4".into(), "intel-mpi".into()],
+    };
+    let cmd = in_environment(vasp("/w", &sb), Some(&modules), &sb);
+    assert_eq!(&argv(&cmd)[..2], ["bash", "-lc"]);
+    assert_eq!(&argv(&cmd)[3..], ["vasp_std", "-n", "2"]);
+    assert_eq!(
+        env_of(&cmd, "ULAB_MODULES"),
+        Some(Some("vasp/6.4 intel-mpi".into()))
+    );
+}
+
+#[test]
+fn test_docker_gets_the_sandbox() {
+    let docker = Environment::Docker {
+        image: "ghcr.io/lab/vasp:6.4".into(),
+    };
+    let gpu = sandbox(vec![1]);
+    let cmd = in_environment(vasp("/w", &gpu), Some(&docker), &gpu);
+    let args = argv(&cmd);
+
+    let image = args
+        .iter()
+        .position(|a| a == "ghcr.io/lab/vasp:6.4")
+        .unwrap();
+    assert_eq!(&args[image + 1..], ["vasp_std", "-n", "2"]);
+    let flags = args[..image].join(" ");
+    assert!(
+        flags.starts_with("docker run --rm -i -v /w:/w -w /w"),
+        "{}",
+        flags
+    );
+    assert!(flags.contains("--cpuset-cpus 2,3"), "{}", flags);
+    assert!(flags.contains("--gpus \"device=1\""), "{}", flags);
+    assert!(flags.contains("--memory 4096m"), "{}", flags);
+    // Values reach the container from docker's own env, not its argv
+    assert!(flags.contains("-e OMP_NUM_THREADS"), "{}", flags);
+    assert!(!flags.contains("OMP_NUM_THREADS="), "{}", flags);
+    assert_eq!(env_of(&cmd, "OMP_NUM_THREADS"), Some(Some("2".into())));
+    // Docker numbers the devices it passes from 0
+    assert!(!flags.contains("CUDA_VISIBLE_DEVICES"), "{}", flags);
+
+    let cpu = sandbox(vec![]);
+    let cmd = in_environment(vasp("/w", &cpu), Some(&docker), &cpu);
+    let args = argv(&cmd).join(" ");
+    assert!(!args.contains("--gpus"), "{}", args);
+    assert!(args.contains("-e CUDA_VISIBLE_DEVICES"), "{}", args);
+    assert_eq!(env_of(&cmd, "CUDA_VISIBLE_DEVICES"), Some(Some("".into())));
+}
+
+#[test]
+fn test_yaml_environment_reaches_the_job() {
+    let dir = std::env::temp_dir().join(format!("ulab_env_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("campaign.yaml");
+    std::fs::write(
+        &path,
+        r#"
+version: 1
+metadata: { name: campaign }
+environment: { kind: uv_project, path: envs/agents }
+nodes:
+  - id: propose
+    type: compute
+    engine: { kind: agent, script: propose.py }
+  - id: relax
+    type: compute
+    engine: { kind: vasp }
+    environment: { kind: docker_image, image: "lab/vasp:6.4" }
+edges:
+  - { from: propose, to: relax }
+"#,
+    )
+    .unwrap();
+
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    let env = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+            .job
+            .config
+            .environment
+            .clone()
+    };
+    assert_eq!(
+        env("propose"),
+        Some(Environment::Uv {
+            project: dir.join("envs/agents").to_string_lossy().into_owned()
+        })
+    );
+    assert_eq!(
+        env("relax"),
+        Some(Environment::Docker {
+            image: "lab/vasp:6.4".into()
+        })
+    );
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_environment_is_part_of_the_fingerprint() {
+    let host = common::config(Default::default(), serde_json::json!({}));
+    let docker = JobConfig {
+        environment: Some(Environment::Docker {
+            image: "lab/vasp:6.4".into(),
+        }),
+        ..host.clone()
+    };
+    assert_ne!(job_fingerprint(&host), job_fingerprint(&docker));
+
+    // Configs from before environments keep their fingerprint
+    let wire = serde_json::to_value(&host).unwrap();
+    assert!(wire.get("environment").is_none());
+}
@@ -0,0 +1,126 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, ElectronVolts, JobStatus, Provenance, ResultPredicate, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+use uuid::Uuid;
+
+mod common;
+
+fn result(energy: f64) -> CalculationResult {
+    let now = chrono::Utc::now();
+    CalculationResult {
+        energy: Some(ElectronVolts(energy)),
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "test".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation: None,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+#[test]
+fn test_predicates_parse_and_test_results() {
+    let low: ResultPredicate = "parent.energy < -5.0".parse().unwrap();
+    assert_eq!(low.to_string(), "energy < -5");
+    assert!(low.holds(Some(&result(-6.0))));
+    assert!(!low.holds(Some(&result(-5.0))));
+    assert!(!low.holds(None));
+
+    let at_most: ResultPredicate = "energy<=-5".parse().unwrap();
+    assert!(at_most.holds(Some(&result(-5.0))));
+    assert!("band_gap".parse::<ResultPredicate>().is_err());
+    assert!("energy < low".parse::<ResultPredicate>().is_err());
+    assert!(!"usage.max_rss_mb > 1"
+        .parse::<ResultPredicate>()
+        .unwrap()
+        .holds(Some(&result(0.0))));
+}
+
+#[tokio::test]
+async fn test_child_runs_only_if_the_parent_result_passes() {
+    let root = std::env::temp_dir().join(format!("ulab_gate_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut client = net.worker(None);
+
+    // relax -> { refine if low, rescue if high -> report }
+    let relax = common::job("gate_test");
+    let mut refine = common::job("gate_test");
+    refine
+        .conditions
+        .insert(relax.id, "energy < -5.0".parse().unwrap());
+    let mut rescue = common::job("gate_test");
+    rescue
+        .conditions
+        .insert(relax.id, "energy >= -5.0".parse().unwrap());
+    let report = common::job("gate_test");
+    let sub = JobSubmit {
+        jobs: vec![
+            relax.clone(),
+            refine.clone(),
+            rescue.clone(),
+            report.clone(),
+        ],
+        deps: vec![
+            (relax.id, refine.id),
+            (relax.id, rescue.id),
+            (rescue.id, report.id),
+        ],
+        routing: Routing::default(),
+        txn: None,
+    };
+    client
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    let rep = JobCompleteReport {
+        job_id: relax.id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(result(-6.2)),
+        error: None,
+    };
+    client
+        .send_to_coordinator(MSG_JOB_COMPLETE, serde_json::to_value(&rep).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    let status = |id: Uuid| {
+        let j = coord.jobs().find(|j| j.id == id).unwrap();
+        (j.status.clone(), j.error_log.clone())
+    };
+    assert_eq!(status(refine.id).0, JobStatus::Pending);
+    let pruned = (
+        JobStatus::Failed,
+        Some("Pruned by Logic Condition".to_string()),
+    );
+    assert_eq!(status(rescue.id), pruned);
+    assert_eq!(status(report.id), pruned);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,143 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, ElectronVolts, JobStatus, Provenance, ResourceReq, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+use unifiedlab::workflow::yaml::YamlLoader;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn job(params: serde_json::Value) -> Job {
+    Job::new(
+        Structure::new(vec![], None, "dataflow_test".into()),
+        common::config(Default::default(), params),
+        ResourceReq::default(),
+    )
+}
+
+fn result(energy: f64) -> CalculationResult {
+    let now = chrono::Utc::now();
+    CalculationResult {
+        energy: Some(ElectronVolts(energy)),
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "test".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation: None,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+#[test]
+fn test_yaml_dataflow_edge_maps_onto_the_child() {
+    let dir = std::env::temp_dir().join(format!("ulab_dataflow_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("flow.yaml");
+    std::fs::write(
+        &path,
+        r#"
+version: 1
+metadata: { name: flow }
+nodes:
+  - { id: relax, type: compute }
+  - { id: refine, type: compute }
+edges:
+  - from: relax
+    to: refine
+    kind: !dataflow { map: { start_energy: energy, cell: final_structure.lattice } }
+"#,
+    )
+    .unwrap();
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    let node = |id: &str| {
+        &graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+            .job
+    };
+    let map = &node("refine").dataflow[&node("relax").id];
+    assert_eq!(map["start_energy"], "energy");
+    assert_eq!(map["cell"], "final_structure.lattice");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_parent_result_is_fed_into_child_params() {
+    let root = std::env::temp_dir().join(format!("ulab_dataflow_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+
+    let parent = job(serde_json::json!({}));
+    let mut child = job(serde_json::json!({ "steps": 40 }));
+    child.parent_ids.push(parent.id);
+    child.dataflow.insert(
+        parent.id,
+        [
+            ("start_energy".to_string(), "parent.energy".to_string()),
+            ("gap".to_string(), "band_gap".to_string()),
+        ]
+        .into(),
+    );
+    let sub = JobSubmit {
+        jobs: vec![parent.clone(), child.clone()],
+        deps: vec![(parent.id, child.id)],
+        routing: Routing::default(),
+        txn: None,
+    };
+    w1.send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+
+    let granted = common::grants(&mut coord, &mut w1, &common::work_request("w1", 8)).await;
+    assert_eq!(granted.len(), 1);
+    assert_eq!(granted[0].id, parent.id);
+    let rep = JobCompleteReport {
+        job_id: parent.id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(result(-7.25)),
+        error: None,
+    };
+    w1.send_to_coordinator(MSG_JOB_COMPLETE, serde_json::to_value(&rep).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    // A field the result lacks is left unset
+    let granted = common::grants(&mut coord, &mut w1, &common::work_request("w1", 8)).await;
+    assert_eq!(granted.len(), 1);
+    assert_eq!(
+        granted[0].config.params,
+        serde_json::json!({ "steps": 40, "start_energy": -7.25 })
+    );
+
+    std::fs::remove_dir_all(&root).ok();
+}
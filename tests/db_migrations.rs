@@ -0,0 +1,59 @@
+use unifiedlab::checkpoint::{CheckpointStore, DB_SCHEMA_VERSION, META_SCHEMA_VERSION};
+use unifiedlab::core::{JobStatus, ResourceReq};
+use unifiedlab::{Job, Structure};
+
+mod common;
+
+#[test]
+fn test_old_db_is_migrated_and_newer_db_refused() {
+    let root = std::env::temp_dir().join(format!("ulab_migrate_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let db = root.join("checkpoint.db");
+
+    // A DB as the first release left it: no version, three tables
+    let conn = rusqlite::Connection::open(&db).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT);
+         CREATE TABLE workers (id TEXT PRIMARY KEY, last_seen_ms INTEGER, state_json TEXT);
+         CREATE TABLE jobs (id TEXT PRIMARY KEY, status TEXT, updated_at_ms INTEGER,
+                            node_id TEXT, full_json TEXT);
+         INSERT INTO meta VALUES ('cursor', '1234');",
+    )
+    .unwrap();
+    let mut old = Job::new(
+        Structure::new(vec![], None, "migrate_test".into()),
+        common::config(Default::default(), serde_json::json!({ "seed": 7 })),
+        ResourceReq::default(),
+    );
+    old.status = JobStatus::Failed;
+    old.error_log = Some("segfault in old binary".into());
+    let id = old.id;
+    let job = serde_json::to_value(&old).unwrap();
+    conn.execute(
+        "INSERT INTO jobs VALUES (?1, 'Failed', 0, NULL, ?2)",
+        rusqlite::params![id.to_string(), job.to_string()],
+    )
+    .unwrap();
+    drop(conn);
+
+    let store = CheckpointStore::open(&db).unwrap();
+    assert_eq!(store.schema_version().unwrap(), DB_SCHEMA_VERSION);
+    assert_eq!(store.get_cursor().unwrap(), 1234);
+    assert!(store.get_job_history(&id.to_string()).unwrap().is_empty());
+    assert_eq!(store.search_jobs("segfault").unwrap().len(), 1);
+
+    // Opening again has nothing left to do
+    drop(store);
+    let store = CheckpointStore::open(&db).unwrap();
+    assert_eq!(store.schema_version().unwrap(), DB_SCHEMA_VERSION);
+
+    // A DB from a newer build is left alone
+    store
+        .set_meta(META_SCHEMA_VERSION, &(DB_SCHEMA_VERSION + 1).to_string())
+        .unwrap();
+    drop(store);
+    let err = CheckpointStore::open(&db).err().unwrap();
+    assert!(format!("{:#}", err).contains("newer UnifiedLAB"));
+
+    std::fs::remove_dir_all(&root).ok();
+}
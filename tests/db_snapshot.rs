@@ -0,0 +1,70 @@
+use unifiedlab::checkpoint::{CheckpointStore, WorkerInfo};
+use unifiedlab::core::{JobStatus, ResourceReq};
+use unifiedlab::{Job, Structure};
+
+mod common;
+
+#[test]
+fn test_snapshot_round_trips_into_an_empty_db() {
+    let root = std::env::temp_dir().join(format!("ulab_snapshot_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("a.db")).unwrap();
+
+    let mut jobs: Vec<Job> = (0..3)
+        .map(|_| {
+            Job::new(
+                Structure::new(vec![], None, "snapshot_test".into()),
+                common::config(Default::default(), serde_json::json!({ "x": 1.5 })),
+                ResourceReq::default(),
+            )
+        })
+        .collect();
+    let worker = WorkerInfo {
+        tasks: 1,
+        ..common::worker_info("w1", 8, 42)
+    };
+    store
+        .apply_batch(7, &jobs.iter().collect::<Vec<_>>(), &[worker])
+        .unwrap();
+    jobs[0].status = JobStatus::Completed;
+    jobs[0].node_id = Some("w1".into());
+    store.apply_batch(9, &[&jobs[0]], &[]).unwrap();
+    store.set_meta("paused", "true").unwrap();
+
+    let first = root.join("first.jsonl");
+    let counts = store.export_jsonl(&first).unwrap();
+    assert_eq!((counts.jobs, counts.job_events, counts.workers), (3, 4, 1));
+
+    let copy = CheckpointStore::open(root.join("b.db")).unwrap();
+    assert_eq!(copy.import_jsonl(&first).unwrap(), counts);
+    assert_eq!(copy.get_cursor().unwrap(), 9);
+    assert_eq!(copy.get_meta("paused").unwrap().as_deref(), Some("true"));
+    assert_eq!(copy.get_active_workers().unwrap()[0].cores, 8);
+    let restored = copy.restore_jobs().unwrap();
+    assert_eq!(restored[&jobs[0].id].status, JobStatus::Completed);
+    assert_eq!(
+        copy.get_job_history(&jobs[0].id.to_string()).unwrap(),
+        store.get_job_history(&jobs[0].id.to_string()).unwrap()
+    );
+
+    // Byte for byte the same once exported again
+    let second = root.join("second.jsonl");
+    copy.export_jsonl(&second).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&first).unwrap(),
+        std::fs::read_to_string(&second).unwrap()
+    );
+
+    // Never mixed into a DB that already has a run in it
+    assert!(copy.import_jsonl(&first).is_err());
+    let empty = CheckpointStore::open(root.join("c.db")).unwrap();
+    std::fs::write(
+        root.join("bad.jsonl"),
+        "{\"type\":\"meta\",\"key\":\"k\"}\n",
+    )
+    .unwrap();
+    assert!(empty.import_jsonl(root.join("bad.jsonl")).is_err());
+    assert!(empty.get_meta("k").unwrap().is_none());
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    JobSubmit, MarketplaceCoordinator, Routing, WorkGrant, EV_JOB_SUBMIT, EV_WORK_GRANT,
+    MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+
+mod common;
+
+async fn heartbeat(t: &mut MemTransport, worker_id: &str, cores: usize) {
+    let req = common::work_request(worker_id, cores);
+    t.send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req).unwrap())
+        .await
+        .unwrap();
+}
+
+async fn granted(t: &mut MemTransport, worker_id: &str) -> usize {
+    t.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_WORK_GRANT)
+        .map(|e| serde_json::from_value::<WorkGrant>(e.record.payload).unwrap())
+        .filter(|g| g.worker_id == worker_id)
+        .map(|g| g.jobs.len())
+        .sum()
+}
+
+#[tokio::test]
+async fn test_silent_worker_loses_its_jobs() {
+    let root = std::env::temp_dir().join(format!("ulab_dead_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap()
+        .with_worker_timeout(Duration::from_millis(200));
+    let mut w1 = net.worker(Some("w1"));
+    let mut w2 = net.worker(Some("w2"));
+
+    let sub = JobSubmit {
+        jobs: vec![
+            common::job("dead_worker_test"),
+            common::job("dead_worker_test"),
+        ],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    w1.send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    heartbeat(&mut w1, "w1", 2).await;
+    coord.tick().await.unwrap();
+    assert_eq!(granted(&mut w1, "w1").await, 2);
+
+    // w2 keeps reporting in while w1 has crashed
+    for _ in 0..3 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        heartbeat(&mut w2, "w2", 0).await;
+        coord.tick().await.unwrap();
+    }
+    assert!(coord.jobs().all(|j| j.status == JobStatus::Pending));
+    assert!(coord.jobs().all(|j| j.node_id.is_none()));
+
+    // The next worker with room gets them
+    heartbeat(&mut w2, "w2", 2).await;
+    coord.tick().await.unwrap();
+    assert_eq!(granted(&mut w2, "w2").await, 2);
+    assert!(coord.jobs().all(|j| j.node_id.as_deref() == Some("w2")));
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,121 @@
+use chrono::{Duration, TimeZone, Utc};
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::deadline::{
+    critical_paths, parse_deadline, DeadlineStatus, FLOW_DEADLINE, FLOW_LATEST_START,
+};
+use unifiedlab::marketplace::{
+    JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, META_DEADLINES,
+};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+use unifiedlab::Job;
+use uuid::Uuid;
+
+mod common;
+
+fn job(due_in: Option<Duration>) -> Job {
+    let mut job = common::job("deadline_test");
+    if let Some(d) = due_in {
+        job.flow_context
+            .insert(FLOW_DEADLINE.into(), (Utc::now() + d).to_rfc3339().into());
+    }
+    job
+}
+
+#[test]
+fn test_deadlines_parse_and_paths_add_up() {
+    let now = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+    assert_eq!(
+        parse_deadline("36h", now).unwrap(),
+        now + Duration::hours(36)
+    );
+    assert_eq!(
+        parse_deadline("2026-03-02T00:00:00Z", now).unwrap(),
+        now + Duration::hours(12)
+    );
+    assert!(parse_deadline("soon", now).is_err());
+    assert!(parse_deadline("3w", now).is_err());
+    assert!(parse_deadline("", now).is_err());
+
+    // a -> b -> c, a -> d: a has 3 hours of work ahead of it
+    let (mut a, mut b, mut c, mut d) = (job(None), job(None), job(None), job(None));
+    a.resources.time_limit_min = 60;
+    b.parent_ids.push(a.id);
+    c.parent_ids.push(b.id);
+    d.parent_ids.push(a.id);
+    d.resources.time_limit_min = 30;
+    let paths = critical_paths([&a, &b, &c, &d]);
+    assert_eq!(paths[&a.id], 180);
+    assert_eq!(paths[&b.id], 120);
+    assert_eq!(paths[&d.id], 30);
+}
+
+#[tokio::test]
+async fn test_least_slack_goes_first_and_misses_are_flagged() {
+    let root = std::env::temp_dir().join(format!("ulab_deadline_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+
+    // No deadline, though submitted first; a two-hour chain due in three
+    // hours; an hour's job due in four. And an hour's job due in half an
+    // hour, too big for the worker: late before it starts
+    let relaxed = job(None);
+    let (head, mut tail) = (job(Some(Duration::hours(3))), job(None));
+    tail.parent_ids.push(head.id);
+    tail.flow_context.insert(
+        FLOW_DEADLINE.into(),
+        head.flow_context[FLOW_DEADLINE].clone(),
+    );
+    let later = job(Some(Duration::hours(4)));
+    let mut hopeless = job(Some(Duration::minutes(30)));
+    hopeless.resources.cores = 2;
+    let sub = JobSubmit {
+        jobs: vec![
+            relaxed.clone(),
+            later.clone(),
+            head.clone(),
+            tail.clone(),
+            hopeless,
+        ],
+        deps: vec![(head.id, tail.id)],
+        routing: Routing::default(),
+        txn: None,
+    };
+    w1.send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        common::request(&mut coord, &mut w1, "w1", 1).await,
+        [head.id]
+    );
+    let stamped = coord.jobs().find(|j| j.id == head.id).unwrap();
+    let due = (Utc::now() + Duration::hours(3)).timestamp_millis();
+    let start_by = stamped.flow_context[FLOW_LATEST_START].as_i64().unwrap();
+    assert!((due - 2 * 3_600_000 - start_by).abs() < 60_000);
+
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let status: Vec<DeadlineStatus> =
+        serde_json::from_str(&store.get_meta(META_DEADLINES).unwrap().unwrap()).unwrap();
+    assert_eq!(status.len(), 3);
+    assert!(status[0].is_late());
+    assert_eq!(status[0].jobs, 1);
+    assert!(status[1..].iter().all(|d| !d.is_late()));
+
+    assert_eq!(
+        common::request(&mut coord, &mut w1, "w1", 1).await,
+        [later.id]
+    );
+    assert_eq!(
+        common::request(&mut coord, &mut w1, "w1", 1).await,
+        [relaxed.id]
+    );
+
+    std::fs::remove_dir_all(&root).ok();
+}
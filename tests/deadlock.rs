@@ -0,0 +1,100 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, META_DEADLOCKED,
+    META_HEARTBEAT, MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::{FileTransport, Role, Transport};
+
+mod common;
+
+#[tokio::test]
+async fn test_children_of_failed_parent_are_cancelled_with_reason_chain() {
+    let root = std::env::temp_dir().join(format!("ulab_dead_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let transport = FileTransport::new(&root, Role::Coordinator, None)
+        .await
+        .unwrap();
+    let mut coord = MarketplaceCoordinator::open(Box::new(transport), store)
+        .await
+        .unwrap()
+        .with_deadlock_scan(Duration::ZERO);
+    let mut worker = FileTransport::new(&root, Role::Worker, Some("w1"))
+        .await
+        .unwrap();
+
+    // a -> b -> c, plus an unrelated d
+    let (a, b, c, d) = (
+        common::job("deadlock_test"),
+        common::job("deadlock_test"),
+        common::job("deadlock_test"),
+        common::job("deadlock_test"),
+    );
+    let sub = JobSubmit {
+        jobs: vec![a.clone(), b.clone(), c.clone(), d.clone()],
+        deps: vec![(a.id, b.id), (b.id, c.id)],
+        routing: Routing::default(),
+        txn: None,
+    };
+    worker
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    let rep = JobCompleteReport {
+        job_id: a.id,
+        worker_id: "w1".into(),
+        status: JobStatus::Failed,
+        result: None,
+        error: Some("SCF did not converge".into()),
+    };
+    worker
+        .send_to_coordinator(MSG_JOB_COMPLETE, serde_json::to_value(&rep).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    coord.checkpoint_now().unwrap();
+
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let get = |id: uuid::Uuid| store.get_job_details(&id.to_string()).unwrap();
+    let short = |id: uuid::Uuid| id.simple().to_string()[..8].to_string();
+
+    let b_now = get(b.id);
+    assert_eq!(b_now.status, JobStatus::Cancelled);
+    assert_eq!(
+        b_now.error_log.unwrap(),
+        format!(
+            "Unsatisfiable: {} Failed: SCF did not converge",
+            short(a.id)
+        )
+    );
+    let c_now = get(c.id);
+    assert_eq!(c_now.status, JobStatus::Cancelled);
+    assert_eq!(
+        c_now.error_log.unwrap(),
+        format!(
+            "Unsatisfiable: {} Cancelled <- {} Failed: SCF did not converge",
+            short(b.id),
+            short(a.id)
+        )
+    );
+    assert_eq!(get(d.id).status, JobStatus::Pending);
+    assert_eq!(
+        store.get_meta(META_DEADLOCKED).unwrap().as_deref(),
+        Some("2")
+    );
+    // Stamped from the first tick on, for the TUI's stale banner
+    let beat: i64 = store
+        .get_meta(META_HEARTBEAT)
+        .unwrap()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(chrono::Utc::now().timestamp_millis() - beat < 60_000);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use chrono::{Duration as Span, Utc};
+use unifiedlab::checkpoint::{CheckpointStore, WorkerInfo};
+use unifiedlab::core::JobStatus;
+use unifiedlab::dryrun::{dry_run_from_store, simulate};
+use unifiedlab::workflow::yaml::YamlLoader;
+use unifiedlab::WorkflowEngine;
+
+mod common;
+
+const CAMPAIGN: &str = r#"
+version: 2
+metadata: { name: campaign }
+nodes:
+  - id: propose
+    type: generator
+    engine: { kind: agent, script: agents/ga.py, strategy: genetic }
+    resources: { cores: 1, time_limit_min: 10, required_tags: [brain] }
+    params: { gen_limit: 4, governor: { max_children: 20, max_descendants: 50 } }
+  - id: relax_a
+    type: compute
+    engine: { kind: vasp }
+    resources: { cores: 8 }
+  - id: relax_b
+    type: compute
+    engine: { kind: vasp }
+    resources: { cores: 8 }
+  - id: collect
+    type: aggregator
+    resources: { time_limit_min: 5 }
+  - id: big
+    type: compute
+    engine: { kind: vasp }
+    resources: { cores: 64 }
+  - id: after_big
+    type: compute
+    engine: { kind: vasp }
+edges:
+  - { from: propose, to: relax_a }
+  - { from: propose, to: relax_b }
+  - { from: relax_a, to: collect }
+  - { from: relax_b, to: collect }
+  - { from: big, to: after_big }
+"#;
+
+fn worker(id: &str, cores: usize, gpus: usize, tags: &[&str]) -> WorkerInfo {
+    WorkerInfo {
+        gpus,
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        ..common::worker_info(id, cores, Utc::now().timestamp_millis())
+    }
+}
+
+fn campaign() -> (std::path::PathBuf, WorkflowEngine) {
+    let dir = std::env::temp_dir().join(format!("ulab_dryrun_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("campaign.yaml");
+    std::fs::write(&path, CAMPAIGN).unwrap();
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    (dir, graph)
+}
+
+fn vasp_code(graph: &WorkflowEngine) -> String {
+    graph
+        .graph
+        .node_weights()
+        .find(|n| n.job.structure.source == "relax_a")
+        .unwrap()
+        .job
+        .config
+        .engine
+        .code()
+}
+
+#[test]
+fn test_dry_run_schedules_the_blueprint_on_the_workers() {
+    let (dir, graph) = campaign();
+    let workers = [
+        worker("w1", 8, 0, &["muscle"]),
+        worker("w2", 4, 1, &["brain", "gpu"]),
+    ];
+    let runtimes = HashMap::from([(vasp_code(&graph), 30 * 60_000)]);
+    let run = simulate(&graph, &workers, &runtimes, 100).unwrap();
+
+    // propose (10m on w2), then both relaxations one after the other on the
+    // only worker with 8 cores (30m each), then collect (5m)
+    assert_eq!(run.makespan_ms, 75 * 60_000);
+    let code = vasp_code(&graph);
+    assert!(!run.unmeasured.contains(&code), "{:?}", run.unmeasured);
+    assert!(run.unmeasured.contains(&"agent:genetic".to_string()));
+
+    assert_eq!(run.unplaceable.len(), 1);
+    assert_eq!(run.unplaceable[0].node, "big");
+    assert_eq!(
+        run.unplaceable[0].reason,
+        "asks 64 core(s), the largest worker has 8"
+    );
+    assert_eq!(run.blocked, 1);
+
+    // 5 expansions of at most 20, capped at 50 by the governor
+    let bound = &run.growth[0];
+    assert_eq!(bound.node, "propose");
+    assert_eq!(
+        (bound.per_expansion, bound.expansions, bound.descendants),
+        (20, 5, 50)
+    );
+    assert_eq!(
+        bound.batch_unplaceable.as_deref(),
+        Some("asks 8 core(s) and 1 GPU(s), no worker has both")
+    );
+
+    // Without a brain, the generator and everything after it never run
+    let run = simulate(&graph, &workers[..1], &runtimes, 100).unwrap();
+    assert!(run.unplaceable[0].reason.contains("(brain)"));
+    assert_eq!(run.blocked, 4);
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_dry_run_counts_live_workers_at_full_capacity() {
+    let (dir, graph) = campaign();
+    let store = CheckpointStore::open(dir.join("checkpoint.db")).unwrap();
+    let now_ms = Utc::now().timestamp_millis();
+    let err = dry_run_from_store(&graph, &store, now_ms)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("No live workers"), "{}", err);
+
+    // w1 is busy with one 8-core job; the silent worker is left out
+    let mut busy = worker("w1", 0, 0, &["muscle"]);
+    busy.tasks = 1;
+    let mut silent = worker("w3", 128, 0, &[]);
+    silent.last_seen_ms = (Utc::now() - Span::hours(1)).timestamp_millis();
+    let mut job = graph
+        .graph
+        .node_weights()
+        .find(|n| n.job.structure.source == "relax_a")
+        .unwrap()
+        .job
+        .clone();
+    job.status = JobStatus::Running;
+    job.node_id = Some("w1".into());
+    store
+        .apply_batch(0, &[&job], &[busy, worker("w2", 4, 1, &["brain"]), silent])
+        .unwrap();
+
+    let run = dry_run_from_store(&graph, &store, now_ms).unwrap();
+    assert_eq!(run.workers, 2);
+    assert_eq!(run.unplaceable.len(), 1);
+    assert_eq!(run.blocked, 1);
+    assert_eq!(run.expand_limit, 100);
+
+    std::fs::remove_dir_all(dir).ok();
+}
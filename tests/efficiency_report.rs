@@ -0,0 +1,64 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::UsageRecord;
+use unifiedlab::core::{ResourceReq, ResourceUsage};
+use unifiedlab::report::{efficiency, Verdict};
+use unifiedlab::resources::UsageSampler;
+
+fn record(code: &str, cores: usize, cpu_seconds: f64, wall_seconds: f64) -> UsageRecord {
+    UsageRecord {
+        code: code.into(),
+        requested: ResourceReq {
+            cores,
+            time_limit_min: 60,
+            ..Default::default()
+        },
+        usage: ResourceUsage {
+            cpu_seconds,
+            wall_seconds,
+            peak_rss_mb: 512.0,
+            samples: 10,
+        },
+    }
+}
+
+#[test]
+fn test_efficiency_flags_over_and_under_provisioning() {
+    let rows = efficiency(&[
+        // Asked for 8 cores, kept ~1.5 busy
+        record("vasp:8p", 8, 150.0, 100.0),
+        record("vasp:8p", 8, 100.0, 100.0),
+        // Asked for 1 core, ran 4 threads
+        record("gulp", 1, 400.0, 100.0),
+        // About right, but ran for 58 of its 60 minutes
+        record("janus:mace_mp", 2, 6600.0, 3480.0),
+        record("agent:default", 2, 190.0, 100.0),
+    ]);
+    let row = |code: &str| rows.iter().find(|r| r.code == code).unwrap();
+
+    assert_eq!(row("vasp:8p").jobs, 2);
+    assert_eq!(row("vasp:8p").verdict, Verdict::Over);
+    assert_eq!(row("vasp:8p").suggestion, "cores 8 -> 2");
+    assert_eq!(row("gulp").verdict, Verdict::Under);
+    assert_eq!(row("janus:mace_mp").verdict, Verdict::Under);
+    assert!(row("janus:mace_mp")
+        .suggestion
+        .contains("time_limit_min 60 -> 87"));
+    assert_eq!(row("agent:default").verdict, Verdict::Ok);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sampler_measures_a_busy_child() {
+    let mut child = tokio::process::Command::new("sh")
+        .args(["-c", "i=0; while [ $i -lt 200000 ]; do i=$((i+1)); done"])
+        .spawn()
+        .unwrap();
+    let sampler = UsageSampler::start(child.id().unwrap(), Duration::from_millis(50));
+    child.wait().await.unwrap();
+
+    let usage = sampler.finish().await.expect("usage");
+    assert!(usage.samples > 1);
+    assert!(usage.cpu_seconds > 0.0);
+    assert!(usage.peak_rss_mb > 0.0);
+    assert!(usage.avg_cores() < 1.5);
+}
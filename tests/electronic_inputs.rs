@@ -0,0 +1,104 @@
+use serde_json::json;
+use unifiedlab::core::{Atom, CalculationResult, Provenance, RESULT_SCHEMA_VERSION};
+use unifiedlab::drivers::electronic::{apply_site_properties, engine_inputs, magmom_tag};
+use unifiedlab::Structure;
+
+fn atom(symbol: &str, charge: Option<f64>, magnetic_moment: Option<f64>) -> Atom {
+    Atom {
+        symbol: symbol.into(),
+        position: [0.0; 3],
+        charge,
+        magnetic_moment,
+        tags: Default::default(),
+    }
+}
+
+fn result() -> CalculationResult {
+    let now = chrono::Utc::now();
+    CalculationResult {
+        energy: None,
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "test".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation: None,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+#[test]
+fn test_charges_and_moments_become_engine_settings() {
+    // Two Fe(3+) with parallel moments and three O(2-): a neutral cell
+    let mut atoms = vec![
+        atom("Fe", Some(3.0), Some(5.0)),
+        atom("Fe", Some(3.0), Some(5.0)),
+    ];
+    atoms.extend((0..3).map(|_| atom("O", Some(-2.0), None)));
+    let s = Structure::new(atoms, None, "t".into());
+
+    let vasp = engine_inputs("vasp", &s).unwrap();
+    assert_eq!(vasp["MAGMOM"], "2*5.0 3*0.0");
+    assert_eq!(vasp["ISPIN"], 2);
+    assert_eq!(vasp["net_charge"], 0.0);
+
+    let gulp = engine_inputs("gulp", &s).unwrap();
+    assert_eq!(gulp["charges"], json!([3.0, 3.0, -2.0, -2.0, -2.0]));
+
+    let cp2k = engine_inputs("cp2k", &s).unwrap();
+    assert_eq!(cp2k["CHARGE"], 0);
+    assert_eq!(cp2k["MULTIPLICITY"], 11);
+    assert_eq!(cp2k["UKS"], true);
+
+    // Agents and plain structures get nothing
+    assert!(engine_inputs("agent", &s).is_none());
+    let plain = Structure::new(vec![atom("Si", None, None)], None, "t".into());
+    assert!(engine_inputs("vasp", &plain).is_none());
+
+    // A charged cell without moments leaves MAGMOM to VASP
+    let ion = Structure::new(vec![atom("Cl", Some(-1.0), None)], None, "t".into());
+    let vasp = engine_inputs("vasp", &ion).unwrap();
+    assert_eq!(vasp["net_charge"], -1.0);
+    assert!(vasp.get("MAGMOM").is_none());
+    assert_eq!(engine_inputs("cp2k", &ion).unwrap()["MULTIPLICITY"], 1);
+
+    assert_eq!(magmom_tag(&[1.0, -1.0, -1.0, 0.5]), "1.0 2*-1.0 0.5");
+}
+
+#[test]
+fn test_parsed_moments_land_on_final_structure() {
+    let input = Structure::new(
+        vec![atom("Ni", None, Some(1.0)), atom("O", None, None)],
+        None,
+        "t".into(),
+    );
+
+    // No final structure from the adapter: the input one carries them
+    let mut r = result();
+    let parsed = json!({ "magnetic_moments": [1.62, 0.01], "charges": [0.9] });
+    assert!(apply_site_properties(&mut r, &input, &parsed));
+    let fin = r.final_structure.unwrap();
+    assert_eq!(fin.atoms[0].magnetic_moment, Some(1.62));
+    assert_eq!(fin.atoms[1].magnetic_moment, Some(0.01));
+    // The charges list was one short and is dropped
+    assert_eq!(fin.atoms[0].charge, None);
+
+    // Nothing usable: the result is left alone
+    let mut r = result();
+    assert!(!apply_site_properties(
+        &mut r,
+        &input,
+        &json!({ "charges": [1.0] })
+    ));
+    assert!(r.final_structure.is_none());
+}
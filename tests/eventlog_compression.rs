@@ -0,0 +1,115 @@
+use serde_json::json;
+use unifiedlab::eventlog::{Compression, EventLogConfig, EventLogReader, EventLogWriter};
+use unifiedlab::transport::TransportConfig;
+
+#[test]
+fn test_mixed_compressed_and_raw_frames() {
+    let path = std::env::temp_dir().join(format!("ulab_evlog_{}.log", uuid::Uuid::new_v4()));
+
+    // A raw writer first (as an older build would have left the file)...
+    {
+        let mut w = EventLogWriter::open(&path, EventLogConfig::default()).expect("writer");
+        w.append("small", json!({"n": 1})).unwrap();
+    }
+
+    // ...then a compressing one appending to the same file
+    let big: Vec<String> = (0..5000).map(|i| format!("Si {} 0.0 0.0", i)).collect();
+    let raw_len = std::fs::metadata(&path).unwrap().len();
+    {
+        let cfg = EventLogConfig {
+            compression: Compression::Zlib,
+            ..Default::default()
+        };
+        let mut w = EventLogWriter::open(&path, cfg).expect("writer");
+        w.append("big", json!({ "atoms": big })).unwrap();
+        w.append("tiny", json!({"n": 2})).unwrap();
+    }
+    let plain_size = serde_json::to_vec(&big).unwrap().len() as u64;
+    let on_disk = std::fs::metadata(&path).unwrap().len() - raw_len;
+    assert!(on_disk < plain_size / 2, "big record was not compressed");
+
+    let mut r = EventLogReader::open(&path).expect("reader");
+    let kinds: Vec<String> = std::iter::from_fn(|| r.next().unwrap())
+        .map(|e| {
+            if e.record.kind == "big" {
+                assert_eq!(e.record.payload["atoms"][4999], "Si 4999 0.0 0.0");
+            }
+            e.record.kind
+        })
+        .collect();
+    assert_eq!(kinds, ["small", "big", "tiny"]);
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_zstd_records_mix_with_zlib_and_seal() {
+    let path = std::env::temp_dir().join(format!("ulab_evlog_{}.log", uuid::Uuid::new_v4()));
+
+    // A multi-MB generator expansion: one record of 50k candidate structures
+    let expansion: Vec<String> = (0..50_000)
+        .map(|i| format!("cand_{} Si {:.3} 0.0 0.0", i, i as f64 * 0.001))
+        .collect();
+    let plain_size = serde_json::to_vec(&expansion).unwrap().len() as u64;
+    {
+        let zlib = EventLogConfig {
+            compression: Compression::Zlib,
+            ..Default::default()
+        };
+        let mut w = EventLogWriter::open(&path, zlib).expect("writer");
+        w.append("old", json!({ "atoms": expansion[..2000] }))
+            .unwrap();
+    }
+    let before = std::fs::metadata(&path).unwrap().len();
+    {
+        let zstd = EventLogConfig {
+            compression: Compression::Zstd,
+            ..Default::default()
+        };
+        let mut w = EventLogWriter::open(&path, zstd).expect("writer");
+        w.append("expansion", json!({ "candidates": expansion }))
+            .unwrap();
+        w.append("tiny", json!({"n": 2})).unwrap();
+    }
+    let on_disk = std::fs::metadata(&path).unwrap().len() - before;
+    assert!(
+        on_disk < plain_size / 4,
+        "expansion took {} of {} bytes",
+        on_disk,
+        plain_size
+    );
+
+    // Sealed records compress inside the seal
+    let key = unifiedlab::eventlog::LogKey::from_hex(&"ab".repeat(32)).unwrap();
+    {
+        let sealed = EventLogConfig {
+            compression: Compression::Zstd,
+            key: Some(key.clone()),
+            ..Default::default()
+        };
+        let mut w = EventLogWriter::open(&path, sealed).expect("writer");
+        w.append("secret", json!({ "candidates": expansion }))
+            .unwrap();
+    }
+
+    let mut r = EventLogReader::open(&path).expect("reader").with_key(key);
+    let records: Vec<_> = std::iter::from_fn(|| r.next().unwrap())
+        .map(|e| e.record)
+        .collect();
+    let kinds: Vec<&str> = records.iter().map(|r| r.kind.as_str()).collect();
+    assert_eq!(kinds, ["old", "expansion", "tiny", "secret"]);
+    assert_eq!(records[1].payload["candidates"][49_999], expansion[49_999]);
+    assert_eq!(records[3].payload["candidates"][123], expansion[123]);
+    assert_eq!(r.stats().corruptions, 0);
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_compression_is_opt_in() {
+    assert_eq!(TransportConfig::default().compression, Compression::None);
+    let cfg: TransportConfig = serde_yaml::from_str("compression: zstd\n").unwrap();
+    assert_eq!(cfg.compression, Compression::Zstd);
+    assert_eq!("ZLIB".parse::<Compression>(), Ok(Compression::Zlib));
+    assert!("lz4".parse::<Compression>().is_err());
+}
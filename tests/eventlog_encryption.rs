@@ -0,0 +1,66 @@
+use serde_json::json;
+use std::io::Write;
+use unifiedlab::eventlog::{Compression, EventLogConfig, EventLogReader, EventLogWriter, LogKey};
+
+#[test]
+fn test_sealed_payloads_read_back_and_self_heal() {
+    let path = std::env::temp_dir().join(format!("ulab_seal_{}.log", uuid::Uuid::new_v4()));
+    let key = LogKey::from_hex(&"7e".repeat(32)).unwrap();
+
+    let big: Vec<String> = (0..5000).map(|i| format!("Si {} 0.0 0.0", i)).collect();
+    {
+        let cfg = EventLogConfig {
+            compression: Compression::Zlib,
+            key: Some(key.clone()),
+            ..Default::default()
+        };
+        let mut w = EventLogWriter::open(&path, cfg).expect("writer");
+        w.append("job.submit", json!({"secret": "POTCAR-licence-1234"}))
+            .unwrap();
+        w.append("big", json!({ "atoms": big })).unwrap();
+    }
+
+    // Nothing readable on disk, and the large payload was still compressed
+    let bytes = std::fs::read(&path).unwrap();
+    let text = String::from_utf8_lossy(&bytes);
+    assert!(!text.contains("POTCAR-licence"));
+    assert!(!text.contains("Si 4999"));
+    let plain_size = serde_json::to_vec(&big).unwrap().len();
+    assert!(
+        bytes.len() < plain_size / 2,
+        "sealed record was not compressed"
+    );
+
+    // A torn write between records is skipped as before
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"ULA\x00garbage")
+        .unwrap();
+    {
+        let cfg = EventLogConfig {
+            key: Some(key.clone()),
+            ..Default::default()
+        };
+        let mut w = EventLogWriter::open(&path, cfg).expect("writer");
+        w.append("tail", json!({"n": 3})).unwrap();
+    }
+
+    let mut r = EventLogReader::open(&path).unwrap().with_key(key);
+    let seen: Vec<_> = std::iter::from_fn(|| r.next().unwrap()).collect();
+    assert_eq!(seen.len(), 3);
+    assert_eq!(seen[0].record.payload["secret"], "POTCAR-licence-1234");
+    assert_eq!(seen[1].record.payload["atoms"][4999], "Si 4999 0.0 0.0");
+    assert_eq!(seen[2].record.kind, "tail");
+
+    // Without the right key the reader stops instead of skipping events
+    let mut wrong = EventLogReader::open(&path)
+        .unwrap()
+        .with_key(LogKey::from_hex(&"01".repeat(32)).unwrap());
+    assert!(wrong.next().is_err());
+    assert!(wrong.next().is_err());
+    assert_eq!(wrong.cursor(), 0);
+
+    std::fs::remove_file(&path).ok();
+}
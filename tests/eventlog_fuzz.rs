@@ -0,0 +1,86 @@
+use unifiedlab::eventlog::testing::{self, Corruption, Rng};
+use unifiedlab::eventlog::{CorruptionKind, EventLogConfig, EventLogReader};
+
+#[test]
+fn test_intact_records_survive_random_damage() {
+    // ULAB_FUZZ_ROUNDS=100000 for a longer soak
+    let rounds = std::env::var("ULAB_FUZZ_ROUNDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(300);
+    for seed in 0..rounds {
+        testing::run_scenario(seed).unwrap();
+    }
+}
+
+#[test]
+fn test_garbage_is_counted_once() {
+    let path = std::env::temp_dir().join(format!("ulab_fuzz_{}.log", uuid::Uuid::new_v4()));
+    let written =
+        testing::write_log(&path, 3, &mut Rng::new(7), EventLogConfig::default()).unwrap();
+
+    // Junk between the first two records and a flipped bit in the last one
+    let gap = written[1].offset;
+    let last = &written[2];
+    testing::corrupt_file(
+        &path,
+        &[
+            Corruption::Garbage {
+                at: gap,
+                bytes: vec![0xAB; 10],
+            },
+            Corruption::FlipBit {
+                at: last.offset + 20,
+                bit: 3,
+            },
+        ],
+    )
+    .unwrap();
+
+    let mut r = EventLogReader::open(&path).unwrap();
+    let seqs: Vec<_> = std::iter::from_fn(|| r.next().unwrap())
+        .map(|e| e.record.payload["seq"].clone())
+        .collect();
+    assert_eq!(seqs, [0, 1]);
+
+    let events = r.take_corruptions();
+    let kinds: Vec<_> = events.iter().map(|c| c.kind).collect();
+    assert_eq!(kinds, [CorruptionKind::BadMagic, CorruptionKind::BadCrc]);
+    assert_eq!(events[0].skipped_bytes, 10);
+    // The damaged last frame is skipped up to 3 bytes short of EOF
+    let frame = last.next_offset - last.offset;
+    assert_eq!(events[1].skipped_bytes, frame - 3);
+
+    // Polling again at EOF reports nothing new
+    assert!(r.next().unwrap().is_none());
+    assert!(r.take_corruptions().is_empty());
+    assert_eq!(r.stats().skipped_bytes, 10 + frame - 3);
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_torn_record_does_not_stall_the_reader() {
+    let path = std::env::temp_dir().join(format!("ulab_fuzz_{}.log", uuid::Uuid::new_v4()));
+    let written =
+        testing::write_log(&path, 3, &mut Rng::new(1), EventLogConfig::default()).unwrap();
+
+    // LEN of the first record now points far past EOF
+    testing::corrupt_file(
+        &path,
+        &[Corruption::FlipBit {
+            at: written[0].offset + 10,
+            bit: 2,
+        }],
+    )
+    .unwrap();
+
+    let mut r = EventLogReader::open(&path).unwrap();
+    let seqs: Vec<_> = std::iter::from_fn(|| r.next().unwrap())
+        .map(|e| e.record.payload["seq"].clone())
+        .collect();
+    assert_eq!(seqs, [1, 2]);
+    assert_eq!(r.take_corruptions()[0].kind, CorruptionKind::Truncated);
+
+    std::fs::remove_file(&path).ok();
+}
@@ -0,0 +1,180 @@
+use serde_json::json;
+use unifiedlab::eventlog::{
+    index, log_end, EventIndex, EventLogConfig, EventLogReader, EventLogWriter, FsyncPolicy,
+};
+
+fn indexed(segment_bytes: Option<u64>) -> EventLogConfig {
+    EventLogConfig {
+        index: true,
+        segment_bytes,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_index_follows_the_writer_across_segments() {
+    let dir = std::env::temp_dir().join(format!("ulab_idx_{}", uuid::Uuid::new_v4()));
+    let log = dir.join("events.log");
+
+    let mut w = EventLogWriter::open(&log, indexed(Some(256))).unwrap();
+    let offsets: Vec<u64> = (0..20)
+        .map(|n| {
+            let kind = if n % 5 == 0 { "rare" } else { "common" };
+            w.append_at(1000 + n * 10, kind, json!({ "n": n })).unwrap()
+        })
+        .collect();
+    assert!(dir.join("events.0001.log").exists());
+
+    let idx = EventIndex::open(&log).unwrap().unwrap();
+    assert_eq!(idx.len(), 20);
+    assert_eq!(idx.end().unwrap(), log_end(&log).unwrap());
+    assert_eq!(idx.get(7).unwrap().unwrap().offset, offsets[7]);
+    assert_eq!(
+        idx.get(5).unwrap().unwrap().kind_hash,
+        index::kind_hash("rare")
+    );
+    assert!(idx.get(20).unwrap().is_none());
+
+    // Jumps by offset and by time land in the right segment
+    let mut r = EventLogReader::open(&log).unwrap();
+    r.seek(offsets[13]).unwrap();
+    assert_eq!(r.next().unwrap().unwrap().record.payload["n"], 13);
+    assert!(r.seek_time(1125).unwrap());
+    assert_eq!(r.next().unwrap().unwrap().record.payload["n"], 13);
+
+    // A cursor in the middle of a record resumes at the next one
+    r.seek(offsets[4] + 3).unwrap();
+    assert_eq!(r.cursor(), offsets[5]);
+    assert_eq!(r.next().unwrap().unwrap().record.payload["n"], 5);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_reader_seeks_into_records_and_segments_written_after_it_opened() {
+    let dir = std::env::temp_dir().join(format!("ulab_idx_{}", uuid::Uuid::new_v4()));
+    let log = dir.join("events.log");
+
+    let mut w = EventLogWriter::open(&log, indexed(Some(256))).unwrap();
+    w.append_at(1000, "early", json!({ "n": 0 })).unwrap();
+    let mut r = EventLogReader::open(&log).unwrap();
+
+    let offsets: Vec<u64> = (1..20)
+        .map(|n| {
+            w.append_at(1000 + n * 10, "late", json!({ "n": n }))
+                .unwrap()
+        })
+        .collect();
+    assert!(dir.join("events.0002.log").exists());
+
+    // Still snapped to whole records, and found in the new segments
+    r.seek(offsets[14] + 3).unwrap();
+    assert_eq!(r.cursor(), offsets[15]);
+    assert_eq!(r.next().unwrap().unwrap().record.payload["n"], 16);
+    assert!(r.seek_time(1050).unwrap());
+    assert_eq!(r.next().unwrap().unwrap().record.payload["n"], 5);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_writer_repairs_a_missing_or_stale_index() {
+    let dir = std::env::temp_dir().join(format!("ulab_idx_{}", uuid::Uuid::new_v4()));
+    let log = dir.join("events.log");
+
+    // A log from before the index existed
+    {
+        let mut w = EventLogWriter::open(&log, EventLogConfig::default()).unwrap();
+        for n in 0..10 {
+            w.append("old", json!({ "n": n })).unwrap();
+        }
+    }
+    assert!(EventIndex::open(&log).unwrap().is_none());
+    assert!(!EventLogReader::open(&log).unwrap().seek_time(0).unwrap());
+
+    // Opening with the index on catches up, then appends keep it current
+    {
+        let mut w = EventLogWriter::open(&log, indexed(None)).unwrap();
+        w.append("new", json!({ "n": 10 })).unwrap();
+    }
+    let idx = EventIndex::open(&log).unwrap().unwrap();
+    assert_eq!(idx.len(), 11);
+    assert_eq!(idx.end().unwrap(), log_end(&log).unwrap());
+
+    // A crash between frame and entry leaves a torn entry behind, and the
+    // log loses its last record; the writer trims and catches up again
+    let last = idx.last().unwrap().unwrap();
+    let idx_path = index::path_for(&log);
+    let len = std::fs::metadata(&idx_path).unwrap().len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&idx_path)
+        .unwrap()
+        .set_len(len - 30)
+        .unwrap();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&log)
+        .unwrap()
+        .set_len(last.offset)
+        .unwrap();
+    {
+        let mut w = EventLogWriter::open(&log, indexed(None)).unwrap();
+        w.append("new", json!({ "n": 11 })).unwrap();
+    }
+    let idx = EventIndex::open(&log).unwrap().unwrap();
+    assert_eq!(idx.len(), 11);
+    assert_eq!(idx.end().unwrap(), log_end(&log).unwrap());
+
+    let mut r = EventLogReader::open(&log).unwrap();
+    r.seek(idx.get(10).unwrap().unwrap().offset).unwrap();
+    assert_eq!(r.next().unwrap().unwrap().record.payload["n"], 11);
+
+    // An index longer than its log is not trusted
+    std::fs::write(&log, b"").unwrap();
+    assert!(EventIndex::open(&log).unwrap().is_none());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_batch_append_rolls_and_indexes_like_single_appends() {
+    let dir = std::env::temp_dir().join(format!("ulab_idx_{}", uuid::Uuid::new_v4()));
+    let log = dir.join("events.log");
+
+    let mut w = EventLogWriter::open(
+        &log,
+        EventLogConfig {
+            fsync: FsyncPolicy::Always,
+            ..indexed(Some(256))
+        },
+    )
+    .unwrap();
+    let first = w.append("single", json!({ "n": 0 })).unwrap();
+    let batch: Vec<(&str, serde_json::Value)> = (1..12)
+        .map(|n| (if n % 2 == 0 { "even" } else { "odd" }, json!({ "n": n })))
+        .collect();
+    let offsets = w.append_batch(&batch).unwrap();
+    assert_eq!(offsets.len(), 11);
+    assert!(offsets[0] > first);
+    assert!(w.append_batch(&[]).unwrap().is_empty());
+    // The batch crossed segment boundaries
+    assert!(dir.join("events.0002.log").exists());
+
+    let idx = EventIndex::open(&log).unwrap().unwrap();
+    assert_eq!(idx.len(), 12);
+    assert_eq!(idx.end().unwrap(), log_end(&log).unwrap());
+    assert_eq!(idx.get(5).unwrap().unwrap().offset, offsets[4]);
+
+    let mut r = EventLogReader::open(&log).unwrap();
+    r.seek(offsets[0]).unwrap();
+    for (n, offset) in (1..12).zip(&offsets) {
+        assert_eq!(r.cursor(), *offset);
+        let env = r.next().unwrap().unwrap();
+        assert_eq!(env.record.payload["n"], n);
+        assert_eq!(env.record.kind, batch[n as usize - 1].0);
+    }
+    assert!(r.next().unwrap().is_none());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,175 @@
+use serde_json::json;
+use std::io::Write;
+use unifiedlab::eventlog::replay::{parse_time, replay, ReplayFilter};
+use unifiedlab::eventlog::{Compression, EventLogConfig, EventLogReader, EventLogWriter, LogKey};
+
+// Runs against buffered reads, and against mapped ones with `--features mmap`
+
+#[test]
+fn test_reader_follows_a_growing_log() {
+    let dir = std::env::temp_dir().join(format!("ulab_replay_{}", uuid::Uuid::new_v4()));
+    let log = dir.join("events.log");
+
+    // Opened while the log is still empty
+    let mut w = EventLogWriter::open(&log, EventLogConfig::default()).unwrap();
+    let mut r = EventLogReader::open(&log).unwrap();
+    assert!(r.next().unwrap().is_none());
+
+    for n in 0..3 {
+        w.append("tick", json!({ "n": n })).unwrap();
+    }
+    for n in 0..3 {
+        assert_eq!(r.next().unwrap().unwrap().record.payload["n"], n);
+    }
+    assert!(r.next().unwrap().is_none());
+
+    // A frame that lands in two writes is read once it is whole
+    let mut scratch =
+        EventLogWriter::open(dir.join("scratch.log"), EventLogConfig::default()).unwrap();
+    scratch.append("tick", json!({ "n": 3 })).unwrap();
+    let frame = std::fs::read(dir.join("scratch.log")).unwrap();
+    let mut raw = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+    raw.write_all(&frame[..7]).unwrap();
+    raw.flush().unwrap();
+    assert!(r.next().unwrap().is_none());
+    raw.write_all(&frame[7..]).unwrap();
+    raw.flush().unwrap();
+    assert_eq!(r.next().unwrap().unwrap().record.payload["n"], 3);
+
+    // Garbage between records is skipped like before
+    raw.write_all(b"not a frame").unwrap();
+    raw.flush().unwrap();
+    w.append("tick", json!({ "n": 4 })).unwrap();
+    assert_eq!(r.next().unwrap().unwrap().record.payload["n"], 4);
+    assert_eq!(r.stats().records, 5);
+    assert_eq!(r.stats().skipped_bytes, 11);
+
+    // A fresh reader replays the whole file from the start
+    let mut replay = EventLogReader::open(&log).unwrap();
+    let mut seen = vec![];
+    while let Some(env) = replay.next().unwrap() {
+        seen.push(env.record.payload["n"].as_i64().unwrap());
+    }
+    assert_eq!(seen, [0, 1, 2, 3, 4]);
+    assert_eq!(replay.cursor(), std::fs::metadata(&log).unwrap().len());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_next_matching_passes_over_other_kinds() {
+    let dir = std::env::temp_dir().join(format!("ulab_replay_{}", uuid::Uuid::new_v4()));
+    let log = dir.join("events.log");
+
+    let mut plain = EventLogWriter::open(
+        &log,
+        EventLogConfig {
+            compression: Compression::Zstd,
+            compress_min_bytes: 0,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let mut sealed = EventLogWriter::open(
+        &log,
+        EventLogConfig {
+            key: Some(LogKey::from_hex(&"7e".repeat(32)).unwrap()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for n in 0..4 {
+        plain.append("wanted", json!({ "n": n })).unwrap();
+        sealed.append("secret", json!({ "n": n })).unwrap();
+        plain.append("chatter", json!({ "n": n })).unwrap();
+    }
+
+    // Sealed records of other kinds need no key to be passed over
+    let mut r = EventLogReader::open(&log).unwrap();
+    let mut seen = vec![];
+    while let Some(env) = r.next_matching(&["wanted"]).unwrap() {
+        assert_eq!(env.record.kind, "wanted");
+        seen.push(env.record.payload["n"].as_i64().unwrap());
+    }
+    assert_eq!(seen, [0, 1, 2, 3]);
+    assert_eq!((r.stats().records, r.stats().filtered), (4, 8));
+    assert_eq!(r.stats().corruptions, 0);
+    assert_eq!(r.cursor(), std::fs::metadata(&log).unwrap().len());
+
+    // Without the filter the first sealed record stops the reader
+    let mut r = EventLogReader::open(&log).unwrap();
+    assert!(r.next().is_ok());
+    assert!(r.next().is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_replay_filters_by_kind_time_and_job() {
+    let dir = std::env::temp_dir().join(format!("ulab_replay_{}", uuid::Uuid::new_v4()));
+    let log = dir.join("events.log");
+    let (a, b) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+
+    let mut w = EventLogWriter::open(
+        &log,
+        EventLogConfig {
+            index: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    w.append_at(
+        1_000,
+        "job.submit",
+        json!({ "jobs": [{ "id": a }, { "id": b }] }),
+    )
+    .unwrap();
+    w.append_at(2_000, "work.grant", json!({ "jobs": [{ "id": a }] }))
+        .unwrap();
+    w.append_at(3_000, "work.grant", json!({ "jobs": [{ "id": b }] }))
+        .unwrap();
+    w.append_at(4_000, "job.complete", json!({ "job_id": b }))
+        .unwrap();
+
+    let run = |filter: &ReplayFilter| {
+        let mut r = EventLogReader::open(&log).unwrap();
+        let mut seen = vec![];
+        replay(&mut r, filter, |line| {
+            seen.push((line.ts_ms, line.kind));
+            Ok(())
+        })
+        .unwrap();
+        seen
+    };
+
+    // Everything about b, by the short id the logs print (any case)
+    let about_b = run(&ReplayFilter {
+        job: Some(b.to_string()[..8].to_uppercase()),
+        ..Default::default()
+    });
+    assert_eq!(
+        about_b,
+        [
+            (1_000, "job.submit".to_string()),
+            (3_000, "work.grant".into()),
+            (4_000, "job.complete".into())
+        ]
+    );
+
+    let grants_after = run(&ReplayFilter {
+        kinds: vec!["work.grant".into()],
+        since_ms: Some(parse_time("1970-01-01T00:00:02.500Z").unwrap()),
+        ..Default::default()
+    });
+    assert_eq!(grants_after, [(3_000, "work.grant".to_string())]);
+
+    let window = run(&ReplayFilter {
+        since_ms: Some(2_000),
+        until_ms: Some(parse_time("3000").unwrap()),
+        ..Default::default()
+    });
+    assert_eq!(window.len(), 2);
+    assert!(parse_time("yesterday").is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
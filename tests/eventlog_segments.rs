@@ -0,0 +1,110 @@
+use serde_json::json;
+use unifiedlab::eventlog::{
+    log_end, EventLogConfig, EventLogReader, EventLogWriter, FsyncPolicy, Manifest,
+};
+
+#[test]
+fn test_segments_keep_offsets_global() {
+    let dir = std::env::temp_dir().join(format!("ulab_seg_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let log = dir.join("events.log");
+
+    // A plain log from before segmentation, with a reader already tailing it
+    {
+        let mut w = EventLogWriter::open(&log, EventLogConfig::default()).unwrap();
+        w.append("old", json!({"n": 0})).unwrap();
+    }
+    let mut tail = EventLogReader::open(&log).unwrap();
+    assert_eq!(tail.next().unwrap().unwrap().record.kind, "old");
+
+    // Tiny segments: every record after the first in a file starts a new one
+    let cfg = EventLogConfig {
+        segment_bytes: Some(64),
+        ..Default::default()
+    };
+    let mut w = EventLogWriter::open(&log, cfg).unwrap();
+    let offsets: Vec<u64> = (1..=5)
+        .map(|n| w.append("new", json!({ "n": n })).unwrap())
+        .collect();
+
+    let manifest = Manifest::load(&log).unwrap().unwrap();
+    assert_eq!(manifest.segments[0].file, "events.log");
+    assert_eq!(manifest.segments.len(), 6);
+    assert!(dir.join("events.0005.log").exists());
+
+    // The old reader follows into the new segments
+    let seen: Vec<_> = std::iter::from_fn(|| tail.next().unwrap()).collect();
+    assert_eq!(seen.len(), 5);
+    assert_eq!(seen.iter().map(|e| e.offset).collect::<Vec<_>>(), offsets);
+    assert_eq!(seen.last().unwrap().next_offset, log_end(&log).unwrap());
+
+    // A stored cursor lands in the right segment
+    let mut r = EventLogReader::open(&log).unwrap();
+    r.seek(offsets[3]).unwrap();
+    let env = r.next().unwrap().unwrap();
+    assert_eq!(env.record.payload["n"], 4);
+    assert_eq!(r.cursor(), offsets[4]);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_every_fsync_policy_keeps_every_record_across_segments() {
+    let dir = std::env::temp_dir().join(format!("ulab_seg_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let policies = [
+        FsyncPolicy::Never,
+        FsyncPolicy::EveryN(3),
+        FsyncPolicy::IntervalMs(5),
+        FsyncPolicy::Always,
+    ];
+    for (i, fsync) in policies.into_iter().enumerate() {
+        let log = dir.join(format!("events_{}.log", i));
+        {
+            let mut w = EventLogWriter::open(
+                &log,
+                EventLogConfig {
+                    fsync,
+                    segment_bytes: Some(256),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            for n in 0..10 {
+                w.append("tick", json!({ "n": n })).unwrap();
+            }
+            w.append_batch(&[("tick", json!({ "n": 10 })), ("tick", json!({ "n": 11 }))])
+                .unwrap();
+            // Readers see records as soon as they are appended, synced or not
+            let mut r = EventLogReader::open(&log).unwrap();
+            assert_eq!(std::iter::from_fn(|| r.next().unwrap()).count(), 12);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        } // Dropping the writer stops the IntervalMs thread
+
+        assert!(Manifest::load(&log).unwrap().unwrap().segments.len() > 1);
+    }
+
+    // As written in transport.yaml
+    let parsed: Vec<FsyncPolicy> =
+        serde_yaml::from_str("[never, Always, every_n=64, interval_ms=200]").unwrap();
+    assert_eq!(
+        parsed,
+        [
+            FsyncPolicy::Never,
+            FsyncPolicy::Always,
+            FsyncPolicy::EveryN(64),
+            FsyncPolicy::IntervalMs(200)
+        ]
+    );
+    assert_eq!(
+        serde_yaml::to_string(&FsyncPolicy::EveryN(8))
+            .unwrap()
+            .trim(),
+        "every_n=8"
+    );
+    assert!(serde_yaml::from_str::<FsyncPolicy>("sometimes").is_err());
+    assert!(serde_yaml::from_str::<FsyncPolicy>("every_n=-1").is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,149 @@
+use serde_json::json;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, JobStatus, Provenance, ResourceReq, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::feedback::generated_by;
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::workflow::{ExpansionGovernor, NodeType, DESCENDANTS_PARAM, GOVERNOR_PARAM};
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn proposals(n: usize) -> CalculationResult {
+    let now = chrono::Utc::now();
+    CalculationResult {
+        energy: None,
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "test".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation: Some((0..n).map(|i| json!({ "a": i })).collect()),
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+async fn complete(
+    coord: &mut MarketplaceCoordinator,
+    client: &mut MemTransport,
+    id: Uuid,
+    n: usize,
+) {
+    let rep = JobCompleteReport {
+        job_id: id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(proposals(n)),
+        error: None,
+    };
+    client
+        .send_to_coordinator(MSG_JOB_COMPLETE, serde_json::to_value(&rep).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+}
+
+#[test]
+fn test_governor_parses_from_params() {
+    let params = json!({ GOVERNOR_PARAM: { "max_children": 8, "max_generations": 3 } });
+    let governor = ExpansionGovernor::from_params(&params).unwrap();
+    assert_eq!(governor.max_children, Some(8));
+    assert_eq!(governor.max_descendants, None);
+    assert_eq!(
+        ExpansionGovernor::from_params(&json!({})).unwrap(),
+        ExpansionGovernor::default()
+    );
+    assert!(ExpansionGovernor::from_params(&json!({ GOVERNOR_PARAM: { "max_kids": 1 } })).is_err());
+}
+
+#[tokio::test]
+async fn test_lineage_fails_once_it_outgrows_its_governor() {
+    let root = std::env::temp_dir().join(format!("ulab_governor_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut client = net.worker(None);
+
+    let physics = common::config(Default::default(), json!({}));
+    let mut gen0 = Job::new(
+        Structure::new(vec![], None, "Agent_Gen0".into()),
+        common::config(
+            Default::default(),
+            json!({
+                "physics_template": physics,
+                "gen_counter": 0,
+                "gen_limit": 5,
+                GOVERNOR_PARAM: { "max_children": 2, "max_descendants": 3 },
+            }),
+        ),
+        ResourceReq::default(),
+    );
+    gen0.flow_context.insert(
+        "node_type".into(),
+        serde_json::to_value(NodeType::Generator {
+            strategy: "default".into(),
+        })
+        .unwrap(),
+    );
+    let sub = JobSubmit {
+        jobs: vec![gen0.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    client
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    // Two candidates are within both limits; the next agent carries the count
+    complete(&mut coord, &mut client, gen0.id, 2).await;
+    assert_eq!(
+        coord
+            .jobs()
+            .filter(|j| generated_by(j) == Some(gen0.id))
+            .count(),
+        2
+    );
+    let gen1 = coord
+        .jobs()
+        .find(|j| j.structure.source == "Agent_Gen1")
+        .unwrap()
+        .clone();
+    assert_eq!(gen1.config.params[DESCENDANTS_PARAM], 2);
+    assert_eq!(gen1.config.params[GOVERNOR_PARAM]["max_descendants"], 3);
+
+    // Two more would make four descendants
+    let before = coord.jobs().count();
+    complete(&mut coord, &mut client, gen1.id, 2).await;
+    let gen1 = coord.jobs().find(|j| j.id == gen1.id).unwrap();
+    assert_eq!(gen1.status, JobStatus::Failed);
+    assert_eq!(
+        gen1.error_log.as_deref(),
+        Some("Expansion Governor: 4 descendants > max_descendants 3")
+    );
+    assert_eq!(coord.jobs().count(), before);
+
+    std::fs::remove_dir_all(&root).ok();
+}
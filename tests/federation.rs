@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{CalculationResult, JobStatus, Provenance, RESULT_SCHEMA_VERSION};
+use unifiedlab::eventlog::{EventLogConfig, EventLogReader, EventLogWriter};
+use unifiedlab::federation::{FederationConfig, FederationLighthouse};
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, Routing, DEADLOCK_REASON_PREFIX, DEFAULT_WORKER_TIMEOUT,
+    EV_JOB_COMPLETE, EV_JOB_SUBMIT,
+};
+use unifiedlab::report::workers_from_store;
+use unifiedlab::transport::{FileTransport, Role, Transport};
+use unifiedlab::Job;
+
+mod common;
+
+/// Everything the Federation forwarded to one member, in order.
+fn forwarded(cluster_root: &Path, cluster: &str) -> Vec<JobSubmit> {
+    let path = cluster_root.join(format!("inbox/worker_federation_{}.log", cluster));
+    let mut r = EventLogReader::open(path).unwrap();
+    std::iter::from_fn(|| r.next().unwrap())
+        .filter(|e| e.record.kind == EV_JOB_SUBMIT)
+        .map(|e| serde_json::from_value(e.record.payload).unwrap())
+        .collect()
+}
+
+/// A Federation over members alpha and beta (gpu) under `base`, and the
+/// three roots: federation, alpha, beta.
+async fn federation(base: &Path) -> (FederationLighthouse, [PathBuf; 3]) {
+    let roots = [base.join("fed"), base.join("alpha"), base.join("beta")];
+    for d in &roots {
+        std::fs::create_dir_all(d).unwrap();
+    }
+    let [fed, alpha, beta] = &roots;
+
+    let cfg: FederationConfig = serde_yaml::from_str(&format!(
+        "clusters:\n  - {{name: alpha, root: {:?}}}\n  - {{name: beta, root: {:?}, tags: [gpu]}}\n",
+        alpha, beta
+    ))
+    .unwrap();
+
+    let transport = FileTransport::new(fed, Role::Coordinator, None)
+        .await
+        .unwrap();
+    let store = CheckpointStore::open(fed.join("checkpoint.db")).unwrap();
+    let lighthouse = FederationLighthouse::open(fed, &cfg, Box::new(transport), store)
+        .await
+        .unwrap();
+    (lighthouse, roots)
+}
+
+/// parent (alpha) -> child (beta, by GPU tag) -> grandchild (beta)
+async fn submit_chain(fed: &Path) -> [Job; 3] {
+    let parent = common::job("fed_test");
+    let mut child = common::job("fed_test");
+    child.resources.gpus = 1;
+    let mut grandchild = common::job("fed_test");
+    grandchild.resources.gpus = 1;
+
+    let mut architect = FileTransport::new(fed, Role::Worker, Some("architect"))
+        .await
+        .unwrap();
+    let sub = JobSubmit {
+        jobs: vec![parent.clone(), child.clone(), grandchild.clone()],
+        deps: vec![(parent.id, child.id), (child.id, grandchild.id)],
+        routing: Routing {
+            jobs: [(parent.id, "alpha".to_string())].into(),
+            ..Default::default()
+        },
+        txn: None,
+    };
+    architect
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    [parent, child, grandchild]
+}
+
+/// Appends `rep` to a member's events.log, as its Coordinator would.
+fn member_reports(cluster_root: &Path, rep: JobCompleteReport) {
+    let mut events =
+        EventLogWriter::open(cluster_root.join("events.log"), EventLogConfig::default()).unwrap();
+    events
+        .append(EV_JOB_COMPLETE, serde_json::to_value(rep).unwrap())
+        .unwrap();
+}
+
+fn done(job: &Job) -> JobCompleteReport {
+    let now = chrono::Utc::now();
+    JobCompleteReport {
+        job_id: job.id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(CalculationResult {
+            energy: None,
+            forces: None,
+            stress: None,
+            t_total_ms: 1.0,
+            final_structure: None,
+            provenance: Provenance {
+                execution_host: "test".into(),
+                start_time: now,
+                end_time: now,
+                binary_hash: None,
+                exit_code: 0,
+                sandbox_info: String::new(),
+                files: vec![],
+            },
+            next_generation: None,
+            usage: None,
+            steps: vec![],
+            schema_version: RESULT_SCHEMA_VERSION,
+        }),
+        error: None,
+    }
+}
+
+#[tokio::test]
+async fn test_cross_cluster_edge_waits_for_parent() {
+    let base = std::env::temp_dir().join(format!("ulab_fed_{}", uuid::Uuid::new_v4()));
+    let (mut lighthouse, [fed, alpha, beta]) = federation(&base).await;
+    let [parent, child, grandchild] = submit_chain(&fed).await;
+
+    lighthouse.tick().await.unwrap();
+
+    // Only the parent may leave; the child's parent runs elsewhere
+    let to_alpha = forwarded(&alpha, "alpha");
+    assert_eq!(to_alpha.len(), 1);
+    assert_eq!(to_alpha[0].jobs.len(), 1);
+    assert_eq!(to_alpha[0].jobs[0].id, parent.id);
+    assert!(forwarded(&beta, "beta").is_empty());
+
+    // Alpha's Coordinator reports the parent done
+    member_reports(&alpha, done(&parent));
+
+    lighthouse.tick().await.unwrap();
+
+    // Child and grandchild go to beta together, linked by an ordinary dep
+    let to_beta = forwarded(&beta, "beta");
+    assert_eq!(to_beta.len(), 1);
+    let ids: Vec<_> = to_beta[0].jobs.iter().map(|j| j.id).collect();
+    assert!(ids.contains(&child.id) && ids.contains(&grandchild.id));
+    assert_eq!(to_beta[0].deps, vec![(child.id, grandchild.id)]);
+    let fwd_child = to_beta[0].jobs.iter().find(|j| j.id == child.id).unwrap();
+    assert!(fwd_child.parent_ids.is_empty());
+
+    // Members just read from are not stale pseudo-workers
+    lighthouse.checkpoint_now().unwrap();
+    let store = CheckpointStore::open(fed.join("checkpoint.db")).unwrap();
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let members = workers_from_store(&store, DEFAULT_WORKER_TIMEOUT, now_ms).unwrap();
+    assert_eq!(members.len(), 2);
+    assert!(members
+        .iter()
+        .all(|m| m.worker_id.starts_with("cluster:") && !m.silent));
+
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[tokio::test]
+async fn test_failed_parent_fails_jobs_waiting_across_clusters() {
+    let base = std::env::temp_dir().join(format!("ulab_fed_{}", uuid::Uuid::new_v4()));
+    let (mut lighthouse, [fed, alpha, beta]) = federation(&base).await;
+    let [parent, child, grandchild] = submit_chain(&fed).await;
+    lighthouse.tick().await.unwrap();
+
+    let mut failed = done(&parent);
+    failed.status = JobStatus::Failed;
+    failed.result = None;
+    failed.error = Some("SCF did not converge".into());
+    member_reports(&alpha, failed);
+    lighthouse.tick().await.unwrap();
+
+    // Nothing goes to beta, and nothing is left Blocked
+    assert!(forwarded(&beta, "beta").is_empty());
+    lighthouse.checkpoint_now().unwrap();
+    let jobs = CheckpointStore::open(fed.join("checkpoint.db"))
+        .unwrap()
+        .restore_jobs()
+        .unwrap();
+    for id in [child.id, grandchild.id] {
+        assert_eq!(jobs[&id].status, JobStatus::Failed);
+    }
+    let reason = jobs[&grandchild.id].error_log.as_deref().unwrap();
+    assert!(reason.starts_with(DEADLOCK_REASON_PREFIX), "{}", reason);
+    assert!(
+        reason.ends_with("Failed: SCF did not converge"),
+        "{}",
+        reason
+    );
+
+    std::fs::remove_dir_all(&base).ok();
+}
@@ -0,0 +1,276 @@
+use serde_json::json;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, ElectronVolts, JobStatus, Provenance, ResourceReq, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::feedback::{generated_by, GenerationFeedback, FEEDBACK_PARAM};
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::workflow::NodeType;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn result(
+    energy: Option<f64>,
+    next_generation: Option<Vec<serde_json::Value>>,
+) -> CalculationResult {
+    let now = chrono::Utc::now();
+    CalculationResult {
+        energy: energy.map(ElectronVolts),
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "test".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+async fn report(client: &mut MemTransport, rep: JobCompleteReport) {
+    client
+        .send_to_coordinator(MSG_JOB_COMPLETE, serde_json::to_value(&rep).unwrap())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_next_agent_receives_ranked_outcome() {
+    let root = std::env::temp_dir().join(format!("ulab_feedback_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut client = net.worker(None);
+
+    // Generation 0 of a two-generation campaign
+    let physics = common::config(Default::default(), json!({}));
+    let mut gen0 = Job::new(
+        Structure::new(vec![], None, "Agent_Gen0".into()),
+        common::config(
+            Default::default(),
+            json!({ "physics_template": physics, "gen_counter": 0, "gen_limit": 1 }),
+        ),
+        ResourceReq::default(),
+    );
+    gen0.flow_context.insert(
+        "node_type".into(),
+        serde_json::to_value(NodeType::Generator {
+            strategy: "default".into(),
+        })
+        .unwrap(),
+    );
+    let sub = JobSubmit {
+        jobs: vec![gen0.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    client
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    // It proposes three candidates
+    let candidates = vec![
+        json!({ "a": 1.0 }),
+        json!({ "a": 2.0 }),
+        json!({ "a": 3.0 }),
+    ];
+    report(
+        &mut client,
+        JobCompleteReport {
+            job_id: gen0.id,
+            worker_id: "w1".into(),
+            status: JobStatus::Completed,
+            result: Some(result(None, Some(candidates))),
+            error: None,
+        },
+    )
+    .await;
+    coord.tick().await.unwrap();
+
+    let pending = coord.generation_feedback(gen0.id).unwrap();
+    assert_eq!((pending.candidates, pending.unfinished), (3, 3));
+
+    coord.checkpoint_now().unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let jobs = store.restore_jobs().unwrap();
+    let mut batch: Vec<&Job> = jobs
+        .values()
+        .filter(|j| generated_by(j) == Some(gen0.id))
+        .collect();
+    batch.sort_by(|a, b| {
+        a.config.params["candidate"]["a"]
+            .as_f64()
+            .partial_cmp(&b.config.params["candidate"]["a"].as_f64())
+            .unwrap()
+    });
+    let gen1 = jobs
+        .values()
+        .find(|j| j.structure.source == "Agent_Gen1")
+        .unwrap()
+        .id;
+
+    // a=2 lands lowest, a=1 just above it
+    for (job, energy) in batch.iter().zip([-1.0, -1.05, 5.0]) {
+        report(
+            &mut client,
+            JobCompleteReport {
+                job_id: job.id,
+                worker_id: "w1".into(),
+                status: JobStatus::Completed,
+                result: Some(result(Some(energy), None)),
+                error: None,
+            },
+        )
+        .await;
+    }
+    coord.tick().await.unwrap();
+    coord.checkpoint_now().unwrap();
+
+    let gen1 = store.get_job_details(&gen1.to_string()).unwrap();
+    assert_eq!(gen1.status, JobStatus::Pending);
+    let fb: GenerationFeedback =
+        serde_json::from_value(gen1.config.params[FEEDBACK_PARAM].clone()).unwrap();
+    assert_eq!(fb.generator_id, gen0.id);
+    assert_eq!((fb.completed, fb.failed, fb.unfinished), (3, 0, 0));
+
+    let order: Vec<f64> = fb
+        .ranked
+        .iter()
+        .map(|r| r.candidate["a"].as_f64().unwrap())
+        .collect();
+    assert_eq!(order, [2.0, 1.0, 3.0]);
+    assert_eq!(fb.energy.unwrap().min, -1.05);
+    // 0.05 eV apart at kT = 0.05 eV: e^-1 of the best; 6 eV worse: nothing
+    let w = |i: usize| fb.ranked[i].weight;
+    assert!((w(1) / w(0) - (-1.0f64).exp()).abs() < 1e-9);
+    assert!(w(2) < 1e-12);
+    assert_eq!(fb.diversity.distinct, 3);
+    assert!((fb.diversity.spread["a"] - (2.0f64 / 3.0).sqrt()).abs() < 1e-9);
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_loop_stops_once_its_batch_converges() {
+    let root = std::env::temp_dir().join(format!("ulab_feedback_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut client = net.worker(None);
+
+    // Up to ten generations, until a candidate gets below -2 eV
+    let physics = common::config(Default::default(), json!({}));
+    let mut gen0 = Job::new(
+        Structure::new(vec![], None, "Agent_Gen0".into()),
+        common::config(
+            Default::default(),
+            json!({ "physics_template": physics, "gen_limit": 9, "until": "energy.min < -2.0" }),
+        ),
+        ResourceReq::default(),
+    );
+    gen0.flow_context.insert(
+        "node_type".into(),
+        serde_json::to_value(NodeType::Generator {
+            strategy: "default".into(),
+        })
+        .unwrap(),
+    );
+    let sub = JobSubmit {
+        jobs: vec![gen0.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    client
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    // The batch a generator spawned, and the generator after it
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let expansion = |coord: &mut MarketplaceCoordinator, gen: Uuid| {
+        coord.checkpoint_now().unwrap();
+        let jobs = store.restore_jobs().unwrap();
+        let batch: Vec<Uuid> = jobs
+            .values()
+            .filter(|j| generated_by(j) == Some(gen))
+            .map(|j| j.id)
+            .collect();
+        let next = jobs
+            .values()
+            .find(|j| j.parent_ids.first().is_some_and(|p| batch.contains(p)))
+            .map(|j| j.id);
+        (batch, next)
+    };
+    let propose = |gen: Uuid| JobCompleteReport {
+        job_id: gen,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(result(
+            None,
+            Some(vec![json!({ "a": 1.0 }), json!({ "a": 2.0 })]),
+        )),
+        error: None,
+    };
+
+    report(&mut client, propose(gen0.id)).await;
+    coord.tick().await.unwrap();
+    let (batch, next) = expansion(&mut coord, gen0.id);
+    assert_eq!(batch.len(), 2);
+    let gen1 = next.unwrap();
+
+    // The best of the first batch is -2.5 eV, so generation 1 ends the loop
+    for (job, energy) in batch.iter().zip([-1.0, -2.5]) {
+        report(
+            &mut client,
+            JobCompleteReport {
+                job_id: *job,
+                worker_id: "w1".into(),
+                status: JobStatus::Completed,
+                result: Some(result(Some(energy), None)),
+                error: None,
+            },
+        )
+        .await;
+    }
+    coord.tick().await.unwrap();
+    report(&mut client, propose(gen1)).await;
+    coord.tick().await.unwrap();
+
+    let (batch, next) = expansion(&mut coord, gen1);
+    assert!(batch.is_empty());
+    assert!(next.is_none());
+    assert_eq!(
+        store.get_job_details(&gen1.to_string()).unwrap().status,
+        JobStatus::Completed
+    );
+
+    std::fs::remove_dir_all(&root).ok();
+}
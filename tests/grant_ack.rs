@@ -0,0 +1,107 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::marketplace::{
+    GrantAck, JobSubmit, MarketplaceCoordinator, Routing, WorkGrant, EV_JOB_SUBMIT, EV_WORK_GRANT,
+    MSG_GRANT_ACK, MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::{FileTransport, Role, Transport};
+
+mod common;
+
+async fn heartbeat(t: &mut FileTransport, worker_id: &str) {
+    let req = common::work_request(worker_id, 1);
+    t.send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req).unwrap())
+        .await
+        .unwrap();
+}
+
+async fn grants_for(t: &mut FileTransport, worker_id: &str) -> Vec<WorkGrant> {
+    t.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_WORK_GRANT)
+        .map(|e| serde_json::from_value::<WorkGrant>(e.record.payload).unwrap())
+        .filter(|g| g.worker_id == worker_id)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_unacked_grant_is_redelivered_to_another_worker() {
+    let root = std::env::temp_dir().join(format!("ulab_ack_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let transport = FileTransport::new(&root, Role::Coordinator, None)
+        .await
+        .unwrap();
+    let mut coord = MarketplaceCoordinator::open(Box::new(transport), store)
+        .await
+        .unwrap()
+        .with_grant_ack_timeout(Duration::from_millis(200));
+
+    let mut w1 = FileTransport::new(&root, Role::Worker, Some("w1"))
+        .await
+        .unwrap();
+    let mut w2 = FileTransport::new(&root, Role::Worker, Some("w2"))
+        .await
+        .unwrap();
+
+    let (a, b) = (common::job("ack_test"), common::job("ack_test"));
+    let sub = JobSubmit {
+        jobs: vec![a.clone(), b.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    w2.send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+
+    // 1. w1 gets the first job and acks it; a second grant goes unanswered
+    heartbeat(&mut w1, "w1").await;
+    coord.tick().await.unwrap();
+    let first = grants_for(&mut w1, "w1").await.remove(0);
+    let ack = GrantAck {
+        worker_id: "w1".into(),
+        grant_id: first.grant_id.clone(),
+    };
+    w1.send_to_coordinator(MSG_GRANT_ACK, serde_json::to_value(&ack).unwrap())
+        .await
+        .unwrap();
+    heartbeat(&mut w1, "w1").await;
+    coord.tick().await.unwrap();
+    let lost = grants_for(&mut w1, "w1").await.remove(0);
+    assert_ne!(lost.jobs[0].id, first.jobs[0].id);
+    assert_eq!(lost.ttl_ms, Some(200));
+
+    // 2. After the timeout only the unacked job is offered to w2
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    heartbeat(&mut w2, "w2").await;
+    coord.tick().await.unwrap();
+    let redelivered = grants_for(&mut w2, "w2").await;
+    assert_eq!(redelivered.len(), 1);
+    assert_eq!(redelivered[0].jobs.len(), 1);
+    assert_eq!(redelivered[0].jobs[0].id, lost.jobs[0].id);
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn test_grant_read_past_its_ttl_is_expired() {
+    let grant = WorkGrant {
+        worker_id: "w1".into(),
+        grant_id: "g_1".into(),
+        jobs: vec![common::job("ack_test")],
+        ttl_ms: Some(30_000),
+    };
+    assert!(!grant.expired(1_000, 30_999));
+    assert!(grant.expired(1_000, 31_000));
+
+    // Grants from before TTLs never expire on the worker
+    let legacy = WorkGrant {
+        ttl_ms: None,
+        ..grant
+    };
+    assert!(!legacy.expired(0, i64::MAX));
+}
@@ -0,0 +1,175 @@
+use unifiedlab::checkpoint::{CheckpointStore, DagEdge, EdgeKind, META_SCHEMA_VERSION};
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    ControlAck, ControlCommand, ControlRequest, JobSubmit, MarketplaceCoordinator, Routing,
+    EV_CONTROL_ACK, EV_GRAPH_EDIT, EV_JOB_SUBMIT, MSG_CONTROL,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use uuid::Uuid;
+
+mod common;
+
+/// Sends one command, lets the Coordinator apply it and returns its ack.
+async fn control(
+    coord: &mut MarketplaceCoordinator,
+    client: &mut MemTransport,
+    command: ControlCommand,
+) -> ControlAck {
+    let req = ControlRequest {
+        request_id: Uuid::new_v4(),
+        command,
+    };
+    client
+        .send_to_coordinator(MSG_CONTROL, serde_json::to_value(&req).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    client
+        .recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_CONTROL_ACK)
+        .map(|e| serde_json::from_value::<ControlAck>(e.record.payload).unwrap())
+        .find(|a| a.request_id == req.request_id)
+        .expect("no ack")
+}
+
+#[tokio::test]
+async fn test_edges_and_nodes_can_be_edited_live() {
+    let root = std::env::temp_dir().join(format!("ulab_graph_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut client = net.worker(None);
+    let mut observer = net.worker(Some("observer"));
+
+    // a -> b, and an unrelated c (no worker asks for work, so nothing runs)
+    let (a, b, c) = (
+        common::job("graph_edit_test"),
+        common::job("graph_edit_test"),
+        common::job("graph_edit_test"),
+    );
+    let sub = JobSubmit {
+        jobs: vec![a.clone(), b.clone(), c.clone()],
+        deps: vec![(a.id, b.id)],
+        routing: Routing::default(),
+        txn: None,
+    };
+    client
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    // The forgotten dependency b -> c blocks c
+    let ack = control(
+        &mut coord,
+        &mut client,
+        ControlCommand::AddEdge {
+            parent: b.id,
+            child: c.id,
+        },
+    )
+    .await;
+    assert!(ack.ok, "{}", ack.message);
+    assert!(ack.message.contains("Blocked"));
+
+    // c -> a would close a -> b -> c -> a
+    let ack = control(
+        &mut coord,
+        &mut client,
+        ControlCommand::AddEdge {
+            parent: c.id,
+            child: a.id,
+        },
+    )
+    .await;
+    assert!(!ack.ok);
+    assert!(ack.message.contains("cycle"));
+
+    // Dropping a -> b releases b
+    let ack = control(
+        &mut coord,
+        &mut client,
+        ControlCommand::RemoveEdge {
+            parent: a.id,
+            child: b.id,
+        },
+    )
+    .await;
+    assert!(ack.ok, "{}", ack.message);
+    let ack = control(
+        &mut coord,
+        &mut client,
+        ControlCommand::RemoveEdge {
+            parent: a.id,
+            child: b.id,
+        },
+    )
+    .await;
+    assert!(!ack.ok);
+
+    // A new job d waiting on c
+    let d = common::job("graph_edit_test");
+    let ack = control(
+        &mut coord,
+        &mut client,
+        ControlCommand::AddNode {
+            job: Box::new(d.clone()),
+            parents: vec![c.id],
+        },
+    )
+    .await;
+    assert!(ack.ok, "{}", ack.message);
+
+    // Only the applied edits are journaled
+    let edits: Vec<ControlCommand> = observer
+        .recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_GRAPH_EDIT)
+        .map(|e| serde_json::from_value(e.record.payload).unwrap())
+        .collect();
+    assert_eq!(edits.len(), 3);
+    assert!(matches!(edits[2], ControlCommand::AddNode { .. }));
+
+    coord.checkpoint_now().unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let get = |id: Uuid| store.get_job_details(&id.to_string()).unwrap();
+    assert_eq!(get(b.id).status, JobStatus::Pending);
+    assert!(get(b.id).parent_ids.is_empty());
+    assert_eq!(get(c.id).status, JobStatus::Blocked);
+    assert_eq!(get(c.id).parent_ids, vec![b.id]);
+    assert_eq!(get(d.id).status, JobStatus::Blocked);
+    assert_eq!(get(d.id).parent_ids, vec![c.id]);
+
+    // The edges table agrees, and says where each edge came from
+    let edge = |parent: Uuid, child: Uuid| DagEdge {
+        parent,
+        child,
+        kind: EdgeKind::Manual,
+    };
+    let mut want = vec![edge(b.id, c.id), edge(c.id, d.id)];
+    want.sort_by_key(|e| (e.child.to_string(), e.parent.to_string()));
+    assert_eq!(store.get_edges().unwrap(), want);
+
+    // A DB from before the table gets them back from parent_ids
+    store.set_meta(META_SCHEMA_VERSION, "4").unwrap();
+    drop(store);
+    let conn = rusqlite::Connection::open(root.join("checkpoint.db")).unwrap();
+    conn.execute("DROP TABLE edges", []).unwrap();
+    drop(conn);
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let edges = store.get_edges().unwrap();
+    assert_eq!(edges.len(), 2);
+    assert!(edges.iter().all(|e| e.kind == EdgeKind::Unknown));
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,143 @@
+use serde_json::json;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, JobStatus, Provenance, ResourceReq, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::eventlog::EventLogReader;
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_COMPLETE, EV_JOB_SUBMIT,
+    MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::{FileTransport, Role, Transport};
+use unifiedlab::workflow::NodeType;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn generator() -> Job {
+    let physics = common::config(Default::default(), json!({}));
+    let mut job = Job::new(
+        Structure::new(vec![], None, "Agent_Gen0".into()),
+        common::config(
+            Default::default(),
+            json!({ "physics_template": physics, "gen_counter": 0, "gen_limit": 1 }),
+        ),
+        ResourceReq::default(),
+    );
+    job.flow_context.insert(
+        "node_type".into(),
+        serde_json::to_value(NodeType::Generator {
+            strategy: "default".into(),
+        })
+        .unwrap(),
+    );
+    job
+}
+
+fn proposal(job_id: Uuid) -> serde_json::Value {
+    let now = chrono::Utc::now();
+    let rep = JobCompleteReport {
+        job_id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(CalculationResult {
+            energy: None,
+            forces: None,
+            stress: None,
+            t_total_ms: 1.0,
+            final_structure: None,
+            provenance: Provenance {
+                execution_host: "w1".into(),
+                start_time: now,
+                end_time: now,
+                binary_hash: None,
+                exit_code: 0,
+                sandbox_info: String::new(),
+                files: vec![],
+            },
+            next_generation: Some(vec![json!({ "a": 1.0 }), json!({ "a": 2.0 })]),
+            usage: None,
+            steps: vec![],
+            schema_version: RESULT_SCHEMA_VERSION,
+        }),
+        error: None,
+    };
+    serde_json::to_value(&rep).unwrap()
+}
+
+async fn coordinator(root: &std::path::Path) -> MarketplaceCoordinator {
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let transport = FileTransport::new(root, Role::Coordinator, None)
+        .await
+        .unwrap();
+    MarketplaceCoordinator::open(Box::new(transport), store)
+        .await
+        .unwrap()
+}
+
+fn completions(root: &std::path::Path) -> usize {
+    let mut reader = EventLogReader::open(root.join("events.log")).unwrap();
+    let mut n = 0;
+    while let Some(env) = reader.next().unwrap() {
+        n += (env.record.kind == EV_JOB_COMPLETE) as usize;
+    }
+    n
+}
+
+#[tokio::test]
+async fn test_resent_report_is_applied_once_across_restarts() {
+    let root = std::env::temp_dir().join(format!("ulab_idem_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let mut coord = coordinator(&root).await;
+    let mut worker = FileTransport::new(&root, Role::Worker, Some("w1"))
+        .await
+        .unwrap();
+
+    let gen0 = generator();
+    let sub = JobSubmit {
+        jobs: vec![gen0.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    worker
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    // The first send went through, but the worker could not tell
+    let event_id = Uuid::new_v4();
+    for _ in 0..2 {
+        worker
+            .send_to_coordinator_with_id(MSG_JOB_COMPLETE, proposal(gen0.id), event_id)
+            .await
+            .unwrap();
+    }
+    coord.tick().await.unwrap();
+    // Two candidates and the next generation
+    assert_eq!(coord.jobs().count(), 4);
+    assert_eq!(completions(&root), 1);
+
+    // The window outlives the Coordinator
+    coord.checkpoint_now().unwrap();
+    drop(coord);
+    let mut coord = coordinator(&root).await;
+    worker
+        .send_to_coordinator_with_id(MSG_JOB_COMPLETE, proposal(gen0.id), event_id)
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    assert_eq!(coord.jobs().count(), 4);
+    assert_eq!(completions(&root), 1);
+
+    // The key is kept in the inbox, and unkeyed records still read
+    let mut inbox = EventLogReader::open(root.join("inbox").join("worker_w1.log")).unwrap();
+    let ids: Vec<_> = std::iter::from_fn(|| inbox.next().unwrap())
+        .map(|env| env.record.event_id)
+        .collect();
+    assert_eq!(ids, [None, Some(event_id), Some(event_id), Some(event_id)]);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,111 @@
+use serde_json::json;
+use std::time::Duration;
+use unifiedlab::eventlog::EventLogReader;
+use unifiedlab::marketplace::{MSG_JOB_COMPLETE, WORKER_MESSAGE_KINDS};
+use unifiedlab::transport::{FileTransport, Role, Transport};
+
+async fn kinds(coord: &mut FileTransport) -> Vec<String> {
+    coord
+        .recv_worker_messages()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.record.kind)
+        .collect()
+}
+
+// Inbox discovery and rotation checks run every 2s
+async fn next_rescan() {
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+}
+
+#[tokio::test]
+async fn test_inbox_rotation_loses_nothing_and_drops_heartbeats() {
+    let root = std::env::temp_dir().join(format!("ulab_rot_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let inbox = root.join("inbox");
+
+    let mut coord = FileTransport::new(&root, Role::Coordinator, None)
+        .await
+        .unwrap();
+    // Every append crosses the threshold: the worker rolls whenever it may
+    let mut worker = FileTransport::new(&root, Role::Worker, Some("w1"))
+        .await
+        .unwrap()
+        .with_inbox_rotation(1);
+    assert!(kinds(&mut coord).await.is_empty());
+
+    // 1. Rolled while the Coordinator holds the file open; the next one must wait
+    worker
+        .send_to_coordinator("work.request", json!({}))
+        .await
+        .unwrap();
+    worker.send_to_coordinator("a", json!({})).await.unwrap();
+    assert!(inbox.join("worker_w1.log.1").exists());
+
+    next_rescan().await;
+    assert_eq!(kinds(&mut coord).await, ["work.request"]);
+    assert!(!inbox.join("worker_w1.log.1").exists());
+    assert_eq!(kinds(&mut coord).await, ["a"]);
+
+    // 2. Now the worker may roll again
+    worker.send_to_coordinator("b", json!({})).await.unwrap();
+    next_rescan().await;
+    assert_eq!(kinds(&mut coord).await, ["b"]);
+
+    let mut archive = EventLogReader::open(inbox.join("archive/worker_w1.log")).unwrap();
+    let archived: Vec<_> = std::iter::from_fn(|| archive.next().unwrap())
+        .map(|e| e.record.kind)
+        .collect();
+    assert_eq!(archived, ["a", "b"]);
+
+    // 3. A restarted Coordinator reads the undrained segment before the live file
+    worker.send_to_coordinator("c", json!({})).await.unwrap();
+    worker.send_to_coordinator("d", json!({})).await.unwrap();
+    let mut coord = FileTransport::new(&root, Role::Coordinator, None)
+        .await
+        .unwrap();
+    assert_eq!(kinds(&mut coord).await, ["c"]);
+    assert_eq!(kinds(&mut coord).await, ["d"]);
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_inbox_kind_filter_survives_rotation() {
+    let root = std::env::temp_dir().join(format!("ulab_rot_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    let mut coord = FileTransport::new(&root, Role::Coordinator, None)
+        .await
+        .unwrap()
+        .with_inbox_kinds(WORKER_MESSAGE_KINDS);
+    let mut worker = FileTransport::new(&root, Role::Worker, Some("w1"))
+        .await
+        .unwrap()
+        .with_inbox_rotation(1);
+    assert!(kinds(&mut coord).await.is_empty());
+
+    // Other kinds are passed over on both sides of a roll
+    worker
+        .send_to_coordinator("debug.trace", json!({}))
+        .await
+        .unwrap();
+    worker
+        .send_to_coordinator(MSG_JOB_COMPLETE, json!({}))
+        .await
+        .unwrap();
+    worker
+        .send_to_coordinator("debug.trace", json!({}))
+        .await
+        .unwrap();
+    assert!(root.join("inbox/worker_w1.log.1").exists());
+
+    next_rescan().await;
+    assert!(kinds(&mut coord).await.is_empty());
+    assert_eq!(kinds(&mut coord).await, [MSG_JOB_COMPLETE]);
+    next_rescan().await;
+    assert!(kinds(&mut coord).await.is_empty());
+
+    std::fs::remove_dir_all(&root).ok();
+}
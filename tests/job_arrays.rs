@@ -0,0 +1,107 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    JobArray, JobCompleteReport, MarketplaceCoordinator, SubmitAck, EV_SUBMIT_ACK,
+    FLOW_ARRAY_INDEX, MSG_JOB_ARRAY, MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::Job;
+use uuid::Uuid;
+
+mod common;
+
+fn array(end: u64, params: Vec<serde_json::Value>) -> JobArray {
+    let mut template = common::job("array_test");
+    template.config.params = serde_json::json!({ "encut": 400 });
+    JobArray {
+        id: Uuid::new_v4(),
+        template,
+        start: 0,
+        end,
+        params,
+    }
+}
+
+async fn submit(
+    coord: &mut MarketplaceCoordinator,
+    t: &mut MemTransport,
+    a: &JobArray,
+) -> SubmitAck {
+    common::send(t, MSG_JOB_ARRAY, a).await;
+    coord.tick().await.unwrap();
+    t.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_SUBMIT_ACK)
+        .map(|e| serde_json::from_value::<SubmitAck>(e.record.payload).unwrap())
+        .find(|ack| ack.txn_id == a.id)
+        .unwrap()
+}
+
+fn indices(jobs: &[Job]) -> Vec<u64> {
+    let mut idx: Vec<u64> = jobs
+        .iter()
+        .map(|j| j.flow_context[FLOW_ARRAY_INDEX].as_u64().unwrap())
+        .collect();
+    idx.sort();
+    idx
+}
+
+#[tokio::test]
+async fn test_array_members_are_made_as_work_is_asked_for() {
+    let root = std::env::temp_dir().join(format!("ulab_array_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+
+    let bad = array(1, vec![serde_json::json!({}), serde_json::json!({})]);
+    assert!(!submit(&mut coord, &mut w1, &bad).await.ok);
+
+    let a = array(1000, vec![serde_json::json!({ "kpoints": 4 })]);
+    let ack = submit(&mut coord, &mut w1, &a).await;
+    assert!(ack.ok, "{}", ack.message);
+    assert_eq!(ack.jobs, 1000);
+    assert_eq!(coord.jobs().count(), 0);
+
+    // Only what the worker has room for exists
+    let mut first = common::grants(&mut coord, &mut w1, &common::work_request("w1", 2)).await;
+    assert_eq!(indices(&first), [0, 1]);
+    first.sort_by_key(|j| j.id != a.member_id(0));
+    assert_eq!(first[0].config.params["kpoints"], 4);
+    assert_eq!(first[1].config.params, serde_json::json!({ "encut": 400 }));
+    assert_eq!(coord.jobs().count(), 2);
+    assert_eq!(coord.array_backlog(), 998);
+
+    // The array picks up where it was after a restart
+    for job in &first {
+        let rep = JobCompleteReport {
+            job_id: job.id,
+            worker_id: "w1".into(),
+            status: JobStatus::Completed,
+            result: None,
+            error: None,
+        };
+        w1.send_to_coordinator(MSG_JOB_COMPLETE, serde_json::to_value(&rep).unwrap())
+            .await
+            .unwrap();
+    }
+    coord.tick().await.unwrap();
+    coord.checkpoint_now().unwrap();
+    coord
+        .crash_restart(Box::new(net.coordinator()))
+        .await
+        .unwrap();
+    assert_eq!(coord.array_backlog(), 998);
+    let next = common::grants(&mut coord, &mut w1, &common::work_request("w1", 2)).await;
+    assert_eq!(indices(&next), [2, 3]);
+    assert_eq!(coord.jobs().count(), 4);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,209 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    ControlAck, ControlCommand, ControlRequest, JobCancel, JobCompleteReport, JobFilter, JobSubmit,
+    MarketplaceCoordinator, Routing, WorkGrant, EV_CONTROL_ACK, EV_JOB_CANCEL, EV_JOB_SUBMIT,
+    EV_WORK_GRANT, FLOW_WORKFLOW, MSG_CONTROL, MSG_JOB_COMPLETE, MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use uuid::Uuid;
+
+mod common;
+
+async fn heartbeat(t: &mut MemTransport) {
+    let req = common::work_request("w1", 1);
+    common::send(t, MSG_WORK_REQUEST, &req).await;
+}
+
+async fn cancel(t: &mut MemTransport, job_id: Uuid) {
+    let req = ControlRequest {
+        request_id: Uuid::new_v4(),
+        command: ControlCommand::Cancel { job_id },
+    };
+    common::send(t, MSG_CONTROL, &req).await;
+}
+
+async fn cancel_jobs(t: &mut MemTransport, filter: JobFilter) {
+    let req = ControlRequest {
+        request_id: Uuid::new_v4(),
+        command: ControlCommand::CancelJobs { filter },
+    };
+    common::send(t, MSG_CONTROL, &req).await;
+}
+
+async fn next_ack(t: &mut MemTransport) -> ControlAck {
+    t.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|e| e.record.kind == EV_CONTROL_ACK)
+        .map(|e| serde_json::from_value(e.record.payload).unwrap())
+        .unwrap()
+}
+
+fn status(coord: &MarketplaceCoordinator, id: Uuid) -> JobStatus {
+    coord.jobs().find(|j| j.id == id).unwrap().status.clone()
+}
+
+/// (granted job ids, cancelled job ids) seen by w1.
+async fn received(t: &mut MemTransport) -> (Vec<Uuid>, Vec<Uuid>) {
+    let (mut granted, mut cancelled) = (vec![], vec![]);
+    for env in t.recv_broadcasts().await.unwrap() {
+        match env.record.kind.as_str() {
+            EV_WORK_GRANT => {
+                let g: WorkGrant = serde_json::from_value(env.record.payload).unwrap();
+                granted.extend(g.jobs.iter().map(|j| j.id));
+            }
+            EV_JOB_CANCEL => {
+                let c: JobCancel = serde_json::from_value(env.record.payload).unwrap();
+                assert_eq!(c.worker_id, "w1");
+                cancelled.extend(c.job_ids);
+            }
+            _ => {}
+        }
+    }
+    (granted, cancelled)
+}
+
+#[tokio::test]
+async fn test_cancel_reaches_the_running_worker() {
+    let root = std::env::temp_dir().join(format!("ulab_cancel_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+    let mut console = net.worker(None);
+
+    let (a, b, c) = (
+        common::job("cancel_test"),
+        common::job("cancel_test"),
+        common::job("cancel_test"),
+    );
+    let sub = JobSubmit {
+        jobs: vec![a.clone(), b.clone(), c.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(&mut console, EV_JOB_SUBMIT, &sub).await;
+    coord.tick().await.unwrap();
+    heartbeat(&mut w1).await;
+    coord.tick().await.unwrap();
+    let (granted, _) = received(&mut w1).await;
+    assert_eq!(granted.len(), 1);
+    let running = granted[0];
+    let waiting: Vec<Uuid> = [a.id, b.id, c.id]
+        .into_iter()
+        .filter(|id| *id != running)
+        .collect();
+
+    // A queued job just leaves the queue; the running one is stopped on w1
+    cancel(&mut console, waiting[0]).await;
+    cancel(&mut console, running).await;
+    coord.tick().await.unwrap();
+    let (_, cancelled) = received(&mut w1).await;
+    assert_eq!(cancelled, [running]);
+    assert_eq!(status(&coord, running), JobStatus::Cancelled);
+    assert_eq!(status(&coord, waiting[0]), JobStatus::Cancelled);
+
+    // No report comes back from w1; its next request takes the last job
+    heartbeat(&mut w1).await;
+    coord.tick().await.unwrap();
+    assert_eq!(received(&mut w1).await.0, [waiting[1]]);
+
+    // A result that raced the cancel is dropped
+    let rep = JobCompleteReport {
+        job_id: running,
+        worker_id: "w1".into(),
+        status: JobStatus::Failed,
+        result: None,
+        error: Some("killed".into()),
+    };
+    common::send(&mut w1, MSG_JOB_COMPLETE, &rep).await;
+    coord.tick().await.unwrap();
+    assert_eq!(status(&coord, running), JobStatus::Cancelled);
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_cancel_jobs_by_workflow() {
+    let root = std::env::temp_dir().join(format!("ulab_cancel_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+    let mut console = net.worker(None);
+
+    let of = |workflow: &str| {
+        let mut j = common::job("cancel_test");
+        j.flow_context
+            .insert(FLOW_WORKFLOW.into(), workflow.to_string().into());
+        j
+    };
+    let fanout = vec![of("fanout"), of("fanout"), of("fanout")];
+    let other = of("screening");
+    let mut jobs = fanout.clone();
+    jobs.push(other.clone());
+    let sub = JobSubmit {
+        jobs,
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(&mut console, EV_JOB_SUBMIT, &sub).await;
+    coord.tick().await.unwrap();
+
+    // An empty filter matches anything; each set field narrows it
+    let filter = JobFilter {
+        workflow: Some("fanout".into()),
+        ..Default::default()
+    };
+    assert!(JobFilter::default().matches(&other));
+    assert!(filter.matches(&fanout[0]) && !filter.matches(&other));
+    let by_engine = JobFilter {
+        engine: Some(fanout[0].config.engine.name().to_uppercase()),
+        statuses: vec![JobStatus::Running],
+        ..Default::default()
+    };
+    assert!(!by_engine.matches(&fanout[0]));
+
+    heartbeat(&mut w1).await;
+    coord.tick().await.unwrap();
+    let (granted, _) = received(&mut w1).await;
+    let running = granted[0];
+
+    cancel_jobs(&mut console, filter.clone()).await;
+    coord.tick().await.unwrap();
+    for j in &fanout {
+        assert_eq!(status(&coord, j.id), JobStatus::Cancelled);
+    }
+    assert_ne!(status(&coord, other.id), JobStatus::Cancelled);
+    let ack = next_ack(&mut console).await;
+    assert!(ack.ok, "{}", ack.message);
+    let stopping = usize::from(running != other.id);
+    assert_eq!(
+        ack.message,
+        format!("3 job(s) cancelled ({} stopping on workers)", stopping)
+    );
+    if stopping == 1 {
+        assert_eq!(received(&mut w1).await.1, [running]);
+    }
+
+    // Nothing left to cancel is an error, not a silent no-op
+    cancel_jobs(&mut console, filter).await;
+    coord.tick().await.unwrap();
+    let ack = next_ack(&mut console).await;
+    assert!(!ack.ok);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,53 @@
+use chrono::{Duration, Utc};
+use unifiedlab::checkpoint::{time_in_status, CheckpointStore};
+use unifiedlab::core::JobStatus;
+use unifiedlab::Job;
+
+mod common;
+
+#[test]
+fn test_status_changes_are_recorded_once() {
+    let root = std::env::temp_dir().join(format!("ulab_history_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let mut job = common::job("history_test");
+    let t0 = Utc::now();
+    let step = |job: &mut Job, status: JobStatus, min: i64| {
+        job.status = status;
+        job.updated_at = t0 + Duration::minutes(min);
+        store.apply_batch(0, &[job], &[]).unwrap();
+    };
+
+    step(&mut job, JobStatus::Blocked, 0);
+    step(&mut job, JobStatus::Pending, 5);
+    // A re-save without a status change adds nothing
+    step(&mut job, JobStatus::Pending, 6);
+    job.node_id = Some("w1".into());
+    step(&mut job, JobStatus::Running, 8);
+    step(&mut job, JobStatus::Completed, 20);
+
+    let history = store.get_job_history(&job.id.to_string()).unwrap();
+    let steps: Vec<_> = history
+        .iter()
+        .map(|t| (t.from_status.as_deref(), t.to_status.as_str()))
+        .collect();
+    assert_eq!(
+        steps,
+        [
+            (None, "Blocked"),
+            (Some("Blocked"), "Pending"),
+            (Some("Pending"), "Running"),
+            (Some("Running"), "Completed"),
+        ]
+    );
+    assert_eq!(history[2].worker_id.as_deref(), Some("w1"));
+
+    let last = history.last().unwrap().ts_ms;
+    let spent = time_in_status(&history, last);
+    let min = |s: &str| spent.iter().find(|(k, _)| k == s).unwrap().1 / 60_000;
+    assert_eq!((min("Blocked"), min("Pending"), min("Running")), (5, 3, 12));
+    assert_eq!(min("Completed"), 0);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,44 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::{ExitStatus, Output};
+
+use unifiedlab::core::{FileRole, SecretRef};
+use unifiedlab::drivers::utils::{keep_output, KEPT_OUTPUT_BYTES, STDERR_FILE, STDOUT_FILE};
+use unifiedlab::provenance::{manifest_dir, ArtifactStore};
+use unifiedlab::secrets;
+
+#[test]
+fn test_compute_output_tail_is_kept_with_the_outputs() {
+    let dir = std::env::temp_dir().join(format!("ulab_logs_{}", uuid::Uuid::new_v4()));
+    let work = dir.join("work");
+    std::fs::create_dir_all(&work).unwrap();
+    let store = ArtifactStore::new(dir.join("store")).unwrap();
+    let mut files = manifest_dir(&work, FileRole::Input).unwrap();
+
+    let var = format!("ULAB_TEST_LOGS_{}", uuid::Uuid::new_v4().simple());
+    std::env::set_var(&var, "hunter2-license");
+    secrets::resolve(&SecretRef::Env(var)).unwrap();
+
+    // A chatty engine whose last line echoes a secret; nothing on stderr
+    let mut stdout = "iteration\n".repeat(KEPT_OUTPUT_BYTES / 4).into_bytes();
+    stdout.extend_from_slice(b"license hunter2-license rejected\n");
+    let output = Output {
+        status: ExitStatus::from_raw(1 << 8),
+        stdout,
+        stderr: vec![],
+    };
+    keep_output(&work, &output).unwrap();
+    assert!(!work.join(STDERR_FILE).exists());
+
+    store.capture_workdir(&work, &mut files).unwrap();
+    let kept = files
+        .iter()
+        .find(|f| f.path == STDOUT_FILE)
+        .expect("stdout is stored as an output");
+    assert_eq!(kept.role, FileRole::Output);
+    let text = String::from_utf8(store.read_workfile(kept).unwrap()).unwrap();
+    assert!(text.len() <= KEPT_OUTPUT_BYTES, "{}", text.len());
+    assert!(text.ends_with(&format!("license {} rejected\n", secrets::REDACTED)));
+    assert!(!text.contains("hunter2"));
+
+    std::fs::remove_dir_all(dir).ok();
+}
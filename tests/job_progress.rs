@@ -0,0 +1,160 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, ElectronVolts, JobStatus, Provenance, ResourceReq, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::drivers::utils::ProgressTail;
+use unifiedlab::drivers::{ProgressSink, PROGRESS_FILE};
+use unifiedlab::marketplace::{
+    JobProgress, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, MSG_JOB_PROGRESS,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::workflow::{LogicCondition, NodeType, EARLY_SWITCH_PARAM};
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn provenance() -> Provenance {
+    let now = chrono::Utc::now();
+    Provenance {
+        execution_host: "test".into(),
+        start_time: now,
+        end_time: now,
+        binary_hash: None,
+        exit_code: 0,
+        sandbox_info: String::new(),
+        files: vec![],
+    }
+}
+
+fn snapshot(energy: f64) -> CalculationResult {
+    CalculationResult {
+        energy: Some(ElectronVolts(energy)),
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: provenance(),
+        next_generation: None,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+fn job(params: serde_json::Value) -> Job {
+    Job::new(
+        Structure::new(vec![], None, "progress_test".into()),
+        common::config(Default::default(), params),
+        ResourceReq::default(),
+    )
+}
+
+async fn progress(w: &mut MemTransport, job_id: Uuid, worker: &str, energy: f64) {
+    let p = JobProgress {
+        job_id,
+        worker_id: worker.into(),
+        snapshot: snapshot(energy),
+    };
+    common::send(w, MSG_JOB_PROGRESS, &p).await;
+}
+
+#[test]
+fn test_progress_file_is_read_a_line_at_a_time() {
+    let dir = std::env::temp_dir().join(format!("ulab_progress_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(PROGRESS_FILE);
+
+    // A line from an earlier pipeline step is not sent again
+    std::fs::write(&path, "{\"energy\": -1.0}\n").unwrap();
+    let mut tail = ProgressTail::new(&dir, provenance());
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let seen = seen.clone();
+        ProgressSink::new(move |s: CalculationResult| seen.lock().unwrap().push(s.energy))
+    };
+    assert_eq!(tail.poll(&sink), 0);
+
+    // Half a line waits for the rest; a bad line is skipped
+    let mut f = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    write!(f, "{{\"energy\": -2.0}}\nnot json\n{{\"energy\": -3").unwrap();
+    assert_eq!(tail.poll(&sink), 1);
+    writeln!(f, ".0}}").unwrap();
+    assert_eq!(tail.poll(&sink), 1);
+    assert_eq!(
+        *seen.lock().unwrap(),
+        [Some(ElectronVolts(-2.0)), Some(ElectronVolts(-3.0))]
+    );
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_snapshots_are_stored_and_can_decide_a_switch() {
+    let root = std::env::temp_dir().join(format!("ulab_progress_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+
+    // A relaxation gated on its energy, deciding early, with one child
+    let mut switch = job(serde_json::json!({ EARLY_SWITCH_PARAM: true }));
+    switch.flow_context.insert(
+        "node_type".into(),
+        serde_json::to_value(NodeType::Switch {
+            condition: LogicCondition::EnergyBelow(-10.0),
+        })
+        .unwrap(),
+    );
+    let mut child = job(serde_json::json!({}));
+    child.parent_ids.push(switch.id);
+    let sub = JobSubmit {
+        jobs: vec![switch.clone(), child.clone()],
+        deps: vec![(switch.id, child.id)],
+        routing: Routing::default(),
+        txn: None,
+    };
+    w1.send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    let granted = common::request(&mut coord, &mut w1, "w1", 8).await;
+    assert_eq!(granted, [switch.id]);
+
+    // Only the worker running it is listened to
+    progress(&mut w1, switch.id, "w1", -12.0).await;
+    progress(&mut w1, switch.id, "w9", 5.0).await;
+    coord.tick().await.unwrap();
+    let status = |coord: &MarketplaceCoordinator, id: Uuid| {
+        coord.jobs().find(|j| j.id == id).unwrap().status.clone()
+    };
+    assert_eq!(status(&coord, child.id), JobStatus::Blocked);
+
+    // A snapshot above the threshold prunes the branch while it still runs
+    progress(&mut w1, switch.id, "w1", -8.0).await;
+    coord.tick().await.unwrap();
+    assert_eq!(status(&coord, child.id), JobStatus::Failed);
+    assert_eq!(status(&coord, switch.id), JobStatus::Running);
+
+    coord.checkpoint_now().unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let stored = store.get_job_progress(&switch.id.to_string()).unwrap();
+    let energies: Vec<_> = stored.iter().map(|p| p.snapshot.energy).collect();
+    assert_eq!(
+        energies,
+        [Some(ElectronVolts(-12.0)), Some(ElectronVolts(-8.0))]
+    );
+    assert!(stored.iter().all(|p| p.worker_id == "w1"));
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,98 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::{CheckpointStore, RetentionPolicy};
+use unifiedlab::core::{CalculationResult, JobStatus, Provenance, WorkFile, RESULT_SCHEMA_VERSION};
+use unifiedlab::provenance::ArtifactStore;
+use unifiedlab::wire::STRUCTURE_BLOB_EXT;
+use unifiedlab::Job;
+
+mod common;
+
+fn finished(status: JobStatus, files: Vec<WorkFile>) -> Job {
+    let mut job = common::job("purge_test");
+    let now = chrono::Utc::now();
+    job.status = status;
+    job.result = Some(CalculationResult {
+        energy: None,
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "w1".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files,
+        },
+        next_generation: None,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    });
+    job
+}
+
+/// Stores the files of `dir` and returns their manifest.
+fn capture(artifacts: &ArtifactStore, dir: &std::path::Path) -> Vec<WorkFile> {
+    let mut files = vec![];
+    artifacts.capture_workdir(dir, &mut files).unwrap();
+    assert!(!files.is_empty());
+    files
+}
+
+fn stored(store: &CheckpointStore, job: &Job) -> bool {
+    !store
+        .get_jobs_summary_for(&[job.id.to_string()])
+        .unwrap()
+        .is_empty()
+}
+
+#[test]
+fn test_purge_deletes_old_jobs_and_only_the_objects_they_freed() {
+    let root = std::env::temp_dir().join(format!("ulab_purge_{}", uuid::Uuid::new_v4()));
+    let (work_a, work_b) = (root.join("work_a"), root.join("work_b"));
+    std::fs::create_dir_all(&work_a).unwrap();
+    std::fs::create_dir_all(&work_b).unwrap();
+    let artifacts = ArtifactStore::new(root.join("store")).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    std::fs::write(work_a.join("OUTCAR"), "TOTEN = -10.8\n").unwrap();
+    std::fs::write(work_b.join("OUTCAR"), "TOTEN = -11.2\n").unwrap();
+    let done = finished(JobStatus::Completed, capture(&artifacts, &work_a));
+    let failed = finished(JobStatus::Failed, capture(&artifacts, &work_b));
+    store.apply_batch(0, &[&done, &failed], &[]).unwrap();
+    let (structure, _) = artifacts.put_bytes(b"packed", STRUCTURE_BLOB_EXT).unwrap();
+    std::thread::sleep(Duration::from_millis(5));
+
+    let policy = RetentionPolicy::new(Duration::ZERO);
+    let plan = policy.plan(&store, Some(&artifacts)).unwrap();
+    assert_eq!(plan.jobs.len(), 2);
+    // The completed result stays in `memo`, and with it its files
+    let freed = &failed.result.as_ref().unwrap().provenance.files[0].sha256;
+    let listed: Vec<&str> = plan.blobs.iter().map(|b| b.hash.as_str()).collect();
+    assert_eq!(listed, [freed.as_str()]);
+
+    // Planning deletes nothing
+    assert_eq!(artifacts.objects().unwrap().len(), 3);
+    assert!(stored(&store, &failed));
+
+    let (jobs, blobs) = policy.purge(&store, Some(&artifacts)).unwrap();
+    assert_eq!(jobs, 2);
+    assert_eq!(blobs.len(), 1);
+    assert!(!stored(&store, &done) && !stored(&store, &failed));
+    let mut left: Vec<String> = artifacts
+        .objects()
+        .unwrap()
+        .into_iter()
+        .map(|o| o.hash)
+        .collect();
+    left.sort();
+    let kept = &done.result.as_ref().unwrap().provenance.files[0].sha256;
+    let mut expected = vec![kept.clone(), structure];
+    expected.sort();
+    assert_eq!(left, expected);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    ControlAck, ControlCommand, ControlRequest, JobCompleteReport, JobFilter, JobSubmit,
+    MarketplaceCoordinator, Routing, WorkGrant, EV_CONTROL_ACK, EV_JOB_SUBMIT, EV_WORK_GRANT,
+    MSG_CONTROL, MSG_JOB_COMPLETE, MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::Job;
+use uuid::Uuid;
+
+mod common;
+
+async fn heartbeat(t: &mut MemTransport) {
+    let req = common::work_request("w1", 1);
+    common::send(t, MSG_WORK_REQUEST, &req).await;
+}
+
+/// Returns the request id its ack will carry.
+async fn retry_jobs(t: &mut MemTransport, filter: JobFilter) -> Uuid {
+    let req = ControlRequest {
+        request_id: Uuid::new_v4(),
+        command: ControlCommand::RetryJobs { filter },
+    };
+    common::send(t, MSG_CONTROL, &req).await;
+    req.request_id
+}
+
+async fn ack_of(t: &mut MemTransport, request_id: Uuid) -> ControlAck {
+    t.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_CONTROL_ACK)
+        .map(|e| serde_json::from_value::<ControlAck>(e.record.payload).unwrap())
+        .find(|a| a.request_id == request_id)
+        .unwrap()
+}
+
+async fn granted(t: &mut MemTransport) -> Vec<Uuid> {
+    let mut out = vec![];
+    for env in t.recv_broadcasts().await.unwrap() {
+        if env.record.kind == EV_WORK_GRANT {
+            let g: WorkGrant = serde_json::from_value(env.record.payload).unwrap();
+            out.extend(g.jobs.iter().map(|j| j.id));
+        }
+    }
+    out
+}
+
+fn job_of(coord: &MarketplaceCoordinator, id: Uuid) -> Job {
+    coord.jobs().find(|j| j.id == id).unwrap().clone()
+}
+
+#[tokio::test]
+async fn test_retry_requeues_failed_jobs_and_revives_their_children() {
+    let root = std::env::temp_dir().join(format!("ulab_retry_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap()
+        .with_deadlock_scan(Duration::ZERO);
+    let mut w1 = net.worker(Some("w1"));
+    let mut console = net.worker(None);
+
+    let (parent, child) = (common::job("retry_test"), common::job("retry_test"));
+    let sub = JobSubmit {
+        jobs: vec![parent.clone(), child.clone()],
+        deps: vec![(parent.id, child.id)],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(&mut console, EV_JOB_SUBMIT, &sub).await;
+    coord.tick().await.unwrap();
+    heartbeat(&mut w1).await;
+    coord.tick().await.unwrap();
+    assert_eq!(granted(&mut w1).await, [parent.id]);
+
+    let rep = JobCompleteReport {
+        job_id: parent.id,
+        worker_id: "w1".into(),
+        status: JobStatus::Failed,
+        result: None,
+        error: Some("node07: Bus error".into()),
+    };
+    common::send(&mut w1, MSG_JOB_COMPLETE, &rep).await;
+    coord.tick().await.unwrap();
+    coord.tick().await.unwrap();
+    assert_eq!(job_of(&coord, parent.id).status, JobStatus::Failed);
+    assert_eq!(job_of(&coord, child.id).status, JobStatus::Cancelled);
+    coord.checkpoint_now().unwrap();
+
+    // A filter nothing matches is an error, not a silent no-op
+    let mut other_engine = JobFilter {
+        statuses: vec![JobStatus::Failed],
+        ..Default::default()
+    };
+    other_engine.set("engine=vasp").unwrap();
+    assert!(other_engine.set("host=node07").is_err());
+    let sent = retry_jobs(&mut console, other_engine).await;
+    coord.tick().await.unwrap();
+    assert!(!ack_of(&mut console, sent).await.ok);
+
+    let sent = retry_jobs(
+        &mut console,
+        JobFilter {
+            statuses: vec![JobStatus::Failed],
+            ..Default::default()
+        },
+    )
+    .await;
+    coord.tick().await.unwrap();
+    let ack = ack_of(&mut console, sent).await;
+    assert!(ack.ok, "{}", ack.message);
+    assert_eq!(
+        ack.message,
+        "1 job(s) queued again (1 dependent job(s) revived)"
+    );
+    let again = job_of(&coord, parent.id);
+    assert_eq!(again.status, JobStatus::Pending);
+    assert_eq!(again.error_log, None);
+    let child_now = job_of(&coord, child.id);
+    assert_eq!(child_now.status, JobStatus::Blocked);
+    assert_eq!(child_now.error_log, None);
+    coord.checkpoint_now().unwrap();
+
+    // It runs again, and its history keeps the failed run
+    heartbeat(&mut w1).await;
+    coord.tick().await.unwrap();
+    assert_eq!(granted(&mut w1).await, [parent.id]);
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let history: Vec<String> = store
+        .get_job_history(&parent.id.to_string())
+        .unwrap()
+        .into_iter()
+        .map(|t| t.to_status)
+        .collect();
+    assert!(history.windows(2).any(|w| w == ["Failed", "Pending"]));
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,68 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{JobStatus, ResourceReq};
+use unifiedlab::{Job, Structure};
+
+mod common;
+
+fn job(params: serde_json::Value) -> Job {
+    Job::new(
+        Structure::new(vec![], None, "search_test".into()),
+        common::config(Default::default(), params),
+        ResourceReq::default(),
+    )
+}
+
+#[test]
+fn test_search_finds_jobs_by_error_log_and_params() {
+    let root = std::env::temp_dir().join(format!("ulab_search_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let db = root.join("checkpoint.db");
+    let store = CheckpointStore::open(&db).unwrap();
+
+    let mut oom = job(serde_json::json!({ "model": "mace-mp-0", "device": "cuda" }));
+    let mut timeout = job(serde_json::json!({ "model": "mace-mp-0", "device": "cpu" }));
+    let other = job(serde_json::json!({ "model": "chgnet" }));
+    store
+        .apply_batch(0, &[&oom, &timeout, &other], &[])
+        .unwrap();
+
+    // The error log is indexed when the job fails
+    assert!(store.search_jobs("memory").unwrap().is_empty());
+    oom.status = JobStatus::Failed;
+    oom.error_log = Some("RuntimeError: CUDA out of memory. Tried to allocate 2.00 GiB".into());
+    timeout.status = JobStatus::Failed;
+    timeout.error_log = Some("Walltime exceeded".into());
+    store.apply_batch(0, &[&oom, &timeout], &[]).unwrap();
+
+    let hits = store.search_jobs("cuda out of memory").unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].job.id, oom.id.to_string());
+    assert_eq!(hits[0].job.status, "Failed");
+    assert!(hits[0].snippet.contains("[memory]"));
+
+    // Params, prefixes, and punctuation that would be FTS syntax
+    assert_eq!(store.search_jobs("mace-mp-0").unwrap().len(), 2);
+    assert_eq!(store.search_jobs("wall*").unwrap().len(), 1);
+    assert_eq!(store.search_jobs("chgnet").unwrap().len(), 1);
+    assert!(store.search_jobs("   ").is_err());
+
+    // A DB from before the index gets it on open
+    drop(store);
+    let conn = rusqlite::Connection::open(&db).unwrap();
+    conn.execute_batch(
+        "DROP TABLE jobs_fts; UPDATE meta SET value = '2' WHERE key = 'schema_version';",
+    )
+    .unwrap();
+    drop(conn);
+    let store = CheckpointStore::open(&db).unwrap();
+    assert_eq!(store.search_jobs("walltime").unwrap().len(), 1);
+
+    // Pruned jobs leave the index too
+    store
+        .prune(std::time::Duration::ZERO, &[JobStatus::Failed])
+        .unwrap();
+    assert!(store.search_jobs("walltime").unwrap().is_empty());
+    assert_eq!(store.search_jobs("mace-mp-0").unwrap().len(), 0);
+
+    std::fs::remove_dir_all(&root).ok();
+}
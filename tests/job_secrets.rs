@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+
+use tokio::process::Command;
+use unifiedlab::core::{Environment, JobConfig, SecretRef};
+use unifiedlab::drivers::utils::in_environment;
+use unifiedlab::marketplace::job_fingerprint;
+use unifiedlab::resources::Sandbox;
+use unifiedlab::secrets;
+use unifiedlab::workflow::yaml::YamlLoader;
+
+/// A fresh env var set to a fresh value, so tests never share one.
+fn host_secret() -> (String, String) {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let var = format!("ULAB_TEST_TOKEN_{}", id);
+    let value = format!("tok-{}", id);
+    std::env::set_var(&var, &value);
+    (var, value)
+}
+
+fn argv(cmd: &Command) -> Vec<String> {
+    let cmd = cmd.as_std();
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn test_secrets_resolve_on_the_host_and_are_redacted() {
+    let (var, value) = host_secret();
+    let line = format!("POST /api?token={} failed", value);
+    // Not resolved in this process yet: nothing to mask
+    assert_eq!(secrets::redact(&line), line);
+
+    assert_eq!(secrets::resolve(&SecretRef::Env(var)).unwrap(), value);
+    assert_eq!(
+        secrets::redact(&line),
+        format!("POST /api?token={} failed", secrets::REDACTED)
+    );
+
+    let err = secrets::resolve(&SecretRef::Env("ULAB_TEST_NOT_SET".into()))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("ULAB_TEST_NOT_SET is not set"), "{}", err);
+}
+
+#[test]
+fn test_secrets_reach_the_program_but_not_its_argv() {
+    let (var, value) = host_secret();
+    let refs = BTreeMap::from([("API_TOKEN".to_string(), SecretRef::Env(var))]);
+    let mut cmd = Command::new("agent.py");
+    secrets::apply(&mut cmd, &refs).unwrap();
+
+    let docker = Environment::Docker {
+        image: "lab/agents:1".into(),
+    };
+    let sandbox = Sandbox {
+        cores: vec![0],
+        gpus: vec![],
+        memory_mb_limit: None,
+    };
+    let cmd = in_environment(cmd, Some(&docker), &sandbox);
+    let args = argv(&cmd).join(" ");
+    assert!(args.contains("-e API_TOKEN"), "{}", args);
+    assert!(!args.contains(&value), "{}", args);
+    let env = cmd
+        .as_std()
+        .get_envs()
+        .find(|(k, _)| *k == OsStr::new("API_TOKEN"))
+        .and_then(|(_, v)| v);
+    assert_eq!(env, Some(OsStr::new(&value)));
+
+    // A missing secret fails the job, naming the var
+    let missing = BTreeMap::from([(
+        "LICENSE".to_string(),
+        SecretRef::Env("ULAB_TEST_NOT_SET".into()),
+    )]);
+    let err = secrets::apply(&mut Command::new("vasp_std"), &missing)
+        .unwrap_err()
+        .to_string();
+    assert!(err.starts_with("secret LICENSE:"), "{}", err);
+}
+
+#[test]
+fn test_yaml_secrets_become_references() {
+    let dir = std::env::temp_dir().join(format!("ulab_secrets_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("campaign.yaml");
+    std::fs::write(
+        &path,
+        r#"
+version: 2
+metadata: { name: campaign }
+secrets:
+  VASP_LICENSE: { env: LAB_VASP_LICENSE }
+  API_TOKEN: { env: LAB_API_TOKEN }
+nodes:
+  - id: propose
+    type: compute
+    engine: { kind: agent, script: propose.py }
+    secrets:
+      API_TOKEN: { keyring: materials-project }
+  - id: relax
+    type: compute
+    engine: { kind: vasp }
+edges:
+  - { from: propose, to: relax }
+"#,
+    )
+    .unwrap();
+
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    let config = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+            .job
+            .config
+            .clone()
+    };
+    let propose = config("propose");
+    assert_eq!(
+        propose.secrets,
+        BTreeMap::from([
+            (
+                "API_TOKEN".to_string(),
+                SecretRef::Keyring("materials-project".into())
+            ),
+            (
+                "VASP_LICENSE".to_string(),
+                SecretRef::Env("LAB_VASP_LICENSE".into())
+            ),
+        ])
+    );
+    assert_eq!(
+        config("relax").secrets["API_TOKEN"],
+        SecretRef::Env("LAB_API_TOKEN".into())
+    );
+
+    // Only names are stored, and they do not change what a job computes
+    let wire = serde_json::to_value(&propose).unwrap();
+    assert_eq!(wire["secrets"]["API_TOKEN"]["keyring"], "materials-project");
+    let plain = JobConfig {
+        secrets: Default::default(),
+        ..propose.clone()
+    };
+    assert_eq!(job_fingerprint(&propose), job_fingerprint(&plain));
+
+    std::fs::write(
+        &path,
+        r#"
+version: 2
+metadata: { name: campaign }
+nodes:
+  - id: relax
+    type: compute
+    secrets:
+      VASP_LICENSE: { env: LAB_VASP_LICENSE, keyring: vasp }
+"#,
+    )
+    .unwrap();
+    let err = YamlLoader::load_from_file(&path, &Default::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("set exactly one of env or keyring"), "{}", err);
+
+    std::fs::remove_dir_all(dir).ok();
+}
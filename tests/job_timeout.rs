@@ -0,0 +1,98 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{JobStatus, ResourceReq};
+use unifiedlab::marketplace::{
+    JobCancel, JobSubmit, MarketplaceCoordinator, Routing, WorkGrant, EV_JOB_CANCEL, EV_JOB_SUBMIT,
+    EV_WORK_GRANT, MSG_WORK_REQUEST, TIMEOUT_REASON_PREFIX,
+};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn job(time_limit_min: usize) -> Job {
+    Job::new(
+        Structure::new(vec![], None, "timeout_test".into()),
+        common::config(Default::default(), serde_json::json!({})),
+        ResourceReq {
+            time_limit_min,
+            ..Default::default()
+        },
+    )
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_hung_job_is_failed_and_killed() {
+    let root = std::env::temp_dir().join(format!("ulab_timeout_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap()
+        .with_timeout_grace(Duration::from_secs(30))
+        .with_worker_timeout(Duration::from_secs(3600))
+        .with_grant_ack_timeout(Duration::from_secs(3600));
+    let mut w1 = net.worker(Some("w1"));
+
+    let (hung, unlimited) = (job(1), job(0));
+    let sub = JobSubmit {
+        jobs: vec![hung.clone(), unlimited.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    w1.send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    let req = common::work_request("w1", 2);
+    w1.send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    let granted: usize = w1
+        .recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_WORK_GRANT)
+        .map(|e| {
+            serde_json::from_value::<WorkGrant>(e.record.payload)
+                .unwrap()
+                .jobs
+                .len()
+        })
+        .sum();
+    assert_eq!(granted, 2);
+
+    // Within the limit plus grace nothing happens
+    tokio::time::advance(Duration::from_secs(85)).await;
+    coord.tick().await.unwrap();
+    assert!(coord.jobs().all(|j| j.status == JobStatus::Running));
+
+    tokio::time::advance(Duration::from_secs(10)).await;
+    coord.tick().await.unwrap();
+    let status = |id: Uuid| coord.jobs().find(|j| j.id == id).unwrap().clone();
+    let failed = status(hung.id);
+    assert_eq!(failed.status, JobStatus::Failed);
+    assert!(failed.error_log.unwrap().starts_with(TIMEOUT_REASON_PREFIX));
+    assert_eq!(status(unlimited.id).status, JobStatus::Running);
+
+    // The worker is told to kill it
+    let kills: Vec<JobCancel> = w1
+        .recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_JOB_CANCEL)
+        .map(|e| serde_json::from_value(e.record.payload).unwrap())
+        .collect();
+    assert_eq!(kills.len(), 1);
+    assert_eq!(kills[0].job_ids, [hung.id]);
+    assert_eq!(kills[0].reason, "Wall time exceeded");
+
+    std::fs::remove_dir_all(&root).ok();
+}
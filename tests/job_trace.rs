@@ -0,0 +1,66 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::logs::TraceContext;
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::Job;
+use uuid::Uuid;
+
+mod common;
+
+#[tokio::test]
+async fn test_trace_id_survives_submit_grant_and_complete() {
+    let root = std::env::temp_dir().join(format!("ulab_trace_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    let net = MemNetwork::new();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+    let mut console = net.worker(None);
+
+    let job = common::job("trace_test");
+    let trace_id = job.trace_id.clone();
+    assert!(!trace_id.is_empty());
+    let sub = JobSubmit {
+        jobs: vec![job.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(&mut console, EV_JOB_SUBMIT, &sub).await;
+    coord.tick().await.unwrap();
+
+    // The worker tags its logs with the id it was granted
+    let granted = common::grants(&mut coord, &mut w1, &common::work_request("w1", 1)).await;
+    assert_eq!(granted.len(), 1);
+    assert_eq!(TraceContext::for_job(&granted[0]).trace_id, trace_id);
+
+    let rep = JobCompleteReport {
+        job_id: job.id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: None,
+        error: None,
+    };
+    common::send(&mut w1, MSG_JOB_COMPLETE, &rep).await;
+    coord.tick().await.unwrap();
+    let done = coord.jobs().find(|j| j.id == job.id).unwrap();
+    assert_eq!(done.status, JobStatus::Completed);
+    assert_eq!(done.trace_id, trace_id);
+}
+
+#[test]
+fn test_job_without_trace_id_loads_with_an_empty_one() {
+    let mut v = serde_json::to_value(common::job("trace_test")).unwrap();
+    v.as_object_mut().unwrap().remove("trace_id");
+    let a: Job = serde_json::from_value(v.clone()).unwrap();
+    let b: Job = serde_json::from_value(v).unwrap();
+    // Not minted afresh on every read, which would split one job's logs
+    assert_eq!(a.trace_id, "");
+    assert_eq!(a.trace_id, b.trace_id);
+}
@@ -0,0 +1,113 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    GrantAck, JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, WorkGrant,
+    EV_JOB_SUBMIT, EV_WORK_GRANT, MSG_GRANT_ACK, MSG_JOB_COMPLETE, MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+
+mod common;
+
+// Long enough that the test only finishes quickly if wake-ups work
+const IDLE_WAIT: Duration = Duration::from_secs(10);
+
+async fn heartbeat(t: &mut MemTransport) {
+    let req = common::work_request("w1", 1);
+    common::send(t, MSG_WORK_REQUEST, &req).await;
+}
+
+#[tokio::test]
+async fn test_whole_loop_runs_in_one_process() {
+    // Only the checkpoint DB lives on disk; no inbox, outbox or events.log
+    let root = std::env::temp_dir().join(format!("ulab_mem_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let stop = Arc::new(AtomicBool::new(false));
+    let coord_stop = stop.clone();
+    let coord_task = tokio::spawn(async move {
+        while !coord_stop.load(Ordering::SeqCst) {
+            coord.tick().await.unwrap();
+            coord.wait_for_traffic(IDLE_WAIT).await;
+        }
+        coord.tick().await.unwrap(); // Drain whatever arrived with the stop
+        coord.checkpoint_now().unwrap();
+    });
+
+    // Architect submits a -> b
+    let (a, b) = (common::job("mem_test"), common::job("mem_test"));
+    let sub = JobSubmit {
+        jobs: vec![a.clone(), b.clone()],
+        deps: vec![(a.id, b.id)],
+        routing: Routing::default(),
+        txn: None,
+    };
+    let mut architect = net.worker(None);
+    common::send(&mut architect, EV_JOB_SUBMIT, &sub).await;
+
+    // Worker: ack and "run" whatever it is granted
+    let started = Instant::now();
+    let mut worker = net.worker(Some("w1"));
+    let mut done = Vec::new();
+    let mut submits_seen = 0;
+    while done.len() < 2 {
+        assert!(started.elapsed() < IDLE_WAIT, "loop stalled");
+        heartbeat(&mut worker).await;
+        worker.wait_for_traffic(IDLE_WAIT).await;
+
+        for env in worker.recv_broadcasts().await.unwrap() {
+            if env.record.kind == EV_JOB_SUBMIT {
+                submits_seen += 1;
+            }
+            if env.record.kind != EV_WORK_GRANT {
+                continue;
+            }
+            let grant: WorkGrant = serde_json::from_value(env.record.payload).unwrap();
+            let ack = GrantAck {
+                worker_id: "w1".into(),
+                grant_id: grant.grant_id.clone(),
+            };
+            common::send(&mut worker, MSG_GRANT_ACK, &ack).await;
+            for j in grant.jobs {
+                let rep = JobCompleteReport {
+                    job_id: j.id,
+                    worker_id: "w1".into(),
+                    status: JobStatus::Completed,
+                    result: None,
+                    error: None,
+                };
+                common::send(&mut worker, MSG_JOB_COMPLETE, &rep).await;
+                done.push(j.id);
+            }
+        }
+    }
+    assert_eq!(done, [a.id, b.id]);
+    assert_eq!(submits_seen, 1);
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    // Wake the Coordinator once more so it sees the stop flag
+    stop.store(true, Ordering::SeqCst);
+    heartbeat(&mut worker).await;
+    coord_task.await.unwrap();
+
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    for id in [a.id, b.id] {
+        let job = store.get_job_details(&id.to_string()).unwrap();
+        assert_eq!(job.status, JobStatus::Completed);
+    }
+    let files: Vec<_> = std::fs::read_dir(&root)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(files, ["checkpoint.db"]);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,163 @@
+use serde_json::json;
+use unifiedlab::checkpoint::{CheckpointStore, MemoCache};
+use unifiedlab::core::{
+    CalculationResult, ElectronVolts, JobStatus, Provenance, ResourceReq, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::feedback::generated_by;
+use unifiedlab::marketplace::{
+    job_fingerprint, JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT,
+    MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::workflow::NodeType;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn result(
+    energy: Option<f64>,
+    next_generation: Option<Vec<serde_json::Value>>,
+) -> CalculationResult {
+    let now = chrono::Utc::now();
+    CalculationResult {
+        energy: energy.map(ElectronVolts),
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "test".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+/// A one-generation agent proposing a single candidate.
+fn generator() -> Job {
+    let physics = common::config(Default::default(), json!({ "encut": 400 }));
+    let mut job = Job::new(
+        Structure::new(vec![], None, "Agent_Gen0".into()),
+        common::config(
+            Default::default(),
+            json!({ "physics_template": physics, "gen_counter": 0, "gen_limit": 0 }),
+        ),
+        ResourceReq::default(),
+    );
+    job.flow_context.insert(
+        "node_type".into(),
+        serde_json::to_value(NodeType::Generator {
+            strategy: "default".into(),
+        })
+        .unwrap(),
+    );
+    job
+}
+
+async fn complete(
+    coord: &mut MarketplaceCoordinator,
+    client: &mut MemTransport,
+    job_id: Uuid,
+    result: CalculationResult,
+) {
+    let rep = JobCompleteReport {
+        job_id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(result),
+        error: None,
+    };
+    client
+        .send_to_coordinator(MSG_JOB_COMPLETE, serde_json::to_value(&rep).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+}
+
+/// Submits a generator and has it propose `{ "a": 1 }`; the child it spawned.
+async fn expand(coord: &mut MarketplaceCoordinator, client: &mut MemTransport) -> Job {
+    let agent = generator();
+    let sub = JobSubmit {
+        jobs: vec![agent.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    client
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    complete(
+        coord,
+        client,
+        agent.id,
+        result(None, Some(vec![json!({ "a": 1 })])),
+    )
+    .await;
+    coord
+        .jobs()
+        .find(|j| generated_by(j) == Some(agent.id))
+        .unwrap()
+        .clone()
+}
+
+/// A coordinator on a fresh root under `dir`, sharing `cache`.
+async fn campaign(
+    net: &MemNetwork,
+    dir: &std::path::Path,
+    name: &str,
+    cache: &std::path::Path,
+) -> MarketplaceCoordinator {
+    let root = dir.join(name);
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap()
+        .with_memo_cache(MemoCache::open(cache).unwrap())
+}
+
+#[tokio::test]
+async fn test_fresh_campaign_reuses_results_through_the_shared_cache() {
+    let dir = std::env::temp_dir().join(format!("ulab_memo_cache_{}", Uuid::new_v4()));
+    let cache = dir.join("shared").join("memo.db");
+
+    // The first campaign computes the candidate and shares it at its checkpoint
+    let net = MemNetwork::new();
+    let mut client = net.worker(None);
+    let mut coord = campaign(&net, &dir, "first", &cache).await;
+    let first = expand(&mut coord, &mut client).await;
+    assert_eq!(first.status, JobStatus::Pending);
+    complete(&mut coord, &mut client, first.id, result(Some(-3.5), None)).await;
+    coord.checkpoint_now().unwrap();
+
+    let shared = MemoCache::open(&cache).unwrap();
+    assert_eq!(shared.entries().unwrap(), 1);
+    let (source, _) = shared
+        .get(&job_fingerprint(&first.config))
+        .unwrap()
+        .unwrap();
+    assert_eq!(source, first.id);
+
+    // A second campaign in its own root never runs it
+    let net = MemNetwork::new();
+    let mut client = net.worker(None);
+    let mut coord = campaign(&net, &dir, "second", &cache).await;
+    let again = expand(&mut coord, &mut client).await;
+    assert_eq!(again.status, JobStatus::Completed);
+    assert_eq!(again.flow_context["memoized_from"], json!(first.id));
+    assert_eq!(again.result.unwrap().energy, Some(ElectronVolts(-3.5)));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,160 @@
+use serde_json::json;
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, ElectronVolts, JobStatus, Provenance, ResourceReq, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::feedback::generated_by;
+use unifiedlab::marketplace::{
+    job_fingerprint, JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT,
+    MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::workflow::NodeType;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn result(
+    energy: Option<f64>,
+    next_generation: Option<Vec<serde_json::Value>>,
+) -> CalculationResult {
+    let now = chrono::Utc::now();
+    CalculationResult {
+        energy: energy.map(ElectronVolts),
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "test".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+/// A one-generation agent proposing a single candidate.
+fn generator() -> Job {
+    let physics = common::config(Default::default(), json!({ "encut": 400 }));
+    let mut job = Job::new(
+        Structure::new(vec![], None, "Agent_Gen0".into()),
+        common::config(
+            Default::default(),
+            json!({ "physics_template": physics, "gen_counter": 0, "gen_limit": 0 }),
+        ),
+        ResourceReq::default(),
+    );
+    job.flow_context.insert(
+        "node_type".into(),
+        serde_json::to_value(NodeType::Generator {
+            strategy: "default".into(),
+        })
+        .unwrap(),
+    );
+    job
+}
+
+async fn complete(
+    coord: &mut MarketplaceCoordinator,
+    client: &mut MemTransport,
+    job_id: Uuid,
+    result: CalculationResult,
+) {
+    let rep = JobCompleteReport {
+        job_id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(result),
+        error: None,
+    };
+    client
+        .send_to_coordinator(MSG_JOB_COMPLETE, serde_json::to_value(&rep).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+}
+
+/// Submits a generator and has it propose `{ "a": 1 }`; the child it spawned.
+async fn expand(coord: &mut MarketplaceCoordinator, client: &mut MemTransport) -> Job {
+    let agent = generator();
+    let sub = JobSubmit {
+        jobs: vec![agent.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    client
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    complete(
+        coord,
+        client,
+        agent.id,
+        result(None, Some(vec![json!({ "a": 1 })])),
+    )
+    .await;
+    coord
+        .jobs()
+        .find(|j| generated_by(j) == Some(agent.id))
+        .unwrap()
+        .clone()
+}
+
+#[tokio::test]
+async fn test_memo_hits_survive_pruning_and_restart() {
+    let root = std::env::temp_dir().join(format!("ulab_memo_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let db = root.join("checkpoint.db");
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(
+        Box::new(net.coordinator()),
+        CheckpointStore::open(&db).unwrap(),
+    )
+    .await
+    .unwrap();
+    let mut client = net.worker(None);
+
+    let first = expand(&mut coord, &mut client).await;
+    assert_eq!(first.status, JobStatus::Pending);
+    complete(&mut coord, &mut client, first.id, result(Some(-3.5), None)).await;
+    coord.checkpoint_now().unwrap();
+
+    // Everything finished is pruned; the result stays behind its fingerprint
+    std::thread::sleep(Duration::from_millis(5));
+    let store = CheckpointStore::open(&db).unwrap();
+    store
+        .prune(Duration::ZERO, &[JobStatus::Completed])
+        .unwrap();
+    assert!(store.get_jobs_summary().unwrap().is_empty());
+    let fp = job_fingerprint(&first.config);
+    assert_eq!(store.get_memo_index().unwrap()[&fp], first.id);
+
+    coord
+        .crash_restart(Box::new(net.coordinator()))
+        .await
+        .unwrap();
+    let again = expand(&mut coord, &mut client).await;
+    assert_eq!(again.status, JobStatus::Completed);
+    assert_eq!(again.flow_context["memoized_from"], json!(first.id));
+    assert_eq!(again.result.unwrap().energy, Some(ElectronVolts(-3.5)));
+
+    // The copy does not take over the row
+    coord.checkpoint_now().unwrap();
+    assert_eq!(store.get_memo_index().unwrap()[&fp], first.id);
+
+    std::fs::remove_dir_all(&root).ok();
+}
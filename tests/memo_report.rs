@@ -0,0 +1,205 @@
+use serde_json::json;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, Engine, JobStatus, Provenance, ResourceReq, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, MSG_JOB_COMPLETE,
+};
+use unifiedlab::report::{memo, memo_from_store, STATIC_CAMPAIGN};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+use unifiedlab::workflow::NodeType;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn job(engine: Engine, params: serde_json::Value, status: JobStatus) -> Job {
+    let mut j = Job::new(
+        Structure::new(vec![], None, "t".into()),
+        common::config(engine, params),
+        ResourceReq::default(),
+    );
+    j.status = status;
+    j
+}
+
+fn janus() -> Engine {
+    Engine::Janus {
+        arch: "mace_mp".into(),
+        device_preference: None,
+        model_path: None,
+    }
+}
+
+fn gulp() -> Engine {
+    Engine::Gulp {
+        binary: "gulp".into(),
+        potential_library: "reaxff".into(),
+    }
+}
+
+fn result(next_generation: Option<Vec<serde_json::Value>>) -> CalculationResult {
+    let now = chrono::Utc::now();
+    CalculationResult {
+        energy: None,
+        forces: None,
+        stress: None,
+        t_total_ms: 1.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "test".into(),
+            start_time: now,
+            end_time: now,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+#[test]
+fn test_memo_report_groups_hits_by_engine_and_campaign() {
+    let mut generator = job(
+        Engine::Agent {
+            script_path: "ga.py".into(),
+            strategy: "genetic".into(),
+        },
+        json!({}),
+        JobStatus::Completed,
+    );
+    generator.flow_context.insert(
+        "node_type".into(),
+        serde_json::to_value(NodeType::Generator {
+            strategy: "genetic".into(),
+        })
+        .unwrap(),
+    );
+    let proposed = json!({ "generated_by": generator.id, "candidate": { "x": 1 } });
+
+    // One computed, then answered twice from the cache
+    let first = job(janus(), proposed.clone(), JobStatus::Completed);
+    let mut cached = vec![];
+    for _ in 0..2 {
+        let mut j = job(janus(), proposed.clone(), JobStatus::Completed);
+        j.flow_context
+            .insert("memoized_from".into(), json!(first.id));
+        cached.push(j);
+    }
+    // The same GULP job written into the workflow three times, all run
+    let static_runs: Vec<Job> = (0..3)
+        .map(|_| job(gulp(), json!({ "T": 300 }), JobStatus::Completed))
+        .collect();
+    // Not decided yet: counts as a duplicate, not as a hit or miss
+    let pending = job(gulp(), json!({ "T": 300 }), JobStatus::Pending);
+
+    let mut jobs = vec![generator.clone(), first];
+    jobs.extend(cached);
+    jobs.extend(static_runs);
+    jobs.push(pending);
+
+    let report = memo(&jobs, None, 10);
+    assert_eq!(report.entries, 3);
+
+    let engine = |code: &str| report.engines.iter().find(|r| r.key == code).unwrap();
+    assert_eq!(
+        (
+            engine("janus:mace_mp").cached,
+            engine("janus:mace_mp").computed
+        ),
+        (2, 1)
+    );
+    assert_eq!((engine("gulp").cached, engine("gulp").computed), (0, 3));
+    assert!((engine("janus:mace_mp").hit_rate() - 2.0 / 3.0).abs() < 1e-9);
+
+    let campaign = format!("genetic:{}", &generator.id.simple().to_string()[..8]);
+    let names: Vec<&str> = report.campaigns.iter().map(|r| r.key.as_str()).collect();
+    assert_eq!(names, [campaign.as_str(), STATIC_CAMPAIGN]);
+
+    // Most shared first; the generator is not a Compute job
+    assert_eq!(report.duplicates.len(), 2);
+    assert_eq!(report.duplicates[0].code, "gulp");
+    assert_eq!(
+        (report.duplicates[0].jobs, report.duplicates[0].computed),
+        (4, 3)
+    );
+    assert_eq!(report.duplicates[1].cached, 2);
+
+    assert_eq!(memo(&jobs, None, 1).duplicates.len(), 1);
+}
+
+#[tokio::test]
+async fn test_coordinator_counts_memo_lookups() {
+    let root = std::env::temp_dir().join(format!("ulab_memo_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut client = net.worker(None);
+
+    let physics = common::config(Default::default(), json!({}));
+    let mut gen0 = Job::new(
+        Structure::new(vec![], None, "Agent_Gen0".into()),
+        common::config(
+            Default::default(),
+            json!({ "physics_template": physics, "gen_counter": 0, "gen_limit": 0 }),
+        ),
+        ResourceReq::default(),
+    );
+    gen0.flow_context.insert(
+        "node_type".into(),
+        serde_json::to_value(NodeType::Generator {
+            strategy: "default".into(),
+        })
+        .unwrap(),
+    );
+    let sub = JobSubmit {
+        jobs: vec![gen0.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    client
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    // The agent proposes the same candidate twice: both look, neither finds
+    let rep = JobCompleteReport {
+        job_id: gen0.id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: Some(result(Some(vec![json!({ "a": 1 }), json!({ "a": 1 })]))),
+        error: None,
+    };
+    client
+        .send_to_coordinator(MSG_JOB_COMPLETE, serde_json::to_value(&rep).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    let stats = coord.memo_stats();
+    assert_eq!((stats.total.lookups, stats.total.hits), (2, 0));
+    assert_eq!(stats.by_engine[&physics.engine.code()].lookups, 2);
+    assert_eq!(stats.entries, 1);
+
+    // Saved for the CLI, the console and the TUI
+    coord.checkpoint_now().unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let report = memo_from_store(&store, 10).unwrap();
+    assert_eq!(report.live.unwrap().total.lookups, 2);
+    assert_eq!(report.duplicates.len(), 1);
+    assert_eq!(report.duplicates[0].jobs, 2);
+
+    std::fs::remove_dir_all(&root).ok();
+}
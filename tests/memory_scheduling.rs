@@ -0,0 +1,77 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::ResourceReq;
+use unifiedlab::marketplace::{
+    JobSubmit, MarketplaceCoordinator, Routing, WorkRequest, EV_JOB_SUBMIT,
+};
+use unifiedlab::resources::ResourceLedger;
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn job(memory_mb: u64) -> Job {
+    Job::new(
+        Structure::new(vec![], None, "memory_test".into()),
+        common::config(Default::default(), serde_json::json!({})),
+        ResourceReq {
+            memory_mb,
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn test_ledger_holds_memory_for_running_sandboxes() {
+    let mut ledger = ResourceLedger::detect();
+    let free = ledger.free_mem_mb();
+    assert!(ledger.try_allocate(1, 0, free + 1).is_none());
+
+    let sb = ledger.try_allocate(1, 0, free / 2).unwrap();
+    assert_eq!(ledger.free_mem_mb(), free - free / 2);
+    ledger.free(&sb);
+    assert_eq!(ledger.free_mem_mb(), free);
+}
+
+#[tokio::test]
+async fn test_big_jobs_are_not_stacked_past_a_workers_memory() {
+    let root = std::env::temp_dir().join(format!("ulab_memory_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+    let mut w2 = net.worker(Some("w2"));
+
+    // Two 200 GB VASP runs and a small job
+    let (big_a, big_b, small) = (job(200_000), job(200_000), job(4_000));
+    let sub = JobSubmit {
+        jobs: vec![big_a.clone(), big_b.clone(), small.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    w1.send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+
+    // A 256 GB node takes one big job and the small one beside it
+    let req = WorkRequest {
+        available_memory_mb: Some(256_000),
+        ..common::work_request("w1", 64)
+    };
+    let granted = common::grants(&mut coord, &mut w1, &req).await;
+    assert_eq!(granted.len(), 2);
+    assert!(granted.iter().any(|j| j.id == small.id));
+
+    // A worker that does not report memory is not held to it
+    let rest = common::request(&mut coord, &mut w2, "w2", 64).await;
+    assert_eq!(rest.len(), 1);
+    assert!([big_a.id, big_b.id].contains(&rest[0]));
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,108 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::ResourceReq;
+use unifiedlab::marketplace::{
+    JobSubmit, MarketplaceCoordinator, Routing, WorkGrant, WorkPreempt, EV_JOB_SUBMIT,
+    EV_WORK_GRANT, EV_WORK_PREEMPT, MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::{FileTransport, Role, Transport};
+use unifiedlab::{Job, Structure};
+
+mod common;
+
+fn job(cores: usize, opportunistic: bool) -> Job {
+    let mut j = Job::new(
+        Structure::new(vec![], None, "opp_test".into()),
+        common::config(Default::default(), serde_json::json!({})),
+        ResourceReq {
+            cores,
+            ..Default::default()
+        },
+    );
+    j.opportunistic = opportunistic;
+    j
+}
+
+async fn submit(t: &mut FileTransport, j: &Job) {
+    let sub = JobSubmit {
+        jobs: vec![j.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    t.send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+}
+
+async fn heartbeat(t: &mut FileTransport, free_cores: usize) {
+    let req = common::work_request("w1", free_cores);
+    t.send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req).unwrap())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_normal_work_preempts_opportunistic() {
+    let root = std::env::temp_dir().join(format!("ulab_opp_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let transport = FileTransport::new(&root, Role::Coordinator, None)
+        .await
+        .unwrap();
+    let mut coord = MarketplaceCoordinator::open(Box::new(transport), store)
+        .await
+        .unwrap()
+        .with_opportunistic_idle(Duration::ZERO);
+
+    let mut worker = FileTransport::new(&root, Role::Worker, Some("w1"))
+        .await
+        .unwrap();
+    let mut architect = FileTransport::new(&root, Role::Worker, Some("architect"))
+        .await
+        .unwrap();
+
+    // 1. An idle worker picks up background work
+    let background = job(2, true);
+    submit(&mut architect, &background).await;
+    heartbeat(&mut worker, 2).await;
+    coord.tick().await.unwrap();
+
+    let events = worker.recv_broadcasts().await.unwrap();
+    let grant: WorkGrant = events
+        .into_iter()
+        .find(|e| e.record.kind == EV_WORK_GRANT)
+        .map(|e| serde_json::from_value(e.record.payload).unwrap())
+        .expect("opportunistic grant");
+    assert_eq!(grant.jobs[0].id, background.id);
+
+    // 2. Normal work arrives while all cores are taken: the background job yields
+    let urgent = job(2, false);
+    submit(&mut architect, &urgent).await;
+    heartbeat(&mut worker, 0).await;
+    coord.tick().await.unwrap();
+
+    let events = worker.recv_broadcasts().await.unwrap();
+    let preempt: WorkPreempt = events
+        .into_iter()
+        .find(|e| e.record.kind == EV_WORK_PREEMPT)
+        .map(|e| serde_json::from_value(e.record.payload).unwrap())
+        .expect("preemption");
+    assert_eq!(preempt.job_ids, vec![background.id]);
+
+    // 3. The freed cores go to the normal job, not back to the background one
+    heartbeat(&mut worker, 2).await;
+    coord.tick().await.unwrap();
+
+    let events = worker.recv_broadcasts().await.unwrap();
+    let grant: WorkGrant = events
+        .into_iter()
+        .find(|e| e.record.kind == EV_WORK_GRANT)
+        .map(|e| serde_json::from_value(e.record.payload).unwrap())
+        .expect("normal grant");
+    assert_eq!(grant.jobs.len(), 1);
+    assert_eq!(grant.jobs[0].id, urgent.id);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,116 @@
+use serde_json::json;
+use unifiedlab::core::{Engine, ResourceReq};
+use unifiedlab::drivers::pipeline::PipelineDriver;
+use unifiedlab::drivers::{DriverFactory, ProgressSink};
+use unifiedlab::logs::TraceContext;
+use unifiedlab::resources::Sandbox;
+use unifiedlab::{Job, Structure};
+
+mod common;
+
+fn agent(strategy: &str) -> Engine {
+    Engine::Agent {
+        script_path: "no_such_agent.py".into(),
+        strategy: strategy.into(),
+    }
+}
+
+#[test]
+fn test_pipeline_spec_roundtrip_and_step_params() {
+    let engine = Engine::Pipeline {
+        steps: vec![
+            Engine::Vasp {
+                binary: "vasp_std".into(),
+                mpi_ranks: 4,
+            },
+            agent("dos"),
+        ],
+    };
+    let wire = serde_json::to_value(&engine).unwrap();
+    assert_eq!(wire["engine_type"], "pipeline");
+    assert_eq!(wire["spec"]["steps"][0]["engine_type"], "vasp");
+
+    let back: Engine = serde_json::from_value(wire).unwrap();
+    assert_eq!(back.name(), "pipeline");
+    assert_eq!(back.code(), "pipeline:vasp:4p+agent:dos");
+
+    let per_step = json!({ "steps": [{ "ISIF": 3 }, { "NEDOS": 2000 }] });
+    assert_eq!(
+        PipelineDriver::step_params(&per_step, 1, 2),
+        json!({ "NEDOS": 2000 })
+    );
+    let shared = json!({ "ENCUT": 520 });
+    assert_eq!(PipelineDriver::step_params(&shared, 1, 2), shared);
+}
+
+/// A `python` that plays the adapters (writes nothing, parses an empty
+/// result) and fails every agent script with exit code 2, logging its
+/// arguments next to itself. Put first on PATH, it makes the test the same
+/// whether or not python and the adapters are installed.
+#[cfg(unix)]
+fn fake_python(dir: &std::path::Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let script = r#"#!/bin/sh
+echo "$*" >> "$(dirname "$0")/calls.log"
+case " $* " in
+  *" write agent "*) exit 0 ;;
+  *" parse agent "*)
+    echo '{"t_total_ms": 0, "provenance": {"execution_host": "fake", "start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-01T00:00:00Z", "binary_hash": null, "exit_code": 0, "sandbox_info": ""}}'
+    exit 0 ;;
+  *) exit 2 ;;
+esac
+"#;
+    let path = dir.join("python");
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let old = std::env::var_os("PATH").unwrap_or_default();
+    let mut paths = vec![dir.to_path_buf()];
+    paths.extend(std::env::split_paths(&old));
+    std::env::set_var("PATH", std::env::join_paths(paths).unwrap());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_pipeline_aborts_on_first_failing_step() {
+    let root = std::env::temp_dir().join(format!("ulab_pipe_{}", uuid::Uuid::new_v4()));
+    let (work_dir, bin) = (root.join("work"), root.join("bin"));
+    std::fs::create_dir_all(&work_dir).unwrap();
+    std::fs::create_dir_all(&bin).unwrap();
+    fake_python(&bin);
+
+    let engine = Engine::Pipeline {
+        steps: vec![agent("first"), agent("second")],
+    };
+    let job = Job::new(
+        Structure::new(vec![], None, "pipe_test".into()),
+        common::config(engine.clone(), json!({})),
+        ResourceReq::default(),
+    );
+    let sandbox = Sandbox {
+        cores: vec![0],
+        gpus: vec![],
+        memory_mb_limit: None,
+    };
+
+    let driver = DriverFactory::get(&engine).unwrap();
+    let err = driver
+        .execute(
+            &job,
+            &sandbox,
+            &work_dir,
+            &TraceContext::for_job(&job),
+            &ProgressSink::default(),
+        )
+        .await
+        .unwrap_err();
+    // The script exits nonzero but its adapters succeed: still a failure
+    assert_eq!(
+        err.to_string(),
+        "Pipeline step 1/2 (agent:first) failed: exit code 2"
+    );
+    let calls = std::fs::read_to_string(bin.join("calls.log")).unwrap();
+    assert!(calls.contains("--strategy=first"));
+    assert!(!calls.contains("--strategy=second"));
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,80 @@
+use chrono::{Duration as Span, Utc};
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::marketplace::{
+    JobSubmit, MarketplaceCoordinator, Routing, WorkGrant, EV_JOB_SUBMIT, EV_WORK_GRANT,
+    FLOW_PRIORITY, MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::Job;
+use uuid::Uuid;
+
+mod common;
+
+fn job(minutes_ago: i64, priority: Option<u32>) -> Job {
+    let mut job = common::job("priority_test");
+    job.created_at = Utc::now() - Span::minutes(minutes_ago);
+    if let Some(p) = priority {
+        job.flow_context.insert(FLOW_PRIORITY.into(), p.into());
+    }
+    job
+}
+
+/// One work request for a single core; the ids granted.
+async fn take_one(coord: &mut MarketplaceCoordinator, worker: &mut MemTransport) -> Vec<Uuid> {
+    let req = common::work_request("w1", 1);
+    worker
+        .send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    worker
+        .recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|env| env.record.kind == EV_WORK_GRANT)
+        .flat_map(|env| {
+            serde_json::from_value::<WorkGrant>(env.record.payload)
+                .unwrap()
+                .jobs
+        })
+        .map(|j| j.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_higher_priority_is_dispatched_first() {
+    let root = std::env::temp_dir().join(format!("ulab_prio_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut worker = net.worker(Some("w1"));
+
+    // Bulk physics submitted before the generator it feeds
+    let newer_bulk = job(5, None);
+    let older_bulk = job(10, Some(50));
+    let generator = job(1, Some(100));
+    let sub = JobSubmit {
+        jobs: vec![newer_bulk.clone(), older_bulk.clone(), generator.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    worker
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+
+    // Priority first, then the older submission
+    assert_eq!(take_one(&mut coord, &mut worker).await, [generator.id]);
+    assert_eq!(take_one(&mut coord, &mut worker).await, [older_bulk.id]);
+    assert_eq!(take_one(&mut coord, &mut worker).await, [newer_bulk.id]);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,116 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{JobStatus, ResourceReq};
+use unifiedlab::marketplace::{
+    JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, WorkRequest, EV_JOB_SUBMIT,
+    META_QUOTAS, MSG_JOB_COMPLETE,
+};
+use unifiedlab::quota::{QuotaConfig, QuotaStatus, FLOW_PROJECT};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn job(project: &str, gpus: usize) -> Job {
+    let mut job = Job::new(
+        Structure::new(vec![], None, "quota_test".into()),
+        common::config(Default::default(), serde_json::json!({})),
+        ResourceReq {
+            gpus,
+            ..Default::default()
+        },
+    );
+    job.flow_context.insert(FLOW_PROJECT.into(), project.into());
+    job
+}
+
+#[test]
+fn test_config_parses_projects_and_tags() {
+    let cfg: QuotaConfig = toml::from_str(
+        r#"
+        [project.screening]
+        gpus = 4
+
+        [tag.brain]
+        jobs = 2
+        "#,
+    )
+    .unwrap();
+    assert_eq!(cfg.project["screening"].gpus, Some(4));
+    assert_eq!(cfg.project["screening"].cores, None);
+    assert_eq!(cfg.tag["brain"].jobs, Some(2));
+    assert!(toml::from_str::<QuotaConfig>("[project.x]\nram = 1").is_err());
+}
+
+#[tokio::test]
+async fn test_project_is_held_to_its_gpu_quota() {
+    let root = std::env::temp_dir().join(format!("ulab_quota_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let quotas: QuotaConfig = toml::from_str("[project.screening]\ngpus = 2").unwrap();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap()
+        .with_quotas(quotas);
+    let mut w1 = net.worker(Some("w1"));
+
+    // Three one-GPU screening jobs and one from another project
+    let screening: Vec<Job> = (0..3).map(|_| job("screening", 1)).collect();
+    let other = job("campaign", 1);
+    let sub = JobSubmit {
+        jobs: screening.iter().cloned().chain([other.clone()]).collect(),
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(&mut w1, EV_JOB_SUBMIT, &sub).await;
+
+    // Plenty of room on w1
+    let room = WorkRequest {
+        available_gpus: 8,
+        ..common::work_request("w1", 16)
+    };
+    let granted: Vec<Uuid> = common::grants(&mut coord, &mut w1, &room)
+        .await
+        .iter()
+        .map(|j| j.id)
+        .collect();
+    assert_eq!(granted.len(), 3);
+    assert!(granted.contains(&other.id));
+    let held = screening
+        .iter()
+        .find(|j| !granted.contains(&j.id))
+        .unwrap()
+        .id;
+
+    // Usage goes to the checkpoint with the heartbeat
+    let status: Vec<QuotaStatus> = serde_json::from_str(
+        &CheckpointStore::open(root.join("checkpoint.db"))
+            .unwrap()
+            .get_meta(META_QUOTAS)
+            .unwrap()
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(status[0].bucket, "project:screening");
+    assert!(status[0].covers(&screening[0]));
+    assert!(!status[0].covers(&other));
+
+    // Room on the worker does not help; a finished screening job does
+    assert!(common::grants(&mut coord, &mut w1, &room).await.is_empty());
+    let rep = JobCompleteReport {
+        job_id: screening.iter().find(|j| j.id != held).unwrap().id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: None,
+        error: None,
+    };
+    common::send(&mut w1, MSG_JOB_COMPLETE, &rep).await;
+    let granted = common::grants(&mut coord, &mut w1, &room).await;
+    assert_eq!(granted.len(), 1);
+    assert_eq!(granted[0].id, held);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,54 @@
+use chrono::{Duration as Span, Utc};
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::report::RecentJobs;
+use unifiedlab::Job;
+
+mod common;
+
+fn job(minutes_ago: i64) -> Job {
+    let mut job = common::job("recent_test");
+    job.updated_at = Utc::now() - Span::minutes(minutes_ago);
+    job
+}
+
+#[test]
+fn test_summaries_are_merged_from_changed_rows() {
+    let root = std::env::temp_dir().join(format!("ulab_recent_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let mut jobs: Vec<Job> = (0..5).map(|i| job(30 + i)).collect();
+    store
+        .apply_batch(0, &jobs.iter().collect::<Vec<_>>(), &[])
+        .unwrap();
+    let mut recent = RecentJobs::new();
+    recent.refresh(&store).unwrap();
+    assert_eq!(recent.jobs().len(), 5);
+    assert_eq!(recent.jobs()[0].id, jobs[0].id.to_string());
+
+    // Only what changed since the watermark is read
+    let since = (Utc::now() - Span::minutes(10)).timestamp_millis();
+    assert!(store.get_jobs_summary_since(since).unwrap().is_empty());
+    jobs[4].status = JobStatus::Running;
+    jobs[4].updated_at = Utc::now();
+    let new = job(0);
+    store.apply_batch(0, &[&jobs[4], &new], &[]).unwrap();
+    assert_eq!(store.get_jobs_summary_since(since).unwrap().len(), 2);
+
+    // The moved job replaces its old row and goes to the top
+    recent.refresh(&store).unwrap();
+    let rows = recent.jobs();
+    assert_eq!(rows.len(), 6);
+    assert!(rows[..2].iter().any(|j| j.id == jobs[4].id.to_string()));
+    assert_eq!(
+        rows.iter()
+            .filter(|j| j.id == jobs[4].id.to_string())
+            .map(|j| j.status.as_str())
+            .collect::<Vec<_>>(),
+        ["Running"]
+    );
+    assert!(rows.windows(2).all(|w| w[0].updated_at >= w[1].updated_at));
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,121 @@
+use serde_json::{json, Value};
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, ElectronVolts, Force, JobStatus, Provenance, ResourceReq,
+    RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::export::{result_columns, write_table, ExportFormat};
+use unifiedlab::{Job, Structure};
+
+mod common;
+
+fn job(node: &str, params: Value, result: Option<CalculationResult>) -> Job {
+    let mut job = Job::new(
+        Structure::new(vec![], None, node.into()),
+        common::config(Default::default(), params),
+        ResourceReq::default(),
+    );
+    job.status = match result {
+        Some(_) => JobStatus::Completed,
+        None => JobStatus::Pending,
+    };
+    job.result = result;
+    job
+}
+
+fn result(energy: f64, forces: Vec<[f64; 3]>, runtime_s: i64) -> CalculationResult {
+    let end = chrono::Utc::now();
+    CalculationResult {
+        energy: Some(ElectronVolts(energy)),
+        forces: Some(forces.into_iter().map(|f| f.map(Force)).collect()),
+        stress: None,
+        t_total_ms: runtime_s as f64 * 1000.0,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "node07".into(),
+            start_time: end - chrono::Duration::seconds(runtime_s),
+            end_time: end,
+            binary_hash: Some("ab12".into()),
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation: None,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    }
+}
+
+#[test]
+fn test_completed_jobs_export_as_one_table() {
+    let root = std::env::temp_dir().join(format!("ulab_export_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let jobs = [
+        job(
+            "relax",
+            json!({ "temperature": 300, "label": "a", "physics_template": { "cores": 8 } }),
+            Some(result(-10.5, vec![[3.0, 4.0, 0.0], [0.0, 0.0, 1.0]], 90)),
+        ),
+        job(
+            "relax",
+            json!({ "temperature": 600.5, "dry": true }),
+            Some(result(-9.25, vec![], 30)),
+        ),
+        job("relax", json!({ "temperature": 900 }), None),
+    ];
+    store
+        .apply_batch(0, &jobs.iter().collect::<Vec<_>>(), &[])
+        .unwrap();
+
+    let records = store.get_completed_results().unwrap();
+    assert_eq!(records.len(), 2);
+    let first = records
+        .iter()
+        .find(|r| r.id == jobs[0].id.to_string())
+        .unwrap();
+    assert_eq!(first.energy_ev, Some(-10.5));
+    assert_eq!(first.max_force, Some(5.0));
+    assert_eq!(first.node, "relax");
+
+    // Nested params are left out unless asked for
+    let columns = result_columns(&records, &[]);
+    let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names[..3], ["id", "engine", "node"]);
+    assert_eq!(
+        names[names.len() - 3..],
+        ["params.dry", "params.label", "params.temperature"]
+    );
+    let runtime = columns.iter().find(|c| c.name == "runtime_ms").unwrap();
+    assert!(runtime.values.contains(&json!(90_000)));
+
+    let out = root.join("results");
+    let csv_path = write_table(&columns, ExportFormat::Csv, &out).unwrap();
+    assert_eq!(csv_path, out.join("results.csv"));
+    let text = std::fs::read_to_string(&csv_path).unwrap();
+    assert!(text.starts_with("id,engine,node,workflow,energy_ev,max_force,"));
+    assert_eq!(text.lines().count(), 3);
+
+    let picked = result_columns(&records, &["physics_template".to_string()]);
+    let json_path = write_table(&picked, ExportFormat::Json, &out).unwrap();
+    let rows: Vec<Value> =
+        serde_json::from_str(&std::fs::read_to_string(json_path).unwrap()).unwrap();
+    let row = rows
+        .iter()
+        .find(|r| r["id"] == json!(jobs[0].id.to_string()))
+        .unwrap();
+    assert_eq!(row["params.physics_template"], json!({ "cores": 8 }));
+    assert_eq!(row["host"], "node07");
+
+    let parquet = write_table(&columns, ExportFormat::Parquet, &out);
+    if cfg!(feature = "parquet") {
+        let bytes = std::fs::read(parquet.unwrap()).unwrap();
+        assert!(bytes.starts_with(b"PAR1"));
+    } else {
+        let err = format!("{:#}", parquet.unwrap_err());
+        assert!(err.contains("feature \"parquet\""), "{}", err);
+    }
+
+    std::fs::remove_dir_all(root).ok();
+}
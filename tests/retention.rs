@@ -0,0 +1,94 @@
+use chrono::{Duration as Span, Utc};
+use std::time::Duration;
+use unifiedlab::checkpoint::{CheckpointStore, RetentionPolicy};
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::MarketplaceCoordinator;
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::Job;
+
+mod common;
+
+fn job(status: JobStatus, hours_ago: i64) -> Job {
+    let mut job = common::job("retention_test");
+    job.status = status;
+    job.updated_at = Utc::now() - Span::hours(hours_ago);
+    job
+}
+
+#[tokio::test]
+async fn test_old_finished_jobs_are_pruned_but_parents_of_live_jobs_kept() {
+    let root = std::env::temp_dir().join(format!("ulab_retention_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let db = root.join("checkpoint.db");
+    let store = CheckpointStore::open(&db).unwrap();
+
+    let old_done = job(JobStatus::Completed, 48);
+    let old_failed = job(JobStatus::Failed, 48);
+    let parent = job(JobStatus::Completed, 48);
+    let mut child = job(JobStatus::Pending, 0);
+    child.parent_ids = vec![parent.id];
+    let fresh = job(JobStatus::Completed, 0);
+    let old_cancelled = job(JobStatus::Cancelled, 48);
+    let all = [
+        &old_done,
+        &old_failed,
+        &parent,
+        &child,
+        &fresh,
+        &old_cancelled,
+    ];
+    store.apply_batch(0, &all, &[]).unwrap();
+
+    let archive = root.join("archive.jsonl");
+    let pruned = store
+        .prune_into(
+            Duration::from_secs(24 * 3600),
+            &[JobStatus::Completed, JobStatus::Failed],
+            Some(&archive),
+        )
+        .unwrap();
+    assert_eq!(pruned, 2);
+
+    let left: Vec<_> = store
+        .get_jobs_summary()
+        .unwrap()
+        .into_iter()
+        .map(|j| j.id)
+        .collect();
+    assert_eq!(left.len(), 4);
+    assert!(!left.contains(&old_done.id.to_string()));
+    assert!(left.contains(&parent.id.to_string()));
+    assert!(store
+        .get_job_history(&old_done.id.to_string())
+        .unwrap()
+        .is_empty());
+
+    let archived: Vec<Job> = std::fs::read_to_string(&archive)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let mut ids: Vec<_> = archived.iter().map(|j| j.id).collect();
+    ids.sort();
+    let mut want = vec![old_done.id, old_failed.id];
+    want.sort();
+    assert_eq!(ids, want);
+
+    // Once the child is done, the Coordinator's first pass takes the parent too
+    child.status = JobStatus::Completed;
+    store.apply_batch(0, &[&child], &[]).unwrap();
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap()
+        .with_retention(RetentionPolicy::new(Duration::from_secs(24 * 3600)));
+    coord.tick().await.unwrap();
+    let left = CheckpointStore::open(&db)
+        .unwrap()
+        .get_jobs_summary()
+        .unwrap();
+    assert_eq!(left.len(), 3);
+    assert!(left.iter().all(|j| j.id != parent.id.to_string()));
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{JobConfig, JobStatus, ResourceReq, RetryPolicy};
+use unifiedlab::marketplace::{
+    job_fingerprint, JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_SUBMIT,
+    MSG_JOB_COMPLETE,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::{Job, Structure};
+use uuid::Uuid;
+
+mod common;
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 3,
+        backoff_s: 0.1,
+        retry_on: vec!["mpi_abort".into()],
+    }
+}
+
+async fn fail(coord: &mut MarketplaceCoordinator, w: &mut MemTransport, id: Uuid, error: &str) {
+    let rep = JobCompleteReport {
+        job_id: id,
+        worker_id: "w1".into(),
+        status: JobStatus::Failed,
+        result: None,
+        error: Some(error.into()),
+    };
+    common::send(w, MSG_JOB_COMPLETE, &rep).await;
+    coord.tick().await.unwrap();
+}
+
+fn job_in(coord: &MarketplaceCoordinator, id: Uuid) -> Job {
+    coord.jobs().find(|j| j.id == id).unwrap().clone()
+}
+
+#[test]
+fn test_backoff_doubles_until_attempts_run_out() {
+    let p = policy();
+    assert_eq!(
+        p.retry_delay(1, "Driver Error: MPI_ABORT was invoked"),
+        Some(Duration::from_millis(100))
+    );
+    assert_eq!(
+        p.retry_delay(2, "MPI_ABORT"),
+        Some(Duration::from_millis(200))
+    );
+    assert_eq!(p.retry_delay(3, "MPI_ABORT"), None);
+    assert_eq!(p.retry_delay(1, "SCF did not converge"), None);
+
+    // The policy is no part of the memoization key
+    let bare = common::config(Default::default(), serde_json::json!({}));
+    let retried = JobConfig {
+        retry: Some(p),
+        ..bare.clone()
+    };
+    assert_eq!(job_fingerprint(&bare), job_fingerprint(&retried));
+}
+
+#[tokio::test]
+async fn test_transient_failure_is_run_again() {
+    let root = std::env::temp_dir().join(format!("ulab_retry_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+
+    let job = Job::new(
+        Structure::new(vec![], None, "retry_test".into()),
+        JobConfig {
+            retry: Some(policy()),
+            ..common::config(Default::default(), serde_json::json!({}))
+        },
+        ResourceReq::default(),
+    );
+    let sub = JobSubmit {
+        jobs: vec![job.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(&mut w1, EV_JOB_SUBMIT, &sub).await;
+    assert_eq!(
+        common::request(&mut coord, &mut w1, "w1", 1).await,
+        [job.id]
+    );
+
+    // A launcher hiccup: back to Pending, but not before the backoff
+    fail(&mut coord, &mut w1, job.id, "Driver Error: MPI_ABORT").await;
+    let j = job_in(&coord, job.id);
+    assert_eq!((j.status, j.attempts), (JobStatus::Pending, 1));
+    assert!(j.node_id.is_none());
+    assert!(common::request(&mut coord, &mut w1, "w1", 1)
+        .await
+        .is_empty());
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(
+        common::request(&mut coord, &mut w1, "w1", 1).await,
+        [job.id]
+    );
+
+    // Anything else fails the job for good
+    fail(&mut coord, &mut w1, job.id, "SCF did not converge").await;
+    let j = job_in(&coord, job.id);
+    assert_eq!((j.status, j.attempts), (JobStatus::Failed, 1));
+    assert_eq!(j.error_log.as_deref(), Some("SCF did not converge"));
+
+    std::fs::remove_dir_all(&root).ok();
+}
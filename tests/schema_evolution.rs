@@ -0,0 +1,179 @@
+use rusqlite::{params, Connection};
+use serde_json::json;
+use std::io::Write;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{JOB_SCHEMA_VERSION, STRUCTURE_SCHEMA_VERSION};
+use unifiedlab::eventlog::{EventLogConfig, EventLogReader, EventLogWriter, EVENT_SCHEMA_VERSION};
+use unifiedlab::marketplace::{JobSubmit, EV_JOB_SUBMIT, MSG_WORK_REQUEST};
+
+mod common;
+
+fn insert_raw(db: &std::path::Path, id: &str, json: &serde_json::Value) {
+    let conn = Connection::open(db).unwrap();
+    conn.execute(
+        "INSERT INTO jobs (id, status, updated_at_ms, node_id, full_json) VALUES (?1, 'Pending', 0, NULL, ?2)",
+        params![id, json.to_string()],
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_restore_upgrades_legacy_and_reports_future_records() {
+    let root = std::env::temp_dir().join(format!("ulab_schema_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let db = root.join("checkpoint.db");
+    let store = CheckpointStore::open(&db).unwrap();
+
+    // 1. A job written by this build
+    let current = common::job("schema_test");
+    store.apply_batch(0, &[&current], &[]).unwrap();
+
+    // 2. A pre-versioning job: no schema_version, no timestamps, partial resources
+    let legacy_id = uuid::Uuid::new_v4();
+    insert_raw(
+        &db,
+        &legacy_id.to_string(),
+        &json!({
+            "id": legacy_id,
+            "status": "Completed",
+            "structure": { "atoms": [], "lattice": null },
+            "config": { "engine": { "engine_type": "gulp", "spec": { "binary": "gulp", "potential_library": "buckingham" } } },
+            "resources": { "cores": 4 },
+            "result": {
+                "energy": -12.5,
+                "forces": null,
+                "stress": null,
+                "final_structure": null,
+                "provenance": { "execution_host": "old-node", "start_time": "2025-01-01T00:00:00Z", "binary_hash": null },
+                "next_generation": null
+            },
+            "error_log": null,
+            "node_id": "old-node"
+        }),
+    );
+
+    // 3. A job from a newer build
+    let future_id = uuid::Uuid::new_v4();
+    let mut future = serde_json::to_value(&current).unwrap();
+    future["id"] = json!(future_id);
+    future["schema_version"] = json!(JOB_SCHEMA_VERSION + 1);
+    insert_raw(&db, &future_id.to_string(), &future);
+
+    let (jobs, report) = store.restore_jobs_with_report().unwrap();
+    assert_eq!(report.loaded, 2);
+    assert_eq!(report.upgraded, 1);
+    assert_eq!(report.dropped.len(), 1);
+    assert_eq!(report.dropped[0].id, future_id.to_string());
+    assert!(report.dropped[0].reason.contains("newer"));
+
+    let legacy = &jobs[&legacy_id];
+    assert_eq!(legacy.resources.cores, 4);
+    assert_eq!(legacy.resources.nodes, 1);
+    assert_eq!(legacy.structure.schema_version, STRUCTURE_SCHEMA_VERSION);
+    let result = legacy.result.as_ref().unwrap();
+    assert_eq!(result.provenance.end_time, result.provenance.start_time);
+    assert!(jobs.contains_key(&current.id));
+
+    // The upgrade is written back: a second restore has nothing left to do
+    let (_, again) = store.restore_jobs_with_report().unwrap();
+    assert_eq!(again.upgraded, 0);
+    assert_eq!(again.dropped.len(), 1);
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+/// A raw frame around a bincode container: [ts][kind][payload_json] as v0.1
+/// wrote it, plus a trailing schema version when given.
+fn append_frame(
+    path: &std::path::Path,
+    kind: &str,
+    payload: &serde_json::Value,
+    version: Option<u32>,
+) {
+    let json = serde_json::to_vec(payload).unwrap();
+    let mut container = 1_700_000_000_000i64.to_le_bytes().to_vec();
+    container.extend((kind.len() as u64).to_le_bytes());
+    container.extend(kind.as_bytes());
+    container.extend((json.len() as u64).to_le_bytes());
+    container.extend(&json);
+    if let Some(v) = version {
+        container.extend(v.to_le_bytes());
+    }
+
+    let mut frame = 0x554C4142u32.to_le_bytes().to_vec();
+    frame.extend(crc32fast::hash(&container).to_le_bytes());
+    frame.extend((container.len() as u32).to_le_bytes());
+    frame.extend(&container);
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    f.write_all(&frame).unwrap();
+}
+
+#[test]
+fn test_event_log_upgrades_v01_records_and_refuses_newer_ones() {
+    let root = std::env::temp_dir().join(format!("ulab_schema_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let log = root.join("events.log");
+
+    // v0.1: no version in the container, and a job without one either
+    let legacy_job = json!({
+        "id": uuid::Uuid::new_v4(),
+        "structure": { "atoms": [], "lattice": null },
+        "config": { "engine": { "engine_type": "gulp", "spec": { "binary": "gulp", "potential_library": "buckingham" } } },
+        "resources": { "cores": 2 },
+        "result": null,
+        "error_log": null,
+        "node_id": null
+    });
+    append_frame(
+        &log,
+        EV_JOB_SUBMIT,
+        &json!({ "jobs": [legacy_job], "deps": [] }),
+        None,
+    );
+    append_frame(&log, MSG_WORK_REQUEST, &json!({ "worker_id": "w1" }), None);
+
+    // This build
+    let mut writer = EventLogWriter::open(&log, EventLogConfig::default()).unwrap();
+    writer
+        .append(MSG_WORK_REQUEST, json!({ "worker_id": "w2" }))
+        .unwrap();
+    drop(writer);
+
+    let mut reader = EventLogReader::open(&log).unwrap();
+    let env = reader.next().unwrap().unwrap();
+    let sub: JobSubmit = serde_json::from_value(env.record.payload).unwrap();
+    assert_eq!(sub.jobs[0].schema_version, JOB_SCHEMA_VERSION);
+    assert_eq!(sub.jobs[0].resources.cores, 2);
+    assert_eq!(
+        sub.jobs[0].structure.schema_version,
+        STRUCTURE_SCHEMA_VERSION
+    );
+
+    let env = reader.next().unwrap().unwrap();
+    assert_eq!(env.record.payload["worker_id"], "w1");
+    let env = reader.next().unwrap().unwrap();
+    assert_eq!(env.record.payload["worker_id"], "w2");
+    assert!(reader.next().unwrap().is_none());
+    let stats = reader.stats();
+    assert_eq!(
+        (stats.records, stats.upgraded, stats.corruptions),
+        (3, 2, 0)
+    );
+
+    // A newer writer's record stops the reader instead of vanishing
+    append_frame(
+        &log,
+        MSG_WORK_REQUEST,
+        &json!({}),
+        Some(EVENT_SCHEMA_VERSION + 1),
+    );
+    let err = reader.next().unwrap_err();
+    assert!(format!("{:#}", err).contains("newer"), "{:#}", err);
+    assert_eq!(reader.stats().corruptions, 0);
+
+    std::fs::remove_dir_all(&root).ok();
+}
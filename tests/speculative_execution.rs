@@ -0,0 +1,155 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    JobCancel, JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, EV_JOB_CANCEL,
+    EV_JOB_SUBMIT, MSG_JOB_COMPLETE, SPECULATION_LOST, SPECULATION_MIN_SAMPLES,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::Job;
+use uuid::Uuid;
+
+mod common;
+
+async fn report(w: &mut MemTransport, worker: &str, job_id: Uuid, status: JobStatus) {
+    let rep = JobCompleteReport {
+        job_id,
+        worker_id: worker.into(),
+        status,
+        result: None,
+        error: None,
+    };
+    common::send(w, MSG_JOB_COMPLETE, &rep).await;
+}
+
+fn status(coord: &MarketplaceCoordinator, id: Uuid) -> JobStatus {
+    coord.jobs().find(|j| j.id == id).unwrap().status.clone()
+}
+
+/// The job cancels `w` was sent.
+async fn kills(w: &mut MemTransport) -> Vec<JobCancel> {
+    w.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_JOB_CANCEL)
+        .map(|e| serde_json::from_value(e.record.payload).unwrap())
+        .collect()
+}
+
+/// A fanout on w1 whose last job straggles until w2 gets a duplicate of it.
+async fn duplicated_straggler(
+    root: &std::path::Path,
+) -> (
+    MemNetwork,
+    MarketplaceCoordinator,
+    MemTransport,
+    MemTransport,
+    Uuid,
+) {
+    std::fs::create_dir_all(root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap()
+        .with_speculation(3.0)
+        .with_worker_timeout(Duration::from_secs(3600))
+        .with_grant_ack_timeout(Duration::from_secs(3600));
+    let mut w1 = net.worker(Some("w1"));
+    let mut w2 = net.worker(Some("w2"));
+
+    let fanout: Vec<Job> = (0..=SPECULATION_MIN_SAMPLES)
+        .map(|_| common::job("speculation_test"))
+        .collect();
+    let sub = JobSubmit {
+        jobs: fanout.clone(),
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    w1.send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&sub).unwrap())
+        .await
+        .unwrap();
+    let granted = common::request(&mut coord, &mut w1, "w1", 64).await;
+    assert_eq!(granted.len(), fanout.len());
+
+    // All but one finish in about a minute
+    tokio::time::advance(Duration::from_secs(60)).await;
+    let (straggler, done) = granted.split_last().unwrap();
+    for id in done {
+        report(&mut w1, "w1", *id, JobStatus::Completed).await;
+    }
+    coord.tick().await.unwrap();
+
+    // Not yet three times the median: an idle worker gets nothing
+    tokio::time::advance(Duration::from_secs(100)).await;
+    assert!(common::request(&mut coord, &mut w2, "w2", 8)
+        .await
+        .is_empty());
+
+    tokio::time::advance(Duration::from_secs(30)).await;
+    assert_eq!(
+        common::request(&mut coord, &mut w2, "w2", 8).await,
+        [*straggler]
+    );
+
+    (net, coord, w1, w2, *straggler)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_straggler_is_duplicated_and_the_loser_killed() {
+    let root = std::env::temp_dir().join(format!("ulab_speculation_{}", Uuid::new_v4()));
+    let (net, mut coord, mut w1, mut w2, straggler) = duplicated_straggler(&root).await;
+    assert!(common::request(&mut coord, &mut w1, "w1", 64)
+        .await
+        .is_empty());
+
+    // A failed copy does not fail the job while the other runs
+    report(&mut w2, "w2", straggler, JobStatus::Failed).await;
+    coord.tick().await.unwrap();
+    assert_eq!(status(&coord, straggler), JobStatus::Running);
+
+    // A fresh duplicate finishes first; only the other copy is told to stop
+    let mut w3 = net.worker(Some("w3"));
+    assert_eq!(
+        common::request(&mut coord, &mut w3, "w3", 8).await,
+        [straggler]
+    );
+    report(&mut w3, "w3", straggler, JobStatus::Completed).await;
+    coord.tick().await.unwrap();
+    assert_eq!(status(&coord, straggler), JobStatus::Completed);
+
+    let lost = kills(&mut w1).await;
+    assert_eq!(lost.len(), 1);
+    assert_eq!(lost[0].job_ids, [straggler]);
+    assert_eq!(lost[0].reason, SPECULATION_LOST);
+    assert!(kills(&mut w3).await.is_empty());
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_failed_primary_leaves_the_job_to_its_copy() {
+    let root = std::env::temp_dir().join(format!("ulab_speculation_{}", Uuid::new_v4()));
+    let (_net, mut coord, mut w1, mut w2, straggler) = duplicated_straggler(&root).await;
+
+    // The first worker's run fails; the duplicate carries on
+    report(&mut w1, "w1", straggler, JobStatus::Failed).await;
+    coord.tick().await.unwrap();
+    assert_eq!(status(&coord, straggler), JobStatus::Running);
+    let job = coord.jobs().find(|j| j.id == straggler).unwrap();
+    assert_eq!(job.node_id.as_deref(), Some("w2"));
+
+    report(&mut w2, "w2", straggler, JobStatus::Completed).await;
+    coord.tick().await.unwrap();
+    assert_eq!(status(&coord, straggler), JobStatus::Completed);
+
+    // Neither run is left to stop
+    assert!(kills(&mut w1).await.is_empty());
+    assert!(kills(&mut w2).await.is_empty());
+
+    std::fs::remove_dir_all(&root).ok();
+}
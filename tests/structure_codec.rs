@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use unifiedlab::core::{Atom, Lattice, Structure};
+use unifiedlab::wire::{decode_structure, encode_structure, StructureCodec};
+
+mod common;
+
+fn big_structure(n: usize) -> Structure {
+    let atoms = (0..n)
+        .map(|i| Atom {
+            symbol: "Si".into(),
+            position: [i as f64 * 2.0, 0.0, 0.0],
+            charge: Some(0.5),
+            magnetic_moment: None,
+            tags: HashMap::new(),
+        })
+        .collect();
+    let mut s = Structure::new(
+        atoms,
+        Some(Lattice {
+            vectors: [[5.4, 0.0, 0.0], [0.0, 5.4, 0.0], [0.0, 0.0, 5.4]],
+            pbc: [true; 3],
+        }),
+        "codec_test".into(),
+    );
+    s.metadata
+        .insert("origin".into(), serde_json::json!({"gen": 3}));
+    s
+}
+
+#[test]
+fn test_binary_roundtrip() {
+    let s = big_structure(10);
+    let bytes = encode_structure(&s).expect("encode");
+    let back = decode_structure(&bytes).expect("decode");
+
+    assert_eq!(back.id, s.id);
+    assert_eq!(back.atoms.len(), 10);
+    assert_eq!(back.atoms[3].position, [6.0, 0.0, 0.0]);
+    assert_eq!(back.atoms[3].charge, Some(0.5));
+    assert_eq!(back.metadata["origin"]["gen"], 3);
+}
+
+#[test]
+fn test_offload_and_hydrate() {
+    let root = std::env::temp_dir().join(format!("ulab_codec_{}", uuid::Uuid::new_v4()));
+    let codec = StructureCodec::new(&root).expect("codec");
+
+    // Small structures stay inline
+    let mut small = big_structure(4);
+    codec.offload(&mut small).unwrap();
+    assert!(!small.is_offloaded());
+
+    // Large structures are replaced by a CAS reference
+    let mut big = big_structure(1000);
+    codec.offload(&mut big).unwrap();
+    assert!(big.is_offloaded());
+    assert!(big.atoms.is_empty());
+
+    codec.hydrate(&mut big).unwrap();
+    assert!(!big.is_offloaded());
+    assert_eq!(big.atoms.len(), 1000);
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn test_offload_failure_names_the_job() {
+    let root = std::env::temp_dir().join(format!("ulab_codec_{}", uuid::Uuid::new_v4()));
+    let codec = StructureCodec::new(&root).expect("codec");
+
+    // The store is gone and a file sits where it was
+    std::fs::remove_dir_all(root.join("store")).unwrap();
+    std::fs::write(root.join("store"), b"").unwrap();
+
+    let mut jobs = vec![common::job("codec_test")];
+    jobs[0].structure = big_structure(1000);
+    let err = codec.offload_jobs(&mut jobs).unwrap_err();
+    assert!(format!("{:#}", err).contains(&jobs[0].id.to_string()));
+    // Left inline, so nothing refers to a blob that was never written
+    assert!(!jobs[0].structure.is_offloaded());
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,168 @@
+use std::path::Path;
+
+use unifiedlab::dsl::lint::{lint_file, Severity};
+use unifiedlab::structure_io::{parse, StructureFormat};
+use unifiedlab::workflow::yaml::YamlLoader;
+
+/// Body-centred iron, written as its asymmetric unit and the centring.
+const FE_BCC_CIF: &str = r#"
+data_Fe
+_cell_length_a    2.8665(2)
+_cell_length_b    2.8665(2)
+_cell_length_c    2.8665(2)
+_cell_angle_alpha 90
+_cell_angle_beta  90
+_cell_angle_gamma 90
+loop_
+_space_group_symop_operation_xyz
+'x, y, z'
+'x+1/2, y+1/2, z+1/2'
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+Fe1 Fe 0.0 0.0 0.0
+"#;
+
+const SI_POSCAR: &str = "Si2
+2.0
+ 0.0 1.0 1.0
+ 1.0 0.0 1.0
+ 1.0 1.0 0.0
+Si
+2
+Selective dynamics
+Direct
+ 0.00 0.00 0.00 T T T
+ 0.25 0.25 0.25 F F F
+";
+
+fn close(a: [f64; 3], b: [f64; 3]) -> bool {
+    a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-6)
+}
+
+#[test]
+fn test_cif_sites_are_expanded_by_symmetry() {
+    let s = parse(FE_BCC_CIF, StructureFormat::Cif).unwrap();
+    assert_eq!(s.atoms.len(), 2);
+    assert!(s.atoms.iter().all(|a| a.symbol == "Fe"));
+    assert!(close(s.atoms[1].position, [1.43325, 1.43325, 1.43325]));
+    let lattice = s.lattice.unwrap();
+    assert!((lattice.volume() - 2.8665f64.powi(3)).abs() < 1e-6);
+
+    // A hexagonal cell keeps a along x and b in the xy plane
+    let hex = FE_BCC_CIF.replace("_cell_angle_gamma 90", "_cell_angle_gamma 120");
+    let lattice = parse(&hex, StructureFormat::Cif).unwrap().lattice.unwrap();
+    assert!(close(
+        lattice.vectors[1],
+        [-1.43325, 2.8665 * 3f64.sqrt() / 2.0, 0.0]
+    ));
+
+    // A site row cut short is an error, not a panic
+    let truncated = format!("{}Si2\n", FE_BCC_CIF);
+    let err = parse(&truncated, StructureFormat::Cif)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        err,
+        "loop of _atom_site_label has 6 values, not a multiple of its 5 tags"
+    );
+}
+
+#[test]
+fn test_poscar_and_xyz_are_read() {
+    let s = parse(SI_POSCAR, StructureFormat::Poscar).unwrap();
+    assert_eq!(s.atoms.len(), 2);
+    assert_eq!(s.lattice.as_ref().unwrap().vectors[0], [0.0, 2.0, 2.0]);
+    // Direct coordinates are scaled with the cell
+    assert!(close(s.atoms[1].position, [1.0, 1.0, 1.0]));
+
+    let vasp4 = SI_POSCAR.replace("Si\n2\n", "2\n");
+    let err = parse(&vasp4, StructureFormat::Poscar)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("no species line"), "{}", err);
+
+    let water = "3\nwater\nO 0.0 0.0 0.0\nH 0.757 0.586 0.0\nH -0.757 0.586 0.0\n";
+    let s = parse(water, StructureFormat::Xyz).unwrap();
+    assert_eq!(s.atoms.len(), 3);
+    assert!(s.lattice.is_none());
+
+    let slab =
+        "1\nLattice=\"4.0 0.0 0.0 0.0 4.0 0.0 0.0 0.0 20.0\" pbc=\"T T F\"\nPt 0.0 0.0 10.0\n";
+    let lattice = parse(slab, StructureFormat::Xyz).unwrap().lattice.unwrap();
+    assert_eq!(lattice.vectors[2], [0.0, 0.0, 20.0]);
+    assert_eq!(lattice.pbc, [true, true, false]);
+
+    assert_eq!(
+        StructureFormat::from_path(Path::new("relax/CONTCAR")),
+        Some(StructureFormat::Poscar)
+    );
+    assert_eq!(StructureFormat::from_path(Path::new("slab.dat")), None);
+}
+
+#[test]
+fn test_yaml_structure_file_is_embedded_in_the_job() {
+    let dir = std::env::temp_dir().join(format!("ulab_structure_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("structures")).unwrap();
+    std::fs::write(dir.join("structures/fe.cif"), FE_BCC_CIF).unwrap();
+    std::fs::write(dir.join("structures/si.txt"), SI_POSCAR).unwrap();
+    let path = dir.join("relax.yaml");
+    std::fs::write(
+        &path,
+        r#"
+version: 2
+metadata: { name: relax }
+nodes:
+  - id: fe
+    type: compute
+    structure: { file: structures/fe.cif }
+  - id: si
+    type: compute
+    structure: { file: structures/si.txt, format: poscar }
+edges:
+  - { from: fe, to: si }
+"#,
+    )
+    .unwrap();
+
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    let structure = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+            .job
+            .structure
+            .clone()
+    };
+    assert_eq!(structure("fe").atoms.len(), 2);
+    assert_eq!(structure("si").atoms[0].symbol, "Si");
+    assert!(structure("fe").metadata["file"]
+        .as_str()
+        .unwrap()
+        .ends_with("structures/fe.cif"));
+
+    // A file that does not read fails the load, and lint points at it
+    std::fs::write(dir.join("structures/fe.cif"), "data_Fe\n").unwrap();
+    let err = YamlLoader::load_from_file(&path, &Default::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        err.contains("node 'fe': structure structures/fe.cif"),
+        "{}",
+        err
+    );
+    let found = lint_file(&path, &Default::default()).unwrap();
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].severity, Severity::Error);
+    assert_eq!(found[0].line, 7);
+
+    std::fs::remove_dir_all(dir).ok();
+}
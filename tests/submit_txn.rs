@@ -0,0 +1,118 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    JobSubmit, MarketplaceCoordinator, Routing, SubmitAck, SubmitEnd, EV_JOB_SUBMIT, EV_SUBMIT_ACK,
+    MSG_SUBMIT_END,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::Job;
+use uuid::Uuid;
+
+mod common;
+
+/// A chain of `n` jobs.
+fn chain(n: usize) -> JobSubmit {
+    let jobs: Vec<Job> = (0..n).map(|_| common::job("txn_test")).collect();
+    let deps = jobs.windows(2).map(|w| (w[0].id, w[1].id)).collect();
+    JobSubmit {
+        jobs,
+        deps,
+        routing: Routing::default(),
+        txn: None,
+    }
+}
+
+async fn acks(t: &mut MemTransport) -> Vec<SubmitAck> {
+    t.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|env| env.record.kind == EV_SUBMIT_ACK)
+        .map(|env| serde_json::from_value(env.record.payload).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_submission_is_applied_whole_or_not_at_all() {
+    let root = std::env::temp_dir().join(format!("ulab_txn_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut architect = net.worker(None);
+
+    // Seven jobs in chunks of three; each edge rides with its child
+    let (txn_id, chunks) = chain(7).into_chunks(3);
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks.iter().map(|c| c.deps.len()).sum::<usize>(), 6);
+    assert!(chunks[1]
+        .deps
+        .iter()
+        .all(|(_, c)| chunks[1].jobs.iter().any(|j| j.id == *c)));
+
+    // Nothing shows until the end marker
+    let n = chunks.len();
+    for chunk in &chunks {
+        common::send(&mut architect, EV_JOB_SUBMIT, chunk).await;
+    }
+    coord.tick().await.unwrap();
+    assert_eq!(coord.jobs().count(), 0);
+
+    let end = SubmitEnd {
+        txn_id,
+        chunks: n,
+        abort: false,
+    };
+    common::send(&mut architect, MSG_SUBMIT_END, &end).await;
+    coord.tick().await.unwrap();
+    assert_eq!(coord.jobs().count(), 7);
+    let blocked = coord
+        .jobs()
+        .filter(|j| j.status == JobStatus::Blocked)
+        .count();
+    assert_eq!(blocked, 6);
+    let got = acks(&mut architect).await;
+    assert_eq!(got.len(), 1);
+    assert!(got[0].ok && got[0].txn_id == txn_id && got[0].jobs == 7);
+
+    // A lost chunk sinks the whole submission
+    let (txn_id, chunks) = chain(4).into_chunks(2);
+    common::send(&mut architect, EV_JOB_SUBMIT, &chunks[1]).await;
+    let end = SubmitEnd {
+        txn_id,
+        chunks: chunks.len(),
+        abort: false,
+    };
+    common::send(&mut architect, MSG_SUBMIT_END, &end).await;
+    coord.tick().await.unwrap();
+    assert_eq!(coord.jobs().count(), 7);
+    let got = acks(&mut architect).await;
+    assert!(!got[0].ok && got[0].message.contains("1 of 2"));
+
+    // So does a deployer that never finishes
+    let mut coord = coord.with_submit_timeout(Duration::ZERO);
+    let (txn_id, chunks) = chain(2).into_chunks(1);
+    common::send(&mut architect, EV_JOB_SUBMIT, &chunks[0]).await;
+    coord.tick().await.unwrap();
+    assert_eq!(coord.jobs().count(), 7);
+    let got = acks(&mut architect).await;
+    assert!(!got[0].ok && got[0].txn_id == txn_id);
+
+    // The late end marker finds nothing to apply
+    let end = SubmitEnd {
+        txn_id,
+        chunks: chunks.len(),
+        abort: false,
+    };
+    common::send(&mut architect, MSG_SUBMIT_END, &end).await;
+    coord.tick().await.unwrap();
+    assert_eq!(coord.jobs().count(), 7);
+    assert!(!acks(&mut architect).await[0].ok);
+
+    std::fs::remove_dir_all(&root).ok();
+}
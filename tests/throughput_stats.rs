@@ -0,0 +1,116 @@
+use chrono::{DateTime, Duration as Span, Utc};
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::{
+    CalculationResult, Engine, JobStatus, Provenance, ResourceReq, RESULT_SCHEMA_VERSION,
+};
+use unifiedlab::report::Throughput;
+use unifiedlab::{Job, Structure};
+
+mod common;
+
+fn job(engine: Engine, created: DateTime<Utc>) -> Job {
+    let mut job = Job::new(
+        Structure::new(vec![], None, "stats_test".into()),
+        common::config(engine, serde_json::json!({})),
+        ResourceReq::default(),
+    );
+    job.created_at = created;
+    job
+}
+
+/// Waited `queue` minutes after `created`, then ran for `run` minutes.
+fn completed(mut job: Job, queue: i64, run: i64) -> Job {
+    let start = job.created_at + Span::minutes(queue);
+    let end = start + Span::minutes(run);
+    job.status = JobStatus::Completed;
+    job.updated_at = end;
+    job.result = Some(CalculationResult {
+        energy: None,
+        forces: None,
+        stress: None,
+        t_total_ms: (run * 60_000) as f64,
+        final_structure: None,
+        provenance: Provenance {
+            execution_host: "w1".into(),
+            start_time: start,
+            end_time: end,
+            binary_hash: None,
+            exit_code: 0,
+            sandbox_info: String::new(),
+            files: vec![],
+        },
+        next_generation: None,
+        usage: None,
+        steps: vec![],
+        schema_version: RESULT_SCHEMA_VERSION,
+    });
+    job
+}
+
+#[test]
+fn test_throughput_is_per_engine_and_incremental() {
+    let root = std::env::temp_dir().join(format!("ulab_stats_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let now = Utc::now();
+    let ago = |min: i64| now - Span::minutes(min);
+    let agent = Engine::default();
+    let code = agent.code();
+
+    let mut failed = job(agent.clone(), ago(20));
+    failed.status = JobStatus::Failed;
+    failed.updated_at = ago(15);
+    let jobs = [
+        completed(job(agent.clone(), ago(30)), 2, 4),
+        completed(job(agent.clone(), ago(10)), 1, 2),
+        failed,
+        // Finished before the window
+        completed(job(agent.clone(), ago(200)), 5, 5),
+    ];
+    store
+        .apply_batch(0, &jobs.iter().collect::<Vec<_>>(), &[])
+        .unwrap();
+
+    let mut stats = Throughput::new(Duration::from_secs(3600));
+    stats.refresh(&store, now.timestamp_millis()).unwrap();
+    let rows = stats.rows();
+    assert_eq!(rows.len(), 1);
+    let row = &rows[0];
+    assert_eq!(row.code, code);
+    assert_eq!((row.completed, row.failed), (2, 1));
+    assert_eq!(row.per_hour, 2.0);
+    assert_eq!(row.median_queue_ms, Some(90_000));
+    assert_eq!(row.median_runtime_ms, Some(180_000));
+    assert!((row.failure_rate() - 1.0 / 3.0).abs() < 1e-9);
+
+    // A retried job finishes, and another engine shows up
+    let mut retried = completed(jobs[2].clone(), 20, 1);
+    retried.updated_at = now;
+    let gulp = completed(
+        job(
+            Engine::Gulp {
+                binary: "gulp".into(),
+                potential_library: "lib".into(),
+            },
+            ago(5),
+        ),
+        0,
+        1,
+    );
+    store.apply_batch(0, &[&retried, &gulp], &[]).unwrap();
+    stats.refresh(&store, now.timestamp_millis()).unwrap();
+    let rows = stats.rows();
+    let row = |c: &str| rows.iter().find(|r| r.code == c).unwrap();
+    assert_eq!((row(&code).completed, row(&code).failed), (3, 0));
+    assert_eq!(row(&gulp.config.engine.code()).completed, 1);
+    assert_eq!(row("all").completed, 4);
+
+    // Two hours on, everything has left the window
+    let later = now + Span::hours(2);
+    stats.refresh(&store, later.timestamp_millis()).unwrap();
+    assert!(stats.rows().is_empty());
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,64 @@
+use serde_json::json;
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::{FileTransport, Role, Transport};
+
+#[tokio::test]
+async fn test_file_transport_counts_bytes_and_read_lag() {
+    let root = std::env::temp_dir().join(format!("ulab_stats_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    let mut coord = FileTransport::new(&root, Role::Coordinator, None)
+        .await
+        .unwrap();
+    let mut worker = FileTransport::new(&root, Role::Worker, Some("w1"))
+        .await
+        .unwrap();
+    assert_eq!(worker.stats().read_lag, Some(0));
+
+    for i in 0..3 {
+        coord.broadcast("tick", json!({ "i": i })).await.unwrap();
+    }
+    let written = coord.stats();
+    assert_eq!(written.events_written, 3);
+    assert!(written.bytes_written > 0);
+
+    // Everything broadcast is still ahead of the worker's cursor
+    assert_eq!(worker.stats().read_lag, Some(written.bytes_written));
+
+    assert_eq!(worker.recv_broadcasts().await.unwrap().len(), 3);
+    let read = worker.stats();
+    assert_eq!(read.events_read, 3);
+    assert_eq!(read.bytes_read, written.bytes_written);
+    assert_eq!(read.read_lag, Some(0));
+
+    worker
+        .send_to_coordinator("hello", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(coord.recv_worker_messages().await.unwrap().len(), 1);
+    let coord_stats = coord.stats();
+    assert_eq!(coord_stats.events_read, 1);
+    assert_eq!(coord_stats.bytes_read, worker.stats().bytes_written);
+    assert_eq!(coord_stats.read_lag, Some(0));
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_mem_transport_reports_backlog() {
+    let net = MemNetwork::new();
+    let mut coord = net.coordinator();
+    let mut worker = net.worker(Some("w1"));
+
+    worker.send_to_coordinator("a", json!({})).await.unwrap();
+    worker.send_to_coordinator("b", json!({})).await.unwrap();
+    assert_eq!(coord.stats().backlog, Some(2));
+
+    coord.broadcast("tick", json!({})).await.unwrap();
+    assert_eq!(worker.stats().read_lag, Some(1));
+
+    assert_eq!(coord.recv_worker_messages().await.unwrap().len(), 2);
+    assert_eq!(coord.stats().backlog, Some(0));
+    assert_eq!(worker.recv_broadcasts().await.unwrap().len(), 1);
+    assert_eq!(worker.stats().read_lag, Some(0));
+}
@@ -0,0 +1,75 @@
+#![cfg(unix)]
+
+use serde_json::json;
+use std::time::Duration;
+use unifiedlab::eventlog::{EventEnvelope, EventLogConfig};
+use unifiedlab::transport::uds::{is_live, UdsTransport};
+use unifiedlab::transport::{FileTransport, Role, Transport};
+
+/// Collects messages until `n` have arrived (or a generous deadline passes).
+async fn collect(t: &mut impl Transport, n: usize, coordinator: bool) -> Vec<String> {
+    let mut got: Vec<EventEnvelope> = Vec::new();
+    for _ in 0..50 {
+        let batch = if coordinator {
+            t.recv_worker_messages().await.unwrap()
+        } else {
+            t.recv_broadcasts().await.unwrap()
+        };
+        got.extend(batch);
+        if got.len() >= n {
+            break;
+        }
+        t.wait_for_traffic(Duration::from_millis(100)).await;
+    }
+    got.into_iter().map(|e| e.record.kind).collect()
+}
+
+#[tokio::test]
+async fn test_socket_delivers_both_ways_without_files() {
+    let root = std::env::temp_dir().join(format!("ulab_uds_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    // Left behind by an earlier file-transport run
+    let mut old = FileTransport::new(&root, Role::Worker, Some("old"))
+        .await
+        .unwrap();
+    old.send_to_coordinator("old.msg", json!({})).await.unwrap();
+
+    let mut coord = UdsTransport::serve(&root, EventLogConfig::default())
+        .await
+        .unwrap();
+    assert!(is_live(&root));
+    coord.broadcast("early", json!({})).await.unwrap();
+
+    let mut w1 = UdsTransport::connect(&root, Some("w1"));
+    let mut w2 = UdsTransport::connect(&root, Some("w2"));
+
+    // 1. Worker -> Coordinator, after the leftover inbox record
+    w1.send_to_coordinator("work.request", json!({ "worker_id": "w1" }))
+        .await
+        .unwrap();
+    assert_eq!(
+        collect(&mut coord, 2, true).await,
+        ["old.msg", "work.request"]
+    );
+
+    // 2. A late subscriber replays the log, then receives live broadcasts
+    assert_eq!(collect(&mut w1, 1, false).await, ["early"]);
+    assert_eq!(collect(&mut w2, 1, false).await, ["early"]);
+    coord.broadcast("live", json!({})).await.unwrap();
+    assert_eq!(collect(&mut w1, 1, false).await, ["live"]);
+
+    // 3. Addressed messages reach their worker only
+    coord
+        .send_to_worker("w2", "work.grant", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(collect(&mut w2, 2, false).await, ["live", "work.grant"]);
+    assert!(w1.recv_broadcasts().await.unwrap().is_empty());
+
+    // 4. The socket goes away with the Coordinator
+    drop(coord);
+    assert!(!is_live(&root));
+
+    std::fs::remove_dir_all(&root).ok();
+}
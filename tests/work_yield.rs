@@ -0,0 +1,96 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::marketplace::{
+    GrantAck, JobSubmit, MarketplaceCoordinator, Routing, WorkGrant, WorkYield, EV_JOB_SUBMIT,
+    EV_WORK_GRANT, MSG_GRANT_ACK, MSG_WORK_REQUEST, MSG_WORK_YIELD,
+};
+use unifiedlab::transport::{FileTransport, Role, Transport};
+
+mod common;
+
+async fn heartbeat(t: &mut FileTransport, worker_id: &str, cores: usize) {
+    let req = common::work_request(worker_id, cores);
+    common::send(t, MSG_WORK_REQUEST, &req).await;
+}
+
+async fn grants_for(t: &mut FileTransport, worker_id: &str) -> Vec<WorkGrant> {
+    t.recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_WORK_GRANT)
+        .map(|e| serde_json::from_value::<WorkGrant>(e.record.payload).unwrap())
+        .filter(|g| g.worker_id == worker_id)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_retiring_worker_hands_back_queued_jobs() {
+    let root = std::env::temp_dir().join(format!("ulab_yield_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let transport = FileTransport::new(&root, Role::Coordinator, None)
+        .await
+        .unwrap();
+    let mut coord = MarketplaceCoordinator::open(Box::new(transport), store)
+        .await
+        .unwrap();
+
+    let mut w1 = FileTransport::new(&root, Role::Worker, Some("w1"))
+        .await
+        .unwrap();
+    let mut w2 = FileTransport::new(&root, Role::Worker, Some("w2"))
+        .await
+        .unwrap();
+
+    let sub = JobSubmit {
+        jobs: vec![
+            common::job("yield_test"),
+            common::job("yield_test"),
+            common::job("yield_test"),
+        ],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(&mut w1, EV_JOB_SUBMIT, &sub).await;
+
+    // 1. w1 takes all three; it runs one and queues the rest locally
+    heartbeat(&mut w1, "w1", 3).await;
+    coord.tick().await.unwrap();
+    let grant = grants_for(&mut w1, "w1").await.remove(0);
+    assert_eq!(grant.jobs.len(), 3);
+    let ack = GrantAck {
+        worker_id: "w1".into(),
+        grant_id: grant.grant_id.clone(),
+    };
+    common::send(&mut w1, MSG_GRANT_ACK, &ack).await;
+
+    // 2. w2 is idle with room for two
+    heartbeat(&mut w2, "w2", 2).await;
+    coord.tick().await.unwrap();
+    assert!(grants_for(&mut w2, "w2").await.is_empty());
+
+    // 3. Slurm takes w1's node: the queued jobs reach w2 in the same tick
+    let retire = WorkYield {
+        worker_id: "w1".into(),
+        jobs: grant.jobs[1..].to_vec(),
+        reason: "allocation shrinking".into(),
+    };
+    common::send(&mut w1, MSG_WORK_YIELD, &retire).await;
+    coord.tick().await.unwrap();
+
+    let moved = grants_for(&mut w2, "w2").await;
+    assert_eq!(moved.len(), 1);
+    let moved_ids: Vec<_> = moved[0].jobs.iter().map(|j| j.id).collect();
+    let yielded_ids: Vec<_> = grant.jobs[1..].iter().map(|j| j.id).collect();
+    assert_eq!(moved_ids, yielded_ids);
+
+    // 4. The running job stays with w1, and w1 gets nothing new
+    heartbeat(&mut w2, "w2", 2).await;
+    coord.tick().await.unwrap();
+    assert!(grants_for(&mut w2, "w2").await.is_empty());
+    assert!(grants_for(&mut w1, "w1").await.is_empty());
+
+    std::fs::remove_dir_all(&root).ok();
+}
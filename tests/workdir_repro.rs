@@ -0,0 +1,74 @@
+use unifiedlab::core::{FileRole, WorkFile};
+use unifiedlab::provenance::{manifest_dir, ArtifactStore};
+
+#[test]
+fn test_workdir_manifest_round_trips_through_the_store() {
+    let dir = std::env::temp_dir().join(format!("ulab_repro_{}", uuid::Uuid::new_v4()));
+    let work = dir.join("work");
+    std::fs::create_dir_all(work.join("pseudo")).unwrap();
+    let store = ArtifactStore::new(dir.join("store")).unwrap();
+
+    // What the adapter's write phase leaves behind
+    std::fs::write(work.join("INCAR"), "ENCUT = 520\n").unwrap();
+    std::fs::write(work.join("pseudo/POTCAR"), "PAW_PBE Si\n").unwrap();
+    std::fs::write(work.join("KPOINTS"), "Gamma\n").unwrap();
+    let mut files = manifest_dir(&work, FileRole::Input).unwrap();
+    let paths: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["INCAR", "KPOINTS", "pseudo/POTCAR"]);
+
+    // The run writes a log and rewrites one of its inputs
+    std::fs::write(work.join("OUTCAR"), "free energy TOTEN = -10.8\n").unwrap();
+    std::fs::write(work.join("KPOINTS"), "Gamma (reduced)\n").unwrap();
+    store.capture_workdir(&work, &mut files).unwrap();
+
+    let outputs: Vec<_> = files
+        .iter()
+        .filter(|f| f.role == FileRole::Output)
+        .map(|f| f.path.as_str())
+        .collect();
+    assert_eq!(outputs, ["KPOINTS", "OUTCAR"]);
+    assert_eq!(files.len(), 5);
+
+    // Inputs the run left alone come back byte for byte
+    let inputs_only = files
+        .iter()
+        .filter(|f| f.path != "KPOINTS")
+        .cloned()
+        .collect::<Vec<_>>();
+    let out = dir.join("repro");
+    let written = store
+        .restore_workdir(&inputs_only, &[FileRole::Input], &out)
+        .unwrap();
+    assert_eq!(written, 2);
+    assert_eq!(
+        std::fs::read_to_string(out.join("pseudo/POTCAR")).unwrap(),
+        "PAW_PBE Si\n"
+    );
+    assert!(!out.join("OUTCAR").exists());
+
+    // The overwritten input's first version was never stored
+    assert!(store
+        .restore_workdir(&files, &[FileRole::Input], &dir.join("again"))
+        .is_err());
+
+    let written = store
+        .restore_workdir(&files, &[FileRole::Output], &out)
+        .unwrap();
+    assert_eq!(written, 2);
+    assert_eq!(
+        std::fs::read_to_string(out.join("KPOINTS")).unwrap(),
+        "Gamma (reduced)\n"
+    );
+
+    // A manifest cannot point outside the target dir
+    let escape = WorkFile {
+        path: "../escape".into(),
+        ..files[0].clone()
+    };
+    assert!(store
+        .restore_workdir(&[escape], &[FileRole::Input], &out)
+        .is_err());
+    assert!(!dir.join("escape").exists());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
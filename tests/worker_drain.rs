@@ -0,0 +1,99 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    ControlAck, JobCompleteReport, JobSubmit, MarketplaceCoordinator, Routing, WorkerDrain,
+    EV_CONTROL_ACK, EV_JOB_SUBMIT, MSG_JOB_COMPLETE, MSG_WORKER_DRAIN,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use uuid::Uuid;
+
+mod common;
+
+async fn drain(
+    coord: &mut MarketplaceCoordinator,
+    console: &mut MemTransport,
+    resume: bool,
+) -> String {
+    let request_id = Uuid::new_v4();
+    let msg = WorkerDrain {
+        worker_id: "w1".into(),
+        resume,
+        request_id: Some(request_id),
+    };
+    common::send(console, MSG_WORKER_DRAIN, &msg).await;
+    coord.tick().await.unwrap();
+    console
+        .recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_CONTROL_ACK)
+        .map(|e| serde_json::from_value::<ControlAck>(e.record.payload).unwrap())
+        .find(|a| a.request_id == request_id)
+        .unwrap()
+        .message
+}
+
+#[tokio::test]
+async fn test_drained_worker_finishes_but_gets_nothing_new() {
+    let root = std::env::temp_dir().join(format!("ulab_drain_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+    let mut console = net.worker(None);
+
+    let (a, b) = (common::job("drain_test"), common::job("drain_test"));
+    let sub = JobSubmit {
+        jobs: vec![a.clone(), b.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(&mut console, EV_JOB_SUBMIT, &sub).await;
+    let running = common::request(&mut coord, &mut w1, "w1", 1).await;
+    assert_eq!(running.len(), 1);
+
+    let msg = drain(&mut coord, &mut console, false).await;
+    assert!(msg.contains("1 job(s) still running"), "{}", msg);
+    assert!(common::request(&mut coord, &mut w1, "w1", 1)
+        .await
+        .is_empty());
+
+    // The running job still reports in
+    let rep = JobCompleteReport {
+        job_id: running[0],
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: None,
+        error: None,
+    };
+    common::send(&mut w1, MSG_JOB_COMPLETE, &rep).await;
+    assert!(common::request(&mut coord, &mut w1, "w1", 1)
+        .await
+        .is_empty());
+    let done = coord.jobs().find(|j| j.id == running[0]).unwrap();
+    assert_eq!(done.status, JobStatus::Completed);
+
+    // Drains survive a coordinator restart until undone
+    coord.checkpoint_now().unwrap();
+    coord
+        .crash_restart(Box::new(net.coordinator()))
+        .await
+        .unwrap();
+    assert!(common::request(&mut coord, &mut w1, "w1", 1)
+        .await
+        .is_empty());
+    assert_eq!(
+        drain(&mut coord, &mut console, true).await,
+        "w1 takes new work again"
+    );
+    assert_eq!(common::request(&mut coord, &mut w1, "w1", 1).await.len(), 1);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::marketplace::{MarketplaceCoordinator, WorkRequest, MSG_WORK_REQUEST};
+use unifiedlab::report::workers_from_store;
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use uuid::Uuid;
+
+mod common;
+
+async fn heartbeat(t: &mut MemTransport, id: &str, totals: Option<(usize, usize)>) {
+    let req = WorkRequest {
+        available_gpus: 1,
+        tags: vec!["muscle".into(), "gpu".into()],
+        hostname: Some(format!("{}.cluster", id)),
+        total_cores: totals.map(|t| t.0),
+        total_gpus: totals.map(|t| t.1),
+        ..common::work_request(id, 6)
+    };
+    t.send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req).unwrap())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_workers_carry_capacity_and_last_seen_and_go_stale() {
+    let root = std::env::temp_dir().join(format!("ulab_workers_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+    let mut w2 = net.worker(Some("w2"));
+
+    // w2 is an older worker that does not report its totals
+    heartbeat(&mut w2, "w2", None).await;
+    coord.tick().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    heartbeat(&mut w1, "w1", Some((8, 2))).await;
+    coord.tick().await.unwrap();
+    coord.checkpoint_now().unwrap();
+
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let workers = workers_from_store(&store, Duration::from_millis(200), now_ms).unwrap();
+    assert_eq!(workers.len(), 2);
+
+    let w1 = workers.iter().find(|w| w.worker_id == "w1").unwrap();
+    assert_eq!((w1.free_cores, w1.total_cores), (6, Some(8)));
+    assert_eq!((w1.free_gpus, w1.total_gpus), (1, Some(2)));
+    assert_eq!(w1.tags, ["gpu", "muscle"]);
+    assert_eq!(w1.hostname.as_deref(), Some("w1.cluster"));
+    assert!(
+        now_ms - w1.last_seen_ms < 200,
+        "{}",
+        now_ms - w1.last_seen_ms
+    );
+    assert!(!w1.silent);
+
+    // Heard from once, 300ms ago: past a 200ms timeout
+    let w2 = workers.iter().find(|w| w.worker_id == "w2").unwrap();
+    assert_eq!(w2.total_cores, None);
+    assert!(now_ms - w2.last_seen_ms >= 300);
+    assert!(w2.silent);
+
+    std::fs::remove_dir_all(&root).ok();
+}
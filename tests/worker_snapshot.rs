@@ -0,0 +1,57 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::marketplace::{MarketplaceCoordinator, WorkRequest, MSG_WORK_REQUEST};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+
+mod common;
+
+#[tokio::test]
+async fn test_worker_tags_and_gpus_survive_a_restart() {
+    let root = std::env::temp_dir().join(format!("ulab_wsnap_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let db = root.join("checkpoint.db");
+    let store = CheckpointStore::open(&db).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut worker = net.worker(Some("node07_r1"));
+    let req = WorkRequest {
+        available_gpus: 2,
+        tags: vec!["muscle".into(), "gpu".into()],
+        hostname: Some("node07".into()),
+        ..common::work_request("node07_r1", 16)
+    };
+    worker
+        .send_to_coordinator(MSG_WORK_REQUEST, serde_json::to_value(&req).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    coord.checkpoint_now().unwrap();
+    drop(coord);
+
+    // What a restarted TUI or console sees before anyone reports in again
+    let store = CheckpointStore::open(&db).unwrap();
+    let workers = store.get_active_workers().unwrap();
+    assert_eq!(workers.len(), 1);
+    let w = &workers[0];
+    assert_eq!((w.cores, w.gpus), (16, 2));
+    assert_eq!(w.tags, ["gpu", "muscle"]);
+    assert_eq!(w.hostname.as_deref(), Some("node07"));
+    assert!(w.has_tags(&["gpu".into()]));
+    assert!(!w.has_tags(&["brain".into()]));
+
+    // Rows written before these fields existed still read
+    let conn = rusqlite::Connection::open(&db).unwrap();
+    conn.execute(
+        "UPDATE workers SET state_json = ?1",
+        [r#"{"worker_id":"node07_r1","cores":4,"tasks":0,"last_seen_ms":0}"#],
+    )
+    .unwrap();
+    drop(conn);
+    let old = &store.get_active_workers().unwrap()[0];
+    assert!(old.tags.is_empty() && old.gpus == 0 && old.hostname.is_none());
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,185 @@
+use unifiedlab::dsl::lint::{lint_file, Diagnostic, Severity};
+
+fn write(name: &str, text: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("ulab_lint_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+fn find<'a>(found: &'a [Diagnostic], needle: &str) -> &'a Diagnostic {
+    found
+        .iter()
+        .find(|d| d.message.contains(needle))
+        .unwrap_or_else(|| panic!("no diagnostic about '{}' in {:#?}", needle, found))
+}
+
+#[test]
+fn test_lint_clean_workflow_has_no_diagnostics() {
+    let path = write(
+        "clean.yaml",
+        r#"
+version: 1
+metadata: { name: clean }
+nodes:
+  - id: relax
+    type: compute
+    engine: { kind: vasp }
+    resources: { cores: 8 }
+    outputs:
+      - { name: energy, type: { kind: float } }
+  - id: check
+    type: switch
+    condition: { energy_below: -5.0 }
+    inputs:
+      - { name: e, type: { kind: float }, source: relax.outputs.energy }
+  - id: refine
+    type: compute
+    engine: { kind: janus }
+edges:
+  - { from: relax, to: check }
+  - { from: check, to: refine }
+"#,
+    );
+    let found = lint_file(&path, &Default::default()).unwrap();
+    assert!(found.is_empty(), "{:#?}", found);
+}
+
+#[test]
+fn test_lint_reports_every_problem_at_its_line() {
+    let path = write(
+        "messy.yaml",
+        r#"version: 1
+metadata: { name: messy }
+nodes:
+  - id: relax
+    type: compute
+    engine: { kind: vasp }
+    outputs:
+      - { name: energy, type: { kind: float } }
+  - id: lonely
+    type: compute
+  - id: use
+    type: compute
+    retry: { max_attempts: 0 }
+    inputs:
+      - { name: e, type: { kind: float }, source: relax.outputs.forces }
+      - { name: f, type: { kind: float }, source: ghost.outputs.energy }
+edges:
+  - { from: relax, to: use }
+  - { from: relax, to: nowhere }
+macros:
+  - id: scan
+    type: fanout
+    anchor: relax
+    params: { width: 0, engine: vsap, spacing: 2 }
+"#,
+    );
+    let found = lint_file(&path, &Default::default()).unwrap();
+
+    // Validation errors are all reported, not just the first.
+    let retry = find(&found, "retry.max_attempts");
+    assert_eq!(retry.severity, Severity::Error);
+    assert_eq!((retry.line, retry.column), (13, 14));
+    let edge = find(&found, "unknown node: 'nowhere'");
+    assert_eq!(edge.severity, Severity::Error);
+    assert_eq!(edge.line, 19);
+
+    let by_line = |needle: &str| {
+        let d = find(&found, needle);
+        assert_eq!(d.severity, Severity::Warning, "{}", d);
+        d.line
+    };
+    assert_eq!(by_line("'vsap'"), 24);
+    assert_eq!(by_line("width is 0"), 24);
+    assert_eq!(by_line("ignores param 'spacing'"), 24);
+
+    // Sorted by position.
+    assert!(found
+        .windows(2)
+        .all(|w| (w[0].line, w[0].column) <= (w[1].line, w[1].column)));
+}
+
+#[test]
+fn test_lint_warns_about_graph_and_resource_problems() {
+    let path = write(
+        "graph.yaml",
+        r#"version: 1
+metadata: { name: graph }
+nodes:
+  - id: relax
+    type: compute
+    engine: { kind: cp2k }
+    outputs:
+      - { name: energy, type: { kind: float } }
+  - id: lonely
+    type: compute
+  - id: use
+    type: compute
+    inputs:
+      - { name: e, type: { kind: float }, source: relax.outputs.forces }
+      - { name: f, type: { kind: float }, source: ghost.outputs.energy }
+      - { name: g, type: { kind: float }, source: relax.energy }
+  - id: gate
+    type: switch
+    condition: { expression: "energy < 0" }
+edges:
+  - { from: relax, to: use }
+  - { from: use, to: gate }
+"#,
+    );
+    let found = lint_file(&path, &Default::default()).unwrap();
+    assert!(
+        found.iter().all(|d| d.severity == Severity::Warning),
+        "{:#?}",
+        found
+    );
+
+    let line = |needle: &str| find(&found, needle).line;
+    assert_eq!(line("runs cp2k with no resources"), 6);
+    assert_eq!(line("'lonely' is not connected"), 9);
+    assert_eq!(line("output 'forces'"), 14);
+    assert_eq!(line("unknown node 'ghost'"), 15);
+    assert_eq!(line("is not <node>.outputs.<port>"), 16);
+    assert_eq!(line("switch 'gate' has nothing downstream"), 17);
+}
+
+#[test]
+fn test_lint_locates_parse_and_cycle_errors() {
+    let path = write(
+        "typo.yaml",
+        "version: 1\nmetadata: { name: typo }\nnodes:\n  - id: a\n    type: compute\n    retry: { max_attempts: lots }\n",
+    );
+    let found = lint_file(&path, &Default::default()).unwrap();
+    assert_eq!(found.len(), 1, "{:#?}", found);
+    assert_eq!(found[0].severity, Severity::Error);
+    assert_eq!(found[0].line, 6);
+
+    let path = write("bad.yaml", "version: 1\nnodes: [a, b\n");
+    let found = lint_file(&path, &Default::default()).unwrap();
+    assert_eq!(found.len(), 1, "{:#?}", found);
+    assert!(found[0].message.starts_with("invalid YAML"));
+
+    let path = write(
+        "cycle.yaml",
+        r#"version: 1
+metadata: { name: cycle }
+nodes:
+  - { id: start, type: compute }
+  - { id: a, type: compute }
+  - { id: b, type: compute }
+edges:
+  - { from: start, to: a }
+  - { from: a, to: b }
+  - { from: b, to: a }
+"#,
+    );
+    let found = lint_file(&path, &Default::default()).unwrap();
+    let errors: Vec<_> = found
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| d.line)
+        .collect();
+    assert_eq!(errors, vec![5, 6], "{:#?}", found);
+}
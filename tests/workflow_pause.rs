@@ -0,0 +1,138 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    ControlAck, ControlCommand, ControlRequest, JobCompleteReport, JobSubmit,
+    MarketplaceCoordinator, Routing, WorkGrant, EV_CONTROL_ACK, EV_JOB_SUBMIT, EV_WORK_GRANT,
+    FLOW_WORKFLOW, META_PAUSED, META_PAUSED_WORKFLOWS, MSG_CONTROL, MSG_JOB_COMPLETE,
+    MSG_WORK_REQUEST,
+};
+use unifiedlab::transport::mem::{MemNetwork, MemTransport};
+use unifiedlab::transport::Transport;
+use unifiedlab::Job;
+use uuid::Uuid;
+
+mod common;
+
+fn job(workflow: &str) -> Job {
+    let mut job = common::job("pause_test");
+    job.flow_context
+        .insert(FLOW_WORKFLOW.into(), workflow.to_string().into());
+    job
+}
+
+async fn heartbeat(t: &mut MemTransport) {
+    let req = common::work_request("w1", 1);
+    common::send(t, MSG_WORK_REQUEST, &req).await;
+}
+
+/// Applies `command` and returns the Coordinator's answer.
+async fn control(
+    coord: &mut MarketplaceCoordinator,
+    console: &mut MemTransport,
+    command: ControlCommand,
+) -> ControlAck {
+    let req = ControlRequest {
+        request_id: Uuid::new_v4(),
+        command,
+    };
+    common::send(console, MSG_CONTROL, &req).await;
+    coord.tick().await.unwrap();
+    console
+        .recv_broadcasts()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.record.kind == EV_CONTROL_ACK)
+        .map(|e| serde_json::from_value::<ControlAck>(e.record.payload).unwrap())
+        .find(|a| a.request_id == req.request_id)
+        .unwrap()
+}
+
+/// Jobs granted to w1 after one heartbeat.
+async fn grants(coord: &mut MarketplaceCoordinator, w1: &mut MemTransport) -> Vec<Uuid> {
+    heartbeat(w1).await;
+    coord.tick().await.unwrap();
+    let mut out = vec![];
+    for env in w1.recv_broadcasts().await.unwrap() {
+        if env.record.kind == EV_WORK_GRANT {
+            let g: WorkGrant = serde_json::from_value(env.record.payload).unwrap();
+            out.extend(g.jobs.iter().map(|j| j.id));
+        }
+    }
+    out
+}
+
+#[tokio::test]
+async fn test_paused_workflow_gets_no_grants_while_completions_go_on() {
+    let root = std::env::temp_dir().join(format!("ulab_pause_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut w1 = net.worker(Some("w1"));
+    let mut console = net.worker(None);
+
+    let (a1, a2, b1) = (job("relax"), job("relax"), job("screening"));
+    let sub = JobSubmit {
+        jobs: vec![a1.clone(), a2.clone(), b1.clone()],
+        deps: vec![],
+        routing: Routing::default(),
+        txn: None,
+    };
+    common::send(&mut console, EV_JOB_SUBMIT, &sub).await;
+    coord.tick().await.unwrap();
+
+    let pause_relax = ControlCommand::PauseWorkflow {
+        workflow: "relax".into(),
+    };
+    let ack = control(&mut coord, &mut console, pause_relax).await;
+    assert_eq!(
+        ack.message,
+        "Workflow 'relax' paused (0 running job(s) continue)"
+    );
+    assert_eq!(grants(&mut coord, &mut w1).await, [b1.id]);
+
+    // Paused outright, a report is still applied but nothing is granted
+    let ack = control(&mut coord, &mut console, ControlCommand::Pause).await;
+    assert!(ack.ok, "{}", ack.message);
+    let rep = JobCompleteReport {
+        job_id: b1.id,
+        worker_id: "w1".into(),
+        status: JobStatus::Completed,
+        result: None,
+        error: None,
+    };
+    common::send(&mut w1, MSG_JOB_COMPLETE, &rep).await;
+    assert!(grants(&mut coord, &mut w1).await.is_empty());
+    let done = coord.jobs().find(|j| j.id == b1.id).unwrap();
+    assert_eq!(done.status, JobStatus::Completed);
+
+    // Resuming the Coordinator leaves the workflow paused
+    control(&mut coord, &mut console, ControlCommand::Resume).await;
+    assert!(grants(&mut coord, &mut w1).await.is_empty());
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    assert_eq!(
+        store.get_meta(META_PAUSED).unwrap().as_deref(),
+        Some("false")
+    );
+    assert_eq!(
+        store.get_meta(META_PAUSED_WORKFLOWS).unwrap().as_deref(),
+        Some(r#"["relax"]"#)
+    );
+
+    let resume_relax = ControlCommand::ResumeWorkflow {
+        workflow: "relax".into(),
+    };
+    let ack = control(&mut coord, &mut console, resume_relax.clone()).await;
+    assert!(ack.ok, "{}", ack.message);
+    let granted = grants(&mut coord, &mut w1).await;
+    assert!(!granted.is_empty() && granted.iter().all(|id| [a1.id, a2.id].contains(id)));
+
+    let ack = control(&mut coord, &mut console, resume_relax).await;
+    assert!(!ack.ok);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,136 @@
+use serde_json::Value;
+use unifiedlab::dsl::{self, schema::json_schema};
+
+fn write(name: &str, text: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("ulab_schema_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+/// Property names of `$defs/<def>`.
+fn properties<'a>(schema: &'a Value, def: &str) -> Vec<&'a str> {
+    schema["$defs"][def]["properties"]
+        .as_object()
+        .unwrap_or_else(|| panic!("no properties for {}", def))
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+#[test]
+fn test_schema_covers_every_field_the_parser_reads() {
+    let schema = json_schema();
+    assert_eq!(
+        schema["$schema"],
+        "https://json-schema.org/draft/2020-12/schema"
+    );
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_str().unwrap())
+        .collect();
+    assert_eq!(required, ["version", "metadata", "nodes"]);
+
+    // Every key of a fully populated workflow has a place in the schema
+    let path = write(
+        "full.yaml",
+        r#"
+version: 2
+metadata: { name: full, description: everything }
+vars: { cores: 4 }
+environment: { kind: docker_image, image: "lab/vasp:6.4" }
+types:
+  energies: { kind: array, of: { kind: float } }
+nodes:
+  - id: relax
+    type: compute
+    title: Relax
+    engine: { kind: vasp }
+    params: { ENCUT: 520 }
+    resources: { cores: "${vars.cores}", memory_mb: 4000, same_node_as: check }
+    environment: { kind: modules, modules: [vasp/6.4] }
+    outputs: [{ name: energy, type: { kind: float } }]
+    cache: true
+    retry: { max_attempts: 2, backoff_s: 10, retry_on: [MPI_ABORT] }
+  - id: check
+    type: switch
+    condition: { energy_below: -5.0 }
+    inputs: [{ name: e, type: { kind: float }, source: relax.outputs.energy }]
+edges:
+  - { from: relax, to: check, when: "energy < 0", map: { e0: energy } }
+macros:
+  - { id: sweep, type: chain, anchor: check, params: { length: 2 } }
+outputs: [{ name: energy, type: { kind: float }, nodes: [relax] }]
+"#,
+    );
+    let spec = dsl::load_yaml(&path, &Default::default()).unwrap();
+    let wire = serde_json::to_value(&spec).unwrap();
+
+    let top: Vec<&str> = schema["properties"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    for (value, allowed) in [
+        (&wire, top),
+        (&wire["nodes"][0], properties(&schema, "NodeSpec")),
+        (
+            &wire["nodes"][0]["resources"],
+            properties(&schema, "ResourceSpec"),
+        ),
+        (&wire["nodes"][0]["retry"], properties(&schema, "RetrySpec")),
+        (&wire["edges"][0], properties(&schema, "EdgeSpec")),
+        (&wire["macros"][0], properties(&schema, "MacroSpec")),
+    ] {
+        for key in value.as_object().unwrap().keys() {
+            assert!(
+                allowed.contains(&key.as_str()),
+                "{} not in {:?}",
+                key,
+                allowed
+            );
+        }
+    }
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_schema_allows_var_refs_and_names_edge_kinds() {
+    let schema = json_schema();
+
+    // A number may be written as a whole ${vars.x} reference
+    let cores = &schema["$defs"]["ResourceSpec"]["properties"]["cores"];
+    assert_eq!(cores["default"], 1);
+    let branches = cores["anyOf"].as_array().unwrap();
+    assert_eq!(branches[0]["type"], "integer");
+    assert_eq!(branches[1]["pattern"], r"^\$\{vars\.[^}]+\}$");
+    // Strings are left alone
+    assert_eq!(
+        schema["$defs"]["NodeSpec"]["properties"]["id"]["type"],
+        "string"
+    );
+
+    // Edge kinds are plain names; a dataflow edge says so with its `map`
+    assert_eq!(
+        schema["$defs"]["EdgeKind"]["enum"],
+        serde_json::json!(["hard", "soft"])
+    );
+    assert_eq!(
+        schema["$defs"]["EdgeSpec"]["properties"]["map"]["additionalProperties"]["type"],
+        "string"
+    );
+
+    // Engines and environments are told apart by `kind`
+    let engines: Vec<&str> = schema["$defs"]["EngineSpec"]["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v["properties"]["kind"]["const"].as_str().unwrap())
+        .collect();
+    assert_eq!(engines, ["janus", "gulp", "vasp", "cp2k", "agent"]);
+}
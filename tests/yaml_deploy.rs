@@ -0,0 +1,85 @@
+use unifiedlab::checkpoint::CheckpointStore;
+use unifiedlab::core::JobStatus;
+use unifiedlab::marketplace::{
+    MarketplaceCoordinator, Routing, EV_JOB_SUBMIT, FLOW_PRIORITY, FLOW_WORKFLOW,
+};
+use unifiedlab::transport::mem::MemNetwork;
+use unifiedlab::transport::Transport;
+use unifiedlab::workflow::blueprint::{build_submission, load_blueprint};
+use unifiedlab::NodeType;
+
+#[tokio::test]
+async fn test_yaml_workflow_deploys_as_one_submission() {
+    let root = std::env::temp_dir().join(format!("ulab_yamldeploy_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let file = root.join("screening.yml");
+    std::fs::write(
+        &file,
+        r#"
+version: 1
+metadata: { name: screening }
+nodes:
+  - id: relax
+    type: compute
+    engine: { kind: vasp }
+  - id: collect
+    type: aggregator
+    engine: { kind: janus }
+    params: { arch: chgnet }
+  - id: report
+    type: compute
+    engine: { kind: gulp }
+edges:
+  - { from: relax, to: collect }
+  - { from: relax, to: report, kind: soft }
+"#,
+    )
+    .unwrap();
+    let file = file.to_str().unwrap();
+
+    let graph = load_blueprint(file, &Default::default()).unwrap();
+    let submit = build_submission(&graph, false, Routing::default()).unwrap();
+    assert_eq!(submit.jobs.len(), 3);
+    let job = |id: &str| {
+        submit
+            .jobs
+            .iter()
+            .find(|j| j.structure.source == id)
+            .unwrap()
+    };
+    let (relax, collect) = (job("relax"), job("collect"));
+    for j in &submit.jobs {
+        assert_eq!(j.status, JobStatus::Pending);
+        assert_eq!(j.flow_context[FLOW_WORKFLOW], "screening");
+        assert!(j.flow_context.contains_key(FLOW_PRIORITY));
+    }
+    let node_type: NodeType =
+        serde_json::from_value(collect.flow_context["node_type"].clone()).unwrap();
+    assert_eq!(node_type, NodeType::Aggregator);
+    // The soft edge orders nothing
+    assert_eq!(submit.deps, [(relax.id, collect.id)]);
+
+    // --set is for YAML only
+    let mut vars = unifiedlab::dsl::Vars::new();
+    vars.insert("temperature".into(), 600.into());
+    let err = load_blueprint("chain_5_janus", &vars).err().unwrap();
+    assert_eq!(err.to_string(), "--set applies to YAML workflows only");
+
+    // The Coordinator rebuilds the DAG from it
+    let store = CheckpointStore::open(root.join("checkpoint.db")).unwrap();
+    let net = MemNetwork::new();
+    let mut coord = MarketplaceCoordinator::open(Box::new(net.coordinator()), store)
+        .await
+        .unwrap();
+    let mut deployer = net.worker(None);
+    deployer
+        .send_to_coordinator(EV_JOB_SUBMIT, serde_json::to_value(&submit).unwrap())
+        .await
+        .unwrap();
+    coord.tick().await.unwrap();
+    let status = |id| coord.jobs().find(|j| j.id == id).unwrap().status.clone();
+    assert_eq!(status(collect.id), JobStatus::Blocked);
+    assert_ne!(status(relax.id), JobStatus::Blocked);
+
+    std::fs::remove_dir_all(&root).ok();
+}
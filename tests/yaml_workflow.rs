@@ -0,0 +1,969 @@
+use unifiedlab::core::{Engine, JobConfig};
+use unifiedlab::dsl;
+use unifiedlab::workflow::yaml::YamlLoader;
+use unifiedlab::workflow::LogicCondition;
+use unifiedlab::NodeType;
+
+fn write(name: &str, text: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("ulab_yaml_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn test_yaml_workflow_becomes_a_graph() {
+    let path = write(
+        "campaign.yaml",
+        r#"
+version: 1
+metadata: { name: campaign }
+nodes:
+  - id: propose
+    type: generator
+    engine: { kind: agent, script: agents/ga.py, strategy: genetic }
+  - id: relax
+    type: compute
+    engine: { kind: vasp }
+    resources: { cores: 8, time_limit_min: 120 }
+  - id: collect
+    type: aggregator
+    engine: { kind: janus }
+    params: { arch: chgnet }
+edges:
+  - { from: propose, to: relax }
+  - { from: relax, to: collect, when: "energy < -5.0" }
+  - { from: propose, to: collect, kind: soft }
+macros:
+  - { id: extra, type: fanout, anchor: relax, params: { width: 2, engine: gulp } }
+"#,
+    );
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    assert_eq!(graph.graph.node_count(), 5);
+    // The soft edge orders nothing
+    assert_eq!(graph.graph.edge_count(), 4);
+
+    let node = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+    };
+    let propose = node("propose");
+    assert_eq!(
+        propose.node_type,
+        NodeType::Generator {
+            strategy: "genetic".into()
+        }
+    );
+    match &propose.job.config.engine {
+        Engine::Agent { script_path, .. } => {
+            assert_eq!(
+                *script_path,
+                path.parent()
+                    .unwrap()
+                    .join("agents/ga.py")
+                    .to_string_lossy()
+            )
+        }
+        other => panic!("unexpected engine {:?}", other),
+    }
+    assert!(matches!(
+        node("relax").job.config.engine,
+        Engine::Vasp { mpi_ranks: 8, .. }
+    ));
+    assert_eq!(node("relax").job.resources.time_limit_min, 120);
+    assert_eq!(node("collect").job.config.engine.code(), "janus:chgnet");
+    assert_eq!(
+        node("collect").job.conditions[&node("relax").job.id].to_string(),
+        "energy < -5"
+    );
+    assert!(matches!(
+        node("extra_2").job.config.engine,
+        Engine::Gulp { .. }
+    ));
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_yaml_cycle_is_rejected() {
+    let path = write(
+        "loop.yaml",
+        r#"
+version: 1
+metadata: { name: loop }
+nodes:
+  - { id: a, type: compute }
+  - { id: b, type: compute }
+  - { id: c, type: compute }
+edges:
+  - { from: a, to: b }
+  - { from: b, to: c }
+  - { from: c, to: b }
+"#,
+    );
+    let err = YamlLoader::load_from_file(&path, &Default::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("cycle through: b, c"), "{}", err);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_yaml_subworkflow_is_flattened_through_its_ports() {
+    let path = write(
+        "campaign.yaml",
+        r#"
+version: 1
+metadata: { name: campaign }
+nodes:
+  - id: prep
+    type: compute
+  - id: screen
+    type: subworkflow
+    workflow: screening.yaml
+    params: { cores: 8 }
+    inputs: [{ name: structure, type: { kind: structure } }]
+    outputs: [{ name: energy, type: { kind: float } }]
+  - id: collect
+    type: aggregator
+edges:
+  - { from: prep, to: screen.inputs.structure }
+  - { from: screen.outputs.energy, to: collect, when: "energy < -5.0" }
+"#,
+    );
+    let dir = path.parent().unwrap();
+    std::fs::write(
+        dir.join("screening.yaml"),
+        r#"
+version: 1
+metadata: { name: screening }
+vars: { cores: 4 }
+inputs: [{ name: structure, type: { kind: structure }, nodes: [cheap] }]
+outputs: [{ name: energy, type: { kind: float }, nodes: [accurate] }]
+nodes:
+  - id: cheap
+    type: compute
+  - id: accurate
+    type: compute
+    engine: { kind: vasp }
+    resources: { cores: "${vars.cores}", same_node_as: cheap }
+edges:
+  - { from: cheap, to: accurate }
+"#,
+    )
+    .unwrap();
+
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    assert_eq!(graph.graph.node_count(), 4);
+    assert_eq!(graph.graph.edge_count(), 3);
+    let node = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+    };
+    let parents = |id: &str| -> Vec<String> {
+        let idx = graph
+            .graph
+            .node_indices()
+            .find(|i| graph.graph[*i].job.structure.source == id)
+            .unwrap();
+        graph
+            .graph
+            .neighbors_directed(idx, petgraph::Direction::Incoming)
+            .map(|p| graph.graph[p].job.structure.source.clone())
+            .collect()
+    };
+    assert_eq!(parents("screen/cheap"), ["prep"]);
+    assert_eq!(parents("screen/accurate"), ["screen/cheap"]);
+    let (cheap, accurate) = (node("screen/cheap"), node("screen/accurate"));
+    assert_eq!(accurate.job.resources.same_node_as, Some(cheap.job.id));
+    // The subworkflow node's params set the child's vars
+    assert_eq!(accurate.job.resources.cores, 8);
+    assert_eq!(
+        node("collect").job.conditions[&accurate.job.id].to_string(),
+        "energy < -5"
+    );
+
+    // A port the child does not have, or a child that includes its parent
+    std::fs::write(
+        &path,
+        r#"
+version: 1
+metadata: { name: campaign }
+nodes:
+  - { id: screen, type: subworkflow, workflow: screening.yaml }
+  - { id: collect, type: aggregator }
+edges:
+  - { from: screen.outputs.forces, to: collect }
+"#,
+    )
+    .unwrap();
+    let err = YamlLoader::load_from_file(&path, &Default::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("no outputs port 'forces'"), "{}", err);
+
+    std::fs::write(
+        dir.join("screening.yaml"),
+        r#"
+version: 1
+metadata: { name: screening }
+nodes:
+  - { id: again, type: subworkflow, workflow: campaign.yaml }
+"#,
+    )
+    .unwrap();
+    let err = YamlLoader::load_from_file(&path, &Default::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("which includes it"), "{}", err);
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_yaml_vars_are_substituted_and_overridden() {
+    let path = write(
+        "md.yaml",
+        r#"
+version: 1
+metadata: { name: "md-${vars.model}" }
+vars: { temperature: 300, model: mace_mp }
+nodes:
+  - id: md
+    type: compute
+    params: { arch: "${vars.model}", temperature: "${vars.temperature}", tag: "T${vars.temperature}" }
+"#,
+    );
+    let params = |vars: &unifiedlab::dsl::Vars| {
+        let graph = YamlLoader::load_from_file(&path, vars).unwrap().graph;
+        let node = graph.graph.node_weights().next().unwrap();
+        (
+            node.job.config.engine.code(),
+            node.job.config.params.clone(),
+        )
+    };
+
+    let (code, p) = params(&Default::default());
+    assert_eq!(code, "janus:mace_mp");
+    assert_eq!(p["temperature"], 300);
+    assert_eq!(p["tag"], "T300");
+
+    let vars = [
+        ("temperature".to_string(), serde_yaml::Value::from(600)),
+        ("model".to_string(), serde_yaml::Value::from("chgnet")),
+    ]
+    .into();
+    let (code, p) = params(&vars);
+    assert_eq!(code, "janus:chgnet");
+    assert_eq!(p["temperature"], 600);
+    assert_eq!(p["tag"], "T600");
+
+    // A var the workflow does not declare, set or referenced
+    let typo = [("temprature".to_string(), serde_yaml::Value::from(600))].into();
+    let err = YamlLoader::load_from_file(&path, &typo)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("cannot set var 'temprature'"), "{}", err);
+    std::fs::write(
+        &path,
+        "version: 1\nmetadata: { name: md }\nnodes: [{ id: md, type: compute, params: { t: \"${vars.t}\" } }]\n",
+    )
+    .unwrap();
+    let err = YamlLoader::load_from_file(&path, &Default::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("unknown var 't'"), "{}", err);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_yaml_switch_conditions_become_logic_conditions() {
+    let path = write(
+        "gates.yaml",
+        r#"
+version: 1
+metadata: { name: gates }
+nodes:
+  - { id: deep, type: switch, condition: { energy_below: -5.0 } }
+  - { id: gap, type: switch, condition: { band_gap_above: 1.5 } }
+  - { id: stiff, type: switch, condition: { expression: "stress.0.0 > 2.5" } }
+  - { id: judge, type: switch, condition: { script: scripts/judge.py } }
+  - { id: open, type: switch }
+"#,
+    );
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    let condition = |id: &str| match &graph
+        .graph
+        .node_weights()
+        .find(|n| n.job.structure.source == id)
+        .unwrap()
+        .node_type
+    {
+        NodeType::Switch { condition } => condition.clone(),
+        other => panic!("unexpected node type {:?}", other),
+    };
+    assert_eq!(condition("deep"), LogicCondition::EnergyBelow(-5.0));
+    assert_eq!(condition("gap"), LogicCondition::BandGapAbove(1.5));
+    assert_eq!(
+        condition("stiff"),
+        LogicCondition::Expression("stress.0.0 > 2.5".parse().unwrap())
+    );
+    assert_eq!(
+        condition("judge"),
+        LogicCondition::ExternalScript(
+            path.parent()
+                .unwrap()
+                .join("scripts/judge.py")
+                .to_string_lossy()
+                .into_owned()
+        )
+    );
+    assert_eq!(condition("open"), LogicCondition::AlwaysTrue);
+
+    // An expression on a stress component decides the branch
+    let LogicCondition::Expression(pred) = condition("stiff") else {
+        unreachable!()
+    };
+    let stress = serde_json::json!({ "stress": [[3.0, 0.0, 0.0]] });
+    assert!(pred.holds_for(&stress));
+
+    for (bad, msg) in [
+        (
+            "{ id: s, type: switch, condition: { energy_below: -5.0, band_gap_above: 1.0 } }",
+            "set exactly one of",
+        ),
+        (
+            "{ id: s, type: switch, condition: { expression: \"energy <\" } }",
+            "expected '<field> <op> <number>'",
+        ),
+        (
+            "{ id: s, type: compute, condition: { energy_below: -5.0 } }",
+            "only switch nodes take a condition",
+        ),
+    ] {
+        std::fs::write(
+            &path,
+            format!(
+                "version: 1\nmetadata: {{ name: gates }}\nnodes: [{}]\n",
+                bad
+            ),
+        )
+        .unwrap();
+        let err = YamlLoader::load_from_file(&path, &Default::default())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains(msg), "{}", err);
+    }
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_yaml_port_edges_are_type_checked() {
+    let workflow = |threshold: &str, forces: &str| {
+        format!(
+            r#"
+version: 1
+metadata: {{ name: typed }}
+types:
+  energies: {{ kind: array, of: {{ kind: float }} }}
+nodes:
+  - id: relax
+    type: compute
+    outputs:
+      - {{ name: energy, type: {{ kind: float }} }}
+      - {{ name: steps, type: {{ kind: int }} }}
+      - {{ name: forces, type: {{ kind: array, of: {{ kind: int }} }} }}
+  - id: screen
+    type: switch
+    inputs:
+      - {{ name: threshold, type: {threshold} }}
+      - {{ name: scale, type: {{ kind: float }}, source: relax.outputs.steps }}
+      - {{ name: forces, type: {forces}, source: relax.outputs.forces }}
+edges:
+  - {{ from: relax.outputs.energy, to: screen.inputs.threshold }}
+"#
+        )
+    };
+    let load = |name: &str, text: String| {
+        let path = write(name, &text);
+        let result = YamlLoader::load_from_file(&path, &Default::default());
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+        result
+    };
+
+    // Named types resolve; ints fit floats, also inside arrays
+    let graph = load("ok.yaml", workflow("{ kind: float }", "energies"))
+        .unwrap()
+        .graph;
+    assert_eq!(graph.graph.node_count(), 2);
+    assert_eq!(graph.graph.edge_count(), 1);
+
+    let err = load("edge.yaml", workflow("{ kind: int }", "energies"))
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        err.contains("output 'energy' is float but input 'threshold' takes int"),
+        "{}",
+        err
+    );
+
+    let err = load(
+        "source.yaml",
+        workflow("{ kind: float }", "{ kind: array, of: { kind: bool } }"),
+    )
+    .err()
+    .unwrap()
+    .to_string();
+    assert!(
+        err.contains("output 'forces' is array of int but input 'forces' takes array of bool"),
+        "{}",
+        err
+    );
+
+    // A port the node does not declare is named as such
+    let err = load(
+        "port.yaml",
+        workflow("{ kind: float }", "energies")
+            .replace("to: screen.inputs.threshold", "to: screen.inputs.cutoff"),
+    )
+    .err()
+    .unwrap()
+    .to_string();
+    assert!(
+        err.contains("inputs port 'cutoff' of node 'screen', which it does not declare"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_yaml_map_reduce_maps_items_and_gathers_results() {
+    let path = write(
+        "relax_all.yaml",
+        r#"
+version: 1
+metadata: { name: relax-all }
+vars: { structures: [a.cif, b.cif, c.cif] }
+nodes:
+  - id: prepare
+    type: compute
+macros:
+  - id: relax
+    type: map_reduce
+    anchor: prepare
+    params:
+      items: "${vars.structures}"
+      as: structure
+      engine: gulp
+      reduce: agents/tabulate.py
+"#,
+    );
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    // prepare -> relax_1..3 -> relax_reduce
+    assert_eq!(graph.graph.node_count(), 5);
+    assert_eq!(graph.graph.edge_count(), 6);
+
+    let node = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+    };
+    assert_eq!(node("relax_2").job.config.params["structure"], "b.cif");
+    assert!(matches!(
+        node("relax_2").job.config.engine,
+        Engine::Gulp { .. }
+    ));
+
+    let reduce = node("relax_reduce");
+    assert_eq!(reduce.node_type, NodeType::Aggregator);
+    assert_eq!(
+        reduce.job.config.params["items"],
+        serde_json::json!(["a.cif", "b.cif", "c.cif"])
+    );
+    match &reduce.job.config.engine {
+        Engine::Agent { script_path, .. } => assert!(script_path.ends_with("agents/tabulate.py")),
+        other => panic!("unexpected engine {:?}", other),
+    }
+    // Each mapped result's energy reaches the reducer under its own name
+    for (k, id) in ["relax_1", "relax_2", "relax_3"].iter().enumerate() {
+        let map = &reduce.job.dataflow[&node(id).job.id];
+        assert_eq!(map[&format!("energy_{}", k + 1)], "energy");
+    }
+
+    // Without a list there is nothing to map
+    std::fs::write(
+        &path,
+        "version: 1\nmetadata: { name: m }\nnodes: [{ id: a, type: compute }]\nmacros: [{ id: m, type: map_reduce, params: { items: a.cif } }]\n",
+    )
+    .unwrap();
+    let err = YamlLoader::load_from_file(&path, &Default::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("requires a non-empty 'items' list"), "{}", err);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_yaml_convergence_loop_becomes_a_bounded_generator() {
+    let path = write(
+        "search.yaml",
+        r#"
+version: 1
+metadata: { name: search }
+nodes:
+  - id: seed
+    type: compute
+macros:
+  - id: search
+    type: convergence_loop
+    anchor: seed
+    params:
+      script: agents/propose.py
+      strategy: bayes
+      engine: gulp
+      compute: { potential: buck }
+      max_iters: 4
+      until: "energy.min < -5.0"
+"#,
+    );
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    assert_eq!(graph.graph.node_count(), 2);
+    assert_eq!(graph.graph.edge_count(), 1);
+
+    let search = graph
+        .graph
+        .node_weights()
+        .find(|n| n.job.structure.source == "search_generate")
+        .unwrap();
+    assert_eq!(
+        search.node_type,
+        NodeType::Generator {
+            strategy: "bayes".into()
+        }
+    );
+    let params = &search.job.config.params;
+    // Generation 0 plus three more
+    assert_eq!(params["gen_limit"], 3);
+    assert_eq!(params["until"], "energy.min < -5.0");
+    let batch: JobConfig = serde_json::from_value(params["physics_template"].clone()).unwrap();
+    assert!(matches!(
+        batch.engine,
+        Engine::Gulp { ref potential_library, .. } if potential_library == "buck"
+    ));
+
+    // A loop needs a bound and a test that parses
+    let load = |params: &str| {
+        std::fs::write(
+            &path,
+            format!(
+                "version: 1\nmetadata: {{ name: s }}\nnodes: [{{ id: a, type: compute }}]\nmacros: [{{ id: s, type: convergence_loop, params: {} }}]\n",
+                params
+            ),
+        )
+        .unwrap();
+        YamlLoader::load_from_file(&path, &Default::default())
+            .err()
+            .unwrap()
+            .to_string()
+    };
+    let err = load(r#"{ until: "energy.min < 0" }"#);
+    assert!(
+        err.contains("requires 'max_iters' of at least 1"),
+        "{}",
+        err
+    );
+    let err = load(r#"{ max_iters: 3, until: "converged" }"#);
+    assert!(err.contains("macro 's' until: expected"), "{}", err);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_yaml_imports_merge_node_templates() {
+    let path = write(
+        "relax.yaml",
+        r#"
+version: 1
+metadata: { name: relax }
+vars: { encut: 520 }
+imports: [lib/nodes.yaml]
+nodes:
+  - id: relax
+    use: vasp_relax
+    params: { ISIF: 2 }
+    resources: { cores: 64 }
+  - id: screen
+    use: vasp_relax
+    engine: { kind: janus }
+    params: { arch: chgnet }
+edges:
+  - { from: screen, to: relax }
+"#,
+    );
+    let dir = path.parent().unwrap();
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    std::fs::write(
+        dir.join("lib/nodes.yaml"),
+        r#"
+templates:
+  vasp_relax:
+    type: compute
+    engine: { kind: vasp }
+    params: { ENCUT: "${vars.encut}", ISIF: 3 }
+    resources: { cores: 32, time_limit_min: 240 }
+"#,
+    )
+    .unwrap();
+
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    let node = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+    };
+    // Maps merge key by key, the node's own values winning
+    let relax = node("relax");
+    assert!(matches!(
+        relax.job.config.engine,
+        Engine::Vasp { mpi_ranks: 64, .. }
+    ));
+    assert_eq!(relax.job.resources.time_limit_min, 240);
+    assert_eq!(relax.job.config.params["ISIF"], 2);
+    // Templates may use the workflow's vars
+    assert_eq!(relax.job.config.params["ENCUT"], 520);
+    // An engine of another kind replaces the template's
+    assert_eq!(node("screen").job.config.engine.code(), "janus:chgnet");
+
+    // Names must be known, and defined once
+    let load = |text: &str| {
+        std::fs::write(&path, text).unwrap();
+        YamlLoader::load_from_file(&path, &Default::default())
+            .err()
+            .unwrap()
+            .to_string()
+    };
+    let err = load("version: 1\nmetadata: { name: r }\nimports: [lib/nodes.yaml]\nnodes: [{ id: a, use: vasp_relx }]\n");
+    assert!(
+        err.contains("node 'a' uses unknown template 'vasp_relx' (imported: vasp_relax)"),
+        "{}",
+        err
+    );
+    let err = load("version: 1\nmetadata: { name: r }\nimports: [lib/nodes.yaml, lib/../lib/nodes.yaml]\nnodes: [{ id: a, use: vasp_relax }]\n");
+    assert!(
+        err.contains("template 'vasp_relax' is defined by both"),
+        "{}",
+        err
+    );
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_yaml_resource_profiles_are_applied_and_inherited() {
+    let path = write(
+        "profiles.yaml",
+        r#"
+version: 1
+metadata: { name: profiles }
+imports: [lib/cluster.yaml]
+profiles:
+  gpu_small: { cores: 8, gpus: 1, time_limit_min: 60 }
+resources: { profile: gpu_small }
+nodes:
+  - id: screen
+    type: compute
+    engine: { kind: janus }
+  - id: relax
+    type: compute
+    engine: { kind: vasp }
+    resources: { profile: cpu_mpi, time_limit_min: 1440 }
+macros:
+  - id: sweep
+    type: fanout
+    anchor: screen
+    params: { width: 2 }
+edges:
+  - { from: screen, to: relax }
+"#,
+    );
+    let dir = path.parent().unwrap();
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    std::fs::write(
+        dir.join("lib/cluster.yaml"),
+        r#"
+profiles:
+  gpu_small: { cores: 4, gpus: 4 }
+  cpu_mpi: { nodes: 2, cores: 128, time_limit_min: 720, required_tags: [mpi] }
+"#,
+    )
+    .unwrap();
+
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    let resources = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+            .job
+            .resources
+            .clone()
+    };
+    // Fields next to the profile override it
+    let relax = resources("relax");
+    assert_eq!((relax.nodes, relax.cores), (2, 128));
+    assert_eq!(relax.time_limit_min, 1440);
+    assert_eq!(relax.required_tags, ["mpi"]);
+    // The workflow's own profile wins over the library's, and is the
+    // default of nodes without resources, macro-made ones included
+    for id in ["screen", "sweep_1", "sweep_2"] {
+        let r = resources(id);
+        assert_eq!((r.cores, r.gpus, r.time_limit_min), (8, 1, 60), "{}", id);
+    }
+
+    std::fs::write(
+        &path,
+        "version: 1\nmetadata: { name: r }\nprofiles: { gpu_small: { gpus: 1 } }\nnodes: [{ id: a, type: compute, resources: { profile: gpu_big } }]\n",
+    )
+    .unwrap();
+    let err = YamlLoader::load_from_file(&path, &Default::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        err.contains("unknown resource profile 'gpu_big' (defined: gpu_small)"),
+        "{}",
+        err
+    );
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_yaml_version_1_files_are_migrated_to_version_2() {
+    let v1 = r#"
+version: 1
+metadata: { name: old }
+vars: { cutoff: -5.0 }
+nodes:
+  - { id: relax, type: compute }
+  - id: gate
+    type: switch
+    params: { condition: { EnergyBelow: "${vars.cutoff}" }, decide_on_progress: true }
+  - { id: refine, type: compute }
+  - { id: open, type: switch, params: { condition: AlwaysTrue } }
+edges:
+  - { from: relax, to: gate }
+  - from: gate
+    to: refine
+    kind: !dataflow { map: { start_energy: energy } }
+  - { from: relax, to: open, kind: { dataflow: { map: {} } } }
+"#;
+    let mut doc: serde_yaml::Value = serde_yaml::from_str(v1).unwrap();
+    dsl::migrate::migrate_v1_to_v2(&mut doc).unwrap();
+    let v2: serde_yaml::Value = serde_yaml::from_str(
+        r#"
+version: 2
+metadata: { name: old }
+vars: { cutoff: -5.0 }
+nodes:
+  - { id: relax, type: compute }
+  - id: gate
+    type: switch
+    params: { decide_on_progress: true }
+    condition: { energy_below: "${vars.cutoff}" }
+  - { id: refine, type: compute }
+  - { id: open, type: switch }
+edges:
+  - { from: relax, to: gate }
+  - { from: gate, to: refine, map: { start_energy: energy } }
+  - { from: relax, to: open }
+"#,
+    )
+    .unwrap();
+    assert_eq!(doc, v2);
+
+    // Old files load as they are
+    let path = write("old.yaml", v1);
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    let node = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+    };
+    assert!(matches!(
+        node("gate").node_type,
+        NodeType::Switch {
+            condition: LogicCondition::EnergyBelow(t)
+        } if t == -5.0
+    ));
+    assert_eq!(node("gate").job.config.params["decide_on_progress"], true);
+    assert_eq!(
+        node("refine").job.dataflow[&node("gate").job.id]["start_energy"],
+        "energy"
+    );
+
+    let load = |text: &str| {
+        std::fs::write(&path, text).unwrap();
+        YamlLoader::load_from_file(&path, &Default::default())
+            .err()
+            .unwrap()
+            .to_string()
+    };
+    // Version 2 no longer reads the old forms
+    let err = load("version: 2\nmetadata: { name: n }\nnodes: [{ id: s, type: switch, params: { condition: AlwaysTrue } }]\n");
+    assert!(
+        err.contains("switch 's' sets params.condition, which version 2 no longer reads"),
+        "{}",
+        err
+    );
+    let err = load("version: 2\nmetadata: { name: n }\nnodes: [{ id: a, type: compute }, { id: b, type: compute }]\nedges: [{ from: a, to: b, kind: soft, map: { e: energy } }]\n");
+    assert!(
+        err.contains("soft edge 'a' -> 'b' cannot hand over values"),
+        "{}",
+        err
+    );
+    let err = load("version: 1\nmetadata: { name: n }\nnodes: [{ id: s, type: switch, params: { condition: { Sometimes: 1 } } }]\n");
+    assert!(
+        err.contains("switch 's': cannot migrate params.condition"),
+        "{}",
+        err
+    );
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_yaml_matrix_expands_to_the_cross_product() {
+    let path = write(
+        "benchmark.yaml",
+        r#"
+version: 2
+metadata: { name: benchmark }
+nodes:
+  - id: prepare
+    type: compute
+  - id: screen
+    type: compute
+    engine: { kind: janus }
+    params: { steps: 100 }
+    matrix:
+      device: [cuda, cpu]
+      arch: [mace_mp, chgnet]
+  - id: report
+    type: compute
+edges:
+  - { from: prepare, to: screen }
+  - { from: screen, to: report }
+"#,
+    );
+    let graph = YamlLoader::load_from_file(&path, &Default::default())
+        .unwrap()
+        .graph;
+    // prepare -> 4 copies -> screen -> report
+    assert_eq!(graph.graph.node_count(), 7);
+    assert_eq!(graph.graph.edge_count(), 9);
+
+    let node = |id: &str| {
+        graph
+            .graph
+            .node_weights()
+            .find(|n| n.job.structure.source == id)
+            .unwrap()
+    };
+    let parents = |id: &str| -> Vec<String> {
+        let idx = graph
+            .graph
+            .node_indices()
+            .find(|i| graph.graph[*i].job.structure.source == id)
+            .unwrap();
+        let mut parents: Vec<String> = graph
+            .graph
+            .neighbors_directed(idx, petgraph::Direction::Incoming)
+            .map(|p| graph.graph[p].job.structure.source.clone())
+            .collect();
+        parents.sort();
+        parents
+    };
+    // Axes go in name order, so ids do not depend on how the file lists them
+    let copies = [
+        "screen_chgnet_cpu",
+        "screen_chgnet_cuda",
+        "screen_mace_mp_cpu",
+        "screen_mace_mp_cuda",
+    ];
+    assert_eq!(parents("screen"), copies);
+    assert_eq!(parents("report"), ["screen"]);
+    for id in copies {
+        assert_eq!(parents(id), ["prepare"]);
+    }
+    let params = &node("screen_mace_mp_cpu").job.config.params;
+    assert_eq!(params["arch"], "mace_mp");
+    assert_eq!(params["device"], "cpu");
+    assert_eq!(params["steps"], 100);
+
+    let gather = node("screen");
+    assert_eq!(gather.node_type, NodeType::Aggregator);
+    assert_eq!(
+        gather.job.config.params["matrix"]["screen_chgnet_cuda"],
+        serde_json::json!({ "arch": "chgnet", "device": "cuda" })
+    );
+
+    std::fs::write(
+        &path,
+        "version: 2\nmetadata: { name: m }\nnodes: [{ id: a, type: compute, matrix: { arch: [] } }]\n",
+    )
+    .unwrap();
+    let err = YamlLoader::load_from_file(&path, &Default::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        err.contains("axis 'arch' must list at least one value"),
+        "{}",
+        err
+    );
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
@@ -18,12 +18,26 @@ def main():
         history = payload.get("history", [])
         sys.stderr.write(f"[Agent] Received history with {len(history)} points.\n")
 
+        # 2b. Coordinator feedback on our previous generation (params.feedback):
+        # candidates ranked by energy, with Boltzmann weights summing to 1
+        params = input_data.get("config", {}).get("params", {})
+        feedback = params.get("feedback") or {}
+        ranked = [r for r in feedback.get("ranked", []) if "Buckingham_A" in (r.get("candidate") or {})]
+        if feedback:
+            sys.stderr.write(
+                f"[Agent] Generation {feedback.get('generation')}: "
+                f"{feedback.get('completed')}/{feedback.get('candidates')} completed.\n"
+            )
+
         # 3. Generate Candidates (Mocking LCB Acquisition)
         # We generate 5 candidates with slightly different parameters
         candidates = []
         for i in range(5):
-            # Perturb based on iteration to show progress
-            base_A = 1000.0 + (len(history) * 10)
+            # Exploit: centre on the weighted best of the last batch, if any
+            if ranked:
+                base_A = sum(r["weight"] * r["candidate"]["Buckingham_A"] for r in ranked)
+            else:
+                base_A = 1000.0 + (len(history) * 10)
             candidates.append({
                 "Buckingham_A": base_A + np.random.uniform(-50, 50),
                 "Buckingham_rho": 0.3 + np.random.uniform(-0.01, 0.01)
@@ -3,6 +3,28 @@ import json
 import os
 import traceback
 
+# Injected by the Rust driver; echo it so logs can be correlated.
+TRACE_ID = os.environ.get("ULAB_TRACE_ID", "-")
+SPAN_ID = os.environ.get("ULAB_SPAN_ID", "-")
+
+def read_outcar_moments(path):
+    """Per-ion total moments from the last 'magnetization (x)' table, or None."""
+    if not os.path.exists(path):
+        return None
+    with open(path) as f:
+        lines = f.read().splitlines()
+    starts = [i for i, l in enumerate(lines) if l.strip().startswith("magnetization (x)")]
+    if not starts:
+        return None
+    moments = []
+    # Header, column names, then a dashed rule before the ion rows
+    for line in lines[starts[-1] + 4:]:
+        cols = line.split()
+        if not cols or not cols[0].isdigit():
+            break
+        moments.append(float(cols[-1]))
+    return moments or None
+
 def main():
     if len(sys.argv) < 3:
         sys.stderr.write("Usage: cli.py [mode] [engine] [work_dir]\n")
@@ -24,6 +46,10 @@ def main():
             with open(input_file, "w") as f:
                 f.write(f"# Mock Input for {engine}\n")
                 f.write(f"# Params: {json.dumps(data.get('config', {}))}\n")
+                # Charge/spin settings templated by Rust (MAGMOM, CHARGE, ...)
+                # In real life: merged into INCAR / the GULP coordinates / &DFT
+                if data.get("electronic"):
+                    f.write(f"# Electronic: {json.dumps(data['electronic'])}\n")
                 
             # No output to stdout for 'write' mode (Rust ignores it)
 
@@ -48,12 +74,18 @@ def main():
                     "exit_code": 0,
                     "sandbox_info": "mock_sandbox"
                 },
-                "next_generation": None
+                "next_generation": None,
+                "trace_id": TRACE_ID
             }
+            # Rust copies these onto final_structure, one per atom
+            if engine == "vasp":
+                moments = read_outcar_moments(os.path.join(work_dir, "OUTCAR"))
+                if moments:
+                    response["magnetic_moments"] = moments
             print(json.dumps(response))
 
     except Exception as e:
-        sys.stderr.write(f"[CLI Error] {traceback.format_exc()}\n")
+        sys.stderr.write(f"[CLI Error] [trace={TRACE_ID} span={SPAN_ID}] {traceback.format_exc()}\n")
         # For parse mode, we must print valid JSON or Rust panics on deserialize.
         # But if we exit(1), Rust catches the exit code anyway.
         sys.exit(1)
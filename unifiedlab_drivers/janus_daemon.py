@@ -53,8 +53,12 @@ def main():
 
     # 2. Event Loop
     for line in sys.stdin:
+        # The daemon is long-lived: trace IDs arrive per request, not via env.
+        trace_id, span_id = None, None
         try:
             req = json.loads(line)
+            trace_id = req.get("trace_id")
+            span_id = req.get("span_id")
             
             # Extract Data
             structure = req["structure"]
@@ -73,20 +77,22 @@ def main():
                 "energy": e,
                 "forces": f.tolist(),
                 "stress": None,
-                "error": None
+                "error": None,
+                "trace_id": trace_id
             }
             print(json.dumps(response), flush=True)
 
         except Exception as e:
             # Log full trace to stderr (Visible in Rust logs)
-            sys.stderr.write(f"[Janus Error] {traceback.format_exc()}\n")
+            sys.stderr.write(f"[Janus Error] [trace={trace_id} span={span_id}] {traceback.format_exc()}\n")
             
             # Send clean error to stdout (Prevents Rust panic)
             err_resp = {
                 "energy": None, 
                 "forces": None, 
                 "stress": None,
-                "error": str(e)
+                "error": str(e),
+                "trace_id": trace_id
             }
             print(json.dumps(err_resp), flush=True)
 